@@ -0,0 +1,124 @@
+//! The heap allocations that Rust makes per conversion with `future_into_py`
+//!
+//! The global allocator counts the allocations while Python awaits Rust futures that complete
+//! right away, after a warm-up that initializes the lazy state and grows the reused buffers.
+//! Python's own objects are allocated by the interpreter, so they aren't counted. Each pattern is
+//! measured with the `asyncio.Future` that conversions return once
+//! [`set_rust_futures`](pyo3_async_runtimes::set_rust_futures) is disabled and with the
+//! `RustFuture` that they return by default, on `asyncio`'s default loop and on `uvloop` if it's
+//! installed.
+//!
+//! ```text
+//! cargo bench --bench allocations --features tokio-runtime
+//! ```
+//!
+//! To see what a change does, run it before and after, the counts are deterministic for a given
+//! pattern. The one-at-a-time pattern is the steady state of a server that awaits a conversion per
+//! request, which is where allocations that could be reused show up.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use pyo3::prelude::*;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const BENCH_CODE: &str = r#"
+import asyncio
+
+async def await_each(convert, n):
+    for _ in range(n):
+        await convert()
+
+async def gather(convert, n):
+    for _ in range(n // 100):
+        await asyncio.gather(*(convert() for _ in range(100)))
+"#;
+
+const CONVERSIONS: u64 = 100_000;
+const WARM_UP: u64 = 1_000;
+
+const LOOPS: &[&str] = &["asyncio", "uvloop"];
+
+#[pyfunction]
+fn ready(py: Python) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::tokio::future_into_py(py, async { Ok(()) })
+}
+
+/// Run `coro_fn(ready, n)` of the bench module on `event_loop`, and count the allocations
+fn run(event_loop: &Bound<PyAny>, coro_fn: &str, n: u64) -> (u64, Duration) {
+    let py = event_loop.py();
+    let bench = PyModule::from_code_bound(py, BENCH_CODE, "allocations.py", "allocations").unwrap();
+    let coro = bench
+        .call_method1(coro_fn, (wrap_pyfunction_bound!(ready, py).unwrap(), n))
+        .unwrap();
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    event_loop
+        .call_method1("run_until_complete", (coro,))
+        .unwrap();
+    let elapsed = start.elapsed();
+
+    (ALLOCATIONS.load(Ordering::Relaxed) - allocations, elapsed)
+}
+
+fn main() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        for kind in LOOPS {
+            let event_loop = match py
+                .import_bound(*kind)
+                .and_then(|module| module.call_method0("new_event_loop"))
+            {
+                Ok(event_loop) => event_loop,
+                Err(_) => continue,
+            };
+
+            for rust_futures in [false, true] {
+                pyo3_async_runtimes::set_rust_futures(rust_futures);
+                let future = if rust_futures {
+                    "RustFuture"
+                } else {
+                    "asyncio.Future"
+                };
+
+                for coro_fn in ["await_each", "gather"] {
+                    run(&event_loop, coro_fn, WARM_UP);
+                    let (allocations, elapsed) = run(&event_loop, coro_fn, CONVERSIONS);
+
+                    println!(
+                        "{}/{}/{}: {:.2} allocations, {:?} per conversion",
+                        kind,
+                        future,
+                        coro_fn,
+                        allocations as f64 / CONVERSIONS as f64,
+                        elapsed / CONVERSIONS as u32,
+                    );
+                }
+            }
+
+            event_loop.call_method0("close").unwrap();
+        }
+    });
+}
@@ -0,0 +1,91 @@
+"""The baseline for benches/conversions.rs: the same awaits with plain asyncio
+
+Each benchmark waits for futures that another thread completes through call_soon_threadsafe, the
+way the Rust runtime completes the futures of future_into_py. The difference to the numbers of
+the Rust benchmarks is what the conversions add.
+
+    python benches/conversions.py [--uvloop]
+"""
+
+import argparse
+import asyncio
+import concurrent.futures
+import time
+
+GATHERED = 100
+
+
+def complete_from_thread(loop, executor):
+    fut = loop.create_future()
+    executor.submit(loop.call_soon_threadsafe, fut.set_result, None)
+    return fut
+
+
+async def await_each(n, executor):
+    loop = asyncio.get_running_loop()
+    for _ in range(n):
+        await complete_from_thread(loop, executor)
+
+
+async def gather(n, executor):
+    loop = asyncio.get_running_loop()
+    for _ in range(n):
+        await asyncio.gather(*(complete_from_thread(loop, executor) for _ in range(GATHERED)))
+
+
+async def ready():
+    pass
+
+
+async def await_ready(n, executor):
+    for _ in range(n):
+        await asyncio.ensure_future(ready())
+
+
+async def count_to(n):
+    for i in range(n):
+        yield i
+
+
+async def iterate(n, executor):
+    async for _ in count_to(n):
+        pass
+
+
+BENCHES = [
+    ("future_into_py/await_each", await_each, 1),
+    ("future_into_py/gather", gather, GATHERED),
+    ("into_future/task", await_ready, 1),
+    ("into_stream_v2", iterate, 1),
+]
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--uvloop", action="store_true", help="run on uvloop")
+    parser.add_argument("-n", type=int, default=10_000, help="iterations per benchmark")
+    args = parser.parse_args()
+
+    if args.uvloop:
+        import uvloop
+
+        new_loop = uvloop.new_event_loop
+    else:
+        new_loop = asyncio.new_event_loop
+
+    with concurrent.futures.ThreadPoolExecutor(max_workers=1) as executor:
+        for name, bench, elements in BENCHES:
+            loop = new_loop()
+            try:
+                start = time.perf_counter()
+                loop.run_until_complete(bench(args.n, executor))
+                elapsed = time.perf_counter() - start
+            finally:
+                loop.close()
+
+            per_element = elapsed / (args.n * elements)
+            print(f"{name}: {per_element * 1e6:.2f} us per element")
+
+
+if __name__ == "__main__":
+    main()
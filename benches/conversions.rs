@@ -0,0 +1,304 @@
+//! The overhead of the conversions between Rust futures and Python awaitables
+//!
+//! Each benchmark runs on every kind of event loop that is available: `asyncio`'s default loop,
+//! `uvloop` if it's installed, and the tokio [`EventLoop`] with the `tokio-event-loop` feature. The
+//! conversions of the tokio runtime are always measured, the ones of async-std with the
+//! `async-std-runtime` feature.
+//!
+//! ```text
+//! cargo bench --bench conversions --features tokio-event-loop,async-std-runtime,unstable-streams
+//! ```
+//!
+//! Besides the time, each benchmark prints what the conversions did according to
+//! [`debug::counters`], in particular how many results were set per callback on the loop. A drop
+//! in that number means the results stopped being batched. `benches/conversions.py` measures the
+//! same loops with plain `asyncio`, as the baseline that the conversions add to.
+//!
+//! [`EventLoop`]: pyo3_async_runtimes::tokio::EventLoop
+
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::StreamExt;
+use pyo3::prelude::*;
+use pyo3_async_runtimes::debug::{self, Counters};
+
+const BENCH_CODE: &str = r#"
+import asyncio
+
+async def await_each(convert, n):
+    for _ in range(n):
+        await convert()
+
+async def gather(convert, n):
+    await asyncio.gather(*(convert() for _ in range(n)))
+
+async def ready():
+    pass
+
+async def count_to(n):
+    for i in range(n):
+        yield i
+"#;
+
+/// The number of conversions that `gather` runs at once
+const GATHERED: u64 = 100;
+
+#[pyfunction]
+fn tokio_ready(py: Python) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::tokio::future_into_py(py, async { Ok(()) })
+}
+
+#[cfg(feature = "async-std-runtime")]
+#[pyfunction]
+fn async_std_ready(py: Python) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::async_std::future_into_py(py, async { Ok(()) })
+}
+
+const LOOPS: &[&str] = &["asyncio", "uvloop", "tokio"];
+
+/// Create an event loop of the given kind, if it's available
+fn new_loop<'p>(py: Python<'p>, kind: &str) -> Option<Bound<'p, PyAny>> {
+    match kind {
+        "asyncio" => py
+            .import_bound("asyncio")
+            .and_then(|asyncio| asyncio.call_method0("new_event_loop"))
+            .ok(),
+        "uvloop" => py
+            .import_bound("uvloop")
+            .and_then(|uvloop| uvloop.call_method0("new_event_loop"))
+            .ok(),
+        #[cfg(feature = "tokio-event-loop")]
+        "tokio" => Bound::new(py, pyo3_async_runtimes::tokio::EventLoop::new())
+            .ok()
+            .map(Bound::into_any),
+        _ => None,
+    }
+}
+
+fn bench_mod(py: Python) -> Bound<PyModule> {
+    PyModule::from_code_bound(py, BENCH_CODE, "conversions.py", "conversions").unwrap()
+}
+
+/// Print what the conversions of a benchmark did, per iteration
+fn report(name: &str, iters: u64, counted: Counters) {
+    let iters = iters.max(1) as f64;
+
+    println!(
+        "{}: {:.2} futures into py, {:.2} awaitables into rust, {:.2} completions per iteration, \
+         {:.2} completions per drain",
+        name,
+        counted.futures_into_py as f64 / iters,
+        counted.awaitables_into_rust as f64 / iters,
+        counted.completions as f64 / iters,
+        counted.completions as f64 / counted.drains.max(1) as f64,
+    );
+}
+
+/// Run `coro_fn(convert, n)` of the bench module on `event_loop` and time it
+fn time_on_loop(
+    event_loop: &Bound<PyAny>,
+    coro_fn: &str,
+    convert: &Bound<PyAny>,
+    n: u64,
+) -> Duration {
+    let coro = bench_mod(event_loop.py())
+        .call_method1(coro_fn, (convert, n))
+        .unwrap();
+
+    let start = Instant::now();
+    event_loop
+        .call_method1("run_until_complete", (coro,))
+        .unwrap();
+    start.elapsed()
+}
+
+/// `future_into_py` awaited by Python, one conversion at a time and many at once
+fn future_into_py(c: &mut Criterion) {
+    pyo3::prepare_freethreaded_python();
+
+    let backends: &[&str] = if cfg!(feature = "async-std-runtime") {
+        &["tokio", "async-std"]
+    } else {
+        &["tokio"]
+    };
+
+    for coro_fn in ["await_each", "gather"] {
+        let mut group = c.benchmark_group(format!("future_into_py/{}", coro_fn));
+        if coro_fn == "gather" {
+            group.throughput(Throughput::Elements(GATHERED));
+        } else {
+            group.throughput(Throughput::Elements(1));
+        }
+
+        for kind in LOOPS {
+            for backend in backends {
+                Python::with_gil(|py| {
+                    let event_loop = match new_loop(py, kind) {
+                        Some(event_loop) => event_loop,
+                        None => return,
+                    };
+                    let convert = match *backend {
+                        #[cfg(feature = "async-std-runtime")]
+                        "async-std" => wrap_pyfunction_bound!(async_std_ready, py).unwrap(),
+                        _ => wrap_pyfunction_bound!(tokio_ready, py).unwrap(),
+                    };
+
+                    let before = debug::counters();
+                    let mut total = 0;
+                    group.bench_function(BenchmarkId::new(*backend, kind), |b| {
+                        b.iter_custom(|iters| {
+                            total += iters;
+                            if coro_fn == "gather" {
+                                (0..iters).fold(Duration::ZERO, |elapsed, _| {
+                                    elapsed + time_on_loop(&event_loop, coro_fn, &convert, GATHERED)
+                                })
+                            } else {
+                                time_on_loop(&event_loop, coro_fn, &convert, iters)
+                            }
+                        })
+                    });
+                    report(
+                        &format!("future_into_py/{}/{}/{}", coro_fn, backend, kind),
+                        total,
+                        debug::counters().since(&before),
+                    );
+
+                    event_loop.call_method0("close").unwrap();
+                });
+            }
+        }
+
+        group.finish();
+    }
+}
+
+/// `into_future` and `into_future_polled` awaited by Rust
+fn into_future(c: &mut Criterion) {
+    pyo3::prepare_freethreaded_python();
+
+    let mut group = c.benchmark_group("into_future");
+    group.throughput(Throughput::Elements(1));
+
+    for kind in LOOPS {
+        for polled in [false, true] {
+            Python::with_gil(|py| {
+                let event_loop = match new_loop(py, kind) {
+                    Some(event_loop) => event_loop,
+                    None => return,
+                };
+                let ready = bench_mod(py).getattr("ready").unwrap().unbind();
+                let name = if polled { "polled" } else { "task" };
+
+                let before = debug::counters();
+                let mut total = 0;
+                group.bench_function(BenchmarkId::new(name, kind), |b| {
+                    b.iter_custom(|iters| {
+                        total += iters;
+                        let ready = ready.clone_ref(py);
+
+                        let start = Instant::now();
+                        pyo3_async_runtimes::tokio::run_until_complete(
+                            event_loop.clone(),
+                            async move {
+                                for _ in 0..iters {
+                                    if polled {
+                                        Python::with_gil(|py| {
+                                            pyo3_async_runtimes::tokio::into_future_polled(
+                                                ready.bind(py).call0()?,
+                                            )
+                                        })?
+                                        .await?;
+                                    } else {
+                                        Python::with_gil(|py| {
+                                            pyo3_async_runtimes::tokio::into_future(
+                                                ready.bind(py).call0()?,
+                                            )
+                                        })?
+                                        .await?;
+                                    }
+                                }
+                                Ok(())
+                            },
+                        )
+                        .unwrap();
+                        start.elapsed()
+                    })
+                });
+                report(
+                    &format!("into_future/{}/{}", name, kind),
+                    total,
+                    debug::counters().since(&before),
+                );
+
+                event_loop.call_method0("close").unwrap();
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// Items of a Python async generator consumed as a Rust stream
+fn stream_into_rust(c: &mut Criterion) {
+    if !cfg!(feature = "unstable-streams") {
+        return;
+    }
+    pyo3::prepare_freethreaded_python();
+
+    let mut group = c.benchmark_group("into_stream_v2");
+    group.throughput(Throughput::Elements(1));
+
+    for kind in LOOPS {
+        Python::with_gil(|py| {
+            let event_loop = match new_loop(py, kind) {
+                Some(event_loop) => event_loop,
+                None => return,
+            };
+            let count_to = bench_mod(py).getattr("count_to").unwrap().unbind();
+
+            group.bench_function(BenchmarkId::from_parameter(kind), |b| {
+                b.iter_custom(|iters| {
+                    let count_to = count_to.clone_ref(py);
+
+                    let start = Instant::now();
+                    pyo3_async_runtimes::tokio::run_until_complete(
+                        event_loop.clone(),
+                        async move {
+                            let stream = Python::with_gil(|py| {
+                                into_stream(count_to.bind(py).call1((iters,))?)
+                            })?;
+                            assert_eq!(stream.count().await as u64, iters);
+                            Ok(())
+                        },
+                    )
+                    .unwrap();
+                    start.elapsed()
+                })
+            });
+
+            event_loop.call_method0("close").unwrap();
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "unstable-streams")]
+fn into_stream(
+    gen: Bound<PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyObject> + Send + 'static> {
+    pyo3_async_runtimes::tokio::into_stream_v2(gen)
+}
+
+#[cfg(not(feature = "unstable-streams"))]
+fn into_stream(_gen: Bound<PyAny>) -> PyResult<futures::stream::Empty<PyObject>> {
+    unreachable!("the stream benchmarks need the `unstable-streams` feature")
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = future_into_py, into_future, stream_into_rust
+}
+criterion_main!(benches);
@@ -0,0 +1,220 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+
+use crate::pyfunction::{result_future, runtime_path, split_runtime, Args};
+
+/// How the async method gets at the instance
+enum Receiver {
+    /// `&self`, which the future borrows from a `Py<Self>` of a frozen class
+    Ref,
+    /// `slf: Py<Self>`
+    Py,
+    /// No receiver, e.g. a `#[staticmethod]`
+    None,
+}
+
+/// Attributes that belong to the generated method, rather than the async fn
+fn is_pymethod_attr(attr: &syn::Attribute) -> bool {
+    [
+        "doc",
+        "pyo3",
+        "staticmethod",
+        "classmethod",
+        "getter",
+        "setter",
+        "new",
+        "classattr",
+    ]
+    .iter()
+    .any(|name| attr.path().is_ident(name))
+}
+
+/// Check whether `ty` is `Py<Self>`
+fn is_py_self(ty: &syn::Type) -> bool {
+    let segment = match ty {
+        syn::Type::Path(path) => match path.path.segments.last() {
+            Some(segment) if segment.ident == "Py" => segment,
+            _ => return false,
+        },
+        _ => return false,
+    };
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => matches!(
+            args.args.first(),
+            Some(syn::GenericArgument::Type(syn::Type::Path(path))) if path.path.is_ident("Self")
+        ),
+        _ => false,
+    }
+}
+
+/// Split the receiver off the inputs of an async method
+fn parse_receiver(sig: &syn::Signature) -> Result<(Receiver, Vec<&syn::FnArg>), syn::Error> {
+    let mut inputs = sig.inputs.iter().collect::<Vec<_>>();
+
+    let receiver = match inputs.first() {
+        Some(syn::FnArg::Receiver(receiver)) => {
+            if receiver.reference.is_none() || receiver.mutability.is_some() {
+                let msg = "async methods can only borrow `&self`, take `slf: Py<Self>` to get \
+                           at the instance otherwise";
+                return Err(syn::Error::new_spanned(receiver, msg));
+            }
+            Receiver::Ref
+        }
+        Some(syn::FnArg::Typed(pat_type)) if is_py_self(&pat_type.ty) => Receiver::Py,
+        _ => Receiver::None,
+    };
+    if !matches!(receiver, Receiver::None) {
+        inputs.remove(0);
+    }
+
+    Ok((receiver, inputs))
+}
+
+/// Replace an async method by a method that returns a Python awaitable, and return the async fn
+/// that it calls
+fn convert_method(
+    method: &mut syn::ImplItemFn,
+    runtime: &proc_macro2::TokenStream,
+) -> Result<syn::ImplItemFn, syn::Error> {
+    let sig = &method.sig;
+    let (receiver, inputs) = parse_receiver(sig)?;
+    let args = Args::parse(inputs.into_iter())?;
+    let Args {
+        inner: inner_inputs,
+        outer: outer_inputs,
+        names: arg_names,
+    } = &args;
+
+    let name = &sig.ident;
+    let ret = &sig.output;
+    let body = &method.block;
+    let inner_name = format_ident!("__pyo3_async_runtimes_{}", name);
+    let py = format_ident!("__pyo3_async_runtimes_py", span = Span::call_site());
+
+    let inner_attrs = method
+        .attrs
+        .iter()
+        .filter(|attr| !is_pymethod_attr(attr))
+        .collect::<Vec<_>>();
+
+    let (inner, wrapper) = match receiver {
+        Receiver::Ref => {
+            let fut = result_future(
+                quote! { pyo3::Py::get(&slf).#inner_name(#(#arg_names),*) },
+                ret,
+            );
+            (
+                quote! {
+                    #(#inner_attrs)*
+                    async fn #inner_name(&self, #(#inner_inputs),*) #ret #body
+                },
+                quote! {
+                    fn #name<'py>(
+                        slf: pyo3::Bound<'py, Self>,
+                        #(#outer_inputs),*
+                    ) -> pyo3::PyResult<pyo3::Bound<'py, pyo3::PyAny>> {
+                        let #py = slf.py();
+                        let slf = slf.unbind();
+                        #runtime::future_into_py(#py, #fut)
+                    }
+                },
+            )
+        }
+        Receiver::Py => {
+            let slf = match sig.inputs.first() {
+                Some(syn::FnArg::Typed(pat_type)) => pat_type,
+                _ => unreachable!("the receiver is a typed argument"),
+            };
+            let fut = result_future(quote! { Self::#inner_name(slf, #(#arg_names),*) }, ret);
+            (
+                quote! {
+                    #(#inner_attrs)*
+                    async fn #inner_name(#slf, #(#inner_inputs),*) #ret #body
+                },
+                quote! {
+                    fn #name<'py>(
+                        slf: pyo3::Bound<'py, Self>,
+                        #(#outer_inputs),*
+                    ) -> pyo3::PyResult<pyo3::Bound<'py, pyo3::PyAny>> {
+                        let #py = slf.py();
+                        let slf = slf.unbind();
+                        #runtime::future_into_py(#py, #fut)
+                    }
+                },
+            )
+        }
+        Receiver::None => {
+            let fut = result_future(quote! { Self::#inner_name(#(#arg_names),*) }, ret);
+            (
+                quote! {
+                    #(#inner_attrs)*
+                    async fn #inner_name(#(#inner_inputs),*) #ret #body
+                },
+                quote! {
+                    fn #name<'py>(
+                        #py: pyo3::Python<'py>,
+                        #(#outer_inputs),*
+                    ) -> pyo3::PyResult<pyo3::Bound<'py, pyo3::PyAny>> {
+                        #runtime::future_into_py(#py, #fut)
+                    }
+                },
+            )
+        }
+    };
+
+    let attrs = &method.attrs;
+    let vis = &method.vis;
+    *method = syn::parse2(quote! {
+        #(#attrs)*
+        #vis #wrapper
+    })?;
+
+    syn::parse2(inner)
+}
+
+fn parse_async_methods(
+    mut input: syn::ItemImpl,
+    args: Punctuated<syn::Meta, syn::Token![,]>,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let (runtime, forwarded) = split_runtime(args)?;
+    if let Some(arg) = forwarded.first() {
+        let msg = "Unknown attribute is specified; expected: `runtime`";
+        return Err(syn::Error::new_spanned(arg, msg));
+    }
+    let (runtime_check, runtime) = runtime_path(runtime);
+
+    let mut inner_methods = Vec::new();
+    for item in &mut input.items {
+        if let syn::ImplItem::Fn(method) = item {
+            if method.sig.asyncness.is_some() {
+                inner_methods.push(convert_method(method, &runtime)?);
+            }
+        }
+    }
+
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+    let self_ty = &input.self_ty;
+
+    Ok(quote! {
+        #runtime_check
+
+        #input
+
+        impl #impl_generics #self_ty #where_clause {
+            #(#inner_methods)*
+        }
+    })
+}
+
+#[cfg(not(test))] // Work around for rust-lang/rust#62127
+pub(crate) fn async_methods(args: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemImpl);
+    let args = syn::parse_macro_input!(args with Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated);
+
+    parse_async_methods(input, args)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -0,0 +1,240 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::spanned::Spanned;
+
+/// The executor configuration given as arguments to the `async-std` attributes
+pub(crate) struct Configuration {
+    worker_threads: Option<usize>,
+    thread_name: Option<String>,
+    event_loop_policy: Option<String>,
+    serial: bool,
+    shared_loop: bool,
+}
+
+impl Configuration {
+    /// The statements that configure the global executor, before anything has started it
+    pub(crate) fn executor_init(&self) -> proc_macro2::TokenStream {
+        let worker_threads = match self.worker_threads {
+            Some(v) => quote! { Some(#v) },
+            None => quote! { None },
+        };
+        let thread_name = match &self.thread_name {
+            Some(v) => quote! { Some(#v) },
+            None => quote! { None },
+        };
+
+        match (&self.worker_threads, &self.thread_name) {
+            (None, None) => quote! {},
+            _ => quote! {
+                pyo3_async_runtimes::async_std::re_exports::configure_executor(
+                    #worker_threads,
+                    #thread_name,
+                );
+            },
+        }
+    }
+
+    /// Wrap `call` so that it runs on a thread of its own if the test has a `thread_name`
+    ///
+    /// A panic on that thread is turned into an error.
+    pub(crate) fn test_thread(&self, call: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let thread_name = match &self.thread_name {
+            Some(thread_name) => thread_name,
+            None => return call,
+        };
+
+        quote! {
+            match std::thread::Builder::new()
+                .name(#thread_name.into())
+                .spawn(move || #call)
+                .expect("failed to spawn the thread of the test")
+                .join()
+            {
+                Ok(result) => result,
+                Err(panic) => {
+                    let panic_message = if let Some(s) = panic.downcast_ref::<&str>() {
+                        s.to_string()
+                    } else if let Some(s) = panic.downcast_ref::<String>() {
+                        s.clone()
+                    } else {
+                        "unknown error".into()
+                    };
+                    Err(pyo3_async_runtimes::err::RustPanic::new_err(format!("rust future panicked: {}", panic_message)))
+                }
+            }
+        }
+    }
+
+    /// Whether the test runs on a thread of its own
+    pub(crate) fn has_test_thread(&self) -> bool {
+        self.thread_name.is_some()
+    }
+
+    /// The event loop policy of the `event_loop_policy` argument
+    pub(crate) fn event_loop_policy(&self) -> Option<&str> {
+        self.event_loop_policy.as_deref()
+    }
+
+    /// Whether the test has the `serial` argument
+    pub(crate) fn serial(&self) -> bool {
+        self.serial
+    }
+
+    /// Whether the test has the `shared_loop` argument
+    pub(crate) fn shared_loop(&self) -> bool {
+        self.shared_loop
+    }
+}
+
+fn parse_int(int: &syn::Lit, span: Span, field: &str) -> Result<usize, syn::Error> {
+    match int {
+        syn::Lit::Int(lit) => match lit.base10_parse::<usize>() {
+            Ok(value) => Ok(value),
+            Err(e) => Err(syn::Error::new(
+                span,
+                format!("Failed to parse {} as integer: {}", field, e),
+            )),
+        },
+        _ => Err(syn::Error::new(
+            span,
+            format!("Failed to parse {} as integer.", field),
+        )),
+    }
+}
+
+fn parse_string(lit: &syn::Lit, span: Span, field: &str) -> Result<String, syn::Error> {
+    match lit {
+        syn::Lit::Str(s) => Ok(s.value()),
+        _ => Err(syn::Error::new(
+            span,
+            format!("Failed to parse {} as string.", field),
+        )),
+    }
+}
+
+fn parse_config(args: Vec<syn::Meta>, is_test: bool) -> Result<Configuration, syn::Error> {
+    let expected = if is_test {
+        "`thread_name`, `event_loop_policy`, `loop`, `serial`, `shared_loop`"
+    } else {
+        "`worker_threads`, `thread_name`, `event_loop_policy`, `loop`"
+    };
+    let mut config = Configuration {
+        worker_threads: None,
+        thread_name: None,
+        event_loop_policy: None,
+        serial: false,
+        shared_loop: false,
+    };
+
+    for arg in args {
+        let namevalue = match arg {
+            syn::Meta::NameValue(namevalue) => namevalue,
+            syn::Meta::Path(path) => {
+                let msg = match path.get_ident().map(|ident| ident.to_string()) {
+                    Some(name) if is_test && (name == "serial" || name == "shared_loop") => {
+                        let flag = if name == "serial" {
+                            &mut config.serial
+                        } else {
+                            &mut config.shared_loop
+                        };
+                        if *flag {
+                            let msg = format!("`{}` set multiple times.", name);
+                            return Err(syn::Error::new_spanned(path, msg));
+                        }
+
+                        *flag = true;
+                        continue;
+                    }
+                    Some(name)
+                        if name == "worker_threads"
+                            || name == "thread_name"
+                            || name == "event_loop_policy"
+                            || name == "loop" =>
+                    {
+                        format!("The `{}` attribute requires an argument.", name)
+                    }
+                    _ => format!(
+                        "Unknown attribute is specified; expected one of: {}",
+                        expected
+                    ),
+                };
+                return Err(syn::Error::new_spanned(path, msg));
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "Unknown attribute inside the macro",
+                ));
+            }
+        };
+
+        let name = match namevalue.path.get_ident() {
+            Some(ident) => ident.to_string(),
+            None => {
+                let msg = "Must have specified ident";
+                return Err(syn::Error::new_spanned(namevalue, msg));
+            }
+        };
+        let lit = match &namevalue.value {
+            syn::Expr::Lit(expr_lit) => &expr_lit.lit,
+            value => return Err(syn::Error::new_spanned(value, "Expected a literal value")),
+        };
+        let span = namevalue.span();
+
+        match name.as_str() {
+            "worker_threads" if is_test => {
+                let msg = "The `worker_threads` option is set on the main function of the tests, \
+                           since all of them share the global executor.";
+                return Err(syn::Error::new(span, msg));
+            }
+            "worker_threads" => {
+                if config.worker_threads.is_some() {
+                    return Err(syn::Error::new(
+                        span,
+                        "`worker_threads` set multiple times.",
+                    ));
+                }
+
+                let worker_threads = parse_int(lit, span, "worker_threads")?;
+                if worker_threads == 0 {
+                    return Err(syn::Error::new(span, "`worker_threads` may not be 0."));
+                }
+                config.worker_threads = Some(worker_threads);
+            }
+            "thread_name" => {
+                if config.thread_name.is_some() {
+                    return Err(syn::Error::new(span, "`thread_name` set multiple times."));
+                }
+
+                config.thread_name = Some(parse_string(lit, span, "thread_name")?);
+            }
+            "event_loop_policy" | "loop" => {
+                if config.event_loop_policy.is_some() {
+                    return Err(syn::Error::new(
+                        span,
+                        "`event_loop_policy` set multiple times.",
+                    ));
+                }
+
+                config.event_loop_policy = Some(crate::policy::parse_policy(lit, span)?);
+            }
+            name => {
+                let msg = format!(
+                    "Unknown attribute {} is specified; expected one of: {}",
+                    name, expected
+                );
+                return Err(syn::Error::new(span, msg));
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+/// Parse the arguments of `#[pyo3_async_runtimes::async_std::main]` or `::test`
+pub(crate) fn parse_args(args: TokenStream, is_test: bool) -> Result<Configuration, syn::Error> {
+    let args = syn::parse::Parser::parse(crate::parse_args, args)?;
+
+    parse_config(args.into_iter().collect(), is_test)
+}
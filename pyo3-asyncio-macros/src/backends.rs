@@ -0,0 +1,139 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
+use quote::quote;
+
+/// A backend of `#[pyo3_async_runtimes::test_all_backends]`
+struct Backend {
+    /// The name of the generated test
+    name: &'static str,
+    /// The variant of `pyo3_async_runtimes::testing::Backend`
+    variant: &'static str,
+    /// The generic runtime that generic tests are instantiated with
+    runtime: proc_macro2::TokenStream,
+}
+
+fn backends() -> (Vec<Backend>, Vec<Backend>) {
+    let tokio = vec![
+        Backend {
+            name: "tokio_multi_thread",
+            variant: "TokioMultiThread",
+            runtime: quote! { pyo3_async_runtimes::tokio::TokioRuntime },
+        },
+        Backend {
+            name: "tokio_current_thread",
+            variant: "TokioCurrentThread",
+            runtime: quote! { pyo3_async_runtimes::tokio::TokioRuntime },
+        },
+    ];
+    let async_std = vec![Backend {
+        name: "async_std",
+        variant: "AsyncStd",
+        runtime: quote! { pyo3_async_runtimes::async_std::AsyncStdRuntime },
+    }];
+
+    (tokio, async_std)
+}
+
+/// Check that the test is an async fn without arguments, and whether it's generic over the runtime
+fn check_signature(sig: &syn::Signature) -> Result<bool, syn::Error> {
+    if sig.asyncness.is_none() {
+        let msg = "the async keyword is missing from the function declaration";
+        return Err(syn::Error::new_spanned(sig.fn_token, msg));
+    }
+
+    if let Some(arg) = sig.inputs.first() {
+        let msg = "tests for all backends can't take arguments";
+        return Err(syn::Error::new_spanned(arg, msg));
+    }
+
+    let mut params = sig.generics.params.iter();
+    match (params.next(), params.next()) {
+        (None, _) => Ok(false),
+        (Some(syn::GenericParam::Type(_)), None) => Ok(true),
+        (Some(param), _) => {
+            let msg = "tests for all backends can only be generic over the runtime, e.g. \
+                       `async fn test<R: ContextExt>()`";
+            Err(syn::Error::new_spanned(param, msg))
+        }
+    }
+}
+
+fn parse_test_all_backends(input: syn::ItemFn) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let is_generic = check_signature(&input.sig)?;
+
+    let vis = &input.vis;
+    let name = &input.sig.ident;
+
+    let tests = |backends: Vec<Backend>| {
+        backends
+            .into_iter()
+            .map(|backend| {
+                let test_name = Ident::new(backend.name, Span::call_site());
+                let variant = Ident::new(backend.variant, Span::call_site());
+                let runtime = &backend.runtime;
+                let test = if is_generic {
+                    quote! { super::#name::<#runtime> }
+                } else {
+                    quote! { super::#name }
+                };
+
+                quote! {
+                    fn #test_name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                        Box::pin(pyo3_async_runtimes::testing::run_on_backend(
+                            pyo3_async_runtimes::testing::Backend::#variant,
+                            #test,
+                        ))
+                    }
+
+                    pyo3_async_runtimes::inventory::submit! {
+                        pyo3_async_runtimes::testing::Test {
+                            name: concat!(std::module_path!(), "::", stringify!(#test_name)),
+                            test_fn: &#test_name,
+                            serial: false,
+                            shared_loop: false,
+                        }
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let (tokio, async_std) = backends();
+    let tokio_tests = tests(tokio);
+    let async_std_tests = tests(async_std);
+
+    // the module has the name of the test, so that the tests are named `<test>::<backend>`
+    Ok(quote! {
+        #input
+
+        #vis mod #name {
+            const _: () = ::std::assert!(
+                pyo3_async_runtimes::__private::TEST_BACKENDS > 0,
+                "no backend is enabled, enable the `tokio-runtime` or `async-std-runtime` feature of pyo3-async-runtimes"
+            );
+
+            pyo3_async_runtimes::__if_tokio_runtime! {
+                #(#tokio_tests)*
+            }
+
+            pyo3_async_runtimes::__if_async_std_runtime! {
+                #(#async_std_tests)*
+            }
+        }
+    })
+}
+
+#[cfg(not(test))] // Work around for rust-lang/rust#62127
+pub(crate) fn test_all_backends(args: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    if !args.is_empty() {
+        let msg = "`test_all_backends` doesn't take arguments";
+        return syn::Error::new(Span::call_site(), msg)
+            .to_compile_error()
+            .into();
+    }
+
+    parse_test_all_backends(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
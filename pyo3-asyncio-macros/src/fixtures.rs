@@ -0,0 +1,80 @@
+use proc_macro2::Ident;
+use quote::{format_ident, quote};
+
+/// The parameters of a test, which are set up before it runs
+pub(crate) struct Fixtures {
+    /// The statements that set up the parameters, in an async fn that returns a `PyResult`
+    pub(crate) setup: proc_macro2::TokenStream,
+    /// The variables that hold the parameters, in the order of the test's signature
+    pub(crate) args: Vec<Ident>,
+}
+
+impl Fixtures {
+    /// Whether the test takes any parameters
+    pub(crate) fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+}
+
+/// Whether `ty` is `PyObject`, which is the event loop for backwards compatibility
+fn is_py_object(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => {
+            matches!(path.path.segments.last(), Some(segment) if segment.ident == "PyObject")
+        }
+        _ => false,
+    }
+}
+
+/// Parse the parameters of a test into the fixtures that are passed to it
+///
+/// `runtime` is the path of the runtime module whose `get_current_loop` gets the event loop for a
+/// `PyObject` parameter.
+pub(crate) fn parse_fixtures(
+    sig: &syn::Signature,
+    runtime: proc_macro2::TokenStream,
+) -> Result<Fixtures, syn::Error> {
+    let mut setup = Vec::new();
+    let mut args = Vec::new();
+
+    for input in &sig.inputs {
+        let pat_type = match input {
+            syn::FnArg::Typed(pat_type) => pat_type,
+            syn::FnArg::Receiver(receiver) => {
+                let msg = "tests can't take `self`";
+                return Err(syn::Error::new_spanned(receiver, msg));
+            }
+        };
+        let name = match &*pat_type.pat {
+            syn::Pat::Ident(pat_ident) => &pat_ident.ident,
+            pat => {
+                let msg = "parameters of tests must be plain identifiers";
+                return Err(syn::Error::new_spanned(pat, msg));
+            }
+        };
+        if let syn::Type::Reference(_) = &*pat_type.ty {
+            let msg = "parameters of tests must be owned, e.g. a `T: Fixture` instead of `&T`";
+            return Err(syn::Error::new_spanned(&pat_type.ty, msg));
+        }
+
+        let ty = &pat_type.ty;
+        let var = format_ident!("__pyo3_async_runtimes_fixture_{}", name);
+        setup.push(if is_py_object(ty) {
+            quote! {
+                let #var: #ty = pyo3::Python::with_gil(|py| {
+                    #runtime::get_current_loop(py).map(::std::convert::Into::into)
+                })?;
+            }
+        } else {
+            quote! {
+                let #var: #ty = <#ty as pyo3_async_runtimes::testing::Fixture>::setup().await?;
+            }
+        });
+        args.push(var);
+    }
+
+    Ok(Fixtures {
+        setup: quote! { #(#setup)* },
+        args,
+    })
+}
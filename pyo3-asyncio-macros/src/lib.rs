@@ -2,14 +2,148 @@
 #![deny(missing_debug_implementations, nonstandard_style)]
 #![recursion_limit = "512"]
 
+mod async_methods;
+mod async_std;
+mod backends;
+mod fixtures;
+mod policy;
+mod pyfunction;
+mod runtime;
 mod tokio;
 
 use proc_macro::TokenStream;
 use quote::{quote, quote_spanned};
-use syn::spanned::Spanned;
+use syn::{ext::IdentExt, punctuated::Punctuated, spanned::Spanned};
+
+/// The end of a generated `main`, which runs the async `main` with `run` and turns its result into
+/// the exit code of the process
+///
+/// A `PyResult` is handled like the Python interpreter would: the traceback of the error is
+/// printed, and a `SystemExit` exits with its code. Other return types, e.g. `anyhow::Result` or
+/// `ExitCode`, are reported like the return value of a synchronous `main`.
+pub(crate) fn run_main(
+    run: proc_macro2::TokenStream,
+    ret: &syn::ReturnType,
+) -> proc_macro2::TokenStream {
+    let is_py_result = match ret {
+        syn::ReturnType::Type(_, ty) => match &**ty {
+            syn::Type::Path(path) => {
+                matches!(path.path.segments.last(), Some(segment) if segment.ident == "PyResult")
+            }
+            _ => false,
+        },
+        syn::ReturnType::Default => false,
+    };
+
+    let fut = if is_py_result {
+        quote! { main() }
+    } else {
+        quote! { async { Ok(main().await) } }
+    };
+
+    quote! {
+        pyo3::Python::with_gil(|py| match #run(py, #fut) {
+            Ok(result) => std::process::Termination::report(result),
+            Err(e) => pyo3_async_runtimes::__private::exit_code(py, e),
+        })
+    }
+}
+
+/// Parse the comma-separated arguments of a `main` or `test` attribute
+///
+/// This is `Punctuated::<syn::Meta, Token![,]>::parse_terminated`, except that the name of a
+/// `name = value` argument may be a keyword, like in `loop = "uvloop"`.
+pub(crate) fn parse_args(
+    input: syn::parse::ParseStream<'_>,
+) -> syn::Result<Punctuated<syn::Meta, syn::Token![,]>> {
+    let mut args = Punctuated::new();
+
+    while !input.is_empty() {
+        if input.peek(syn::Ident::peek_any) && input.peek2(syn::Token![=]) {
+            args.push_value(syn::Meta::NameValue(syn::MetaNameValue {
+                path: input.call(syn::Ident::parse_any)?.into(),
+                eq_token: input.parse()?,
+                value: input.parse()?,
+            }));
+        } else {
+            args.push_value(input.parse()?);
+        }
+
+        if input.is_empty() {
+            break;
+        }
+        args.push_punct(input.parse()?);
+    }
+
+    Ok(args)
+}
+
+/// Enables an async main function that uses the actix runtime.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::actix::main]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn actix_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let ret = &input.sig.output;
+    let inputs = &input.sig.inputs;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+
+    if name != "main" {
+        return TokenStream::from(quote_spanned! { name.span() =>
+            compile_error!("only the main function can be tagged with #[actix::main]"),
+        });
+    }
+
+    if input.sig.asyncness.is_none() {
+        return TokenStream::from(quote_spanned! { input.span() =>
+            compile_error!("the async keyword is missing from the function declaration"),
+        });
+    }
+
+    let run_main = run_main(quote! { pyo3_async_runtimes::actix::run }, ret);
+
+    let result = quote! {
+        #vis fn main() -> std::process::ExitCode {
+            #(#attrs)*
+            async fn main(#inputs) #ret {
+                #body
+            }
+
+            pyo3::prepare_freethreaded_python();
+
+            #run_main
+        }
+    };
+
+    result.into()
+}
 
 /// Enables an async main function that uses the async-std runtime.
 ///
+/// # Arguments
+/// * `worker_threads` - number of threads of the global executor, defaults to the number of CPUs
+///   on the system
+/// * `thread_name` - name of the threads of the global executor
+/// * `event_loop_policy` - installs this event loop policy before the event loop is created
+///   ["asyncio", "uvloop", "winloop", "selector", "proactor"]. The process fails if its module
+///   isn't installed.
+/// * `loop` - an alias of `event_loop_policy`, e.g. `loop = "uvloop"`
+///
+/// The arguments take precedence over the `ASYNC_STD_THREAD_COUNT` and `ASYNC_STD_THREAD_NAME`
+/// environment variables.
+///
 /// # Examples
 ///
 /// ```ignore
@@ -18,10 +152,23 @@ use syn::spanned::Spanned;
 ///     Ok(())
 /// }
 /// ```
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::async_std::main(worker_threads = 2, thread_name = "executor")]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
 #[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
 #[proc_macro_attribute]
-pub fn async_std_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn async_std_main(args: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
+    let config = match async_std::parse_args(args, false) {
+        Ok(config) => config,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let executor_init = config.executor_init();
+    let policy_init = policy::main_init(config.event_loop_policy());
 
     let ret = &input.sig.output;
     let inputs = &input.sig.inputs;
@@ -42,70 +189,953 @@ pub fn async_std_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
         });
     }
 
+    let run_main = run_main(quote! { pyo3_async_runtimes::async_std::run }, ret);
+
     let result = quote! {
-        #vis fn main() {
+        #vis fn main() -> std::process::ExitCode {
             #(#attrs)*
             async fn main(#inputs) #ret {
                 #body
             }
 
+            #executor_init
+
             pyo3::prepare_freethreaded_python();
 
-            pyo3::Python::with_gil(|py| {
-                pyo3_async_runtimes::async_std::run(py, main())
-                    .map_err(|e| {
-                        e.print_and_set_sys_last_vars(py);
-                    })
-                    .unwrap();
-            });
+            #policy_init
+
+            #run_main
         }
     };
 
     result.into()
 }
 
-/// Enables an async main function that uses the tokio runtime.
+/// Enables an async main function that uses the compio runtime.
 ///
-/// # Arguments
-/// * `flavor` - selects the type of tokio runtime ["multi_thread", "current_thread"]
-/// * `worker_threads` - number of worker threads, defaults to the number of CPUs on the system
+/// # Examples
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::compio::main]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn compio_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let ret = &input.sig.output;
+    let inputs = &input.sig.inputs;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+
+    if name != "main" {
+        return TokenStream::from(quote_spanned! { name.span() =>
+            compile_error!("only the main function can be tagged with #[compio::main]"),
+        });
+    }
+
+    if input.sig.asyncness.is_none() {
+        return TokenStream::from(quote_spanned! { input.span() =>
+            compile_error!("the async keyword is missing from the function declaration"),
+        });
+    }
+
+    let run_main = run_main(quote! { pyo3_async_runtimes::compio::run }, ret);
+
+    let result = quote! {
+        #vis fn main() -> std::process::ExitCode {
+            #(#attrs)*
+            async fn main(#inputs) #ret {
+                #body
+            }
+
+            pyo3::prepare_freethreaded_python();
+
+            #run_main
+        }
+    };
+
+    result.into()
+}
+
+/// Enables an async main function that uses the glommio runtime.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::glommio::main]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn glommio_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let ret = &input.sig.output;
+    let inputs = &input.sig.inputs;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+
+    if name != "main" {
+        return TokenStream::from(quote_spanned! { name.span() =>
+            compile_error!("only the main function can be tagged with #[glommio::main]"),
+        });
+    }
+
+    if input.sig.asyncness.is_none() {
+        return TokenStream::from(quote_spanned! { input.span() =>
+            compile_error!("the async keyword is missing from the function declaration"),
+        });
+    }
+
+    let run_main = run_main(quote! { pyo3_async_runtimes::glommio::run }, ret);
+
+    let result = quote! {
+        #vis fn main() -> std::process::ExitCode {
+            #(#attrs)*
+            async fn main(#inputs) #ret {
+                #body
+            }
+
+            pyo3::prepare_freethreaded_python();
+
+            #run_main
+        }
+    };
+
+    result.into()
+}
+
+/// Enables an async main function that uses the `futures` `LocalPool` executor.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::local_pool::main]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn local_pool_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let ret = &input.sig.output;
+    let inputs = &input.sig.inputs;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+
+    if name != "main" {
+        return TokenStream::from(quote_spanned! { name.span() =>
+            compile_error!("only the main function can be tagged with #[local_pool::main]"),
+        });
+    }
+
+    if input.sig.asyncness.is_none() {
+        return TokenStream::from(quote_spanned! { input.span() =>
+            compile_error!("the async keyword is missing from the function declaration"),
+        });
+    }
+
+    let run_main = run_main(quote! { pyo3_async_runtimes::local_pool::run }, ret);
+
+    let result = quote! {
+        #vis fn main() -> std::process::ExitCode {
+            #(#attrs)*
+            async fn main(#inputs) #ret {
+                #body
+            }
+
+            pyo3::prepare_freethreaded_python();
+
+            #run_main
+        }
+    };
+
+    result.into()
+}
+
+/// Enables an async main function that uses the monoio runtime.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::monoio::main]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn monoio_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let ret = &input.sig.output;
+    let inputs = &input.sig.inputs;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+
+    if name != "main" {
+        return TokenStream::from(quote_spanned! { name.span() =>
+            compile_error!("only the main function can be tagged with #[monoio::main]"),
+        });
+    }
+
+    if input.sig.asyncness.is_none() {
+        return TokenStream::from(quote_spanned! { input.span() =>
+            compile_error!("the async keyword is missing from the function declaration"),
+        });
+    }
+
+    let run_main = run_main(quote! { pyo3_async_runtimes::monoio::run }, ret);
+
+    let result = quote! {
+        #vis fn main() -> std::process::ExitCode {
+            #(#attrs)*
+            async fn main(#inputs) #ret {
+                #body
+            }
+
+            pyo3::prepare_freethreaded_python();
+
+            #run_main
+        }
+    };
+
+    result.into()
+}
+
+/// Enables an async main function that uses the smol runtime.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::smol::main]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn smol_main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let ret = &input.sig.output;
+    let inputs = &input.sig.inputs;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+
+    if name != "main" {
+        return TokenStream::from(quote_spanned! { name.span() =>
+            compile_error!("only the main function can be tagged with #[smol::main]"),
+        });
+    }
+
+    if input.sig.asyncness.is_none() {
+        return TokenStream::from(quote_spanned! { input.span() =>
+            compile_error!("the async keyword is missing from the function declaration"),
+        });
+    }
+
+    let run_main = run_main(quote! { pyo3_async_runtimes::smol::run }, ret);
+
+    let result = quote! {
+        #vis fn main() -> std::process::ExitCode {
+            #(#attrs)*
+            async fn main(#inputs) #ret {
+                #body
+            }
+
+            pyo3::prepare_freethreaded_python();
+
+            #run_main
+        }
+    };
+
+    result.into()
+}
+
+/// Enables an async main function that uses the tokio runtime.
+///
+/// # Arguments
+/// * `flavor` - selects the type of tokio runtime ["multi_thread", "current_thread"]
+/// * `worker_threads` - number of worker threads, defaults to the number of CPUs on the system
+/// * `builder` - path of a function that returns the `tokio::runtime::Builder` of the runtime, for
+///   settings beyond `flavor` and `worker_threads`, e.g. thread names, stack sizes or
+///   `on_thread_start` hooks. The builder is used as is, so enable the drivers with `enable_all`.
+///   Can't be combined with `flavor` or `worker_threads`.
+/// * `event_loop_policy` - installs this event loop policy before the event loop is created
+///   ["asyncio", "uvloop", "winloop", "selector", "proactor"]. The process fails if its module
+///   isn't installed.
+/// * `loop` - an alias of `event_loop_policy`, e.g. `loop = "uvloop"`
+///
+/// # Exit code
+///
+/// If `main` returns a `PyResult`, an error is handled like the Python interpreter would: its
+/// traceback is printed and the process exits with code 1, unless it's a `SystemExit`, which exits
+/// with its code. The `Ok` value, as well as any other return type, e.g. `std::process::ExitCode`
+/// or `anyhow::Result<()>`, is reported like the return value of a synchronous `main`. The same
+/// applies to the `main` attributes of the other runtimes.
+///
+/// # Examples
+///
+/// Default configuration:
+/// ```ignore
+/// #[pyo3_async_runtimes::tokio::main]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+///
+/// Current-thread scheduler:
+/// ```ignore
+/// #[pyo3_async_runtimes::tokio::main(flavor = "current_thread")]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+///
+/// Multi-thread scheduler with custom worker thread count:
+/// ```ignore
+/// #[pyo3_async_runtimes::tokio::main(flavor = "multi_thread", worker_threads = 10)]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+///
+/// Runtime from a builder function:
+/// ```ignore
+/// fn build_runtime() -> tokio::runtime::Builder {
+///     let mut builder = tokio::runtime::Builder::new_multi_thread();
+///     builder.enable_all().thread_name("my-worker").thread_stack_size(4 * 1024 * 1024);
+///     builder
+/// }
+///
+/// #[pyo3_async_runtimes::tokio::main(builder = "build_runtime")]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+///
+/// Exit code of the process:
+/// ```ignore
+/// use std::process::ExitCode;
+///
+/// #[pyo3_async_runtimes::tokio::main]
+/// async fn main() -> PyResult<ExitCode> {
+///     Ok(ExitCode::from(2))
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn tokio_main(args: TokenStream, item: TokenStream) -> TokenStream {
+    tokio::main(args, item, true)
+}
+
+/// Registers an `actix` test with the `pyo3-asyncio` test harness.
+///
+/// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
+/// testing within an integration test. It will accept `async` test functions, but it will also
+/// accept blocking functions as well. Blocking functions are run on their own thread so that they
+/// don't stall the arbiter.
+///
+/// # Examples
+/// ```ignore
+/// use std::{time::Duration, thread};
+///
+/// use pyo3::prelude::*;
+///
+/// // async test function
+/// #[pyo3_async_runtimes::actix::test]
+/// async fn test_async_sleep() -> PyResult<()> {
+///     actix_rt::time::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// }
+///
+/// // blocking test function
+/// #[pyo3_async_runtimes::actix::test]
+/// fn test_blocking_sleep() -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+///
+/// // blocking test functions can optionally accept an event_loop parameter
+/// #[pyo3_async_runtimes::actix::test]
+/// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn actix_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let sig = &input.sig;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let vis = &input.vis;
+
+    let fn_impl = if input.sig.asyncness.is_none() {
+        // Optionally pass an event_loop parameter to blocking tasks
+        let task = if sig.inputs.is_empty() {
+            quote! {
+                Box::pin(pyo3_async_runtimes::actix::re_exports::unblock(move || {
+                    #name()
+                }))
+            }
+        } else {
+            quote! {
+                let event_loop = Python::with_gil(|py| {
+                    pyo3_async_runtimes::actix::get_current_loop(py).unwrap().into()
+                });
+                Box::pin(pyo3_async_runtimes::actix::re_exports::unblock(move || {
+                    #name(event_loop)
+                }))
+            }
+        };
+
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                #task
+            }
+        }
+    } else {
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                Box::pin(#name())
+            }
+        }
+    };
+
+    let result = quote! {
+        #fn_impl
+
+        pyo3_async_runtimes::inventory::submit! {
+            pyo3_async_runtimes::testing::Test {
+                name: concat!(std::module_path!(), "::", stringify!(#name)),
+                test_fn: &#name,
+                serial: false,
+                shared_loop: false,
+            }
+        }
+    };
+
+    result.into()
+}
+
+/// Registers an `async-std` test with the `pyo3-asyncio` test harness.
+///
+/// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
+/// testing within an integration test. Like the `#[async_std::test]` attribute, it will accept
+/// `async` test functions, but it will also accept blocking functions as well.
+///
+/// The test can take parameters whose types implement `pyo3_async_runtimes::testing::Fixture`,
+/// e.g. `TaskLocals`, `PyEventLoop` or `testing::TempEventLoop`, which are set up before it runs.
+///
+/// # Arguments
+/// * `thread_name` - runs the test on a thread of its own with this name, instead of the global
+///   executor or its blocking pool. Async tests are blocked on with the task locals of the
+///   harness, so conversions in the test still use its event loop.
+/// * `event_loop_policy` - runs the async test on an event loop of its own, created by this
+///   event loop policy ["asyncio", "uvloop", "winloop", "selector", "proactor"] and running on a
+///   thread of its own. The policy isn't installed, so the other tests keep using the event loop
+///   of the harness.
+/// * `loop` - an alias of `event_loop_policy`, e.g. `loop = "uvloop"`
+/// * `serial` - runs the test on its own, after the tests that run concurrently, e.g. because it
+///   changes the global state of Python
+/// * `shared_loop` - runs the test with an event loop policy on the loop that all tests with the
+///   policy and `shared_loop` share, instead of a new loop. Tests without a policy run on the loop
+///   of the harness either way.
+///
+/// The number of threads of the global executor is shared by all tests, set it with the
+/// `worker_threads` argument of `#[pyo3_async_runtimes::async_std::main]`.
+///
+/// # Examples
+/// ```ignore
+/// use std::{time::Duration, thread};
+///
+/// use pyo3::prelude::*;
+///
+/// // async test function
+/// #[pyo3_async_runtimes::async_std::test]
+/// async fn test_async_sleep() -> PyResult<()> {
+///     async_std::task::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// }
+///
+/// // blocking test function
+/// #[pyo3_async_runtimes::async_std::test]
+/// fn test_blocking_sleep() -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+///
+/// // blocking test functions can optionally accept an event_loop parameter
+/// #[pyo3_async_runtimes::async_std::test]
+/// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+///
+/// // async test function that runs on a thread of its own
+/// #[pyo3_async_runtimes::async_std::test(thread_name = "sleeper")]
+/// async fn test_async_sleep_on_thread() -> PyResult<()> {
+///     assert_eq!(thread::current().name(), Some("sleeper"));
+///     async_std::task::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn async_std_test(args: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+    let config = match async_std::parse_args(args, true) {
+        Ok(config) => config,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    if let Err(e) = policy::check_test(&input.sig, config.event_loop_policy()) {
+        return e.to_compile_error().into();
+    }
+
+    let sig = &input.sig;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let vis = &input.vis;
+    let serial = config.serial();
+    let shared_loop = config.shared_loop();
+
+    // the parameters are set up by the task, in the task locals of the test
+    let fixtures = match fixtures::parse_fixtures(sig, quote! { pyo3_async_runtimes::async_std }) {
+        Ok(fixtures) => fixtures,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let setup = &fixtures.setup;
+    let args = &fixtures.args;
+
+    let fn_impl = if input.sig.asyncness.is_none() {
+        let call = config.test_thread(quote! { #name(#(#args),*) });
+        let task = quote! {
+            Box::pin(async move {
+                #setup
+                pyo3_async_runtimes::async_std::re_exports::spawn_blocking(move || {
+                    #call
+                }).await
+            })
+        };
+
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                #task
+            }
+        }
+    } else {
+        let task = if config.has_test_thread() {
+            let call = config.test_thread(quote! {
+                pyo3_async_runtimes::async_std::re_exports::block_on(
+                    pyo3_async_runtimes::async_std::scope(locals, #name(#(#args),*))
+                )
+            });
+
+            quote! {
+                Box::pin(async move {
+                    let locals = pyo3::Python::with_gil(pyo3_async_runtimes::async_std::get_current_locals)?;
+                    #setup
+                    pyo3_async_runtimes::async_std::re_exports::spawn_blocking(move || {
+                        #call
+                    }).await
+                })
+            }
+        } else if fixtures.is_empty() {
+            quote! {
+                Box::pin(#name())
+            }
+        } else {
+            quote! {
+                Box::pin(async move {
+                    #setup
+                    #name(#(#args),*).await
+                })
+            }
+        };
+        let task = policy::test_task(
+            config.event_loop_policy(),
+            config.shared_loop(),
+            quote! { pyo3_async_runtimes::async_std::scope },
+            task,
+        );
+
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                #task
+            }
+        }
+    };
+
+    let result = quote! {
+        #fn_impl
+
+        pyo3_async_runtimes::inventory::submit! {
+            pyo3_async_runtimes::testing::Test {
+                name: concat!(std::module_path!(), "::", stringify!(#name)),
+                test_fn: &#name,
+                serial: #serial,
+                shared_loop: #shared_loop,
+            }
+        }
+    };
+
+    result.into()
+}
+
+/// Registers a `compio` test with the `pyo3-asyncio` test harness.
+///
+/// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
+/// testing within an integration test. It will accept `async` test functions, but it will also
+/// accept blocking functions as well. Blocking functions are run on their own thread so that they
+/// don't stall the compio executor.
+///
+/// # Examples
+/// ```ignore
+/// use std::{time::Duration, thread};
+///
+/// use pyo3::prelude::*;
+///
+/// // async test function
+/// #[pyo3_async_runtimes::compio::test]
+/// async fn test_async_sleep() -> PyResult<()> {
+///     pyo3_async_runtimes::compio::spawn_pinned(|| {
+///         compio::time::sleep(Duration::from_secs(1))
+///     })
+///     .await;
+///     Ok(())
+/// }
+///
+/// // blocking test function
+/// #[pyo3_async_runtimes::compio::test]
+/// fn test_blocking_sleep() -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+///
+/// // blocking test functions can optionally accept an event_loop parameter
+/// #[pyo3_async_runtimes::compio::test]
+/// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn compio_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let sig = &input.sig;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let vis = &input.vis;
+
+    let fn_impl = if input.sig.asyncness.is_none() {
+        // Optionally pass an event_loop parameter to blocking tasks
+        let task = if sig.inputs.is_empty() {
+            quote! {
+                Box::pin(pyo3_async_runtimes::compio::re_exports::unblock(move || {
+                    #name()
+                }))
+            }
+        } else {
+            quote! {
+                let event_loop = Python::with_gil(|py| {
+                    pyo3_async_runtimes::compio::get_current_loop(py).unwrap().into()
+                });
+                Box::pin(pyo3_async_runtimes::compio::re_exports::unblock(move || {
+                    #name(event_loop)
+                }))
+            }
+        };
+
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                #task
+            }
+        }
+    } else {
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                Box::pin(#name())
+            }
+        }
+    };
+
+    let result = quote! {
+        #fn_impl
+
+        pyo3_async_runtimes::inventory::submit! {
+            pyo3_async_runtimes::testing::Test {
+                name: concat!(std::module_path!(), "::", stringify!(#name)),
+                test_fn: &#name,
+                serial: false,
+                shared_loop: false,
+            }
+        }
+    };
+
+    result.into()
+}
+
+/// Registers a `glommio` test with the `pyo3-asyncio` test harness.
+///
+/// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
+/// testing within an integration test. It will accept `async` test functions, but it will also
+/// accept blocking functions as well. Blocking functions are run on their own thread so that they
+/// don't stall the glommio executor.
+///
+/// # Examples
+/// ```ignore
+/// use std::{time::Duration, thread};
+///
+/// use pyo3::prelude::*;
+///
+/// // async test function
+/// #[pyo3_async_runtimes::glommio::test]
+/// async fn test_async_sleep() -> PyResult<()> {
+///     pyo3_async_runtimes::glommio::spawn_pinned(|| {
+///         glommio::timer::sleep(Duration::from_secs(1))
+///     })
+///     .await;
+///     Ok(())
+/// }
+///
+/// // blocking test function
+/// #[pyo3_async_runtimes::glommio::test]
+/// fn test_blocking_sleep() -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+///
+/// // blocking test functions can optionally accept an event_loop parameter
+/// #[pyo3_async_runtimes::glommio::test]
+/// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn glommio_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let sig = &input.sig;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let vis = &input.vis;
+
+    let fn_impl = if input.sig.asyncness.is_none() {
+        // Optionally pass an event_loop parameter to blocking tasks
+        let task = if sig.inputs.is_empty() {
+            quote! {
+                Box::pin(pyo3_async_runtimes::glommio::re_exports::unblock(move || {
+                    #name()
+                }))
+            }
+        } else {
+            quote! {
+                let event_loop = Python::with_gil(|py| {
+                    pyo3_async_runtimes::glommio::get_current_loop(py).unwrap().into()
+                });
+                Box::pin(pyo3_async_runtimes::glommio::re_exports::unblock(move || {
+                    #name(event_loop)
+                }))
+            }
+        };
+
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                #task
+            }
+        }
+    } else {
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                Box::pin(#name())
+            }
+        }
+    };
+
+    let result = quote! {
+        #fn_impl
+
+        pyo3_async_runtimes::inventory::submit! {
+            pyo3_async_runtimes::testing::Test {
+                name: concat!(std::module_path!(), "::", stringify!(#name)),
+                test_fn: &#name,
+                serial: false,
+                shared_loop: false,
+            }
+        }
+    };
+
+    result.into()
+}
+
+/// Registers a `local_pool` test with the `pyo3-asyncio` test harness.
+///
+/// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
+/// testing within an integration test. It will accept `async` test functions, but it will also
+/// accept blocking functions as well. Blocking functions are run on their own thread so that they
+/// don't stall the `LocalPool` executor.
 ///
 /// # Examples
-///
-/// Default configuration:
 /// ```ignore
-/// #[pyo3_async_runtimes::tokio::main]
-/// async fn main() -> PyResult<()> {
+/// use std::{time::Duration, thread};
+///
+/// use pyo3::prelude::*;
+///
+/// // async test function
+/// #[pyo3_async_runtimes::local_pool::test]
+/// async fn test_async_sleep() -> PyResult<()> {
+///     pyo3_async_runtimes::local_pool::re_exports::unblock(|| {
+///         thread::sleep(Duration::from_secs(1))
+///     })
+///     .await;
 ///     Ok(())
 /// }
-/// ```
 ///
-/// Current-thread scheduler:
-/// ```ignore
-/// #[pyo3_async_runtimes::tokio::main(flavor = "current_thread")]
-/// async fn main() -> PyResult<()> {
+/// // blocking test function
+/// #[pyo3_async_runtimes::local_pool::test]
+/// fn test_blocking_sleep() -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
 ///     Ok(())
 /// }
-/// ```
 ///
-/// Multi-thread scheduler with custom worker thread count:
-/// ```ignore
-/// #[pyo3_async_runtimes::tokio::main(flavor = "multi_thread", worker_threads = 10)]
-/// async fn main() -> PyResult<()> {
+/// // blocking test functions can optionally accept an event_loop parameter
+/// #[pyo3_async_runtimes::local_pool::test]
+/// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
 ///     Ok(())
 /// }
 /// ```
 #[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
 #[proc_macro_attribute]
-pub fn tokio_main(args: TokenStream, item: TokenStream) -> TokenStream {
-    tokio::main(args, item, true)
+pub fn local_pool_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+
+    let sig = &input.sig;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let vis = &input.vis;
+
+    let fn_impl = if input.sig.asyncness.is_none() {
+        // Optionally pass an event_loop parameter to blocking tasks
+        let task = if sig.inputs.is_empty() {
+            quote! {
+                Box::pin(pyo3_async_runtimes::local_pool::re_exports::unblock(move || {
+                    #name()
+                }))
+            }
+        } else {
+            quote! {
+                let event_loop = Python::with_gil(|py| {
+                    pyo3_async_runtimes::local_pool::get_current_loop(py).unwrap().into()
+                });
+                Box::pin(pyo3_async_runtimes::local_pool::re_exports::unblock(move || {
+                    #name(event_loop)
+                }))
+            }
+        };
+
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                #task
+            }
+        }
+    } else {
+        quote! {
+            #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+                #sig {
+                    #body
+                }
+
+                Box::pin(#name())
+            }
+        }
+    };
+
+    let result = quote! {
+        #fn_impl
+
+        pyo3_async_runtimes::inventory::submit! {
+            pyo3_async_runtimes::testing::Test {
+                name: concat!(std::module_path!(), "::", stringify!(#name)),
+                test_fn: &#name,
+                serial: false,
+                shared_loop: false,
+            }
+        }
+    };
+
+    result.into()
 }
 
-/// Registers an `async-std` test with the `pyo3-asyncio` test harness.
+/// Registers a `monoio` test with the `pyo3-asyncio` test harness.
 ///
 /// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
-/// testing within an integration test. Like the `#[async_std::test]` attribute, it will accept
-/// `async` test functions, but it will also accept blocking functions as well.
+/// testing within an integration test. It will accept `async` test functions, but it will also
+/// accept blocking functions as well. Blocking functions are run on their own thread so that they
+/// don't stall the monoio executor.
 ///
 /// # Examples
 /// ```ignore
@@ -114,21 +1144,24 @@ pub fn tokio_main(args: TokenStream, item: TokenStream) -> TokenStream {
 /// use pyo3::prelude::*;
 ///
 /// // async test function
-/// #[pyo3_async_runtimes::async_std::test]
+/// #[pyo3_async_runtimes::monoio::test]
 /// async fn test_async_sleep() -> PyResult<()> {
-///     async_std::task::sleep(Duration::from_secs(1)).await;
+///     pyo3_async_runtimes::monoio::spawn_pinned(|| {
+///         monoio::time::sleep(Duration::from_secs(1))
+///     })
+///     .await;
 ///     Ok(())
 /// }
 ///
 /// // blocking test function
-/// #[pyo3_async_runtimes::async_std::test]
+/// #[pyo3_async_runtimes::monoio::test]
 /// fn test_blocking_sleep() -> PyResult<()> {
 ///     thread::sleep(Duration::from_secs(1));
 ///     Ok(())
 /// }
 ///
 /// // blocking test functions can optionally accept an event_loop parameter
-/// #[pyo3_async_runtimes::async_std::test]
+/// #[pyo3_async_runtimes::monoio::test]
 /// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
 ///     thread::sleep(Duration::from_secs(1));
 ///     Ok(())
@@ -136,7 +1169,7 @@ pub fn tokio_main(args: TokenStream, item: TokenStream) -> TokenStream {
 /// ```
 #[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
 #[proc_macro_attribute]
-pub fn async_std_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn monoio_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
 
     let sig = &input.sig;
@@ -148,16 +1181,16 @@ pub fn async_std_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
         // Optionally pass an event_loop parameter to blocking tasks
         let task = if sig.inputs.is_empty() {
             quote! {
-                Box::pin(pyo3_async_runtimes::async_std::re_exports::spawn_blocking(move || {
+                Box::pin(pyo3_async_runtimes::monoio::re_exports::unblock(move || {
                     #name()
                 }))
             }
         } else {
             quote! {
                 let event_loop = Python::with_gil(|py| {
-                    pyo3_async_runtimes::async_std::get_current_loop(py).unwrap().into()
+                    pyo3_async_runtimes::monoio::get_current_loop(py).unwrap().into()
                 });
-                Box::pin(pyo3_async_runtimes::async_std::re_exports::spawn_blocking(move || {
+                Box::pin(pyo3_async_runtimes::monoio::re_exports::unblock(move || {
                     #name(event_loop)
                 }))
             }
@@ -190,7 +1223,9 @@ pub fn async_std_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
         pyo3_async_runtimes::inventory::submit! {
             pyo3_async_runtimes::testing::Test {
                 name: concat!(std::module_path!(), "::", stringify!(#name)),
-                test_fn: &#name
+                test_fn: &#name,
+                serial: false,
+                shared_loop: false,
             }
         }
     };
@@ -198,11 +1233,12 @@ pub fn async_std_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
     result.into()
 }
 
-/// Registers a `tokio` test with the `pyo3-asyncio` test harness.
+/// Registers a `smol` test with the `pyo3-asyncio` test harness.
 ///
 /// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
-/// testing within an integration test. Like the `#[tokio::test]` attribute, it will accept `async`
-/// test functions, but it will also accept blocking functions as well.
+/// testing within an integration test. It will accept `async` test functions, but it will also
+/// accept blocking functions as well. Blocking functions are run on smol's blocking thread pool via
+/// `smol::unblock`.
 ///
 /// # Examples
 /// ```ignore
@@ -211,21 +1247,21 @@ pub fn async_std_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
 /// use pyo3::prelude::*;
 ///
 /// // async test function
-/// #[pyo3_async_runtimes::tokio::test]
+/// #[pyo3_async_runtimes::smol::test]
 /// async fn test_async_sleep() -> PyResult<()> {
-///     tokio::time::sleep(Duration::from_secs(1)).await;
+///     smol::Timer::after(Duration::from_secs(1)).await;
 ///     Ok(())
 /// }
 ///
 /// // blocking test function
-/// #[pyo3_async_runtimes::tokio::test]
+/// #[pyo3_async_runtimes::smol::test]
 /// fn test_blocking_sleep() -> PyResult<()> {
 ///     thread::sleep(Duration::from_secs(1));
 ///     Ok(())
 /// }
 ///
 /// // blocking test functions can optionally accept an event_loop parameter
-/// #[pyo3_async_runtimes::tokio::test]
+/// #[pyo3_async_runtimes::smol::test]
 /// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
 ///     thread::sleep(Duration::from_secs(1));
 ///     Ok(())
@@ -233,7 +1269,7 @@ pub fn async_std_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
 /// ```
 #[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
 #[proc_macro_attribute]
-pub fn tokio_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn smol_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
 
     let sig = &input.sig;
@@ -245,46 +1281,18 @@ pub fn tokio_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
         // Optionally pass an event_loop parameter to blocking tasks
         let task = if sig.inputs.is_empty() {
             quote! {
-                Box::pin(async move {
-                    match pyo3_async_runtimes::tokio::get_runtime().spawn_blocking(move || #name()).await {
-                        Ok(result) => result,
-                        Err(e) => {
-                            assert!(e.is_panic());
-                            let panic = e.into_panic();
-                            let panic_message = if let Some(s) = panic.downcast_ref::<&str>() {
-                                s.to_string()
-                            } else if let Some(s) = panic.downcast_ref::<String>() {
-                                s.clone()
-                            } else {
-                                "unknown error".into()
-                            };
-                            Err(pyo3_async_runtimes::err::RustPanic::new_err(format!("rust future panicked: {}", panic_message)))
-                        }
-                    }
-                })
+                Box::pin(pyo3_async_runtimes::smol::re_exports::unblock(move || {
+                    #name()
+                }))
             }
         } else {
             quote! {
                 let event_loop = Python::with_gil(|py| {
-                    pyo3_async_runtimes::tokio::get_current_loop(py).unwrap().into()
+                    pyo3_async_runtimes::smol::get_current_loop(py).unwrap().into()
                 });
-                Box::pin(async move {
-                    match pyo3_async_runtimes::tokio::get_runtime().spawn_blocking(move || #name(event_loop)).await {
-                        Ok(result) => result,
-                        Err(e) => {
-                            assert!(e.is_panic());
-                            let panic = e.into_panic();
-                            let panic_message = if let Some(s) = panic.downcast_ref::<&str>() {
-                                s.to_string()
-                            } else if let Some(s) = panic.downcast_ref::<String>() {
-                                s.clone()
-                            } else {
-                                "unknown error".into()
-                            };
-                            Err(pyo3_async_runtimes::err::RustPanic::new_err(format!("rust future panicked: {}", panic_message)))
-                        }
-                    }
-                })
+                Box::pin(pyo3_async_runtimes::smol::re_exports::unblock(move || {
+                    #name(event_loop)
+                }))
             }
         };
 
@@ -315,10 +1323,327 @@ pub fn tokio_test(_attr: TokenStream, item: TokenStream) -> TokenStream {
         pyo3_async_runtimes::inventory::submit! {
             pyo3_async_runtimes::testing::Test {
                 name: concat!(std::module_path!(), "::", stringify!(#name)),
-                test_fn: &#name
+                test_fn: &#name,
+                serial: false,
+                shared_loop: false,
             }
         }
     };
 
     result.into()
 }
+
+/// Registers a `tokio` test with the `pyo3-asyncio` test harness.
+///
+/// This attribute is meant to mirror the `#[test]` attribute and allow you to mark a function for
+/// testing within an integration test. Like the `#[tokio::test]` attribute, it will accept `async`
+/// test functions, but it will also accept blocking functions as well.
+///
+/// The test can take parameters whose types implement `pyo3_async_runtimes::testing::Fixture`,
+/// e.g. `TaskLocals`, `PyEventLoop` or `testing::TempEventLoop`, which are set up before it runs.
+///
+/// # Arguments
+/// * `flavor` - runs the test on a runtime of its own of this type ["multi_thread",
+///   "current_thread"]. Conversions in the test still spawn their futures on the runtime of
+///   the harness. Blocking tests run within the context of the runtime.
+/// * `worker_threads` - number of worker threads of the test's own `multi_thread` runtime
+/// * `start_paused` - starts the clock of the test's own `current_thread` runtime paused, see
+///   [`Builder::start_paused`](https://docs.rs/tokio/latest/tokio/runtime/struct.Builder.html#method.start_paused).
+///   Requires the `tokio-test-util` feature. The test still runs on the event loop of the
+///   harness, whose `loop.time()` follows the real clock.
+/// * `mock_clock` - runs an async test that starts paused in
+///   `pyo3_async_runtimes::tokio::with_mock_clock`, on an event loop of its own whose
+///   `loop.time()` is the paused clock, so that Python timers skip ahead along with the tokio ones
+/// * `timeout` - fails the test if it takes longer than this, e.g. `"500ms"`, `"30s"` or `"2m"`.
+///   A blocking test keeps running in the background, but the harness reports the failure.
+/// * `raises` - expects the test to fail with this Python exception or a subclass of it, e.g.
+///   `"ValueError"` or `"asyncio.CancelledError"`. If it succeeds or raises something else, the
+///   harness prints the exception that was actually raised.
+/// * `should_panic` - expects the test to panic, either directly or through a converted future
+///   that raised `RustPanic`. `should_panic = "..."` also checks that the panic message contains
+///   the given string.
+/// * `event_loop_policy` - runs the async test on an event loop of its own, created by this
+///   event loop policy ["asyncio", "uvloop", "winloop", "selector", "proactor"] and running on a
+///   thread of its own. The policy isn't installed, so the other tests keep using the event loop
+///   of the harness.
+/// * `loop` - an alias of `event_loop_policy`, e.g. `loop = "uvloop"`
+/// * `serial` - runs the test on its own, after the tests that run concurrently, e.g. because it
+///   changes the global state of Python
+/// * `shared_loop` - runs the test with an event loop policy on the loop that all tests with the
+///   policy and `shared_loop` share, instead of a new loop. Tests without a policy run on the loop
+///   of the harness either way.
+///
+/// # Examples
+/// ```ignore
+/// use std::{time::Duration, thread};
+///
+/// use pyo3::prelude::*;
+///
+/// // async test function
+/// #[pyo3_async_runtimes::tokio::test]
+/// async fn test_async_sleep() -> PyResult<()> {
+///     tokio::time::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// }
+///
+/// // blocking test function
+/// #[pyo3_async_runtimes::tokio::test]
+/// fn test_blocking_sleep() -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+///
+/// // blocking test functions can optionally accept an event_loop parameter
+/// #[pyo3_async_runtimes::tokio::test]
+/// fn test_blocking_sleep_with_event_loop(event_loop: PyObject) -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+///
+/// // tests can take fixtures, e.g. an event loop that is closed after the test
+/// #[pyo3_async_runtimes::tokio::test]
+/// fn test_temp_event_loop(
+///     event_loop: pyo3_async_runtimes::testing::TempEventLoop,
+/// ) -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         let sleep = py.import_bound("asyncio")?.call_method1("sleep", (0.1,))?;
+///         event_loop.bind(py).call_method1("run_until_complete", (sleep,))?;
+///         Ok(())
+///     })
+/// }
+///
+/// // async test function on a runtime of its own that fails after 30 seconds
+/// #[pyo3_async_runtimes::tokio::test(flavor = "multi_thread", worker_threads = 2, timeout = "30s")]
+/// async fn test_configured() -> PyResult<()> {
+///     tokio::time::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// }
+///
+/// // async test function whose clock skips ahead while it's idle
+/// #[pyo3_async_runtimes::tokio::test(start_paused = true)]
+/// async fn test_paused() -> PyResult<()> {
+///     tokio::time::sleep(Duration::from_secs(3600)).await;
+///     Ok(())
+/// }
+///
+/// // async test function whose Python timers skip ahead along with the tokio ones
+/// #[pyo3_async_runtimes::tokio::test(start_paused = true, mock_clock)]
+/// async fn test_mock_clock() -> PyResult<()> {
+///     let sleep = Python::with_gil(|py| {
+///         pyo3_async_runtimes::tokio::into_future(
+///             py.import_bound("asyncio")?.call_method1("sleep", (3600,))?,
+///         )
+///     })?;
+///     sleep.await?;
+///     Ok(())
+/// }
+///
+/// // async test function that passes if the awaitable raises a `TypeError`
+/// #[pyo3_async_runtimes::tokio::test(raises = "TypeError")]
+/// async fn test_raises() -> PyResult<()> {
+///     let fut = Python::with_gil(|py| {
+///         let coro = py.import_bound("asyncio")?.call_method1("sleep", ("not a delay",))?;
+///         pyo3_async_runtimes::tokio::into_future(coro)
+///     })?;
+///     fut.await?;
+///     Ok(())
+/// }
+///
+/// // blocking test function that passes if it panics with this message
+/// #[pyo3_async_runtimes::tokio::test(should_panic = "out of range")]
+/// fn test_should_panic() -> PyResult<()> {
+///     panic!("index out of range");
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn tokio_test(args: TokenStream, item: TokenStream) -> TokenStream {
+    tokio::test(args, item, true)
+}
+
+/// Enables an async main function that uses the runtime selected by the enabled Cargo features.
+///
+/// The runtime can be chosen explicitly with the `runtime` argument, which is required when more
+/// than one runtime feature is enabled. Any other arguments are passed through to the runtime's
+/// `main` attribute.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::main]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+///
+/// ```ignore
+/// #[pyo3_async_runtimes::main(runtime = "tokio", flavor = "current_thread")]
+/// async fn main() -> PyResult<()> {
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn runtime_main(args: TokenStream, item: TokenStream) -> TokenStream {
+    runtime::expand("main", args, item)
+}
+
+/// Registers a test with the `pyo3-asyncio` test harness using the runtime selected by the enabled
+/// Cargo features.
+///
+/// The runtime can be chosen explicitly with the `runtime` argument, which is required when more
+/// than one runtime feature is enabled.
+///
+/// # Examples
+/// ```ignore
+/// use std::{time::Duration, thread};
+///
+/// use pyo3::prelude::*;
+///
+/// // blocking test function
+/// #[pyo3_async_runtimes::test]
+/// fn test_blocking_sleep() -> PyResult<()> {
+///     thread::sleep(Duration::from_secs(1));
+///     Ok(())
+/// }
+///
+/// // test function for an explicitly chosen runtime
+/// #[pyo3_async_runtimes::test(runtime = "async-std")]
+/// async fn test_async_sleep() -> PyResult<()> {
+///     async_std::task::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn runtime_test(args: TokenStream, item: TokenStream) -> TokenStream {
+    runtime::expand("test", args, item)
+}
+
+/// Registers an async test with the `pyo3-asyncio` test harness once for every enabled backend.
+///
+/// The backends are a `multi_thread` and a `current_thread` tokio runtime if the `tokio-runtime`
+/// feature is enabled, and async-std if the `async-std-runtime` feature is enabled. Each instance
+/// of the test blocks a thread of its own on its backend, with the task locals of the harness, and
+/// is named after the backend, e.g. `test_sleep::tokio_current_thread`.
+///
+/// The test can be generic over the runtime, in which case each instance gets the runtime of its
+/// backend, e.g. `TokioRuntime` or `AsyncStdRuntime`, to pass to the conversions in
+/// `pyo3_async_runtimes::generic`.
+///
+/// # Examples
+/// ```ignore
+/// use pyo3::prelude::*;
+/// use pyo3_async_runtimes::generic::{self, ContextExt};
+///
+/// #[pyo3_async_runtimes::test_all_backends]
+/// async fn test_sleep<R: ContextExt>() -> PyResult<()> {
+///     let fut = Python::with_gil(|py| {
+///         generic::into_future::<R>(py.import_bound("asyncio")?.call_method1("sleep", (0.1,))?)
+///     })?;
+///     fut.await?;
+///     Ok(())
+/// }
+/// ```
+#[cfg(not(test))] // NOTE: exporting main breaks tests, we should file an issue.
+#[proc_macro_attribute]
+pub fn test_all_backends(args: TokenStream, item: TokenStream) -> TokenStream {
+    backends::test_all_backends(args, item)
+}
+
+/// Wraps an `async fn` into a `#[pyfunction]` that returns a Python awaitable.
+///
+/// The generated function extracts the arguments like any `#[pyfunction]`, and converts the future
+/// of the `async fn` with `future_into_py`, so it runs on the Rust runtime with the task locals of
+/// the caller. The arguments have to be owned, e.g. `String` rather than `&str` and `Py<T>` rather
+/// than `Bound<'py, T>`, because the future outlives the call. The `async fn` returns either
+/// nothing or a `Result` whose error converts into a `PyErr`.
+///
+/// The runtime can be chosen explicitly with the `runtime` argument, which is required when more
+/// than one runtime feature is enabled. Any other arguments, as well as `#[pyo3(...)]` attributes,
+/// are passed through to `#[pyfunction]`.
+///
+/// # Examples
+///
+/// ```ignore
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Sleep for `secs` seconds and return them
+/// #[pyo3_async_runtimes::pyfunction_async(signature = (secs = 1.0))]
+/// async fn sleep(secs: f64) -> PyResult<f64> {
+///     tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+///     Ok(secs)
+/// }
+///
+/// #[pymodule]
+/// fn my_async_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+///     m.add_function(wrap_pyfunction!(sleep, m)?)
+/// }
+/// ```
+#[cfg(not(test))]
+#[proc_macro_attribute]
+pub fn pyfunction_async(args: TokenStream, item: TokenStream) -> TokenStream {
+    pyfunction::pyfunction(args, item)
+}
+
+/// Converts the `async fn` methods of a `#[pymethods]` block into methods that return a Python
+/// awaitable.
+///
+/// The attribute has to be placed above `#[pymethods]`, so that PyO3 sees the converted methods.
+/// Like [`macro@pyfunction_async`], the arguments are extracted when the method is called, and the
+/// future runs on the Rust runtime with the task locals of the caller. The methods can get at the
+/// instance in one of two ways:
+///
+/// * `&self` - the future borrows the instance from a `Py<Self>` that it holds on to, which
+///   requires a `#[pyclass(frozen)]` that is `Sync`
+/// * `slf: Py<Self>` - the future gets its own reference to the instance, to be borrowed with the
+///   GIL held, e.g. `Python::with_gil(|py| slf.borrow(py).value)`
+///
+/// Methods without a receiver, e.g. `#[staticmethod]`s, are converted as well.
+///
+/// The runtime can be chosen explicitly with the `runtime` argument, which is required when more
+/// than one runtime feature is enabled.
+///
+/// # Examples
+///
+/// ```ignore
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// #[pyclass(frozen)]
+/// struct Timer {
+///     secs: f64,
+/// }
+///
+/// #[pyo3_async_runtimes::async_methods]
+/// #[pymethods]
+/// impl Timer {
+///     #[new]
+///     fn new(secs: f64) -> Self {
+///         Self { secs }
+///     }
+///
+///     /// Sleep for the time of the timer and return it
+///     async fn sleep(&self) -> PyResult<f64> {
+///         tokio::time::sleep(Duration::from_secs_f64(self.secs)).await;
+///         Ok(self.secs)
+///     }
+///
+///     /// Sleep for `n` times the time of the timer
+///     async fn repeat(slf: Py<Self>, n: u32) -> PyResult<()> {
+///         for _ in 0..n {
+///             let secs = slf.get().secs;
+///             tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+#[cfg(not(test))]
+#[proc_macro_attribute]
+pub fn async_methods(args: TokenStream, item: TokenStream) -> TokenStream {
+    async_methods::async_methods(args, item)
+}
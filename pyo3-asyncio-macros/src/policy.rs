@@ -0,0 +1,98 @@
+use proc_macro2::Span;
+use quote::quote;
+
+/// The event loop policies of `pyo3_async_runtimes::EVENT_LOOP_POLICIES`
+const POLICIES: &[&str] = &["asyncio", "uvloop", "winloop", "selector", "proactor"];
+
+/// Parse the value of the `event_loop_policy` argument
+pub(crate) fn parse_policy(lit: &syn::Lit, span: Span) -> Result<String, syn::Error> {
+    let policy = match lit {
+        syn::Lit::Str(s) => s.value(),
+        _ => {
+            return Err(syn::Error::new(
+                span,
+                "Failed to parse value of `event_loop_policy` as string.",
+            ))
+        }
+    };
+
+    if POLICIES.contains(&policy.as_str()) {
+        Ok(policy)
+    } else {
+        Err(syn::Error::new(
+            span,
+            format!(
+                "No such event loop policy `{}`. The event loop policies are {}.",
+                policy,
+                POLICIES
+                    .iter()
+                    .map(|policy| format!("`{}`", policy))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ))
+    }
+}
+
+/// The statements of a generated `main` that install the policy before the event loop is created
+///
+/// Failing to install it ends the process like an error of `main` would.
+pub(crate) fn main_init(policy: Option<&str>) -> proc_macro2::TokenStream {
+    let policy = match policy {
+        Some(policy) => policy,
+        None => return quote! {},
+    };
+
+    quote! {
+        if let Some(code) = pyo3::Python::with_gil(|py| {
+            pyo3_async_runtimes::install_event_loop_policy(py, #policy)
+                .err()
+                .map(|e| pyo3_async_runtimes::__private::exit_code(py, e))
+        }) {
+            return code;
+        }
+    }
+}
+
+/// Check that a test with an event loop policy is async
+pub(crate) fn check_test(sig: &syn::Signature, policy: Option<&str>) -> Result<(), syn::Error> {
+    if sig.asyncness.is_none() && policy.is_some() {
+        let msg = "The `event_loop_policy` option requires an async test, blocking tests can \
+                   create event loops of their own.";
+        return Err(syn::Error::new_spanned(sig.fn_token, msg));
+    }
+
+    Ok(())
+}
+
+/// Wrap the boxed `task` of an async test so that it runs on an event loop of the policy, with the
+/// task locals set by the `scope` function of the runtime
+///
+/// With `shared_loop`, the event loop is the one that the tests with the policy share.
+pub(crate) fn test_task(
+    policy: Option<&str>,
+    shared_loop: bool,
+    scope: proc_macro2::TokenStream,
+    task: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let policy = match policy {
+        Some(policy) => policy,
+        None => return task,
+    };
+    let with_event_loop = if shared_loop {
+        quote! { pyo3_async_runtimes::testing::with_shared_event_loop }
+    } else {
+        quote! { pyo3_async_runtimes::testing::with_event_loop_policy }
+    };
+
+    quote! {
+        let task: std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> = {
+            #task
+        };
+
+        Box::pin(#with_event_loop(
+            #policy,
+            move |locals| #scope(locals, task),
+        ))
+    }
+}
@@ -0,0 +1,218 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
+use quote::{format_ident, quote};
+use syn::{punctuated::Punctuated, spanned::Spanned};
+
+use crate::runtime::parse_runtime;
+
+/// Check that an argument can be moved into the future, which outlives the call
+pub(crate) fn check_arg_type(ty: &syn::Type) -> Result<(), syn::Error> {
+    match ty {
+        syn::Type::Reference(_) => Err(syn::Error::new_spanned(
+            ty,
+            "arguments of async pyfunctions must be owned, e.g. `String` instead of `&str`, \
+             because the future outlives the call",
+        )),
+        syn::Type::Path(path) => match path.path.segments.last() {
+            Some(segment) if segment.ident == "Python" => Err(syn::Error::new_spanned(
+                ty,
+                "async pyfunctions can't take the GIL token, use `Python::with_gil` in the body \
+                 instead",
+            )),
+            Some(segment) if segment.ident == "Bound" || segment.ident == "Borrowed" => {
+                Err(syn::Error::new_spanned(
+                    ty,
+                    "arguments of async pyfunctions can't borrow the GIL, take a `Py<T>` instead",
+                ))
+            }
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Attributes that belong to the generated `#[pyfunction]`, rather than the async fn
+pub(crate) fn is_pyfunction_attr(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("doc") || attr.path().is_ident("pyo3")
+}
+
+/// The arguments of an async fn, as they're passed from the generated wrapper
+pub(crate) struct Args {
+    /// The arguments of the async fn, without the attributes for PyO3
+    pub(crate) inner: Vec<syn::PatType>,
+    /// The arguments of the wrapper
+    pub(crate) outer: Vec<syn::PatType>,
+    pub(crate) names: Vec<Ident>,
+}
+
+impl Args {
+    pub(crate) fn parse<'a>(
+        inputs: impl Iterator<Item = &'a syn::FnArg>,
+    ) -> Result<Self, syn::Error> {
+        let mut args = Args {
+            inner: Vec::new(),
+            outer: Vec::new(),
+            names: Vec::new(),
+        };
+
+        for input in inputs {
+            let pat_type = match input {
+                syn::FnArg::Typed(pat_type) => pat_type,
+                syn::FnArg::Receiver(receiver) => {
+                    let msg = "async pyfunctions can't take `self`";
+                    return Err(syn::Error::new_spanned(receiver, msg));
+                }
+            };
+            let name = match &*pat_type.pat {
+                syn::Pat::Ident(pat_ident) => &pat_ident.ident,
+                pat => {
+                    let msg = "arguments of async pyfunctions must be plain identifiers";
+                    return Err(syn::Error::new_spanned(pat, msg));
+                }
+            };
+            check_arg_type(&pat_type.ty)?;
+
+            // the attributes of the arguments, e.g. `#[pyo3(from_py_with = "...")]`, are for PyO3
+            let mut inner = pat_type.clone();
+            inner.attrs.clear();
+            args.inner.push(inner);
+            args.outer.push(pat_type.clone());
+            args.names.push(name.clone());
+        }
+
+        Ok(args)
+    }
+}
+
+/// Split the `runtime = "..."` argument off the arguments of an attribute
+pub(crate) fn split_runtime(
+    args: Punctuated<syn::Meta, syn::Token![,]>,
+) -> Result<(Option<Ident>, Vec<syn::Meta>), syn::Error> {
+    let mut runtime = None;
+    let mut forwarded = Vec::new();
+
+    for arg in args {
+        match &arg {
+            syn::Meta::NameValue(nv) if nv.path.is_ident("runtime") => {
+                if runtime.is_some() {
+                    return Err(syn::Error::new(arg.span(), "`runtime` set multiple times."));
+                }
+
+                let lit = match &nv.value {
+                    syn::Expr::Lit(syn::ExprLit { lit, .. }) => lit,
+                    expr => return Err(syn::Error::new(expr.span(), "Must be a literal")),
+                };
+                runtime = Some(parse_runtime(lit, lit.span())?);
+            }
+            _ => forwarded.push(arg),
+        }
+    }
+
+    Ok((runtime, forwarded))
+}
+
+/// The path of the runtime module whose `future_into_py` converts the futures, along with a check
+/// that it's unambiguous if it's left to the Cargo features
+pub(crate) fn runtime_path(
+    runtime: Option<Ident>,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    match runtime {
+        Some(rt) => (quote! {}, quote! { pyo3_async_runtimes::#rt }),
+        None => (
+            quote! {
+                const _: () = ::std::assert!(
+                    pyo3_async_runtimes::__private::ENABLED_RUNTIMES == 1,
+                    "multiple runtime features are enabled, choose one with `runtime = \"...\"`"
+                );
+            },
+            quote! { pyo3_async_runtimes::__private::default_runtime },
+        ),
+    }
+}
+
+/// The future that awaits `call` and converts its result for `future_into_py`
+///
+/// Functions without a return type are awaited for their side effects.
+pub(crate) fn result_future(
+    call: proc_macro2::TokenStream,
+    ret: &syn::ReturnType,
+) -> proc_macro2::TokenStream {
+    match ret {
+        syn::ReturnType::Default => quote! {
+            async move {
+                #call.await;
+                Ok(())
+            }
+        },
+        syn::ReturnType::Type(..) => quote! {
+            async move {
+                #call.await.map_err(::std::convert::Into::<pyo3::PyErr>::into)
+            }
+        },
+    }
+}
+
+fn parse_pyfunction(
+    input: syn::ItemFn,
+    args: Punctuated<syn::Meta, syn::Token![,]>,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let (runtime, forwarded) = split_runtime(args)?;
+
+    if input.sig.asyncness.is_none() {
+        let msg = "the async keyword is missing from the function declaration";
+        return Err(syn::Error::new_spanned(input.sig.fn_token, msg));
+    }
+
+    let args = Args::parse(input.sig.inputs.iter())?;
+    let Args {
+        inner: inner_inputs,
+        outer: outer_inputs,
+        names: arg_names,
+    } = &args;
+
+    let (pyfunction_attrs, fn_attrs): (Vec<_>, Vec<_>) = input
+        .attrs
+        .iter()
+        .partition(|attr| is_pyfunction_attr(attr));
+
+    let vis = &input.vis;
+    let name = &input.sig.ident;
+    let ret = &input.sig.output;
+    let body = &input.block;
+    let py = format_ident!("__pyo3_async_runtimes_py", span = Span::call_site());
+
+    let (runtime_check, runtime) = runtime_path(runtime);
+    let fut = result_future(quote! { #name(#(#arg_names),*) }, ret);
+
+    let pyfunction = if forwarded.is_empty() {
+        quote! { #[pyo3::pyfunction] }
+    } else {
+        quote! { #[pyo3::pyfunction(#(#forwarded),*)] }
+    };
+
+    Ok(quote! {
+        #(#pyfunction_attrs)*
+        #pyfunction
+        #vis fn #name<'py>(
+            #py: pyo3::Python<'py>,
+            #(#outer_inputs),*
+        ) -> pyo3::PyResult<pyo3::Bound<'py, pyo3::PyAny>> {
+            #(#fn_attrs)*
+            async fn #name(#(#inner_inputs),*) #ret #body
+
+            #runtime_check
+
+            #runtime::future_into_py(#py, #fut)
+        }
+    })
+}
+
+#[cfg(not(test))] // Work around for rust-lang/rust#62127
+pub(crate) fn pyfunction(args: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+    let args = syn::parse_macro_input!(args with Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated);
+
+    parse_pyfunction(input, args)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -0,0 +1,99 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
+use quote::quote;
+use syn::spanned::Spanned;
+
+/// The runtime modules that provide `main` and `test` attributes
+const RUNTIMES: &[&str] = &[
+    "actix",
+    "async_std",
+    "compio",
+    "glommio",
+    "local_pool",
+    "monoio",
+    "smol",
+    "tokio",
+];
+
+pub(crate) fn parse_runtime(lit: &syn::Lit, span: Span) -> Result<Ident, syn::Error> {
+    let name = match lit {
+        syn::Lit::Str(s) => s.value().replace('-', "_"),
+        _ => {
+            return Err(syn::Error::new(
+                span,
+                "Failed to parse value of `runtime` as string.",
+            ))
+        }
+    };
+
+    if RUNTIMES.contains(&name.as_str()) {
+        Ok(Ident::new(&name, span))
+    } else {
+        Err(syn::Error::new(
+            span,
+            format!(
+                "No such runtime `{}`. The runtimes are {}.",
+                name,
+                RUNTIMES
+                    .iter()
+                    .map(|rt| format!("`{}`", rt))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ))
+    }
+}
+
+/// Forward the item to `pyo3_async_runtimes::<runtime>::<kind>`, where the runtime is either given
+/// by the `runtime = "..."` argument or selected by the enabled Cargo features
+///
+/// The remaining arguments are passed through to the runtime's attribute.
+pub(crate) fn expand(kind: &str, args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(args with crate::parse_args);
+    let item = proc_macro2::TokenStream::from(item);
+    let kind = Ident::new(kind, Span::call_site());
+
+    let mut runtime = None;
+    let mut forwarded = Vec::new();
+
+    for arg in args {
+        match &arg {
+            syn::Meta::NameValue(nv) if nv.path.is_ident("runtime") => {
+                if runtime.is_some() {
+                    return syn::Error::new(arg.span(), "`runtime` set multiple times.")
+                        .to_compile_error()
+                        .into();
+                }
+
+                let lit = match &nv.value {
+                    syn::Expr::Lit(syn::ExprLit { lit, .. }) => lit,
+                    expr => {
+                        return syn::Error::new(expr.span(), "Must be a literal")
+                            .to_compile_error()
+                            .into()
+                    }
+                };
+
+                match parse_runtime(lit, lit.span()) {
+                    Ok(rt) => runtime = Some(rt),
+                    Err(e) => return e.to_compile_error().into(),
+                }
+            }
+            _ => forwarded.push(arg),
+        }
+    }
+
+    let result = match runtime {
+        Some(rt) => quote! {
+            #[pyo3_async_runtimes::#rt::#kind(#(#forwarded),*)]
+            #item
+        },
+        None => quote! {
+            pyo3_async_runtimes::__default_runtime! {
+                #kind [#(#forwarded),*] #item
+            }
+        },
+    };
+
+    result.into()
+}
@@ -22,9 +22,24 @@ impl RuntimeFlavor {
     }
 }
 
+/// How a test is expected to fail
+#[derive(Clone)]
+enum Expectation {
+    /// `raises = "..."`, the name of the Python exception
+    Raises(String),
+    /// `should_panic`, with the expected part of the panic message
+    Panics(Option<String>),
+}
+
 struct FinalConfig {
     flavor: RuntimeFlavor,
     worker_threads: Option<usize>,
+    start_paused: bool,
+    mock_clock: bool,
+    timeout: Option<(u64, String)>,
+    builder: Option<syn::Path>,
+    expectation: Option<Expectation>,
+    event_loop_policy: Option<String>,
 }
 
 struct Configuration {
@@ -32,6 +47,14 @@ struct Configuration {
     default_flavor: RuntimeFlavor,
     flavor: Option<RuntimeFlavor>,
     worker_threads: Option<(usize, Span)>,
+    start_paused: Option<(bool, Span)>,
+    mock_clock: Option<Span>,
+    timeout: Option<(u64, String)>,
+    builder: Option<(syn::Path, Span)>,
+    expectation: Option<Expectation>,
+    event_loop_policy: Option<String>,
+    serial: bool,
+    shared_loop: bool,
 }
 
 impl Configuration {
@@ -44,9 +67,22 @@ impl Configuration {
             },
             flavor: None,
             worker_threads: None,
+            start_paused: None,
+            mock_clock: None,
+            timeout: None,
+            builder: None,
+            expectation: None,
+            event_loop_policy: None,
+            serial: false,
+            shared_loop: false,
         }
     }
 
+    /// Whether the runtime was configured explicitly, rather than left to the defaults
+    fn is_configured(&self) -> bool {
+        self.flavor.is_some() || self.worker_threads.is_some() || self.start_paused.is_some()
+    }
+
     fn set_flavor(&mut self, runtime: syn::Lit, span: Span) -> Result<(), syn::Error> {
         if self.flavor.is_some() {
             return Err(syn::Error::new(span, "`flavor` set multiple times."));
@@ -79,9 +115,179 @@ impl Configuration {
         Ok(())
     }
 
+    fn set_start_paused(&mut self, start_paused: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        if self.start_paused.is_some() {
+            return Err(syn::Error::new(span, "`start_paused` set multiple times."));
+        }
+
+        let start_paused = parse_bool(start_paused, span, "start_paused")?;
+        self.start_paused = Some((start_paused, span));
+        Ok(())
+    }
+
+    fn set_mock_clock(&mut self, span: Span) -> Result<(), syn::Error> {
+        if self.mock_clock.is_some() {
+            return Err(syn::Error::new(span, "`mock_clock` set multiple times."));
+        }
+
+        self.mock_clock = Some(span);
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        if self.timeout.is_some() {
+            return Err(syn::Error::new(span, "`timeout` set multiple times."));
+        }
+
+        let timeout_str = parse_string(timeout, span, "timeout")?;
+        let millis = parse_duration(&timeout_str).map_err(|err| syn::Error::new(span, err))?;
+        if millis == 0 {
+            return Err(syn::Error::new(span, "`timeout` may not be 0."));
+        }
+        self.timeout = Some((millis, timeout_str));
+        Ok(())
+    }
+
+    fn set_expectation(&mut self, expectation: Expectation, span: Span) -> Result<(), syn::Error> {
+        if self.expectation.is_some() {
+            return Err(syn::Error::new(
+                span,
+                "`raises` and `should_panic` may only be set once, and not together.",
+            ));
+        }
+
+        self.expectation = Some(expectation);
+        Ok(())
+    }
+
+    fn set_raises(&mut self, raises: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        let exception = parse_string(raises, span, "raises")?;
+        let is_path = exception.split('.').all(|part| {
+            matches!(part.chars().next(), Some(c) if c.is_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+        });
+        if !is_path {
+            let msg = format!(
+                "`{}` is not the name of an exception, e.g. `ValueError` or `asyncio.CancelledError`.",
+                exception
+            );
+            return Err(syn::Error::new(span, msg));
+        }
+
+        self.set_expectation(Expectation::Raises(exception), span)
+    }
+
+    fn set_should_panic(
+        &mut self,
+        expected: Option<syn::Lit>,
+        span: Span,
+    ) -> Result<(), syn::Error> {
+        let expected = match expected {
+            Some(expected) => Some(parse_string(expected, span, "should_panic")?),
+            None => None,
+        };
+
+        self.set_expectation(Expectation::Panics(expected), span)
+    }
+
+    fn set_event_loop_policy(&mut self, policy: syn::Lit, span: Span) -> Result<(), syn::Error> {
+        if self.event_loop_policy.is_some() {
+            return Err(syn::Error::new(
+                span,
+                "`event_loop_policy` set multiple times.",
+            ));
+        }
+
+        self.event_loop_policy = Some(crate::policy::parse_policy(&policy, span)?);
+        Ok(())
+    }
+
+    fn set_serial(&mut self, span: Span) -> Result<(), syn::Error> {
+        if self.serial {
+            return Err(syn::Error::new(span, "`serial` set multiple times."));
+        }
+
+        self.serial = true;
+        Ok(())
+    }
+
+    fn set_shared_loop(&mut self, span: Span) -> Result<(), syn::Error> {
+        if self.shared_loop {
+            return Err(syn::Error::new(span, "`shared_loop` set multiple times."));
+        }
+
+        self.shared_loop = true;
+        Ok(())
+    }
+
+    fn set_builder(&mut self, builder: &syn::Expr, span: Span) -> Result<(), syn::Error> {
+        if self.builder.is_some() {
+            return Err(syn::Error::new(span, "`builder` set multiple times."));
+        }
+
+        let path = match builder {
+            syn::Expr::Path(path) => path.path.clone(),
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(s),
+                ..
+            }) => s.parse::<syn::Path>().map_err(|e| {
+                syn::Error::new(span, format!("Failed to parse builder as a path: {}", e))
+            })?,
+            _ => {
+                return Err(syn::Error::new(
+                    span,
+                    "Failed to parse builder as a path, e.g. \"my_crate::build_runtime\".",
+                ))
+            }
+        };
+        self.builder = Some((path, span));
+        Ok(())
+    }
+
     fn build(&self) -> Result<FinalConfig, syn::Error> {
         let flavor = self.flavor.unwrap_or(self.default_flavor);
         use RuntimeFlavor::*;
+
+        if let Some((path, span)) = &self.builder {
+            if self.flavor.is_some() || self.worker_threads.is_some() {
+                return Err(syn::Error::new(
+                    *span,
+                    "The `builder` option can't be combined with `flavor` or `worker_threads`, configure the runtime in the builder instead.",
+                ));
+            }
+
+            return Ok(FinalConfig {
+                flavor,
+                worker_threads: None,
+                start_paused: false,
+                mock_clock: false,
+                timeout: None,
+                builder: Some(path.clone()),
+                expectation: None,
+                event_loop_policy: self.event_loop_policy.clone(),
+            });
+        }
+
+        let start_paused = match (flavor, self.start_paused) {
+            (Threaded, Some((true, start_paused_span))) => {
+                return Err(syn::Error::new(
+                    start_paused_span,
+                    "The `start_paused` option requires the `current_thread` runtime flavor.",
+                ))
+            }
+            (_, start_paused) => matches!(start_paused, Some((true, _))),
+        };
+
+        let mock_clock = match self.mock_clock {
+            Some(mock_clock_span) if !start_paused => {
+                return Err(syn::Error::new(
+                    mock_clock_span,
+                    "The `mock_clock` option requires `start_paused = true`.",
+                ))
+            }
+            mock_clock => mock_clock.is_some(),
+        };
+
         match (flavor, self.worker_threads) {
             (CurrentThread, Some((_, worker_threads_span))) => Err(syn::Error::new(
                 worker_threads_span,
@@ -90,10 +296,22 @@ impl Configuration {
             (CurrentThread, None) => Ok(FinalConfig {
                 flavor,
                 worker_threads: None,
+                start_paused,
+                mock_clock,
+                timeout: self.timeout.clone(),
+                builder: None,
+                expectation: self.expectation.clone(),
+                event_loop_policy: self.event_loop_policy.clone(),
             }),
             (Threaded, worker_threads) if self.rt_multi_thread_available => Ok(FinalConfig {
                 flavor,
                 worker_threads: worker_threads.map(|(val, _span)| val),
+                start_paused,
+                mock_clock,
+                timeout: self.timeout.clone(),
+                builder: None,
+                expectation: self.expectation.clone(),
+                event_loop_policy: self.event_loop_policy.clone(),
             }),
             (Threaded, _) => {
                 let msg = if self.flavor.is_none() {
@@ -123,6 +341,16 @@ fn parse_int(int: syn::Lit, span: Span, field: &str) -> Result<usize, syn::Error
     }
 }
 
+fn parse_bool(bool: syn::Lit, span: Span, field: &str) -> Result<bool, syn::Error> {
+    match bool {
+        syn::Lit::Bool(b) => Ok(b.value),
+        _ => Err(syn::Error::new(
+            span,
+            format!("Failed to parse {} as bool.", field),
+        )),
+    }
+}
+
 fn parse_string(int: syn::Lit, span: Span, field: &str) -> Result<String, syn::Error> {
     match int {
         syn::Lit::Str(s) => Ok(s.value()),
@@ -134,24 +362,49 @@ fn parse_string(int: syn::Lit, span: Span, field: &str) -> Result<String, syn::E
     }
 }
 
-fn parse_knobs(
-    input: syn::ItemFn,
+/// Parse a duration like `"500ms"`, `"30s"` or `"2m"` into milliseconds
+fn parse_duration(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| format!("Failed to parse `{}` as a duration like \"30s\".", s))?;
+    let factor = match unit.trim() {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        _ => {
+            return Err(format!(
+                "Unknown unit in duration `{}`. The units are `ms`, `s` and `m`.",
+                s
+            ))
+        }
+    };
+
+    value
+        .checked_mul(factor)
+        .ok_or_else(|| format!("The duration `{}` is too long.", s))
+}
+
+fn parse_config(
     args: Vec<syn::Meta>,
     is_test: bool,
     rt_multi_thread: bool,
-) -> Result<TokenStream, syn::Error> {
-    let sig = &input.sig;
-    let ret = &input.sig.output;
-    let body = &input.block;
-    let attrs = &input.attrs;
-    let vis = input.vis;
-
-    if sig.asyncness.is_none() {
-        let msg = "the async keyword is missing from the function declaration";
-        return Err(syn::Error::new_spanned(sig.fn_token, msg));
-    }
-
-    let macro_name = "pyo3_async_runtimes::tokio::main";
+) -> Result<Configuration, syn::Error> {
+    let (macro_name, expected) = if is_test {
+        (
+            "pyo3_async_runtimes::tokio::test",
+            "`flavor`, `worker_threads`, `start_paused`, `mock_clock`, `timeout`, `raises`, \
+             `should_panic`, `event_loop_policy`, `loop`, `serial`, `shared_loop`",
+        )
+    } else {
+        (
+            "pyo3_async_runtimes::tokio::main",
+            "`flavor`, `worker_threads`, `builder`, `event_loop_policy`, `loop`",
+        )
+    };
     let mut config = Configuration::new(is_test, rt_multi_thread);
 
     for arg in args {
@@ -183,12 +436,69 @@ fn parse_knobs(
                             ));
                         }
                     }
+                    "event_loop_policy" | "loop" => {
+                        if let syn::Expr::Lit(expr_lit) = &namevalue.value {
+                            config.set_event_loop_policy(expr_lit.lit.clone(), namevalue.span())?;
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                &namevalue.value,
+                                "Expected a literal value",
+                            ));
+                        }
+                    }
+                    "builder" if !is_test => {
+                        config.set_builder(&namevalue.value, namevalue.span())?;
+                    }
+                    "start_paused" if is_test => {
+                        if let syn::Expr::Lit(expr_lit) = &namevalue.value {
+                            config.set_start_paused(expr_lit.lit.clone(), namevalue.span())?;
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                &namevalue.value,
+                                "Expected a literal value",
+                            ));
+                        }
+                    }
+                    "timeout" if is_test => {
+                        if let syn::Expr::Lit(expr_lit) = &namevalue.value {
+                            config.set_timeout(expr_lit.lit.clone(), namevalue.span())?;
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                &namevalue.value,
+                                "Expected a literal value",
+                            ));
+                        }
+                    }
+                    "raises" if is_test => {
+                        if let syn::Expr::Lit(expr_lit) = &namevalue.value {
+                            config.set_raises(expr_lit.lit.clone(), namevalue.span())?;
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                &namevalue.value,
+                                "Expected a literal value",
+                            ));
+                        }
+                    }
+                    "should_panic" if is_test => {
+                        if let syn::Expr::Lit(expr_lit) = &namevalue.value {
+                            config
+                                .set_should_panic(Some(expr_lit.lit.clone()), namevalue.span())?;
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                &namevalue.value,
+                                "Expected a literal value",
+                            ));
+                        }
+                    }
                     "core_threads" => {
                         let msg = "Attribute `core_threads` is renamed to `worker_threads`";
                         return Err(syn::Error::new_spanned(namevalue, msg));
                     }
                     name => {
-                        let msg = format!("Unknown attribute {} is specified; expected one of: `flavor`, `worker_threads`", name);
+                        let msg = format!(
+                            "Unknown attribute {} is specified; expected one of: {}",
+                            name, expected
+                        );
                         return Err(syn::Error::new_spanned(namevalue, msg));
                     }
                 }
@@ -200,6 +510,22 @@ fn parse_knobs(
                     return Err(syn::Error::new_spanned(path, msg));
                 }
                 let name = ident.unwrap().to_string().to_lowercase();
+                if is_test && name == "should_panic" {
+                    config.set_should_panic(None, path.span())?;
+                    continue;
+                }
+                if is_test && name == "serial" {
+                    config.set_serial(path.span())?;
+                    continue;
+                }
+                if is_test && name == "shared_loop" {
+                    config.set_shared_loop(path.span())?;
+                    continue;
+                }
+                if is_test && name == "mock_clock" {
+                    config.set_mock_clock(path.span())?;
+                    continue;
+                }
                 let msg = match name.as_str() {
                     "threaded_scheduler" | "multi_thread" => {
                         format!(
@@ -213,11 +539,15 @@ fn parse_knobs(
                             macro_name
                         )
                     }
-                    "flavor" | "worker_threads" => {
+                    "flavor" | "worker_threads" | "start_paused" | "timeout" | "builder"
+                    | "raises" | "event_loop_policy" | "loop" => {
                         format!("The `{}` attribute requires an argument.", name)
                     }
                     name => {
-                        format!("Unknown attribute {} is specified; expected one of: `flavor`, `worker_threads`", name)
+                        format!(
+                            "Unknown attribute {} is specified; expected one of: {}",
+                            name, expected
+                        )
                     }
                 };
                 return Err(syn::Error::new_spanned(path, msg));
@@ -231,7 +561,17 @@ fn parse_knobs(
         }
     }
 
-    let config = config.build()?;
+    Ok(config)
+}
+
+/// The builder of a runtime with the flavor, worker threads and clock of `config`, or the one
+/// returned by the user's builder function
+fn runtime_builder(config: &FinalConfig) -> proc_macro2::TokenStream {
+    if let Some(path) = &config.builder {
+        return quote! {
+            let builder: pyo3_async_runtimes::tokio::re_exports::runtime::Builder = #path();
+        };
+    }
 
     let builder = match config.flavor {
         RuntimeFlavor::CurrentThread => quote! {
@@ -251,8 +591,47 @@ fn parse_knobs(
             #builder_init;
         };
     }
+    if config.start_paused {
+        builder_init = quote! {
+            builder.start_paused(true);
+            #builder_init;
+        };
+    }
+
+    quote! {
+        let mut builder = #builder;
+        #builder_init;
+    }
+}
+
+fn parse_knobs(
+    input: syn::ItemFn,
+    args: Vec<syn::Meta>,
+    is_test: bool,
+    rt_multi_thread: bool,
+) -> Result<TokenStream, syn::Error> {
+    let sig = &input.sig;
+    let ret = &input.sig.output;
+    let body = &input.block;
+    let attrs = &input.attrs;
+    let vis = input.vis;
+
+    if sig.asyncness.is_none() {
+        let msg = "the async keyword is missing from the function declaration";
+        return Err(syn::Error::new_spanned(sig.fn_token, msg));
+    }
 
+    let config = parse_config(args, is_test, rt_multi_thread)?.build()?;
+    let builder = runtime_builder(&config);
+
+    // the flavor of a runtime from a builder function isn't known, but blocking on a multi-thread
+    // runtime as well doesn't hurt
     let rt_init = match config.flavor {
+        _ if config.builder.is_some() => quote! {
+            std::thread::spawn(|| pyo3_async_runtimes::tokio::get_runtime().block_on(
+                pyo3_async_runtimes::tokio::re_exports::pending::<()>()
+            ));
+        },
         RuntimeFlavor::CurrentThread => quote! {
             std::thread::spawn(|| pyo3_async_runtimes::tokio::get_runtime().block_on(
                 pyo3_async_runtimes::tokio::re_exports::pending::<()>()
@@ -261,29 +640,27 @@ fn parse_knobs(
         _ => quote! {},
     };
 
+    let policy_init = crate::policy::main_init(config.event_loop_policy.as_deref());
+    let run_main = crate::run_main(quote! { pyo3_async_runtimes::tokio::run }, ret);
+
     let result = quote! {
         #(#attrs)*
-        #vis fn main() {
+        #vis fn main() -> std::process::ExitCode {
             async fn main() #ret {
                 #body
             }
 
             pyo3::prepare_freethreaded_python();
 
-            let mut builder = #builder;
-            #builder_init;
+            #policy_init
+
+            #builder
 
             pyo3_async_runtimes::tokio::init(builder);
 
             #rt_init
 
-            pyo3::Python::with_gil(|py| {
-                pyo3_async_runtimes::tokio::run(py, main())
-                    .map_err(|e| {
-                        e.print_and_set_sys_last_vars(py);
-                    })
-                    .unwrap();
-            });
+            #run_main
         }
     };
 
@@ -293,7 +670,7 @@ fn parse_knobs(
 #[cfg(not(test))] // Work around for rust-lang/rust#62127
 pub(crate) fn main(args: TokenStream, item: TokenStream, rt_multi_thread: bool) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemFn);
-    let args = syn::parse_macro_input!(args with syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated);
+    let args = syn::parse_macro_input!(args with crate::parse_args);
     let args: Vec<syn::Meta> = args.into_iter().collect();
 
     if input.sig.ident == "main" && !input.sig.inputs.is_empty() {
@@ -305,3 +682,202 @@ pub(crate) fn main(args: TokenStream, item: TokenStream, rt_multi_thread: bool)
 
     parse_knobs(input, args, false, rt_multi_thread).unwrap_or_else(|e| e.to_compile_error().into())
 }
+
+fn parse_test(
+    input: syn::ItemFn,
+    args: Vec<syn::Meta>,
+    rt_multi_thread: bool,
+) -> Result<TokenStream, syn::Error> {
+    let config = parse_config(args, true, rt_multi_thread)?;
+    let custom_runtime = config.is_configured();
+    let (serial, shared_loop) = (config.serial, config.shared_loop);
+    let config = config.build()?;
+
+    let sig = &input.sig;
+    let name = &input.sig.ident;
+    let body = &input.block;
+    let vis = &input.vis;
+    crate::policy::check_test(sig, config.event_loop_policy.as_deref())?;
+    if config.mock_clock && sig.asyncness.is_none() {
+        let msg = "The `mock_clock` option requires an async test, blocking tests don't run on an \
+                   event loop.";
+        return Err(syn::Error::new_spanned(sig.fn_token, msg));
+    }
+
+    // the parameters are set up by the task, in the task locals of the test
+    let fixtures = crate::fixtures::parse_fixtures(sig, quote! { pyo3_async_runtimes::tokio })?;
+    let setup = &fixtures.setup;
+    let args = &fixtures.args;
+
+    // runs `call` on the blocking pool and turns a panic into an error
+    let join = |call: proc_macro2::TokenStream| {
+        quote! {
+            match pyo3_async_runtimes::tokio::get_runtime().spawn_blocking(move || #call).await {
+                Ok(result) => result,
+                Err(e) => {
+                    assert!(e.is_panic());
+                    let panic = e.into_panic();
+                    let panic_message = if let Some(s) = panic.downcast_ref::<&str>() {
+                        s.to_string()
+                    } else if let Some(s) = panic.downcast_ref::<String>() {
+                        s.clone()
+                    } else {
+                        "unknown error".into()
+                    };
+                    Err(pyo3_async_runtimes::err::RustPanic::new_err(format!("rust future panicked: {}", panic_message)))
+                }
+            }
+        }
+    };
+
+    // the runtime the test runs on, if it was configured explicitly
+    let build_runtime = if custom_runtime {
+        let builder = runtime_builder(&config);
+        quote! {
+            #builder
+            let rt = builder.build().expect("failed to build the tokio runtime of the test");
+        }
+    } else {
+        quote! {}
+    };
+
+    // the event loop of the test follows the paused clock, see `mock_clock`
+    let test_fut = if config.mock_clock {
+        quote! {
+            pyo3_async_runtimes::tokio::with_mock_clock(#name(#(#args),*))
+        }
+    } else {
+        quote! { #name(#(#args),*) }
+    };
+
+    let task = if sig.asyncness.is_none() {
+        // blocking tests run within the context of their runtime
+        let call = if custom_runtime {
+            quote! {{
+                #build_runtime
+                let _guard = rt.enter();
+                #name(#(#args),*)
+            }}
+        } else {
+            quote! { #name(#(#args),*) }
+        };
+        let join = join(call);
+
+        quote! {
+            Box::pin(async move {
+                #setup
+                #join
+            })
+        }
+    } else if custom_runtime {
+        // the runtime is blocked on by a thread of the blocking pool, so that the harness keeps
+        // running in the meantime
+        let join = join(quote! {{
+            #build_runtime
+            rt.block_on(pyo3_async_runtimes::tokio::scope(locals, #test_fut))
+        }});
+
+        quote! {
+            Box::pin(async move {
+                let locals = pyo3::Python::with_gil(pyo3_async_runtimes::tokio::get_current_locals)?;
+                #setup
+                #join
+            })
+        }
+    } else if fixtures.is_empty() {
+        quote! {
+            Box::pin(#name())
+        }
+    } else {
+        quote! {
+            Box::pin(async move {
+                #setup
+                #name(#(#args),*).await
+            })
+        }
+    };
+
+    let task = crate::policy::test_task(
+        config.event_loop_policy.as_deref(),
+        shared_loop,
+        quote! { pyo3_async_runtimes::tokio::scope },
+        task,
+    );
+
+    // a test that should fail succeeds if it fails the expected way, and the timeout still applies
+    let task = match &config.expectation {
+        Some(expectation) => {
+            let expect = match expectation {
+                Expectation::Raises(exception) => quote! {
+                    pyo3_async_runtimes::testing::expect_raises(task, #exception)
+                },
+                Expectation::Panics(Some(expected)) => quote! {
+                    pyo3_async_runtimes::testing::expect_panic(task, Some(#expected))
+                },
+                Expectation::Panics(None) => quote! {
+                    pyo3_async_runtimes::testing::expect_panic(task, None)
+                },
+            };
+            quote! {
+                let task: std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> = {
+                    #task
+                };
+
+                Box::pin(#expect)
+            }
+        }
+        None => task,
+    };
+
+    let task = match &config.timeout {
+        Some((millis, timeout_str)) => {
+            let msg = format!("test timed out after {}", timeout_str);
+            quote! {
+                let task: std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> = {
+                    #task
+                };
+
+                Box::pin(async move {
+                    match pyo3_async_runtimes::tokio::re_exports::timeout(
+                        std::time::Duration::from_millis(#millis),
+                        task,
+                    ).await {
+                        Ok(result) => result,
+                        Err(_) => Err(pyo3::exceptions::PyTimeoutError::new_err(#msg)),
+                    }
+                })
+            }
+        }
+        None => task,
+    };
+
+    let result = quote! {
+        #vis fn #name() -> std::pin::Pin<Box<dyn std::future::Future<Output = pyo3::PyResult<()>> + Send>> {
+            #sig {
+                #body
+            }
+
+            #task
+        }
+
+        pyo3_async_runtimes::inventory::submit! {
+            pyo3_async_runtimes::testing::Test {
+                name: concat!(std::module_path!(), "::", stringify!(#name)),
+                test_fn: &#name,
+                serial: #serial,
+                shared_loop: #shared_loop,
+            }
+        }
+    };
+
+    Ok(result.into())
+}
+
+#[cfg(not(test))] // Work around for rust-lang/rust#62127
+pub(crate) fn test(args: TokenStream, item: TokenStream, rt_multi_thread: bool) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::ItemFn);
+    let args = syn::parse_macro_input!(args with crate::parse_args);
+    let args: Vec<syn::Meta> = args.into_iter().collect();
+
+    parse_test(input, args, rt_multi_thread).unwrap_or_else(|e| e.to_compile_error().into())
+}
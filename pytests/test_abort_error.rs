@@ -0,0 +1,90 @@
+use pyo3::{exceptions::PyValueError, prelude::*};
+use pyo3_async_runtimes::err::{self, AbortReason, RustFutureAborted, RustPanic};
+
+async fn panicking_future() -> PyErr {
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            pyo3_async_runtimes::tokio::future_into_py::<_, ()>(py, async {
+                std::panic::panic_any(PyValueError::new_err("this panic was intentional!"))
+            })?,
+        )
+    })
+    .unwrap();
+
+    fut.await.expect_err("coroutine should panic")
+}
+
+async fn test_default_abort_error() -> PyResult<()> {
+    let e = panicking_future().await;
+
+    Python::with_gil(|py| {
+        assert!(e.is_instance_of::<RustPanic>(py));
+        assert!(e.is_instance_of::<RustFutureAborted>(py));
+    });
+
+    Ok(())
+}
+
+async fn test_panic_attributes() -> PyResult<()> {
+    #[cfg(feature = "panic-backtrace")]
+    err::capture_panic_backtraces();
+
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            pyo3_async_runtimes::tokio::future_into_py::<_, ()>(py, async {
+                panic!("this panic was intentional!")
+            })?,
+        )
+    })?;
+    let e = fut.await.expect_err("coroutine should panic");
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(e.is_instance_of::<RustPanic>(py));
+
+        let value = e.value_bound(py);
+        assert_eq!(
+            value.getattr("message")?.extract::<String>()?,
+            "this panic was intentional!"
+        );
+        let backtrace = value.getattr("backtrace")?.extract::<Option<String>>()?;
+        assert_eq!(backtrace.is_some(), cfg!(feature = "panic-backtrace"));
+
+        Ok(())
+    })
+}
+
+async fn test_custom_abort_error() -> PyResult<()> {
+    err::set_abort_error(|_py, reason| match reason {
+        AbortReason::Panic(payload) => *payload.downcast::<PyErr>().unwrap(),
+        _ => unreachable!("the future panicked"),
+    });
+
+    let e = panicking_future().await;
+
+    Python::with_gil(|py| {
+        assert!(e.is_instance_of::<PyValueError>(py));
+        assert_eq!(e.value_bound(py).to_string(), "this panic was intentional!");
+    });
+
+    Ok(())
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    // the abort error is global, so the tests run one after the other
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::run(py, async {
+            test_default_abort_error().await?;
+            println!("test test_abort_error::test_default_abort_error ... ok");
+
+            test_panic_attributes().await?;
+            println!("test test_abort_error::test_panic_attributes ... ok");
+
+            test_custom_abort_error().await?;
+            println!("test test_abort_error::test_custom_abort_error ... ok");
+
+            Ok(())
+        })
+    })
+}
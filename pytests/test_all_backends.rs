@@ -0,0 +1,59 @@
+use std::{any::type_name, thread, time::Duration};
+
+use pyo3::prelude::*;
+use pyo3_async_runtimes::generic::{self, ContextExt};
+
+#[pyo3_async_runtimes::test_all_backends]
+async fn test_into_future<R: ContextExt>() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        generic::into_future::<R>(py.import_bound("asyncio")?.call_method1("sleep", (0.1,))?)
+    })?;
+    fut.await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::test_all_backends]
+async fn test_round_trip<R: ContextExt>() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let awaitable = generic::future_into_py::<R, _, _>(py, async {
+            sleep(Duration::from_millis(100)).await;
+            Ok(42)
+        })?;
+        generic::into_future::<R>(awaitable)
+    })?;
+    let value = fut.await?;
+    assert_eq!(Python::with_gil(|py| value.extract::<i32>(py))?, 42);
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::test_all_backends]
+async fn test_backend_runtime<R: ContextExt>() -> PyResult<()> {
+    // every instance runs on the runtime it was instantiated with
+    let on_tokio = tokio::runtime::Handle::try_current().is_ok();
+    assert_eq!(on_tokio, type_name::<R>().ends_with("TokioRuntime"));
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::test_all_backends]
+async fn test_not_generic() -> PyResult<()> {
+    assert_ne!(thread::current().name(), Some("main"));
+    Ok(())
+}
+
+/// A sleep that doesn't depend on the backend
+async fn sleep(duration: Duration) {
+    let (tx, rx) = futures::channel::oneshot::channel();
+    thread::spawn(move || {
+        thread::sleep(duration);
+        let _ = tx.send(());
+    });
+    let _ = rx.await;
+}
+
+#[pyo3_async_runtimes::tokio::main]
+async fn main() -> pyo3::PyResult<()> {
+    pyo3_async_runtimes::testing::main().await
+}
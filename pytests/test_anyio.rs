@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use pyo3::{prelude::*, wrap_pyfunction};
+use pyo3_async_runtimes::tokio::TokioRuntime;
+
+const ANYIO_TEST_MOD: &str = r#"
+import anyio
+
+async def call_with_timeout(func, secs):
+    with anyio.fail_after(5):
+        return await func(secs)
+
+def run(func, secs, backend):
+    return anyio.run(call_with_timeout, func, secs, backend=backend)
+"#;
+
+/// Sleeps on tokio
+#[pyfunction]
+fn sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let secs = secs.extract()?;
+
+    pyo3_async_runtimes::anyio::future_into_py::<TokioRuntime, _, _>(py, async move {
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+        Ok(secs)
+    })
+}
+
+/// Sleeps on the async library of the caller
+#[pyfunction]
+fn anyio_sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let secs = secs.unbind();
+
+    pyo3_async_runtimes::anyio::future_into_py::<TokioRuntime, _, _>(py, async move {
+        let fut = Python::with_gil(|py| {
+            pyo3_async_runtimes::anyio::into_future::<TokioRuntime>(
+                py.import_bound("anyio")?
+                    .call_method1("sleep", (secs.clone_ref(py),))?,
+            )
+        })?;
+
+        fut.await?;
+        Ok(secs)
+    })
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let rust_mod = PyModule::new_bound(py, "rust_sleeper")?;
+        rust_mod.add_wrapped(wrap_pyfunction!(sleep))?;
+        rust_mod.add_wrapped(wrap_pyfunction!(anyio_sleep))?;
+
+        let test_mod =
+            PyModule::from_code_bound(py, ANYIO_TEST_MOD, "test_anyio_mod.py", "test_anyio_mod")?;
+
+        for backend in ["asyncio", "trio"] {
+            for func in ["sleep", "anyio_sleep"] {
+                let secs: u64 = test_mod
+                    .call_method1("run", (rust_mod.getattr(func)?, 1, backend))?
+                    .extract()?;
+                assert_eq!(secs, 1);
+
+                println!("test test_anyio::test_{}_{} ... ok", func, backend);
+            }
+        }
+
+        Ok(())
+    })
+}
@@ -133,6 +133,46 @@ async fn test_local_future_into_py() -> PyResult<()> {
     Ok(())
 }
 
+#[pyo3_async_runtimes::async_std::test]
+async fn test_scope_local() -> PyResult<()> {
+    let (outer_loop, event_loop) = Python::with_gil(|py| -> PyResult<_> {
+        Ok((
+            pyo3_async_runtimes::async_std::get_current_loop(py)?.unbind(),
+            py.import_bound("asyncio")?
+                .call_method0("new_event_loop")?
+                .unbind(),
+        ))
+    })?;
+
+    let locals = Python::with_gil(|py| TaskLocals::new(event_loop.bind(py).clone()));
+
+    // the test itself has to be Send, so the future that isn't runs in a local task
+    let scoped_loop = task::spawn_local(async move {
+        let non_send = Rc::new(());
+
+        pyo3_async_runtimes::async_std::scope_local(locals, async move {
+            task::sleep(Duration::from_millis(10)).await;
+            drop(non_send);
+
+            Python::with_gil(|py| {
+                pyo3_async_runtimes::async_std::get_current_loop(py).map(Bound::unbind)
+            })
+        })
+        .await
+    })
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(scoped_loop.bind(py).is(event_loop.bind(py)));
+
+        // the locals of the surrounding task are left alone
+        assert!(pyo3_async_runtimes::async_std::get_current_loop(py)?.is(outer_loop.bind(py)));
+
+        event_loop.call_method0(py, "close")?;
+        Ok(())
+    })
+}
+
 #[pyo3_async_runtimes::async_std::test]
 async fn test_cancel() -> PyResult<()> {
     let completed = Arc::new(Mutex::new(false));
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use pyo3::{exceptions::PyKeyboardInterrupt, prelude::*};
+
+fn test_cancel_on_interrupt() -> PyResult<()> {
+    pyo3_async_runtimes::cancel_on_interrupt(true);
+
+    let (interrupted_tx, interrupted_rx) = oneshot::channel();
+    pyo3_async_runtimes::tokio::on_interrupt(async move {
+        interrupted_tx.send(()).unwrap();
+    });
+
+    let (dropped_tx, dropped_rx) = oneshot::channel::<()>();
+    let (py_result_tx, py_result_rx) = oneshot::channel();
+
+    Python::with_gil(|py| {
+        let result = pyo3_async_runtimes::tokio::run(py, async move {
+            Python::with_gil(|py| -> PyResult<()> {
+                // the sender is dropped along with the Rust future
+                pyo3_async_runtimes::tokio::future_into_py(py, async move {
+                    let _dropped_tx = dropped_tx;
+                    futures::future::pending::<PyResult<()>>().await
+                })?;
+
+                let sleep = pyo3_async_runtimes::tokio::into_future(
+                    py.import_bound("asyncio")?.call_method1("sleep", (3600,))?,
+                )?;
+                pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+                    let _ = py_result_tx.send(sleep.await);
+                });
+
+                // simulates a CTRL-C that wasn't delivered to the main thread
+                py.import_bound("_thread")?.call_method0("interrupt_main")?;
+                Ok(())
+            })?;
+
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(())
+        });
+
+        match result {
+            Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => Ok(()),
+            Err(e) => Err(e),
+            Ok(()) => panic!("the loop should have been interrupted"),
+        }
+    })?;
+
+    pyo3_async_runtimes::tokio::get_runtime().block_on(async move {
+        tokio::time::timeout(Duration::from_secs(1), dropped_rx)
+            .await
+            .expect("the Rust future should have been dropped")
+            .unwrap_err();
+
+        let py_result = tokio::time::timeout(Duration::from_secs(1), py_result_rx)
+            .await
+            .expect("the Rust side of the Python task should have been cancelled")
+            .unwrap();
+        Python::with_gil(|py| -> PyResult<()> {
+            let cancelled_error = py.import_bound("asyncio")?.getattr("CancelledError")?;
+            assert!(py_result
+                .unwrap_err()
+                .is_instance_bound(py, &cancelled_error));
+            Ok(())
+        })?;
+
+        tokio::time::timeout(Duration::from_secs(1), interrupted_rx)
+            .await
+            .expect("the shutdown future should have been spawned")
+            .unwrap();
+
+        Ok(())
+    })
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    test_cancel_on_interrupt()?;
+    println!("test test_cancel_on_interrupt::test_cancel_on_interrupt ... ok");
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::{future::Future, pin::Pin, sync::Mutex};
+
+use pyo3::prelude::*;
+use pyo3_async_runtimes::{
+    err::RustFutureAborted,
+    generic::{ContextExt, JoinError, Runtime},
+    TaskLocals,
+};
+use tokio::{runtime, task};
+
+/// The runtime of the running test, which the test drops with the conversions it spawned
+static RUNTIME: Mutex<Option<runtime::Runtime>> = Mutex::new(None);
+
+tokio::task_local! {
+    static TASK_LOCALS: TaskLocals;
+}
+
+struct DroppableRuntime;
+
+struct DroppableJoinError(task::JoinError);
+
+impl JoinError for DroppableJoinError {
+    fn is_panic(&self) -> bool {
+        self.0.is_panic()
+    }
+    fn into_panic(self) -> Box<dyn std::any::Any + Send + 'static> {
+        self.0.into_panic()
+    }
+}
+
+impl Runtime for DroppableRuntime {
+    type JoinError = DroppableJoinError;
+    type JoinHandle = Pin<Box<dyn Future<Output = Result<(), DroppableJoinError>> + Send>>;
+
+    fn spawn<F>(fut: F) -> Self::JoinHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = RUNTIME.lock().unwrap().as_ref().unwrap().spawn(fut);
+        Box::pin(async move { handle.await.map_err(DroppableJoinError) })
+    }
+}
+
+impl ContextExt for DroppableRuntime {
+    fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+    where
+        F: Future<Output = R> + Send + 'static,
+    {
+        Box::pin(TASK_LOCALS.scope(locals, fut))
+    }
+
+    fn get_task_locals() -> Option<TaskLocals> {
+        TASK_LOCALS.try_with(|locals| locals.clone()).ok()
+    }
+}
+
+/// Drop `rt` with a pending conversion while holding the GIL, and await the conversion
+fn drop_pending_conversion(rt: runtime::Runtime) -> PyResult<()> {
+    *RUNTIME.lock().unwrap() = Some(rt);
+
+    Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        let event_loop = asyncio.call_method0("new_event_loop")?;
+        let py_fut =
+            pyo3_async_runtimes::generic::future_into_py_with_locals::<DroppableRuntime, _, ()>(
+                py,
+                TaskLocals::new(event_loop.clone()),
+                futures::future::pending(),
+            )?;
+
+        let rt = RUNTIME.lock().unwrap().take();
+        drop(rt);
+
+        // the loop gets the error without a shutdown, the timeout only keeps a hang from blocking
+        // the test forever
+        let awaited = asyncio.call_method1("wait_for", (py_fut, 5))?;
+        let err = event_loop
+            .call_method1("run_until_complete", (awaited,))
+            .expect_err("the dropped conversion should fail");
+        assert!(err.is_instance_of::<RustFutureAborted>(py), "{err}");
+
+        event_loop.call_method0("close")?;
+        Ok(())
+    })
+}
+
+fn test_drop_on_workers() -> PyResult<()> {
+    // the workers drop the conversion while this thread holds the GIL and waits for them
+    drop_pending_conversion(
+        runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .unwrap(),
+    )
+}
+
+fn test_drop_with_gil() -> PyResult<()> {
+    // the task never ran, and is dropped by this thread, which holds the GIL
+    drop_pending_conversion(
+        runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap(),
+    )
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    test_drop_on_workers()?;
+    println!("test test_dropped_conversion::test_drop_on_workers ... ok");
+    test_drop_with_gil()?;
+    println!("test test_dropped_conversion::test_drop_with_gil ... ok");
+
+    Ok(())
+}
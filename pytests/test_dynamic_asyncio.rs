@@ -0,0 +1,165 @@
+mod common;
+
+use std::{thread, time::Duration};
+
+use futures::{channel::oneshot, future::BoxFuture, FutureExt};
+use pyo3::{prelude::*, wrap_pyfunction};
+use pyo3_async_runtimes::testing::{parse_args, test_harness, Test};
+
+/// Drives every task on its own thread
+struct ThreadRuntime;
+
+impl pyo3_async_runtimes::dynamic::Runtime for ThreadRuntime {
+    fn spawn(&self, fut: BoxFuture<'static, ()>) {
+        thread::spawn(move || futures::executor::block_on(fut));
+    }
+
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        thread::spawn(f);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let (tx, rx) = oneshot::channel();
+        thread::spawn(move || {
+            thread::sleep(duration);
+            let _ = tx.send(());
+        });
+        rx.map(|_| ()).boxed()
+    }
+}
+
+#[pyfunction]
+fn sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let secs = secs.extract()?;
+
+    pyo3_async_runtimes::dynamic::future_into_py(py, async move {
+        pyo3_async_runtimes::dynamic::sleep(Duration::from_secs(secs)).await;
+        Ok(())
+    })
+}
+
+async fn test_future_into_py() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let sleeper_mod = PyModule::new_bound(py, "rust_sleeper")?;
+
+        sleeper_mod.add_wrapped(wrap_pyfunction!(sleep))?;
+
+        let test_mod = PyModule::from_code_bound(
+            py,
+            common::TEST_MOD,
+            "test_future_into_py_mod.py",
+            "test_future_into_py_mod",
+        )?;
+
+        pyo3_async_runtimes::dynamic::into_future(
+            test_mod.call_method1("sleep_for_1s", (sleeper_mod.getattr("sleep")?,))?,
+        )
+    })?;
+
+    fut.await?;
+
+    Ok(())
+}
+
+async fn test_into_future() -> PyResult<()> {
+    common::test_into_future(Python::with_gil(|py| {
+        pyo3_async_runtimes::dynamic::get_current_loop(py)
+            .unwrap()
+            .into()
+    }))
+    .await
+}
+
+async fn test_other_awaitables() -> PyResult<()> {
+    common::test_other_awaitables(Python::with_gil(|py| {
+        pyo3_async_runtimes::dynamic::get_current_loop(py)
+            .unwrap()
+            .into()
+    }))
+    .await
+}
+
+async fn test_spawn_blocking() -> PyResult<()> {
+    pyo3_async_runtimes::dynamic::spawn_blocking(common::test_blocking_sleep).await
+}
+
+async fn test_spawn_blocking_panic() -> PyResult<()> {
+    let result = std::panic::AssertUnwindSafe(pyo3_async_runtimes::dynamic::spawn_blocking(|| {
+        panic!("this panic was intentional!")
+    }))
+    .catch_unwind()
+    .await;
+
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+async fn test_panic() -> PyResult<()> {
+    let fut = Python::with_gil(|py| -> PyResult<_> {
+        pyo3_async_runtimes::dynamic::into_future(pyo3_async_runtimes::dynamic::future_into_py::<
+            _,
+            (),
+        >(py, async {
+            panic!("this panic was intentional!")
+        })?)
+    })?;
+
+    match fut.await {
+        Ok(_) => panic!("coroutine should panic"),
+        Err(e) => Python::with_gil(|py| {
+            if e.is_instance_of::<pyo3_async_runtimes::err::RustPanic>(py) {
+                Ok(())
+            } else {
+                panic!("expected RustPanic err")
+            }
+        }),
+    }
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    pyo3_async_runtimes::dynamic::register(Box::new(ThreadRuntime)).unwrap();
+
+    let tests = vec![
+        Test {
+            name: "test_dynamic_asyncio::test_future_into_py",
+            test_fn: &|| Box::pin(test_future_into_py()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_dynamic_asyncio::test_into_future",
+            test_fn: &|| Box::pin(test_into_future()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_dynamic_asyncio::test_other_awaitables",
+            test_fn: &|| Box::pin(test_other_awaitables()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_dynamic_asyncio::test_spawn_blocking",
+            test_fn: &|| Box::pin(test_spawn_blocking()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_dynamic_asyncio::test_spawn_blocking_panic",
+            test_fn: &|| Box::pin(test_spawn_blocking_panic()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_dynamic_asyncio::test_panic",
+            test_fn: &|| Box::pin(test_panic()),
+            serial: false,
+            shared_loop: false,
+        },
+    ];
+
+    Python::with_gil(|py| pyo3_async_runtimes::dynamic::run(py, test_harness(tests, parse_args())))
+}
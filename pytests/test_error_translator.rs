@@ -0,0 +1,281 @@
+use std::{fmt, io, time::Duration};
+
+use pyo3::{
+    exceptions::{PyFileNotFoundError, PyKeyError, PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use pyo3_async_runtimes::err::{self, TranslateErr};
+
+#[derive(Debug)]
+struct NotFound(&'static str);
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no such key: {}", self.0)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+async fn lookup(key: &'static str) -> Result<u32, NotFound> {
+    Err(NotFound(key))
+}
+
+/// Await `lookup` through a conversion, so that its error reaches Rust as a Python exception
+async fn convert_lookup() -> PyErr {
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::tokio::future_into_py(
+            py,
+            async { lookup("answer").await.translate_err() },
+        )?)
+    })
+    .unwrap();
+
+    fut.await.expect_err("the lookup should fail")
+}
+
+async fn test_default_translation() -> PyResult<()> {
+    let e = convert_lookup().await;
+    let elapsed = pyo3_async_runtimes::tokio::timeout(
+        Duration::from_millis(10),
+        futures::future::pending::<PyResult<()>>(),
+    )
+    .await
+    .unwrap_err();
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(e.is_instance_of::<PyRuntimeError>(py));
+        assert_eq!(e.value_bound(py).to_string(), "no such key: answer");
+
+        let timeout_error = py.import_bound("asyncio")?.getattr("TimeoutError")?;
+        assert!(elapsed.value_bound(py).is_instance(&timeout_error)?);
+        Ok(())
+    })
+}
+
+async fn test_error_mappings() -> PyResult<()> {
+    let io_err = err::translate_error(io::Error::new(io::ErrorKind::NotFound, "missing"));
+    let elapsed = err::translate_error(
+        tokio::time::timeout(Duration::from_millis(10), futures::future::pending::<()>())
+            .await
+            .unwrap_err(),
+    );
+
+    let cancelled = tokio::spawn(futures::future::pending::<()>());
+    cancelled.abort();
+    let cancelled = err::translate_error(cancelled.await.unwrap_err());
+
+    let panicked = err::translate_error(
+        tokio::spawn(async { panic!("this panic was intentional!") })
+            .await
+            .unwrap_err(),
+    );
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let asyncio = py.import_bound("asyncio")?;
+        let is_mapped =
+            |e: &PyErr, exception: &Bound<PyAny>| e.value_bound(py).is_instance(exception).unwrap();
+
+        assert_eq!(
+            io_err.is_instance_of::<PyFileNotFoundError>(py),
+            cfg!(feature = "error-mappings")
+        );
+        assert_eq!(
+            is_mapped(&elapsed, &asyncio.getattr("TimeoutError")?),
+            cfg!(feature = "error-mappings")
+        );
+        assert_eq!(
+            is_mapped(&cancelled, &asyncio.getattr("CancelledError")?),
+            cfg!(feature = "error-mappings")
+        );
+        assert_eq!(
+            panicked.is_instance_of::<err::RustPanic>(py),
+            cfg!(feature = "error-mappings")
+        );
+        Ok(())
+    })
+}
+
+const RAISE_CODE: &str = r#"
+async def inner():
+    raise ValueError("invalid value")
+
+async def outer(fut):
+    try:
+        await fut
+    except RuntimeError as e:
+        return e
+"#;
+
+/// A Rust error around the exception of a Python coroutine
+#[derive(Debug)]
+struct Wrapped(PyErr);
+
+impl fmt::Display for Wrapped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the coroutine failed")
+    }
+}
+
+impl std::error::Error for Wrapped {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+async fn test_python_cause() -> PyResult<()> {
+    let (module, fut) = Python::with_gil(|py| -> PyResult<_> {
+        let module = PyModule::from_code_bound(py, RAISE_CODE, "raise_code.py", "raise_code")?;
+        let fut = pyo3_async_runtimes::tokio::into_future(module.call_method0("inner")?)?;
+        Ok((module.unbind(), fut))
+    })?;
+
+    // the exception is raised again as is
+    let e = err::translate_error(fut.await.unwrap_err());
+    Python::with_gil(|py| {
+        assert!(e.is_instance_of::<PyValueError>(py));
+        assert!(e.cause(py).is_none());
+    });
+
+    let outer = Python::with_gil(|py| -> PyResult<_> {
+        let fut = pyo3_async_runtimes::tokio::into_future(
+            module.call_method0(py, "inner")?.into_bound(py),
+        )?;
+        let converted = pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            fut.await.map_err(Wrapped).translate_err()
+        })?;
+        pyo3_async_runtimes::tokio::into_future(
+            module
+                .call_method1(py, "outer", (converted,))?
+                .into_bound(py),
+        )
+    })?;
+    let e = outer.await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let e = e.bind(py);
+        assert_eq!(e.str()?.to_string(), "the coroutine failed");
+
+        let cause = e.getattr("__cause__")?;
+        assert!(cause.is_instance_of::<PyValueError>());
+
+        // the frames of the coroutine are still in the traceback of the cause
+        let formatted = py
+            .import_bound("traceback")?
+            .call_method1("format_exception", (e,))?
+            .extract::<Vec<String>>()?
+            .concat();
+        assert!(formatted.contains("in inner"), "{}", formatted);
+        assert!(formatted.contains("The above exception was the direct cause"));
+        Ok(())
+    })
+}
+
+async fn test_custom_translation() -> PyResult<()> {
+    err::set_error_translator(|_py, e| {
+        if let Some(NotFound(key)) = e.downcast_ref::<NotFound>() {
+            Some(PyKeyError::new_err(*key))
+        } else if e.is::<tokio::time::error::Elapsed>() {
+            Some(PyValueError::new_err("took too long"))
+        } else {
+            None
+        }
+    });
+
+    let e = convert_lookup().await;
+    let io_err = err::translate_error(io::Error::new(io::ErrorKind::NotFound, "missing"));
+    let elapsed = pyo3_async_runtimes::tokio::timeout(
+        Duration::from_millis(10),
+        futures::future::pending::<PyResult<()>>(),
+    )
+    .await
+    .unwrap_err();
+
+    Python::with_gil(|py| {
+        assert!(e.is_instance_of::<PyKeyError>(py));
+
+        // the errors the translator leaves alone keep their default exceptions
+        assert_eq!(
+            io_err.is_instance_of::<PyFileNotFoundError>(py),
+            cfg!(feature = "error-mappings")
+        );
+
+        assert!(elapsed.is_instance_of::<PyValueError>(py));
+        assert_eq!(elapsed.value_bound(py).to_string(), "took too long");
+    });
+
+    Ok(())
+}
+
+/// Await a conversion of `fut` and get its exception
+#[cfg(feature = "anyhow")]
+async fn convert_anyhow<F>(fut: F) -> PyErr
+where
+    F: std::future::Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::tokio::anyhow_future_into_py(
+            py, fut,
+        )?)
+    })
+    .unwrap();
+
+    fut.await.expect_err("the future should fail")
+}
+
+#[cfg(feature = "anyhow")]
+async fn test_anyhow() -> PyResult<()> {
+    let not_found = convert_anyhow(async { Err(NotFound("answer").into()) }).await;
+    let with_context = convert_anyhow(async {
+        Err(anyhow::Error::new(NotFound("answer")).context("looking up the answer"))
+    })
+    .await;
+    let message = convert_anyhow(async { Err(anyhow::anyhow!("something went wrong")) }).await;
+    let python = convert_anyhow(async {
+        Python::with_gil(|py| py.import_bound("no_such_module").map(drop))?;
+        Ok(())
+    })
+    .await;
+
+    Python::with_gil(|py| {
+        // the translator gets the error inside of the `anyhow::Error`
+        assert!(not_found.is_instance_of::<PyKeyError>(py));
+        assert!(with_context.is_instance_of::<PyKeyError>(py));
+
+        assert!(message.is_instance_of::<PyRuntimeError>(py));
+        assert_eq!(message.value_bound(py).to_string(), "something went wrong");
+
+        assert!(python.is_instance_of::<pyo3::exceptions::PyModuleNotFoundError>(py));
+    });
+
+    Ok(())
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    // the translator is global, so the tests run one after the other
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::run(py, async {
+            test_default_translation().await?;
+            println!("test test_error_translator::test_default_translation ... ok");
+
+            test_error_mappings().await?;
+            println!("test test_error_translator::test_error_mappings ... ok");
+
+            test_python_cause().await?;
+            println!("test test_error_translator::test_python_cause ... ok");
+
+            test_custom_translation().await?;
+            println!("test test_error_translator::test_custom_translation ... ok");
+
+            #[cfg(feature = "anyhow")]
+            {
+                test_anyhow().await?;
+                println!("test test_error_translator::test_anyhow ... ok");
+            }
+
+            Ok(())
+        })
+    })
+}
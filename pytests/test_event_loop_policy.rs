@@ -0,0 +1,67 @@
+#[cfg(not(target_os = "windows"))]
+mod tests {
+    use pyo3::prelude::*;
+
+    /// The name of the thread that runs the current event loop
+    async fn loop_thread_name() -> PyResult<String> {
+        let fut = Python::with_gil(|py| {
+            let code = "import threading\n\
+                        async def thread_name():\n    return threading.current_thread().name\n";
+            let module = PyModule::from_code_bound(py, code, "loop_thread.py", "loop_thread")?;
+            pyo3_async_runtimes::tokio::into_future(module.call_method0("thread_name")?)
+        })?;
+        let name = fut.await?;
+
+        Python::with_gil(|py| name.extract(py))
+    }
+
+    #[pyo3_async_runtimes::tokio::test]
+    async fn test_main_policy() -> PyResult<()> {
+        // the harness runs on the event loop of the policy of `main`
+        Python::with_gil(|py| -> PyResult<()> {
+            assert!(pyo3_async_runtimes::is_uvloop(
+                &pyo3_async_runtimes::tokio::get_current_loop(py)?
+            )?);
+            Ok(())
+        })?;
+        assert_eq!(loop_thread_name().await?, "MainThread");
+
+        Ok(())
+    }
+
+    #[pyo3_async_runtimes::tokio::test(event_loop_policy = "asyncio")]
+    async fn test_test_policy() -> PyResult<()> {
+        // the test has an event loop of its own, on a thread of its own
+        Python::with_gil(|py| -> PyResult<()> {
+            assert!(!pyo3_async_runtimes::is_uvloop(
+                &pyo3_async_runtimes::tokio::get_current_loop(py)?
+            )?);
+            Ok(())
+        })?;
+        assert_ne!(loop_thread_name().await?, "MainThread");
+
+        Ok(())
+    }
+
+    #[pyo3_async_runtimes::tokio::test(flavor = "multi_thread", event_loop_policy = "uvloop")]
+    async fn test_test_policy_configured_runtime() -> PyResult<()> {
+        Python::with_gil(|py| -> PyResult<()> {
+            assert!(pyo3_async_runtimes::is_uvloop(
+                &pyo3_async_runtimes::tokio::get_current_loop(py)?
+            )?);
+            Ok(())
+        })?;
+        assert_ne!(loop_thread_name().await?, "MainThread");
+
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+#[pyo3_async_runtimes::tokio::main(event_loop_policy = "uvloop")]
+async fn main() -> pyo3::PyResult<()> {
+    pyo3_async_runtimes::testing::main().await
+}
+
+#[cfg(target_os = "windows")]
+fn main() {}
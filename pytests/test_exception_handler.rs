@@ -0,0 +1,64 @@
+use std::sync::Mutex;
+
+use pyo3::prelude::*;
+
+/// Keeps the records of the target of the exception handler
+struct Records(Mutex<Vec<String>>);
+
+impl log::Log for Records {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "pyo3_async_runtimes"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static RECORDS: Records = Records(Mutex::new(Vec::new()));
+
+const TEST_CODE: &str = r#"
+import asyncio
+
+def fail():
+    raise ValueError("callback failed")
+
+async def main():
+    asyncio.get_running_loop().call_soon(fail)
+    await asyncio.sleep(0.1)
+"#;
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+    log::set_logger(&RECORDS).unwrap();
+    log::set_max_level(log::LevelFilter::Error);
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let test_mod =
+            PyModule::from_code_bound(py, TEST_CODE, "test_exception_handler.py", "test_mod")?;
+        let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+        pyo3_async_runtimes::install_exception_handler(&event_loop)?;
+
+        event_loop.call_method1("run_until_complete", (test_mod.call_method0("main")?,))?;
+        event_loop.call_method0("close")?;
+        Ok(())
+    })?;
+
+    // with the `tracing` feature, the errors are reported to `tracing` instead
+    if !cfg!(feature = "tracing") {
+        let records = RECORDS.0.lock().unwrap();
+        assert_eq!(records.len(), 1, "{:?}", records);
+
+        let record = &records[0];
+        assert!(record.starts_with("Exception in callback"), "{}", record);
+        assert!(record.contains("\ncallback: <Handle fail()"), "{}", record);
+        assert!(record.contains("ValueError: callback failed"), "{}", record);
+    }
+    println!("test test_exception_handler ... ok");
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+use pyo3_async_runtimes::tokio::TokioRuntime;
+
+const GEVENT_TEST_MOD: &str = r#"
+import gevent
+
+def sleep_for_1s(sleep_for):
+    return sleep_for(1).get(timeout=5)
+
+def join_sleeping_greenlet(join):
+    def sleep():
+        gevent.sleep(1)
+        return 1
+
+    return join(gevent.spawn(sleep)).get(timeout=5)
+
+def join_failing_greenlet(join):
+    def fail():
+        raise ValueError("this error was intentional!")
+
+    try:
+        join(gevent.spawn(fail)).get(timeout=5)
+    except ValueError:
+        return True
+
+    return False
+"#;
+
+#[pyfunction]
+fn sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let secs = secs.extract()?;
+
+    pyo3_async_runtimes::gevent::future_into_py::<TokioRuntime, _, _>(py, async move {
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+        Ok(secs)
+    })
+}
+
+#[pyfunction]
+fn join<'p>(py: Python<'p>, greenlet: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let fut = pyo3_async_runtimes::gevent::into_future::<TokioRuntime>(greenlet)?;
+
+    pyo3_async_runtimes::gevent::future_into_py::<TokioRuntime, _, _>(py, fut)
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            GEVENT_TEST_MOD,
+            "test_gevent_mod.py",
+            "test_gevent_mod",
+        )?;
+
+        let secs: u64 = test_mod
+            .call_method1("sleep_for_1s", (wrap_pyfunction_bound!(sleep, py)?,))?
+            .extract()?;
+        assert_eq!(secs, 1);
+        println!("test test_gevent::test_future_into_py ... ok");
+
+        let secs: u64 = test_mod
+            .call_method1(
+                "join_sleeping_greenlet",
+                (wrap_pyfunction_bound!(join, py)?,),
+            )?
+            .extract()?;
+        assert_eq!(secs, 1);
+        println!("test test_gevent::test_into_future ... ok");
+
+        let raised: bool = test_mod
+            .call_method1(
+                "join_failing_greenlet",
+                (wrap_pyfunction_bound!(join, py)?,),
+            )?
+            .extract()?;
+        assert!(raised);
+        println!("test test_gevent::test_into_future_error ... ok");
+
+        Ok(())
+    })
+}
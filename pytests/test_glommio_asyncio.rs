@@ -0,0 +1,394 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use pyo3::{
+    prelude::*,
+    types::{IntoPyDict, PyType},
+    wrap_pyfunction, wrap_pymodule,
+};
+use pyo3_async_runtimes::TaskLocals;
+
+#[cfg(feature = "unstable-streams")]
+use futures::{StreamExt, TryStreamExt};
+
+/// glommio timers are `!Send`, so they have to be created on the executor thread
+async fn sleep_pinned(duration: Duration) {
+    pyo3_async_runtimes::glommio::spawn_pinned(move || glommio::timer::sleep(duration)).await
+}
+
+#[pyfunction]
+fn sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let secs = secs.extract()?;
+
+    pyo3_async_runtimes::glommio::future_into_py(py, async move {
+        sleep_pinned(Duration::from_secs(secs)).await;
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::glommio::test]
+async fn test_future_into_py() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let sleeper_mod = PyModule::new_bound(py, "rust_sleeper")?;
+
+        sleeper_mod.add_wrapped(wrap_pyfunction!(sleep))?;
+
+        let test_mod = PyModule::from_code_bound(
+            py,
+            common::TEST_MOD,
+            "test_future_into_py_mod.py",
+            "test_future_into_py_mod",
+        )?;
+
+        pyo3_async_runtimes::glommio::into_future(
+            test_mod.call_method1("sleep_for_1s", (sleeper_mod.getattr("sleep")?,))?,
+        )
+    })?;
+
+    fut.await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::glommio::test]
+async fn test_async_sleep() -> PyResult<()> {
+    let asyncio = Python::with_gil(|py| py.import_bound("asyncio").map(PyObject::from))?;
+
+    sleep_pinned(Duration::from_secs(1)).await;
+
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::glommio::into_future(asyncio.bind(py).call_method1("sleep", (1.0,))?)
+    })?
+    .await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::glommio::test]
+fn test_blocking_sleep() -> PyResult<()> {
+    common::test_blocking_sleep()
+}
+
+#[pyo3_async_runtimes::glommio::test]
+async fn test_into_future() -> PyResult<()> {
+    common::test_into_future(Python::with_gil(|py| {
+        pyo3_async_runtimes::glommio::get_current_loop(py)
+            .unwrap()
+            .into()
+    }))
+    .await
+}
+
+#[pyo3_async_runtimes::glommio::test]
+async fn test_other_awaitables() -> PyResult<()> {
+    common::test_other_awaitables(Python::with_gil(|py| {
+        pyo3_async_runtimes::glommio::get_current_loop(py)
+            .unwrap()
+            .into()
+    }))
+    .await
+}
+
+#[pyo3_async_runtimes::glommio::test]
+async fn test_panic() -> PyResult<()> {
+    let fut = Python::with_gil(|py| -> PyResult<_> {
+        pyo3_async_runtimes::glommio::into_future(pyo3_async_runtimes::glommio::future_into_py::<
+            _,
+            (),
+        >(py, async {
+            panic!("this panic was intentional!")
+        })?)
+    })?;
+
+    match fut.await {
+        Ok(_) => panic!("coroutine should panic"),
+        Err(e) => Python::with_gil(|py| {
+            if e.is_instance_of::<pyo3_async_runtimes::err::RustPanic>(py) {
+                Ok(())
+            } else {
+                panic!("expected RustPanic err")
+            }
+        }),
+    }
+}
+
+#[pyo3_async_runtimes::glommio::test]
+async fn test_cancel() -> PyResult<()> {
+    let completed = Arc::new(Mutex::new(false));
+
+    let py_future = Python::with_gil(|py| -> PyResult<PyObject> {
+        let completed = Arc::clone(&completed);
+        Ok(
+            pyo3_async_runtimes::glommio::future_into_py(py, async move {
+                sleep_pinned(Duration::from_secs(1)).await;
+                *completed.lock().unwrap() = true;
+
+                Ok(())
+            })?
+            .into(),
+        )
+    })?;
+
+    if let Err(e) = Python::with_gil(|py| -> PyResult<_> {
+        py_future.bind(py).call_method0("cancel")?;
+        pyo3_async_runtimes::glommio::into_future(py_future.into_bound(py))
+    })?
+    .await
+    {
+        Python::with_gil(|py| -> PyResult<()> {
+            assert!(e.value_bound(py).is_instance(
+                py.import_bound("asyncio")?
+                    .getattr("CancelledError")?
+                    .downcast::<PyType>()
+                    .unwrap()
+            )?);
+            Ok(())
+        })?;
+    } else {
+        panic!("expected CancelledError");
+    }
+
+    sleep_pinned(Duration::from_secs(1)).await;
+    if *completed.lock().unwrap() {
+        panic!("future still completed")
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "unstable-streams")]
+const GLOMMIO_TEST_MOD: &str = r#"
+import asyncio
+
+async def gen():
+    for i in range(10):
+        await asyncio.sleep(0.1)
+        yield i
+"#;
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::glommio::test]
+async fn test_async_gen_v1() -> PyResult<()> {
+    let stream = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            GLOMMIO_TEST_MOD,
+            "test_rust_coroutine/glommio_test_mod.py",
+            "glommio_test_mod",
+        )?;
+
+        pyo3_async_runtimes::glommio::into_stream_v1(test_mod.call_method0("gen")?)
+    })?;
+
+    let vals = stream
+        .map(|item| Python::with_gil(|py| -> PyResult<i32> { item?.bind(py).extract() }))
+        .try_collect::<Vec<i32>>()
+        .await?;
+
+    assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::glommio::test]
+fn test_spawn_pinned_locals(event_loop: PyObject) -> PyResult<()> {
+    let locals = Python::with_gil(|py| -> PyResult<TaskLocals> {
+        TaskLocals::new(event_loop.into_bound(py)).copy_context(py)
+    })?;
+    let expected = Python::with_gil(|py| locals.event_loop(py).into_py(py));
+
+    futures::executor::block_on(pyo3_async_runtimes::glommio::scope(locals, async move {
+        let event_loop = pyo3_async_runtimes::glommio::spawn_pinned(|| async {
+            // glommio's `!Send` futures can be awaited alongside the task locals
+            glommio::yield_if_needed().await;
+            Python::with_gil(|py| {
+                pyo3_async_runtimes::glommio::get_current_loop(py).map(PyObject::from)
+            })
+        })
+        .await?;
+
+        Python::with_gil(|py| {
+            assert!(event_loop.bind(py).is(expected.bind(py)));
+        });
+
+        Ok(())
+    }))
+}
+
+#[pyo3_async_runtimes::glommio::test]
+async fn test_pinned_future_into_py() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::glommio::into_future(
+            pyo3_async_runtimes::glommio::pinned_future_into_py(py, || async {
+                // Rc is !Send, so this future could not be passed to `future_into_py`
+                let value = std::rc::Rc::new(42);
+                glommio::timer::sleep(Duration::from_millis(100)).await;
+                Ok(*value)
+            })?,
+        )
+    })?;
+
+    let value = fut.await?;
+    Python::with_gil(|py| assert_eq!(value.extract::<i32>(py).unwrap(), 42));
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::glommio::test]
+async fn test_spawn_pinned_panic() -> PyResult<()> {
+    let fut = Python::with_gil(|py| -> PyResult<_> {
+        pyo3_async_runtimes::glommio::into_future(pyo3_async_runtimes::glommio::future_into_py::<
+            _,
+            (),
+        >(py, async {
+            pyo3_async_runtimes::glommio::spawn_pinned(|| async {
+                panic!("this panic was intentional!")
+            })
+            .await
+        })?)
+    })?;
+
+    match fut.await {
+        Ok(_) => panic!("coroutine should panic"),
+        Err(e) => Python::with_gil(|py| {
+            if e.is_instance_of::<pyo3_async_runtimes::err::RustPanic>(py) {
+                Ok(())
+            } else {
+                panic!("expected RustPanic err")
+            }
+        }),
+    }
+}
+
+/// This module is implemented in Rust.
+#[pymodule]
+fn test_mod(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    #![allow(deprecated)]
+    #[pyfunction(name = "sleep")]
+    fn sleep_(py: Python) -> PyResult<Bound<PyAny>> {
+        pyo3_async_runtimes::glommio::future_into_py(py, async move {
+            sleep_pinned(Duration::from_millis(500)).await;
+            Ok(())
+        })
+    }
+
+    m.add_function(wrap_pyfunction!(sleep_, m)?)?;
+
+    Ok(())
+}
+
+const MULTI_ASYNCIO_CODE: &str = r#"
+async def main():
+    return await test_mod.sleep()
+
+asyncio.new_event_loop().run_until_complete(main())
+"#;
+
+#[pyo3_async_runtimes::glommio::test]
+fn test_multiple_asyncio_run() -> PyResult<()> {
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::glommio::run(py, async move {
+            sleep_pinned(Duration::from_millis(500)).await;
+            Ok(())
+        })?;
+        pyo3_async_runtimes::glommio::run(py, async move {
+            sleep_pinned(Duration::from_millis(500)).await;
+            Ok(())
+        })?;
+
+        let d = [
+            ("asyncio", py.import_bound("asyncio")?.into()),
+            ("test_mod", wrap_pymodule!(test_mod)(py)),
+        ]
+        .into_py_dict_bound(py);
+
+        py.run_bound(MULTI_ASYNCIO_CODE, Some(&d), None)?;
+        py.run_bound(MULTI_ASYNCIO_CODE, Some(&d), None)?;
+        Ok(())
+    })
+}
+
+#[pymodule]
+fn cvars_mod(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    #![allow(deprecated)]
+    #[pyfunction]
+    pub(crate) fn async_callback(py: Python, callback: PyObject) -> PyResult<Bound<PyAny>> {
+        pyo3_async_runtimes::glommio::future_into_py(py, async move {
+            Python::with_gil(|py| {
+                pyo3_async_runtimes::glommio::into_future(callback.bind(py).call0()?)
+            })?
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    m.add_function(wrap_pyfunction!(async_callback, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::glommio::test]
+async fn test_async_gen_v2() -> PyResult<()> {
+    let stream = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            GLOMMIO_TEST_MOD,
+            "test_rust_coroutine/glommio_test_mod.py",
+            "glommio_test_mod",
+        )?;
+
+        pyo3_async_runtimes::glommio::into_stream_v2(test_mod.call_method0("gen")?)
+    })?;
+
+    let vals = stream
+        .map(|item| Python::with_gil(|py| -> PyResult<i32> { item.bind(py).extract() }))
+        .try_collect::<Vec<i32>>()
+        .await?;
+
+    assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+
+    Ok(())
+}
+
+const CONTEXTVARS_CODE: &str = r#"
+cx = contextvars.ContextVar("cx")
+
+async def contextvars_test():
+    assert cx.get() == "foobar"
+
+async def main():
+    cx.set("foobar")
+    await cvars_mod.async_callback(contextvars_test)
+
+asyncio.run(main())
+"#;
+
+#[pyo3_async_runtimes::glommio::test]
+fn test_contextvars() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let d = [
+            ("asyncio", py.import_bound("asyncio")?.into()),
+            ("contextvars", py.import_bound("contextvars")?.into()),
+            ("cvars_mod", wrap_pymodule!(cvars_mod)(py)),
+        ]
+        .into_py_dict_bound(py);
+
+        py.run_bound(CONTEXTVARS_CODE, Some(&d), None)?;
+        py.run_bound(CONTEXTVARS_CODE, Some(&d), None)?;
+        Ok(())
+    })
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::glommio::run(py, pyo3_async_runtimes::testing::main())
+    })
+}
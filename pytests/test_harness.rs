@@ -0,0 +1,237 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use pyo3::{exceptions::PySystemExit, prelude::*};
+use pyo3_async_runtimes::testing::{
+    parse_args_from, test_harness, test_harness_with_hooks, Hooks, Test,
+};
+
+static PASSING_RUNS: AtomicUsize = AtomicUsize::new(0);
+static FAILING_RUNS: AtomicUsize = AtomicUsize::new(0);
+static HOOK_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+fn passing() -> Pin<Box<dyn Future<Output = PyResult<()>> + Send>> {
+    Box::pin(async {
+        PASSING_RUNS.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    })
+}
+
+fn passing_slow() -> Pin<Box<dyn Future<Output = PyResult<()>> + Send>> {
+    Box::pin(async {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        PASSING_RUNS.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    })
+}
+
+fn failing() -> Pin<Box<dyn Future<Output = PyResult<()>> + Send>> {
+    Box::pin(async {
+        FAILING_RUNS.fetch_add(1, Ordering::SeqCst);
+        Python::with_gil(|py| py.run_bound("raise ValueError('expected failure')", None, None))
+    })
+}
+
+fn panicking() -> Pin<Box<dyn Future<Output = PyResult<()>> + Send>> {
+    Box::pin(async {
+        FAILING_RUNS.fetch_add(1, Ordering::SeqCst);
+        panic!("expected panic");
+    })
+}
+
+fn printing() -> Pin<Box<dyn Future<Output = PyResult<()>> + Send>> {
+    Box::pin(async {
+        // both synchronously and in a coroutine on the event loop
+        let log = Python::with_gil(|py| {
+            py.run_bound("print('printed by the test')", None, None)?;
+
+            let module = PyModule::from_code_bound(
+                py,
+                "import logging\n\nasync def log():\n    logging.getLogger('harness').warning('logged by the test')\n",
+                "log.py",
+                "log",
+            )?;
+            pyo3_async_runtimes::tokio::into_future(module.call_method0("log")?)
+        })?;
+        log.await?;
+
+        Python::with_gil(|py| py.run_bound("raise ValueError('expected failure')", None, None))
+    })
+}
+
+fn tests() -> Vec<Test> {
+    vec![
+        Test {
+            name: "harness::passing",
+            test_fn: &passing,
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "harness::passing_slow",
+            test_fn: &passing_slow,
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "harness::failing",
+            test_fn: &failing,
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "harness::panicking",
+            test_fn: &panicking,
+            serial: false,
+            shared_loop: false,
+        },
+    ]
+}
+
+/// Run the tests with the arguments and return the number of passing and failing tests that ran
+async fn run(args: &[&str]) -> PyResult<(usize, usize)> {
+    PASSING_RUNS.store(0, Ordering::SeqCst);
+    FAILING_RUNS.store(0, Ordering::SeqCst);
+
+    let args = std::iter::once("test_harness").chain(args.iter().copied());
+    test_harness(tests(), parse_args_from(args)).await?;
+
+    Ok((
+        PASSING_RUNS.load(Ordering::SeqCst),
+        FAILING_RUNS.load(Ordering::SeqCst),
+    ))
+}
+
+/// Run the passing tests with hooks that count how often they run, and check that the hooks run in
+/// the task locals of the harness
+async fn run_with_hooks(before_each_fails: bool) -> PyResult<usize> {
+    PASSING_RUNS.store(0, Ordering::SeqCst);
+    HOOK_RUNS.store(0, Ordering::SeqCst);
+
+    let count = || {
+        Python::with_gil(|py| -> PyResult<()> {
+            pyo3_async_runtimes::tokio::get_current_loop(py)?;
+            HOOK_RUNS.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        })
+    };
+    let hooks = Hooks::new()
+        .with_setup(move || async move { count() })
+        .with_teardown(move || async move { count() })
+        .with_before_each(move |name| async move {
+            count()?;
+            if before_each_fails {
+                Err(pyo3::exceptions::PyRuntimeError::new_err(format!(
+                    "no setup for {}",
+                    name
+                )))
+            } else {
+                Ok(())
+            }
+        })
+        .with_after_each(move |_| async move { count() });
+
+    let args = parse_args_from(["test_harness", "passing"]);
+    test_harness_with_hooks(tests(), args, hooks).await?;
+
+    Ok(PASSING_RUNS.load(Ordering::SeqCst))
+}
+
+/// Run the test that prints and logs, and return what it wrote to the streams of `sys`
+async fn run_printing(args: &[&str], streams: &(PyObject, PyObject)) -> PyResult<String> {
+    let tests = vec![Test {
+        name: "harness::printing",
+        test_fn: &printing,
+        serial: false,
+        shared_loop: false,
+    }];
+    let args = std::iter::once("test_harness").chain(args.iter().copied());
+    assert!(test_harness(tests, parse_args_from(args)).await.is_err());
+
+    Python::with_gil(|py| {
+        let (stdout, stderr) = streams;
+        let stdout = stdout
+            .bind(py)
+            .call_method0("getvalue")?
+            .extract::<String>()?;
+        let stderr = stderr
+            .bind(py)
+            .call_method0("getvalue")?
+            .extract::<String>()?;
+        Ok(stdout + &stderr)
+    })
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        // the streams are replaced before the harness captures them for the first time, so that
+        // the output that isn't captured can be checked
+        let sys = py.import_bound("sys")?;
+        let io = py.import_bound("io")?;
+        let streams = (
+            io.call_method0("StringIO")?.unbind(),
+            io.call_method0("StringIO")?.unbind(),
+        );
+        sys.setattr("stdout", &streams.0)?;
+        sys.setattr("stderr", &streams.1)?;
+
+        pyo3_async_runtimes::tokio::run(py, async move {
+            // substring filters
+            assert_eq!(run(&["passing"]).await?, (2, 0));
+            assert_eq!(run(&["passing", "--skip", "slow"]).await?, (1, 0));
+
+            // exact filters
+            assert_eq!(run(&["--exact", "harness::passing"]).await?, (1, 0));
+            assert_eq!(run(&["--exact", "passing"]).await?, (0, 0));
+
+            // the structured formats run the same tests
+            assert_eq!(run(&["--format", "json", "passing"]).await?, (2, 0));
+            assert_eq!(run(&["--format", "junit", "passing"]).await?, (2, 0));
+            let err = run(&["--format", "junit", "failing"]).await.unwrap_err();
+            Python::with_gil(|py| assert!(err.is_instance_of::<PySystemExit>(py)));
+
+            // listing doesn't run anything
+            assert_eq!(run(&["--list"]).await?, (0, 0));
+
+            // the failures don't stop the other tests, and fail the harness afterwards
+            let err = run(&["--test-threads", "1", "--nocapture"])
+                .await
+                .unwrap_err();
+            Python::with_gil(|py| -> PyResult<()> {
+                assert!(err.is_instance_of::<PySystemExit>(py));
+                assert_eq!(err.value_bound(py).getattr("code")?.extract::<i32>()?, 101);
+                Ok(())
+            })?;
+            assert_eq!(PASSING_RUNS.load(Ordering::SeqCst), 2);
+            assert_eq!(FAILING_RUNS.load(Ordering::SeqCst), 2);
+
+            let err = run(&["-q", "--skip", "panicking"]).await.unwrap_err();
+            Python::with_gil(|py| assert!(err.is_instance_of::<PySystemExit>(py)));
+
+            // setup and teardown once, before and after each of the 2 tests
+            assert_eq!(run_with_hooks(false).await?, 2);
+            assert_eq!(HOOK_RUNS.load(Ordering::SeqCst), 6);
+
+            // the tests don't run when their before hook fails, and neither do the after hooks
+            assert!(run_with_hooks(true).await.is_err());
+            assert_eq!(PASSING_RUNS.load(Ordering::SeqCst), 0);
+            assert_eq!(HOOK_RUNS.load(Ordering::SeqCst), 4);
+
+            // the Python output of the tests is captured, unless `--nocapture` is passed
+            let output = run_printing(&[], &streams).await?;
+            assert!(!output.contains("printed by the test"));
+            assert!(!output.contains("logged by the test"));
+
+            let output = run_printing(&["--nocapture"], &streams).await?;
+            assert!(output.contains("printed by the test"));
+            assert!(output.contains("logged by the test"));
+
+            Ok(())
+        })
+    })
+}
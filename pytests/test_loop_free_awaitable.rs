@@ -0,0 +1,118 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+const SCHEDULER_MOD: &str = r#"
+import threading
+import time
+
+def run(awaitable):
+    """A minimal scheduler that polls the coroutine without an event loop"""
+    async def main():
+        return await awaitable
+
+    coro = main()
+    while True:
+        try:
+            coro.send(None)
+        except StopIteration as e:
+            return e.value
+        time.sleep(0.01)
+
+def run_parked(awaitable):
+    """A scheduler that parks the coroutine until the done callback fires"""
+    woken = threading.Event()
+    awaitable.add_done_callback(woken.set)
+
+    async def main():
+        return await awaitable
+
+    coro = main()
+    while True:
+        try:
+            coro.send(None)
+        except StopIteration as e:
+            return e.value
+        woken.wait()
+
+def cancel(awaitable):
+    async def main():
+        return await awaitable
+
+    coro = main()
+    coro.send(None)
+    try:
+        coro.throw(KeyError("cancelled"))
+    except KeyError:
+        return True
+    return False
+"#;
+
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let scheduler = PyModule::from_code_bound(
+            py,
+            SCHEDULER_MOD,
+            "test_loop_free_awaitable_mod.py",
+            "test_loop_free_awaitable_mod",
+        )?;
+
+        let awaitable = pyo3_async_runtimes::tokio::future_into_awaitable(py, async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(42)
+        })?;
+        let value: i32 = scheduler.call_method1("run", (awaitable,))?.extract()?;
+        assert_eq!(value, 42);
+        println!("test test_loop_free_awaitable::test_poll ... ok");
+
+        let awaitable = pyo3_async_runtimes::tokio::future_into_awaitable(py, async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok("done")
+        })?;
+        let value: String = scheduler
+            .call_method1("run_parked", (awaitable,))?
+            .extract()?;
+        assert_eq!(value, "done");
+        println!("test test_loop_free_awaitable::test_done_callback ... ok");
+
+        let awaitable = pyo3_async_runtimes::tokio::future_into_awaitable(py, async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Err::<(), _>(PyValueError::new_err("rust error"))
+        })?;
+        let err = scheduler.call_method1("run", (awaitable,)).unwrap_err();
+        assert!(err.is_instance_of::<PyValueError>(py));
+        println!("test test_loop_free_awaitable::test_error ... ok");
+
+        let dropped = Arc::new(AtomicBool::new(false));
+        let guard = SetOnDrop(dropped.clone());
+        let awaitable = pyo3_async_runtimes::tokio::future_into_awaitable(py, async move {
+            let _guard = guard;
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(())
+        })?;
+        assert!(scheduler
+            .call_method1("cancel", (awaitable,))?
+            .extract::<bool>()?);
+        py.allow_threads(|| std::thread::sleep(Duration::from_millis(100)));
+        assert!(dropped.load(Ordering::SeqCst));
+        println!("test test_loop_free_awaitable::test_cancel ... ok");
+
+        Ok(())
+    })
+}
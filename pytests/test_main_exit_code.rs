@@ -0,0 +1,63 @@
+use std::{
+    process::{Command, ExitCode},
+    thread,
+    time::{Duration, Instant},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+const CASE_VAR: &str = "PYO3_ASYNC_RUNTIMES_EXIT_CODE_CASE";
+
+/// How long a case gets to exit before it counts as hanging
+const CASE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Run this test again with the given case and get its exit code
+fn exit_code_of(case: &str) -> Option<i32> {
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .env(CASE_VAR, case)
+        .spawn()
+        .unwrap();
+
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            return status.code();
+        }
+        if start.elapsed() > CASE_TIMEOUT {
+            child.kill().unwrap();
+            panic!("case {} didn't exit within {:?}", case, CASE_TIMEOUT);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+async fn run_case(case: &str) -> PyResult<ExitCode> {
+    match case {
+        "exit_code" => Ok(ExitCode::from(4)),
+        "system_exit" => Python::with_gil(|py| {
+            py.import_bound("sys")?.call_method1("exit", (3,))?;
+            unreachable!("sys.exit raises SystemExit")
+        }),
+        "system_exit_none" => Python::with_gil(|py| {
+            py.import_bound("sys")?.call_method0("exit")?;
+            unreachable!("sys.exit raises SystemExit")
+        }),
+        "error" => Err(PyValueError::new_err("this error was intentional!")),
+        case => panic!("unknown case {}", case),
+    }
+}
+
+#[pyo3_async_runtimes::tokio::main(flavor = "current_thread")]
+async fn main() -> PyResult<ExitCode> {
+    if let Ok(case) = std::env::var(CASE_VAR) {
+        return run_case(&case).await;
+    }
+
+    assert_eq!(exit_code_of("exit_code"), Some(4));
+    assert_eq!(exit_code_of("system_exit"), Some(3));
+    assert_eq!(exit_code_of("system_exit_none"), Some(0));
+    assert_eq!(exit_code_of("error"), Some(1));
+    println!("test test_main_exit_code::test_main_exit_code ... ok");
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+
+/// An event loop that mimics GUI-integrated loops like `qasync.QEventLoop`, which lack some of the
+/// methods and arguments of the loops in `asyncio`
+const MINIMAL_LOOP_MOD: &str = r#"
+import asyncio
+
+class MinimalEventLoop(asyncio.SelectorEventLoop):
+    def __getattribute__(self, name):
+        if name == "create_future":
+            raise AttributeError(name)
+        return super().__getattribute__(name)
+
+    def call_soon_threadsafe(self, callback, *args):
+        return super().call_soon_threadsafe(callback, *args)
+
+async def sleep_for_1s(sleep_for):
+    return await sleep_for(1)
+
+async def sleep_for_1s_unregistered(sleep_for):
+    # older qasync versions run without registering themselves as the running loop
+    event_loop = asyncio.events._get_running_loop()
+    asyncio.events._set_running_loop(None)
+    try:
+        awaitable = sleep_for(1)
+    finally:
+        asyncio.events._set_running_loop(event_loop)
+
+    return await awaitable
+
+class ThirdPartyPolicy(asyncio.AbstractEventLoopPolicy):
+    """A policy that keeps its loop somewhere else than the default policies, like uvloop's"""
+
+    def __init__(self):
+        self.event_loop = None
+
+    def get_event_loop(self):
+        if self.event_loop is None:
+            raise RuntimeError("no loop")
+        return self.event_loop
+
+    def set_event_loop(self, event_loop):
+        self.event_loop = event_loop
+
+    def new_event_loop(self):
+        return MinimalEventLoop()
+"#;
+
+#[pyfunction]
+fn sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let secs = secs.extract()?;
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+        Ok(secs)
+    })
+}
+
+fn run_test(py: Python, test_mod: &Bound<PyModule>, test_fn: &str) -> PyResult<()> {
+    let event_loop = test_mod.getattr("MinimalEventLoop")?.call0()?;
+    let asyncio = py.import_bound("asyncio")?;
+    asyncio.call_method1("set_event_loop", (&event_loop,))?;
+
+    let secs: PyResult<u64> = event_loop
+        .call_method1(
+            "run_until_complete",
+            (test_mod.call_method1(test_fn, (wrap_pyfunction_bound!(sleep, py)?,))?,),
+        )
+        .and_then(|secs| secs.extract());
+
+    asyncio.call_method1("set_event_loop", (py.None(),))?;
+    event_loop.call_method0("close")?;
+
+    assert_eq!(secs?, 1);
+    Ok(())
+}
+
+fn test_no_loop_created(py: Python) -> PyResult<()> {
+    // the error is the one of `asyncio.get_running_loop`, not the one of the fallback
+    let err = pyo3_async_runtimes::get_running_loop(py).unwrap_err();
+    assert!(err.to_string().contains("no running event loop"));
+
+    let policy = py
+        .import_bound("asyncio")?
+        .call_method0("get_event_loop_policy")?;
+    let policy_loop = policy.getattr("_local")?.getattr("_loop")?;
+    if py.version_info() >= (3, 12) {
+        // the lookup must not have created and set a loop for the main thread as a side effect
+        assert!(policy_loop.is_none());
+    } else if !policy_loop.is_none() {
+        // like `asyncio.get_event_loop`, which older versions create a loop for
+        policy.call_method1("set_event_loop", (py.None(),))?;
+        policy_loop.call_method0("close")?;
+    }
+    Ok(())
+}
+
+fn test_third_party_policy(py: Python, test_mod: &Bound<PyModule>) -> PyResult<()> {
+    let asyncio = py.import_bound("asyncio")?;
+    let default_policy = asyncio.call_method0("get_event_loop_policy")?;
+    asyncio.call_method1(
+        "set_event_loop_policy",
+        (test_mod.getattr("ThirdPartyPolicy")?.call0()?,),
+    )?;
+
+    let result = run_test(py, test_mod, "sleep_for_1s_unregistered");
+
+    asyncio.call_method1("set_event_loop_policy", (default_policy,))?;
+    result
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            MINIMAL_LOOP_MOD,
+            "test_minimal_event_loop_mod.py",
+            "test_minimal_event_loop_mod",
+        )?;
+
+        test_no_loop_created(py)?;
+        println!("test test_minimal_event_loop::test_no_loop_created ... ok");
+
+        run_test(py, &test_mod, "sleep_for_1s")?;
+        println!("test test_minimal_event_loop::test_future_into_py ... ok");
+
+        run_test(py, &test_mod, "sleep_for_1s_unregistered")?;
+        println!("test test_minimal_event_loop::test_unregistered_running_loop ... ok");
+
+        test_third_party_policy(py, &test_mod)?;
+        println!("test test_minimal_event_loop::test_third_party_policy ... ok");
+
+        Ok(())
+    })
+}
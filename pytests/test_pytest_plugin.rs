@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use pyo3::{prelude::*, types::PyDict};
+use pyo3_async_runtimes::tokio::TokioRuntime;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_passes() -> PyResult<()> {
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+fn test_blocking_passes() -> PyResult<()> {
+    std::thread::sleep(Duration::from_millis(100));
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_fails() -> PyResult<()> {
+    Python::with_gil(|py| {
+        py.run_bound(
+            "raise ValueError('this error was intentional!')",
+            None,
+            None,
+        )
+    })
+}
+
+const TEST_FILE: &str = r#"
+from rust_tests_mod import test_rust
+"#;
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        if py.import_bound("pytest").is_err() {
+            println!("test test_pytest_plugin ... skipped, pytest isn't installed");
+            return Ok(());
+        }
+
+        let module = PyModule::new_bound(py, "rust_tests_mod")?;
+        pyo3_async_runtimes::testing::add_pytest_plugin::<TokioRuntime>(&module)?;
+        py.import_bound("sys")?
+            .getattr("modules")?
+            .set_item("rust_tests_mod", &module)?;
+
+        let names = module
+            .getattr("rust_tests")?
+            .call_method0("names")?
+            .extract::<Vec<String>>()?;
+        assert_eq!(
+            names,
+            [
+                "test_pytest_plugin::test_blocking_passes",
+                "test_pytest_plugin::test_fails",
+                "test_pytest_plugin::test_passes",
+            ]
+        );
+
+        let locals = PyDict::new_bound(py);
+        locals.set_item("source", TEST_FILE)?;
+        py.run_bound(
+            r#"
+import tempfile, pathlib
+
+tmp = tempfile.TemporaryDirectory()
+path = str(pathlib.Path(tmp.name) / "test_rust.py")
+with open(path, "w") as f:
+    f.write(source)
+"#,
+            None,
+            Some(&locals),
+        )?;
+        let path = locals.get_item("path")?.unwrap();
+
+        let pytest = py.import_bound("pytest")?;
+        let run = |args: Vec<&str>| -> PyResult<i32> {
+            let mut argv = vec![path.extract::<String>()?];
+            argv.extend(
+                ["-q", "-p", "no:cacheprovider"]
+                    .iter()
+                    .map(|s| s.to_string()),
+            );
+            argv.extend(args.into_iter().map(String::from));
+            pytest.call_method1("main", (argv,))?.extract()
+        };
+
+        // pytest.ExitCode.OK
+        assert_eq!(run(vec!["-k", "passes"])?, 0);
+        // pytest.ExitCode.TESTS_FAILED
+        assert_eq!(run(vec![])?, 1);
+
+        locals.get_item("tmp")?.unwrap().call_method0("cleanup")?;
+        println!("test test_pytest_plugin ... ok");
+        Ok(())
+    })
+}
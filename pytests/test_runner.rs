@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+use pyo3_async_runtimes::RunnerOptions;
+
+const RUNNER_TEST_MOD: &str = r#"
+import asyncio
+import contextvars
+
+var = contextvars.ContextVar("var")
+
+class TrackedEventLoop(asyncio.SelectorEventLoop):
+    created = 0
+
+    def __init__(self):
+        super().__init__()
+        TrackedEventLoop.created += 1
+
+def context_with_var(value):
+    context = contextvars.copy_context()
+    context.run(var.set, value)
+    return context
+
+async def read_var():
+    return var.get()
+"#;
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        // asyncio.Runner was added in Python 3.11
+        if py.version_info() < (3, 11) {
+            return Ok(());
+        }
+
+        let test_mod = PyModule::from_code_bound(
+            py,
+            RUNNER_TEST_MOD,
+            "test_runner_mod.py",
+            "test_runner_mod",
+        )?;
+
+        let options = RunnerOptions::new()
+            .with_debug(true)
+            .with_loop_factory(test_mod.getattr("TrackedEventLoop")?);
+        let debug = pyo3_async_runtimes::tokio::run_with_runner(py, options, async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            Python::with_gil(|py| {
+                pyo3_async_runtimes::tokio::get_current_loop(py)?
+                    .call_method0("get_debug")?
+                    .extract::<bool>()
+            })
+        })?;
+        assert!(debug);
+        assert_eq!(
+            test_mod
+                .getattr("TrackedEventLoop")?
+                .getattr("created")?
+                .extract::<usize>()?,
+            1
+        );
+        println!("test test_runner::test_loop_factory ... ok");
+
+        let options =
+            RunnerOptions::new().with_context(test_mod.call_method1("context_with_var", (42,))?);
+        let test_mod = test_mod.unbind();
+        let value = pyo3_async_runtimes::tokio::run_with_runner(py, options, async move {
+            // the coroutine is created in the runner's context, which carries the variable
+            let fut = Python::with_gil(|py| {
+                pyo3_async_runtimes::tokio::into_future(test_mod.bind(py).call_method0("read_var")?)
+            })?;
+
+            let value = fut.await?;
+            Python::with_gil(|py| value.extract::<i32>(py))
+        })?;
+        assert_eq!(value, 42);
+        println!("test test_runner::test_context ... ok");
+
+        Ok(())
+    })
+}
@@ -0,0 +1,138 @@
+#[allow(dead_code)]
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use pyo3::prelude::*;
+
+#[pyo3_async_runtimes::test(runtime = "async-std")]
+async fn test_async_sleep() -> PyResult<()> {
+    async_std::task::sleep(Duration::from_secs(1)).await;
+
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::async_std::into_future(
+            py.import_bound("asyncio")?.call_method1("sleep", (1.0,))?,
+        )
+    })?
+    .await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::test(runtime = "async-std")]
+fn test_blocking_sleep() -> PyResult<()> {
+    common::test_blocking_sleep()
+}
+
+#[pyo3_async_runtimes::test(runtime = "async-std")]
+async fn test_executor_thread_name() -> PyResult<()> {
+    let name =
+        async_std::task::spawn(async { std::thread::current().name().map(String::from) }).await;
+    assert_eq!(name.as_deref(), Some("pytests-executor"));
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::test(runtime = "async-std", thread_name = "pytests-async")]
+async fn test_async_thread_name() -> PyResult<()> {
+    assert_eq!(std::thread::current().name(), Some("pytests-async"));
+
+    // the test still has the task locals of the harness
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::async_std::into_future(
+            py.import_bound("asyncio")?.call_method1("sleep", (0.1,))?,
+        )
+    })?
+    .await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::test(runtime = "async-std", thread_name = "pytests-blocking")]
+fn test_blocking_thread_name() -> PyResult<()> {
+    assert_eq!(std::thread::current().name(), Some("pytests-blocking"));
+    Ok(())
+}
+
+/// Check that the test runs on an event loop of the asyncio class `class`
+fn assert_event_loop_class(class: &str) -> PyResult<()> {
+    Python::with_gil(|py| {
+        let event_loop = pyo3_async_runtimes::async_std::get_current_loop(py)?;
+        let class = py.import_bound("asyncio")?.getattr(class)?;
+        assert!(event_loop.is_instance(&class)?);
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::testing::test(runtime = "async-std", loop = "selector")]
+async fn test_selector_loop() -> PyResult<()> {
+    assert_event_loop_class("SelectorEventLoop")
+}
+
+#[cfg(windows)]
+#[pyo3_async_runtimes::testing::test(runtime = "async-std", loop = "proactor")]
+async fn test_proactor_loop() -> PyResult<()> {
+    assert_event_loop_class("ProactorEventLoop")
+}
+
+/// The event loop of the first test with a shared loop
+static SHARED_LOOP: Mutex<Option<PyObject>> = Mutex::new(None);
+
+/// Check that the tests with a shared loop run on the same event loop
+fn assert_shared_loop() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let event_loop = pyo3_async_runtimes::async_std::get_current_loop(py)?;
+
+        let mut shared = SHARED_LOOP.lock().unwrap();
+        match &*shared {
+            Some(shared) => assert!(shared.bind(py).is(&event_loop)),
+            None => *shared = Some(event_loop.unbind()),
+        }
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::testing::test(runtime = "async-std", loop = "asyncio", shared_loop)]
+async fn test_shared_loop() -> PyResult<()> {
+    assert_shared_loop()
+}
+
+#[pyo3_async_runtimes::testing::test(runtime = "async-std", loop = "asyncio", shared_loop)]
+async fn test_shared_loop_again() -> PyResult<()> {
+    assert_shared_loop()
+}
+
+static SERIAL_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Check that no other serial test runs until this one is done
+async fn assert_serial() -> PyResult<()> {
+    assert!(!SERIAL_RUNNING.swap(true, Ordering::SeqCst));
+    async_std::task::sleep(Duration::from_millis(100)).await;
+    SERIAL_RUNNING.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+#[pyo3_async_runtimes::testing::test(runtime = "async-std", serial)]
+async fn test_serial() -> PyResult<()> {
+    assert_serial().await
+}
+
+#[pyo3_async_runtimes::testing::test(runtime = "async-std", serial)]
+async fn test_serial_again() -> PyResult<()> {
+    assert_serial().await
+}
+
+#[pyo3_async_runtimes::main(
+    runtime = "async-std",
+    worker_threads = 2,
+    thread_name = "pytests-executor"
+)]
+async fn main() -> pyo3::PyResult<()> {
+    pyo3_async_runtimes::testing::main().await
+}
@@ -0,0 +1,128 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::channel::oneshot;
+use pyo3::prelude::*;
+use pyo3_async_runtimes::{err::ShuttingDown, generic::Runtime, tokio::TokioRuntime};
+
+fn test_shutdown() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        let event_loop = asyncio.call_method0("new_event_loop")?;
+        let other_loop = asyncio.call_method0("new_event_loop")?;
+
+        // a conversion of another loop, which the shutdown leaves alone
+        let other_pending =
+            pyo3_async_runtimes::tokio::run_until_complete(other_loop.clone(), async move {
+                Python::with_gil(|py| -> PyResult<_> {
+                    Ok(pyo3_async_runtimes::tokio::future_into_py(
+                        py,
+                        futures::future::pending::<PyResult<()>>(),
+                    )?
+                    .unbind())
+                })
+            })?;
+
+        let (py_result_tx, py_result_rx) = oneshot::channel();
+
+        let (finishing, pending) =
+            pyo3_async_runtimes::tokio::run_until_complete(event_loop.clone(), async move {
+                Python::with_gil(|py| -> PyResult<_> {
+                    let finishing = pyo3_async_runtimes::tokio::future_into_py(py, async {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        Ok(42)
+                    })?;
+                    let pending = pyo3_async_runtimes::tokio::future_into_py(
+                        py,
+                        futures::future::pending::<PyResult<()>>(),
+                    )?;
+
+                    let sleep = pyo3_async_runtimes::tokio::into_future(
+                        py.import_bound("asyncio")?.call_method1("sleep", (3600,))?,
+                    )?;
+                    pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+                        let _ = py_result_tx.send(sleep.await);
+                    });
+
+                    Ok((finishing.unbind(), pending.unbind()))
+                })
+            })?;
+
+        assert!(!pyo3_async_runtimes::is_shutting_down(&event_loop));
+        let start = Instant::now();
+        pyo3_async_runtimes::shutdown(&event_loop, Duration::from_secs(1))?;
+        assert!(pyo3_async_runtimes::is_shutting_down(&event_loop));
+        assert!(!pyo3_async_runtimes::is_shutting_down(&other_loop));
+
+        // only the conversions of the loop were waited for, so the cancelled ones unwound right
+        // away instead of waiting for the other loop for a second timeout
+        assert!(start.elapsed() < Duration::from_millis(1500));
+        assert!(!other_pending.call_method0(py, "done")?.is_truthy(py)?);
+
+        // the Rust future that finished in time delivered its result
+        assert_eq!(
+            finishing.call_method0(py, "result")?.extract::<i32>(py)?,
+            42
+        );
+
+        // the others were cancelled
+        assert!(pending.call_method0(py, "cancelled")?.is_truthy(py)?);
+
+        let py_result = py
+            .allow_threads(|| pyo3_async_runtimes::tokio::get_runtime().block_on(py_result_rx))
+            .expect("the Rust side of the Python task should have finished");
+        let cancelled_error = asyncio.getattr("CancelledError")?;
+        assert!(py_result
+            .unwrap_err()
+            .is_instance_bound(py, &cancelled_error));
+
+        // no new conversions are accepted
+        let result =
+            pyo3_async_runtimes::tokio::run_until_complete(event_loop.clone(), async { Ok(()) });
+        assert!(result.unwrap_err().is_instance_of::<ShuttingDown>(py));
+
+        // while the other loop still accepts them
+        pyo3_async_runtimes::tokio::run_until_complete(other_loop.clone(), async { Ok(()) })?;
+
+        event_loop.call_method0("close")?;
+
+        // the tokio runtime step aborts the tasks that are left on the runtime
+        let dropped = Arc::new(AtomicBool::new(false));
+        let guard = DropFlag(dropped.clone());
+        TokioRuntime::spawn(async move {
+            let _guard = guard;
+            futures::future::pending::<()>().await;
+        });
+
+        pyo3_async_runtimes::tokio::shutdown(&other_loop, Duration::from_millis(500))?;
+        assert!(other_pending.call_method0(py, "cancelled")?.is_truthy(py)?);
+        assert!(dropped.load(Ordering::SeqCst));
+
+        other_loop.call_method0("close")?;
+
+        Ok(())
+    })
+}
+
+/// Sets its flag when it's dropped
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    test_shutdown()?;
+    println!("test test_shutdown::test_shutdown ... ok");
+
+    Ok(())
+}
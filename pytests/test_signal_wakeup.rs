@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use pyo3::{exceptions::PyKeyboardInterrupt, prelude::*};
+
+/// Simulates a CTRL-C that wasn't delivered to the main thread, which doesn't wake up the selector
+fn interrupt_main() -> PyResult<()> {
+    Python::with_gil(|py| {
+        py.import_bound("_thread")?.call_method0("interrupt_main")?;
+        Ok(())
+    })
+}
+
+fn test_run() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let result = pyo3_async_runtimes::tokio::run(py, async move {
+            interrupt_main()?;
+
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(())
+        });
+
+        match result {
+            Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => Ok(()),
+            Err(e) => Err(e),
+            Ok(()) => panic!("the loop should have been interrupted"),
+        }
+    })
+}
+
+fn test_run_forever() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        let event_loop = asyncio.call_method0("new_event_loop")?;
+
+        pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            interrupt_main().unwrap();
+        });
+
+        let result = pyo3_async_runtimes::run_forever(&event_loop);
+        event_loop.call_method0("close")?;
+
+        match result {
+            Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => Ok(()),
+            Err(e) => Err(e),
+            Ok(()) => panic!("the loop should have been interrupted"),
+        }
+    })
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    test_run()?;
+    println!("test test_signal_wakeup::test_run ... ok");
+
+    test_run_forever()?;
+    println!("test test_signal_wakeup::test_run_forever ... ok");
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+use pyo3_async_runtimes::testing::{parse_args, test_harness, Test};
+use tokio_util::sync::CancellationToken;
+
+const CANCELLATION_TEST_MOD: &str = r#"
+import asyncio
+
+async def cancelled(fut):
+    try:
+        await fut
+    except asyncio.CancelledError:
+        return True
+
+    return False
+"#;
+
+fn test_mod(py: Python) -> PyResult<Bound<PyModule>> {
+    PyModule::from_code_bound(
+        py,
+        CANCELLATION_TEST_MOD,
+        "test_tokio_cancellation_mod.py",
+        "test_tokio_cancellation_mod",
+    )
+}
+
+async fn test_token_cancels_future() -> PyResult<()> {
+    let token = CancellationToken::new();
+
+    let fut = Python::with_gil(|py| {
+        let py_fut =
+            pyo3_async_runtimes::tokio::get_current_loop(py)?.call_method0("create_future")?;
+        pyo3_async_runtimes::tokio::link_cancellation(&py_fut, token.clone())?;
+
+        pyo3_async_runtimes::tokio::into_future(test_mod(py)?.call_method1("cancelled", (py_fut,))?)
+    })?;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    token.cancel();
+
+    let cancelled = fut.await?;
+    Python::with_gil(|py| {
+        assert!(cancelled.extract::<bool>(py)?);
+        Ok(())
+    })
+}
+
+async fn test_future_cancels_token() -> PyResult<()> {
+    let token = CancellationToken::new();
+
+    let py_fut = Python::with_gil(|py| -> PyResult<PyObject> {
+        let py_fut = pyo3_async_runtimes::tokio::future_into_py_with_token(
+            py,
+            token.clone(),
+            futures::future::pending::<PyResult<()>>(),
+        )?;
+
+        Ok(py_fut.into())
+    })?;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!token.is_cancelled());
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let py_fut = py_fut.bind(py);
+        py_fut
+            .call_method0("get_loop")?
+            .call_method1("call_soon_threadsafe", (py_fut.getattr("cancel")?,))?;
+        Ok(())
+    })?;
+
+    tokio::time::timeout(Duration::from_secs(1), token.cancelled())
+        .await
+        .expect("cancelling the future should cancel the token");
+
+    Ok(())
+}
+
+async fn test_completed_future_keeps_token() -> PyResult<()> {
+    let token = CancellationToken::new();
+
+    let fut = Python::with_gil(|py| {
+        let py_fut =
+            pyo3_async_runtimes::tokio::future_into_py_with_token(py, token.clone(), async {
+                Ok(42)
+            })?;
+        pyo3_async_runtimes::tokio::into_future(py_fut)
+    })?;
+
+    let value = fut.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(value.extract::<i32>(py)?, 42);
+        Ok(())
+    })?;
+
+    // a future that completed normally doesn't cancel the token
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!token.is_cancelled());
+
+    Ok(())
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    let tests = vec![
+        Test {
+            name: "test_tokio_cancellation::test_token_cancels_future",
+            test_fn: &|| Box::pin(test_token_cancels_future()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_cancellation::test_future_cancels_token",
+            test_fn: &|| Box::pin(test_future_cancels_token()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_cancellation::test_completed_future_keeps_token",
+            test_fn: &|| Box::pin(test_completed_future_keeps_token()),
+            serial: false,
+            shared_loop: false,
+        },
+    ];
+
+    Python::with_gil(|py| pyo3_async_runtimes::tokio::run(py, test_harness(tests, parse_args())))
+}
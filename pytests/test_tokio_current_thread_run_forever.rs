@@ -13,4 +13,7 @@ fn main() {
 
     tokio_run_forever::test_main();
     println!("test test_tokio_current_thread_run_forever ... ok");
+
+    tokio_run_forever::test_run_forever();
+    println!("test test_tokio_current_thread_run_forever::test_run_forever ... ok");
 }
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use pyo3::{prelude::*, types::PyTuple};
+use pyo3_async_runtimes::tokio::EventLoop;
+
+const EVENT_LOOP_TEST_MOD: &str = r#"
+import asyncio
+import socket
+import time
+
+async def sleep_and_gather():
+    start = asyncio.get_running_loop().time()
+    results = await asyncio.gather(*(asyncio.sleep(0.1, result=i) for i in range(10)))
+    assert results == list(range(10))
+    assert asyncio.get_running_loop().time() - start < 1.0
+
+async def await_rust(sleep_for):
+    assert await sleep_for(1) == 1
+
+async def gather_rust(sleep_for):
+    # the results are handed to the loop without call_soon_threadsafe
+    assert await asyncio.gather(*(sleep_for(0) for _ in range(100))) == [0] * 100
+
+async def run_in_executor():
+    loop = asyncio.get_running_loop()
+    assert await loop.run_in_executor(None, lambda secs: time.sleep(secs) or secs, 0.1) == 0.1
+
+async def add_reader():
+    loop = asyncio.get_running_loop()
+    rsock, wsock = socket.socketpair()
+    rsock.setblocking(False)
+    received = loop.create_future()
+    chunks = []
+
+    def on_readable():
+        # read one byte at a time to check that the watcher is level-triggered
+        chunks.append(rsock.recv(1))
+        if len(chunks) == 3:
+            loop.remove_reader(rsock)
+            received.set_result(b"".join(chunks))
+
+    loop.add_reader(rsock, on_readable)
+    loop.call_later(0.1, wsock.send, b"abc")
+    assert await asyncio.wait_for(received, 5) == b"abc"
+
+    rsock.close()
+    wsock.close()
+
+async def exception_handler():
+    loop = asyncio.get_running_loop()
+    handled = loop.create_future()
+    loop.set_exception_handler(lambda loop, context: handled.set_result(context["exception"]))
+
+    def fail():
+        raise ValueError("this error was intentional!")
+
+    loop.call_soon(fail)
+    assert isinstance(await handled, ValueError)
+    loop.set_exception_handler(None)
+"#;
+
+#[pyfunction]
+fn sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let secs = secs.extract()?;
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+        Ok(secs)
+    })
+}
+
+fn run_test(
+    py: Python,
+    test_mod: &Bound<PyModule>,
+    name: &str,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> PyResult<()> {
+    let event_loop = Bound::new(py, EventLoop::new())?;
+
+    let result =
+        event_loop.call_method1("run_until_complete", (test_mod.call_method1(name, args)?,));
+    event_loop.call_method0("close")?;
+    result?;
+
+    println!("test test_tokio_event_loop::test_{} ... ok", name);
+    Ok(())
+}
+
+fn main() -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            EVENT_LOOP_TEST_MOD,
+            "test_tokio_event_loop_mod.py",
+            "test_tokio_event_loop_mod",
+        )?;
+
+        run_test(py, &test_mod, "sleep_and_gather", ())?;
+        run_test(
+            py,
+            &test_mod,
+            "await_rust",
+            (wrap_pyfunction_bound!(sleep, py)?,),
+        )?;
+        run_test(
+            py,
+            &test_mod,
+            "gather_rust",
+            (wrap_pyfunction_bound!(sleep, py)?,),
+        )?;
+        run_test(py, &test_mod, "run_in_executor", ())?;
+        #[cfg(unix)]
+        run_test(py, &test_mod, "add_reader", ())?;
+        run_test(py, &test_mod, "exception_handler", ())?;
+
+        let event_loop = Bound::new(py, EventLoop::new())?;
+        pyo3_async_runtimes::tokio::run_until_complete(
+            event_loop.clone().into_any(),
+            async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok(())
+            },
+        )?;
+        event_loop.call_method0("close")?;
+        println!("test test_tokio_event_loop::test_run_until_complete ... ok");
+
+        Ok(())
+    })
+}
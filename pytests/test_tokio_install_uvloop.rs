@@ -0,0 +1,55 @@
+#[cfg(not(target_os = "windows"))]
+fn main() -> pyo3::PyResult<()> {
+    use pyo3::{exceptions::PyImportError, prelude::*};
+
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        let available = py.import_bound("uvloop").is_ok();
+
+        // the default event loop isn't a uvloop loop
+        let event_loop = asyncio.call_method0("new_event_loop")?;
+        assert!(!pyo3_async_runtimes::is_uvloop(&event_loop)?);
+        event_loop.call_method0("close")?;
+        println!("test test_tokio_install_uvloop::test_is_uvloop_default ... ok");
+
+        // the run functions install uvloop if the environment asks for it, and fail if it's missing
+        std::env::set_var("PYO3_ASYNC_RUNTIMES_UVLOOP", "1");
+        let result = pyo3_async_runtimes::tokio::run(py, async move {
+            Python::with_gil(|py| -> PyResult<()> {
+                assert!(pyo3_async_runtimes::is_uvloop(
+                    &pyo3_async_runtimes::tokio::get_current_loop(py)?
+                )?);
+                Ok(())
+            })
+        });
+        std::env::remove_var("PYO3_ASYNC_RUNTIMES_UVLOOP");
+        match result {
+            Ok(()) => assert!(available),
+            Err(e) => {
+                assert!(!available);
+                assert!(e.is_instance_of::<PyImportError>(py));
+            }
+        }
+        println!("test test_tokio_install_uvloop::test_uvloop_env ... ok");
+
+        // install_uvloop reports whether uvloop is available
+        asyncio.call_method1("set_event_loop_policy", (py.None(),))?;
+        assert_eq!(pyo3_async_runtimes::install_uvloop(py)?, available);
+
+        pyo3_async_runtimes::tokio::run(py, async move {
+            Python::with_gil(|py| -> PyResult<()> {
+                let event_loop = pyo3_async_runtimes::tokio::get_current_loop(py)?;
+                assert_eq!(pyo3_async_runtimes::is_uvloop(&event_loop)?, available);
+                Ok(())
+            })
+        })?;
+        println!("test test_tokio_install_uvloop::test_install_uvloop ... ok");
+
+        Ok(())
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn main() {}
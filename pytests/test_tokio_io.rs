@@ -0,0 +1,183 @@
+use pyo3::prelude::*;
+use pyo3_async_runtimes::{
+    testing::{parse_args, test_harness, Test},
+    tokio::{AsyncioReader, AsyncioStream},
+};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+
+const IO_TEST_MOD: &str = r#"
+async def echo_line(reader, writer):
+    line = await reader.readline()
+    writer.write(line.upper())
+    await writer.drain()
+    writer.close()
+
+async def write_buffers(reader, writer):
+    writer.write(b"bytes ")
+    writer.write(bytearray(b"bytearray "))
+    writer.write(memoryview(b"xxmemoryview")[2:])
+    await writer.drain()
+    writer.close()
+"#;
+
+async fn test_into_asyncio_streams() -> PyResult<()> {
+    let (client, mut server) = tokio::io::duplex(1024);
+
+    let fut = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            IO_TEST_MOD,
+            "test_tokio_io_mod.py",
+            "test_tokio_io_mod",
+        )?;
+        let (reader, writer) = pyo3_async_runtimes::tokio::into_asyncio_streams(client)?;
+
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod.call_method1("echo_line", (reader, writer))?,
+        )
+    })?;
+
+    server.write_all(b"hello from rust\n").await?;
+    fut.await?;
+
+    let mut echoed = Vec::new();
+    server.read_to_end(&mut echoed).await?;
+    assert_eq!(echoed, b"HELLO FROM RUST\n");
+
+    Ok(())
+}
+
+async fn test_asyncio_stream() -> PyResult<()> {
+    let (client, mut server) = tokio::io::duplex(1024);
+
+    // the Python streams are wrapped right back up for Rust, so all of the data goes through
+    // asyncio in both directions
+    let mut stream = Python::with_gil(|py| {
+        let (reader, writer) = pyo3_async_runtimes::tokio::into_asyncio_streams(client)?;
+        AsyncioStream::new(reader.into_bound(py), writer.into_bound(py))
+    })?;
+
+    stream.write_all(b"ping").await?;
+    let mut buf = [0; 4];
+    server.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"ping");
+
+    server.write_all(b"pong").await?;
+    stream.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"pong");
+
+    // shutting down sends EOF through the StreamWriter
+    stream.shutdown().await?;
+    assert_eq!(server.read(&mut buf).await?, 0);
+
+    drop(server);
+    assert_eq!(stream.read(&mut buf).await?, 0);
+
+    Ok(())
+}
+
+async fn test_asyncio_duplex() -> PyResult<()> {
+    let (mut rust_half, (reader, writer)) = pyo3_async_runtimes::tokio::asyncio_duplex(1024)?;
+
+    let fut = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            IO_TEST_MOD,
+            "test_tokio_io_mod.py",
+            "test_tokio_io_mod",
+        )?;
+
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod.call_method1("echo_line", (reader, writer))?,
+        )
+    })?;
+
+    rust_half.write_all(b"over the pipe\n").await?;
+    fut.await?;
+
+    let mut echoed = Vec::new();
+    rust_half.read_to_end(&mut echoed).await?;
+    assert_eq!(echoed, b"OVER THE PIPE\n");
+
+    Ok(())
+}
+
+async fn test_write_buffer_types() -> PyResult<()> {
+    let (client, mut server) = tokio::io::duplex(1024);
+
+    let fut = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            IO_TEST_MOD,
+            "test_tokio_io_mod.py",
+            "test_tokio_io_mod",
+        )?;
+        let (reader, writer) = pyo3_async_runtimes::tokio::into_asyncio_streams(client)?;
+
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod.call_method1("write_buffers", (reader, writer))?,
+        )
+    })?;
+    fut.await?;
+
+    let mut written = Vec::new();
+    server.read_to_end(&mut written).await?;
+    assert_eq!(written, b"bytes bytearray memoryview");
+
+    Ok(())
+}
+
+async fn test_asyncio_reader() -> PyResult<()> {
+    let (mut rust_half, (reader, _writer)) = pyo3_async_runtimes::tokio::asyncio_duplex(1024)?;
+    let reader = Python::with_gil(|py| AsyncioReader::new(reader.into_bound(py)))?;
+
+    rust_half.write_all(b"first\nsecond\nno newline").await?;
+    rust_half.shutdown().await?;
+
+    let mut lines = reader.lines();
+    assert_eq!(lines.next_line().await?.as_deref(), Some("first"));
+    assert_eq!(lines.next_line().await?.as_deref(), Some("second"));
+    assert_eq!(lines.next_line().await?.as_deref(), Some("no newline"));
+    assert_eq!(lines.next_line().await?, None);
+
+    Ok(())
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    let tests = vec![
+        Test {
+            name: "test_tokio_io::test_into_asyncio_streams",
+            test_fn: &|| Box::pin(test_into_asyncio_streams()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_io::test_asyncio_stream",
+            test_fn: &|| Box::pin(test_asyncio_stream()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_io::test_asyncio_duplex",
+            test_fn: &|| Box::pin(test_asyncio_duplex()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_io::test_write_buffer_types",
+            test_fn: &|| Box::pin(test_write_buffer_types()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_io::test_asyncio_reader",
+            test_fn: &|| Box::pin(test_asyncio_reader()),
+            serial: false,
+            shared_loop: false,
+        },
+    ];
+
+    Python::with_gil(|py| pyo3_async_runtimes::tokio::run(py, test_harness(tests, parse_args())))
+}
@@ -0,0 +1,37 @@
+use pyo3::prelude::*;
+
+fn build_runtime() -> tokio::runtime::Builder {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder
+        .enable_all()
+        .worker_threads(2)
+        .thread_name("pytests-builder");
+    builder
+}
+
+#[pyo3_async_runtimes::tokio::main(builder = "build_runtime")]
+async fn main() -> PyResult<()> {
+    let name = tokio::spawn(async { std::thread::current().name().map(String::from) })
+        .await
+        .unwrap();
+    assert_eq!(name.as_deref(), Some("pytests-builder"));
+
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            py.import_bound("asyncio")?.call_method1("sleep", (0.1,))?,
+        )
+    })?
+    .await?;
+
+    println!("test test_tokio_main_builder::test_builder ... ok");
+
+    // the runtime is running, so another builder can't take effect anymore
+    assert!(pyo3_async_runtimes::tokio::is_initialized());
+    assert!(
+        pyo3_async_runtimes::tokio::try_init(tokio::runtime::Builder::new_current_thread())
+            .is_err()
+    );
+    assert!(pyo3_async_runtimes::tokio::try_get_runtime().is_ok());
+    println!("test test_tokio_main_builder::test_try_init ... ok");
+    Ok(())
+}
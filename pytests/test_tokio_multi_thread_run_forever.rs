@@ -4,4 +4,7 @@ fn main() {
     pyo3::prepare_freethreaded_python();
     tokio_run_forever::test_main();
     println!("test test_tokio_multi_thread_run_forever ... ok");
+
+    tokio_run_forever::test_run_forever();
+    println!("test test_tokio_multi_thread_run_forever::test_run_forever ... ok");
 }
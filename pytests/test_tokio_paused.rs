@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+
+#[pyo3_async_runtimes::tokio::test(start_paused = true, timeout = "30s")]
+async fn test_paused_sleep() -> PyResult<()> {
+    let start = std::time::Instant::now();
+    let py_start = loop_time()?;
+    tokio::time::sleep(Duration::from_secs(3600)).await;
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    // without `mock_clock`, the event loop keeps following the real clock
+    let py_elapsed = loop_time()? - py_start;
+    assert!(py_elapsed < 10.0, "loop.time() elapsed: {}", py_elapsed);
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test(start_paused = true, mock_clock, timeout = "30s")]
+async fn test_paused_python_await() -> PyResult<()> {
+    let (event_loop, fut) = Python::with_gil(|py| -> PyResult<_> {
+        let event_loop = pyo3_async_runtimes::tokio::get_current_loop(py)?.unbind();
+        let fut = pyo3_async_runtimes::tokio::into_future(
+            py.import_bound("asyncio")?.call_method1("sleep", (0.2,))?,
+        )?;
+        Ok((event_loop, fut))
+    })?;
+    let loop_time = |event_loop: &PyObject| {
+        Python::with_gil(|py| event_loop.call_method0(py, "time")?.extract::<f64>(py))
+    };
+
+    let py_start = loop_time(&event_loop)?;
+    let start = tokio::time::Instant::now();
+
+    // the clock skips ahead to the timer of the Python sleep, not to the timeout
+    tokio::time::timeout(Duration::from_secs(1), fut)
+        .await
+        .expect("the paused clock shouldn't advance past the timeout")?;
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let py_elapsed = loop_time(&event_loop)? - py_start;
+    assert!((elapsed - 0.2).abs() < 0.01, "elapsed: {}", elapsed);
+    assert!(
+        (elapsed - py_elapsed).abs() < 0.01,
+        "elapsed: {}, loop.time() elapsed: {}",
+        elapsed,
+        py_elapsed
+    );
+
+    // and keeps skipping ahead afterwards
+    let start = std::time::Instant::now();
+    tokio::time::sleep(Duration::from_secs(3600)).await;
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    Ok(())
+}
+
+const MOCK_CLOCK_TEST_MOD: &str = r#"
+import asyncio
+
+async def sleep_then(secs, order, name):
+    await asyncio.sleep(secs)
+    order.append(name)
+
+async def timed_out(secs, timeout):
+    try:
+        await asyncio.wait_for(asyncio.sleep(secs), timeout)
+    except asyncio.TimeoutError:
+        return True
+    return False
+"#;
+
+fn mock_clock_test_mod(py: Python) -> PyResult<Bound<PyModule>> {
+    PyModule::from_code_bound(
+        py,
+        MOCK_CLOCK_TEST_MOD,
+        "mock_clock_test_mod.py",
+        "mock_clock_test_mod",
+    )
+}
+
+fn loop_time() -> PyResult<f64> {
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::get_current_loop(py)?
+            .call_method0("time")?
+            .extract()
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test(start_paused = true, mock_clock, timeout = "30s")]
+async fn test_mock_clock_timeout() -> PyResult<()> {
+    let start = std::time::Instant::now();
+    let py_start = loop_time()?;
+
+    // a tokio timeout around a Python sleep
+    let sleep = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            py.import_bound("asyncio")?.call_method1("sleep", (3600,))?,
+        )
+    })?;
+    assert!(tokio::time::timeout(Duration::from_secs(1), sleep)
+        .await
+        .is_err());
+    let py_elapsed = loop_time()? - py_start;
+    assert!(
+        (py_elapsed - 1.0).abs() < 0.01,
+        "loop.time() elapsed: {}",
+        py_elapsed
+    );
+
+    // an asyncio timeout around a Python sleep
+    let timed_out = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            mock_clock_test_mod(py)?.call_method1("timed_out", (3600, 2))?,
+        )
+    })?
+    .await?;
+    assert!(Python::with_gil(|py| timed_out.extract::<bool>(py))?);
+    let py_elapsed = loop_time()? - py_start;
+    assert!(
+        (py_elapsed - 3.0).abs() < 0.01,
+        "loop.time() elapsed: {}",
+        py_elapsed
+    );
+
+    assert!(start.elapsed() < Duration::from_secs(10));
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test(start_paused = true, mock_clock, timeout = "30s")]
+async fn test_mock_clock_order() -> PyResult<()> {
+    let order = Python::with_gil(|py| pyo3::types::PyList::empty_bound(py).unbind());
+    let python_sleep = |secs: f64, name: &str| {
+        Python::with_gil(|py| {
+            pyo3_async_runtimes::tokio::into_future(
+                mock_clock_test_mod(py)?
+                    .call_method1("sleep_then", (secs, order.clone_ref(py), name))?,
+            )
+        })
+    };
+    let tokio_sleep = |secs: u64, name: &'static str| {
+        let order = Python::with_gil(|py| order.clone_ref(py));
+        async move {
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+            Python::with_gil(|py| order.bind(py).append(name))
+        }
+    };
+
+    let (a, b, c, d) = futures::join!(
+        python_sleep(30.0, "python 30s")?,
+        tokio_sleep(20, "tokio 20s"),
+        python_sleep(10.0, "python 10s")?,
+        tokio_sleep(40, "tokio 40s"),
+    );
+    a?;
+    b?;
+    c?;
+    d?;
+
+    let order = Python::with_gil(|py| order.bind(py).extract::<Vec<String>>())?;
+    assert_eq!(
+        order,
+        ["python 10s", "tokio 20s", "python 30s", "tokio 40s"]
+    );
+    Ok(())
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let mut builder = tokio::runtime::Builder::new_current_thread();
+        builder.enable_all();
+
+        pyo3_async_runtimes::tokio::init(builder);
+        std::thread::spawn(move || {
+            pyo3_async_runtimes::tokio::get_runtime().block_on(futures::future::pending::<()>());
+        });
+
+        pyo3_async_runtimes::tokio::run(py, pyo3_async_runtimes::testing::main())
+    })
+}
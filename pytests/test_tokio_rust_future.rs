@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+
+#[pyfunction]
+fn sleep(py: Python, secs: u64) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn fail_with(py: Python, message: String) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::tokio::future_into_py::<_, ()>(py, async move {
+        Err(pyo3::exceptions::PyValueError::new_err(message))
+    })
+}
+
+#[pyfunction]
+fn pending_until_dropped(py: Python, dropped: PyObject) -> PyResult<Bound<PyAny>> {
+    struct SetOnDrop(PyObject);
+
+    impl Drop for SetOnDrop {
+        fn drop(&mut self) {
+            Python::with_gil(|py| self.0.call_method0(py, "set").unwrap());
+        }
+    }
+
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        let _guard = SetOnDrop(dropped);
+        futures::future::pending::<()>().await;
+        Ok(())
+    })
+}
+
+const RUST_FUTURE_CODE: &str = r#"
+import asyncio
+import threading
+
+async def wait(fut):
+    await fut
+
+async def check_disabled(sleep):
+    fut = sleep(0)
+    assert isinstance(fut, asyncio.Future), type(fut)
+    assert await fut is None
+
+async def check_callbacks_scheduled(sleep):
+    # a callback that's scheduled with `call_soon` like the ones of `asyncio.Future` has its
+    # errors reported by the loop, not by the drain that completed the future
+    messages = []
+    event_loop = asyncio.get_running_loop()
+    event_loop.set_exception_handler(lambda _loop, context: messages.append(context["message"]))
+
+    def fail(_fut):
+        raise ValueError("callback")
+
+    fut = sleep(0)
+    assert type(fut).__name__ == "RustFuture", type(fut)
+    fut.add_done_callback(fail)
+    await fut
+    await asyncio.sleep(0)
+
+    event_loop.set_exception_handler(None)
+    assert len(messages) == 1 and messages[0].startswith("Exception in callback"), messages
+
+async def check(sleep, fail_with, pending_until_dropped):
+    fut = sleep(0)
+    assert type(fut).__name__ == "RustFuture", type(fut)
+    assert asyncio.isfuture(fut)
+    assert fut.get_loop() is asyncio.get_running_loop()
+    assert await fut is None
+    assert fut.done() and fut.result() is None
+
+    results = await asyncio.gather(sleep(0), fail_with("gathered"), return_exceptions=True)
+    assert results[0] is None and str(results[1]) == "gathered", results
+
+    try:
+        await fail_with("awaited")
+        raise AssertionError("the error wasn't raised")
+    except ValueError as e:
+        assert str(e) == "awaited"
+
+    dropped = threading.Event()
+    fut = pending_until_dropped(dropped)
+    task = asyncio.ensure_future(wait(fut))
+    await asyncio.sleep(0.1)
+    task.cancel()
+    try:
+        await task
+        raise AssertionError("the task wasn't cancelled")
+    except asyncio.CancelledError:
+        pass
+    assert fut.cancelled()
+    assert await asyncio.get_running_loop().run_in_executor(None, dropped.wait, 5)
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_rust_future() -> PyResult<()> {
+    let test_mod = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            RUST_FUTURE_CODE,
+            "test_rust_future.py",
+            "test_rust_future",
+        )?;
+
+        Ok::<_, PyErr>(test_mod.unbind())
+    })?;
+
+    // conversions return rust futures by default
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod
+                .call_method1(
+                    py,
+                    "check",
+                    (
+                        wrap_pyfunction_bound!(sleep, py)?,
+                        wrap_pyfunction_bound!(fail_with, py)?,
+                        wrap_pyfunction_bound!(pending_until_dropped, py)?,
+                    ),
+                )?
+                .into_bound(py),
+        )
+    })?
+    .await?;
+
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod
+                .call_method1(
+                    py,
+                    "check_callbacks_scheduled",
+                    (wrap_pyfunction_bound!(sleep, py)?,),
+                )?
+                .into_bound(py),
+        )
+    })?
+    .await?;
+
+    // and an `asyncio.Future` once they are disabled, the flag is global, which is why this test
+    // has a binary of its own
+    pyo3_async_runtimes::set_rust_futures(false);
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod
+                .call_method1(py, "check_disabled", (wrap_pyfunction_bound!(sleep, py)?,))?
+                .into_bound(py),
+        )
+    })?
+    .await?;
+
+    Ok(())
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| pyo3_async_runtimes::tokio::run(py, pyo3_async_runtimes::testing::main()))
+}
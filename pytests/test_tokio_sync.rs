@@ -0,0 +1,310 @@
+use pyo3::prelude::*;
+use pyo3_async_runtimes::{
+    err::{ChannelClosed, ChannelLagged},
+    testing::{parse_args, test_harness, Test},
+};
+
+const SYNC_TEST_MOD: &str = r#"
+import asyncio
+
+async def drain(queue, channel_closed):
+    items = [await queue.get()]
+    async for item in queue:
+        items.append(item)
+
+    try:
+        await queue.get()
+    except channel_closed:
+        return items
+
+    raise AssertionError("get didn't raise on a closed channel")
+
+async def fill(queue, n):
+    for i in range(n):
+        await queue.put(i)
+
+    queue.close()
+
+async def collect_lagged(updates, channel_lagged):
+    items = []
+    while True:
+        try:
+            items.append(await updates.__anext__())
+        except channel_lagged as e:
+            items.append(("lagged", e.args[0]))
+        except StopAsyncIteration:
+            return items
+
+async def watch_until(updates, last):
+    values = []
+    async for value in updates:
+        values.append(value)
+        if value == last:
+            return values
+
+def put_until_full(queue):
+    n = 0
+    while not queue.full():
+        queue.put_nowait(n)
+        n += 1
+
+    try:
+        queue.put_nowait(n)
+    except asyncio.QueueFull:
+        return n
+
+    raise AssertionError("put_nowait didn't raise on a full queue")
+"#;
+
+fn test_mod(py: Python) -> PyResult<Bound<PyModule>> {
+    PyModule::from_code_bound(
+        py,
+        SYNC_TEST_MOD,
+        "test_tokio_sync_mod.py",
+        "test_tokio_sync_mod",
+    )
+}
+
+async fn test_receiver_into_py() -> PyResult<()> {
+    let (tx, rx) = tokio::sync::mpsc::channel(1);
+
+    let fut = Python::with_gil(|py| {
+        let queue = pyo3_async_runtimes::tokio::receiver_into_py(py, rx)?;
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod(py)?.call_method1("drain", (queue, py.get_type_bound::<ChannelClosed>()))?,
+        )
+    })?;
+
+    for i in 0..5 {
+        tx.send(i).await.unwrap();
+    }
+    drop(tx);
+
+    let items = fut.await?;
+    Python::with_gil(|py| {
+        assert_eq!(items.extract::<Vec<i32>>(py)?, vec![0, 1, 2, 3, 4]);
+        Ok(())
+    })
+}
+
+async fn test_sender_into_py() -> PyResult<()> {
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<i32>(1);
+
+    let fut = Python::with_gil(|py| {
+        let queue = pyo3_async_runtimes::tokio::sender_into_py(py, tx)?;
+        pyo3_async_runtimes::tokio::into_future(test_mod(py)?.call_method1("fill", (queue, 5))?)
+    })?;
+
+    let mut items = Vec::new();
+    while let Some(item) = rx.recv().await {
+        items.push(item);
+    }
+    fut.await?;
+
+    assert_eq!(items, vec![0, 1, 2, 3, 4]);
+
+    Ok(())
+}
+
+async fn test_sender_into_py_full() -> PyResult<()> {
+    let (tx, _rx) = tokio::sync::mpsc::channel::<i32>(3);
+
+    Python::with_gil(|py| {
+        let queue = pyo3_async_runtimes::tokio::sender_into_py(py, tx)?;
+        let n: usize = test_mod(py)?
+            .call_method1("put_until_full", (queue,))?
+            .extract()?;
+        assert_eq!(n, 3);
+
+        Ok(())
+    })
+}
+
+async fn test_queue_into_receiver() -> PyResult<()> {
+    let (queue, mut rx) = Python::with_gil(|py| -> PyResult<_> {
+        let queue = py.import_bound("asyncio")?.call_method0("Queue")?;
+        let rx = pyo3_async_runtimes::tokio::queue_into_receiver::<i32>(queue.clone(), 1)?;
+        Ok((queue.unbind(), rx))
+    })?;
+
+    Python::with_gil(|py| {
+        for i in 0..3 {
+            queue.bind(py).call_method1("put_nowait", (i,))?;
+        }
+        queue
+            .bind(py)
+            .call_method1("put_nowait", ("not a number",))?;
+        Ok::<_, PyErr>(())
+    })?;
+
+    for i in 0..3 {
+        assert_eq!(rx.recv().await.unwrap()?, i);
+    }
+    assert!(rx.recv().await.unwrap().is_err());
+
+    Ok(())
+}
+
+async fn test_queue_into_sender() -> PyResult<()> {
+    let (queue, tx) = Python::with_gil(|py| -> PyResult<_> {
+        let queue = py.import_bound("asyncio")?.call_method1("Queue", (1,))?;
+        let tx = pyo3_async_runtimes::tokio::queue_into_sender::<i32>(queue.clone(), 1, true)?;
+        Ok((queue.unbind(), tx))
+    })?;
+
+    pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+        for i in 0..5 {
+            tx.send(i).await.unwrap();
+        }
+    });
+
+    for i in 0..5 {
+        let item = Python::with_gil(|py| {
+            pyo3_async_runtimes::tokio::into_future(queue.bind(py).call_method0("get")?)
+        })?
+        .await?;
+        Python::with_gil(|py| {
+            assert_eq!(item.extract::<i32>(py)?, i);
+            Ok::<_, PyErr>(())
+        })?;
+    }
+
+    Ok(())
+}
+
+async fn test_broadcast_into_py() -> PyResult<()> {
+    let (tx, rx) = tokio::sync::broadcast::channel(2);
+
+    // the receiver falls behind by two items before Python starts reading
+    for i in 0..4 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+
+    let items = Python::with_gil(|py| {
+        let updates = pyo3_async_runtimes::tokio::broadcast_into_py(py, rx)?;
+        pyo3_async_runtimes::tokio::into_future(test_mod(py)?.call_method1(
+            "collect_lagged",
+            (updates, py.get_type_bound::<ChannelLagged>()),
+        )?)
+    })?
+    .await?;
+
+    Python::with_gil(|py| {
+        assert_eq!(items.bind(py).repr()?.to_str()?, "[('lagged', 2), 2, 3]");
+        Ok(())
+    })
+}
+
+async fn test_watch_into_py() -> PyResult<()> {
+    for initial in [true, false] {
+        let (tx, rx) = tokio::sync::watch::channel(0);
+
+        let fut = Python::with_gil(|py| {
+            let updates = pyo3_async_runtimes::tokio::watch_into_py(py, rx, initial)?;
+            pyo3_async_runtimes::tokio::into_future(
+                test_mod(py)?.call_method1("watch_until", (updates, 1))?,
+            )
+        })?;
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        tx.send(1).unwrap();
+
+        let values = fut.await?;
+        Python::with_gil(|py| {
+            let expected = if initial { vec![0, 1] } else { vec![1] };
+            assert_eq!(values.extract::<Vec<i32>>(py)?, expected);
+            Ok::<_, PyErr>(())
+        })?;
+    }
+
+    Ok(())
+}
+
+async fn test_oneshot_into_py() -> PyResult<()> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::tokio::oneshot_into_py(
+            py, rx,
+        )?)
+    })?;
+
+    tx.send(42).unwrap();
+    let value = fut.await?;
+    Python::with_gil(|py| {
+        assert_eq!(value.extract::<i32>(py)?, 42);
+        Ok::<_, PyErr>(())
+    })?;
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<i32>();
+
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::tokio::oneshot_into_py(
+            py, rx,
+        )?)
+    })?;
+
+    drop(tx);
+    let err = fut.await.unwrap_err();
+    Python::with_gil(|py| assert!(err.is_instance_of::<ChannelClosed>(py)));
+
+    Ok(())
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    let tests = vec![
+        Test {
+            name: "test_tokio_sync::test_receiver_into_py",
+            test_fn: &|| Box::pin(test_receiver_into_py()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_sync::test_sender_into_py",
+            test_fn: &|| Box::pin(test_sender_into_py()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_sync::test_sender_into_py_full",
+            test_fn: &|| Box::pin(test_sender_into_py_full()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_sync::test_queue_into_receiver",
+            test_fn: &|| Box::pin(test_queue_into_receiver()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_sync::test_queue_into_sender",
+            test_fn: &|| Box::pin(test_queue_into_sender()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_sync::test_broadcast_into_py",
+            test_fn: &|| Box::pin(test_broadcast_into_py()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_sync::test_watch_into_py",
+            test_fn: &|| Box::pin(test_watch_into_py()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_tokio_sync::test_oneshot_into_py",
+            test_fn: &|| Box::pin(test_oneshot_into_py()),
+            serial: false,
+            shared_loop: false,
+        },
+    ];
+
+    Python::with_gil(|py| pyo3_async_runtimes::tokio::run(py, test_harness(tests, parse_args())))
+}
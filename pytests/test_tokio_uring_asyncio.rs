@@ -0,0 +1,48 @@
+mod common;
+mod tokio_asyncio;
+
+use pyo3::prelude::*;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_uring_pinned_future_into_py() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::tokio::pinned_future_into_py(
+            py,
+            || async {
+                // tokio-uring files are !Send, so they could not be used with `future_into_py`
+                let file = tokio_uring::fs::File::open("Cargo.toml").await?;
+                let (res, buf) = file.read_at(vec![0; 9], 0).await;
+                let n = res?;
+                file.close().await?;
+                Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+            },
+        )?)
+    })?;
+
+    let contents = fut.await?;
+    Python::with_gil(|py| assert_eq!(contents.extract::<String>(py).unwrap(), "[package]"));
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_uring_spawn_pinned_panic() -> PyResult<()> {
+    let result = futures::FutureExt::catch_unwind(std::panic::AssertUnwindSafe(
+        pyo3_async_runtimes::tokio::spawn_pinned(|| async {
+            panic!("this panic was intentional!")
+        }),
+    ))
+    .await;
+
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    pyo3_async_runtimes::tokio::init_uring(tokio_uring::builder()).unwrap();
+
+    Python::with_gil(|py| pyo3_async_runtimes::tokio::run(py, pyo3_async_runtimes::testing::main()))
+}
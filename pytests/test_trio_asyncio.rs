@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use pyo3::prelude::*;
+use pyo3_async_runtimes::{
+    testing::{parse_args, test_harness, Test},
+    tokio::TokioRuntime,
+};
+
+const TRIO_TEST_MOD: &str = r#"
+import trio
+
+async def trio_sleep(duration):
+    await trio.sleep(duration)
+    return duration
+
+async def sleep_for_1s(sleep_for):
+    with trio.fail_after(5):
+        await sleep_for(1)
+"#;
+
+#[pyfunction]
+fn sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    let secs = secs.extract()?;
+
+    pyo3_async_runtimes::trio::future_into_py::<TokioRuntime, _, _>(py, async move {
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+        Ok(())
+    })
+}
+
+async fn test_into_future() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            TRIO_TEST_MOD,
+            "test_into_future_mod.py",
+            "test_into_future_mod",
+        )?;
+
+        pyo3_async_runtimes::trio::into_future::<TokioRuntime>(
+            test_mod.call_method1("trio_sleep", (1,))?,
+        )
+    })?;
+
+    let duration = fut.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(duration.extract::<u64>(py)?, 1);
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+async fn test_future_into_py() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let sleeper_mod = PyModule::new_bound(py, "rust_sleeper")?;
+
+        sleeper_mod.add_wrapped(wrap_pyfunction_bound!(sleep))?;
+
+        let test_mod = PyModule::from_code_bound(
+            py,
+            TRIO_TEST_MOD,
+            "test_future_into_py_mod.py",
+            "test_future_into_py_mod",
+        )?;
+
+        pyo3_async_runtimes::trio::into_future::<TokioRuntime>(
+            test_mod.call_method1("sleep_for_1s", (sleeper_mod.getattr("sleep")?,))?,
+        )
+    })?;
+
+    fut.await?;
+
+    Ok(())
+}
+
+async fn test_trio_error() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::trio::into_future::<TokioRuntime>(
+            py.import_bound("trio")?.call_method1("sleep", (-1,))?,
+        )
+    })?;
+
+    match fut.await {
+        Ok(_) => panic!("trio.sleep should reject negative durations"),
+        Err(e) => Python::with_gil(|py| {
+            assert!(e.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            Ok(())
+        }),
+    }
+}
+
+fn main() -> pyo3::PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+
+    let tests = vec![
+        Test {
+            name: "test_trio_asyncio::test_into_future",
+            test_fn: &|| Box::pin(test_into_future()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_trio_asyncio::test_future_into_py",
+            test_fn: &|| Box::pin(test_future_into_py()),
+            serial: false,
+            shared_loop: false,
+        },
+        Test {
+            name: "test_trio_asyncio::test_trio_error",
+            test_fn: &|| Box::pin(test_trio_error()),
+            serial: false,
+            shared_loop: false,
+        },
+    ];
+
+    Python::with_gil(|py| {
+        pyo3_async_runtimes::trio::run::<TokioRuntime, _, _>(py, test_harness(tests, parse_args()))
+    })
+}
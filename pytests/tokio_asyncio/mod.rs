@@ -9,8 +9,9 @@ use pyo3::{
     types::{IntoPyDict, PyType},
     wrap_pyfunction, wrap_pymodule,
 };
-use pyo3_async_runtimes::TaskLocals;
+use pyo3_async_runtimes::{PyOutcome, TaskLocals};
 
+use futures::FutureExt;
 #[cfg(feature = "unstable-streams")]
 use futures::{StreamExt, TryStreamExt};
 
@@ -50,6 +51,152 @@ async fn test_future_into_py() -> PyResult<()> {
     Ok(())
 }
 
+/// Sleep for `secs` seconds and label them
+#[pyo3_async_runtimes::pyfunction_async(runtime = "tokio", signature = (secs, label = None))]
+async fn labelled_sleep(secs: f64, label: Option<String>) -> PyResult<(String, f64)> {
+    tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+    Ok((label.unwrap_or_else(|| "sleep".into()), secs))
+}
+
+#[pyo3_async_runtimes::pyfunction_async(runtime = "tokio")]
+async fn sleep_only(secs: f64) {
+    tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+}
+
+const PYFUNCTION_ASYNC_CODE: &str = r#"
+async def main(m):
+    assert m.labelled_sleep.__doc__.startswith("Sleep for `secs` seconds")
+    assert await m.labelled_sleep(0.1) == ("sleep", 0.1)
+    assert await m.labelled_sleep(0.1, label="nap") == ("nap", 0.1)
+    assert await m.sleep_only(0.1) is None
+
+    try:
+        m.labelled_sleep("soon")
+    except TypeError:
+        pass
+    else:
+        raise AssertionError("arguments are extracted when the function is called")
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_pyfunction_async() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let m = PyModule::new_bound(py, "rust_pyfunction_async")?;
+        m.add_function(wrap_pyfunction!(labelled_sleep, &m)?)?;
+        m.add_function(wrap_pyfunction!(sleep_only, &m)?)?;
+
+        let test_mod = PyModule::from_code_bound(
+            py,
+            PYFUNCTION_ASYNC_CODE,
+            "test_pyfunction_async_mod.py",
+            "test_pyfunction_async_mod",
+        )?;
+
+        pyo3_async_runtimes::tokio::into_future(test_mod.call_method1("main", (m,))?)
+    })?;
+
+    fut.await?;
+
+    Ok(())
+}
+
+#[pyclass(frozen)]
+struct Timer {
+    secs: f64,
+}
+
+#[pyclass]
+struct Counter {
+    count: u32,
+}
+
+#[pyo3_async_runtimes::async_methods(runtime = "tokio")]
+#[pymethods]
+impl Timer {
+    #[new]
+    fn new(secs: f64) -> Self {
+        Self { secs }
+    }
+
+    /// Sleep for the time of the timer and return it
+    async fn sleep(&self) -> PyResult<f64> {
+        tokio::time::sleep(Duration::from_secs_f64(self.secs)).await;
+        Ok(self.secs)
+    }
+
+    #[pyo3(signature = (counter, n = 1))]
+    async fn count(&self, counter: Py<Counter>, n: u32) -> PyResult<u32> {
+        for _ in 0..n {
+            tokio::time::sleep(Duration::from_secs_f64(self.secs)).await;
+            Python::with_gil(|py| counter.borrow_mut(py).count += 1);
+        }
+        Ok(Python::with_gil(|py| counter.borrow(py).count))
+    }
+
+    #[staticmethod]
+    async fn double(value: u32) -> PyResult<u32> {
+        Ok(value * 2)
+    }
+}
+
+#[pyo3_async_runtimes::async_methods(runtime = "tokio")]
+#[pymethods]
+impl Counter {
+    #[new]
+    fn new() -> Self {
+        Self { count: 0 }
+    }
+
+    #[getter]
+    fn count(&self) -> u32 {
+        self.count
+    }
+
+    async fn reset_later(slf: Py<Self>, secs: f64) {
+        tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+        Python::with_gil(|py| slf.borrow_mut(py).count = 0);
+    }
+}
+
+const ASYNC_METHODS_CODE: &str = r#"
+async def main(m):
+    timer = m.Timer(0.05)
+    assert timer.sleep.__doc__ == "Sleep for the time of the timer and return it"
+    assert await timer.sleep() == 0.05
+
+    counter = m.Counter()
+    assert await timer.count(counter) == 1
+    assert await timer.count(counter, n=2) == 3
+    assert counter.count == 3
+
+    assert await counter.reset_later(0.05) is None
+    assert counter.count == 0
+
+    assert await m.Timer.double(21) == 42
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_async_methods() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let m = PyModule::new_bound(py, "rust_async_methods")?;
+        m.add_class::<Timer>()?;
+        m.add_class::<Counter>()?;
+
+        let test_mod = PyModule::from_code_bound(
+            py,
+            ASYNC_METHODS_CODE,
+            "test_async_methods_mod.py",
+            "test_async_methods_mod",
+        )?;
+
+        pyo3_async_runtimes::tokio::into_future(test_mod.call_method1("main", (m,))?)
+    })?;
+
+    fut.await?;
+
+    Ok(())
+}
+
 #[pyo3_async_runtimes::tokio::test]
 async fn test_async_sleep() -> PyResult<()> {
     let asyncio = Python::with_gil(|py| {
@@ -82,14 +229,1102 @@ async fn test_into_future() -> PyResult<()> {
     .await
 }
 
-#[pyo3_async_runtimes::tokio::test]
-async fn test_other_awaitables() -> PyResult<()> {
-    common::test_other_awaitables(Python::with_gil(|py| {
-        pyo3_async_runtimes::tokio::get_current_loop(py)
-            .unwrap()
-            .into()
-    }))
-    .await
+#[pyo3_async_runtimes::tokio::test]
+async fn test_other_awaitables() -> PyResult<()> {
+    common::test_other_awaitables(Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::get_current_loop(py)
+            .unwrap()
+            .into()
+    }))
+    .await
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_into_future_asyncio_future() -> PyResult<()> {
+    let (py_fut, fut) = Python::with_gil(|py| -> PyResult<_> {
+        let event_loop = pyo3_async_runtimes::tokio::get_current_loop(py)?;
+        let py_fut = event_loop.call_method0("create_future")?;
+        let fut = pyo3_async_runtimes::tokio::into_future(py_fut.clone())?;
+
+        Ok((PyObject::from(py_fut), fut))
+    })?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let py_fut = py_fut.bind(py);
+        py_fut
+            .call_method0("get_loop")?
+            .call_method1("call_soon_threadsafe", (py_fut.getattr("set_result")?, 42))?;
+        Ok(())
+    })?;
+
+    let value = fut.await?;
+    Python::with_gil(|py| {
+        assert_eq!(value.extract::<i32>(py)?, 42);
+
+        // futures that are already done are resolved without the event loop
+        let done =
+            pyo3_async_runtimes::tokio::get_current_loop(py)?.call_method0("create_future")?;
+        done.call_method1("set_result", (43,))?;
+        let value = pyo3_async_runtimes::tokio::into_future(done)?
+            .now_or_never()
+            .expect("the future should be ready");
+        assert_eq!(value?.extract::<i32>(py)?, 43);
+
+        Ok(())
+    })
+}
+
+const DROP_INTO_FUTURE_CODE: &str = r#"
+import asyncio
+
+async def record_cancel(events):
+    try:
+        await asyncio.sleep(3600)
+    except asyncio.CancelledError:
+        events.append("cancelled")
+        raise
+
+async def record_done(events):
+    await asyncio.sleep(0.1)
+    events.append("done")
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_into_future_drop() -> PyResult<()> {
+    let (events, fut, detached) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            DROP_INTO_FUTURE_CODE,
+            "test_into_future_drop/test_mod.py",
+            "test_mod",
+        )?;
+        let events = pyo3::types::PyList::empty_bound(py);
+
+        let fut = pyo3_async_runtimes::tokio::into_future(
+            test_mod.call_method1("record_cancel", (&events,))?,
+        )?;
+        let detached = pyo3_async_runtimes::tokio::into_future(
+            test_mod.call_method1("record_done", (&events,))?,
+        )?
+        .detach();
+
+        Ok((PyObject::from(events), fut, detached))
+    })?;
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    drop(fut);
+    drop(detached);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    Python::with_gil(|py| {
+        // the dropped future cancelled its task, the detached one kept running
+        let mut events = events.extract::<Vec<String>>(py)?;
+        events.sort();
+        assert_eq!(events, vec!["cancelled", "done"]);
+        Ok(())
+    })
+}
+
+const POLLED_CODE: &str = r#"
+import asyncio
+
+async def yield_many(n):
+    # more bare yields than a poll takes at once
+    for _ in range(n):
+        await asyncio.sleep(0)
+    return n
+
+async def wait_for(fut):
+    return await fut
+
+async def fail():
+    await asyncio.sleep(0)
+    raise ValueError("this error was intentional!")
+
+class BadYield:
+    def __await__(self):
+        yield 1
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_into_future_polled() -> PyResult<()> {
+    let polled_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(PyModule::from_code_bound(py, POLLED_CODE, "polled.py", "polled")?.into())
+    })?;
+
+    let yield_many = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future_polled(
+            polled_mod
+                .call_method1(py, "yield_many", (100,))?
+                .into_bound(py),
+        )
+    })?;
+    let result = yield_many.await?;
+    Python::with_gil(|py| assert_eq!(result.extract::<usize>(py).unwrap(), 100));
+
+    // the future belongs to the event loop, which sets its result on its own thread
+    let wait_for = Python::with_gil(|py| {
+        let event_loop = pyo3_async_runtimes::tokio::get_current_loop(py)?;
+        let fut = event_loop.call_method0("create_future")?;
+        event_loop.call_method1("call_soon_threadsafe", (fut.getattr("set_result")?, 42))?;
+
+        pyo3_async_runtimes::tokio::into_future_polled(
+            polled_mod
+                .call_method1(py, "wait_for", (fut,))?
+                .into_bound(py),
+        )
+    })?;
+    let result = wait_for.await?;
+    Python::with_gil(|py| assert_eq!(result.extract::<i32>(py).unwrap(), 42));
+
+    let fail = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future_polled(
+            polled_mod.call_method0(py, "fail")?.into_bound(py),
+        )
+    })?;
+    let err = fail.await.unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+    });
+
+    let bad_yield = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future_polled(
+            polled_mod.call_method0(py, "BadYield")?.into_bound(py),
+        )
+    })?;
+    let err = bad_yield.await.unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyRuntimeError>(py));
+    });
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_into_future_typed() -> PyResult<()> {
+    let value = Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        pyo3_async_runtimes::tokio::into_future_typed::<String>(
+            asyncio.call_method1("sleep", (0.1, "done"))?,
+        )
+    })?
+    .await?;
+    assert_eq!(value, "done");
+
+    let err = Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        pyo3_async_runtimes::tokio::into_future_typed::<i32>(
+            asyncio.call_method1("sleep", (0.1, "not a number"))?,
+        )
+    })?
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+    });
+
+    Ok(())
+}
+
+const OUTCOME_CODE: &str = r#"
+import asyncio
+
+async def cancelled():
+    raise asyncio.CancelledError()
+
+async def raises():
+    raise ValueError("invalid value")
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_into_future_result() -> PyResult<()> {
+    let (completed, cancelled, raised) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            OUTCOME_CODE,
+            "test_into_future_result/test_mod.py",
+            "test_mod",
+        )?;
+        let asyncio = py.import_bound("asyncio")?;
+
+        Ok((
+            pyo3_async_runtimes::tokio::into_future_result(
+                asyncio.call_method1("sleep", (0.1, "done"))?,
+            )?,
+            pyo3_async_runtimes::tokio::into_future_result(test_mod.call_method0("cancelled")?)?,
+            pyo3_async_runtimes::tokio::into_future_result(test_mod.call_method0("raises")?)?,
+        ))
+    })?;
+
+    match completed.await {
+        PyOutcome::Completed(value) => {
+            Python::with_gil(|py| assert_eq!(value.extract::<String>(py).unwrap(), "done"))
+        }
+        outcome => panic!("expected a value, got {:?}", outcome),
+    }
+
+    let cancelled = cancelled.await;
+    assert!(cancelled.is_cancelled());
+    let err = cancelled.into_result().unwrap_err();
+    Python::with_gil(|py| -> PyResult<()> {
+        let cancelled_error = py.import_bound("asyncio")?.getattr("CancelledError")?;
+        assert!(err.is_instance_bound(py, &cancelled_error));
+        Ok(())
+    })?;
+
+    match raised.await {
+        PyOutcome::Raised(e) => Python::with_gil(|py| {
+            assert!(e.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        }),
+        outcome => panic!("expected an exception, got {:?}", outcome),
+    }
+
+    Ok(())
+}
+
+const EXECUTOR_CODE: &str = r#"
+import concurrent.futures
+import contextvars
+import threading
+
+cx = contextvars.ContextVar("cx", default=None)
+
+executor = concurrent.futures.ThreadPoolExecutor(max_workers=1, thread_name_prefix="custom")
+
+def describe(suffix):
+    return f"{threading.current_thread().name} {cx.get()}{suffix}"
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_run_in_executor() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            EXECUTOR_CODE,
+            "test_run_in_executor/test_mod.py",
+            "test_mod",
+        )?;
+
+        // set the variable in a context of its own, like a Python task would
+        let context = py.import_bound("contextvars")?.call_method0("Context")?;
+        context.call_method1("run", (test_mod.getattr("cx")?.getattr("set")?, "foobar"))?;
+
+        let locals = pyo3_async_runtimes::tokio::get_current_locals(py)?
+            .with_context(context)
+            .with_executor(test_mod.getattr("executor")?);
+
+        pyo3_async_runtimes::run_in_executor_with_locals(
+            &locals,
+            test_mod.getattr("describe")?,
+            ("!",),
+        )
+    })?;
+
+    let description = fut.await?;
+    Python::with_gil(|py| {
+        let description = description.extract::<String>(py)?;
+        assert!(description.starts_with("custom"));
+        assert!(description.ends_with(" foobar!"));
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_cancellable_future_into_py() -> PyResult<()> {
+    let (cleaned_up_tx, cleaned_up_rx) = futures::channel::oneshot::channel();
+
+    let py_fut = Python::with_gil(|py| -> PyResult<PyObject> {
+        let py_fut =
+            pyo3_async_runtimes::tokio::cancellable_future_into_py(py, move |handle| async move {
+                assert!(!handle.is_cancelled());
+                handle.cancelled().await;
+                assert!(handle.is_cancelled());
+
+                // the future keeps running after the cancellation so it can clean up
+                cleaned_up_tx.send(()).unwrap();
+                Ok(())
+            })?;
+
+        Ok(py_fut.into())
+    })?;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    Python::with_gil(|py| -> PyResult<()> {
+        let py_fut = py_fut.bind(py);
+        py_fut
+            .call_method0("get_loop")?
+            .call_method1("call_soon_threadsafe", (py_fut.getattr("cancel")?,))?;
+        Ok(())
+    })?;
+
+    tokio::time::timeout(Duration::from_secs(1), cleaned_up_rx)
+        .await
+        .expect("the cancel handle should have fired")
+        .unwrap();
+
+    Ok(())
+}
+
+const GATHER_CODE: &str = r#"
+import asyncio
+
+async def gather(futs):
+    futs[0].cancel()
+    return await asyncio.gather(*futs, return_exceptions=True)
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_batched_completions() -> PyResult<()> {
+    const COUNT: usize = 200;
+
+    // the futures complete together, so their results are set in a few batches
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(100);
+    let fut = Python::with_gil(|py| {
+        let futs = (0..COUNT)
+            .map(|i| {
+                pyo3_async_runtimes::tokio::future_into_py(py, async move {
+                    tokio::time::sleep_until(deadline).await;
+                    Ok(i)
+                })
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let gather_mod = PyModule::from_code_bound(py, GATHER_CODE, "gather.py", "gather")?;
+        pyo3_async_runtimes::tokio::into_future(gather_mod.call_method1("gather", (futs,))?)
+    })?;
+
+    let results = fut.await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let results = results.bind(py);
+        assert_eq!(results.len()?, COUNT);
+        // the result of the cancelled future is dropped instead of failing the batch
+        assert!(results
+            .get_item(0)?
+            .is_instance(&py.import_bound("asyncio")?.getattr("CancelledError")?)?);
+        for i in 1..COUNT {
+            assert_eq!(results.get_item(i)?.extract::<usize>()?, i);
+        }
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+const FAILING_HANDLER_CODE: &str = r#"
+import asyncio
+import time
+
+def call_exception_handler(context):
+    raise RuntimeError("the exception handler failed")
+
+async def gather(futs, done):
+    loop = asyncio.get_running_loop()
+    # the handlers that are set with `set_exception_handler` can't fail it
+    loop.call_exception_handler = call_exception_handler
+    try:
+        # setting the results of these futures again fails in the drain
+        for fut in done:
+            fut.set_result(None)
+        # block the loop until every result is queued, so they are set by the same drain
+        time.sleep(0.2)
+        return await asyncio.wait_for(asyncio.gather(*futs), 5)
+    finally:
+        del loop.call_exception_handler
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_batched_completions_failing_handler() -> PyResult<()> {
+    const COUNT: usize = 50;
+
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(100);
+    let fut = Python::with_gil(|py| {
+        let futs = (0..COUNT)
+            .map(|i| {
+                pyo3_async_runtimes::tokio::future_into_py(py, async move {
+                    tokio::time::sleep_until(deadline).await;
+                    Ok(i)
+                })
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        let done = futs.iter().step_by(10).cloned().collect::<Vec<_>>();
+
+        let gather_mod =
+            PyModule::from_code_bound(py, FAILING_HANDLER_CODE, "gather.py", "gather")?;
+        pyo3_async_runtimes::tokio::into_future(gather_mod.call_method1("gather", (futs, done))?)
+    })?;
+
+    let results = fut.await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let results = results.bind(py);
+        assert_eq!(results.len()?, COUNT);
+        // the results after a failure that the handler couldn't report are still set
+        for i in 0..COUNT {
+            if i % 10 != 0 {
+                assert_eq!(results.get_item(i)?.extract::<usize>()?, i);
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_spawn_py() -> PyResult<()> {
+    let task = Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        pyo3_async_runtimes::tokio::spawn_py::<i32>(asyncio.call_method1("sleep", (0.1, 42))?)
+    })?;
+
+    assert_eq!(task.await?, 42);
+
+    let task = Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        pyo3_async_runtimes::tokio::spawn_py::<i32>(asyncio.call_method1("sleep", (3600,))?)
+    })?;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(!task.done(py)?);
+        task.cancel(py)
+    })?;
+
+    let err = task.await.unwrap_err();
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(err.is_instance_bound(py, &py.import_bound("asyncio")?.getattr("CancelledError")?));
+        Ok(())
+    })
+}
+
+const TASK_GROUP_CODE: &str = r#"
+import asyncio
+
+async def sleep_then(events, name):
+    try:
+        await asyncio.sleep(3600)
+    except asyncio.CancelledError:
+        events.append(name)
+        raise
+
+async def fail_on_cancel(msg):
+    try:
+        await asyncio.sleep(3600)
+    except asyncio.CancelledError:
+        raise ValueError(msg)
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_task_group() -> PyResult<()> {
+    let (events, mut group) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            TASK_GROUP_CODE,
+            "test_task_group/test_mod.py",
+            "test_mod",
+        )?;
+        let events = pyo3::types::PyList::empty_bound(py);
+
+        let mut group = pyo3_async_runtimes::tokio::TaskGroup::new(py)?;
+        group.spawn_py(test_mod.call_method1("sleep_then", (&events, "python"))?)?;
+        group.spawn_py(test_mod.call_method1("fail_on_cancel", ("python failed",))?)?;
+
+        Ok((PyObject::from(events), group))
+    })?;
+
+    let rust_cancelled = Arc::new(Mutex::new(true));
+    let rust_cancelled2 = rust_cancelled.clone();
+    group.spawn(async move {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        *rust_cancelled2.lock().unwrap() = false;
+        Ok(())
+    });
+    group.spawn(async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Err(pyo3::exceptions::PyRuntimeError::new_err("rust failed"))
+    });
+
+    let err = group.join().await.unwrap_err();
+
+    // the sibling that was still sleeping got cancelled on both sides
+    assert!(*rust_cancelled.lock().unwrap());
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(events.extract::<Vec<String>>(py)?, vec!["python"]);
+
+        // errors raised while cancelling are reported too, all of them together on Python 3.11+
+        if py.version_info() >= (3, 11) {
+            // all of the errors are an `Exception`, so `except*` can handle the group
+            let exception_group = py.import_bound("builtins")?.getattr("ExceptionGroup")?;
+            assert!(err.value_bound(py).is_instance(&exception_group)?);
+
+            let mut messages = err
+                .value_bound(py)
+                .getattr("exceptions")?
+                .iter()?
+                .map(|e| e?.str()?.extract::<String>())
+                .collect::<PyResult<Vec<_>>>()?;
+            messages.sort();
+            assert_eq!(messages, vec!["python failed", "rust failed"]);
+        }
+
+        Ok(())
+    })?;
+
+    let mut group = Python::with_gil(pyo3_async_runtimes::tokio::TaskGroup::new)?;
+    group.spawn(async { Ok(()) });
+    group.join().await
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_nested_spawn_locals() -> PyResult<()> {
+    let event_loop = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::get_current_locals(py)
+            .map(|locals| locals.event_loop(py).unbind())
+    })?;
+
+    let nested_loop = pyo3_async_runtimes::tokio::spawn(async {
+        pyo3_async_runtimes::tokio::spawn(async {
+            Python::with_gil(|py| {
+                pyo3_async_runtimes::tokio::get_current_locals(py)
+                    .map(|locals| locals.event_loop(py).unbind())
+            })
+        })
+        .await
+        .unwrap()
+    })
+    .await
+    .unwrap()?;
+
+    Python::with_gil(|py| {
+        assert!(nested_loop.bind(py).is(event_loop.bind(py)));
+    });
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+fn test_spawn_with_locals(event_loop: PyObject) -> PyResult<()> {
+    // blocking tests run on a thread without task locals, so they are captured from the thread
+    let handle = Python::with_gil(|py| {
+        pyo3_async_runtimes::set_thread_event_loop(Some(event_loop.bind(py).clone()));
+        let handle = pyo3_async_runtimes::tokio::spawn_with_locals(py, async {
+            Python::with_gil(|py| {
+                pyo3_async_runtimes::tokio::get_current_loop(py).map(Bound::unbind)
+            })
+        });
+        pyo3_async_runtimes::set_thread_event_loop(None);
+
+        handle
+    })?;
+
+    let spawned_loop = pyo3_async_runtimes::tokio::get_runtime()
+        .block_on(handle)
+        .unwrap()?;
+    Python::with_gil(|py| {
+        assert!(spawned_loop.bind(py).is(event_loop.bind(py)));
+    });
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_with_event_loop() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        let task_loop = pyo3_async_runtimes::tokio::get_current_loop(py)?;
+        let outer_loop = asyncio.call_method0("new_event_loop")?;
+        let inner_loop = asyncio.call_method0("new_event_loop")?;
+
+        pyo3_async_runtimes::with_event_loop(outer_loop.clone(), || -> PyResult<()> {
+            // the loop takes precedence over the task locals
+            assert!(pyo3_async_runtimes::tokio::get_current_loop(py)?.is(&outer_loop));
+
+            pyo3_async_runtimes::with_event_loop(inner_loop.clone(), || -> PyResult<()> {
+                let locals = pyo3_async_runtimes::tokio::get_current_locals(py)?;
+                assert!(locals.event_loop(py).is(&inner_loop));
+                Ok(())
+            })?;
+
+            assert!(pyo3_async_runtimes::tokio::get_current_loop(py)?.is(&outer_loop));
+            Ok(())
+        })?;
+
+        assert!(pyo3_async_runtimes::tokio::get_current_loop(py)?.is(&task_loop));
+
+        outer_loop.call_method0("close")?;
+        inner_loop.call_method0("close")?;
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test(flavor = "multi_thread", worker_threads = 2, timeout = "30s")]
+async fn test_configured_runtime() -> PyResult<()> {
+    // the test runs on a runtime of its own, scoped to the locals of the harness
+    Python::with_gil(pyo3_async_runtimes::tokio::get_current_locals)?;
+    let worker = tokio::spawn(async { std::thread::current().id() });
+    assert_ne!(worker.await.unwrap(), std::thread::current().id());
+
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            py.import_bound("asyncio")?.call_method1("sleep", (0.1,))?,
+        )
+    })?;
+    fut.await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test(flavor = "multi_thread", worker_threads = 1, timeout = "30s")]
+fn test_configured_runtime_blocking() -> PyResult<()> {
+    // blocking tests run within the context of their runtime, whose worker drives the timer
+    tokio::runtime::Handle::current().block_on(tokio::time::sleep(Duration::from_millis(10)));
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test(raises = "TypeError")]
+async fn test_raises() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(
+            py.import_bound("asyncio")?
+                .call_method1("sleep", ("not a delay",))?,
+        )
+    })?;
+    fut.await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test(raises = "asyncio.TimeoutError", timeout = "30s")]
+async fn test_raises_module_exception() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        pyo3_async_runtimes::tokio::into_future(
+            asyncio.call_method1("wait_for", (asyncio.call_method1("sleep", (3600,))?, 0.01))?,
+        )
+    })?;
+    fut.await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_raises_mismatch() -> PyResult<()> {
+    let err = pyo3_async_runtimes::testing::expect_raises(
+        async { Err(pyo3::exceptions::PyTypeError::new_err("wrong type")) },
+        "ValueError",
+    )
+    .await
+    .unwrap_err();
+
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyAssertionError>(py));
+        assert_eq!(
+            err.value_bound(py).to_string(),
+            "expected the test to raise ValueError, but it raised TypeError: wrong type"
+        );
+        assert!(err
+            .cause(py)
+            .unwrap()
+            .is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+    });
+
+    let err = pyo3_async_runtimes::testing::expect_raises(async { Ok(()) }, "ValueError")
+        .await
+        .unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyAssertionError>(py));
+    });
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test(should_panic)]
+async fn test_should_panic() -> PyResult<()> {
+    panic!("this test is expected to panic");
+}
+
+#[pyo3_async_runtimes::tokio::test(should_panic = "expected message")]
+fn test_should_panic_blocking() -> PyResult<()> {
+    panic!("panicked with the expected message");
+}
+
+#[pyo3_async_runtimes::tokio::test(should_panic = "converted")]
+async fn test_should_panic_converted() -> PyResult<()> {
+    // the panic reaches the test as a `RustPanic` raised by the awaitable
+    let fut = Python::with_gil(|py| {
+        let awaitable = pyo3_async_runtimes::tokio::future_into_py::<_, ()>(py, async {
+            panic!("converted future panicked")
+        })?;
+        pyo3_async_runtimes::tokio::into_future(awaitable)
+    })?;
+    fut.await?;
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_should_panic_mismatch() -> PyResult<()> {
+    let err = pyo3_async_runtimes::testing::expect_panic(
+        async { panic!("some other message") },
+        Some("expected message"),
+    )
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyAssertionError>(py));
+    });
+
+    Ok(())
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_fixtures(
+    locals: TaskLocals,
+    event_loop: pyo3_async_runtimes::PyEventLoop,
+) -> PyResult<()> {
+    Python::with_gil(|py| {
+        assert!(locals.event_loop(py).is(event_loop.bind(py)));
+        assert!(event_loop.is_running(py)?);
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test]
+fn test_temp_event_loop_fixture(
+    event_loop: pyo3_async_runtimes::testing::TempEventLoop,
+) -> PyResult<()> {
+    Python::with_gil(|py| {
+        assert!(!event_loop.is_running(py)?);
+
+        let sleep = py
+            .import_bound("asyncio")?
+            .call_method1("sleep", (0.1, "done"))?;
+        let result = event_loop
+            .bind(py)
+            .call_method1("run_until_complete", (sleep,))?;
+        assert_eq!(result.extract::<String>()?, "done");
+        Ok(())
+    })
+}
+
+/// A fixture that takes a while to set up
+struct Answer(u32);
+
+impl pyo3_async_runtimes::testing::Fixture for Answer {
+    fn setup() -> std::pin::Pin<Box<dyn std::future::Future<Output = PyResult<Self>> + Send>> {
+        Box::pin(async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(Answer(42))
+        })
+    }
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_custom_fixture(answer: Answer, event_loop: PyObject) -> PyResult<()> {
+    assert_eq!(answer.0, 42);
+    Python::with_gil(|py| {
+        assert!(event_loop
+            .bind(py)
+            .call_method0("is_running")?
+            .extract::<bool>()?);
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_timeout() -> PyResult<()> {
+    fn is_timeout(py: Python, err: &PyErr) -> PyResult<bool> {
+        Ok(err.is_instance_bound(py, &py.import_bound("asyncio")?.getattr("TimeoutError")?))
+    }
+
+    // a Python awaitable awaited from Rust
+    let err = Python::with_gil(|py| {
+        let asyncio = py.import_bound("asyncio")?;
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::timeout(
+            Duration::from_millis(100),
+            asyncio.call_method1("sleep", (3600,))?,
+        )?)
+    })?
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(is_timeout(py, &err)?);
+        Ok(())
+    })?;
+
+    // a Rust future awaited from Python
+    let err = Python::with_gil(|py| {
+        let fut = pyo3_async_runtimes::tokio::future_into_py(py, async {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            Ok(())
+        })?;
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::timeout(
+            Duration::from_millis(100),
+            fut,
+        )?)
+    })?
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(is_timeout(py, &err)?);
+        Ok(())
+    })?;
+
+    // a Rust future on the tokio timer
+    let err = pyo3_async_runtimes::tokio::timeout(Duration::from_millis(100), async {
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        Ok(())
+    })
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(is_timeout(py, &err)?);
+        Ok(())
+    })?;
+
+    let value =
+        pyo3_async_runtimes::tokio::timeout(Duration::from_secs(1), async { Ok(42) }).await?;
+    assert_eq!(value, 42);
+
+    Ok(())
+}
+
+const CANCELLATION_TOKEN_CODE: &str = r#"
+import asyncio
+
+async def wait_for_cancel(token):
+    assert not token.is_cancelled()
+    await token.cancelled()
+    return token.is_cancelled()
+
+async def cancel_later(token):
+    await asyncio.sleep(0.1)
+    token.cancel()
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_cancellation_token() -> PyResult<()> {
+    let token = pyo3_async_runtimes::CancellationToken::new();
+
+    // Rust cancels, Python waits
+    let waiter = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            CANCELLATION_TOKEN_CODE,
+            "test_cancellation_token/test_mod.py",
+            "test_mod",
+        )?;
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod.call_method1("wait_for_cancel", (token.clone(),))?,
+        )
+    })?;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    token.cancel();
+
+    let cancelled = waiter.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(cancelled.extract::<bool>(py)?);
+        Ok(())
+    })?;
+
+    // Python cancels, Rust waits
+    let token = pyo3_async_runtimes::CancellationToken::new();
+    let canceller = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            CANCELLATION_TOKEN_CODE,
+            "test_cancellation_token/test_mod.py",
+            "test_mod",
+        )?;
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod.call_method1("cancel_later", (token.clone(),))?,
+        )
+    })?;
+
+    tokio::time::timeout(Duration::from_secs(1), token.cancelled())
+        .await
+        .expect("the token should have been cancelled from Python");
+    assert!(token.is_cancelled());
+    canceller.await?;
+
+    Ok(())
+}
+
+const SHIELD_CODE: &str = r#"
+import asyncio
+
+async def cancel_then_wait(shielded, handle):
+    await asyncio.sleep(0.05)
+    shielded.cancel()
+
+    try:
+        await shielded
+    except asyncio.CancelledError:
+        pass
+    else:
+        raise AssertionError("the shielded awaitable wasn't cancelled")
+
+    return await handle
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_shielded_future_into_py() -> PyResult<()> {
+    let value = Python::with_gil(|py| {
+        let test_mod =
+            PyModule::from_code_bound(py, SHIELD_CODE, "test_shield/test_mod.py", "test_mod")?;
+        let (shielded, handle) = pyo3_async_runtimes::tokio::shielded_future_into_py(py, async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok(7)
+        })?;
+
+        pyo3_async_runtimes::tokio::into_future(
+            test_mod.call_method1("cancel_then_wait", (shielded, handle))?,
+        )
+    })?
+    .await?;
+
+    // the Rust future finished even though its awaiter was cancelled
+    Python::with_gil(|py| {
+        assert_eq!(value.extract::<i32>(py)?, 7);
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_join_handle_into_py() -> PyResult<()> {
+    let handle = pyo3_async_runtimes::tokio::get_runtime().spawn(async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Ok(7)
+    });
+
+    let value = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::tokio::join_handle_into_py(
+            py, handle,
+        )?)
+    })?
+    .await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(value.extract::<i32>(py)?, 7);
+        Ok(())
+    })?;
+
+    let handle: tokio::task::JoinHandle<PyResult<()>> = pyo3_async_runtimes::tokio::get_runtime()
+        .spawn(async { panic!("this panic was intentional!") });
+
+    let err = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::into_future(pyo3_async_runtimes::tokio::join_handle_into_py(
+            py, handle,
+        )?)
+    })?
+    .await
+    .unwrap_err();
+    Python::with_gil(|py| {
+        assert!(err.is_instance_of::<pyo3_async_runtimes::err::RustPanic>(py));
+    });
+
+    Ok(())
+}
+
+const ASYNC_CONTEXT_CODE: &str = r#"
+class Resource:
+    def __init__(self):
+        self.events = []
+
+    async def __aenter__(self):
+        self.events.append("enter")
+        return "value"
+
+    async def __aexit__(self, ty, value, tb):
+        self.events.append(("exit", None if ty is None else ty.__name__))
+        return ty is KeyError
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_async_context() -> PyResult<()> {
+    use pyo3_async_runtimes::{tokio::TokioRuntime, PyAsyncContext};
+
+    let resource = Python::with_gil(|py| -> PyResult<PyObject> {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            ASYNC_CONTEXT_CODE,
+            "test_async_context_mod.py",
+            "test_async_context_mod",
+        )?;
+        Ok(test_mod.call_method0("Resource")?.unbind())
+    })?;
+
+    let enter =
+        || Python::with_gil(|py| PyAsyncContext::enter::<TokioRuntime>(resource.bind(py).clone()));
+
+    let guard = enter()?.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(guard.value().extract::<String>(py)?, "value");
+        Ok(())
+    })?;
+    guard.close().await?;
+
+    let guard = enter()?.await?;
+    let suppressed = guard
+        .close_with_error(&pyo3::exceptions::PyKeyError::new_err("missing"))
+        .await?;
+    assert!(suppressed);
+
+    // dropping the guard schedules __aexit__ without waiting for it
+    drop(enter()?.await?);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(
+            resource.getattr(py, "events")?.bind(py).repr()?.to_str()?,
+            "['enter', ('exit', None), 'enter', ('exit', 'KeyError'), 'enter', ('exit', None)]"
+        );
+        Ok(())
+    })
+}
+
+const ASYNC_CALLBACK_CODE: &str = r#"
+import asyncio
+
+async def call_twice(callback):
+    coro = callback(1, b=2)
+    assert asyncio.iscoroutine(coro)
+
+    return [await coro, await callback(3, b=4)]
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_py_async_callback() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let callback = pyo3_async_runtimes::tokio::py_async_callback(py, |args, kwargs| {
+            let a: i32 = args.get_item(0)?.extract()?;
+            let b: i32 = kwargs
+                .and_then(|kwargs| kwargs.get_item("b").transpose())
+                .transpose()?
+                .map_or(Ok(0), |b| b.extract())?;
+
+            Ok(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(a + b)
+            })
+        })?;
+
+        let test_mod = PyModule::from_code_bound(
+            py,
+            ASYNC_CALLBACK_CODE,
+            "test_py_async_callback_mod.py",
+            "test_py_async_callback_mod",
+        )?;
+
+        pyo3_async_runtimes::tokio::into_future(test_mod.call_method1("call_twice", (callback,))?)
+    })?;
+
+    let results = fut.await?;
+    Python::with_gil(|py| -> PyResult<()> {
+        assert_eq!(results.extract::<Vec<i32>>(py)?, vec![3, 7]);
+        Ok(())
+    })
 }
 
 #[pyo3_async_runtimes::tokio::test]
@@ -119,6 +1354,131 @@ fn test_local_future_into_py(event_loop: PyObject) -> PyResult<()> {
     })
 }
 
+#[pyo3_async_runtimes::tokio::test]
+fn test_scope_local(event_loop: PyObject) -> PyResult<()> {
+    use pyo3_async_runtimes::{generic, tokio::TokioRuntime};
+
+    tokio::task::LocalSet::new().block_on(pyo3_async_runtimes::tokio::get_runtime(), async {
+        let locals = Python::with_gil(|py| TaskLocals::new(event_loop.bind(py).clone()));
+        let non_send = Rc::new(());
+
+        let scoped_loop = generic::scope_local::<TokioRuntime, _, _>(locals, async move {
+            tokio::task::yield_now().await;
+            drop(non_send);
+
+            Python::with_gil(|py| {
+                pyo3_async_runtimes::tokio::get_current_loop(py).map(Bound::unbind)
+            })
+        })
+        .await?;
+
+        Python::with_gil(|py| {
+            assert!(scoped_loop.bind(py).is(event_loop.bind(py)));
+        });
+
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test]
+fn test_thread_event_loop(event_loop: PyObject) -> PyResult<()> {
+    // blocking tests run on a thread that has no running loop
+    Python::with_gil(|py| {
+        assert!(pyo3_async_runtimes::get_thread_event_loop(py).is_none());
+        pyo3_async_runtimes::set_thread_event_loop(Some(event_loop.bind(py).clone()));
+
+        let fut = pyo3_async_runtimes::tokio::future_into_py(py, async { Ok(42) });
+        pyo3_async_runtimes::set_thread_event_loop(None);
+
+        assert!(fut?.call_method0("get_loop")?.is(event_loop.bind(py)));
+        assert!(pyo3_async_runtimes::get_thread_event_loop(py).is_none());
+
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test]
+fn test_default_locals(event_loop: PyObject) -> PyResult<()> {
+    Python::with_gil(|py| {
+        // blocking tests run on a thread that has no running loop
+        assert!(pyo3_async_runtimes::tokio::get_current_loop(py).is_err());
+
+        let guard =
+            pyo3_async_runtimes::push_default_locals(TaskLocals::new(event_loop.bind(py).clone()));
+        let fut = pyo3_async_runtimes::tokio::future_into_py(py, async { Ok(42) });
+
+        // defaults pushed later take precedence until they are popped
+        let other_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+        let _other_guard =
+            pyo3_async_runtimes::push_default_locals(TaskLocals::new(other_loop.clone()));
+        assert!(pyo3_async_runtimes::tokio::get_current_loop(py)?.is(&other_loop));
+        let popped = pyo3_async_runtimes::pop_default_locals().unwrap();
+        assert!(popped.event_loop(py).is(&other_loop));
+        assert!(pyo3_async_runtimes::tokio::get_current_loop(py)?.is(event_loop.bind(py)));
+        other_loop.call_method0("close")?;
+
+        drop(guard);
+
+        assert!(fut?.call_method0("get_loop")?.is(event_loop.bind(py)));
+        assert!(pyo3_async_runtimes::tokio::get_current_loop(py).is_err());
+
+        Ok(())
+    })
+}
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_debug_current_locals() -> PyResult<()> {
+    use pyo3_async_runtimes::debug::{self, LocalsSource};
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let current = debug::current_locals(py)?;
+        assert_eq!(current.source(), LocalsSource::Task);
+        assert_eq!(current.runtime(), Some("tokio"));
+        assert!(current
+            .event_loop(py)
+            .is(&pyo3_async_runtimes::tokio::get_current_loop(py)?));
+        Ok(())
+    })
+}
+
+const EVENT_LOOP_CODE: &str = r#"
+import contextvars
+
+cx = contextvars.ContextVar("cx", default=None)
+
+async def get_cx():
+    return cx.get()
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_py_event_loop() -> PyResult<()> {
+    let fut = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            EVENT_LOOP_CODE,
+            "test_py_event_loop/test_mod.py",
+            "test_mod",
+        )?;
+
+        let locals = pyo3_async_runtimes::tokio::get_current_locals(py)?;
+        let event_loop = locals.py_event_loop();
+        assert!(!event_loop.is_closed(py)?);
+        assert!(event_loop.time(py)? > 0.0);
+
+        let context = py.import_bound("contextvars")?.call_method0("Context")?;
+        context.call_method1("run", (test_mod.getattr("cx")?.getattr("set")?, "foobar"))?;
+
+        let task = event_loop.create_task(py, &test_mod.call_method0("get_cx")?, &context)?;
+        pyo3_async_runtimes::into_future_with_locals(&locals, task)
+    })?;
+
+    let value = fut.await?;
+    Python::with_gil(|py| {
+        assert_eq!(value.extract::<String>(py)?, "foobar");
+        Ok(())
+    })
+}
+
 #[pyo3_async_runtimes::tokio::test]
 async fn test_panic() -> PyResult<()> {
     let fut = Python::with_gil(|py| -> PyResult<_> {
@@ -286,6 +1646,126 @@ fn test_multiple_asyncio_run() -> PyResult<()> {
     })
 }
 
+const SWITCH_LOOPS_CODE: &str = r#"
+import asyncio
+import threading
+
+async def on_loop(sleep):
+    fut = sleep(0)
+    assert fut.get_loop() is asyncio.get_running_loop()
+    await fut
+    return asyncio.get_running_loop()
+
+def switch_loops(sleep, running_loop):
+    loops = [asyncio.new_event_loop() for _ in range(3)]
+    try:
+        # back and forth, so that each loop runs again after another one ran on the thread
+        for event_loop in loops + loops[::-1]:
+            assert event_loop.run_until_complete(on_loop(sleep)) is event_loop
+
+            # the loop that stopped isn't the running loop of the thread anymore
+            try:
+                running_loop()
+            except RuntimeError:
+                pass
+            else:
+                raise AssertionError("the stopped loop is still the running loop")
+    finally:
+        for event_loop in loops:
+            event_loop.close()
+
+def switch_loops_in_thread(sleep, running_loop):
+    errors = []
+
+    def run():
+        try:
+            switch_loops(sleep, running_loop)
+        except BaseException as e:
+            errors.append(e)
+
+    thread = threading.Thread(target=run)
+    thread.start()
+    thread.join()
+    if errors:
+        raise errors[0]
+"#;
+
+#[pyfunction]
+fn running_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    pyo3_async_runtimes::get_running_loop(py)
+}
+
+#[pyo3_async_runtimes::tokio::test]
+fn test_switch_loops() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let switch_mod =
+            PyModule::from_code_bound(py, SWITCH_LOOPS_CODE, "switch_loops.py", "switch_loops")?;
+        let args = (
+            wrap_pyfunction_bound!(sleep, py)?,
+            wrap_pyfunction_bound!(running_loop, py)?,
+        );
+
+        switch_mod.call_method1("switch_loops", args.clone())?;
+        switch_mod.call_method1("switch_loops_in_thread", args)?;
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn block_on_sleep(py: Python) -> PyResult<()> {
+    pyo3_async_runtimes::tokio::run(py, async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        Ok(())
+    })
+}
+
+const DEADLOCK_CODE: &str = r#"
+async def block_on_loop(block_on_sleep):
+    try:
+        block_on_sleep()
+    except RuntimeError as e:
+        return str(e)
+"#;
+
+#[pyo3_async_runtimes::tokio::test]
+async fn test_run_deadlock_guard() -> PyResult<()> {
+    let ran = Arc::new(Mutex::new(false));
+    let ran2 = ran.clone();
+
+    // from a task of the runtime
+    let err = Python::with_gil(|py| {
+        pyo3_async_runtimes::tokio::run(py, async move {
+            *ran2.lock().unwrap() = true;
+            Ok(())
+        })
+    })
+    .unwrap_err();
+    assert!(err.to_string().contains("would deadlock"), "{}", err);
+    assert!(!*ran.lock().unwrap());
+
+    // from the thread of the running event loop
+    let message = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            DEADLOCK_CODE,
+            "test_run_deadlock_guard/test_mod.py",
+            "test_mod",
+        )?;
+
+        pyo3_async_runtimes::tokio::into_future(test_mod.call_method1(
+            "block_on_loop",
+            (wrap_pyfunction_bound!(block_on_sleep, py)?,),
+        )?)
+    })?
+    .await?;
+
+    Python::with_gil(|py| -> PyResult<()> {
+        let message = message.extract::<String>(py)?;
+        assert!(message.contains("would deadlock"), "{}", message);
+        Ok(())
+    })
+}
+
 #[pymodule]
 fn cvars_mod(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     #![allow(deprecated)]
@@ -301,7 +1781,22 @@ fn cvars_mod(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
         })
     }
 
+    #[pyfunction]
+    fn sync_callback(py: Python, callback: PyObject) -> PyResult<Bound<PyAny>> {
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            Python::with_gil(|py| {
+                pyo3_async_runtimes::tokio::get_current_locals(py)?.call_in_context(
+                    py,
+                    callback.bind(py),
+                    (),
+                )?;
+                Ok(())
+            })
+        })
+    }
+
     m.add_function(wrap_pyfunction!(async_callback, m)?)?;
+    m.add_function(wrap_pyfunction!(sync_callback, m)?)?;
 
     Ok(())
 }
@@ -364,15 +1859,343 @@ async fn test_async_gen_v2() -> PyResult<()> {
     Ok(())
 }
 
+#[cfg(feature = "unstable-streams")]
+const ASYNC_ITERABLE_CODE: &str = r#"
+import asyncio
+
+class Counter:
+    """An async iterable that isn't a generator, like most library response objects"""
+
+    def __init__(self, n):
+        self.n = n
+
+    def __aiter__(self):
+        return CounterIter(self.n)
+
+class CounterIter:
+    def __init__(self, n):
+        self.i = 0
+        self.n = n
+
+    def __aiter__(self):
+        return self
+
+    async def __anext__(self):
+        if self.i == self.n:
+            raise StopAsyncIteration
+
+        await asyncio.sleep(0.01)
+        self.i += 1
+        return self.i - 1
+"#;
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::tokio::test]
+async fn test_async_iterable() -> PyResult<()> {
+    let (stream_v1, stream_v2) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            ASYNC_ITERABLE_CODE,
+            "test_async_iterable_mod.py",
+            "test_async_iterable_mod",
+        )?;
+
+        Ok((
+            pyo3_async_runtimes::tokio::into_stream_v1(test_mod.call_method1("Counter", (10,))?)?,
+            pyo3_async_runtimes::tokio::into_stream_v2(test_mod.call_method1("Counter", (10,))?)?,
+        ))
+    })?;
+
+    let vals = stream_v1
+        .map(|item| Python::with_gil(|py| -> PyResult<i32> { item?.bind(py).extract() }))
+        .try_collect::<Vec<i32>>()
+        .await?;
+    assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+
+    let vals = stream_v2
+        .map(|item| Python::with_gil(|py| -> PyResult<i32> { item.bind(py).extract() }))
+        .try_collect::<Vec<i32>>()
+        .await?;
+    assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+
+    Ok(())
+}
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::tokio::test]
+async fn test_async_gen_typed() -> PyResult<()> {
+    let stream = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            TOKIO_TEST_MOD,
+            "test_rust_coroutine/tokio_test_mod.py",
+            "tokio_test_mod",
+        )?;
+
+        pyo3_async_runtimes::tokio::into_stream_typed::<i32>(test_mod.call_method0("gen")?)
+    })?;
+
+    let vals = stream.try_collect::<Vec<i32>>().await?;
+
+    assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+
+    Ok(())
+}
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::tokio::test]
+async fn test_async_gen_typed_extract_error() -> PyResult<()> {
+    let stream = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            TOKIO_TEST_MOD,
+            "test_rust_coroutine/tokio_test_mod.py",
+            "tokio_test_mod",
+        )?;
+
+        pyo3_async_runtimes::tokio::into_stream_typed::<String>(test_mod.call_method0("gen")?)
+    })?;
+
+    let items = stream.collect::<Vec<PyResult<String>>>().await;
+
+    // the stream keeps going after an item fails to extract
+    assert_eq!(items.len(), 10);
+    assert!(items.iter().all(|item| item.is_err()));
+
+    Ok(())
+}
+
+#[cfg(feature = "unstable-streams")]
+const PREFETCH_CODE: &str = r#"
+import asyncio
+
+class Pages:
+    def __init__(self, count):
+        self.next_page = 0
+        self.count = count
+        self.in_flight = 0
+        self.max_in_flight = 0
+
+    def __aiter__(self):
+        return self
+
+    async def __anext__(self):
+        page = self.next_page
+        if page >= self.count:
+            raise StopAsyncIteration
+        self.next_page += 1
+
+        self.in_flight += 1
+        self.max_in_flight = max(self.max_in_flight, self.in_flight)
+        # later pages finish first, but the stream keeps the request order
+        await asyncio.sleep(0.05 * (self.count - page))
+        self.in_flight -= 1
+        return page
+"#;
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::tokio::test]
+async fn test_into_stream_prefetch() -> PyResult<()> {
+    let (pages, stream) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            PREFETCH_CODE,
+            "test_into_stream_prefetch/test_mod.py",
+            "test_mod",
+        )?;
+        let pages = test_mod.call_method1("Pages", (10,))?;
+        let stream = pyo3_async_runtimes::tokio::into_stream_prefetch(pages.clone(), 4)?;
+
+        Ok((PyObject::from(pages), stream))
+    })?;
+
+    let vals = stream
+        .map(|item| Python::with_gil(|py| item.extract::<i32>(py)))
+        .try_collect::<Vec<i32>>()
+        .await?;
+
+    assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+    Python::with_gil(|py| {
+        assert_eq!(pages.getattr(py, "max_in_flight")?.extract::<i32>(py)?, 4);
+
+        // at least one call has to be in flight
+        assert!(pyo3_async_runtimes::tokio::into_stream_prefetch(pages.into_bound(py), 0).is_err());
+        Ok(())
+    })
+}
+
+#[cfg(feature = "unstable-streams")]
+const FORWARD_INTO_SINK_CODE: &str = r#"
+async def gen(errors):
+    for item in [0, 1, "two", 3, 4, 5]:
+        try:
+            yield item
+        except Exception as e:
+            errors.append(type(e).__name__)
+"#;
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::tokio::test]
+async fn test_forward_into_sink() -> PyResult<()> {
+    let accepted = Arc::new(Mutex::new(Vec::new()));
+    let sink = futures::sink::unfold(accepted.clone(), |accepted, item: i32| async move {
+        if item == 3 {
+            return Err(pyo3::exceptions::PyValueError::new_err("rejected 3"));
+        }
+
+        accepted.lock().unwrap().push(item);
+        Ok(accepted)
+    });
+
+    let (errors, forward) = Python::with_gil(|py| -> PyResult<_> {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            FORWARD_INTO_SINK_CODE,
+            "test_forward_into_sink_mod.py",
+            "test_forward_into_sink_mod",
+        )?;
+        let errors = pyo3::types::PyList::empty_bound(py);
+
+        let forward = pyo3_async_runtimes::tokio::forward_into_sink(
+            test_mod.call_method1("gen", (errors.clone(),))?,
+            sink,
+            0,
+        )?;
+
+        Ok((errors.unbind(), forward))
+    })?;
+
+    let err = forward.await.unwrap_err();
+
+    // the generator handled the item that didn't extract and kept going, but the rejected item
+    // ended the forwarding
+    assert_eq!(*accepted.lock().unwrap(), vec![0, 1]);
+    Python::with_gil(|py| -> PyResult<()> {
+        assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+        assert_eq!(
+            errors.bind(py).extract::<Vec<String>>()?,
+            vec!["TypeError", "ValueError"]
+        );
+        Ok(())
+    })
+}
+
+#[cfg(feature = "unstable-streams")]
+const STREAM_INTO_PY_CODE: &str = r#"
+async def take(it, n):
+    items = []
+    async for i in it:
+        items.append(i)
+        if len(items) == n:
+            break
+
+    await it.aclose()
+    return items
+
+async def collect_errors(it):
+    items = []
+    while True:
+        try:
+            items.append(await it.__anext__())
+        except ValueError as e:
+            items.append(str(e))
+        except StopAsyncIteration:
+            return items
+"#;
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::tokio::test]
+async fn test_stream_into_py() -> PyResult<()> {
+    let produced = Arc::new(Mutex::new(0));
+    let produced_tx = produced.clone();
+
+    let stream = futures::stream::iter(0..10).then(move |i| {
+        let produced = produced_tx.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            *produced.lock().unwrap() += 1;
+            PyResult::Ok(i)
+        }
+    });
+
+    let fut = Python::with_gil(|py| {
+        let test_mod = PyModule::from_code_bound(
+            py,
+            STREAM_INTO_PY_CODE,
+            "test_stream_into_py_mod.py",
+            "test_stream_into_py_mod",
+        )?;
+
+        pyo3_async_runtimes::tokio::into_future(test_mod.call_method1(
+            "take",
+            (pyo3_async_runtimes::tokio::stream_into_py(py, stream)?, 3),
+        )?)
+    })?;
+
+    let vals = fut.await?;
+    let vals: Vec<i32> = Python::with_gil(|py| vals.extract(py))?;
+
+    assert_eq!(vals, vec![0, 1, 2]);
+    // the rest of the stream is never polled since nobody asked for it
+    assert_eq!(*produced.lock().unwrap(), 3);
+
+    Ok(())
+}
+
+#[cfg(feature = "unstable-streams")]
+#[pyo3_async_runtimes::tokio::test]
+async fn test_try_stream_into_py() -> PyResult<()> {
+    use pyo3_async_runtimes::generic::OnError;
+
+    for (on_error, expected) in [
+        (OnError::Continue, "[1, 'bad item', 3]"),
+        (OnError::Abort, "[1, 'bad item']"),
+    ] {
+        let stream = futures::stream::iter(vec![
+            Ok(1),
+            Err(pyo3::exceptions::PyValueError::new_err("bad item")),
+            Ok(3),
+        ]);
+
+        let fut = Python::with_gil(|py| {
+            let test_mod = PyModule::from_code_bound(
+                py,
+                STREAM_INTO_PY_CODE,
+                "test_try_stream_into_py_mod.py",
+                "test_try_stream_into_py_mod",
+            )?;
+
+            pyo3_async_runtimes::tokio::into_future(test_mod.call_method1(
+                "collect_errors",
+                (pyo3_async_runtimes::tokio::try_stream_into_py(
+                    py, stream, on_error,
+                )?,),
+            )?)
+        })?;
+
+        let items = fut.await?;
+        Python::with_gil(|py| -> PyResult<()> {
+            assert_eq!(items.bind(py).repr()?.to_str()?, expected);
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
 const CONTEXTVARS_CODE: &str = r#"
 cx = contextvars.ContextVar("cx")
 
 async def contextvars_test():
     assert cx.get() == "foobar"
 
+def contextvars_sync_test():
+    assert cx.get() == "foobar"
+
 async def main():
     cx.set("foobar")
     await cvars_mod.async_callback(contextvars_test)
+    await cvars_mod.sync_callback(contextvars_sync_test)
 
 asyncio.run(main())
 "#;
@@ -43,3 +43,30 @@ pub(super) fn test_main() {
     .map_err(|e| Python::with_gil(|py| dump_err(py, e)))
     .unwrap();
 }
+
+pub(super) fn test_run_forever() {
+    Python::with_gil(|py| {
+        let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+
+        let event_loop_hdl = PyObject::from(event_loop.clone());
+        let stop = PyObject::from(event_loop.getattr("stop")?);
+
+        pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+
+            Python::with_gil(|py| {
+                event_loop_hdl
+                    .call_method1(py, "call_soon_threadsafe", (stop,))
+                    .map_err(|e| dump_err(py, e))
+                    .unwrap();
+            })
+        });
+
+        pyo3_async_runtimes::run_forever(&event_loop)?;
+        event_loop.call_method0("close")?;
+
+        Ok(())
+    })
+    .map_err(|e| Python::with_gil(|py| dump_err(py, e)))
+    .unwrap();
+}
@@ -0,0 +1,387 @@
+//! <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>anyio</code></span> Conversions that work under whichever async library is running
+//!
+//! Libraries built on [anyio](https://anyio.readthedocs.io) can run on `asyncio` or on trio, and
+//! the caller decides which. The conversions in this module use
+//! [sniffio](https://sniffio.readthedocs.io) to detect the library of the calling task and produce
+//! the matching awaitable:
+//!
+//! - On `asyncio`, they behave exactly like the [`generic`](crate::generic) conversions.
+//! - On trio, Rust futures are awaited through a `trio.Event` that the Rust runtime sets through
+//!   the run's `TrioToken`, and trio awaitables are run as trio system tasks. No `asyncio` loop is
+//!   needed, so trio-asyncio doesn't have to be installed.
+//!
+//! The task locals of a Rust future that was converted on trio hold the `TrioToken` in place of the
+//! event loop, so [`into_future`] called from such a future runs the awaitable on the same trio run.
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["anyio"]
+//! ```
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    channel::oneshot,
+    future::{AbortHandle, Abortable},
+    FutureExt,
+};
+use once_cell::sync::OnceCell;
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+
+use crate::{
+    dump_err,
+    err::abort_error,
+    generic::{self, abort_reason, ContextExt, Runtime},
+    TaskLocals,
+};
+
+const TRIO_GLUE: &str = r#"
+import trio
+
+async def wait(event, result, cancel):
+    try:
+        await event.wait()
+    except BaseException:
+        cancel()
+        raise
+
+    return result()
+
+async def forward(awaitable, done):
+    try:
+        value = await awaitable
+    except Exception as e:
+        done(False, e)
+    except BaseException as e:
+        done(False, e)
+        raise
+    else:
+        done(True, value)
+
+def spawn(awaitable, done, context):
+    trio.lowlevel.spawn_system_task(forward, awaitable, done, context=context)
+"#;
+
+static SNIFFIO: OnceCell<PyObject> = OnceCell::new();
+static TRIO_GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
+
+fn sniffio(py: Python) -> PyResult<&Bound<PyAny>> {
+    SNIFFIO
+        .get_or_try_init(|| Ok(py.import_bound("sniffio")?.into()))
+        .map(|sniffio| sniffio.bind(py))
+}
+
+fn trio_glue(py: Python) -> PyResult<&Bound<PyAny>> {
+    TRIO_GLUE_MOD
+        .get_or_try_init(|| -> PyResult<PyObject> {
+            Ok(PyModule::from_code_bound(
+                py,
+                TRIO_GLUE,
+                "pyo3_asyncio/pyo3_asyncio_trio_glue.py",
+                "pyo3_asyncio_trio_glue",
+            )?
+            .into())
+        })
+        .map(|glue| glue.bind(py))
+}
+
+/// Get the name of the async library running the current Python task, e.g. `"asyncio"` or
+/// `"trio"`
+///
+/// This is `sniffio.current_async_library()`, so it fails with
+/// `sniffio.AsyncLibraryNotFoundError` when called outside of an async task.
+pub fn current_async_library(py: Python) -> PyResult<String> {
+    sniffio(py)?
+        .call_method0("current_async_library")?
+        .extract()
+}
+
+/// Check whether the task locals refer to a trio run rather than an `asyncio` event loop
+fn is_trio(py: Python, locals: &TaskLocals) -> PyResult<bool> {
+    // trio can't be the running library if it hasn't been imported
+    let trio = py
+        .import_bound("sys")?
+        .getattr("modules")?
+        .call_method1("get", ("trio",))?;
+
+    if trio.is_none() {
+        return Ok(false);
+    }
+
+    locals
+        .event_loop(py)
+        .is_instance(&trio.getattr("lowlevel")?.getattr("TrioToken")?)
+}
+
+/// Either copy the task locals from the current Rust task OR get the event loop (or `TrioToken`)
+/// and contextvars of the current Python task
+pub fn get_current_locals<R>(py: Python) -> PyResult<TaskLocals>
+where
+    R: ContextExt,
+{
+    if let Some(locals) = R::get_task_locals() {
+        return Ok(locals);
+    }
+
+    match current_async_library(py)?.as_str() {
+        "asyncio" => TaskLocals::with_running_loop(py)?.copy_context_if_enabled(py),
+        "trio" => TaskLocals::new(
+            py.import_bound("trio")?
+                .getattr("lowlevel")?
+                .call_method0("current_trio_token")?,
+        )
+        .copy_context_if_enabled(py),
+        library => Err(PyRuntimeError::new_err(format!(
+            "unsupported async library `{}`",
+            library
+        ))),
+    }
+}
+
+/// Takes the result of the Rust future once the trio event is set
+#[pyclass]
+struct TrioResult {
+    result: Arc<Mutex<Option<PyResult<PyObject>>>>,
+}
+
+#[pymethods]
+impl TrioResult {
+    fn __call__(&self) -> PyResult<PyObject> {
+        self.result
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(|| Err(PyRuntimeError::new_err("rust future has no result")))
+    }
+}
+
+/// Aborts the Rust future when the trio task waiting on it is cancelled
+#[pyclass]
+struct TrioCancel {
+    handle: AbortHandle,
+}
+
+#[pymethods]
+impl TrioCancel {
+    fn __call__(&self) {
+        self.handle.abort();
+    }
+}
+
+#[allow(unused_must_use)]
+fn trio_future_into_py<R, F, T>(py: Python, locals: TaskLocals, fut: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let event = py.import_bound("trio")?.call_method0("Event")?;
+    let result = Arc::new(Mutex::new(None));
+    let (handle, registration) = AbortHandle::new_pair();
+
+    let awaitable = trio_glue(py)?.call_method1(
+        "wait",
+        (
+            event.clone(),
+            TrioResult {
+                result: result.clone(),
+            },
+            TrioCancel { handle },
+        ),
+    )?;
+
+    let token = locals.event_loop(py).unbind();
+    let event = event.unbind();
+
+    R::spawn(async move {
+        let locals2 = locals.clone();
+        let result_tx = result.clone();
+
+        let joined = R::spawn(async move {
+            let output = R::scope(locals2, Abortable::new(fut, registration)).await;
+
+            // the trio task is gone if the future was aborted
+            if let Ok(output) = output {
+                Python::with_gil(|py| {
+                    *result_tx.lock().unwrap() = Some(output.map(|val| val.into_py(py)));
+                });
+            }
+        })
+        .await;
+
+        if let Err(e) = joined {
+            *result.lock().unwrap() = Some(Err(abort_error(abort_reason(e))));
+        }
+
+        if result.lock().unwrap().is_none() {
+            return;
+        }
+
+        Python::with_gil(|py| {
+            let _ = event
+                .bind(py)
+                .getattr("set")
+                .and_then(|set| token.bind(py).call_method1("run_sync_soon", (set,)))
+                .map_err(dump_err(py));
+        });
+    });
+
+    Ok(awaitable)
+}
+
+/// Convert a Rust Future into an awaitable for the async library in `locals`
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future, see [`get_current_locals`]
+/// * `fut` - The Rust future to be converted
+pub fn future_into_py_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    if is_trio(py, &locals)? {
+        trio_future_into_py::<R, F, T>(py, locals, fut)
+    } else {
+        generic::future_into_py_with_locals::<R, F, T>(py, locals, fut)
+    }
+}
+
+/// Convert a Rust Future into an awaitable for the async library of the calling task
+///
+/// The returned object is an `asyncio.Future` when called from `asyncio` and a trio awaitable when
+/// called from trio.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function for asyncio and trio alike
+/// # #[cfg(feature = "tokio-runtime")]
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::anyio::future_into_py::<pyo3_async_runtimes::tokio::TokioRuntime, _, _>(
+///         py,
+///         async move {
+///             tokio::time::sleep(Duration::from_secs(secs)).await;
+///             Ok(())
+///         },
+///     )
+/// }
+/// ```
+pub fn future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// Sends the outcome of a trio system task to the Rust future awaiting it
+#[pyclass]
+struct TrioDone {
+    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+}
+
+#[pymethods]
+impl TrioDone {
+    fn __call__(&mut self, ok: bool, value: Bound<PyAny>) {
+        let result = if ok {
+            Ok(value.unbind())
+        } else {
+            Err(PyErr::from_value_bound(value))
+        };
+
+        if let Some(tx) = self.tx.take() {
+            // the receiver is gone if the Rust future was dropped
+            let _ = tx.send(result);
+        }
+    }
+}
+
+/// Convert an awaitable for the async library in `locals` into a Rust Future
+///
+/// # Arguments
+/// * `locals` - The event loop (or `TrioToken`) and context to be used for the awaitable
+/// * `awaitable` - The Python awaitable to be converted
+pub fn into_future_with_locals(
+    locals: &TaskLocals,
+    awaitable: Bound<PyAny>,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+    let py = awaitable.py();
+
+    if !is_trio(py, locals)? {
+        return Ok(crate::into_future_with_locals(locals, awaitable)?.boxed());
+    }
+
+    let (tx, rx) = oneshot::channel();
+
+    locals.event_loop(py).call_method1(
+        "run_sync_soon",
+        (
+            trio_glue(py)?.getattr("spawn")?,
+            awaitable,
+            TrioDone { tx: Some(tx) },
+            locals.context(py),
+        ),
+    )?;
+
+    Ok(async move {
+        match rx.await {
+            Ok(item) => item,
+            Err(_) => Err(PyRuntimeError::new_err(
+                "trio run finished before the awaitable completed",
+            )),
+        }
+    }
+    .boxed())
+}
+
+/// Convert an awaitable into a Rust Future, running it on the async library of the calling task
+///
+/// # Arguments
+/// * `awaitable` - The Python awaitable to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// async fn anyio_sleep() -> PyResult<()> {
+///     let fut = Python::with_gil(|py| {
+///         pyo3_async_runtimes::anyio::into_future::<pyo3_async_runtimes::tokio::TokioRuntime>(
+///             py.import_bound("anyio")?.call_method1("sleep", (1,))?,
+///         )
+///     })?;
+///
+///     fut.await?;
+///     Ok(())
+/// }
+/// ```
+pub fn into_future<R>(
+    awaitable: Bound<PyAny>,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    R: ContextExt,
+{
+    into_future_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
@@ -16,19 +16,35 @@
 use async_std::task;
 use futures::FutureExt;
 use pyo3::prelude::*;
-use std::{any::Any, cell::RefCell, future::Future, panic::AssertUnwindSafe, pin::Pin};
+use std::{any::Any, future::Future, panic::AssertUnwindSafe, pin::Pin};
 
 use crate::{
     generic::{self, ContextExt, JoinError, LocalContextExt, Runtime, SpawnLocalExt},
-    TaskLocals,
+    scoped::{self, Scoped},
+    PyFuture, PyOutcomeFuture, PyTask, RunnerOptions, TaskLocals,
 };
 
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
 /// re-exports for macros
 #[cfg(feature = "attributes")]
 pub mod re_exports {
+    /// re-export block_on for the `thread_name` argument of the `#[test]` macro
+    pub use async_std::task::block_on;
     /// re-export spawn_blocking for use in `#[test]` macro without external dependency
     pub use async_std::task::spawn_blocking;
+
+    /// Configure the global executor for the arguments of the `#[main]` macro
+    ///
+    /// async-std reads the configuration from the environment when the executor starts, so this
+    /// has to be called before anything is spawned.
+    pub fn configure_executor(worker_threads: Option<usize>, thread_name: Option<&str>) {
+        if let Some(worker_threads) = worker_threads {
+            std::env::set_var("ASYNC_STD_THREAD_COUNT", worker_threads.to_string());
+        }
+        if let Some(thread_name) = thread_name {
+            std::env::set_var("ASYNC_STD_THREAD_NAME", thread_name);
+        }
+    }
 }
 
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span> Provides the boilerplate for the `async-std` runtime and runs an async fn as main
@@ -41,7 +57,8 @@ pub use pyo3_async_runtimes_macros::async_std_main as main;
 #[cfg(all(feature = "attributes", feature = "testing"))]
 pub use pyo3_async_runtimes_macros::async_std_test as test;
 
-struct AsyncStdJoinErr(Box<dyn Any + Send + 'static>);
+/// The error of a task of [`AsyncStdRuntime`] that panicked
+pub struct AsyncStdJoinErr(Box<dyn Any + Send + 'static>);
 
 impl JoinError for AsyncStdJoinErr {
     fn is_panic(&self) -> bool {
@@ -52,11 +69,12 @@ impl JoinError for AsyncStdJoinErr {
     }
 }
 
-async_std::task_local! {
-    static TASK_LOCALS: RefCell<Option<TaskLocals>> = RefCell::new(None);
-}
-
-struct AsyncStdRuntime;
+/// The async-std runtime as a [`generic`] runtime
+///
+/// Conversions that are generic over the Rust runtime, such as the tests of
+/// [`#[pyo3_async_runtimes::test_all_backends]`](crate::test_all_backends), can run on async-std
+/// by naming this type.
+pub struct AsyncStdRuntime;
 
 impl Runtime for AsyncStdRuntime {
     type JoinError = AsyncStdJoinErr;
@@ -80,22 +98,11 @@ impl ContextExt for AsyncStdRuntime {
     where
         F: Future<Output = R> + Send + 'static,
     {
-        let old = TASK_LOCALS.with(|c| c.replace(Some(locals)));
-        Box::pin(async move {
-            let result = fut.await;
-            TASK_LOCALS.with(|c| c.replace(old));
-            result
-        })
+        Box::pin(Scoped::new(locals, fut))
     }
 
     fn get_task_locals() -> Option<TaskLocals> {
-        TASK_LOCALS
-            .try_with(|c| {
-                c.borrow()
-                    .as_ref()
-                    .map(|locals| Python::with_gil(|py| locals.clone_ref(py)))
-            })
-            .unwrap_or_default()
+        scoped::get_task_locals()
     }
 }
 
@@ -116,12 +123,7 @@ impl LocalContextExt for AsyncStdRuntime {
     where
         F: Future<Output = R> + 'static,
     {
-        let old = TASK_LOCALS.with(|c| c.replace(Some(locals)));
-        Box::pin(async move {
-            let result = fut.await;
-            TASK_LOCALS.with(|c| c.replace(old));
-            result
-        })
+        Box::pin(Scoped::new(locals, fut))
     }
 }
 
@@ -143,9 +145,11 @@ where
 
 /// Get the current event loop from either Python or Rust async task local context
 ///
-/// This function first checks if the runtime has a task-local reference to the Python event loop.
-/// If not, it calls [`get_running_loop`](`crate::get_running_loop`) to get the event loop
-/// associated with the current OS thread.
+/// Within [`with_event_loop`](`crate::with_event_loop`), this returns the loop passed to it.
+/// Otherwise, it first checks if the runtime has a task-local reference to the Python event loop,
+/// and then whether a loop was set for the current OS thread with
+/// [`set_thread_event_loop`](`crate::set_thread_event_loop`). If not, it calls
+/// [`get_running_loop`](`crate::get_running_loop`) to get the event loop running on the thread.
 pub fn get_current_loop(py: Python) -> PyResult<Bound<PyAny>> {
     generic::get_current_loop::<AsyncStdRuntime>(py)
 }
@@ -229,6 +233,41 @@ where
     generic::run::<AsyncStdRuntime, F, T>(py, fut)
 }
 
+/// Run the given Future on a new `asyncio.Runner` (Python 3.11+)
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `options` - The options for the runner
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         let options = pyo3_async_runtimes::RunnerOptions::new().with_debug(true);
+///
+///         pyo3_async_runtimes::async_std::run_with_runner(py, options, async move {
+///             async_std::task::sleep(Duration::from_secs(1)).await;
+///             Ok(())
+///         })
+///     })
+/// }
+/// ```
+pub fn run_with_runner<F, T>(py: Python, options: RunnerOptions, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_with_runner::<AsyncStdRuntime, F, T>(py, options, fut)
+}
+
 /// Convert a Rust Future into a Python awaitable
 ///
 /// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
@@ -330,6 +369,73 @@ where
     generic::future_into_py::<AsyncStdRuntime, _, T>(py, fut)
 }
 
+/// Convert a Rust Future that fails with a Rust error into a Python awaitable
+///
+/// The error can be any error that converts into a `Box<dyn Error + Send + Sync>`. See
+/// [`generic::try_future_into_py_with_locals`] for how the error is raised, and
+/// [`anyhow_future_into_py`] for futures that fail with an `anyhow::Error`.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+pub fn try_future_into_py<F, T, E>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    generic::try_future_into_py::<AsyncStdRuntime, _, T, E>(py, fut)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>anyhow</code></span> Convert a Rust Future that fails with an `anyhow::Error` into a Python awaitable
+///
+/// See [`generic::anyhow_future_into_py_with_locals`] for how the error is raised.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+#[cfg(feature = "anyhow")]
+pub fn anyhow_future_into_py<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = anyhow::Result<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::anyhow_future_into_py::<AsyncStdRuntime, _, T>(py, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable that doesn't need an event loop
+///
+/// See [`generic::future_into_awaitable`] for how the returned object is awaited and cancelled.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Sleep function that can be awaited under any coroutine scheduler
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::async_std::future_into_awaitable(py, async move {
+///         async_std::task::sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_awaitable<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_awaitable::<AsyncStdRuntime, _, T>(py, fut)
+}
+
 /// Convert a `!Send` Rust Future into a Python awaitable
 ///
 /// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
@@ -519,12 +625,49 @@ where
 ///     Ok(())
 /// }
 /// ```
-pub fn into_future(
-    awaitable: Bound<PyAny>,
-) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+pub fn into_future(awaitable: Bound<PyAny>) -> PyResult<PyFuture> {
     generic::into_future::<AsyncStdRuntime>(awaitable)
 }
 
+/// Convert a Python `awaitable` into a Rust Future resolving to how it finished
+///
+/// The future resolves to a [`PyOutcome`](crate::PyOutcome) that tells a cancelled awaitable apart
+/// from one that raised. See
+/// [`into_future_result_with_locals`](crate::into_future_result_with_locals) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_result(awaitable: Bound<PyAny>) -> PyResult<PyOutcomeFuture> {
+    generic::into_future_result::<AsyncStdRuntime>(awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future resolving to its result extracted into `T`
+///
+/// The result is extracted on the event loop once the awaitable is done, so no extra
+/// `Python::with_gil` is needed after awaiting the future. See
+/// [`into_future_typed_with_locals`](crate::into_future_typed_with_locals) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_typed<T>(awaitable: Bound<PyAny>) -> PyResult<PyFuture<T>>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    generic::into_future_typed::<AsyncStdRuntime, T>(awaitable)
+}
+
+/// Spawn a Python `awaitable` as a task and get a handle to it
+///
+/// The returned [`PyTask`] can cancel the task, check whether it's done and be
+/// awaited for the result of the task, extracted into `T`. See
+/// [`spawn_py_with_locals`](crate::spawn_py_with_locals) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be spawned
+pub fn spawn_py<T>(awaitable: Bound<PyAny>) -> PyResult<PyTask<T>> {
+    generic::spawn_py::<AsyncStdRuntime, T>(awaitable)
+}
+
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
 ///
 /// **This API is marked as unstable** and is only available when the
@@ -532,7 +675,7 @@ pub fn into_future(
 /// stability guarantees, and could be changed or removed at any time.
 ///
 /// # Arguments
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```
@@ -589,7 +732,7 @@ pub fn into_stream_v1(
 ///
 /// # Arguments
 /// * `locals` - The current task locals
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```
@@ -650,7 +793,7 @@ pub fn into_stream_with_locals_v1(
 ///
 /// # Arguments
 /// * `locals` - The current task locals
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```
@@ -710,7 +853,7 @@ pub fn into_stream_with_locals_v2(
 /// stability guarantees, and could be changed or removed at any time.
 ///
 /// # Arguments
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```
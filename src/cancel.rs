@@ -0,0 +1,156 @@
+//! A cancellation token that's shared between Rust and Python
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+use futures::future;
+use pyo3::prelude::*;
+
+use crate::{completions::set_result, get_running_loop, TaskLocals};
+
+#[derive(Default)]
+struct State {
+    cancelled: bool,
+    wakers: Vec<Waker>,
+    // the `asyncio.Future`s handed out by `cancelled()`, with the event loops they belong to
+    futures: Vec<(TaskLocals, PyObject)>,
+}
+
+/// Signal that lets Python and Rust code cancel work cooperatively
+///
+/// The token is a Python class, so the same token can be handed to Python code and to Rust code
+/// running on any runtime. Either side can cancel it, and both sides can check or wait for the
+/// cancellation:
+///
+/// - In Python, `token.cancel()` cancels the token, `token.is_cancelled()` checks it and
+///   `await token.cancelled()` waits until it's cancelled.
+/// - In Rust, [`CancellationToken::cancel`] and [`CancellationToken::is_cancelled`] work the same
+///   way, and [`CancellationToken::cancelled`] returns a Rust future.
+///
+/// Tokens are cheap to clone, and all clones share the same state. A `#[pyfunction]` can take a
+/// `CancellationToken` argument directly, which is a clone of the Python object.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_async_runtimes::CancellationToken;
+///
+/// /// Process items until the Python side cancels the token
+/// # #[cfg(feature = "tokio-runtime")]
+/// #[pyfunction]
+/// fn run_pipeline(py: Python, token: CancellationToken) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::future_into_py(py, async move {
+///         let mut processed = 0;
+///         while !token.is_cancelled() {
+///             tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+///             processed += 1;
+///         }
+///
+///         Ok(processed)
+///     })
+/// }
+/// ```
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<Mutex<State>>,
+}
+
+impl CancellationToken {
+    /// Wait until the token is cancelled
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + 'static {
+        let state = self.state.clone();
+
+        future::poll_fn(move |cx| {
+            let mut state = state.lock().unwrap();
+
+            if state.cancelled {
+                return Poll::Ready(());
+            }
+
+            if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                state.wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+    }
+}
+
+#[pymethods]
+impl CancellationToken {
+    /// Create a token that isn't cancelled yet
+    #[new]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token and wake up everything that waits for it
+    ///
+    /// Cancelling a token more than once has no effect.
+    pub fn cancel(&self) {
+        let (wakers, futures) = {
+            let mut state = self.state.lock().unwrap();
+            if state.cancelled {
+                return;
+            }
+
+            state.cancelled = true;
+            (
+                std::mem::take(&mut state.wakers),
+                std::mem::take(&mut state.futures),
+            )
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+
+        if futures.is_empty() {
+            return;
+        }
+
+        Python::with_gil(|py| {
+            for (locals, fut) in futures {
+                // the event loop may be closed already, in which case nobody's waiting anymore
+                let _ = set_result(&locals, fut.bind(py), Ok(py.None()));
+            }
+        });
+    }
+
+    /// Check whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled
+    }
+
+    /// Get an `asyncio.Future` on the running event loop that's resolved once the token is
+    /// cancelled
+    #[pyo3(name = "cancelled")]
+    fn py_cancelled<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let locals = TaskLocals::new(get_running_loop(py)?);
+        let fut = locals.create_future(py)?;
+
+        let mut state = self.state.lock().unwrap();
+        if state.cancelled {
+            fut.call_method1("set_result", (py.None(),))?;
+        } else {
+            // forget about the futures whose awaiters were cancelled
+            state.futures.retain(|(_, fut)| {
+                !fut.bind(py)
+                    .call_method0("done")
+                    .and_then(|done| done.is_truthy())
+                    .unwrap_or(true)
+            });
+            state.futures.push((locals, fut.clone().unbind()));
+        }
+
+        Ok(fut)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CancellationToken(cancelled={})", self.is_cancelled())
+    }
+}
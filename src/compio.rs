@@ -0,0 +1,717 @@
+//! <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>compio-runtime</code></span> PyO3 Asyncio functions specific to the compio runtime
+//!
+//! compio is a completion-based, thread-per-core runtime built on IOCP on Windows and `io_uring`
+//! (with a polling fallback) elsewhere. Its tasks are pinned to the thread that spawned them and
+//! most of its futures are `!Send`. PyO3 Asyncio lazily starts a dedicated compio runtime thread
+//! (configurable via [`init`]) and spawns its tasks onto it. Futures passed to
+//! [`future_into_py`] and friends still need to be `Send`, since they may be created on any
+//! thread. `!Send` futures can be converted with [`pinned_future_into_py`], or awaited from a
+//! `Send` future with [`spawn_pinned`]; both build the future on the runtime thread.
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>unstable-streams</code></span>
+//! > are only available when the `unstable-streams` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["unstable-streams"]
+//! ```
+
+use std::future::Future;
+
+use ::compio::runtime::RuntimeBuilder;
+use futures::{channel::mpsc, StreamExt};
+use pyo3::prelude::*;
+
+use crate::{
+    generic::{self, ContextExt, LocalContextExt},
+    pinned::{self, Executor, Job},
+    PyFuture, TaskLocals,
+};
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
+/// re-exports for macros
+#[cfg(feature = "attributes")]
+pub mod re_exports {
+    /// re-export unblock for use in `#[test]` macro so that blocking tests don't stall the executor
+    pub use crate::pinned::unblock;
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span> Provides the boilerplate for the `compio` runtime and runs an async fn as main
+#[cfg(feature = "attributes")]
+pub use pyo3_async_runtimes_macros::compio_main as main;
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>testing</code></span>
+/// Registers a `compio` test with the `pyo3-asyncio` test harness
+#[cfg(all(feature = "attributes", feature = "testing"))]
+pub use pyo3_async_runtimes_macros::compio_test as test;
+
+static COMPIO_EXECUTOR: Executor<RuntimeBuilder> = Executor::new(start);
+
+/// Initialize the compio runtime with a custom builder
+///
+/// This must be called before the runtime is first used, otherwise the default [`RuntimeBuilder`]
+/// is used.
+///
+/// Returns Ok(()) if success and Err(()) if the runtime had already been started, in which case
+/// `builder` is dropped.
+#[allow(clippy::result_unit_err)]
+pub fn init(builder: RuntimeBuilder) -> Result<(), ()> {
+    COMPIO_EXECUTOR.init(builder)
+}
+
+fn start(builder: Option<RuntimeBuilder>, mut jobs: mpsc::UnboundedReceiver<Job>) {
+    pinned::spawn_executor_thread(
+        "pyo3-compio",
+        move || builder.unwrap_or_else(RuntimeBuilder::new).build(),
+        move |rt| {
+            rt.block_on(async move {
+                while let Some(job) = jobs.next().await {
+                    // dropping a compio task cancels it, so it has to be detached
+                    ::compio::runtime::spawn(job()).detach();
+                }
+            });
+        },
+    );
+}
+
+pinned::pinned_runtime!(
+    CompioRuntime,
+    submit = |job| COMPIO_EXECUTOR.submit(job),
+    spawn_local = |task| {
+        ::compio::runtime::spawn(task).detach();
+    },
+);
+
+/// Spawn a `!Send` future onto the compio runtime thread from any thread
+///
+/// The future is created by `f` on the runtime thread, so it is free to use compio's
+/// thread-local resources. The returned future is `Send` and resolves to the output of the
+/// spawned future, which makes it suitable for the conversions in this module. The task locals of
+/// the caller are carried over to the spawned task.
+///
+/// Dropping the returned future aborts the spawned task. If the spawned task panics, the panic is
+/// resumed in the task awaiting the returned future.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function backed by a compio timer
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::compio::future_into_py(py, async move {
+///         pyo3_async_runtimes::compio::spawn_pinned(move || {
+///             compio::time::sleep(Duration::from_secs(secs))
+///         })
+///         .await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn spawn_pinned<F, Fut>(f: F) -> impl Future<Output = Fut::Output> + Send + 'static
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future + 'static,
+    Fut::Output: Send + 'static,
+{
+    pinned::spawn_pinned(|job| COMPIO_EXECUTOR.submit(job), f)
+}
+
+/// Set the task local event loop for the given future
+pub async fn scope<F, R>(locals: TaskLocals, fut: F) -> R
+where
+    F: Future<Output = R> + Send + 'static,
+{
+    CompioRuntime::scope(locals, fut).await
+}
+
+/// Set the task local event loop for the given !Send future
+pub async fn scope_local<F, R>(locals: TaskLocals, fut: F) -> R
+where
+    F: Future<Output = R> + 'static,
+{
+    CompioRuntime::scope_local(locals, fut).await
+}
+
+/// Get the current event loop from either Python or Rust async task local context
+///
+/// Within [`with_event_loop`](`crate::with_event_loop`), this returns the loop passed to it.
+/// Otherwise, it first checks if the runtime has a task-local reference to the Python event loop,
+/// and then whether a loop was set for the current OS thread with
+/// [`set_thread_event_loop`](`crate::set_thread_event_loop`). If not, it calls
+/// [`get_running_loop`](`crate::get_running_loop`) to get the event loop running on the thread.
+pub fn get_current_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    generic::get_current_loop::<CompioRuntime>(py)
+}
+
+/// Either copy the task locals from the current task OR get the current running loop and
+/// contextvars from Python.
+pub fn get_current_locals(py: Python) -> PyResult<TaskLocals> {
+    generic::get_current_locals::<CompioRuntime>(py)
+}
+
+/// Run the event loop until the given Future completes
+///
+/// The event loop runs until the given future is complete.
+///
+/// After this function returns, the event loop can be resumed with [`run_until_complete`]
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that should run the future
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// # pyo3::prepare_freethreaded_python();
+/// #
+/// # Python::with_gil(|py| -> PyResult<()> {
+/// # let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+/// pyo3_async_runtimes::compio::run_until_complete(event_loop, async move {
+///     pyo3_async_runtimes::compio::spawn_pinned(|| {
+///         compio::time::sleep(Duration::from_secs(1))
+///     })
+///     .await;
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }).unwrap();
+/// ```
+pub fn run_until_complete<F, T>(event_loop: Bound<PyAny>, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_until_complete::<CompioRuntime, _, T>(&event_loop, fut)
+}
+
+/// Run the event loop until the given Future completes
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::compio::run(py, async move {
+///             pyo3_async_runtimes::compio::spawn_pinned(|| {
+///                 compio::time::sleep(Duration::from_secs(1))
+///             })
+///             .await;
+///             Ok(())
+///         })
+///         .map_err(|e| {
+///             e.print_and_set_sys_last_vars(py);
+///         })
+///         .unwrap();
+///     })
+/// }
+/// ```
+pub fn run<F, T>(py: Python, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run::<CompioRuntime, F, T>(py, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`].
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// > unfortunately fail to resolve them when called within the Rust future. This is because the
+/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// > synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::compio::future_into_py_with_locals(
+///         py,
+///         pyo3_async_runtimes::compio::get_current_locals(py)?,
+///         async move {
+///             pyo3_async_runtimes::compio::spawn_pinned(move || {
+///                 compio::time::sleep(Duration::from_secs(secs))
+///             })
+///             .await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )
+/// }
+/// ```
+pub fn future_into_py_with_locals<F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py_with_locals::<CompioRuntime, F, T>(py, locals, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`].
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// > unfortunately fail to resolve them when called within the Rust future. This is because the
+/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// > synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::compio::future_into_py(py, async move {
+///         pyo3_async_runtimes::compio::spawn_pinned(move || {
+///             compio::time::sleep(Duration::from_secs(secs))
+///         })
+///         .await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py::<CompioRuntime, _, T>(py, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// Unlike [`future_into_py_with_locals`], the future is created by `f` on the compio runtime
+/// thread, so it is free to use compio's `!Send` resources across await points.
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `f` - Creates the Rust future to be converted on the executor thread
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::compio::pinned_future_into_py_with_locals(
+///         py,
+///         pyo3_async_runtimes::compio::get_current_locals(py)?,
+///         move || async move {
+///             compio::time::sleep(Duration::from_secs(secs)).await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )
+/// }
+/// ```
+pub fn pinned_future_into_py_with_locals<F, Fut, T>(
+    py: Python,
+    locals: TaskLocals,
+    f: F,
+) -> PyResult<Bound<PyAny>>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::pinned_future_into_py_with_locals::<CompioRuntime, F, Fut, T>(py, locals, f)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable
+///
+/// Unlike [`future_into_py`], the future is created by `f` on the compio runtime thread, so it
+/// is free to use compio's `!Send` resources across await points.
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - Creates the Rust future to be converted on the executor thread
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::compio::pinned_future_into_py(py, move || async move {
+///         compio::time::sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn pinned_future_into_py<F, Fut, T>(py: Python, f: F) -> PyResult<Bound<PyAny>>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::pinned_future_into_py::<CompioRuntime, F, Fut, T>(py, f)
+}
+
+/// Convert a Python `awaitable` into a Rust Future
+///
+/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
+/// completion handler sends the result of this Task through a
+/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
+/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def py_sleep(duration):
+///     await asyncio.sleep(duration)
+/// "#;
+///
+/// async fn py_sleep(seconds: f32) -> PyResult<()> {
+///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+///         Ok(
+///             PyModule::from_code_bound(
+///                 py,
+///                 PYTHON_CODE,
+///                 "test_into_future/test_mod.py",
+///                 "test_mod"
+///             )?
+///             .into()
+///         )
+///     })?;
+///
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::compio::into_future(
+///             test_mod
+///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
+///                 .into_bound(py),
+///         )
+///     })?
+///     .await?;
+///     Ok(())
+/// }
+/// ```
+pub fn into_future(awaitable: Bound<PyAny>) -> PyResult<PyFuture> {
+    generic::into_future::<CompioRuntime>(awaitable)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// # Arguments
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::{StreamExt, TryStreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # #[cfg(all(feature = "compio-runtime", feature = "attributes"))]
+/// # #[pyo3_async_runtimes::compio::main]
+/// # async fn main() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::compio::into_stream_v1(test_mod.call_method0("gen")?)
+/// })?;
+///
+/// let vals = stream
+///     .map(|item| Python::with_gil(|py| -> PyResult<i32> { Ok(item?.bind(py).extract()?) }))
+///     .try_collect::<Vec<i32>>()
+///     .await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # #[cfg(not(all(feature = "compio-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_v1(
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyResult<PyObject>> + 'static> {
+    generic::into_stream_v1::<CompioRuntime>(gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// # Arguments
+/// * `locals` - The current task locals
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::{StreamExt, TryStreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # #[cfg(all(feature = "compio-runtime", feature = "attributes"))]
+/// # #[pyo3_async_runtimes::compio::main]
+/// # async fn main() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::compio::into_stream_with_locals_v1(
+///         pyo3_async_runtimes::compio::get_current_locals(py)?,
+///         test_mod.call_method0("gen")?
+///     )
+/// })?;
+///
+/// let vals = stream
+///     .map(|item| Python::with_gil(|py| -> PyResult<i32> { Ok(item?.bind(py).extract()?) }))
+///     .try_collect::<Vec<i32>>()
+///     .await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # #[cfg(not(all(feature = "compio-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_with_locals_v1(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyResult<PyObject>> + 'static> {
+    generic::into_stream_with_locals_v1::<CompioRuntime>(locals, gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// # Arguments
+/// * `locals` - The current task locals
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::{StreamExt, TryStreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # #[cfg(all(feature = "compio-runtime", feature = "attributes"))]
+/// # #[pyo3_async_runtimes::compio::main]
+/// # async fn main() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::compio::into_stream_with_locals_v2(
+///         pyo3_async_runtimes::compio::get_current_locals(py)?,
+///         test_mod.call_method0("gen")?
+///     )
+/// })?;
+///
+/// let vals = stream
+///     .map(|item| Python::with_gil(|py| -> PyResult<i32> { Ok(item.bind(py).extract()?) }))
+///     .try_collect::<Vec<i32>>()
+///     .await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # #[cfg(not(all(feature = "compio-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_with_locals_v2(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyObject> + 'static> {
+    generic::into_stream_with_locals_v2::<CompioRuntime>(locals, gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// # Arguments
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::{StreamExt, TryStreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # #[cfg(all(feature = "compio-runtime", feature = "attributes"))]
+/// # #[pyo3_async_runtimes::compio::main]
+/// # async fn main() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::compio::into_stream_v2(test_mod.call_method0("gen")?)
+/// })?;
+///
+/// let vals = stream
+///     .map(|item| Python::with_gil(|py| -> PyResult<i32> { Ok(item.bind(py).extract()?) }))
+///     .try_collect::<Vec<i32>>()
+///     .await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # #[cfg(not(all(feature = "compio-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_v2(
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyObject> + 'static> {
+    generic::into_stream_v2::<CompioRuntime>(gen)
+}
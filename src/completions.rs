@@ -0,0 +1,429 @@
+//! Coalesces the results that Rust futures hand to an event loop into one callback per batch
+//!
+//! Every `call_soon_threadsafe` takes the loop's lock and writes to its self-pipe to wake it up,
+//! which dominates the time the loop spends on conversions when many Rust futures complete at
+//! once. Instead, the results are queued per loop, and only the result that finds no drain
+//! scheduled schedules one. That callback completes everything that was queued until it runs, so
+//! a burst of completions costs a single trip to the loop.
+//!
+//! The runtime threads push to the queue under a short lock and flip an atomic flag that tells
+//! whether a drain is scheduled, and the results are set on the futures by the drain on the thread
+//! of the loop, so the futures are only touched by the thread of their loop. The queue keeps the
+//! buffer of the last batch for the next one, so queueing a result doesn't allocate once the
+//! buffers have grown to the size of a batch.
+//!
+//! This is not a lock-free queue, and like the rest of the crate, it is built for interpreters with
+//! the GIL.
+//!
+//! How the drain gets onto the thread of the loop is the [`Strategy`] of the queue. Any loop can
+//! be reached with `call_soon_threadsafe`, but a loop that is implemented in Rust can take the
+//! drain as it is, which saves creating a Python callback and a handle for it per batch.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    thread,
+};
+
+use once_cell::sync::Lazy;
+use pyo3::{
+    ffi,
+    prelude::*,
+    types::{PyCFunction, PyDict, PyWeakrefReference},
+};
+
+use crate::{
+    debug,
+    err::{abort_error, AbortReason},
+    rust_future::RustFuture,
+    TaskLocals,
+};
+
+/// A result to set on a future
+enum Completion {
+    /// An `asyncio.Future`
+    Future {
+        future: PyObject,
+        result: PyResult<PyObject>,
+    },
+    /// A future of this library, which is completed without a trip through Python
+    Rust {
+        future: Py<RustFuture>,
+        result: PyResult<PyObject>,
+    },
+    /// A future whose conversion was dropped by its runtime before it completed, which gets the
+    /// error for aborted conversions unless it's done by then
+    ///
+    /// The error is created by the drain, since that takes the GIL.
+    Dropped { future: PyObject },
+}
+
+/// A loop that runs the drains of its queue itself, without a trip through Python
+pub(crate) trait DirectLoop: Send + Sync {
+    /// Run [`drain`] for `queue` on the thread of the loop
+    ///
+    /// Fails if the loop is closed, like `call_soon_threadsafe` does.
+    fn schedule_drain(&self, queue: Arc<CompletionQueue>) -> PyResult<()>;
+}
+
+/// How the drain of a queue is scheduled on its loop
+pub(crate) enum Strategy {
+    /// With `loop.call_soon_threadsafe`, which works for any loop
+    CallSoonThreadsafe,
+    /// Handed to a loop that is implemented in Rust
+    #[cfg_attr(not(feature = "tokio-event-loop"), allow(dead_code))]
+    Direct(Arc<dyn DirectLoop>),
+}
+
+impl Strategy {
+    /// Pick the strategy for `event_loop`
+    fn of(event_loop: &Bound<PyAny>) -> Self {
+        #[cfg(feature = "tokio-event-loop")]
+        if let Ok(event_loop) = event_loop.downcast::<crate::tokio::EventLoop>() {
+            return Self::Direct(event_loop.borrow().direct());
+        }
+
+        let _ = event_loop;
+        Self::CallSoonThreadsafe
+    }
+}
+
+/// The queued completions of an event loop, shared by all of its task locals
+pub(crate) struct CompletionQueue {
+    queued: Mutex<Vec<Completion>>,
+    /// The buffer of the last batch, which the next one takes over so that it isn't reallocated
+    spare: Mutex<Vec<Completion>>,
+    /// Whether a drain is scheduled that hasn't started to take the completions yet
+    scheduled: AtomicBool,
+    strategy: Strategy,
+}
+
+impl fmt::Debug for CompletionQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let strategy = match self.strategy {
+            Strategy::CallSoonThreadsafe => "CallSoonThreadsafe",
+            Strategy::Direct(_) => "Direct",
+        };
+
+        f.debug_struct("CompletionQueue")
+            .field("scheduled", &self.scheduled)
+            .field("strategy", &strategy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How a queue is kept in [`QUEUES`]
+enum Kept {
+    /// Until its loop is collected, which the weak reference to the loop reports by forgetting it
+    ///
+    /// A loop that awaits one conversion at a time has no task locals left between them, so this
+    /// is what lets the next conversion reuse the queue and its buffers.
+    UntilCollected {
+        queue: Arc<CompletionQueue>,
+        _loop_ref: PyObject,
+    },
+    /// For as long as task locals hold it, for loops that don't support weak references
+    WhileHeld(Weak<CompletionQueue>),
+}
+
+impl Kept {
+    /// Keep `queue` for `event_loop`, whose queue is stored under `key`
+    fn new(event_loop: &Bound<PyAny>, key: usize, queue: &Arc<CompletionQueue>) -> Self {
+        let py = event_loop.py();
+        let forget = PyCFunction::new_closure_bound(py, None, None, move |_, _| {
+            // drop the queue after the lock is released, its completions may hold the last
+            // references to loops whose own weak references forget their queues
+            let forgotten = QUEUES.lock().unwrap().remove(&key);
+            drop(forgotten);
+        });
+
+        match forget.and_then(|forget| PyWeakrefReference::new_bound_with(event_loop, forget)) {
+            Ok(loop_ref) => Self::UntilCollected {
+                queue: queue.clone(),
+                _loop_ref: loop_ref.into_any().unbind(),
+            },
+            Err(_) => Self::WhileHeld(Arc::downgrade(queue)),
+        }
+    }
+
+    fn get(&self) -> Option<Arc<CompletionQueue>> {
+        match self {
+            Self::UntilCollected { queue, .. } => Some(queue.clone()),
+            Self::WhileHeld(queue) => queue.upgrade(),
+        }
+    }
+}
+
+/// The queues by the address of their event loop
+///
+/// A queue is kept until its loop is collected, or while the task locals that hold it keep the
+/// loop alive, so an address whose queue is still alive can't have been reused by another loop.
+static QUEUES: Lazy<Mutex<HashMap<usize, Kept>>> = Lazy::new(Default::default);
+
+impl CompletionQueue {
+    /// Get the queue of `event_loop`
+    pub(crate) fn of(event_loop: &Bound<PyAny>) -> Arc<Self> {
+        let key = event_loop.as_ptr() as usize;
+        if let Some(queue) = QUEUES.lock().unwrap().get(&key).and_then(Kept::get) {
+            return queue;
+        }
+
+        let queue = Arc::new(Self {
+            queued: Mutex::new(Vec::new()),
+            spare: Mutex::new(Vec::new()),
+            scheduled: AtomicBool::new(false),
+            strategy: Strategy::of(event_loop),
+        });
+        // the weak reference is created without holding the lock, since a garbage collection can
+        // run the callbacks of other loops' weak references
+        let kept = Kept::new(event_loop, key, &queue);
+
+        let mut queues = QUEUES.lock().unwrap();
+        // the GIL may have been released while creating the weak reference, and another thread
+        // may have got here first
+        if let Some(queue) = queues.get(&key).and_then(Kept::get) {
+            drop(queues);
+            drop(kept);
+            return queue;
+        }
+
+        // forget the queues of the loops that are gone while we're at it
+        queues.retain(|_, kept| kept.get().is_some());
+        let replaced = queues.insert(key, kept);
+        drop(queues);
+        drop(replaced);
+
+        queue
+    }
+
+    /// Take the completions that are queued
+    ///
+    /// The queue is left with the buffer that was handed back by [`recycle`](Self::recycle), so
+    /// once the buffers have grown to the size of a batch, queueing a completion doesn't allocate.
+    fn take(&self) -> Vec<Completion> {
+        let mut batch = std::mem::take(&mut *self.spare.lock().unwrap());
+        std::mem::swap(&mut *self.queued.lock().unwrap(), &mut batch);
+        batch
+    }
+
+    /// Hand back the buffer of a batch that was taken, once its completions are done with
+    fn recycle(&self, mut batch: Vec<Completion>) {
+        batch.clear();
+        *self.spare.lock().unwrap() = batch;
+    }
+}
+
+/// Queue the result of `future` to be set on the thread of the event loop of `locals`
+///
+/// The result is dropped if the future was cancelled by then.
+pub(crate) fn set_result(
+    locals: &TaskLocals,
+    future: &Bound<PyAny>,
+    result: PyResult<PyObject>,
+) -> PyResult<()> {
+    let py = future.py();
+    let completion = match future.downcast::<RustFuture>() {
+        Ok(future) => Completion::Rust {
+            future: future.clone().unbind(),
+            result,
+        },
+        Err(_) => Completion::Future {
+            future: future.clone().unbind(),
+            result,
+        },
+    };
+
+    let queue = locals.completions();
+    queue.queued.lock().unwrap().push(completion);
+    debug::count(&debug::COMPLETIONS);
+
+    // pairs with the swap in the drain, which takes this completion if it clears the flag after
+    if queue.scheduled.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+
+    debug::count(&debug::DRAINS);
+    if let Err(e) = schedule(py, locals) {
+        // the loop is closed, so the queued results, including those of other conversions, would
+        // never be set
+        abandon(queue);
+
+        // nobody is waiting for a future that was cancelled, e.g. when the loop was shut down
+        let cancelled = future
+            .call_method0("cancelled")
+            .and_then(|cancelled| cancelled.is_truthy())
+            .unwrap_or(false);
+        return if cancelled { Ok(()) } else { Err(e) };
+    }
+
+    Ok(())
+}
+
+/// Queue the error for aborted conversions to be set on `future`, without waiting for the GIL
+///
+/// A runtime can drop the task of a conversion on any thread, e.g. on its workers while a thread
+/// that holds the GIL shuts it down and waits for them, so this must not wait for the GIL. A loop
+/// that is implemented in Rust takes the drain without it, and so does `call_soon_threadsafe` on a
+/// thread that holds the GIL already. Otherwise the drain is scheduled by a thread of its own,
+/// which waits for the GIL in place of the thread that dropped the conversion.
+pub(crate) fn set_dropped(locals: &TaskLocals, future: PyObject) {
+    let queue = locals.completions();
+    queue
+        .queued
+        .lock()
+        .unwrap()
+        .push(Completion::Dropped { future });
+    debug::count(&debug::COMPLETIONS);
+
+    if queue.scheduled.swap(true, Ordering::AcqRel) {
+        return;
+    }
+
+    debug::count(&debug::DRAINS);
+    match &queue.strategy {
+        Strategy::Direct(direct) => {
+            if direct.schedule_drain(queue.clone()).is_err() {
+                abandon(queue);
+            }
+        }
+        Strategy::CallSoonThreadsafe => {
+            // the runtime can drop its tasks when it shuts down after the interpreter was
+            // finalized, and then the GIL can't be taken anymore
+            if unsafe { ffi::Py_IsInitialized() } == 0 {
+                return;
+            }
+
+            if unsafe { ffi::PyGILState_Check() } == 1 {
+                schedule_dropped(locals);
+            } else {
+                let locals = locals.clone();
+                thread::spawn(move || schedule_dropped(&locals));
+            }
+        }
+    }
+}
+
+/// Schedule the drain of a [`Completion::Dropped`] with the GIL
+fn schedule_dropped(locals: &TaskLocals) {
+    Python::with_gil(|py| {
+        if schedule(py, locals).is_err() {
+            // the loop is closed, and nobody awaits its futures anymore
+            abandon(locals.completions());
+        }
+    });
+}
+
+/// Schedule a drain of the queue of `locals` on its loop
+fn schedule(py: Python, locals: &TaskLocals) -> PyResult<()> {
+    let queue = locals.completions();
+    match &queue.strategy {
+        Strategy::CallSoonThreadsafe => {
+            let drain = Drain {
+                queue: queue.clone(),
+                event_loop: locals.event_loop(py).unbind(),
+            };
+            locals.call_soon_threadsafe(py, &py.None().into_bound(py), (drain,))
+        }
+        Strategy::Direct(direct) => direct.schedule_drain(queue.clone()),
+    }
+}
+
+/// Drop the completions of a queue whose drain couldn't be scheduled, since its loop is closed
+fn abandon(queue: &CompletionQueue) {
+    queue.scheduled.store(false, Ordering::Release);
+    let mut batch = queue.take();
+    for _ in batch.drain(..) {
+        debug::dropped_completion();
+    }
+    queue.recycle(batch);
+}
+
+/// Set the results that are queued for `event_loop` right away, on its thread
+///
+/// This delivers the completions whose drain hasn't run yet, e.g. because the thread that
+/// schedules it for [`set_dropped`] still waits for the GIL.
+pub(crate) fn flush(event_loop: &Bound<PyAny>) -> PyResult<()> {
+    drain(&CompletionQueue::of(event_loop), event_loop)
+}
+
+/// Sets the results that were queued for the loop when it runs
+#[pyclass(frozen)]
+struct Drain {
+    queue: Arc<CompletionQueue>,
+    event_loop: PyObject,
+}
+
+#[pymethods]
+impl Drain {
+    fn __call__(&self, py: Python) -> PyResult<()> {
+        drain(&self.queue, self.event_loop.bind(py))
+    }
+}
+
+/// Set the results that were queued for `event_loop`, on its thread
+pub(crate) fn drain(queue: &CompletionQueue, event_loop: &Bound<PyAny>) -> PyResult<()> {
+    let py = event_loop.py();
+    // the completions that are queued from now on schedule another drain
+    queue.scheduled.swap(false, Ordering::AcqRel);
+
+    let mut batch = queue.take();
+    for completion in batch.drain(..) {
+        if let Err(e) = complete(py, completion) {
+            // one failure doesn't hold up the rest of the batch, even if the handler fails too
+            if let Err(e) = report(event_loop, e) {
+                e.print_and_set_sys_last_vars(py);
+            }
+        }
+    }
+    queue.recycle(batch);
+
+    Ok(())
+}
+
+/// Hand the failure to set a result to the exception handler of `event_loop`
+fn report(event_loop: &Bound<PyAny>, e: PyErr) -> PyResult<()> {
+    let py = event_loop.py();
+    let context = PyDict::new_bound(py);
+    context.set_item("message", "Exception in a completion of a Rust future")?;
+    context.set_item("exception", e.into_value(py))?;
+    event_loop.call_method1("call_exception_handler", (context,))?;
+
+    Ok(())
+}
+
+fn complete(py: Python, completion: Completion) -> PyResult<()> {
+    match completion {
+        Completion::Future { future, result } => {
+            let future = future.bind(py);
+            if future.call_method0("cancelled")?.is_truthy()? {
+                return Ok(());
+            }
+
+            match result {
+                Ok(val) => future.call_method1("set_result", (val,))?,
+                Err(err) => future.call_method1("set_exception", (err.into_value(py),))?,
+            };
+        }
+        Completion::Rust { future, result } => RustFuture::complete(future.bind(py), result)?,
+        Completion::Dropped { future } => {
+            let future = future.bind(py);
+            if let Ok(future) = future.downcast::<RustFuture>() {
+                return RustFuture::complete(future, Err(abort_error(AbortReason::Dropped)));
+            }
+
+            // e.g. the conversion was cancelled from Python, which dropped the Rust future
+            if future.call_method0("done")?.is_truthy()? {
+                return Ok(());
+            }
+
+            let err = abort_error(AbortReason::Dropped);
+            future.call_method1("set_exception", (err.into_value(py),))?;
+        }
+    }
+
+    Ok(())
+}
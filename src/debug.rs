@@ -0,0 +1,249 @@
+//! Inspection of the task locals and conversions as they are right now
+//!
+//! These functions don't change anything, they report what a conversion started at the call site
+//! would use. That makes it possible to diagnose errors like "attached to a different loop" in
+//! assertions of your own, e.g. to check that a worker thread picks up the intended event loop.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use pyo3::prelude::*;
+
+#[cfg(feature = "tokio-runtime")]
+use crate::generic::ContextExt;
+use crate::{
+    current_locals_with_source, scoped, scoped_event_loop, shutdown, TaskLocals, DEFAULT_LOCALS,
+};
+
+/// Where the task locals of a conversion come from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalsSource {
+    /// The event loop was passed to [`with_event_loop`](crate::with_event_loop)
+    WithEventLoop,
+    /// The current Rust task is scoped to them, e.g. by `future_into_py` or a `scope` function
+    Task,
+    /// The event loop was set for the current OS thread with
+    /// [`set_thread_event_loop`](crate::set_thread_event_loop)
+    ThreadEventLoop,
+    /// The event loop is running on the current OS thread
+    RunningLoop,
+    /// The defaults pushed with [`push_default_locals`](crate::push_default_locals)
+    Default,
+}
+
+/// The task locals that a conversion would use right now, returned by [`current_locals`]
+pub struct CurrentLocals {
+    source: LocalsSource,
+    runtime: Option<&'static str>,
+    locals: TaskLocals,
+}
+
+impl CurrentLocals {
+    /// Where the task locals come from
+    pub fn source(&self) -> LocalsSource {
+        self.source
+    }
+
+    /// The name of the runtime whose task-local storage holds the locals, if the source is
+    /// [`LocalsSource::Task`]
+    ///
+    /// This is `"tokio"` for the tokio runtime, and `"scoped"` for the storage that all other
+    /// runtimes share.
+    pub fn runtime(&self) -> Option<&'static str> {
+        self.runtime
+    }
+
+    /// The task locals themselves
+    pub fn locals(&self) -> &TaskLocals {
+        &self.locals
+    }
+
+    /// The event loop that the conversion would use
+    pub fn event_loop<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
+        self.locals.event_loop(py)
+    }
+
+    /// The contextvars that the conversion would run Python code in, or `None` for the current
+    /// context at the time
+    pub fn context<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
+        self.locals.context(py)
+    }
+
+    /// The executor that blocking Python functions would run in, or `None` for the default
+    /// executor of the event loop
+    pub fn executor<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
+        self.locals.executor(py)
+    }
+}
+
+/// Report the task locals that a conversion started here would use
+///
+/// This follows the same order as the `get_current_locals` functions of the runtimes: the loop of
+/// [`with_event_loop`](crate::with_event_loop), the task locals of the current Rust task, the loop
+/// set for this thread, the running loop, and finally the defaults. It fails with the error of
+/// `asyncio.get_running_loop` if none of them is available.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_async_runtimes::debug::{self, LocalsSource};
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+///     pyo3_async_runtimes::set_thread_event_loop(Some(event_loop.clone()));
+///
+///     let current = debug::current_locals(py)?;
+///     assert_eq!(current.source(), LocalsSource::ThreadEventLoop);
+///     assert!(current.event_loop(py).is(&event_loop));
+///
+///     pyo3_async_runtimes::set_thread_event_loop(None);
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })
+/// # .unwrap();
+/// ```
+pub fn current_locals(py: Python) -> PyResult<CurrentLocals> {
+    if let Some(event_loop) = scoped_event_loop(py) {
+        return Ok(CurrentLocals {
+            source: LocalsSource::WithEventLoop,
+            runtime: None,
+            locals: TaskLocals::new(event_loop).copy_context_if_enabled(py)?,
+        });
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    if let Some(locals) = crate::tokio::TokioRuntime::get_task_locals() {
+        return Ok(CurrentLocals {
+            source: LocalsSource::Task,
+            runtime: Some("tokio"),
+            locals,
+        });
+    }
+
+    if let Some(locals) = scoped::get_task_locals() {
+        return Ok(CurrentLocals {
+            source: LocalsSource::Task,
+            runtime: Some("scoped"),
+            locals,
+        });
+    }
+
+    let (source, locals) = current_locals_with_source(py)?;
+    Ok(CurrentLocals {
+        source,
+        runtime: None,
+        locals,
+    })
+}
+
+/// Get the number of defaults on the stack of [`push_default_locals`](crate::push_default_locals)
+pub fn default_locals_depth() -> usize {
+    DEFAULT_LOCALS.lock().unwrap().len()
+}
+
+/// Get the number of conversions between Rust and Python that are in flight
+///
+/// These are the conversions that [`shutdown`](crate::shutdown) would wait for.
+pub fn in_flight_conversions() -> usize {
+    shutdown::in_flight()
+}
+
+pub(crate) static FUTURES_INTO_PY: AtomicU64 = AtomicU64::new(0);
+pub(crate) static RUST_FUTURES: AtomicU64 = AtomicU64::new(0);
+pub(crate) static AWAITABLES_INTO_RUST: AtomicU64 = AtomicU64::new(0);
+pub(crate) static COMPLETIONS: AtomicU64 = AtomicU64::new(0);
+pub(crate) static DRAINS: AtomicU64 = AtomicU64::new(0);
+pub(crate) static DROPPED_COMPLETIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Count one more of `counter`
+pub(crate) fn count(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a result that was dropped because its event loop is closed, and log it with the `log`
+/// feature
+pub(crate) fn dropped_completion() {
+    count(&DROPPED_COMPLETIONS);
+
+    #[cfg(feature = "log")]
+    log::debug!(
+        target: "pyo3_async_runtimes",
+        "dropped the result of a Rust future, its event loop is closed"
+    );
+}
+
+/// How many conversions were made and how their results got to the event loops, returned by
+/// [`counters`]
+///
+/// The numbers count up from the start of the process. Take the difference of two snapshots with
+/// [`Counters::since`] to see what a piece of code did, e.g. in a benchmark.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Counters {
+    /// Rust futures converted into Python awaitables, e.g. by `future_into_py`
+    pub futures_into_py: u64,
+    /// How many of those were returned as a future implemented in Rust, see
+    /// [`set_rust_futures`](crate::set_rust_futures)
+    pub rust_futures: u64,
+    /// Python awaitables converted into Rust futures by `into_future` and its variants
+    pub awaitables_into_rust: u64,
+    /// Results of Rust futures handed to an event loop
+    pub completions: u64,
+    /// Callbacks scheduled on an event loop to set those results
+    ///
+    /// The results that are handed to a loop together are set by one callback, so `completions`
+    /// divided by this is how well they're batched.
+    pub drains: u64,
+    /// Results that were dropped instead, because their event loop was closed by the time they
+    /// were handed to it
+    pub dropped_completions: u64,
+}
+
+impl Counters {
+    /// Get what was counted between `earlier` and these counters
+    ///
+    /// A counter that is lower than in `earlier`, e.g. because the two were passed the other way
+    /// around, counts as 0.
+    pub fn since(&self, earlier: &Counters) -> Counters {
+        Counters {
+            futures_into_py: self.futures_into_py.saturating_sub(earlier.futures_into_py),
+            rust_futures: self.rust_futures.saturating_sub(earlier.rust_futures),
+            awaitables_into_rust: self
+                .awaitables_into_rust
+                .saturating_sub(earlier.awaitables_into_rust),
+            completions: self.completions.saturating_sub(earlier.completions),
+            drains: self.drains.saturating_sub(earlier.drains),
+            dropped_completions: self
+                .dropped_completions
+                .saturating_sub(earlier.dropped_completions),
+        }
+    }
+}
+
+/// Get the counters of the conversions made so far
+///
+/// # Examples
+///
+/// ```
+/// use pyo3_async_runtimes::debug;
+///
+/// let before = debug::counters();
+/// // ... run some conversions
+/// let counted = debug::counters().since(&before);
+///
+/// println!(
+///     "{} futures converted, {} results set by {} callbacks",
+///     counted.futures_into_py, counted.completions, counted.drains
+/// );
+/// ```
+pub fn counters() -> Counters {
+    Counters {
+        futures_into_py: FUTURES_INTO_PY.load(Ordering::Relaxed),
+        rust_futures: RUST_FUTURES.load(Ordering::Relaxed),
+        awaitables_into_rust: AWAITABLES_INTO_RUST.load(Ordering::Relaxed),
+        completions: COMPLETIONS.load(Ordering::Relaxed),
+        drains: DRAINS.load(Ordering::Relaxed),
+        dropped_completions: DROPPED_COMPLETIONS.load(Ordering::Relaxed),
+    }
+}
@@ -0,0 +1,652 @@
+//! <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>dynamic-runtime</code></span> PyO3 Asyncio functions that run on a runtime registered at startup
+//!
+//! The runtimes in the other modules are selected at compile time. This module instead routes all
+//! of its conversions through a [`Runtime`] trait object that the host application registers with
+//! [`register`] before the first conversion is made. This lets library authors ship Python
+//! bindings that use whatever executor the application already owns, without depending on it.
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>unstable-streams</code></span>
+//! > are only available when the `unstable-streams` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["unstable-streams"]
+//! ```
+
+use std::{future::Future, pin::Pin, time::Duration};
+
+use futures::{channel::oneshot, future::BoxFuture};
+use once_cell::sync::OnceCell;
+use pyo3::prelude::*;
+
+use crate::{
+    generic::{self, ContextExt, LocalContextExt},
+    pinned::{self, PinnedJoinErr, PinnedJoinHandle},
+    scoped::{self, Scoped},
+    PyFuture, TaskLocals,
+};
+
+/// An async/await runtime that can be registered with [`register`]
+///
+/// Unlike [`generic::Runtime`], this trait is object safe, so the runtime can be chosen by the
+/// application at startup rather than by the library at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use std::{thread, time::Duration};
+///
+/// use futures::{channel::oneshot, future::BoxFuture, FutureExt};
+///
+/// /// A toy runtime that drives every task on its own thread
+/// struct ThreadRuntime;
+///
+/// impl pyo3_async_runtimes::dynamic::Runtime for ThreadRuntime {
+///     fn spawn(&self, fut: BoxFuture<'static, ()>) {
+///         thread::spawn(move || futures::executor::block_on(fut));
+///     }
+///
+///     fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+///         thread::spawn(f);
+///     }
+///
+///     fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+///         let (tx, rx) = oneshot::channel();
+///         thread::spawn(move || {
+///             thread::sleep(duration);
+///             let _ = tx.send(());
+///         });
+///         rx.map(|_| ()).boxed()
+///     }
+/// }
+///
+/// pyo3_async_runtimes::dynamic::register(Box::new(ThreadRuntime)).unwrap();
+/// ```
+pub trait Runtime: Send + Sync + 'static {
+    /// Spawn a future onto the runtime's event loop
+    ///
+    /// The future must be driven to completion. Panics are caught before they reach the runtime.
+    fn spawn(&self, fut: BoxFuture<'static, ()>);
+
+    /// Run a blocking function on a thread where it won't stall the runtime's event loop
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>);
+
+    /// Create a future that completes after the given duration
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+static DYNAMIC_RUNTIME: OnceCell<Box<dyn Runtime>> = OnceCell::new();
+
+/// Register the runtime that the conversions in this module should use
+///
+/// This must be called before any conversions are made.
+///
+/// Returns Ok(()) if success and Err(()) if a runtime had already been registered.
+#[allow(clippy::result_unit_err)]
+pub fn register(runtime: Box<dyn Runtime>) -> Result<(), ()> {
+    DYNAMIC_RUNTIME.set(runtime).map_err(|_| ())
+}
+
+/// Get a reference to the registered runtime
+///
+/// # Panics
+///
+/// Panics if no runtime has been registered with [`register`].
+pub fn get_runtime() -> &'static dyn Runtime {
+    DYNAMIC_RUNTIME
+        .get()
+        .expect(
+            "no runtime has been registered, call `pyo3_async_runtimes::dynamic::register` first",
+        )
+        .as_ref()
+}
+
+struct DynamicRuntime;
+
+impl generic::Runtime for DynamicRuntime {
+    type JoinError = PinnedJoinErr;
+    type JoinHandle = PinnedJoinHandle;
+
+    fn spawn<F>(fut: F) -> Self::JoinHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (task, handle) = pinned::task(fut);
+        get_runtime().spawn(Box::pin(task));
+        handle
+    }
+}
+
+impl ContextExt for DynamicRuntime {
+    fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+    where
+        F: Future<Output = R> + Send + 'static,
+    {
+        Box::pin(Scoped::new(locals, fut))
+    }
+
+    fn get_task_locals() -> Option<TaskLocals> {
+        scoped::get_task_locals()
+    }
+}
+
+impl LocalContextExt for DynamicRuntime {
+    fn scope_local<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R>>>
+    where
+        F: Future<Output = R> + 'static,
+    {
+        Box::pin(Scoped::new(locals, fut))
+    }
+}
+
+/// Create a future that completes after the given duration on the registered runtime
+pub fn sleep(duration: Duration) -> impl Future<Output = ()> + Send + 'static {
+    get_runtime().sleep(duration)
+}
+
+/// Run a blocking function on the registered runtime without stalling its event loop
+///
+/// Panics are propagated to the task awaiting the result.
+pub fn spawn_blocking<F, T>(f: F) -> impl Future<Output = T> + Send + 'static
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+
+    get_runtime().spawn_blocking(Box::new(move || {
+        let _ = tx.send(std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)));
+    }));
+
+    async move {
+        match rx.await.expect("blocking task was dropped by the runtime") {
+            Ok(val) => val,
+            Err(e) => std::panic::resume_unwind(e),
+        }
+    }
+}
+
+/// Set the task local event loop for the given future
+pub async fn scope<F, R>(locals: TaskLocals, fut: F) -> R
+where
+    F: Future<Output = R> + Send + 'static,
+{
+    DynamicRuntime::scope(locals, fut).await
+}
+
+/// Set the task local event loop for the given !Send future
+pub async fn scope_local<F, R>(locals: TaskLocals, fut: F) -> R
+where
+    F: Future<Output = R> + 'static,
+{
+    DynamicRuntime::scope_local(locals, fut).await
+}
+
+/// Get the current event loop from either Python or Rust async task local context
+///
+/// Within [`with_event_loop`](`crate::with_event_loop`), this returns the loop passed to it.
+/// Otherwise, it first checks if the runtime has a task-local reference to the Python event loop,
+/// and then whether a loop was set for the current OS thread with
+/// [`set_thread_event_loop`](`crate::set_thread_event_loop`). If not, it calls
+/// [`get_running_loop`](`crate::get_running_loop`) to get the event loop running on the thread.
+pub fn get_current_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    generic::get_current_loop::<DynamicRuntime>(py)
+}
+
+/// Either copy the task locals from the current task OR get the current running loop and
+/// contextvars from Python.
+pub fn get_current_locals(py: Python) -> PyResult<TaskLocals> {
+    generic::get_current_locals::<DynamicRuntime>(py)
+}
+
+/// Run the event loop until the given Future completes
+///
+/// The event loop runs until the given future is complete.
+///
+/// After this function returns, the event loop can be resumed with [`run_until_complete`]
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that should run the future
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// # pyo3::prepare_freethreaded_python();
+/// #
+/// # Python::with_gil(|py| -> PyResult<()> {
+/// # let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+/// pyo3_async_runtimes::dynamic::run_until_complete(event_loop, async move {
+///     pyo3_async_runtimes::dynamic::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }).unwrap();
+/// ```
+pub fn run_until_complete<F, T>(event_loop: Bound<PyAny>, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_until_complete::<DynamicRuntime, _, T>(&event_loop, fut)
+}
+
+/// Run the event loop until the given Future completes
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::dynamic::run(py, async move {
+///             pyo3_async_runtimes::dynamic::sleep(Duration::from_secs(1)).await;
+///             Ok(())
+///         })
+///         .map_err(|e| {
+///             e.print_and_set_sys_last_vars(py);
+///         })
+///         .unwrap();
+///     })
+/// }
+/// ```
+pub fn run<F, T>(py: Python, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run::<DynamicRuntime, F, T>(py, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`].
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// > unfortunately fail to resolve them when called within the Rust future. This is because the
+/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// > synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::dynamic::future_into_py_with_locals(
+///         py,
+///         pyo3_async_runtimes::dynamic::get_current_locals(py)?,
+///         async move {
+///             pyo3_async_runtimes::dynamic::sleep(Duration::from_secs(secs)).await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )
+/// }
+/// ```
+pub fn future_into_py_with_locals<F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py_with_locals::<DynamicRuntime, F, T>(py, locals, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`].
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// > unfortunately fail to resolve them when called within the Rust future. This is because the
+/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// > synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::dynamic::future_into_py(py, async move {
+///         pyo3_async_runtimes::dynamic::sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py::<DynamicRuntime, _, T>(py, fut)
+}
+
+/// Convert a Python `awaitable` into a Rust Future
+///
+/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
+/// completion handler sends the result of this Task through a
+/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
+/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def py_sleep(duration):
+///     await asyncio.sleep(duration)
+/// "#;
+///
+/// async fn py_sleep(seconds: f32) -> PyResult<()> {
+///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+///         Ok(
+///             PyModule::from_code_bound(
+///                 py,
+///                 PYTHON_CODE,
+///                 "test_into_future/test_mod.py",
+///                 "test_mod"
+///             )?
+///             .into()
+///         )
+///     })?;
+///
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::dynamic::into_future(
+///             test_mod
+///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
+///                 .into_bound(py),
+///         )
+///     })?
+///     .await?;
+///     Ok(())
+/// }
+/// ```
+pub fn into_future(awaitable: Bound<PyAny>) -> PyResult<PyFuture> {
+    generic::into_future::<DynamicRuntime>(awaitable)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// # Arguments
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::{StreamExt, TryStreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # async fn example() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::dynamic::into_stream_v1(test_mod.call_method0("gen")?)
+/// })?;
+///
+/// let vals = stream
+///     .map(|item| Python::with_gil(|py| -> PyResult<i32> { Ok(item?.bind(py).extract()?) }))
+///     .try_collect::<Vec<i32>>()
+///     .await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_v1(
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyResult<PyObject>> + 'static> {
+    generic::into_stream_v1::<DynamicRuntime>(gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// # Arguments
+/// * `locals` - The current task locals
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::{StreamExt, TryStreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # async fn example() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::dynamic::into_stream_with_locals_v1(
+///         pyo3_async_runtimes::dynamic::get_current_locals(py)?,
+///         test_mod.call_method0("gen")?
+///     )
+/// })?;
+///
+/// let vals = stream
+///     .map(|item| Python::with_gil(|py| -> PyResult<i32> { Ok(item?.bind(py).extract()?) }))
+///     .try_collect::<Vec<i32>>()
+///     .await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_with_locals_v1(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyResult<PyObject>> + 'static> {
+    generic::into_stream_with_locals_v1::<DynamicRuntime>(locals, gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// # Arguments
+/// * `locals` - The current task locals
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::{StreamExt, TryStreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # async fn example() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::dynamic::into_stream_with_locals_v2(
+///         pyo3_async_runtimes::dynamic::get_current_locals(py)?,
+///         test_mod.call_method0("gen")?
+///     )
+/// })?;
+///
+/// let vals = stream
+///     .map(|item| Python::with_gil(|py| -> PyResult<i32> { Ok(item.bind(py).extract()?) }))
+///     .try_collect::<Vec<i32>>()
+///     .await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_with_locals_v2(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyObject> + 'static> {
+    generic::into_stream_with_locals_v2::<DynamicRuntime>(locals, gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// # Arguments
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::{StreamExt, TryStreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # async fn example() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::dynamic::into_stream_v2(test_mod.call_method0("gen")?)
+/// })?;
+///
+/// let vals = stream
+///     .map(|item| Python::with_gil(|py| -> PyResult<i32> { Ok(item.bind(py).extract()?) }))
+///     .try_collect::<Vec<i32>>()
+///     .await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_v2(
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyObject> + 'static> {
+    generic::into_stream_v2::<DynamicRuntime>(gen)
+}
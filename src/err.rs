@@ -1,9 +1,537 @@
+use std::{
+    any::Any,
+    future::Future,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::RwLock,
+    task::{Context, Poll},
+};
+#[cfg(feature = "panic-backtrace")]
+use std::{cell::RefCell, panic, sync::Once};
+
+use futures::{future::CatchUnwind, FutureExt};
+use once_cell::sync::Lazy;
+use pin_project_lite::pin_project;
+use pyo3::prelude::*;
+
+use crate::generic::get_panic_message;
+
 // FIXME - is there a way to document custom PyO3 exceptions?
 #[allow(missing_docs)]
 mod exceptions {
-    use pyo3::{create_exception, exceptions::PyException};
+    use pyo3::{
+        create_exception,
+        exceptions::{PyException, PyRuntimeError},
+    };
+
+    create_exception!(pyo3_asyncio, RustFutureAborted, PyException);
+    create_exception!(pyo3_asyncio, RustPanic, RustFutureAborted);
+    create_exception!(pyo3_asyncio, ChannelClosed, PyException);
+    create_exception!(pyo3_asyncio, ChannelLagged, PyException);
+    create_exception!(pyo3_asyncio, ShuttingDown, PyRuntimeError);
+}
+
+pub use exceptions::{ChannelClosed, ChannelLagged, RustFutureAborted, RustPanic, ShuttingDown};
+
+type AbortErrorFn = dyn Fn(Python, AbortReason) -> PyErr + Send + Sync;
+
+static ABORT_ERROR: Lazy<RwLock<Option<Box<AbortErrorFn>>>> = Lazy::new(Default::default);
+
+/// Why the Rust side of a conversion stopped before it completed
+#[non_exhaustive]
+pub enum AbortReason {
+    /// The Rust future panicked with this payload
+    Panic(Box<dyn Any + Send + 'static>),
+    /// The Rust future was dropped by its runtime, e.g. because the executor shut down
+    Dropped,
+}
 
-    create_exception!(pyo3_asyncio, RustPanic, PyException);
+/// Set how the exception is created that Python code sees when it awaits a conversion whose Rust
+/// side panicked or was dropped
+///
+/// By default, a panic raises [`RustPanic`] with the panic message, which is also its `message`
+/// attribute, along with a `backtrace` attribute (see [`capture_panic_backtraces`]). A dropped
+/// future raises [`RustFutureAborted`], which is the base class of `RustPanic`. `f` is called with the GIL held
+/// and gets the original panic payload, so it can e.g. raise a domain-specific exception, or pass
+/// on a `PyErr` that the Rust code panicked with.
+///
+/// # Arguments
+/// * `f` - The function that creates the exception
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::{exceptions::PyRuntimeError, prelude::*};
+/// use pyo3_async_runtimes::err::{self, AbortReason};
+///
+/// err::set_abort_error(|_py, reason| match reason {
+///     AbortReason::Panic(payload) => match payload.downcast::<PyErr>() {
+///         Ok(err) => *err,
+///         Err(_) => PyRuntimeError::new_err("internal error"),
+///     },
+///     _ => PyRuntimeError::new_err("the operation was aborted"),
+/// });
+/// ```
+pub fn set_abort_error<F>(f: F)
+where
+    F: Fn(Python, AbortReason) -> PyErr + Send + Sync + 'static,
+{
+    *ABORT_ERROR.write().unwrap() = Some(Box::new(f));
 }
 
-pub use exceptions::RustPanic;
+/// Create the exception for a conversion whose Rust side stopped before it completed
+pub(crate) fn abort_error(reason: AbortReason) -> PyErr {
+    abort_error_with_backtrace(reason, None)
+}
+
+/// Create the exception for a conversion whose Rust side stopped before it completed, with the
+/// backtrace of its panic if it was captured
+fn abort_error_with_backtrace(reason: AbortReason, backtrace: Option<String>) -> PyErr {
+    Python::with_gil(|py| match ABORT_ERROR.read().unwrap().as_ref() {
+        Some(f) => f(py, reason),
+        None => match reason {
+            AbortReason::Panic(payload) => rust_panic(py, get_panic_message(&*payload), backtrace),
+            AbortReason::Dropped => {
+                RustFutureAborted::new_err("rust future was dropped before it completed")
+            }
+        },
+    })
+}
+
+/// Create a [`RustPanic`] whose `message` and `backtrace` attributes are the message and the
+/// backtrace of the panic
+fn rust_panic(py: Python, message: &str, backtrace: Option<String>) -> PyErr {
+    let err = RustPanic::new_err(format!("rust future panicked: {}", message));
+
+    let value = err.value_bound(py);
+    if let Err(e) = value
+        .setattr("message", message)
+        .and_then(|()| value.setattr("backtrace", backtrace))
+    {
+        e.print_and_set_sys_last_vars(py);
+    }
+
+    err
+}
+
+pin_project! {
+    /// Future returned by [`catch_panic`], which turns a panic of the future it runs into the
+    /// error for [aborted conversions](set_abort_error)
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub(crate) struct CatchPanic<F> {
+        #[pin]
+        future: CatchUnwind<AssertUnwindSafe<F>>,
+    }
+}
+
+/// Run `fut`, turning a panic into the error for [aborted conversions](set_abort_error)
+///
+/// The panic is caught on the thread it happened on, so its backtrace is still there if
+/// [`capture_panic_backtraces`] was called.
+pub(crate) fn catch_panic<F, T>(fut: F) -> CatchPanic<F>
+where
+    F: Future<Output = PyResult<T>>,
+{
+    CatchPanic {
+        future: AssertUnwindSafe(fut).catch_unwind(),
+    }
+}
+
+impl<F, T> Future for CatchPanic<F>
+where
+    F: Future<Output = PyResult<T>>,
+{
+    type Output = PyResult<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().future.poll(cx).map(|result| match result {
+            Ok(result) => result,
+            Err(payload) => Err(abort_error_with_backtrace(
+                AbortReason::Panic(payload),
+                take_panic_backtrace(),
+            )),
+        })
+    }
+}
+
+#[cfg(feature = "panic-backtrace")]
+thread_local! {
+    /// The backtrace of the last panic on this thread, until a conversion takes it
+    static PANIC_BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>panic-backtrace</code></span> Capture the backtraces of panics, so that the [`RustPanic`] of a conversion whose future panicked carries one
+///
+/// By default, the `RustPanic` that Python code sees when it awaits a future that panicked has the
+/// panic message as its `message` attribute, and `None` as its `backtrace` attribute. After this
+/// function is called, `backtrace` is the backtrace of the panic as a string instead, which can be
+/// logged along with the Python traceback. Capturing backtraces is slow, so it's best left to
+/// debugging and tests.
+///
+/// The backtraces are captured by a panic hook that runs before the hook that was set before, so
+/// panics are still printed as usual. Calling this function again has no effect.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "panic-backtrace")]
+/// pyo3_async_runtimes::err::capture_panic_backtraces();
+/// ```
+#[cfg(feature = "panic-backtrace")]
+pub fn capture_panic_backtraces() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let backtrace = format!("{:?}", backtrace::Backtrace::new());
+            let _ = PANIC_BACKTRACE.try_with(|cell| *cell.borrow_mut() = Some(backtrace));
+
+            previous(info);
+        }));
+    });
+}
+
+/// Take the backtrace of the last panic on this thread
+#[cfg(feature = "panic-backtrace")]
+fn take_panic_backtrace() -> Option<String> {
+    PANIC_BACKTRACE
+        .try_with(|cell| cell.borrow_mut().take())
+        .unwrap_or_default()
+}
+
+#[cfg(not(feature = "panic-backtrace"))]
+fn take_panic_backtrace() -> Option<String> {
+    None
+}
+
+type ErrorTranslatorFn =
+    dyn Fn(Python, &(dyn std::error::Error + 'static)) -> Option<PyErr> + Send + Sync;
+
+static ERROR_TRANSLATOR: Lazy<RwLock<Option<Box<ErrorTranslatorFn>>>> = Lazy::new(Default::default);
+
+/// Set how Rust errors are translated into Python exceptions
+///
+/// The translator is used wherever this library turns a Rust error into an exception: a
+/// [`timeout`](crate::tokio::timeout) that elapsed, a tokio `JoinError` of a cancelled task, an
+/// `io::Error` that closes a connection, and every error that goes through [`translate_error`] or
+/// [`TranslateErr::translate_err`], which is how the futures of conversions turn their own error
+/// types into exceptions. `f` is called with the GIL held and gets the error, which it can downcast
+/// to the error types it knows. If it returns `None`, the error gets its default exception: a
+/// `TimeoutError` for a timeout, a `CancelledError` for a cancelled task, and the exception of
+/// [`translate_error`] otherwise.
+///
+/// A Python exception that the error wraps, e.g. the error of a future from
+/// [`into_future`](crate::tokio::into_future) that is the `source` of a Rust error, is kept as the
+/// `__cause__` of the translated exception, so its traceback is shown along with it. An error that
+/// is a `PyErr` itself is raised as is.
+///
+/// # Arguments
+/// * `f` - The function that translates an error, or returns `None` to leave it to the default
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt;
+///
+/// use pyo3::{exceptions::{PyKeyError, PyPermissionError}, prelude::*};
+/// use pyo3_async_runtimes::err::{self, TranslateErr};
+///
+/// #[derive(Debug)]
+/// enum StoreError {
+///     NotFound(String),
+///     Forbidden,
+/// }
+///
+/// impl fmt::Display for StoreError {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         match self {
+///             StoreError::NotFound(key) => write!(f, "no such key: {}", key),
+///             StoreError::Forbidden => write!(f, "access denied"),
+///         }
+///     }
+/// }
+///
+/// impl std::error::Error for StoreError {}
+///
+/// err::set_error_translator(|_py, e| match e.downcast_ref::<StoreError>()? {
+///     StoreError::NotFound(key) => Some(PyKeyError::new_err(key.clone())),
+///     StoreError::Forbidden => Some(PyPermissionError::new_err(e.to_string())),
+/// });
+///
+/// async fn get(key: &str) -> Result<String, StoreError> {
+///     Err(StoreError::NotFound(key.into()))
+/// }
+///
+/// # async fn conversion() -> PyResult<String> {
+/// // e.g. in the future of `future_into_py`
+/// let value = get("answer").await.translate_err()?;
+/// # Ok(value)
+/// # }
+/// ```
+pub fn set_error_translator<F>(f: F)
+where
+    F: Fn(Python, &(dyn std::error::Error + 'static)) -> Option<PyErr> + Send + Sync + 'static,
+{
+    *ERROR_TRANSLATOR.write().unwrap() = Some(Box::new(f));
+}
+
+/// Translate a Rust error into a Python exception with the [translator](set_error_translator)
+///
+/// If the translator leaves the error alone, a `PyErr` is raised as is, and with the
+/// `error-mappings` Cargo feature (enabled by default) these errors get their matching exceptions:
+///
+/// | Rust error | Python exception |
+/// |---|---|
+/// | `std::io::Error` | the `OSError` subclass of its kind, e.g. `FileNotFoundError` for `NotFound` |
+/// | `tokio::time::error::Elapsed` | `asyncio.TimeoutError` |
+/// | `tokio::task::JoinError` of a cancelled task | `asyncio.CancelledError` |
+/// | `tokio::task::JoinError` of a panicked task | the [abort error](set_abort_error) of the panic |
+///
+/// Any other error is raised as a `RuntimeError` with its message.
+pub fn translate_error<E>(e: E) -> PyErr
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    translate_boxed_error(e)
+}
+
+/// Translate a boxed Rust error into a Python exception like [`translate_error`]
+///
+/// This takes any error that converts into a `Box<dyn Error + Send + Sync>`, e.g. an error type of
+/// the application or an `eyre::Report`. An `anyhow::Error` converts too, but into a box of its own
+/// that hides the error it wraps, so use [`translate_anyhow_error`] for it instead.
+pub fn translate_boxed_error<E>(e: E) -> PyErr
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    translate_with(
+        e.into(),
+        |e| &**e,
+        |py, e| {
+            let e = match e.downcast::<PyErr>() {
+                Ok(e) => return *e,
+                Err(e) => e,
+            };
+            #[cfg(feature = "error-mappings")]
+            let e = match mappings::translate(py, e) {
+                Ok(err) => return err,
+                Err(e) => e,
+            };
+            #[cfg(not(feature = "error-mappings"))]
+            let _ = py;
+
+            pyo3::exceptions::PyRuntimeError::new_err(e.to_string())
+        },
+    )
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>anyhow</code></span> Translate an `anyhow::Error` into a Python exception like [`translate_error`]
+///
+/// The translator gets the root cause of the error, i.e. the error it was created from unless it
+/// has a source of its own, so it can downcast it to the error types it knows even if context was
+/// added to it. A `PyErr` that the error wraps, e.g. after `?` on the result of a Python call, is
+/// raised as is. Any other error is raised as a `RuntimeError` with the messages of its whole
+/// chain, like `{:#}` formats it.
+#[cfg(feature = "anyhow")]
+pub fn translate_anyhow_error(e: anyhow::Error) -> PyErr {
+    translate_with(
+        e,
+        |e| e.root_cause(),
+        |py, e| {
+            let e = match e.downcast::<PyErr>() {
+                Ok(e) => return e,
+                Err(e) => e,
+            };
+            #[cfg(feature = "error-mappings")]
+            let e = match mappings::translate(py, e) {
+                Ok(err) => return err,
+                Err(e) => e,
+            };
+            #[cfg(not(feature = "error-mappings"))]
+            let _ = py;
+
+            pyo3::exceptions::PyRuntimeError::new_err(format!("{:#}", e))
+        },
+    )
+}
+
+/// The exceptions of the common Rust errors
+#[cfg(feature = "error-mappings")]
+mod mappings {
+    use std::error::Error;
+
+    use pyo3::prelude::*;
+
+    type BoxError = Box<dyn Error + Send + Sync + 'static>;
+
+    /// An owned error that can be taken apart into the error it wraps
+    pub(super) trait Downcast: Sized {
+        fn downcast<T>(self) -> Result<T, Self>
+        where
+            T: Error + Send + Sync + 'static;
+    }
+
+    impl Downcast for BoxError {
+        fn downcast<T>(self) -> Result<T, Self>
+        where
+            T: Error + Send + Sync + 'static,
+        {
+            self.downcast::<T>().map(|e| *e)
+        }
+    }
+
+    #[cfg(feature = "anyhow")]
+    impl Downcast for anyhow::Error {
+        fn downcast<T>(self) -> Result<T, Self>
+        where
+            T: Error + Send + Sync + 'static,
+        {
+            self.downcast::<T>()
+        }
+    }
+
+    /// Translate `e` if it has a matching exception, or give it back
+    pub(super) fn translate<E: Downcast>(py: Python, e: E) -> Result<PyErr, E> {
+        let e = match e.downcast::<std::io::Error>() {
+            Ok(e) => return Ok(PyErr::from(e)),
+            Err(e) => e,
+        };
+
+        #[cfg(feature = "tokio-runtime")]
+        let e = match e.downcast::<tokio::time::error::Elapsed>() {
+            Ok(_) => return Ok(super::asyncio_exception(py, "TimeoutError")),
+            Err(e) => e,
+        };
+        #[cfg(feature = "tokio-runtime")]
+        let e = match e.downcast::<tokio::task::JoinError>() {
+            Ok(e) if e.is_panic() => {
+                return Ok(super::abort_error(super::AbortReason::Panic(
+                    e.into_panic(),
+                )))
+            }
+            Ok(_) => return Ok(super::asyncio_exception(py, "CancelledError")),
+            Err(e) => e,
+        };
+        #[cfg(not(feature = "tokio-runtime"))]
+        let _ = py;
+
+        Err(e)
+    }
+}
+
+/// Raise several errors together in an exception group, like `asyncio.TaskGroup` does
+///
+/// On Python 3.11+, the errors are raised in a `BaseExceptionGroup` with `message`, which is an
+/// `ExceptionGroup` if all of them are an `Exception`, so Python code can handle them with
+/// `except*`. Older versions use the `exceptiongroup` backport if it's installed, and raise the
+/// first error otherwise.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `message` - The message of the group
+/// * `errors` - The errors in the group, which must not be empty
+pub fn exception_group(py: Python, message: &str, mut errors: Vec<PyErr>) -> PyErr {
+    assert!(!errors.is_empty(), "an exception group can't be empty");
+
+    let group = py
+        .import_bound("builtins")
+        .and_then(|builtins| builtins.getattr("BaseExceptionGroup"))
+        .or_else(|_| {
+            // exception groups were added in Python 3.11
+            py.import_bound("exceptiongroup")
+                .and_then(|backport| backport.getattr("BaseExceptionGroup"))
+        });
+    let group = match group {
+        Ok(group) => group,
+        Err(_) => return errors.swap_remove(0),
+    };
+
+    let errors = errors
+        .into_iter()
+        .map(|err| err.into_value(py))
+        .collect::<Vec<_>>();
+    group
+        .call1((message, errors))
+        .map_or_else(|e| e, PyErr::from_value_bound)
+}
+
+/// Create the asyncio exception `name`, or the error of creating it
+#[cfg(feature = "tokio-runtime")]
+pub(crate) fn asyncio_exception(py: Python, name: &str) -> PyErr {
+    crate::asyncio(py)
+        .and_then(|asyncio| asyncio.getattr(name)?.call0())
+        .map_or_else(|e| e, PyErr::from_value_bound)
+}
+
+/// Translate a Rust error into a Python exception with the [translator](set_error_translator), or
+/// with `default` if there is none or it leaves the error alone
+#[cfg(feature = "tokio-runtime")]
+pub(crate) fn translate_error_or<E, D>(e: E, default: D) -> PyErr
+where
+    E: std::error::Error + Send + Sync + 'static,
+    D: FnOnce(Python, E) -> PyErr,
+{
+    translate_with(e, |e| e, default)
+}
+
+/// Translate `e`, which `as_error` borrows as a `dyn Error`, with the translator or `default`
+///
+/// The first Python exception in the chain of sources of the error becomes the `__cause__` of the
+/// translated exception, unless it is that exception or the translator chained it already.
+fn translate_with<E, D>(
+    e: E,
+    as_error: fn(&E) -> &(dyn std::error::Error + 'static),
+    default: D,
+) -> PyErr
+where
+    D: FnOnce(Python, E) -> PyErr,
+{
+    Python::with_gil(|py| {
+        let translated = ERROR_TRANSLATOR
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|f| f(py, as_error(&e)));
+        let cause = python_source(as_error(&e)).map(|cause| cause.clone_ref(py));
+
+        let err = match translated {
+            Some(err) => err,
+            None => default(py, e),
+        };
+        if let Some(cause) = cause {
+            if !err.value_bound(py).is(cause.value_bound(py)) && err.cause(py).is_none() {
+                err.set_cause(py, Some(cause));
+            }
+        }
+        err
+    })
+}
+
+/// The first Python exception in the chain of `e` and its sources
+fn python_source<'a>(e: &'a (dyn std::error::Error + 'static)) -> Option<&'a PyErr> {
+    let mut source = Some(e);
+    while let Some(e) = source {
+        if let Some(err) = e.downcast_ref::<PyErr>() {
+            return Some(err);
+        }
+        source = e.source();
+    }
+    None
+}
+
+/// Translate the error of a `Result` into a Python exception with the
+/// [translator](set_error_translator)
+pub trait TranslateErr<T> {
+    /// Translate the error with [`translate_error`]
+    fn translate_err(self) -> PyResult<T>;
+}
+
+impl<T, E> TranslateErr<T> for Result<T, E>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn translate_err(self) -> PyResult<T> {
+        self.map_err(translate_error)
+    }
+}
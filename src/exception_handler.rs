@@ -0,0 +1,153 @@
+//! An exception handler for the event loop that reports to the logger of the Rust application
+//!
+//! The callbacks this library schedules on the loop with `call_soon_threadsafe` have no one to
+//! raise to, so the loop hands their errors to its exception handler. The default handler prints
+//! them to stderr, where they are easily lost next to the logs of the application.
+
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyString},
+};
+
+/// The target of the records of the handler
+const TARGET: &str = "pyo3_async_runtimes";
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>log</code></span>
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tracing</code></span>
+/// Report the unhandled errors of `event_loop` to `tracing` or `log` instead of stderr
+///
+/// This sets the exception handler of the loop, so the errors of callbacks, including the ones
+/// this library schedules with `call_soon_threadsafe`, and of tasks that nobody awaited are logged
+/// as errors with the target `pyo3_async_runtimes`. With the `tracing` feature, the event has
+/// these fields, which are left out if the loop doesn't report them:
+///
+/// - `task`: the name of the task or the repr of the future that failed
+/// - `callback`: the repr of the handle of the callback that failed
+/// - `exception`: the exception, with its traceback
+///
+/// With only the `log` feature, they are part of the message of the record. An exception handler
+/// that was set before is replaced.
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop whose errors should be logged
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+///         pyo3_async_runtimes::install_exception_handler(&event_loop)?;
+///
+///         pyo3_async_runtimes::tokio::run_until_complete(event_loop, async { Ok(()) })
+///     })
+/// }
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {}
+/// ```
+pub fn install_exception_handler(event_loop: &Bound<PyAny>) -> PyResult<()> {
+    event_loop.call_method1("set_exception_handler", (LogExceptionHandler,))?;
+    Ok(())
+}
+
+#[pyclass]
+struct LogExceptionHandler;
+
+#[pymethods]
+impl LogExceptionHandler {
+    fn __call__(&self, _event_loop: &Bound<PyAny>, context: &Bound<PyDict>) {
+        let py = context.py();
+        let message = get_str(context, "message", |message| message.str())
+            .unwrap_or_else(|| "Unhandled exception in event loop".into());
+        let task = context
+            .get_item("task")
+            .ok()
+            .flatten()
+            .or_else(|| context.get_item("future").ok().flatten())
+            .and_then(|task| match task.call_method0("get_name") {
+                Ok(name) => name.str().ok(),
+                Err(_) => task.repr().ok(),
+            })
+            .map(|task| task.to_string());
+        let callback = get_str(context, "handle", |handle| handle.repr());
+        let exception = context
+            .get_item("exception")
+            .ok()
+            .flatten()
+            .and_then(|exception| format_exception(py, &exception).ok());
+
+        report(message, task, callback, exception);
+    }
+}
+
+/// The string of the `key` of `context`, if it has one
+fn get_str<'py>(
+    context: &Bound<'py, PyDict>,
+    key: &str,
+    to_str: impl FnOnce(&Bound<'py, PyAny>) -> PyResult<Bound<'py, PyString>>,
+) -> Option<String> {
+    let value = context.get_item(key).ok().flatten()?;
+    to_str(&value).ok().map(|s| s.to_string())
+}
+
+/// The exception with its traceback, as Python prints it
+fn format_exception(py: Python, exception: &Bound<PyAny>) -> PyResult<String> {
+    let lines = py.import_bound("traceback")?.call_method1(
+        "format_exception",
+        (
+            exception.get_type(),
+            exception,
+            exception.getattr("__traceback__")?,
+        ),
+    )?;
+
+    Ok(lines
+        .extract::<Vec<String>>()?
+        .concat()
+        .trim_end()
+        .to_string())
+}
+
+#[cfg(feature = "tracing")]
+fn report(
+    message: String,
+    task: Option<String>,
+    callback: Option<String>,
+    exception: Option<String>,
+) {
+    tracing::error!(
+        target: TARGET,
+        task = task.as_deref(),
+        callback = callback.as_deref(),
+        exception = exception.as_deref(),
+        "{}",
+        message
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+fn report(
+    message: String,
+    task: Option<String>,
+    callback: Option<String>,
+    exception: Option<String>,
+) {
+    let mut record = message;
+    if let Some(task) = task {
+        record.push_str(&format!("\ntask: {}", task));
+    }
+    if let Some(callback) = callback {
+        record.push_str(&format!("\ncallback: {}", callback));
+    }
+    if let Some(exception) = exception {
+        record.push('\n');
+        record.push_str(&exception);
+    }
+
+    log::error!(target: TARGET, "{}", record);
+}
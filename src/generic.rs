@@ -1,5 +1,8 @@
 //! Generic implementations of PyO3 Asyncio utilities that can be used for any Rust runtime
 //!
+//! The traits in this module can be implemented by hand, or generated for an executor along with
+//! its module-level conversions by [`impl_runtime`](crate::impl_runtime).
+//!
 //! Items marked with
 //! <span
 //!   class="module-item stab portability"
@@ -15,22 +18,43 @@
 
 use std::{
     future::Future,
+    panic::{self, AssertUnwindSafe},
     pin::Pin,
     sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
 use crate::{
-    asyncio, call_soon_threadsafe, close, create_future, dump_err, err::RustPanic,
-    get_running_loop, into_future_with_locals, TaskLocals,
+    asyncio, check_not_in_running_loop, close,
+    completions::{self, set_result},
+    current_locals, current_loop, debug, dump_err,
+    err::{abort_error, catch_panic, translate_boxed_error, AbortReason, ChannelClosed},
+    install_requested_uvloop, into_future_polled_with_locals, into_future_result_with_locals,
+    into_future_typed_with_locals, into_future_with_locals, new_event_loop,
+    run_in_executor_with_locals,
+    rust_future::{awaited_by_plain_task, RustFuture},
+    scoped_event_loop,
+    shutdown::{self, register, register_abortable, Registration, Target},
+    signals::SignalWakeup,
+    spawn_py_with_locals, PyFuture, PyOutcomeFuture, PyPolledFuture, PyTask, RunnerOptions,
+    TaskLocals,
 };
-use futures::channel::oneshot;
-#[cfg(feature = "unstable-streams")]
-use futures::{channel::mpsc, SinkExt};
 #[cfg(feature = "unstable-streams")]
+use futures::{channel::mpsc, SinkExt, StreamExt};
+use futures::{
+    channel::oneshot,
+    future::{self, AbortHandle, Abortable, Aborted, Either},
+    FutureExt,
+};
 use once_cell::sync::OnceCell;
 use pin_project_lite::pin_project;
-use pyo3::prelude::*;
+#[cfg(feature = "unstable-streams")]
+use pyo3::exceptions::PyValueError;
+use pyo3::{
+    exceptions::{PyKeyboardInterrupt, PyRuntimeError, PyStopIteration, PySystemExit},
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
 #[cfg(feature = "unstable-streams")]
 use std::marker::PhantomData;
 
@@ -63,6 +87,18 @@ pub trait SpawnLocalExt: Runtime {
         F: Future<Output = ()> + 'static;
 }
 
+/// Extension trait for async/await runtimes whose tasks are pinned to the thread they run on
+///
+/// Thread-per-core runtimes can't move a `!Send` future onto their executor, but they can move a
+/// `Send` closure there and build the future on the executor thread instead.
+pub trait SpawnPinnedExt: Runtime {
+    /// Spawn the `!Send` future created by `f` onto one of this runtime's executor threads
+    fn spawn_pinned<F, Fut>(f: F) -> Self::JoinHandle
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static;
+}
+
 /// Exposes the utilities necessary for using task-local data in the Runtime
 pub trait ContextExt: Runtime {
     /// Set the task locals for the given future
@@ -84,17 +120,21 @@ pub trait LocalContextExt: Runtime {
 
 /// Get the current event loop from either Python or Rust async task local context
 ///
-/// This function first checks if the runtime has a task-local reference to the Python event loop.
-/// If not, it calls [`get_running_loop`](crate::get_running_loop`) to get the event loop associated
-/// with the current OS thread.
+/// Within [`with_event_loop`](`crate::with_event_loop`), this returns the loop passed to it.
+/// Otherwise, it first checks if the runtime has a task-local reference to the Python event loop,
+/// and then whether a loop was set for the current OS thread with
+/// [`set_thread_event_loop`](`crate::set_thread_event_loop`). If not, it calls
+/// [`get_running_loop`](`crate::get_running_loop`) to get the event loop running on the thread.
 pub fn get_current_loop<R>(py: Python) -> PyResult<Bound<PyAny>>
 where
     R: ContextExt,
 {
-    if let Some(locals) = R::get_task_locals() {
-        Ok(locals.event_loop.into_bound(py))
+    if let Some(event_loop) = scoped_event_loop(py) {
+        Ok(event_loop)
+    } else if let Some(locals) = R::get_task_locals() {
+        Ok(locals.event_loop(py))
     } else {
-        get_running_loop(py)
+        current_loop(py)
     }
 }
 
@@ -104,17 +144,50 @@ pub fn get_current_locals<R>(py: Python) -> PyResult<TaskLocals>
 where
     R: ContextExt,
 {
-    if let Some(locals) = R::get_task_locals() {
+    if let Some(event_loop) = scoped_event_loop(py) {
+        TaskLocals::new(event_loop).copy_context_if_enabled(py)
+    } else if let Some(locals) = R::get_task_locals() {
         Ok(locals)
     } else {
-        Ok(TaskLocals::with_running_loop(py)?.copy_context(py)?)
+        current_locals(py)
     }
 }
 
+/// Set the task locals for the given future
+///
+/// Like the `scope` functions of the runtime modules, for any runtime that implements
+/// [`ContextExt`].
+pub async fn scope<R, F, T>(locals: TaskLocals, fut: F) -> T
+where
+    R: ContextExt,
+    F: Future<Output = T> + Send + 'static,
+{
+    R::scope(locals, fut).await
+}
+
+/// Set the task locals for the given !Send future
+///
+/// Like the `scope_local` functions of the runtime modules, for any runtime that implements
+/// [`LocalContextExt`]. [`get_current_locals`] returns the locals within `fut`, even across
+/// awaits that hold `!Send` values such as Python references.
+pub async fn scope_local<R, F, T>(locals: TaskLocals, fut: F) -> T
+where
+    R: ContextExt + LocalContextExt,
+    F: Future<Output = T> + 'static,
+{
+    R::scope_local(locals, fut).await
+}
+
 /// Run the event loop until the given Future completes
 ///
 /// After this function returns, the event loop can be resumed with [`run_until_complete`]
 ///
+/// A CTRL-C on the main thread stops the loop with a `KeyboardInterrupt` error, even on Windows'
+/// `ProactorEventLoop` (see [`run_forever`](crate::run_forever)), and cancels the future.
+///
+/// Calls that would deadlock raise a `RuntimeError` right away instead of hanging: from the thread
+/// of a running event loop, or from a task of the Rust runtime `R`. The future isn't run then.
+///
 /// # Arguments
 /// * `event_loop` - The Python event loop that should run the future
 /// * `fut` - The future to drive to completion
@@ -197,24 +270,72 @@ where
     T: Send + Sync + 'static,
 {
     let py = event_loop.py();
+    check_blocking::<R>(py, "run_until_complete")?;
+
     let result_tx = Arc::new(Mutex::new(None));
     let result_rx = Arc::clone(&result_tx);
     let coro = future_into_py_with_locals::<R, _, ()>(
         py,
-        TaskLocals::new(event_loop.clone()).copy_context(py)?,
+        TaskLocals::new(event_loop.clone()).copy_context_if_enabled(py)?,
         async move {
-            let val = fut.await?;
-            if let Ok(mut result) = result_tx.lock() {
-                *result = Some(val);
+            let result = match fut.await {
+                // asyncio takes a future that fails with these for a task that raised them out of
+                // the loop already, and leaves the loop running, so they're raised from here
+                Err(e) if Python::with_gil(|py| is_exit(py, &e)) => Err(e),
+                Err(e) => return Err(e),
+                Ok(val) => Ok(val),
+            };
+            if let Ok(mut slot) = result_tx.lock() {
+                *slot = Some(result);
             }
             Ok(())
         },
     )?;
 
-    event_loop.call_method1("run_until_complete", (coro,))?;
+    let wakeup = SignalWakeup::start(event_loop)?;
+    let completed = event_loop.call_method1("run_until_complete", (coro.clone(),));
+    drop(wakeup);
 
-    let result = result_rx.lock().unwrap().take().unwrap();
-    Ok(result)
+    if let Err(e) = completed {
+        // stop the Rust future if the loop was interrupted, e.g. by CTRL-C
+        if !coro.call_method0("done")?.is_truthy()? {
+            coro.call_method0("cancel")?;
+        }
+
+        shutdown::interrupted(py, &e);
+        return Err(e);
+    }
+
+    let result = result_rx.lock().unwrap().take();
+    result.unwrap()
+}
+
+/// Whether `err` is a `SystemExit` or `KeyboardInterrupt`, which asyncio raises out of the loop
+fn is_exit(py: Python, err: &PyErr) -> bool {
+    err.is_instance_of::<PySystemExit>(py) || err.is_instance_of::<PyKeyboardInterrupt>(py)
+}
+
+/// Raise instead of blocking in `entry` where it would deadlock
+///
+/// Besides the thread of a running event loop, a task of the Rust runtime can't block on a loop
+/// either: the loop waits for a future on that same runtime, which may need the blocked thread to
+/// make progress, e.g. on a single-threaded runtime.
+pub(crate) fn check_blocking<R>(py: Python, entry: &str) -> PyResult<()>
+where
+    R: ContextExt,
+{
+    check_not_in_running_loop(py, entry)?;
+
+    if R::get_task_locals().is_some() {
+        return Err(PyRuntimeError::new_err(format!(
+            "`{}` would deadlock: it blocks a task of the Rust runtime, which the event loop may \
+             need to complete the future. Await the future in the task instead, e.g. with \
+             `into_future`",
+            entry
+        )));
+    }
+
+    Ok(())
 }
 
 /// Run the event loop until the given Future completes
@@ -304,7 +425,10 @@ where
     F: Future<Output = PyResult<T>> + Send + 'static,
     T: Send + Sync + 'static,
 {
-    let event_loop = asyncio(py)?.call_method0("new_event_loop")?;
+    // before the loop is created, since closing it can't run the loop either
+    check_blocking::<R>(py, "run")?;
+
+    let event_loop = new_event_loop(py)?;
 
     let result = run_until_complete::<R, F, T>(&event_loop, fut);
 
@@ -313,46 +437,121 @@ where
     result
 }
 
-fn cancelled(future: &Bound<PyAny>) -> PyResult<bool> {
-    future.getattr("cancelled")?.call0()?.is_truthy()
-}
+const RUNNER_GLUE: &str = r#"
+async def wait(fut):
+    return await fut
+"#;
 
-#[pyclass]
-struct CheckedCompletor;
+/// Run the given Future on an `asyncio.Runner` (Python 3.11+)
+///
+/// The runner is left open, so it can run more futures (or coroutines) on the same event loop
+/// afterwards. Closing it is up to the caller.
+///
+/// # Arguments
+/// * `runner` - The `asyncio.Runner` to run the future on
+/// * `context` - The `contextvars.Context` to run the future in
+/// * `fut` - The future to drive to completion
+pub fn run_on_runner<R, F, T>(runner: &Bound<PyAny>, context: Bound<PyAny>, fut: F) -> PyResult<T>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    static GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
+    let py = runner.py();
+    check_blocking::<R>(py, "run_on_runner")?;
 
-#[pymethods]
-impl CheckedCompletor {
-    fn __call__(
-        &self,
-        future: &Bound<PyAny>,
-        complete: &Bound<PyAny>,
-        value: &Bound<PyAny>,
-    ) -> PyResult<()> {
-        if cancelled(future)? {
-            return Ok(());
-        }
+    let glue = GLUE_MOD
+        .get_or_try_init(|| -> PyResult<PyObject> {
+            Ok(PyModule::from_code_bound(
+                py,
+                RUNNER_GLUE,
+                "pyo3_asyncio/pyo3_asyncio_runner_glue.py",
+                "pyo3_asyncio_runner_glue",
+            )?
+            .into())
+        })?
+        .bind(py);
 
-        complete.call1((value,))?;
+    let result_tx = Arc::new(Mutex::new(None));
+    let result_rx = Arc::clone(&result_tx);
+    let py_fut = future_into_py_with_locals::<R, _, ()>(
+        py,
+        TaskLocals::new(runner.call_method0("get_loop")?).with_context(context.clone()),
+        async move {
+            let val = fut.await?;
+            if let Ok(mut result) = result_tx.lock() {
+                *result = Some(val);
+            }
+            Ok(())
+        },
+    )?;
 
-        Ok(())
+    // Runner.run only accepts coroutines
+    let kwargs = PyDict::new_bound(py);
+    kwargs.set_item("context", context)?;
+    let _wakeup = SignalWakeup::start(&runner.call_method0("get_loop")?)?;
+    if let Err(e) = runner.call_method(
+        "run",
+        (glue.call_method1("wait", (py_fut,))?,),
+        Some(&kwargs),
+    ) {
+        shutdown::interrupted(py, &e);
+        return Err(e);
     }
+
+    let result = result_rx.lock().unwrap().take().unwrap();
+    Ok(result)
 }
 
-fn set_result(
-    event_loop: &Bound<PyAny>,
-    future: &Bound<PyAny>,
-    result: PyResult<PyObject>,
-) -> PyResult<()> {
-    let py = event_loop.py();
-    let none = py.None().into_bound(py);
+/// Run the given Future on a new `asyncio.Runner` (Python 3.11+)
+///
+/// Unlike [`run`], which creates and closes the event loop by hand, this leaves the setup and
+/// teardown of the event loop to `asyncio.Runner`, so the loop can come from a custom loop factory
+/// and is shut down the same way as with `asyncio.run`.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `options` - The options for the runner
+/// * `fut` - The future to drive to completion
+pub fn run_with_runner<R, F, T>(py: Python, options: RunnerOptions, fut: F) -> PyResult<T>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    if options.loop_factory.is_none() {
+        install_requested_uvloop(py)?;
+    }
+    let runner = options.runner(py)?;
 
-    let (complete, val) = match result {
-        Ok(val) => (future.getattr("set_result")?, val.into_py(py)),
-        Err(err) => (future.getattr("set_exception")?, err.into_py(py)),
-    };
-    call_soon_threadsafe(event_loop, &none, (CheckedCompletor, future, complete, val))?;
+    let result = run_on_runner::<R, F, T>(&runner, options.context(py)?, fut);
 
-    Ok(())
+    runner.call_method0("close")?;
+
+    result
+}
+
+/// Spawn `fut` on the runtime after a `KeyboardInterrupt` cancelled the conversions in flight
+///
+/// This only happens if [`cancel_on_interrupt`](crate::cancel_on_interrupt) is enabled, and only
+/// once. Registering another future replaces the previous one.
+///
+/// # Arguments
+/// * `fut` - The shutdown future to be spawned
+#[allow(unused_must_use)]
+pub fn on_interrupt<R, F>(fut: F)
+where
+    R: Runtime,
+    F: Future<Output = ()> + Send + 'static,
+{
+    shutdown::set_on_interrupt(Box::new(move || {
+        R::spawn(fut);
+    }));
+}
+
+fn cancelled(future: &Bound<PyAny>) -> PyResult<bool> {
+    future.getattr("cancelled")?.call0()?.is_truthy()
 }
 
 /// Convert a Python `awaitable` into a Rust Future
@@ -460,20 +659,102 @@ fn set_result(
 ///     Ok(())
 /// }
 /// ```
-pub fn into_future<R>(
-    awaitable: Bound<PyAny>,
-) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+pub fn into_future<R>(awaitable: Bound<PyAny>) -> PyResult<PyFuture>
 where
     R: Runtime + ContextExt,
 {
     into_future_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
 }
 
+/// Convert a Python `awaitable` into a Rust Future resolving to how it finished with a generic
+/// runtime
+///
+/// This forwards the awaitable and the task locals returned by [`get_current_locals`] to
+/// [`into_future_result_with_locals`](`crate::into_future_result_with_locals`). See
+/// [`into_future_result_with_locals`](`crate::into_future_result_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_result<R>(awaitable: Bound<PyAny>) -> PyResult<PyOutcomeFuture>
+where
+    R: Runtime + ContextExt,
+{
+    into_future_result_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future resolving to its result extracted into `T` with
+/// a generic runtime
+///
+/// This forwards the awaitable and the task locals returned by [`get_current_locals`] to
+/// [`into_future_typed_with_locals`](`crate::into_future_typed_with_locals`). See
+/// [`into_future_typed_with_locals`](`crate::into_future_typed_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_typed<R, T>(awaitable: Bound<PyAny>) -> PyResult<PyFuture<T>>
+where
+    R: Runtime + ContextExt,
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    into_future_typed_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that steps it from the runtime with a generic
+/// runtime
+///
+/// This forwards the awaitable and the task locals returned by [`get_current_locals`] to
+/// [`into_future_polled_with_locals`](`crate::into_future_polled_with_locals`). See
+/// [`into_future_polled_with_locals`](`crate::into_future_polled_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_polled<R>(awaitable: Bound<PyAny>) -> PyResult<PyPolledFuture>
+where
+    R: Runtime + ContextExt,
+{
+    into_future_polled_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
+/// Run a synchronous Python function in an executor with a generic runtime
+///
+/// This forwards the function and the task locals returned by [`get_current_locals`] to
+/// [`run_in_executor_with_locals`](`crate::run_in_executor_with_locals`). See
+/// [`run_in_executor_with_locals`](`crate::run_in_executor_with_locals`) for more details.
+///
+/// # Arguments
+/// * `func` - The synchronous Python function to be called
+/// * `args` - The positional arguments for `func`
+pub fn run_in_executor<R>(func: Bound<PyAny>, args: impl IntoPy<Py<PyTuple>>) -> PyResult<PyFuture>
+where
+    R: Runtime + ContextExt,
+{
+    run_in_executor_with_locals(&get_current_locals::<R>(func.py())?, func, args)
+}
+
+/// Spawn a Python `awaitable` as a task and get a handle to it with a generic runtime
+///
+/// This forwards the awaitable and the task locals returned by [`get_current_locals`] to
+/// [`spawn_py_with_locals`](`crate::spawn_py_with_locals`). See
+/// [`spawn_py_with_locals`](`crate::spawn_py_with_locals`) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be spawned
+pub fn spawn_py<R, T>(awaitable: Bound<PyAny>) -> PyResult<PyTask<T>>
+where
+    R: Runtime + ContextExt,
+{
+    spawn_py_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
 /// Convert a Rust Future into a Python awaitable with a generic runtime
 ///
 /// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
 /// the Rust future will be cancelled as well (new behaviour in `v0.15`).
 ///
+/// Unless [`set_rust_futures`](crate::set_rust_futures) is disabled, a conversion made from a
+/// coroutine that runs in a plain `asyncio.Task` returns a future implemented in Rust instead,
+/// whose result is set without going through an `asyncio.Future`.
+///
 /// Python `contextvars` are preserved when calling async Python functions within the Rust future
 /// via [`into_future`] (new behaviour in `v0.15`).
 ///
@@ -583,75 +864,120 @@ where
     F: Future<Output = PyResult<T>> + Send + 'static,
     T: IntoPy<PyObject>,
 {
-    let (cancel_tx, cancel_rx) = oneshot::channel();
+    // a cancellation aborts the Rust future like a shutdown does, which saves a channel for it
+    let (abort, abort_registration) = AbortHandle::new_pair();
 
-    let py_fut = create_future(locals.event_loop.bind(py).clone())?;
-    py_fut.call_method1(
-        "add_done_callback",
-        (PyDoneCallback {
-            cancel_tx: Some(cancel_tx),
-        },),
+    let py_fut = if awaited_by_plain_task(py, &locals)? {
+        debug::count(&debug::RUST_FUTURES);
+        // the future aborts the conversion itself once it's cancelled
+        RustFuture::new_bound(py, locals.clone(), abort.clone())?.into_any()
+    } else {
+        let py_fut = locals.create_future(py)?;
+        py_fut.call_method1(
+            "add_done_callback",
+            (PyAbortCallback {
+                abort: abort.clone(),
+            },),
+        )?;
+        py_fut
+    };
+
+    let registration = register_abortable(
+        locals.py_event_loop().bind(py),
+        Target::Future(py_fut.clone().unbind()),
+        (abort, abort_registration),
     )?;
+    spawn_into_py_future::<R, _, T>(locals, &py_fut, registration, fut);
 
-    let future_tx1 = PyObject::from(py_fut.clone());
-    let future_tx2 = future_tx1.clone_ref(py);
+    Ok(py_fut)
+}
+
+/// Run `fut` in a task scope and resolve `py_fut` with its output, or with the error for
+/// [aborted conversions](crate::err::set_abort_error) if it panics or is dropped
+#[allow(unused_must_use)]
+fn spawn_into_py_future<R, F, T>(
+    locals: TaskLocals,
+    py_fut: &Bound<PyAny>,
+    mut registration: Registration,
+    fut: F,
+) where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    debug::count(&debug::FUTURES_INTO_PY);
+    // the panic is caught within the task, where its backtrace is
+    let fut = Abortable::new(catch_panic(fut), registration.abort_registration());
+
+    let resolver = Resolver {
+        locals: locals.clone(),
+        py_fut: Some(py_fut.clone().unbind()),
+    };
 
     R::spawn(async move {
-        let locals2 = Python::with_gil(|py| locals.clone_ref(py));
+        let result = match R::scope(locals, fut).await {
+            Ok(result) => result,
+            // the asyncio.Future is cancelled by whoever aborted the conversion
+            Err(Aborted) => return resolver.forget(),
+        };
+
+        Python::with_gil(move |py| {
+            // the future's own panics are caught by `catch_panic`, this catches the conversion's
+            let result =
+                panic::catch_unwind(AssertUnwindSafe(|| result.map(|val| val.into_py(py))))
+                    .unwrap_or_else(|payload| Err(abort_error(AbortReason::Panic(payload))));
+            resolver.resolve(py, result);
+            // the registration holds the loop and the future, and releasing them while we hold
+            // the GIL saves pyo3 deferring it
+            drop(registration);
+        });
+    });
+}
 
-        if let Err(e) = R::spawn(async move {
-            let result = R::scope(
-                Python::with_gil(|py| locals2.clone_ref(py)),
-                Cancellable::new_with_cancel_rx(fut, cancel_rx),
-            )
-            .await;
+/// Resolves the Python future of a conversion from its task
+///
+/// If the task is dropped by its runtime before it got to resolve the future, e.g. because the
+/// executor shut down, the error for [aborted conversions](crate::err::set_abort_error) is queued
+/// for the future instead, and set by a drain of its loop. This saves spawning a second task per
+/// conversion that waits for the first one to find out.
+struct Resolver {
+    locals: TaskLocals,
+    py_fut: Option<PyObject>,
+}
 
-            Python::with_gil(move |py| {
-                if cancelled(future_tx1.bind(py))
-                    .map_err(dump_err(py))
-                    .unwrap_or(false)
-                {
-                    return;
-                }
+impl Resolver {
+    fn resolve(mut self, py: Python, result: PyResult<PyObject>) {
+        if let Some(py_fut) = self.py_fut.take() {
+            let _ = set_result(&self.locals, py_fut.bind(py), result).map_err(dump_err(py));
+        }
+    }
 
-                let _ = set_result(
-                    &locals2.event_loop(py),
-                    future_tx1.bind(py),
-                    result.map(|val| val.into_py(py)),
-                )
-                .map_err(dump_err(py));
-            });
-        })
-        .await
-        {
-            if e.is_panic() {
-                Python::with_gil(move |py| {
-                    if cancelled(future_tx2.bind(py))
-                        .map_err(dump_err(py))
-                        .unwrap_or(false)
-                    {
-                        return;
-                    }
+    /// Leave the future to whoever aborted the conversion
+    fn forget(mut self) {
+        self.py_fut = None;
+    }
+}
 
-                    let panic_message = format!(
-                        "rust future panicked: {}",
-                        get_panic_message(&e.into_panic())
-                    );
-                    let _ = set_result(
-                        locals.event_loop.bind(py),
-                        future_tx2.bind(py),
-                        Err(RustPanic::new_err(panic_message)),
-                    )
-                    .map_err(dump_err(py));
-                });
-            }
+impl Drop for Resolver {
+    fn drop(&mut self) {
+        // the GIL isn't taken here, since the runtime may drop the task while another thread holds
+        // the GIL and waits for the runtime to shut down
+        if let Some(py_fut) = self.py_fut.take() {
+            completions::set_dropped(&self.locals, py_fut);
         }
-    });
+    }
+}
 
-    Ok(py_fut)
+/// Get the reason why the task of a conversion stopped before it completed
+pub(crate) fn abort_reason<E: JoinError>(e: E) -> AbortReason {
+    if e.is_panic() {
+        AbortReason::Panic(e.into_panic())
+    } else {
+        AbortReason::Dropped
+    }
 }
 
-fn get_panic_message(any: &dyn std::any::Any) -> &str {
+pub(crate) fn get_panic_message(any: &dyn std::any::Any) -> &str {
     if let Some(str_slice) = any.downcast_ref::<&str>() {
         str_slice
     } else if let Some(string) = any.downcast_ref::<String>() {
@@ -724,6 +1050,21 @@ where
     }
 }
 
+/// Aborts the Rust future of a conversion once its `asyncio.Future` is cancelled
+#[pyclass(frozen)]
+struct PyAbortCallback {
+    abort: AbortHandle,
+}
+
+#[pymethods]
+impl PyAbortCallback {
+    fn __call__(&self, fut: &Bound<PyAny>) {
+        if cancelled(fut).map_err(dump_err(fut.py())).unwrap_or(false) {
+            self.abort.abort();
+        }
+    }
+}
+
 #[pyclass]
 struct PyDoneCallback {
     cancel_tx: Option<oneshot::Sender<()>>,
@@ -849,30 +1190,851 @@ where
     future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
 }
 
-/// Convert a `!Send` Rust Future into a Python awaitable with a generic runtime and manual
-/// specification of task locals.
+/// Convert a Rust Future that fails with a Rust error into a Python awaitable with a generic
+/// runtime and manual scope
 ///
-/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
-/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+/// Like [`future_into_py_with_locals`], but the error of the future can be any error that converts
+/// into a `Box<dyn Error + Send + Sync>`, e.g. an `eyre::Report` or an error type of the
+/// application. It's raised in Python as the exception of
+/// [`translate_boxed_error`](crate::err::translate_boxed_error), so it goes through the
+/// [error translator](crate::err::set_error_translator). For an `anyhow::Error`, use
+/// [`anyhow_future_into_py_with_locals`] instead.
 ///
-/// Python `contextvars` are preserved when calling async Python functions within the Rust future
-/// via [`into_future`] (new behaviour in `v0.15`).
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+pub fn try_future_into_py_with_locals<R, F, T, E>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    future_into_py_with_locals::<R, _, T>(py, locals, async move {
+        fut.await.map_err(translate_boxed_error)
+    })
+}
+
+/// Convert a Rust Future that fails with a Rust error into a Python awaitable with a generic
+/// runtime
 ///
-/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
-/// > unfortunately fail to resolve them when called within the Rust future. This is because the
-/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
-/// >
-/// > As a workaround, you can get the `contextvars` from the current task locals using
-/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
-/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
-/// > synchronous function, and restore the previous context when it returns or raises an exception.
+/// See [`try_future_into_py_with_locals`] for how the error is raised.
 ///
 /// # Arguments
-/// * `py` - PyO3 GIL guard
-/// * `locals` - The task locals for the future
+/// * `py` - The current PyO3 GIL guard
 /// * `fut` - The Rust future to be converted
+pub fn try_future_into_py<R, F, T, E>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    try_future_into_py_with_locals::<R, F, T, E>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>anyhow</code></span> Convert a Rust Future that fails with an `anyhow::Error` into a Python awaitable with a generic runtime and manual scope
 ///
-/// # Examples
+/// Like [`future_into_py_with_locals`], but the error of the future is raised in Python as the
+/// exception of [`translate_anyhow_error`](crate::err::translate_anyhow_error), so the
+/// [error translator](crate::err::set_error_translator) gets the error that the `anyhow::Error`
+/// wraps.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+#[cfg(feature = "anyhow")]
+pub fn anyhow_future_into_py_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = anyhow::Result<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_with_locals::<R, _, T>(py, locals, async move {
+        fut.await.map_err(crate::err::translate_anyhow_error)
+    })
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>anyhow</code></span> Convert a Rust Future that fails with an `anyhow::Error` into a Python awaitable with a generic runtime
+///
+/// See [`anyhow_future_into_py_with_locals`] for how the error is raised.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+#[cfg(feature = "anyhow")]
+pub fn anyhow_future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = anyhow::Result<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    anyhow_future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable that can't be cancelled from Python, with a
+/// generic runtime
+///
+/// This mirrors `asyncio.shield`. Returns a `(shielded, handle)` pair:
+///
+/// - `shielded` is what the caller should await. Cancelling it raises `asyncio.CancelledError` in
+///   the awaiter, but the Rust future keeps running.
+/// - `handle` is the `asyncio.Future` of the Rust future itself. It can be awaited later to get
+///   the result even if `shielded` was cancelled. Cancelling `handle` explicitly still drops the
+///   Rust future.
+///
+/// This is meant for work that mustn't be interrupted halfway, like commits or cleanup. In the
+/// other direction, a Python awaitable that's converted into a Rust future can be shielded with
+/// [`PyFuture::detach`](crate::PyFuture::detach).
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task-local data for Python
+/// * `fut` - The Rust future to be converted
+pub fn shielded_future_into_py_with_locals<'py, R, F, T>(
+    py: Python<'py>,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>)>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let handle = future_into_py_with_locals::<R, F, T>(py, locals, fut)?;
+    let shielded = asyncio(py)?.call_method1("shield", (&handle,))?;
+
+    Ok((shielded, handle))
+}
+
+/// Convert a Rust Future into a Python awaitable that can't be cancelled from Python, with a
+/// generic runtime
+///
+/// See [`shielded_future_into_py_with_locals`] for how the returned pair is used.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+pub fn shielded_future_into_py<R, F, T>(
+    py: Python,
+    fut: F,
+) -> PyResult<(Bound<PyAny>, Bound<PyAny>)>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    shielded_future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// Fires when the Python awaitable of a Rust future is cancelled
+///
+/// A handle is passed to the closure given to [`cancellable_future_into_py_with_locals`]. The
+/// handle can be cloned and moved into other tasks, all of them see the cancellation.
+#[derive(Clone)]
+pub struct CancelHandle {
+    cancelled: future::Shared<oneshot::Receiver<()>>,
+}
+
+impl CancelHandle {
+    /// Check whether the Python awaitable has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.cancelled.clone().now_or_never(), Some(Ok(())))
+    }
+
+    /// Wait until the Python awaitable is cancelled
+    ///
+    /// This never resolves if the awaitable completes without being cancelled.
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + 'static {
+        let cancelled = self.cancelled.clone();
+
+        async move {
+            if cancelled.await.is_err() {
+                future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+/// Convert a Rust Future that reacts to cancellation into a Python awaitable with a generic
+/// runtime
+///
+/// Unlike [`future_into_py_with_locals`], the Rust future isn't dropped when the `asyncio.Future`
+/// is cancelled. Instead, the [`CancelHandle`] passed to `f` fires, so that the future can clean
+/// up and return early. Its output is discarded, since the `asyncio.Future` is already done.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task-local data for Python
+/// * `f` - The function that creates the Rust future from a [`CancelHandle`]
+pub fn cancellable_future_into_py_with_locals<R, F, Fut, T>(
+    py: Python,
+    locals: TaskLocals,
+    f: F,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: FnOnce(CancelHandle) -> Fut,
+    Fut: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+
+    let py_fut = locals.create_future(py)?;
+    py_fut.call_method1(
+        "add_done_callback",
+        (PyDoneCallback {
+            cancel_tx: Some(cancel_tx),
+        },),
+    )?;
+
+    let handle = CancelHandle {
+        cancelled: cancel_rx.shared(),
+    };
+    let registration = register(
+        locals.py_event_loop().bind(py),
+        Target::Future(py_fut.clone().unbind()),
+    )?;
+    spawn_into_py_future::<R, _, T>(locals, &py_fut, registration, f(handle));
+
+    Ok(py_fut)
+}
+
+/// Convert a Rust Future that reacts to cancellation into a Python awaitable with a generic
+/// runtime
+///
+/// See [`cancellable_future_into_py_with_locals`] for how cancellation is handled.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - The function that creates the Rust future from a [`CancelHandle`]
+pub fn cancellable_future_into_py<R, F, Fut, T>(py: Python, f: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: FnOnce(CancelHandle) -> Fut,
+    Fut: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    cancellable_future_into_py_with_locals::<R, F, Fut, T>(py, get_current_locals::<R>(py)?, f)
+}
+
+/// Resolve a new `asyncio.Future` with the output of `fut`
+///
+/// Unlike [`future_into_py_with_locals`], this doesn't set up a task scope or catch panics, since
+/// `fut` only waits for a value that's produced elsewhere. `fut` is dropped if the `asyncio.Future`
+/// is cancelled.
+#[allow(unused_must_use)]
+pub(crate) fn completion_into_py<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+
+    let py_fut = locals.create_future(py)?;
+    py_fut.call_method1(
+        "add_done_callback",
+        (PyDoneCallback {
+            cancel_tx: Some(cancel_tx),
+        },),
+    )?;
+
+    let future_tx = PyObject::from(py_fut.clone());
+
+    R::spawn(async move {
+        let result = match future::select(Box::pin(fut), cancel_rx).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => return,
+        };
+
+        Python::with_gil(move |py| {
+            let _ = set_result(
+                &locals,
+                future_tx.bind(py),
+                result.map(|val| val.into_py(py)),
+            )
+            .map_err(dump_err(py));
+        });
+    });
+
+    Ok(py_fut)
+}
+
+pub(crate) fn sender_dropped() -> PyErr {
+    ChannelClosed::new_err("the sender was dropped without sending a value")
+}
+
+/// Convert the receiving end of a oneshot channel into an `asyncio.Future` with the given task
+/// locals
+///
+/// This is a lighter alternative to [`future_into_py_with_locals`] for values that are produced
+/// elsewhere, e.g. by a callback or by a task that's already running. The future is resolved
+/// threadsafely on the event loop in `locals` as soon as the value is sent. If the sender is
+/// dropped without sending a value, the future raises
+/// [`ChannelClosed`](crate::err::ChannelClosed). Cancelling the future drops the receiver.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals holding the event loop to resolve the future on
+/// * `rx` - The receiving end of the channel
+pub fn oneshot_into_py_with_locals<R, T>(
+    py: Python,
+    locals: TaskLocals,
+    rx: oneshot::Receiver<T>,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime,
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    // dropping the receiver on cancellation lets the sender know that nobody's waiting
+    completion_into_py::<R, _, T>(
+        py,
+        locals,
+        async move { rx.await.map_err(|_| sender_dropped()) },
+    )
+}
+
+/// Convert the receiving end of a oneshot channel into an `asyncio.Future`
+///
+/// See [`oneshot_into_py_with_locals`] for details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `rx` - The receiving end of the channel
+pub fn oneshot_into_py<R, T>(py: Python, rx: oneshot::Receiver<T>) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    oneshot_into_py_with_locals::<R, T>(py, get_current_locals::<R>(py)?, rx)
+}
+
+const CALLBACK_GLUE: &str = r#"
+async def call(start, args, kwargs):
+    return await start(*args, **kwargs)
+"#;
+
+type CallbackFuture = Pin<Box<dyn Future<Output = PyResult<PyObject>> + Send>>;
+
+type CallbackFn =
+    dyn Fn(&Bound<PyTuple>, Option<&Bound<PyDict>>) -> PyResult<CallbackFuture> + Send + Sync;
+
+// pins down the signature of the closure, so that it's generic over the lifetimes of the arguments
+fn callback_fn<F>(f: F) -> Box<CallbackFn>
+where
+    F: Fn(&Bound<PyTuple>, Option<&Bound<PyDict>>) -> PyResult<CallbackFuture>
+        + Send
+        + Sync
+        + 'static,
+{
+    Box::new(f)
+}
+
+/// Python callable returned by [`py_async_callback`]
+#[pyclass]
+struct PyAsyncCallback {
+    f: Box<CallbackFn>,
+    // future_into_py_with_locals for the runtime the callback was created with
+    future_into_py:
+        for<'p> fn(Python<'p>, TaskLocals, CallbackFuture) -> PyResult<Bound<'p, PyAny>>,
+}
+
+#[pymethods]
+impl PyAsyncCallback {
+    #[pyo3(signature = (*args, **kwargs))]
+    fn __call__<'p>(
+        slf: &Bound<'p, Self>,
+        args: &Bound<'p, PyTuple>,
+        kwargs: Option<&Bound<'p, PyDict>>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        static GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
+        let py = slf.py();
+        let glue = GLUE_MOD
+            .get_or_try_init(|| -> PyResult<PyObject> {
+                Ok(PyModule::from_code_bound(
+                    py,
+                    CALLBACK_GLUE,
+                    "pyo3_asyncio/pyo3_asyncio_callback_glue.py",
+                    "pyo3_asyncio_callback_glue",
+                )?
+                .into())
+            })?
+            .bind(py);
+
+        // the Rust future is only created once the coroutine is awaited
+        glue.call_method1("call", (slf.getattr("_start")?, args, kwargs))
+    }
+
+    #[pyo3(signature = (*args, **kwargs))]
+    fn _start<'p>(
+        &self,
+        py: Python<'p>,
+        args: &Bound<'p, PyTuple>,
+        kwargs: Option<&Bound<'p, PyDict>>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let fut = (self.f)(args, kwargs)?;
+        let locals = TaskLocals::with_running_loop(py)?.copy_context_if_enabled(py)?;
+
+        (self.future_into_py)(py, locals, fut)
+    }
+}
+
+/// Wrap a Rust async function into a Python callable that returns a coroutine
+///
+/// Calling the returned object from Python calls `f` with the positional and keyword arguments of
+/// the call, and returns a coroutine that awaits the future returned by `f` on the event loop the
+/// coroutine runs on. `f` is only called once the coroutine is awaited, like an `async def`
+/// function. This makes it possible to register Rust handlers with Python frameworks that expect
+/// async callbacks without writing a `#[pyclass]` for each of them.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - The function called with the arguments of each call
+pub fn py_async_callback<R, F, Fut, T>(py: Python, f: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Fn(&Bound<PyTuple>, Option<&Bound<PyDict>>) -> PyResult<Fut> + Send + Sync + 'static,
+    Fut: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let f = callback_fn(move |args, kwargs| {
+        let fut = f(args, kwargs)?;
+
+        Ok(Box::pin(async move {
+            let val = fut.await?;
+            Ok(Python::with_gil(|py| val.into_py(py)))
+        }) as CallbackFuture)
+    });
+
+    Ok(Bound::new(
+        py,
+        PyAsyncCallback {
+            f,
+            future_into_py: future_into_py_with_locals::<R, CallbackFuture, PyObject>,
+        },
+    )?
+    .into_any())
+}
+
+struct AwaitableState {
+    result: Option<PyResult<PyObject>>,
+    done: bool,
+    callbacks: Vec<PyObject>,
+}
+
+fn complete_awaitable(py: Python, state: &Mutex<AwaitableState>, result: PyResult<PyObject>) {
+    let callbacks = {
+        let mut state = state.lock().unwrap();
+        state.result = Some(result);
+        state.done = true;
+        std::mem::take(&mut state.callbacks)
+    };
+
+    for callback in callbacks {
+        let _ = callback.call0(py).map_err(dump_err(py));
+    }
+}
+
+/// Awaitable returned by [`future_into_awaitable`]
+///
+/// This implements the iterator side of the `__await__` protocol directly, so it can be awaited by
+/// anything that drives coroutines with `send` / `throw`.
+#[pyclass(frozen)]
+struct RustAwaitable {
+    state: Arc<Mutex<AwaitableState>>,
+    abort: AbortHandle,
+}
+
+impl RustAwaitable {
+    fn poll(&self, py: Python) -> PyResult<PyObject> {
+        let mut state = self.state.lock().unwrap();
+
+        if !state.done {
+            // a bare yield hands control back to the scheduler, which resumes us later
+            return Ok(py.None());
+        }
+
+        match state.result.take() {
+            Some(Ok(val)) => Err(PyStopIteration::new_err((val,))),
+            Some(Err(e)) => Err(e),
+            None => Err(PyRuntimeError::new_err(
+                "cannot reuse already awaited rust future",
+            )),
+        }
+    }
+}
+
+#[pymethods]
+impl RustAwaitable {
+    fn __await__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python) -> PyResult<PyObject> {
+        self.poll(py)
+    }
+
+    fn send(&self, py: Python, _value: &Bound<PyAny>) -> PyResult<PyObject> {
+        self.poll(py)
+    }
+
+    #[pyo3(signature = (typ, val=None, _tb=None))]
+    fn throw(
+        &self,
+        typ: Bound<PyAny>,
+        val: Option<Bound<PyAny>>,
+        _tb: Option<Bound<PyAny>>,
+    ) -> PyResult<PyObject> {
+        // the awaiting task was cancelled or otherwise failed, so the result isn't needed anymore
+        self.abort.abort();
+
+        match val {
+            Some(val) if !val.is_none() => Err(PyErr::from_value_bound(val)),
+            _ => Err(PyErr::from_value_bound(typ)),
+        }
+    }
+
+    fn close(&self) {
+        self.abort.abort();
+    }
+
+    /// Check whether the Rust future has completed
+    fn done(&self) -> bool {
+        self.state.lock().unwrap().done
+    }
+
+    /// Call `callback` without arguments once the Rust future has completed
+    ///
+    /// The callback is called on a Rust runtime thread, or right away if the future is done.
+    fn add_done_callback(&self, py: Python, callback: PyObject) -> PyResult<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+
+            if !state.done {
+                state.callbacks.push(callback);
+                return Ok(());
+            }
+        }
+
+        callback.call0(py)?;
+        Ok(())
+    }
+}
+
+impl Drop for RustAwaitable {
+    fn drop(&mut self) {
+        self.abort.abort();
+    }
+}
+
+/// Convert a Rust Future into a Python awaitable that doesn't need an event loop
+///
+/// Unlike [`future_into_py`], which returns an `asyncio.Future` that is completed on its event loop
+/// with `call_soon_threadsafe`, this returns an object that implements the `__await__` protocol on
+/// its own. This makes it possible to await Rust futures under schedulers that aren't `asyncio`,
+/// as long as they drive coroutines with `send` / `throw`:
+///
+/// - While the Rust future is pending, awaiting the object yields `None` to the scheduler, which
+///   should resume the awaiting coroutine later. Schedulers that don't want to poll can park the
+///   coroutine and resume it from a callback registered with `add_done_callback`, which is called
+///   on a Rust runtime thread once the future completes.
+/// - Throwing an exception into the awaitable (which is how most schedulers cancel a coroutine),
+///   closing it or dropping it cancels the Rust future.
+///
+/// Since there is no event loop, the future isn't given any task locals, so the conversions that
+/// look up the current event loop won't work inside of it.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+#[allow(unused_must_use)]
+pub fn future_into_awaitable<R, F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let state = Arc::new(Mutex::new(AwaitableState {
+        result: None,
+        done: false,
+        callbacks: Vec::new(),
+    }));
+    let (abort, registration) = AbortHandle::new_pair();
+
+    let awaitable = Bound::new(
+        py,
+        RustAwaitable {
+            state: state.clone(),
+            abort,
+        },
+    )?;
+
+    R::spawn(async move {
+        let state2 = state.clone();
+
+        let joined = R::spawn(async move {
+            // nobody is waiting for the result if the future was aborted
+            if let Ok(result) = Abortable::new(fut, registration).await {
+                Python::with_gil(|py| {
+                    complete_awaitable(py, &state2, result.map(|val| val.into_py(py)));
+                });
+            }
+        })
+        .await;
+
+        if let Err(e) = joined {
+            let err = abort_error(abort_reason(e));
+            Python::with_gil(|py| complete_awaitable(py, &state, Err(err)));
+        }
+    });
+
+    Ok(awaitable.into_any())
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable with a generic runtime and manual
+/// specification of task locals.
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`] (new behaviour in `v0.15`).
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// > unfortunately fail to resolve them when called within the Rust future. This is because the
+/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// > synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the future
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{any::Any, task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_async_runtimes::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// #     fn into_panic(self) -> Box<(dyn Any + Send + 'static)> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl SpawnLocalExt for MyCustomRuntime {
+/// #     fn spawn_local<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl LocalContextExt for MyCustomRuntime {
+/// #     fn scope_local<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R>>>
+/// #     where
+/// #         F: Future<Output = R> + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// use std::{rc::Rc, time::Duration};
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for(py: Python, secs: u64) -> PyResult<Bound<PyAny>> {
+///     // Rc is !Send so it cannot be passed into pyo3_async_runtimes::generic::future_into_py
+///     let secs = Rc::new(secs);
+///
+///     pyo3_async_runtimes::generic::local_future_into_py_with_locals::<MyCustomRuntime, _, _>(
+///         py,
+///         pyo3_async_runtimes::generic::get_current_locals::<MyCustomRuntime>(py)?,
+///         async move {
+///             MyCustomRuntime::sleep(Duration::from_secs(*secs)).await;
+///             Ok(())
+///         }
+///     )
+/// }
+/// ```
+#[deprecated(
+    since = "0.18.0",
+    note = "Questionable whether these conversions have real-world utility (see https://github.com/awestlake87/pyo3-asyncio/issues/59#issuecomment-1008038497 and let me know if you disagree!)"
+)]
+#[allow(unused_must_use)]
+pub fn local_future_into_py_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + SpawnLocalExt + LocalContextExt,
+    F: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+
+    let py_fut = locals.create_future(py)?;
+    py_fut.call_method1(
+        "add_done_callback",
+        (PyDoneCallback {
+            cancel_tx: Some(cancel_tx),
+        },),
+    )?;
+
+    let mut registration = register(
+        locals.py_event_loop().bind(py),
+        Target::Future(py_fut.clone().unbind()),
+    )?;
+    debug::count(&debug::FUTURES_INTO_PY);
+    let abort_registration = registration.abort_registration();
+
+    let future_tx1 = PyObject::from(py_fut.clone());
+    let future_tx2 = future_tx1.clone_ref(py);
+
+    R::spawn_local(async move {
+        let _registration = registration;
+        let locals2 = locals.clone();
+
+        if let Err(e) = R::spawn_local(async move {
+            let result = match R::scope_local(
+                locals2.clone(),
+                Abortable::new(
+                    Cancellable::new_with_cancel_rx(fut, cancel_rx),
+                    abort_registration,
+                ),
+            )
+            .await
+            {
+                Ok(result) => result,
+                // the asyncio.Future is cancelled by whoever aborted the conversion
+                Err(Aborted) => return,
+            };
+
+            Python::with_gil(move |py| {
+                let _ = set_result(
+                    &locals2,
+                    future_tx1.bind(py),
+                    result.map(|val| val.into_py(py)),
+                )
+                .map_err(dump_err(py));
+            });
+        })
+        .await
+        {
+            Python::with_gil(move |py| {
+                let _ = set_result(
+                    &locals,
+                    future_tx2.bind(py),
+                    Err(abort_error(abort_reason(e))),
+                )
+                .map_err(dump_err(py));
+            });
+        }
+    });
+
+    Ok(py_fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable with a generic runtime
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`] (new behaviour in `v0.15`).
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// > unfortunately fail to resolve them when called within the Rust future. This is because the
+/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// > synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
 ///
 /// ```no_run
 /// # use std::{any::Any, task::{Context, Poll}, pin::Pin, future::Future};
@@ -963,34 +2125,159 @@ where
 ///     // Rc is !Send so it cannot be passed into pyo3_async_runtimes::generic::future_into_py
 ///     let secs = Rc::new(secs);
 ///
-///     pyo3_async_runtimes::generic::local_future_into_py_with_locals::<MyCustomRuntime, _, _>(
+///     pyo3_async_runtimes::generic::local_future_into_py::<MyCustomRuntime, _, _>(py, async move {
+///         MyCustomRuntime::sleep(Duration::from_secs(*secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+#[deprecated(
+    since = "0.18.0",
+    note = "Questionable whether these conversions have real-world utility (see https://github.com/awestlake87/pyo3-asyncio/issues/59#issuecomment-1008038497 and let me know if you disagree!)"
+)]
+#[allow(deprecated)]
+pub fn local_future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt + SpawnLocalExt + LocalContextExt,
+    F: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    local_future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// Convert a `!Send` Rust Future into a Python awaitable with a generic thread-per-core runtime
+/// and manual specification of task locals.
+///
+/// Unlike [`future_into_py_with_locals`], the future is created by `f` on the runtime's executor
+/// thread, so it is free to hold `!Send` state across await points.
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`].
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the future
+/// * `f` - Creates the Rust future to be converted on the executor thread
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::{any::Any, task::{Context, Poll}, pin::Pin, future::Future};
+/// #
+/// # use pyo3_async_runtimes::{
+/// #     TaskLocals,
+/// #     generic::{JoinError, ContextExt, LocalContextExt, Runtime, SpawnPinnedExt}
+/// # };
+/// #
+/// # struct MyCustomJoinError;
+/// #
+/// # impl JoinError for MyCustomJoinError {
+/// #     fn is_panic(&self) -> bool {
+/// #         unreachable!()
+/// #     }
+/// #     fn into_panic(self) -> Box<(dyn Any + Send + 'static)> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomJoinHandle;
+/// #
+/// # impl Future for MyCustomJoinHandle {
+/// #     type Output = Result<(), MyCustomJoinError>;
+/// #
+/// #     fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # struct MyCustomRuntime;
+/// #
+/// # impl MyCustomRuntime {
+/// #     async fn sleep(_: Duration) {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl Runtime for MyCustomRuntime {
+/// #     type JoinError = MyCustomJoinError;
+/// #     type JoinHandle = MyCustomJoinHandle;
+/// #
+/// #     fn spawn<F>(fut: F) -> Self::JoinHandle
+/// #     where
+/// #         F: Future<Output = ()> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl ContextExt for MyCustomRuntime {
+/// #     fn scope<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R> + Send>>
+/// #     where
+/// #         F: Future<Output = R> + Send + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// #     fn get_task_locals() -> Option<TaskLocals> {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl SpawnPinnedExt for MyCustomRuntime {
+/// #     fn spawn_pinned<F, Fut>(f: F) -> Self::JoinHandle
+/// #     where
+/// #         F: FnOnce() -> Fut + Send + 'static,
+/// #         Fut: Future<Output = ()> + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// # impl LocalContextExt for MyCustomRuntime {
+/// #     fn scope_local<F, R>(locals: TaskLocals, fut: F) -> Pin<Box<dyn Future<Output = R>>>
+/// #     where
+/// #         F: Future<Output = R> + 'static
+/// #     {
+/// #         unreachable!()
+/// #     }
+/// # }
+/// #
+/// use std::{rc::Rc, time::Duration};
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for(py: Python, secs: u64) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::generic::pinned_future_into_py_with_locals::<MyCustomRuntime, _, _, _>(
 ///         py,
 ///         pyo3_async_runtimes::generic::get_current_locals::<MyCustomRuntime>(py)?,
-///         async move {
+///         move || async move {
+///             // Rc is !Send so it could not be held by a future passed to `future_into_py`
+///             let secs = Rc::new(secs);
 ///             MyCustomRuntime::sleep(Duration::from_secs(*secs)).await;
 ///             Ok(())
 ///         }
 ///     )
 /// }
 /// ```
-#[deprecated(
-    since = "0.18.0",
-    note = "Questionable whether these conversions have real-world utility (see https://github.com/awestlake87/pyo3-asyncio/issues/59#issuecomment-1008038497 and let me know if you disagree!)"
-)]
 #[allow(unused_must_use)]
-pub fn local_future_into_py_with_locals<R, F, T>(
+pub fn pinned_future_into_py_with_locals<R, F, Fut, T>(
     py: Python,
     locals: TaskLocals,
-    fut: F,
+    f: F,
 ) -> PyResult<Bound<PyAny>>
 where
-    R: Runtime + SpawnLocalExt + LocalContextExt,
-    F: Future<Output = PyResult<T>> + 'static,
+    R: Runtime + SpawnPinnedExt + LocalContextExt,
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = PyResult<T>> + 'static,
     T: IntoPy<PyObject>,
 {
     let (cancel_tx, cancel_rx) = oneshot::channel();
 
-    let py_fut = create_future(locals.event_loop.clone_ref(py).into_bound(py))?;
+    let py_fut = locals.create_future(py)?;
     py_fut.call_method1(
         "add_done_callback",
         (PyDoneCallback {
@@ -998,29 +2285,38 @@ where
         },),
     )?;
 
+    let mut registration = register(
+        locals.py_event_loop().bind(py),
+        Target::Future(py_fut.clone().unbind()),
+    )?;
+    debug::count(&debug::FUTURES_INTO_PY);
+    let abort_registration = registration.abort_registration();
+
     let future_tx1 = PyObject::from(py_fut.clone());
     let future_tx2 = future_tx1.clone_ref(py);
 
-    R::spawn_local(async move {
-        let locals2 = Python::with_gil(|py| locals.clone_ref(py));
-
-        if let Err(e) = R::spawn_local(async move {
-            let result = R::scope_local(
-                Python::with_gil(|py| locals2.clone_ref(py)),
-                Cancellable::new_with_cancel_rx(fut, cancel_rx),
+    R::spawn(async move {
+        let _registration = registration;
+        let locals2 = locals.clone();
+
+        if let Err(e) = R::spawn_pinned(move || async move {
+            let result = match R::scope_local(
+                locals2.clone(),
+                Abortable::new(
+                    Cancellable::new_with_cancel_rx(f(), cancel_rx),
+                    abort_registration,
+                ),
             )
-            .await;
+            .await
+            {
+                Ok(result) => result,
+                // the asyncio.Future is cancelled by whoever aborted the conversion
+                Err(Aborted) => return,
+            };
 
             Python::with_gil(move |py| {
-                if cancelled(future_tx1.bind(py))
-                    .map_err(dump_err(py))
-                    .unwrap_or(false)
-                {
-                    return;
-                }
-
                 let _ = set_result(
-                    locals2.event_loop.bind(py),
+                    &locals2,
                     future_tx1.bind(py),
                     result.map(|val| val.into_py(py)),
                 )
@@ -1029,53 +2325,34 @@ where
         })
         .await
         {
-            if e.is_panic() {
-                Python::with_gil(move |py| {
-                    if cancelled(future_tx2.bind(py))
-                        .map_err(dump_err(py))
-                        .unwrap_or(false)
-                    {
-                        return;
-                    }
-
-                    let panic_message = format!(
-                        "rust future panicked: {}",
-                        get_panic_message(&e.into_panic())
-                    );
-                    let _ = set_result(
-                        locals.event_loop.bind(py),
-                        future_tx2.bind(py),
-                        Err(RustPanic::new_err(panic_message)),
-                    )
-                    .map_err(dump_err(py));
-                });
-            }
+            Python::with_gil(move |py| {
+                let _ = set_result(
+                    &locals,
+                    future_tx2.bind(py),
+                    Err(abort_error(abort_reason(e))),
+                )
+                .map_err(dump_err(py));
+            });
         }
     });
 
     Ok(py_fut)
 }
 
-/// Convert a `!Send` Rust Future into a Python awaitable with a generic runtime
+/// Convert a `!Send` Rust Future into a Python awaitable with a generic thread-per-core runtime
+///
+/// Unlike [`future_into_py`], the future is created by `f` on the runtime's executor thread, so
+/// it is free to hold `!Send` state across await points.
 ///
 /// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
-/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+/// the Rust future will be cancelled as well.
 ///
 /// Python `contextvars` are preserved when calling async Python functions within the Rust future
-/// via [`into_future`] (new behaviour in `v0.15`).
-///
-/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
-/// > unfortunately fail to resolve them when called within the Rust future. This is because the
-/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
-/// >
-/// > As a workaround, you can get the `contextvars` from the current task locals using
-/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
-/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
-/// > synchronous function, and restore the previous context when it returns or raises an exception.
+/// via [`into_future`].
 ///
 /// # Arguments
 /// * `py` - The current PyO3 GIL guard
-/// * `fut` - The Rust future to be converted
+/// * `f` - Creates the Rust future to be converted on the executor thread
 ///
 /// # Examples
 ///
@@ -1084,7 +2361,7 @@ where
 /// #
 /// # use pyo3_async_runtimes::{
 /// #     TaskLocals,
-/// #     generic::{JoinError, SpawnLocalExt, ContextExt, LocalContextExt, Runtime}
+/// #     generic::{JoinError, ContextExt, LocalContextExt, Runtime, SpawnPinnedExt}
 /// # };
 /// #
 /// # struct MyCustomJoinError;
@@ -1140,10 +2417,11 @@ where
 /// #     }
 /// # }
 /// #
-/// # impl SpawnLocalExt for MyCustomRuntime {
-/// #     fn spawn_local<F>(fut: F) -> Self::JoinHandle
+/// # impl SpawnPinnedExt for MyCustomRuntime {
+/// #     fn spawn_pinned<F, Fut>(f: F) -> Self::JoinHandle
 /// #     where
-/// #         F: Future<Output = ()> + 'static
+/// #         F: FnOnce() -> Fut + Send + 'static,
+/// #         Fut: Future<Output = ()> + 'static
 /// #     {
 /// #         unreachable!()
 /// #     }
@@ -1165,27 +2443,25 @@ where
 /// /// Awaitable sleep function
 /// #[pyfunction]
 /// fn sleep_for(py: Python, secs: u64) -> PyResult<Bound<PyAny>> {
-///     // Rc is !Send so it cannot be passed into pyo3_async_runtimes::generic::future_into_py
-///     let secs = Rc::new(secs);
-///
-///     pyo3_async_runtimes::generic::local_future_into_py::<MyCustomRuntime, _, _>(py, async move {
-///         MyCustomRuntime::sleep(Duration::from_secs(*secs)).await;
-///         Ok(())
-///     })
+///     pyo3_async_runtimes::generic::pinned_future_into_py::<MyCustomRuntime, _, _, _>(
+///         py,
+///         move || async move {
+///             // Rc is !Send so it could not be held by a future passed to `future_into_py`
+///             let secs = Rc::new(secs);
+///             MyCustomRuntime::sleep(Duration::from_secs(*secs)).await;
+///             Ok(())
+///         }
+///     )
 /// }
 /// ```
-#[deprecated(
-    since = "0.18.0",
-    note = "Questionable whether these conversions have real-world utility (see https://github.com/awestlake87/pyo3-asyncio/issues/59#issuecomment-1008038497 and let me know if you disagree!)"
-)]
-#[allow(deprecated)]
-pub fn local_future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+pub fn pinned_future_into_py<R, F, Fut, T>(py: Python, f: F) -> PyResult<Bound<PyAny>>
 where
-    R: Runtime + ContextExt + SpawnLocalExt + LocalContextExt,
-    F: Future<Output = PyResult<T>> + 'static,
+    R: Runtime + ContextExt + SpawnPinnedExt + LocalContextExt,
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = PyResult<T>> + 'static,
     T: IntoPy<PyObject>,
 {
-    local_future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+    pinned_future_into_py_with_locals::<R, F, Fut, T>(py, get_current_locals::<R>(py)?, f)
 }
 
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
@@ -1196,7 +2472,7 @@ where
 ///
 /// # Arguments
 /// * `locals` - The current task locals
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```no_run
@@ -1301,7 +2577,9 @@ where
     R: Runtime,
 {
     let (tx, rx) = async_channel::bounded(1);
-    let anext = PyObject::from(gen.getattr("__anext__")?);
+    // async iterables like aiohttp responses hand out a separate iterator, generators return
+    // themselves
+    let anext = PyObject::from(gen.call_method0("__aiter__")?.getattr("__anext__")?);
 
     R::spawn(async move {
         loop {
@@ -1344,7 +2622,7 @@ where
 /// stability guarantees, and could be changed or removed at any time.
 ///
 /// # Arguments
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```no_run
@@ -1452,24 +2730,28 @@ trait Sender: Send + 'static {
 }
 
 #[cfg(feature = "unstable-streams")]
-struct GenericSender<R>
+struct GenericSender<R, I = PyObject>
 where
     R: Runtime,
 {
     runtime: PhantomData<R>,
-    tx: mpsc::Sender<PyObject>,
+    tx: mpsc::Sender<I>,
+    // converts the items while the GIL is held by the Python task
+    convert: fn(Python, PyObject) -> I,
 }
 
 #[cfg(feature = "unstable-streams")]
-impl<R> Sender for GenericSender<R>
+impl<R, I> Sender for GenericSender<R, I>
 where
     R: Runtime + ContextExt,
+    I: Send + 'static,
 {
     fn send(&mut self, py: Python, locals: TaskLocals, item: PyObject) -> PyResult<PyObject> {
-        match self.tx.try_send(item.clone_ref(py)) {
+        match self.tx.try_send((self.convert)(py, item)) {
             Ok(_) => Ok(true.into_py(py)),
             Err(e) => {
                 if e.is_full() {
+                    let item = e.into_inner();
                     let mut tx = self.tx.clone();
                     Python::with_gil(move |py| {
                         Ok(
@@ -1517,21 +2799,111 @@ impl SenderGlue {
 #[cfg(feature = "unstable-streams")]
 const STREAM_GLUE: &str = r#"
 import asyncio
+import collections
+import inspect
 
 async def forward(gen, sender):
-    async for item in gen:
-        should_continue = sender.send(item)
+    try:
+        async for item in gen:
+            should_continue = sender.send(item)
+
+            if asyncio.iscoroutine(should_continue):
+                should_continue = await should_continue
+
+            if should_continue:
+                continue
+            else:
+                break
+    finally:
+        # end the stream even if the iterator raised
+        sender.close()
+
+async def forward_prefetch(gen, sender, prefetch):
+    it = gen.__aiter__()
+    pending = collections.deque()
+    exhausted = False
+
+    try:
+        while True:
+            # keep up to `prefetch` calls to __anext__ running at once
+            while not exhausted and len(pending) < prefetch:
+                pending.append(asyncio.ensure_future(it.__anext__()))
+
+            if not pending:
+                break
+
+            try:
+                item = await pending.popleft()
+            except StopAsyncIteration:
+                exhausted = True
+                continue
+
+            should_continue = sender.send(item)
+
+            if inspect.isawaitable(should_continue):
+                should_continue = await should_continue
+
+            if not should_continue:
+                break
+    finally:
+        for fut in pending:
+            fut.cancel()
+
+        sender.close()
+"#;
+
+/// Forward the items of an async generator into a channel with a task on the event loop in `locals`
+///
+/// With a `prefetch` above 1, that many `__anext__` calls are kept running at once.
+#[cfg(feature = "unstable-streams")]
+fn into_stream_with_sender<R, I>(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+    prefetch: usize,
+    convert: fn(Python, PyObject) -> I,
+) -> PyResult<mpsc::Receiver<I>>
+where
+    R: Runtime + ContextExt,
+    I: Send + 'static,
+{
+    static GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
+    let py = gen.py();
+    let glue = GLUE_MOD
+        .get_or_try_init(|| -> PyResult<PyObject> {
+            Ok(PyModule::from_code_bound(
+                py,
+                STREAM_GLUE,
+                "pyo3_asyncio/pyo3_asyncio_glue.py",
+                "pyo3_asyncio_glue",
+            )?
+            .into())
+        })?
+        .bind(py);
 
-        if asyncio.iscoroutine(should_continue):
-            should_continue = await should_continue
+    let (tx, rx) = mpsc::channel(10);
+    let sender = SenderGlue {
+        locals: locals.clone_ref(py),
+        tx: Box::new(GenericSender {
+            runtime: PhantomData::<R>,
+            tx,
+            convert,
+        }),
+    };
 
-        if should_continue:
-            continue
-        else:
-            break
+    let forward = if prefetch > 1 {
+        glue.call_method1("forward_prefetch", (gen, sender, prefetch))?
+    } else {
+        glue.call_method1("forward", (gen, sender))?
+    };
 
-    sender.close()
-"#;
+    // the generator runs in the task's contextvars, like the awaitables of into_future
+    locals.call_soon_threadsafe(
+        py,
+        &locals.context(py),
+        (locals.create_task_method(py)?, forward),
+    )?;
+    Ok(rx)
+}
 
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
 ///
@@ -1541,7 +2913,7 @@ async def forward(gen, sender):
 ///
 /// # Arguments
 /// * `locals` - The current task locals
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```no_run
@@ -1644,42 +3016,7 @@ pub fn into_stream_with_locals_v2<R>(
 where
     R: Runtime + ContextExt,
 {
-    static GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
-    let py = gen.py();
-    let glue = GLUE_MOD
-        .get_or_try_init(|| -> PyResult<PyObject> {
-            Ok(PyModule::from_code_bound(
-                py,
-                STREAM_GLUE,
-                "pyo3_asyncio/pyo3_asyncio_glue.py",
-                "pyo3_asyncio_glue",
-            )?
-            .into())
-        })?
-        .bind(py);
-
-    let (tx, rx) = mpsc::channel(10);
-
-    locals.event_loop(py).call_method1(
-        "call_soon_threadsafe",
-        (
-            locals.event_loop(py).getattr("create_task")?,
-            glue.call_method1(
-                "forward",
-                (
-                    gen,
-                    SenderGlue {
-                        locals,
-                        tx: Box::new(GenericSender {
-                            runtime: PhantomData::<R>,
-                            tx,
-                        }),
-                    },
-                ),
-            )?,
-        ),
-    )?;
-    Ok(rx)
+    into_stream_with_sender::<R, PyObject>(locals, gen, 1, |_, item| item)
 }
 
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
@@ -1689,7 +3026,7 @@ where
 /// stability guarantees, and could be changed or removed at any time.
 ///
 /// # Arguments
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```no_run
@@ -1790,3 +3127,440 @@ where
 {
     into_stream_with_locals_v2::<R>(get_current_locals::<R>(gen.py())?, gen)
 }
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream of extracted items
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// This works like [`into_stream_with_locals_v2`], except that each item is extracted into a `T`
+/// by the Python task that forwards it, while it already holds the GIL. A failed extraction is
+/// yielded as an `Err` item and doesn't end the stream.
+///
+/// # Arguments
+/// * `locals` - The current task locals
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_typed_with_locals<R, T>(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyResult<T>> + 'static>
+where
+    R: Runtime + ContextExt,
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    into_stream_with_sender::<R, PyResult<T>>(locals, gen, 1, |py, item| item.extract(py))
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream of extracted items
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`into_stream_typed_with_locals`] for how the items are extracted.
+///
+/// # Arguments
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_typed<R, T>(
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyResult<T>> + 'static>
+where
+    R: Runtime + ContextExt,
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    into_stream_typed_with_locals::<R, T>(get_current_locals::<R>(gen.py())?, gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async iterator into a stream, keeping several items in flight
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// This works like [`into_stream_with_locals_v2`], except that up to `prefetch` calls to
+/// `__anext__` run concurrently on the event loop instead of one after another. This hides the
+/// latency of IO-bound iterators such as database cursors or paginated HTTP APIs. Items are still
+/// yielded in the order the calls were made.
+///
+/// The iterator has to support overlapping `__anext__` calls. Async generators don't, they raise
+/// a `RuntimeError` if `prefetch` is above 1.
+///
+/// # Arguments
+/// * `locals` - The current task locals
+/// * `gen` - The Python async iterable to be converted
+/// * `prefetch` - The maximum number of `__anext__` calls in flight, at least 1
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_prefetch_with_locals<R>(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+    prefetch: usize,
+) -> PyResult<impl futures::Stream<Item = PyObject> + 'static>
+where
+    R: Runtime + ContextExt,
+{
+    if prefetch == 0 {
+        return Err(PyValueError::new_err("prefetch must be at least 1"));
+    }
+
+    into_stream_with_sender::<R, PyObject>(locals, gen, prefetch, |_, item| item)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async iterator into a stream, keeping several items in flight
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`into_stream_prefetch_with_locals`] for which iterators support prefetching.
+///
+/// # Arguments
+/// * `gen` - The Python async iterable to be converted
+/// * `prefetch` - The maximum number of `__anext__` calls in flight, at least 1
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_prefetch<R>(
+    gen: Bound<'_, PyAny>,
+    prefetch: usize,
+) -> PyResult<impl futures::Stream<Item = PyObject> + 'static>
+where
+    R: Runtime + ContextExt,
+{
+    into_stream_prefetch_with_locals::<R>(get_current_locals::<R>(gen.py())?, gen, prefetch)
+}
+
+#[cfg(feature = "unstable-streams")]
+type PyItemStream = Pin<Box<dyn futures::Stream<Item = PyResult<PyObject>> + Send>>;
+
+#[cfg(feature = "unstable-streams")]
+type PyItemFuture = Pin<Box<dyn Future<Output = PyResult<PyObject>> + Send>>;
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> What a Python async iterator over a Rust stream does after an error
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// Either way, the error is raised by the `__anext__` call for the item that failed.
+#[cfg(feature = "unstable-streams")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// Keep iterating over the items after the error
+    #[default]
+    Continue,
+    /// Stop the iteration, later calls to `__anext__` raise `StopAsyncIteration`
+    Abort,
+}
+
+/// Async iterator over a Rust stream, returned by [`try_stream_into_py_with_locals`]
+#[cfg(feature = "unstable-streams")]
+#[pyclass]
+struct PyStreamIter {
+    locals: TaskLocals,
+    stream: Arc<futures::lock::Mutex<Option<PyItemStream>>>,
+    // future_into_py_with_locals for the runtime the stream was converted with
+    future_into_py: for<'p> fn(Python<'p>, TaskLocals, PyItemFuture) -> PyResult<Bound<'p, PyAny>>,
+    on_error: OnError,
+}
+
+#[cfg(feature = "unstable-streams")]
+#[pymethods]
+impl PyStreamIter {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__(&self, py: Python) -> PyResult<PyObject> {
+        let stream = self.stream.clone();
+        let on_error = self.on_error;
+
+        // the stream is only polled while Python is waiting for the next item
+        let next = async move {
+            let mut stream = stream.lock().await;
+
+            let item = match stream.as_mut() {
+                Some(stream) => stream.next().await,
+                None => None,
+            };
+
+            match item {
+                Some(Err(e)) if on_error == OnError::Abort => {
+                    *stream = None;
+                    Err(e)
+                }
+                Some(item) => item,
+                None => {
+                    *stream = None;
+                    Err(pyo3::exceptions::PyStopAsyncIteration::new_err(()))
+                }
+            }
+        };
+
+        Ok((self.future_into_py)(py, self.locals.clone_ref(py), Box::pin(next))?.unbind())
+    }
+
+    fn aclose(&self, py: Python) -> PyResult<PyObject> {
+        let stream = self.stream.clone();
+
+        let close = async move {
+            stream.lock().await.take();
+            Ok(Python::with_gil(|py| py.None()))
+        };
+
+        Ok((self.future_into_py)(py, self.locals.clone_ref(py), Box::pin(close))?.unbind())
+    }
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert a stream into a Python async iterator
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// The returned object supports `async for` and `aclose()`. Each call to `__anext__` returns an
+/// `asyncio.Future` for the next item, and the stream is only polled while one of these futures is
+/// pending, so a slow consumer in Python holds back the producer in Rust. An `Err` item is raised by
+/// `__anext__` without ending the iteration, see [`try_stream_into_py_with_locals`] to stop at the
+/// first error instead.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the futures returned by `__anext__`
+/// * `stream` - The Rust stream to be converted
+#[cfg(feature = "unstable-streams")]
+pub fn stream_into_py_with_locals<R, S, T>(
+    py: Python,
+    locals: TaskLocals,
+    stream: S,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    S: futures::Stream<Item = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    try_stream_into_py_with_locals::<R, S, T, PyErr>(py, locals, stream, OnError::Continue)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert a stream into a Python async iterator
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`stream_into_py_with_locals`] for how the stream is driven.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `stream` - The Rust stream to be converted
+#[cfg(feature = "unstable-streams")]
+pub fn stream_into_py<R, S, T>(py: Python, stream: S) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    S: futures::Stream<Item = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    stream_into_py_with_locals::<R, S, T>(py, get_current_locals::<R>(py)?, stream)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert a stream of results into a Python async iterator
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// Like [`stream_into_py_with_locals`], but the errors of the stream can be any type that converts
+/// into a `PyErr`. Each error is raised by the `__anext__` call for the item that failed, and
+/// `on_error` decides whether the iteration continues with the next item or stops there.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the futures returned by `__anext__`
+/// * `stream` - The Rust stream to be converted
+/// * `on_error` - What to do after an error
+#[cfg(feature = "unstable-streams")]
+pub fn try_stream_into_py_with_locals<R, S, T, E>(
+    py: Python,
+    locals: TaskLocals,
+    stream: S,
+    on_error: OnError,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    S: futures::Stream<Item = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: Into<PyErr>,
+{
+    let stream = stream
+        .map(|item| Python::with_gil(|py| item.map(|val| val.into_py(py)).map_err(Into::into)));
+
+    Ok(Bound::new(
+        py,
+        PyStreamIter {
+            locals,
+            stream: Arc::new(futures::lock::Mutex::new(Some(Box::pin(stream)))),
+            future_into_py: future_into_py_with_locals::<R, PyItemFuture, PyObject>,
+            on_error,
+        },
+    )?
+    .into_any())
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert a stream of results into a Python async iterator
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`try_stream_into_py_with_locals`] for how errors are raised.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `stream` - The Rust stream to be converted
+/// * `on_error` - What to do after an error
+#[cfg(feature = "unstable-streams")]
+pub fn try_stream_into_py<R, S, T, E>(
+    py: Python,
+    stream: S,
+    on_error: OnError,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    S: futures::Stream<Item = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: Into<PyErr>,
+{
+    try_stream_into_py_with_locals::<R, S, T, E>(py, get_current_locals::<R>(py)?, stream, on_error)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Forward the items of an async iterable into a sink
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// The returned future pulls items from `iterable` one at a time, extracts them into `T` and feeds
+/// them into `sink`. Up to `buffer` items are queued in front of the sink before the iterable is
+/// held back, and `0` hands each item to the sink as soon as it's ready for it. The sink is flushed
+/// and closed once the iterable is exhausted.
+///
+/// When an item fails to extract, the error is thrown into the iterable with `athrow` (if it has
+/// one, like async generators do). A generator that catches the error can keep yielding items,
+/// otherwise the error ends the forwarding and is returned by the future. Errors of the sink are
+/// thrown into the iterable as well, but always end the forwarding, since a sink can't take any
+/// more items once it failed.
+///
+/// # Arguments
+/// * `locals` - The task locals used to await the iterable
+/// * `iterable` - The Python async iterable to be consumed
+/// * `sink` - The Rust sink that receives the items
+/// * `buffer` - The number of items to buffer in front of the sink
+#[cfg(feature = "unstable-streams")]
+pub fn forward_into_sink_with_locals<T, S>(
+    locals: TaskLocals,
+    iterable: Bound<'_, PyAny>,
+    sink: S,
+    buffer: usize,
+) -> PyResult<impl Future<Output = PyResult<()>> + Send + 'static>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+    S: futures::Sink<T> + Send + 'static,
+    S::Error: Into<PyErr>,
+{
+    let iter = iterable.call_method0("__aiter__")?;
+    let athrow = if iter.hasattr("athrow")? {
+        Some(iter.getattr("athrow")?.unbind())
+    } else {
+        None
+    };
+    let anext = iter.getattr("__anext__")?.unbind();
+
+    Ok(async move {
+        let mut sink = Box::pin(sink.buffer(buffer));
+        let mut next = Python::with_gil(|py| anext.call0(py))?;
+
+        loop {
+            let fut = Python::with_gil(|py| into_future_with_locals(&locals, next.into_bound(py)))?;
+
+            let item = match fut.await {
+                Ok(item) => item,
+                Err(e) => {
+                    if Python::with_gil(|py| {
+                        e.is_instance_of::<pyo3::exceptions::PyStopAsyncIteration>(py)
+                    }) {
+                        break;
+                    }
+
+                    return Err(e);
+                }
+            };
+
+            let item = match Python::with_gil(|py| item.extract::<T>(py)) {
+                Ok(item) => item,
+                Err(e) => {
+                    // let the generator decide whether the error ends the iteration
+                    next = match &athrow {
+                        Some(athrow) => {
+                            Python::with_gil(|py| athrow.call1(py, (e.into_value(py),)))?
+                        }
+                        None => return Err(e),
+                    };
+                    continue;
+                }
+            };
+
+            let fed: PyResult<()> = sink.feed(item).await.map_err(Into::into);
+            if let Err(e) = fed {
+                // a sink can't take any more items once it failed, so the generator only gets to
+                // see the error before the forwarding ends with it
+                if let Some(athrow) = &athrow {
+                    let thrown = Python::with_gil(|py| {
+                        into_future_with_locals(
+                            &locals,
+                            athrow.bind(py).call1((e.clone_ref(py).into_value(py),))?,
+                        )
+                    })?;
+                    let _ = thrown.await;
+                }
+
+                return Err(e);
+            }
+
+            next = Python::with_gil(|py| anext.call0(py))?;
+        }
+
+        sink.close().await.map_err(Into::into)
+    })
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Forward the items of an async iterable into a sink
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`forward_into_sink_with_locals`] for how items are buffered and errors are handled.
+///
+/// # Arguments
+/// * `iterable` - The Python async iterable to be consumed
+/// * `sink` - The Rust sink that receives the items
+/// * `buffer` - The number of items to buffer in front of the sink
+#[cfg(feature = "unstable-streams")]
+pub fn forward_into_sink<R, T, S>(
+    iterable: Bound<'_, PyAny>,
+    sink: S,
+    buffer: usize,
+) -> PyResult<impl Future<Output = PyResult<()>> + Send + 'static>
+where
+    R: ContextExt,
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+    S: futures::Sink<T> + Send + 'static,
+    S::Error: Into<PyErr>,
+{
+    forward_into_sink_with_locals::<T, S>(
+        get_current_locals::<R>(iterable.py())?,
+        iterable,
+        sink,
+        buffer,
+    )
+}
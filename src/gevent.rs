@@ -0,0 +1,251 @@
+//! <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>gevent</code></span> Conversions between Rust futures and [gevent](https://www.gevent.org)
+//!
+//! gevent code doesn't use `asyncio` at all, so the conversions in this module don't need an event
+//! loop. Instead, they hand results across threads through the gevent hub's
+//! `loop.run_callback_threadsafe`:
+//!
+//! - [`future_into_py`] converts a Rust future into a `gevent.event.AsyncResult`, which greenlets
+//!   can wait on with `get()` without blocking the hub.
+//! - [`into_future`] converts a `Greenlet` or an `AsyncResult` into a Rust future.
+//!
+//! The functions are generic over the Rust runtime, so they can be used with any of the runtimes
+//! of this crate or a runtime implemented with the [`generic`](crate::generic) traits.
+//!
+//! The task locals of a Rust future converted by this module hold the hub's loop in place of the
+//! `asyncio` event loop, so [`into_future`] called from such a future links to the same hub.
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["gevent"]
+//! ```
+
+use std::future::Future;
+
+use futures::channel::oneshot;
+use once_cell::sync::OnceCell;
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+
+use crate::{
+    dump_err,
+    err::abort_error,
+    generic::{abort_reason, ContextExt, Runtime},
+    TaskLocals,
+};
+
+static GEVENT: OnceCell<PyObject> = OnceCell::new();
+
+fn gevent(py: Python) -> PyResult<&Bound<PyAny>> {
+    GEVENT
+        .get_or_try_init(|| Ok(py.import_bound("gevent")?.into()))
+        .map(|gevent| gevent.bind(py))
+}
+
+/// Get the loop of the gevent hub running on the current thread
+pub fn get_current_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    gevent(py)?.call_method0("get_hub")?.getattr("loop")
+}
+
+/// Either copy the task locals from the current Rust task OR get the loop of the gevent hub on the
+/// current thread
+pub fn get_current_locals<R>(py: Python) -> PyResult<TaskLocals>
+where
+    R: ContextExt,
+{
+    if let Some(locals) = R::get_task_locals() {
+        Ok(locals)
+    } else {
+        Ok(TaskLocals::new(get_current_loop(py)?))
+    }
+}
+
+fn set_result(hub_loop: &Bound<PyAny>, async_result: &Bound<PyAny>, result: PyResult<PyObject>) {
+    let py = hub_loop.py();
+
+    let (complete, val) = match result {
+        Ok(val) => ("set", val),
+        Err(err) => ("set_exception", err.into_py(py)),
+    };
+
+    // AsyncResult isn't thread-safe, so complete it on the hub's thread
+    let _ = async_result
+        .getattr(complete)
+        .and_then(|complete| hub_loop.call_method1("run_callback_threadsafe", (complete, val)))
+        .map_err(dump_err(py));
+}
+
+/// Convert a Rust Future into a `gevent.event.AsyncResult` with the given task locals
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals holding the loop of the gevent hub to complete the result on
+/// * `fut` - The Rust future to be converted
+#[allow(unused_must_use)]
+pub fn future_into_py_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let async_result = py
+        .import_bound("gevent.event")?
+        .call_method0("AsyncResult")?;
+
+    let result_tx1 = PyObject::from(async_result.clone());
+    let result_tx2 = result_tx1.clone_ref(py);
+
+    R::spawn(async move {
+        let locals2 = locals.clone();
+
+        if let Err(e) = R::spawn(async move {
+            let result = R::scope(locals2.clone(), fut).await;
+
+            Python::with_gil(move |py| {
+                set_result(
+                    &locals2.event_loop(py),
+                    result_tx1.bind(py),
+                    result.map(|val| val.into_py(py)),
+                );
+            });
+        })
+        .await
+        {
+            Python::with_gil(move |py| {
+                set_result(
+                    &locals.event_loop(py),
+                    result_tx2.bind(py),
+                    Err(abort_error(abort_reason(e))),
+                );
+            });
+        }
+    });
+
+    Ok(async_result)
+}
+
+/// Convert a Rust Future into a `gevent.event.AsyncResult`
+///
+/// This has to be called on a thread running a gevent hub, or from a Rust future that was
+/// converted by this module.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Sleep that greenlets can wait on with `sleep_for(secs).get()`
+/// # #[cfg(feature = "tokio-runtime")]
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::gevent::future_into_py::<pyo3_async_runtimes::tokio::TokioRuntime, _, _>(
+///         py,
+///         async move {
+///             tokio::time::sleep(Duration::from_secs(secs)).await;
+///             Ok(())
+///         },
+///     )
+/// }
+/// ```
+pub fn future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// Sends the outcome of a greenlet or `AsyncResult` to the Rust future awaiting it
+#[pyclass]
+struct GeventLink {
+    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+}
+
+#[pymethods]
+impl GeventLink {
+    fn __call__(&mut self, source: &Bound<PyAny>) -> PyResult<()> {
+        let result = if source.call_method0("successful")?.is_truthy()? {
+            Ok(source.getattr("value")?.unbind())
+        } else {
+            Err(PyErr::from_value_bound(source.getattr("exception")?))
+        };
+
+        if let Some(tx) = self.tx.take() {
+            // the receiver is gone if the Rust future was dropped
+            let _ = tx.send(result);
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert a `Greenlet` or `gevent.event.AsyncResult` into a Rust Future with the given task
+/// locals
+///
+/// # Arguments
+/// * `locals` - The task locals holding the loop of the gevent hub that runs `source`
+/// * `source` - The greenlet or `AsyncResult` to be converted
+pub fn into_future_with_locals(
+    locals: &TaskLocals,
+    source: Bound<PyAny>,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+    let (tx, rx) = oneshot::channel();
+
+    // rawlink has to be called on the hub's thread
+    locals.event_loop(source.py()).call_method1(
+        "run_callback_threadsafe",
+        (source.getattr("rawlink")?, GeventLink { tx: Some(tx) }),
+    )?;
+
+    Ok(async move {
+        match rx.await {
+            Ok(item) => item,
+            Err(_) => Err(PyRuntimeError::new_err(
+                "gevent hub exited before the greenlet completed",
+            )),
+        }
+    })
+}
+
+/// Convert a `Greenlet` or `gevent.event.AsyncResult` into a Rust Future
+///
+/// # Arguments
+/// * `source` - The greenlet or `AsyncResult` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Join a greenlet from Rust and hand its value back to gevent
+/// # #[cfg(feature = "tokio-runtime")]
+/// #[pyfunction]
+/// fn join<'p>(py: Python<'p>, greenlet: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let fut = pyo3_async_runtimes::gevent::into_future::<
+///         pyo3_async_runtimes::tokio::TokioRuntime,
+///     >(greenlet)?;
+///
+///     pyo3_async_runtimes::gevent::future_into_py::<pyo3_async_runtimes::tokio::TokioRuntime, _, _>(
+///         py, fut,
+///     )
+/// }
+/// ```
+pub fn into_future<R>(
+    source: Bound<PyAny>,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    R: ContextExt,
+{
+    into_future_with_locals(&get_current_locals::<R>(source.py())?, source)
+}
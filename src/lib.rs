@@ -282,9 +282,15 @@
 //!
 //! ## Rust's Event Loop
 //!
-//! Currently only the Async-Std and Tokio runtimes are supported by this crate. If you need support
-//! for another runtime, feel free to make a request on GitHub (or attempt to add support yourself
-//! with the [`generic`] module)!
+//! Currently only the actix, Async-Std, compio, glommio, monoio, Smol, and Tokio runtimes, as well
+//! as the `futures` `LocalPool` executor, are supported by this crate. If you need support for
+//! another runtime, feel free to make a request on GitHub (or attempt to add support yourself with
+//! the [`generic`] module)! Libraries that want to leave the choice of runtime to the application
+//! can use the `dynamic` module instead, which runs on a runtime registered at startup.
+//!
+//! On the Python side, trio is supported through trio-asyncio by the `trio` module, and the `anyio`
+//! module picks between `asyncio` and trio at runtime. Code that uses gevent instead of `asyncio`
+//! can use the `gevent` module.
 //!
 //! > _In the future, we may implement first class support for more Rust runtimes. Contributions are
 //! > welcome as well!_
@@ -308,6 +314,19 @@
 //! <span
 //!   class="module-item stab portability"
 //!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>actix-runtime</code></span>
+//! > are only available when the `actix-runtime` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["actix-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
 //! ><code>async-std-runtime</code></span>
 //! > are only available when the `async-std-runtime` Cargo feature is enabled:
 //!
@@ -321,6 +340,84 @@
 //! <span
 //!   class="module-item stab portability"
 //!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>compio-runtime</code></span>
+//! > are only available when the `compio-runtime` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["compio-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>dynamic-runtime</code></span>
+//! > are only available when the `dynamic-runtime` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["dynamic-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>glommio-runtime</code></span>
+//! > are only available when the `glommio-runtime` Cargo feature is enabled (Linux only):
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["glommio-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>local-pool-runtime</code></span>
+//! > are only available when the `local-pool-runtime` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["local-pool-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>monoio-runtime</code></span>
+//! > are only available when the `monoio-runtime` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["monoio-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>smol-runtime</code></span>
+//! > are only available when the `smol-runtime` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["smol-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
 //! ><code>tokio-runtime</code></span>
 //! > are only available when the `tokio-runtime` Cargo feature is enabled:
 //!
@@ -334,6 +431,110 @@
 //! <span
 //!   class="module-item stab portability"
 //!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-uring-runtime</code></span>
+//! > are only available when the `tokio-uring-runtime` Cargo feature is enabled (Linux only):
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-uring-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-event-loop</code></span>
+//! > are only available when the `tokio-event-loop` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-event-loop"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-io</code></span>
+//! > are only available when the `tokio-io` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-io"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-sync</code></span>
+//! > are only available when the `tokio-sync` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-sync"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-cancellation</code></span>
+//! > are only available when the `tokio-cancellation` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-cancellation"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>anyio</code></span>
+//! > are only available when the `anyio` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["anyio"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>gevent</code></span>
+//! > are only available when the `gevent` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["gevent"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>trio-asyncio</code></span>
+//! > are only available when the `trio-asyncio` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["trio-asyncio"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
 //! ><code>testing</code></span>
 //! > are only available when the `testing` Cargo feature is enabled:
 //!
@@ -342,29 +543,313 @@
 //! version = "0.21"
 //! features = ["testing"]
 //! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>log</code></span>
+//! > are only available when the `log` or the `tracing` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tracing"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>anyhow</code></span>
+//! > are only available when the `anyhow` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["anyhow"]
+//! ```
+//!
+//! The `error-mappings` Cargo feature is enabled by default. It translates common Rust errors into
+//! their matching Python exceptions, see [`err::translate_error`]. Without it, they are raised as
+//! a `RuntimeError` with their message:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! default-features = false
+//! ```
 
 /// Re-exported for #[test] attributes
 #[cfg(all(feature = "attributes", feature = "testing"))]
 pub use inventory;
 
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span> Provides the boilerplate for the runtime selected by the enabled Cargo features and runs an async fn as main
+///
+/// If more than one runtime feature is enabled, the runtime has to be chosen with the `runtime`
+/// argument, e.g. `#[pyo3_async_runtimes::main(runtime = "tokio")]`.
+#[cfg(feature = "attributes")]
+pub use pyo3_async_runtimes_macros::runtime_main as main;
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>testing</code></span>
+/// Registers a test for the runtime selected by the enabled Cargo features with the
+/// `pyo3-asyncio` test harness
+///
+/// If more than one runtime feature is enabled, the runtime has to be chosen with the `runtime`
+/// argument, e.g. `#[pyo3_async_runtimes::test(runtime = "tokio")]`.
+#[cfg(all(feature = "attributes", feature = "testing"))]
+pub use pyo3_async_runtimes_macros::runtime_test as test;
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>testing</code></span>
+/// Registers an async test with the `pyo3-asyncio` test harness once for every enabled backend
+///
+/// The backends are a `multi_thread` and a `current_thread` tokio runtime if the `tokio-runtime`
+/// feature is enabled, and async-std if the `async-std-runtime` feature is enabled. A test that is
+/// generic over a [`generic::Runtime`] is instantiated with the runtime of each backend, e.g.
+/// [`tokio::TokioRuntime`].
+#[cfg(all(feature = "attributes", feature = "testing"))]
+pub use pyo3_async_runtimes_macros::test_all_backends;
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span> Wraps an `async fn` into a `#[pyfunction]` that returns a Python awaitable
+///
+/// The future runs on the runtime selected by the enabled Cargo features, or the one chosen with
+/// the `runtime` argument, e.g. `#[pyo3_async_runtimes::pyfunction_async(runtime = "tokio")]`.
+#[cfg(feature = "attributes")]
+pub use pyo3_async_runtimes_macros::pyfunction_async;
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span> Converts the `async fn` methods of a `#[pymethods]` block into methods that return a Python awaitable
+///
+/// Place it above `#[pymethods]`. Like [`pyfunction_async`], the runtime can be chosen with the
+/// `runtime` argument, e.g. `#[pyo3_async_runtimes::async_methods(runtime = "tokio")]`.
+#[cfg(feature = "attributes")]
+pub use pyo3_async_runtimes_macros::async_methods;
+
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>testing</code></span> Utilities for writing PyO3 Asyncio tests
 #[cfg(feature = "testing")]
 pub mod testing;
 
+#[cfg(feature = "actix-runtime")]
+pub mod actix;
+
+#[cfg(feature = "anyio")]
+pub mod anyio;
+
 #[cfg(feature = "async-std")]
 pub mod async_std;
 
+#[cfg(feature = "compio-runtime")]
+pub mod compio;
+
+#[cfg(feature = "dynamic-runtime")]
+pub mod dynamic;
+
+#[cfg(feature = "gevent")]
+pub mod gevent;
+
+#[cfg(all(feature = "glommio-runtime", target_os = "linux"))]
+pub mod glommio;
+
+#[cfg(feature = "local-pool-runtime")]
+pub mod local_pool;
+
+#[cfg(feature = "monoio-runtime")]
+pub mod monoio;
+
+#[cfg(feature = "smol-runtime")]
+pub mod smol;
+
+// runtimes that aren't pinned to an executor thread only use the join handles
+#[cfg_attr(
+    not(any(
+        feature = "actix-runtime",
+        feature = "compio-runtime",
+        all(feature = "glommio-runtime", target_os = "linux"),
+        feature = "local-pool-runtime",
+        feature = "monoio-runtime"
+    )),
+    allow(dead_code, unused_imports, unused_macros)
+)]
+mod pinned;
+
+mod scoped;
+
+mod signals;
+
+mod completions;
+
+mod rust_future;
+
+pub use rust_future::set_rust_futures;
+
+mod polled;
+
+pub use polled::{into_future_polled_with_locals, PyPolledFuture};
+
+mod cancel;
+
+pub use cancel::CancellationToken;
+
+mod shutdown;
+
+pub use shutdown::{cancel_on_interrupt, is_shutting_down, shutdown};
+
+mod py_event_loop;
+
+pub use py_event_loop::PyEventLoop;
+
+#[cfg(any(feature = "log", feature = "tracing"))]
+mod exception_handler;
+
+#[cfg(any(feature = "log", feature = "tracing"))]
+pub use exception_handler::install_exception_handler;
+
+mod macros;
+
+/// Items used by the code generated by [`impl_runtime`]
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::pinned::{task, PinnedJoinErr as JoinErr, PinnedJoinHandle as JoinHandle};
+    pub use crate::scoped::{get_task_locals, Scoped};
+    pub use pyo3;
+
+    use pyo3::{exceptions::PySystemExit, prelude::*};
+    use std::process::ExitCode;
+
+    /// The exit code of a `main` that failed with `err`, like the Python interpreter would exit
+    ///
+    /// A `SystemExit` exits with its code, any other error prints its traceback and fails.
+    pub fn exit_code(py: Python, err: PyErr) -> ExitCode {
+        if err.is_instance_of::<PySystemExit>(py) {
+            if let Ok(code) = err.value_bound(py).getattr("code") {
+                if code.is_none() {
+                    return ExitCode::SUCCESS;
+                }
+                if let Ok(code) = code.extract::<i32>() {
+                    return ExitCode::from(code as u8);
+                }
+
+                // like `sys.exit("message")`
+                eprintln!("{}", code);
+                return ExitCode::FAILURE;
+            }
+        }
+
+        err.print_and_set_sys_last_vars(py);
+        ExitCode::FAILURE
+    }
+
+    /// The number of runtimes with `main` and `test` attributes that are enabled
+    pub const ENABLED_RUNTIMES: usize = cfg!(feature = "actix-runtime") as usize
+        + cfg!(feature = "async-std-runtime") as usize
+        + cfg!(feature = "compio-runtime") as usize
+        + cfg!(all(feature = "glommio-runtime", target_os = "linux")) as usize
+        + cfg!(feature = "local-pool-runtime") as usize
+        + cfg!(feature = "monoio-runtime") as usize
+        + cfg!(feature = "smol-runtime") as usize
+        + cfg!(feature = "tokio-runtime") as usize;
+
+    /// The number of backends that `#[pyo3_async_runtimes::test_all_backends]` runs tests on
+    pub const TEST_BACKENDS: usize =
+        2 * cfg!(feature = "tokio-runtime") as usize + cfg!(feature = "async-std-runtime") as usize;
+
+    #[cfg(feature = "tokio-runtime")]
+    pub use crate::tokio as default_runtime;
+
+    #[cfg(all(feature = "async-std-runtime", not(feature = "tokio-runtime")))]
+    pub use crate::async_std as default_runtime;
+
+    #[cfg(all(
+        feature = "smol-runtime",
+        not(any(feature = "tokio-runtime", feature = "async-std-runtime"))
+    ))]
+    pub use crate::smol as default_runtime;
+
+    #[cfg(all(
+        feature = "actix-runtime",
+        not(any(
+            feature = "tokio-runtime",
+            feature = "async-std-runtime",
+            feature = "smol-runtime"
+        ))
+    ))]
+    pub use crate::actix as default_runtime;
+
+    #[cfg(all(
+        feature = "compio-runtime",
+        not(any(
+            feature = "tokio-runtime",
+            feature = "async-std-runtime",
+            feature = "smol-runtime",
+            feature = "actix-runtime"
+        ))
+    ))]
+    pub use crate::compio as default_runtime;
+
+    #[cfg(all(
+        feature = "glommio-runtime",
+        target_os = "linux",
+        not(any(
+            feature = "tokio-runtime",
+            feature = "async-std-runtime",
+            feature = "smol-runtime",
+            feature = "actix-runtime",
+            feature = "compio-runtime"
+        ))
+    ))]
+    pub use crate::glommio as default_runtime;
+
+    #[cfg(all(
+        feature = "monoio-runtime",
+        not(any(
+            feature = "tokio-runtime",
+            feature = "async-std-runtime",
+            feature = "smol-runtime",
+            feature = "actix-runtime",
+            feature = "compio-runtime",
+            all(feature = "glommio-runtime", target_os = "linux")
+        ))
+    ))]
+    pub use crate::monoio as default_runtime;
+
+    #[cfg(all(
+        feature = "local-pool-runtime",
+        not(any(
+            feature = "tokio-runtime",
+            feature = "async-std-runtime",
+            feature = "smol-runtime",
+            feature = "actix-runtime",
+            feature = "compio-runtime",
+            all(feature = "glommio-runtime", target_os = "linux"),
+            feature = "monoio-runtime"
+        ))
+    ))]
+    pub use crate::local_pool as default_runtime;
+}
+
 #[cfg(feature = "tokio-runtime")]
 pub mod tokio;
 
+#[cfg(feature = "trio-asyncio")]
+pub mod trio;
+
 /// Errors and exceptions related to PyO3 Asyncio
 pub mod err;
 
+pub mod debug;
+
 pub mod generic;
 
 #[pymodule]
 fn pyo3_asyncio(py: Python, m: &Bound<PyModule>) -> PyResult<()> {
+    m.add(
+        "RustFutureAborted",
+        py.get_type_bound::<err::RustFutureAborted>(),
+    )?;
     m.add("RustPanic", py.get_type_bound::<err::RustPanic>())?;
+    m.add("ShuttingDown", py.get_type_bound::<err::ShuttingDown>())?;
+    m.add_class::<CancellationToken>()?;
     Ok(())
 }
 
@@ -394,33 +879,63 @@ pub mod doc_test {
     doctest!("../README.md", readme_md);
 }
 
-use std::future::Future;
+use std::{
+    cell::RefCell,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
 
-use futures::channel::oneshot;
-use once_cell::sync::OnceCell;
+use futures::{channel::oneshot, future::Abortable, ready};
+use once_cell::sync::{Lazy, OnceCell};
 use pyo3::{
+    exceptions::{PyDeprecationWarning, PyImportError, PyRuntimeError, PyValueError},
     prelude::*,
-    types::{PyDict, PyTuple},
+    types::{PyCFunction, PyDict, PyTuple},
+};
+
+use crate::{
+    completions::CompletionQueue,
+    debug::LocalsSource,
+    py_event_loop::{call_soon_threadsafe, LoopMethods},
+    shutdown::{register, Registration, Target},
 };
 
 static ASYNCIO: OnceCell<PyObject> = OnceCell::new();
 static CONTEXTVARS: OnceCell<PyObject> = OnceCell::new();
 static ENSURE_FUTURE: OnceCell<PyObject> = OnceCell::new();
 static GET_RUNNING_LOOP: OnceCell<PyObject> = OnceCell::new();
+static PEEK_RUNNING_LOOP: OnceCell<PyObject> = OnceCell::new();
+static BASE_EVENT_LOOP: OnceCell<PyObject> = OnceCell::new();
+static RUNNING_LOOP_HOOKED: OnceCell<bool> = OnceCell::new();
+static UVLOOP: OnceCell<Option<PyObject>> = OnceCell::new();
 
-fn ensure_future<'p>(py: Python<'p>, awaitable: &Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
-    ENSURE_FUTURE
-        .get_or_try_init(|| -> PyResult<PyObject> {
+static COPY_CONTEXT: AtomicBool = AtomicBool::new(true);
+
+thread_local! {
+    static THREAD_EVENT_LOOP: RefCell<Option<PyObject>> = const { RefCell::new(None) };
+    static SCOPED_EVENT_LOOP: RefCell<Option<PyObject>> = const { RefCell::new(None) };
+    static RUNNING_LOCALS: RefCell<Option<TaskLocals>> = const { RefCell::new(None) };
+}
+
+static NEXT_DEFAULT_LOCALS_ID: AtomicU64 = AtomicU64::new(0);
+pub(crate) static DEFAULT_LOCALS: Lazy<Mutex<Vec<(u64, TaskLocals)>>> = Lazy::new(Default::default);
+
+fn ensure_future<'p>(py: Python<'p>, awaitable: &Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+    ENSURE_FUTURE
+        .get_or_try_init(|| -> PyResult<PyObject> {
             Ok(asyncio(py)?.getattr("ensure_future")?.into())
         })?
         .bind(py)
         .call1((awaitable,))
 }
 
-fn create_future(event_loop: Bound<PyAny>) -> PyResult<Bound<'_, PyAny>> {
-    event_loop.call_method0("create_future")
-}
-
 fn close(event_loop: Bound<PyAny>) -> PyResult<()> {
     event_loop.call_method1(
         "run_until_complete",
@@ -440,6 +955,60 @@ fn close(event_loop: Bound<PyAny>) -> PyResult<()> {
     Ok(())
 }
 
+/// Run the event loop until `stop()` is called on it
+///
+/// This calls `event_loop.run_forever()`, and on the main thread it also wakes up the loop
+/// periodically so that signals are raised while it's idle. Without this, a CTRL-C can go unnoticed
+/// until the next callback runs, which happens on Windows' `ProactorEventLoop` and whenever the
+/// signal is delivered to one of the Rust runtime's threads. The `KeyboardInterrupt` is returned as
+/// an error, and it cancels the conversions in flight if [`cancel_on_interrupt`] is enabled.
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop to run
+///
+/// # Examples
+///
+/// ```no_run
+/// # use pyo3::prelude::*;
+/// #
+/// # fn main() -> PyResult<()> {
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| {
+///     let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+///
+///     // runs until CTRL-C is pressed
+///     pyo3_async_runtimes::run_forever(&event_loop)
+/// })
+/// # }
+/// ```
+pub fn run_forever(event_loop: &Bound<PyAny>) -> PyResult<()> {
+    check_not_in_running_loop(event_loop.py(), "run_forever")?;
+
+    let _wakeup = signals::SignalWakeup::start(event_loop)?;
+    if let Err(e) = event_loop.call_method0("run_forever") {
+        shutdown::interrupted(event_loop.py(), &e);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Raise instead of blocking in `entry` on a thread whose event loop is running
+///
+/// Blocking that thread would wait for the loop forever, since the loop can't run while it's
+/// blocked. Python raises in that case too, but only once the future was already converted.
+pub(crate) fn check_not_in_running_loop(py: Python, entry: &str) -> PyResult<()> {
+    if asyncio(py)?.call_method0("_get_running_loop")?.is_none() {
+        return Ok(());
+    }
+
+    Err(PyRuntimeError::new_err(format!(
+        "`{}` would deadlock: it blocks the thread of a running event loop, which can't run \
+         until it returns. Await the future on the loop instead, e.g. with `future_into_py`",
+        entry
+    )))
+}
+
 fn asyncio(py: Python) -> PyResult<&Bound<PyAny>> {
     ASYNCIO
         .get_or_try_init(|| Ok(py.import_bound("asyncio")?.into()))
@@ -450,6 +1019,10 @@ fn asyncio(py: Python) -> PyResult<&Bound<PyAny>> {
 ///
 /// Equivalent to `asyncio.get_running_loop()` in Python 3.7+.
 pub fn get_running_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    if let Some(locals) = RUNNING_LOCALS.with(|cached| cached.borrow().clone()) {
+        return Ok(locals.event_loop(py));
+    }
+
     // Ideally should call get_running_loop, but calls get_event_loop for compatibility when
     // get_running_loop is not available.
     GET_RUNNING_LOOP
@@ -460,215 +1033,1801 @@ pub fn get_running_loop(py: Python) -> PyResult<Bound<PyAny>> {
         })?
         .bind(py)
         .call0()
-}
+        .or_else(|e| {
+            if e.is_instance_of::<PyRuntimeError>(py) {
+                // Some GUI-integrated loops (e.g. older versions of qasync) run without registering
+                // themselves as the running loop, so fall back on a running loop set on the policy.
+                // If that fails too, the error that no loop is running is the one that matters.
+                let running = policy_loop(py).and_then(|event_loop| {
+                    Ok(event_loop
+                        .call_method0("is_running")?
+                        .is_truthy()?
+                        .then_some(event_loop))
+                });
+                if let Ok(Some(event_loop)) = running {
+                    return Ok(event_loop);
+                }
+            }
 
-fn contextvars(py: Python) -> PyResult<&Bound<PyAny>> {
-    Ok(CONTEXTVARS
-        .get_or_try_init(|| py.import_bound("contextvars").map(|m| m.into()))?
-        .bind(py))
+            Err(e)
+        })
 }
 
-fn copy_context(py: Python) -> PyResult<Bound<PyAny>> {
-    contextvars(py)?.call_method0("copy_context")
+/// Set the event loop that conversions on the current OS thread use by default
+///
+/// Worker threads that were handed an event loop explicitly can register it here instead of
+/// passing [`TaskLocals`] to every conversion. The `get_current_loop` and `get_current_locals`
+/// functions, and with them the conversions that don't take explicit locals, use this loop when
+/// the current task isn't scoped to other task locals. Only if neither is set do they fall back on
+/// [`get_running_loop`].
+///
+/// # Arguments
+/// * `event_loop` - The event loop for this thread, or `None` to clear it
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "tokio-runtime")]
+/// # fn main() -> pyo3::PyResult<()> {
+/// use pyo3::prelude::*;
+///
+/// pyo3::prepare_freethreaded_python();
+///
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+///     pyo3_async_runtimes::set_thread_event_loop(Some(event_loop.clone()));
+///
+///     // no loop is running on this thread, but the conversions know which one to use
+///     let current_loop = pyo3_async_runtimes::tokio::get_current_loop(py)?;
+///     assert!(current_loop.is(&event_loop));
+///
+///     pyo3_async_runtimes::set_thread_event_loop(None);
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {}
+/// ```
+pub fn set_thread_event_loop(event_loop: Option<Bound<PyAny>>) {
+    THREAD_EVENT_LOOP.with(|c| c.replace(event_loop.map(Bound::unbind)));
 }
 
-/// Task-local data to store for Python conversions.
-#[derive(Debug)]
-pub struct TaskLocals {
-    /// Track the event loop of the Python task
-    event_loop: PyObject,
-    /// Track the contextvars of the Python task
-    context: PyObject,
+/// Get the event loop that was set for the current OS thread with [`set_thread_event_loop`]
+pub fn get_thread_event_loop(py: Python) -> Option<Bound<PyAny>> {
+    THREAD_EVENT_LOOP.with(|c| {
+        c.borrow()
+            .as_ref()
+            .map(|event_loop| event_loop.clone_ref(py).into_bound(py))
+    })
 }
 
-impl TaskLocals {
-    /// At a minimum, TaskLocals must store the event loop.
-    pub fn new(event_loop: Bound<PyAny>) -> Self {
-        Self {
-            context: event_loop.py().None(),
-            event_loop: event_loop.into(),
+/// Call `f` with `event_loop` as the loop that conversions use, restoring the previous state
+/// afterwards
+///
+/// Within `f`, the `get_current_loop` and `get_current_locals` functions, and with them the
+/// conversions that don't take explicit locals, use `event_loop` even if the current task is
+/// scoped to other task locals, or another loop is set for the thread. This is meant for code that
+/// juggles multiple loops, like tests and embedders. Calls can be nested, and the previous loop is
+/// restored even if `f` panics.
+///
+/// The loop only applies to the code that runs synchronously within `f`. Futures that are
+/// awaited later should be scoped to their loop with the `scope` functions of the runtimes
+/// instead.
+///
+/// # Arguments
+/// * `event_loop` - The event loop to be used within `f`
+/// * `f` - The function to be called
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "tokio-runtime")]
+/// # fn main() -> pyo3::PyResult<()> {
+/// use pyo3::prelude::*;
+///
+/// pyo3::prepare_freethreaded_python();
+///
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+///
+///     let fut = pyo3_async_runtimes::with_event_loop(event_loop.clone(), || {
+///         pyo3_async_runtimes::tokio::future_into_py(py, async { Ok(42) })
+///     })?;
+///     assert!(fut.call_method0("get_loop")?.is(&event_loop));
+///
+///     // the previous state is restored, and there's no running loop on this thread
+///     assert!(pyo3_async_runtimes::tokio::get_current_loop(py).is_err());
+///
+///     event_loop.call_method1("run_until_complete", (fut,))?;
+///     event_loop.call_method0("close")?;
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {}
+/// ```
+pub fn with_event_loop<F, R>(event_loop: Bound<PyAny>, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct Restore(Option<PyObject>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SCOPED_EVENT_LOOP.with(|c| *c.borrow_mut() = previous);
         }
     }
 
-    /// Construct TaskLocals with the event loop returned by `get_running_loop`
-    pub fn with_running_loop(py: Python) -> PyResult<Self> {
-        Ok(Self::new(get_running_loop(py)?))
-    }
+    let previous = SCOPED_EVENT_LOOP.with(|c| c.replace(Some(event_loop.unbind())));
+    let _restore = Restore(previous);
 
-    /// Manually provide the contextvars for the current task.
-    pub fn with_context(self, context: Bound<PyAny>) -> Self {
-        Self {
-            context: context.into(),
-            ..self
-        }
+    f()
+}
+
+/// Get the event loop of the innermost [`with_event_loop`] call on this thread
+pub(crate) fn scoped_event_loop(py: Python) -> Option<Bound<PyAny>> {
+    SCOPED_EVENT_LOOP.with(|c| {
+        c.borrow()
+            .as_ref()
+            .map(|event_loop| event_loop.clone_ref(py).into_bound(py))
+    })
+}
+
+/// Establish process-wide default task locals until the returned guard is dropped
+///
+/// Conversions that are started from arbitrary Rust threads fail by default, because there is no
+/// running loop to detect on them. The defaults on top of this stack are used instead, after the
+/// task locals of the current task, the loop set with [`set_thread_event_loop`], and the running
+/// loop have been tried. Unlike with a running loop, the context of the defaults is used as is.
+///
+/// Dropping the guard removes its defaults from the stack, wherever they are by then, so guards
+/// don't need to be dropped in order.
+///
+/// # Arguments
+/// * `locals` - The task locals to be used by default
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "tokio-runtime")]
+/// # fn main() -> pyo3::PyResult<()> {
+/// use pyo3::prelude::*;
+/// use pyo3_async_runtimes::TaskLocals;
+///
+/// pyo3::prepare_freethreaded_python();
+///
+/// let (event_loop, guard) = Python::with_gil(|py| -> PyResult<_> {
+///     let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+///     let guard = pyo3_async_runtimes::push_default_locals(TaskLocals::new(event_loop.clone()));
+///     Ok((event_loop.unbind(), guard))
+/// })?;
+///
+/// std::thread::spawn(|| {
+///     Python::with_gil(|py| -> PyResult<()> {
+///         // no loop is running on this thread, so the defaults are used
+///         pyo3_async_runtimes::tokio::future_into_py(py, async { Ok(()) })?;
+///         Ok(())
+///     })
+/// })
+/// .join()
+/// .unwrap()?;
+///
+/// drop(guard);
+/// # Python::with_gil(|py| event_loop.call_method0(py, "close"))?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {}
+/// ```
+pub fn push_default_locals(locals: TaskLocals) -> DefaultLocalsGuard {
+    let id = NEXT_DEFAULT_LOCALS_ID.fetch_add(1, Ordering::Relaxed);
+    DEFAULT_LOCALS.lock().unwrap().push((id, locals));
+
+    DefaultLocalsGuard { id }
+}
+
+/// Remove the defaults that were pushed last with [`push_default_locals`]
+///
+/// The guard of the removed defaults does nothing when it's dropped afterwards.
+pub fn pop_default_locals() -> Option<TaskLocals> {
+    DEFAULT_LOCALS
+        .lock()
+        .unwrap()
+        .pop()
+        .map(|(_, locals)| locals)
+}
+
+/// Keeps the task locals passed to [`push_default_locals`] on the stack of defaults until it's
+/// dropped
+#[must_use = "the defaults are removed again when the guard is dropped"]
+pub struct DefaultLocalsGuard {
+    id: u64,
+}
+
+impl Drop for DefaultLocalsGuard {
+    fn drop(&mut self) {
+        let mut defaults = DEFAULT_LOCALS.lock().unwrap();
+        let removed = defaults
+            .iter()
+            .position(|(id, _)| *id == self.id)
+            .map(|i| defaults.remove(i));
+
+        // drop the Python objects after the lock is released
+        drop(defaults);
+        drop(removed);
     }
+}
 
-    /// Capture the current task's contextvars
-    pub fn copy_context(self, py: Python) -> PyResult<Self> {
-        Ok(self.with_context(copy_context(py)?))
+/// Get the defaults on top of the stack of [`push_default_locals`]
+fn default_locals(py: Python) -> Option<TaskLocals> {
+    DEFAULT_LOCALS
+        .lock()
+        .unwrap()
+        .last()
+        .map(|(_, locals)| locals.clone_ref(py))
+}
+
+/// Get the task locals of the loop that runs on the current OS thread, without contextvars
+///
+/// Looking up the running loop and building its task locals, which looks up its methods and its
+/// completion queue, is most of the cost of looking up the locals of a conversion. So the locals
+/// of the running loop are kept per OS thread in [`RUNNING_LOCALS`], from when the loop starts
+/// until it stops, by a hook on `asyncio._set_running_loop`, see [`hook_set_running_loop`].
+///
+/// The hook is what keeps the cache correct for threads that switch loops, e.g. with `asyncio.run`
+/// in a row or with the loop of another thread: every change of the running loop of a thread goes
+/// through it, on that thread, and a loop has to stop before it can be closed, so the locals of a
+/// loop that stopped or closed are never handed out. A thread that isn't known to run a loop
+/// through the hook, e.g. because its loop sets itself running with a `_set_running_loop` that it
+/// imported before the hook was installed, like uvloop does, looks up its running loop with
+/// `asyncio._get_running_loop` on every call instead.
+fn running_locals(py: Python) -> PyResult<Option<TaskLocals>> {
+    let hooked = hook_set_running_loop(py);
+    if let Some(locals) = RUNNING_LOCALS.with(|cached| cached.borrow().clone()) {
+        return Ok(Some(locals));
     }
 
-    /// Get a reference to the event loop
-    pub fn event_loop<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
-        self.event_loop.clone_ref(py).into_bound(py)
+    let event_loop = peek_running_loop(py)?;
+    if event_loop.is_none() {
+        return Ok(None);
     }
 
-    /// Get a reference to the python context
-    pub fn context<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
-        self.context.clone_ref(py).into_bound(py)
+    // a loop that was started before the hook was installed still stops through it, if it's one
+    // of asyncio's own
+    let base_event_loop = BASE_EVENT_LOOP.get_or_try_init(|| -> PyResult<PyObject> {
+        Ok(asyncio(py)?.getattr("BaseEventLoop")?.into())
+    })?;
+    let keep = hooked && event_loop.is_instance(base_event_loop.bind(py))?;
+
+    let locals = TaskLocals::new(event_loop);
+    if keep {
+        RUNNING_LOCALS.with(|cached| *cached.borrow_mut() = Some(locals.clone()));
     }
+    Ok(Some(locals))
+}
 
-    /// Create a clone of the TaskLocals by incrementing the reference counters of the event loop and
-    /// contextvars.
-    pub fn clone_ref(&self, py: Python<'_>) -> Self {
-        Self {
-            event_loop: self.event_loop.clone_ref(py),
-            context: self.context.clone_ref(py),
-        }
+/// Check whether `event_loop` is the running loop of the current OS thread
+///
+/// This only calls into Python for threads whose running loop isn't in [`RUNNING_LOCALS`].
+pub(crate) fn is_running_loop(event_loop: &Bound<PyAny>) -> PyResult<bool> {
+    let py = event_loop.py();
+    let cached = RUNNING_LOCALS.with(|cached| {
+        cached
+            .borrow()
+            .as_ref()
+            .map(|locals| locals.py_event_loop().bind(py).is(event_loop))
+    });
+
+    match cached {
+        Some(is_running) => Ok(is_running),
+        None => Ok(peek_running_loop(py)?.is(event_loop)),
     }
 }
 
-#[pyclass]
-struct PyTaskCompleter {
-    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+/// Get the running loop of the current OS thread, or `None`, without raising
+fn peek_running_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    PEEK_RUNNING_LOOP
+        .get_or_try_init(|| -> PyResult<PyObject> {
+            Ok(asyncio(py)?.getattr("_get_running_loop")?.into())
+        })?
+        .bind(py)
+        .call0()
 }
 
-#[pymethods]
-impl PyTaskCompleter {
-    #[pyo3(signature = (task))]
-    pub fn __call__(&mut self, task: &Bound<PyAny>) -> PyResult<()> {
-        debug_assert!(task.call_method0("done")?.extract()?);
-        let result = match task.call_method0("result") {
-            Ok(val) => Ok(val.into()),
-            Err(e) => Err(e),
-        };
+/// Replace `_set_running_loop` in `asyncio` with a hook that keeps [`RUNNING_LOCALS`] up to date
+///
+/// The loops that are built on `asyncio.BaseEventLoop`, and the tokio event loop of this crate,
+/// look up `asyncio.events._set_running_loop` whenever they start and stop, so the hook sees them
+/// set and unset themselves as the running loop of their thread, even if they started before it
+/// was installed. Returns whether the hook is installed.
+fn hook_set_running_loop(py: Python) -> bool {
+    *RUNNING_LOOP_HOOKED.get_or_init(|| install_running_loop_hook(py).is_ok())
+}
 
-        // unclear to me whether or not this should be a panic or silent error.
-        //
-        // calling PyTaskCompleter twice should not be possible, but I don't think it really hurts
-        // anything if it happens.
-        if let Some(tx) = self.tx.take() {
-            if tx.send(result).is_err() {
-                // cancellation is not an error
-            }
+fn install_running_loop_hook(py: Python) -> PyResult<()> {
+    let asyncio = asyncio(py)?;
+    let events = asyncio.getattr("events")?;
+    let set_running_loop = events.getattr("_set_running_loop")?;
+
+    let original = set_running_loop.clone().unbind();
+    let hook = PyCFunction::new_closure_bound(
+        py,
+        Some(pyo3::ffi::c_str!("_set_running_loop")),
+        None,
+        move |args, _kwargs| -> PyResult<PyObject> {
+            let py = args.py();
+            let (event_loop,): (Bound<PyAny>,) = args.extract()?;
+            original.bind(py).call1((&event_loop,))?;
+
+            let locals = if event_loop.is_none() {
+                None
+            } else {
+                Some(TaskLocals::new(event_loop))
+            };
+            // the replaced locals are dropped outside of the borrow, since that can run Python code
+            let replaced = RUNNING_LOCALS.with(|cached| cached.replace(locals));
+            drop(replaced);
+
+            Ok(py.None())
+        },
+    )?;
+
+    for module in [&events, asyncio] {
+        if module.getattr("_set_running_loop")?.is(&set_running_loop) {
+            module.setattr("_set_running_loop", &hook)?;
         }
+    }
 
-        Ok(())
+    Ok(())
+}
+
+/// Get the event loop of the current OS thread, preferring the one set with
+/// [`set_thread_event_loop`] over the running loop, and falling back on the defaults of
+/// [`push_default_locals`]
+pub(crate) fn current_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    if let Some(event_loop) = get_thread_event_loop(py) {
+        return Ok(event_loop);
     }
+
+    get_running_loop(py).or_else(|e| match default_locals(py) {
+        Some(locals) => Ok(locals.event_loop(py)),
+        None => Err(e),
+    })
 }
 
-#[pyclass]
-struct PyEnsureFuture {
-    awaitable: PyObject,
-    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+/// Get the task locals of the current OS thread, like [`current_loop`]
+///
+/// The context is copied from the current thread, unless the locals are the defaults of
+/// [`push_default_locals`].
+pub(crate) fn current_locals(py: Python) -> PyResult<TaskLocals> {
+    current_locals_with_source(py).map(|(_, locals)| locals)
 }
 
-#[pymethods]
-impl PyEnsureFuture {
-    pub fn __call__(&mut self) -> PyResult<()> {
-        Python::with_gil(|py| {
-            let task = ensure_future(py, self.awaitable.bind(py))?;
-            let on_complete = PyTaskCompleter { tx: self.tx.take() };
-            task.call_method1("add_done_callback", (on_complete,))?;
+/// Get the task locals of the current OS thread along with where they come from
+pub(crate) fn current_locals_with_source(py: Python) -> PyResult<(LocalsSource, TaskLocals)> {
+    if let Some(event_loop) = get_thread_event_loop(py) {
+        let locals = TaskLocals::new(event_loop).copy_context_if_enabled(py)?;
+        return Ok((LocalsSource::ThreadEventLoop, locals));
+    }
 
-            Ok(())
-        })
+    if let Some(locals) = running_locals(py)? {
+        let locals = locals.copy_context_if_enabled(py)?;
+        return Ok((LocalsSource::RunningLoop, locals));
+    }
+
+    match get_running_loop(py) {
+        Ok(event_loop) => {
+            let locals = TaskLocals::new(event_loop).copy_context_if_enabled(py)?;
+            Ok((LocalsSource::RunningLoop, locals))
+        }
+        Err(e) => match default_locals(py) {
+            Some(locals) => Ok((LocalsSource::Default, locals)),
+            None => Err(e),
+        },
     }
 }
 
-fn call_soon_threadsafe(
-    event_loop: &Bound<PyAny>,
-    context: &Bound<PyAny>,
-    args: impl IntoPy<Py<PyTuple>>,
-) -> PyResult<()> {
-    let py = event_loop.py();
+/// Get the loop of the current event loop policy for this thread
+///
+/// Since Python 3.12, the default policies warn with a `DeprecationWarning` before they create and
+/// set a new loop on the main thread, so the warning is raised as an error instead, which fails
+/// the lookup before the loop is created. Before 3.12, a loop is created there like with
+/// `asyncio.get_event_loop`, but it isn't running, so it's never mistaken for the running loop.
+fn policy_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    let warnings = py.import_bound("warnings")?;
+    let catch_warnings = warnings.call_method0("catch_warnings")?;
+    catch_warnings.call_method0("__enter__")?;
 
-    let kwargs = PyDict::new_bound(py);
-    kwargs.set_item("context", context)?;
+    let event_loop = warnings
+        .call_method1(
+            "simplefilter",
+            ("error", py.get_type_bound::<PyDeprecationWarning>()),
+        )
+        .and_then(|_| {
+            asyncio(py)?
+                .call_method0("get_event_loop_policy")?
+                .call_method0("get_event_loop")
+        });
 
-    event_loop.call_method("call_soon_threadsafe", args, Some(&kwargs))?;
-    Ok(())
+    catch_warnings.call_method1("__exit__", (py.None(), py.None(), py.None()))?;
+    event_loop
 }
 
-/// Convert a Python `awaitable` into a Rust Future
+/// The environment variable that makes the `run` functions use uvloop when it is set to `1`
+const UVLOOP_ENV: &str = "PYO3_ASYNC_RUNTIMES_UVLOOP";
+
+fn uvloop(py: Python) -> PyResult<Option<&Bound<PyAny>>> {
+    Ok(UVLOOP
+        .get_or_try_init(|| match py.import_bound("uvloop") {
+            Ok(uvloop) => Ok(Some(uvloop.into())),
+            Err(e) if e.is_instance_of::<PyImportError>(py) => Ok(None),
+            Err(e) => Err(e),
+        })?
+        .as_ref()
+        .map(|uvloop| uvloop.bind(py)))
+}
+
+/// Install uvloop's event loop policy if uvloop is available
 ///
-/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
-/// completion handler sends the result of this Task through a
-/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
-/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+/// Event loops created by `asyncio.new_event_loop` after this call, including the ones created by
+/// the `run` functions of this crate, will be uvloop loops. This is equivalent to calling
+/// `asyncio.set_event_loop_policy(uvloop.EventLoopPolicy())` in Python, which unlike
+/// `uvloop.install()` isn't deprecated on recent Python versions.
 ///
-/// # Arguments
-/// * `locals` - The Python event loop and context to be used for the provided awaitable
-/// * `awaitable` - The Python `awaitable` to be converted
+/// Returns `Ok(false)` without changing the policy if uvloop is not installed.
 ///
-/// # Examples
+/// The `run` functions also install uvloop automatically when the `PYO3_ASYNC_RUNTIMES_UVLOOP`
+/// environment variable is set to `1`, which makes it easy to run a whole test suite on uvloop.
 ///
-/// ```
-/// use std::time::Duration;
+/// # Examples
 ///
+/// ```no_run
 /// use pyo3::prelude::*;
 ///
-/// const PYTHON_CODE: &'static str = r#"
-/// import asyncio
-///
-/// async def py_sleep(duration):
-///     await asyncio.sleep(duration)
-/// "#;
-///
 /// # #[cfg(feature = "tokio-runtime")]
-/// async fn py_sleep(seconds: f32) -> PyResult<()> {
-///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
-///         Ok(
-///             PyModule::from_code_bound(
-///                 py,
-///                 PYTHON_CODE,
-///                 "test_into_future/test_mod.py",
-///                 "test_mod"
-///             )?
-///             .into()
-///         )
-///     })?;
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
 ///
 ///     Python::with_gil(|py| {
-///         pyo3_async_runtimes::into_future_with_locals(
-///             &pyo3_async_runtimes::tokio::get_current_locals(py)?,
-///             test_mod
-///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
-///                 .into_bound(py),
-///         )
-///     })?
-///     .await?;
-///     Ok(())
+///         if !pyo3_async_runtimes::install_uvloop(py)? {
+///             println!("uvloop is not available, falling back to the default event loop");
+///         }
+///
+///         pyo3_async_runtimes::tokio::run(py, async move { Ok(()) })
+///     })
 /// }
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {}
 /// ```
-pub fn into_future_with_locals(
-    locals: &TaskLocals,
-    awaitable: Bound<PyAny>,
-) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
-    let py = awaitable.py();
-    let (tx, rx) = oneshot::channel();
+pub fn install_uvloop(py: Python) -> PyResult<bool> {
+    match uvloop(py)? {
+        Some(uvloop) => {
+            asyncio(py)?.call_method1(
+                "set_event_loop_policy",
+                (uvloop.getattr("EventLoopPolicy")?.call0()?,),
+            )?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
 
-    call_soon_threadsafe(
-        &locals.event_loop(py),
-        &locals.context(py),
-        (PyEnsureFuture {
-            awaitable: awaitable.into(),
-            tx: Some(tx),
-        },),
-    )?;
+/// Check whether the given event loop is a uvloop loop
+///
+/// Returns `Ok(false)` if uvloop is not installed.
+pub fn is_uvloop(event_loop: &Bound<PyAny>) -> PyResult<bool> {
+    match uvloop(event_loop.py())? {
+        Some(uvloop) => event_loop.is_instance(&uvloop.getattr("Loop")?),
+        None => Ok(false),
+    }
+}
+
+/// The names of the event loop policies accepted by [`install_event_loop_policy`]
+pub const EVENT_LOOP_POLICIES: &[&str] = &["asyncio", "uvloop", "winloop", "selector", "proactor"];
+
+/// Create the event loop policy `name`, one of [`EVENT_LOOP_POLICIES`]
+pub(crate) fn event_loop_policy<'p>(py: Python<'p>, name: &str) -> PyResult<Bound<'p, PyAny>> {
+    match name {
+        "asyncio" => asyncio(py)?.getattr("DefaultEventLoopPolicy")?.call0(),
+        "uvloop" | "winloop" => py.import_bound(name)?.getattr("EventLoopPolicy")?.call0(),
+        // the default policy uses selector event loops on every platform but Windows
+        "selector" if cfg!(windows) => asyncio(py)?
+            .getattr("WindowsSelectorEventLoopPolicy")?
+            .call0(),
+        "selector" => asyncio(py)?.getattr("DefaultEventLoopPolicy")?.call0(),
+        "proactor" if cfg!(windows) => asyncio(py)?
+            .getattr("WindowsProactorEventLoopPolicy")?
+            .call0(),
+        "proactor" => Err(PyValueError::new_err(
+            "the `proactor` event loop policy is only available on Windows",
+        )),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown event loop policy `{}`, expected one of {:?}",
+            name, EVENT_LOOP_POLICIES
+        ))),
+    }
+}
+
+/// Install the event loop policy `name`, which is one of [`EVENT_LOOP_POLICIES`]
+///
+/// `"asyncio"` is the default policy of asyncio, `"uvloop"` and `"winloop"` are the policies of
+/// [uvloop](https://github.com/MagicStack/uvloop) and its Windows port
+/// [winloop](https://github.com/Vizonex/Winloop). `"selector"` and `"proactor"` choose between the
+/// two event loops of asyncio on Windows, where the proactor loop is the default. Other platforms
+/// only have the selector loop, so `"selector"` is the default policy there and `"proactor"` fails
+/// with a `ValueError`. Unlike [`install_uvloop`], this fails with an
+/// `ImportError` if the module of the policy is not installed, since the policy was asked for
+/// explicitly. This is what the `event_loop_policy` argument of the `main` attributes calls before
+/// the event loop is created.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pyo3::prelude::*;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::install_event_loop_policy(py, "uvloop")?;
+///         pyo3_async_runtimes::tokio::run(py, async move { Ok(()) })
+///     })
+/// }
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {}
+/// ```
+pub fn install_event_loop_policy(py: Python, name: &str) -> PyResult<()> {
+    let policy = event_loop_policy(py, name)?;
+    asyncio(py)?.call_method1("set_event_loop_policy", (policy,))?;
+    Ok(())
+}
+
+/// Install uvloop if it was requested through the environment
+fn install_requested_uvloop(py: Python) -> PyResult<()> {
+    if std::env::var_os(UVLOOP_ENV).map_or(false, |val| val == "1") && !install_uvloop(py)? {
+        return Err(PyImportError::new_err(format!(
+            "{} is set, but uvloop is not installed",
+            UVLOOP_ENV
+        )));
+    }
+
+    Ok(())
+}
+
+/// Create a new event loop for the `run` functions, installing uvloop first if it was requested
+/// through the environment
+fn new_event_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    install_requested_uvloop(py)?;
+    asyncio(py)?.call_method0("new_event_loop")
+}
+
+fn contextvars(py: Python) -> PyResult<&Bound<PyAny>> {
+    Ok(CONTEXTVARS
+        .get_or_try_init(|| py.import_bound("contextvars").map(|m| m.into()))?
+        .bind(py))
+}
+
+fn copy_context(py: Python) -> PyResult<Bound<PyAny>> {
+    contextvars(py)?.call_method0("copy_context")
+}
+
+/// Task-local data to store for Python conversions.
+///
+/// Besides the event loop, the task locals can carry the `contextvars.Context` that Python code
+/// runs in, and the executor that [`run_in_executor_with_locals`] runs synchronous functions in.
+/// They're built up with the `with_*` methods:
+///
+/// ```
+/// # use pyo3::prelude::*;
+/// # fn build(py: Python) -> PyResult<pyo3_async_runtimes::TaskLocals> {
+/// let asyncio = py.import_bound("asyncio")?;
+/// let executor = py
+///     .import_bound("concurrent.futures")?
+///     .call_method1("ThreadPoolExecutor", (4,))?;
+///
+/// let locals = pyo3_async_runtimes::TaskLocals::new(asyncio.call_method0("new_event_loop")?)
+///     .copy_context(py)?
+///     .with_executor(executor);
+/// # Ok(locals)
+/// # }
+/// ```
+///
+/// The locals are shared behind a reference count, so cloning them is cheap and doesn't need the
+/// GIL. This is what the runtimes do for every conversion that looks them up.
+#[derive(Debug, Clone)]
+pub struct TaskLocals {
+    inner: Arc<Locals>,
+}
+
+#[derive(Debug)]
+struct Locals {
+    /// Track the event loop of the Python task
+    event_loop: PyEventLoop,
+    /// Track the contextvars of the Python task
+    context: PyObject,
+    /// The executor for synchronous functions, or `None` for the loop's default executor
+    executor: PyObject,
+    /// The methods of the event loop, looked up once instead of per conversion
+    methods: LoopMethods,
+    /// The results of Rust futures that wait to be set on the event loop
+    completions: Arc<CompletionQueue>,
+}
+
+impl Locals {
+    fn clone_ref(&self, py: Python) -> Self {
+        Self {
+            event_loop: self.event_loop.clone_ref(py),
+            context: self.context.clone_ref(py),
+            executor: self.executor.clone_ref(py),
+            methods: self.methods.clone_ref(py),
+            completions: self.completions.clone(),
+        }
+    }
+}
+
+impl TaskLocals {
+    /// At a minimum, TaskLocals must store the event loop.
+    ///
+    /// The methods of the loop that every conversion calls, such as `call_soon_threadsafe`, are
+    /// looked up here once, so replacing them on the loop afterwards doesn't affect these locals.
+    pub fn new(event_loop: Bound<PyAny>) -> Self {
+        let py = event_loop.py();
+
+        Self {
+            inner: Arc::new(Locals {
+                context: py.None(),
+                executor: py.None(),
+                methods: LoopMethods::new(&event_loop),
+                completions: CompletionQueue::of(&event_loop),
+                event_loop: PyEventLoop::new(event_loop),
+            }),
+        }
+    }
+
+    /// Change the locals, without copying them unless they're shared
+    fn modify(mut self, py: Python, f: impl FnOnce(&mut Locals)) -> Self {
+        match Arc::get_mut(&mut self.inner) {
+            Some(inner) => f(inner),
+            None => {
+                let mut inner = self.inner.clone_ref(py);
+                f(&mut inner);
+                self.inner = Arc::new(inner);
+            }
+        }
+
+        self
+    }
+
+    /// Construct TaskLocals with the event loop returned by `get_running_loop`
+    pub fn with_running_loop(py: Python) -> PyResult<Self> {
+        match running_locals(py)? {
+            Some(locals) => Ok(locals),
+            None => Ok(Self::new(get_running_loop(py)?)),
+        }
+    }
+
+    /// Manually provide the contextvars for the current task.
+    pub fn with_context(self, context: Bound<PyAny>) -> Self {
+        let py = context.py();
+        self.modify(py, |locals| locals.context = context.unbind())
+    }
+
+    /// Capture the current task's contextvars
+    pub fn copy_context(self, py: Python) -> PyResult<Self> {
+        Ok(self.with_context(copy_context(py)?))
+    }
+
+    /// Forget the contextvars, so that Python code runs in the event loop's context instead
+    pub fn without_context(self, py: Python) -> Self {
+        self.modify(py, |locals| locals.context = py.None())
+    }
+
+    /// Run synchronous functions in `executor` instead of the event loop's default executor
+    ///
+    /// `executor` is passed on to `loop.run_in_executor`, so it's usually a
+    /// `concurrent.futures.Executor`.
+    pub fn with_executor(self, executor: Bound<PyAny>) -> Self {
+        let py = executor.py();
+        self.modify(py, |locals| locals.executor = executor.unbind())
+    }
+
+    /// Set whether the task locals that are captured from Python copy the current contextvars
+    ///
+    /// This is enabled by default: the `get_current_locals` and `run*` functions of the runtimes
+    /// copy the current `contextvars.Context`, and the Python code that a Rust future awaits runs
+    /// in that context. That way, context variables such as tracing IDs or database sessions
+    /// survive a hop through Rust. Applications that don't use context variables can disable it
+    /// to save a copy per conversion.
+    ///
+    /// Task locals that are given a context explicitly with [`TaskLocals::with_context`] or
+    /// [`TaskLocals::copy_context`] aren't affected.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether the current contextvars are copied
+    pub fn set_copy_context(enabled: bool) {
+        COPY_CONTEXT.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Capture the current task's contextvars, unless copying them is disabled with
+    /// [`TaskLocals::set_copy_context`]
+    pub(crate) fn copy_context_if_enabled(self, py: Python) -> PyResult<Self> {
+        if COPY_CONTEXT.load(Ordering::Relaxed) {
+            self.copy_context(py)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Get a reference to the event loop
+    pub fn event_loop<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
+        self.inner.event_loop.bind(py).clone()
+    }
+
+    /// Get the event loop as a [`PyEventLoop`]
+    pub fn py_event_loop(&self) -> &PyEventLoop {
+        &self.inner.event_loop
+    }
+
+    /// Get a reference to the python context
+    pub fn context<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
+        self.inner.context.bind(py).clone()
+    }
+
+    /// Get a reference to the executor, which is `None` for the event loop's default executor
+    pub fn executor<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
+        self.inner.executor.bind(py).clone()
+    }
+
+    /// Call a synchronous Python function within the contextvars of these task locals
+    ///
+    /// Synchronous functions called from a Rust future don't see the contextvars of the Python
+    /// task, since they're called from a Rust thread. This calls `callable` with `args` through
+    /// `contextvars.Context.run` instead, or directly if there is no context. Like with
+    /// `Context.run`, a context can't be entered by two threads at the same time.
+    ///
+    /// # Arguments
+    /// * `py` - PyO3 GIL guard
+    /// * `callable` - The Python function to be called
+    /// * `args` - The positional arguments for `callable`
+    pub fn call_in_context<'p>(
+        &self,
+        py: Python<'p>,
+        callable: &Bound<'p, PyAny>,
+        args: impl IntoPy<Py<PyTuple>>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let args = args.into_py(py).into_bound(py);
+        let context = self.context(py);
+
+        if context.is_none() {
+            callable.call1(args)
+        } else {
+            context.call_method1(
+                "run",
+                PyTuple::new_bound(
+                    py,
+                    std::iter::once(callable.clone())
+                        .chain(args.iter())
+                        .collect::<Vec<_>>(),
+                ),
+            )
+        }
+    }
+
+    /// Schedule `callback(*args)` on the event loop from any thread, within `context`
+    pub(crate) fn call_soon_threadsafe(
+        &self,
+        py: Python,
+        context: &Bound<PyAny>,
+        args: impl IntoPy<Py<PyTuple>>,
+    ) -> PyResult<()> {
+        self.inner
+            .methods
+            .call_soon_threadsafe(self.inner.event_loop.bind(py), context, args)
+    }
+
+    /// Create an `asyncio.Future` attached to the event loop
+    pub(crate) fn create_future<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        self.inner
+            .methods
+            .create_future(self.inner.event_loop.bind(py))
+    }
+
+    /// Get the `create_task` method of the event loop
+    #[cfg_attr(not(feature = "unstable-streams"), allow(dead_code))]
+    pub(crate) fn create_task_method<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        self.inner
+            .methods
+            .create_task(self.inner.event_loop.bind(py))
+    }
+
+    /// Get the queue of the results that wait to be set on the event loop
+    pub(crate) fn completions(&self) -> &Arc<CompletionQueue> {
+        &self.inner.completions
+    }
+
+    /// Create a clone of the TaskLocals, which shares the event loop, contextvars and executor
+    ///
+    /// This is the same as [`Clone::clone`], which doesn't need the GIL.
+    pub fn clone_ref(&self, _py: Python<'_>) -> Self {
+        self.clone()
+    }
+}
+
+/// Options for the `asyncio.Runner` used by the `run_with_runner` functions (Python 3.11+)
+///
+/// `asyncio.Runner` takes care of setting up and tearing down the event loop: it cancels the tasks
+/// that are still pending, shuts down async generators and the default executor, and installs a
+/// `SIGINT` handler that cancels the main task on the main thread.
+#[derive(Debug, Default)]
+pub struct RunnerOptions {
+    debug: Option<bool>,
+    loop_factory: Option<PyObject>,
+    context: Option<PyObject>,
+}
+
+impl RunnerOptions {
+    /// Use the default options, which create the loop from the current event loop policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the event loop in debug mode
+    pub fn with_debug(self, debug: bool) -> Self {
+        Self {
+            debug: Some(debug),
+            ..self
+        }
+    }
+
+    /// Create the event loop with the given callable instead of the event loop policy
+    pub fn with_loop_factory(self, loop_factory: Bound<PyAny>) -> Self {
+        Self {
+            loop_factory: Some(loop_factory.into()),
+            ..self
+        }
+    }
+
+    /// Run the future in the given `contextvars.Context` instead of a copy of the current one
+    pub fn with_context(self, context: Bound<PyAny>) -> Self {
+        Self {
+            context: Some(context.into()),
+            ..self
+        }
+    }
+
+    /// Create the `asyncio.Runner` for these options
+    pub fn runner<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        let kwargs = PyDict::new_bound(py);
+        if let Some(debug) = self.debug {
+            kwargs.set_item("debug", debug)?;
+        }
+        if let Some(loop_factory) = self.loop_factory.as_ref() {
+            kwargs.set_item("loop_factory", loop_factory)?;
+        }
+
+        asyncio(py)?.getattr("Runner")?.call((), Some(&kwargs))
+    }
+
+    /// Get the context to run the future in
+    pub fn context<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        match self.context.as_ref() {
+            Some(context) => Ok(context.clone_ref(py).into_bound(py)),
+            None => copy_context(py),
+        }
+    }
+}
+
+#[pyclass]
+struct PyTaskCompleter {
+    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+}
+
+#[pymethods]
+impl PyTaskCompleter {
+    #[pyo3(signature = (task))]
+    pub fn __call__(&mut self, task: &Bound<PyAny>) -> PyResult<()> {
+        debug_assert!(task.call_method0("done")?.extract()?);
+        let result = match task.call_method0("result") {
+            Ok(val) => Ok(val.into()),
+            Err(e) => Err(e),
+        };
+
+        // unclear to me whether or not this should be a panic or silent error.
+        //
+        // calling PyTaskCompleter twice should not be possible, but I don't think it really hurts
+        // anything if it happens.
+        if let Some(tx) = self.tx.take() {
+            if tx.send(result).is_err() {
+                // cancellation is not an error
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The slot that the Task wrapping an awaitable is stored in once the event loop creates it
+type TaskSlot = Arc<Mutex<Option<PyObject>>>;
+
+#[pyclass]
+struct PyEnsureFuture {
+    awaitable: PyObject,
+    on_complete: PyObject,
+    task: TaskSlot,
+}
+
+#[pymethods]
+impl PyEnsureFuture {
+    pub fn __call__(&mut self) -> PyResult<()> {
+        Python::with_gil(|py| {
+            let task = ensure_future(py, self.awaitable.bind(py))?;
+            *self.task.lock().unwrap() = Some(task.clone().unbind());
+            task.call_method1("add_done_callback", (self.on_complete.bind(py),))?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Extracts the result of a task and sends it to the typed Rust future
+type TypedComplete = Box<dyn FnOnce(&Bound<PyAny>) + Send>;
+
+/// Completes a typed Rust future with the extracted result of a task
+#[pyclass]
+struct PyTypedCompleter {
+    complete: Option<TypedComplete>,
+}
+
+#[pymethods]
+impl PyTypedCompleter {
+    pub fn __call__(&mut self, task: &Bound<PyAny>) {
+        if let Some(complete) = self.complete.take() {
+            complete(task);
+        }
+    }
+}
+
+/// Call `on_complete` with `awaitable` (wrapped in a Task if needed) once it's done
+///
+/// Returns the slot that the Task is stored in once the event loop creates it, or `None` if the
+/// awaitable was already a future, along with the registration of the conversion.
+fn await_on_loop(
+    locals: &TaskLocals,
+    awaitable: Bound<PyAny>,
+    on_complete: Bound<PyAny>,
+) -> PyResult<(Option<TaskSlot>, Registration)> {
+    let py = awaitable.py();
+    let event_loop = locals.event_loop(py);
+    crate::debug::count(&crate::debug::AWAITABLES_INTO_RUST);
+
+    if asyncio(py)?
+        .call_method1("isfuture", (&awaitable,))?
+        .is_truthy()?
+    {
+        let registration = register(&event_loop, Target::Future(awaitable.clone().unbind()))?;
+
+        // futures and tasks are already scheduled, so only wait for them
+        add_done_callback(locals, &awaitable, on_complete)?;
+        Ok((None, registration))
+    } else {
+        let task = Arc::new(Mutex::new(None));
+        let registration = register(&event_loop, Target::Task(task.clone()))?;
+
+        locals.call_soon_threadsafe(
+            py,
+            &locals.context(py),
+            (PyEnsureFuture {
+                awaitable: awaitable.into(),
+                on_complete: on_complete.into(),
+                task: task.clone(),
+            },),
+        )?;
+        Ok((Some(task), registration))
+    }
+}
+
+fn add_done_callback(
+    locals: &TaskLocals,
+    future: &Bound<PyAny>,
+    on_complete: Bound<PyAny>,
+) -> PyResult<()> {
+    let py = future.py();
+
+    if future.call_method0("done")?.is_truthy()? {
+        // no need for a round trip through the event loop
+        on_complete.call1((future,))?;
+        return Ok(());
+    }
+
+    // the future may belong to another loop than the one in the task locals
+    let event_loop = match future.call_method0("get_loop") {
+        Ok(event_loop) => event_loop,
+        Err(_) => locals.event_loop(py),
+    };
+
+    if asyncio(py)?
+        .call_method0("_get_running_loop")?
+        .is(&event_loop)
+    {
+        future.call_method1("add_done_callback", (on_complete,))?;
+        Ok(())
+    } else {
+        // add_done_callback isn't threadsafe
+        call_soon_threadsafe(
+            &event_loop,
+            &locals.context(py),
+            (future.getattr("add_done_callback")?, on_complete),
+        )
+    }
+}
+
+/// Convert a Python `awaitable` into a Rust Future
+///
+/// This function converts the `awaitable` into a Python Task using `run_coroutine_threadsafe`. A
+/// completion handler sends the result of this Task through a
+/// `futures::channel::oneshot::Sender<PyResult<PyObject>>` and the future returned by this function
+/// simply awaits the result through the `futures::channel::oneshot::Receiver<PyResult<PyObject>>`.
+///
+/// An `awaitable` that is already an `asyncio.Future` (or a Task) isn't wrapped in another Task.
+/// The completion handler is attached to it directly on its own event loop, and a future that is
+/// already done is resolved right away without going through the event loop at all.
+///
+/// Dropping the returned [`PyFuture`] before it completes cancels the Task, unless the future was
+/// [detached](PyFuture::detach).
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def py_sleep(duration):
+///     await asyncio.sleep(duration)
+/// "#;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// async fn py_sleep(seconds: f32) -> PyResult<()> {
+///     let test_mod = Python::with_gil(|py| -> PyResult<PyObject> {
+///         Ok(
+///             PyModule::from_code_bound(
+///                 py,
+///                 PYTHON_CODE,
+///                 "test_into_future/test_mod.py",
+///                 "test_mod"
+///             )?
+///             .into()
+///         )
+///     })?;
+///
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::into_future_with_locals(
+///             &pyo3_async_runtimes::tokio::get_current_locals(py)?,
+///             test_mod
+///                 .call_method1(py, "py_sleep", (seconds.into_py(py),))?
+///                 .into_bound(py),
+///         )
+///     })?
+///     .await?;
+///     Ok(())
+/// }
+/// ```
+pub fn into_future_with_locals(locals: &TaskLocals, awaitable: Bound<PyAny>) -> PyResult<PyFuture> {
+    let py = awaitable.py();
+    let (tx, rx) = oneshot::channel();
+
+    let on_complete = Bound::new(py, PyTaskCompleter { tx: Some(tx) })?.into_any();
+    let (task, mut registration) = await_on_loop(locals, awaitable, on_complete)?;
+    let rx = Abortable::new(rx, registration.abort_registration());
+
+    Ok(PyFuture::new(locals.clone_ref(py), task, rx, registration))
+}
+
+/// Convert a Python `awaitable` into a Rust Future that resolves to how it finished
+///
+/// This works like [`into_future_with_locals`], but the future resolves to a [`PyOutcome`] that
+/// tells a cancelled awaitable apart from one that raised, so that `CancelledError` can be handled
+/// as control flow with a `match` instead of checking the type of the exception.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_async_runtimes::PyOutcome;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// async fn wait_for(awaitable: PyObject) -> PyResult<Option<PyObject>> {
+///     let fut = Python::with_gil(|py| {
+///         pyo3_async_runtimes::into_future_result_with_locals(
+///             &pyo3_async_runtimes::tokio::get_current_locals(py)?,
+///             awaitable.into_bound(py),
+///         )
+///     })?;
+///
+///     match fut.await {
+///         PyOutcome::Completed(value) => Ok(Some(value)),
+///         // someone else gave up on it, which isn't an error here
+///         PyOutcome::Cancelled => Ok(None),
+///         PyOutcome::Raised(e) => Err(e),
+///     }
+/// }
+/// ```
+pub fn into_future_result_with_locals(
+    locals: &TaskLocals,
+    awaitable: Bound<PyAny>,
+) -> PyResult<PyOutcomeFuture> {
+    into_future_with_locals(locals, awaitable).map(PyOutcomeFuture::new)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that resolves to its result extracted into `T`
+///
+/// This works like [`into_future_with_locals`], but the result is extracted by the completion
+/// handler on the event loop, which already holds the GIL. That saves the caller another
+/// `Python::with_gil` after awaiting the future. A result that fails to extract resolves to the
+/// extraction error.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_typed_with_locals<T>(
+    locals: &TaskLocals,
+    awaitable: Bound<PyAny>,
+) -> PyResult<PyFuture<T>>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    let py = awaitable.py();
+    let (tx, rx) = oneshot::channel();
+
+    let on_complete = Bound::new(
+        py,
+        PyTypedCompleter {
+            complete: Some(Box::new(move |task: &Bound<PyAny>| {
+                let result = task
+                    .call_method0("result")
+                    .and_then(|val| val.extract::<T>());
+
+                // the receiver is gone if the Rust future was dropped
+                let _ = tx.send(result);
+            })),
+        },
+    )?
+    .into_any();
+    let (task, mut registration) = await_on_loop(locals, awaitable, on_complete)?;
+    let rx = Abortable::new(rx, registration.abort_registration());
+
+    Ok(PyFuture::new(locals.clone_ref(py), task, rx, registration))
+}
+
+const EXECUTOR_GLUE: &str = r#"
+import asyncio
+import contextvars
+import functools
+
+async def run_in_executor(executor, func, *args):
+    # the executor's threads don't see the contextvars of the task otherwise
+    context = contextvars.copy_context()
+    return await asyncio.get_running_loop().run_in_executor(
+        executor, functools.partial(context.run, func, *args)
+    )
+"#;
+
+/// Run a synchronous Python function in the executor of `locals` and get a Rust Future for its
+/// result
+///
+/// `func(*args)` is called through `loop.run_in_executor` with the executor set by
+/// [`TaskLocals::with_executor`], or with the event loop's default executor if there is none.
+/// The function runs in a copy of the contextvars of `locals`, so blocking Python code can be
+/// moved off the event loop without losing its context.
+///
+/// Dropping the returned future cancels the `asyncio.Future` of `run_in_executor`, but like with
+/// `asyncio`, a call that's already running in the executor runs to completion.
+///
+/// # Arguments
+/// * `locals` - The Python event loop, context and executor to be used
+/// * `func` - The synchronous Python function to be called
+/// * `args` - The positional arguments for `func`
+pub fn run_in_executor_with_locals(
+    locals: &TaskLocals,
+    func: Bound<PyAny>,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> PyResult<PyFuture> {
+    static GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
+    let py = func.py();
+    let glue = GLUE_MOD
+        .get_or_try_init(|| -> PyResult<PyObject> {
+            Ok(PyModule::from_code_bound(
+                py,
+                EXECUTOR_GLUE,
+                "pyo3_asyncio/pyo3_asyncio_executor_glue.py",
+                "pyo3_asyncio_executor_glue",
+            )?
+            .into())
+        })?
+        .bind(py);
+
+    let args = args.into_py(py).into_bound(py);
+    let coro = glue.getattr("run_in_executor")?.call1(PyTuple::new_bound(
+        py,
+        [locals.executor(py), func]
+            .into_iter()
+            .chain(args.iter())
+            .collect::<Vec<_>>(),
+    ))?;
+
+    into_future_with_locals(locals, coro)
+}
+
+/// Rust future for a Python `awaitable`, returned by [`into_future_with_locals`] and
+/// [`into_future_typed_with_locals`]
+///
+/// If the future is dropped before it completes, e.g. because it lost a `select!`, the Task that
+/// wraps the awaitable is cancelled on the event loop. Call [`PyFuture::detach`] to let the Task
+/// run to completion instead. Awaitables that are already an `asyncio.Future` (or a Task) belong
+/// to the caller, so they're never cancelled.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct PyFuture<T = PyObject> {
+    locals: TaskLocals,
+    task: Option<TaskSlot>,
+    rx: Abortable<oneshot::Receiver<PyResult<T>>>,
+    done: bool,
+    _registration: Registration,
+}
+
+impl<T> PyFuture<T> {
+    fn new(
+        locals: TaskLocals,
+        task: Option<TaskSlot>,
+        rx: Abortable<oneshot::Receiver<PyResult<T>>>,
+        registration: Registration,
+    ) -> Self {
+        Self {
+            locals,
+            task,
+            rx,
+            done: false,
+            _registration: registration,
+        }
+    }
+
+    /// Keep the Task running if this future is dropped before it completes
+    pub fn detach(mut self) -> Self {
+        self.task = None;
+        self
+    }
+}
+
+impl<T> Future for PyFuture<T> {
+    type Output = PyResult<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let result = ready!(Pin::new(&mut self.rx).poll(cx));
+        self.done = true;
+
+        Poll::Ready(match result {
+            Ok(Ok(item)) => item,
+            // the completion handler was dropped, or the conversion was aborted
+            _ => Python::with_gil(|py| {
+                Err(PyErr::from_value_bound(
+                    asyncio(py)?.call_method0("CancelledError")?,
+                ))
+            }),
+        })
+    }
+}
+
+impl<T> Drop for PyFuture<T> {
+    fn drop(&mut self) {
+        let task = match self.task.take() {
+            Some(task) if !self.done => task,
+            _ => return,
+        };
+
+        Python::with_gil(|py| {
+            // scheduled after the PyEnsureFuture callback, so the task has been created by then.
+            // The event loop may be closed already, in which case there's nothing left to cancel.
+            let _ = self.locals.call_soon_threadsafe(
+                py,
+                &py.None().into_bound(py),
+                (PyCancelTask { task },),
+            );
+        });
+    }
+}
+
+/// How a Python awaitable converted by [`into_future_result_with_locals`] finished
+#[derive(Debug)]
+pub enum PyOutcome<T = PyObject> {
+    /// The awaitable returned this value
+    Completed(T),
+    /// The awaitable was cancelled, i.e. it raised `asyncio.CancelledError`
+    Cancelled,
+    /// The awaitable raised this exception
+    Raised(PyErr),
+}
+
+impl<T> PyOutcome<T> {
+    /// Whether the awaitable was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, PyOutcome::Cancelled)
+    }
 
-    Ok(async move {
-        match rx.await {
-            Ok(item) => item,
-            Err(_) => Python::with_gil(|py| {
+    /// Convert back into the result of the awaitable, with the `CancelledError` of a cancellation
+    pub fn into_result(self) -> PyResult<T> {
+        match self {
+            PyOutcome::Completed(value) => Ok(value),
+            PyOutcome::Cancelled => Python::with_gil(|py| {
                 Err(PyErr::from_value_bound(
                     asyncio(py)?.call_method0("CancelledError")?,
                 ))
             }),
+            PyOutcome::Raised(e) => Err(e),
+        }
+    }
+}
+
+/// Rust future for a Python `awaitable` that resolves to a [`PyOutcome`], returned by
+/// [`into_future_result_with_locals`]
+///
+/// It cancels the Task that wraps the awaitable when it's dropped like [`PyFuture`] does.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct PyOutcomeFuture<T = PyObject> {
+    inner: PyFuture<T>,
+}
+
+impl<T> PyOutcomeFuture<T> {
+    fn new(inner: PyFuture<T>) -> Self {
+        Self { inner }
+    }
+
+    /// Keep the Task running if this future is dropped before it completes
+    pub fn detach(self) -> Self {
+        Self::new(self.inner.detach())
+    }
+}
+
+impl<T> Future for PyOutcomeFuture<T> {
+    type Output = PyOutcome<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        Poll::Ready(match ready!(Pin::new(&mut self.inner).poll(cx)) {
+            Ok(value) => PyOutcome::Completed(value),
+            Err(e) => Python::with_gil(|py| {
+                let cancelled = asyncio(py)
+                    .and_then(|asyncio| asyncio.getattr("CancelledError"))
+                    .map(|cancelled_error| e.is_instance_bound(py, &cancelled_error))
+                    .unwrap_or(false);
+
+                if cancelled {
+                    PyOutcome::Cancelled
+                } else {
+                    PyOutcome::Raised(e)
+                }
+            }),
+        })
+    }
+}
+
+#[pyclass]
+struct PySpawnTask {
+    awaitable: Option<PyObject>,
+    task: TaskSlot,
+    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+}
+
+#[pymethods]
+impl PySpawnTask {
+    pub fn __call__(&mut self, py: Python) -> PyResult<()> {
+        let awaitable = match self.awaitable.take() {
+            Some(awaitable) => awaitable,
+            None => return Ok(()),
+        };
+
+        let task = ensure_future(py, awaitable.bind(py))?;
+        *self.task.lock().unwrap() = Some(task.clone().unbind());
+        task.call_method1(
+            "add_done_callback",
+            (PyTaskCompleter { tx: self.tx.take() },),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Handle to a Python task spawned from Rust with [`spawn_py_with_locals`]
+///
+/// Awaiting the handle resolves to the result of the task, extracted into `T`. Unlike the future
+/// returned by [`into_future_with_locals`], the handle also lets Rust check on the task and cancel
+/// it. Dropping the handle detaches the task, it keeps running on the event loop.
+pub struct PyTask<T = PyObject> {
+    locals: TaskLocals,
+    task: TaskSlot,
+    rx: oneshot::Receiver<PyResult<PyObject>>,
+    _result: PhantomData<fn() -> T>,
+}
+
+impl<T> PyTask<T> {
+    /// Request the cancellation of the task
+    ///
+    /// Like `asyncio.Task.cancel`, this only requests the cancellation. Awaiting the handle
+    /// afterwards raises `asyncio.CancelledError` unless the task catches it.
+    pub fn cancel(&self, py: Python) -> PyResult<()> {
+        self.locals.call_soon_threadsafe(
+            py,
+            &py.None().into_bound(py),
+            (PyCancelTask {
+                task: self.task.clone(),
+            },),
+        )
+    }
+
+    /// Check whether the task is done
+    pub fn done(&self, py: Python) -> PyResult<bool> {
+        self.task_state(py, "done")
+    }
+
+    /// Check whether the task was cancelled
+    pub fn cancelled(&self, py: Python) -> PyResult<bool> {
+        self.task_state(py, "cancelled")
+    }
+
+    /// Get the underlying `asyncio.Task`, or `None` if the event loop hasn't created it yet
+    pub fn task<'p>(&self, py: Python<'p>) -> Option<Bound<'p, PyAny>> {
+        self.task
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|task| task.bind(py).clone())
+    }
+
+    fn task_state(&self, py: Python, state: &str) -> PyResult<bool> {
+        match self.task(py) {
+            Some(task) => task.call_method0(state)?.is_truthy(),
+            // the task hasn't been created yet
+            None => Ok(false),
+        }
+    }
+}
+
+impl<T> Future for PyTask<T>
+where
+    T: for<'py> FromPyObject<'py>,
+{
+    type Output = PyResult<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let result = ready!(Pin::new(&mut self.rx).poll(cx));
+
+        Poll::Ready(Python::with_gil(|py| match result {
+            Ok(Ok(val)) => val.extract(py),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(PyErr::from_value_bound(
+                asyncio(py)?.call_method0("CancelledError")?,
+            )),
+        }))
+    }
+}
+
+#[pyclass]
+struct PyCancelTask {
+    task: TaskSlot,
+}
+
+#[pymethods]
+impl PyCancelTask {
+    pub fn __call__(&self, py: Python) -> PyResult<()> {
+        // scheduled after the PySpawnTask callback, so the task has been created by now
+        if let Some(task) = self.task.lock().unwrap().as_ref() {
+            task.call_method0(py, "cancel")?;
         }
+
+        Ok(())
+    }
+}
+
+/// Spawn a Python `awaitable` as a task on the event loop in `locals` and get a handle to it
+///
+/// The `awaitable` is wrapped in an `asyncio.Task` like in [`into_future_with_locals`], but the
+/// returned [`PyTask`] also lets Rust code cancel the task and query its state, and it resolves to
+/// a result of type `T` instead of a `PyObject`.
+///
+/// # Arguments
+/// * `locals` - The Python event loop and context to be used for the provided awaitable
+/// * `awaitable` - The Python `awaitable` to be spawned
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def forever():
+///     await asyncio.sleep(3600)
+///     return 1
+/// "#;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// async fn give_up() -> PyResult<()> {
+///     let task = Python::with_gil(|py| {
+///         let test_mod = PyModule::from_code_bound(py, PYTHON_CODE, "forever.py", "forever")?;
+///
+///         pyo3_async_runtimes::spawn_py_with_locals::<i32>(
+///             &pyo3_async_runtimes::tokio::get_current_locals(py)?,
+///             test_mod.call_method0("forever")?,
+///         )
+///     })?;
+///
+///     tokio::time::sleep(Duration::from_millis(100)).await;
+///     Python::with_gil(|py| task.cancel(py))?;
+///
+///     assert!(task.await.is_err());
+///     Ok(())
+/// }
+/// ```
+pub fn spawn_py_with_locals<T>(
+    locals: &TaskLocals,
+    awaitable: Bound<PyAny>,
+) -> PyResult<PyTask<T>> {
+    let py = awaitable.py();
+    shutdown::ensure_running(&locals.event_loop(py))?;
+
+    let (tx, rx) = oneshot::channel();
+    let task = Arc::new(Mutex::new(None));
+
+    locals.call_soon_threadsafe(
+        py,
+        &locals.context(py),
+        (PySpawnTask {
+            awaitable: Some(awaitable.unbind()),
+            task: task.clone(),
+            tx: Some(tx),
+        },),
+    )?;
+
+    Ok(PyTask {
+        locals: locals.clone_ref(py),
+        task,
+        rx,
+        _result: PhantomData,
     })
 }
 
+/// Limit the time that a Python `awaitable` may take to complete
+///
+/// This wraps the `awaitable` in `asyncio.wait_for`, so it raises `asyncio.TimeoutError` and is
+/// cancelled if it doesn't complete within `duration`. The returned awaitable can go either way:
+///
+/// - Convert it with [`into_future_with_locals`] to wait for a Python coroutine from Rust with a
+///   deadline.
+/// - Return it to Python in place of the awaitable of a Rust future, e.g. one converted with
+///   [`future_into_py_with_locals`](crate::generic::future_into_py_with_locals). The Rust future
+///   is dropped when the timeout cancels the awaitable.
+///
+/// Rust futures can also time out on the timer of their runtime, e.g. with `tokio::timeout`, which
+/// raises the same error.
+///
+/// # Arguments
+/// * `duration` - How long the `awaitable` may take
+/// * `awaitable` - The Python `awaitable` to be limited
+pub fn timeout<'py>(
+    duration: Duration,
+    awaitable: Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    asyncio(awaitable.py())?.call_method1("wait_for", (awaitable, duration.as_secs_f64()))
+}
+
+/// Prints the exception of a task that nobody awaits
+#[pyclass]
+struct PyDumpTaskError;
+
+#[pymethods]
+impl PyDumpTaskError {
+    pub fn __call__(&self, task: &Bound<PyAny>) -> PyResult<()> {
+        if task.call_method0("cancelled")?.is_truthy()? {
+            return Ok(());
+        }
+
+        let exception = task.call_method0("exception")?;
+        if !exception.is_none() {
+            dump_err(task.py())(PyErr::from_value_bound(exception));
+        }
+
+        Ok(())
+    }
+}
+
+#[pyclass]
+struct PyExitContext {
+    manager: PyObject,
+}
+
+#[pymethods]
+impl PyExitContext {
+    pub fn __call__(&self, py: Python) -> PyResult<()> {
+        let exit = self
+            .manager
+            .call_method1(py, "__aexit__", (py.None(), py.None(), py.None()))?;
+
+        ensure_future(py, exit.bind(py))?.call_method1("add_done_callback", (PyDumpTaskError,))?;
+
+        Ok(())
+    }
+}
+
+/// Guard for a Python async context manager entered from Rust
+///
+/// [`PyAsyncContext::enter_with_locals`] awaits `__aenter__` and returns the guard, which holds
+/// its result in [`value`](PyAsyncContext::value). Awaiting [`close`](PyAsyncContext::close) or
+/// [`close_with_error`](PyAsyncContext::close_with_error) awaits `__aexit__` again.
+///
+/// A guard that is dropped without being closed, e.g. because of an early return with `?`, still
+/// schedules `__aexit__` on the event loop, but nobody waits for it to complete and its errors are
+/// only printed.
+pub struct PyAsyncContext {
+    locals: TaskLocals,
+    manager: PyObject,
+    value: PyObject,
+    exited: bool,
+}
+
+impl PyAsyncContext {
+    /// Enter an async context manager on the event loop in `locals`
+    ///
+    /// # Arguments
+    /// * `locals` - The Python event loop and context to run `__aenter__` and `__aexit__` on
+    /// * `manager` - The async context manager to enter
+    pub fn enter_with_locals(
+        locals: TaskLocals,
+        manager: Bound<PyAny>,
+    ) -> PyResult<impl Future<Output = PyResult<Self>> + Send> {
+        let enter = into_future_with_locals(&locals, manager.call_method0("__aenter__")?)?;
+        let manager = manager.unbind();
+
+        Ok(async move {
+            let value = enter.await?;
+
+            Ok(Self {
+                locals,
+                manager,
+                value,
+                exited: false,
+            })
+        })
+    }
+
+    /// Enter an async context manager on the event loop of the current task
+    ///
+    /// # Arguments
+    /// * `manager` - The async context manager to enter
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pyo3::prelude::*;
+    /// use pyo3_async_runtimes::PyAsyncContext;
+    ///
+    /// # #[cfg(feature = "tokio-runtime")]
+    /// async fn fetch(session: PyObject, url: String) -> PyResult<PyObject> {
+    ///     let response = Python::with_gil(|py| {
+    ///         PyAsyncContext::enter::<pyo3_async_runtimes::tokio::TokioRuntime>(
+    ///             session.call_method1(py, "get", (url,))?.into_bound(py),
+    ///         )
+    ///     })?
+    ///     .await?;
+    ///
+    ///     let text = Python::with_gil(|py| {
+    ///         pyo3_async_runtimes::tokio::into_future(
+    ///             response.value().call_method0(py, "text")?.into_bound(py),
+    ///         )
+    ///     })?
+    ///     .await;
+    ///
+    ///     match text {
+    ///         Ok(text) => {
+    ///             response.close().await?;
+    ///             Ok(text)
+    ///         }
+    ///         Err(e) => {
+    ///             response.close_with_error(&e).await?;
+    ///             Err(e)
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn enter<R>(manager: Bound<PyAny>) -> PyResult<impl Future<Output = PyResult<Self>> + Send>
+    where
+        R: generic::ContextExt,
+    {
+        let locals = generic::get_current_locals::<R>(manager.py())?;
+        Self::enter_with_locals(locals, manager)
+    }
+
+    /// The result of `__aenter__`
+    pub fn value(&self) -> &PyObject {
+        &self.value
+    }
+
+    /// Exit the context manager without an error
+    pub async fn close(self) -> PyResult<()> {
+        self.exit(None).await.map(|_| ())
+    }
+
+    /// Exit the context manager with `err`
+    ///
+    /// Resolves to `true` if the context manager suppressed the error.
+    pub async fn close_with_error(self, err: &PyErr) -> PyResult<bool> {
+        let err = Python::with_gil(|py| err.clone_ref(py));
+        self.exit(Some(err)).await
+    }
+
+    async fn exit(mut self, err: Option<PyErr>) -> PyResult<bool> {
+        self.exited = true;
+
+        let exit = Python::with_gil(|py| {
+            let (ty, value, traceback) = match err {
+                Some(err) => (
+                    err.get_type_bound(py).into_any().unbind(),
+                    err.value_bound(py).clone().into_any().unbind(),
+                    err.traceback_bound(py)
+                        .map_or_else(|| py.None(), |tb| tb.into_any().unbind()),
+                ),
+                None => (py.None(), py.None(), py.None()),
+            };
+
+            into_future_with_locals(
+                &self.locals,
+                self.manager
+                    .bind(py)
+                    .call_method1("__aexit__", (ty, value, traceback))?,
+            )
+        })?;
+
+        let suppressed = exit.await?;
+        Python::with_gil(|py| suppressed.bind(py).is_truthy())
+    }
+}
+
+impl Drop for PyAsyncContext {
+    fn drop(&mut self) {
+        if self.exited {
+            return;
+        }
+
+        Python::with_gil(|py| {
+            let _ = self
+                .locals
+                .call_soon_threadsafe(
+                    py,
+                    &self.locals.context(py),
+                    (PyExitContext {
+                        manager: self.manager.clone_ref(py),
+                    },),
+                )
+                .map_err(dump_err(py));
+        });
+    }
+}
+
 fn dump_err(py: Python<'_>) -> impl FnOnce(PyErr) + '_ {
     move |e| {
         // We can't display Python exceptions via std::fmt::Display,
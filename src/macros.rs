@@ -0,0 +1,308 @@
+//! `impl_runtime!` boilerplate for custom runtimes and runtime selection for the `main` and `test`
+//! attributes
+
+/// Implement the [`generic`](crate::generic) traits for a custom executor and generate the
+/// module-level conversions for it
+///
+/// Hand-writing the [`generic`](crate::generic) plumbing for a custom executor takes a join handle,
+/// a task-local context, and a wrapper for every conversion. This macro generates all of it from
+/// the executor's spawn functions:
+///
+/// - `spawn` is called with a `Pin<Box<dyn Future<Output = ()> + Send>>` and must drive it to
+///   completion on the executor.
+/// - `spawn_local` is optional. It is called with a `Pin<Box<dyn Future<Output = ()>>>` and must
+///   drive it to completion on the current thread. If it is given, the runtime also implements
+///   [`SpawnLocalExt`](crate::generic::SpawnLocalExt).
+///
+/// Panics are caught before the futures reach the executor. Task locals are stored in a
+/// thread-local for the duration of each poll, so the executor doesn't need to provide task-local
+/// storage of its own.
+///
+/// Besides the runtime struct, the macro generates the following functions in the module it is
+/// invoked in, mirroring the runtime modules of this crate: `scope`, `scope_local`,
+/// `get_current_loop`, `get_current_locals`, `run_until_complete`, `run`,
+/// `future_into_py_with_locals`, `future_into_py`, and `into_future`.
+///
+/// # Examples
+///
+/// ```
+/// mod my_runtime {
+///     use std::{future::Future, pin::Pin, thread};
+///
+///     /// Drives every task on its own thread
+///     fn spawn(fut: Pin<Box<dyn Future<Output = ()> + Send>>) {
+///         thread::spawn(move || futures::executor::block_on(fut));
+///     }
+///
+///     pyo3_async_runtimes::impl_runtime! {
+///         /// A toy runtime for PyO3 Asyncio
+///         pub struct MyRuntime;
+///         spawn = spawn;
+///     }
+/// }
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable function that returns a value computed on the custom runtime
+/// #[pyfunction]
+/// fn answer(py: Python) -> PyResult<Bound<PyAny>> {
+///     my_runtime::future_into_py(py, async move { Ok(42) })
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_runtime {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident;
+        spawn = $spawn:expr;
+        $(spawn_local = $spawn_local:expr;)?
+    ) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $crate::generic::Runtime for $name {
+            type JoinError = $crate::__private::JoinErr;
+            type JoinHandle = $crate::__private::JoinHandle;
+
+            fn spawn<F>(fut: F) -> Self::JoinHandle
+            where
+                F: ::std::future::Future<Output = ()> + Send + 'static,
+            {
+                let (task, handle) = $crate::__private::task(fut);
+                ($spawn)(::std::boxed::Box::pin(task));
+                handle
+            }
+        }
+
+        impl $crate::generic::ContextExt for $name {
+            fn scope<F, R>(
+                locals: $crate::TaskLocals,
+                fut: F,
+            ) -> ::std::pin::Pin<::std::boxed::Box<dyn ::std::future::Future<Output = R> + Send>>
+            where
+                F: ::std::future::Future<Output = R> + Send + 'static,
+            {
+                ::std::boxed::Box::pin($crate::__private::Scoped::new(locals, fut))
+            }
+
+            fn get_task_locals() -> ::std::option::Option<$crate::TaskLocals> {
+                $crate::__private::get_task_locals()
+            }
+        }
+
+        impl $crate::generic::LocalContextExt for $name {
+            fn scope_local<F, R>(
+                locals: $crate::TaskLocals,
+                fut: F,
+            ) -> ::std::pin::Pin<::std::boxed::Box<dyn ::std::future::Future<Output = R>>>
+            where
+                F: ::std::future::Future<Output = R> + 'static,
+            {
+                ::std::boxed::Box::pin($crate::__private::Scoped::new(locals, fut))
+            }
+        }
+
+        $(
+            impl $crate::generic::SpawnLocalExt for $name {
+                fn spawn_local<F>(fut: F) -> Self::JoinHandle
+                where
+                    F: ::std::future::Future<Output = ()> + 'static,
+                {
+                    let (task, handle) = $crate::__private::task(fut);
+                    ($spawn_local)(::std::boxed::Box::pin(task));
+                    handle
+                }
+            }
+        )?
+
+        /// Set the task local event loop for the given future
+        #[allow(dead_code)]
+        $vis async fn scope<F, R>(locals: $crate::TaskLocals, fut: F) -> R
+        where
+            F: ::std::future::Future<Output = R> + Send + 'static,
+        {
+            <$name as $crate::generic::ContextExt>::scope(locals, fut).await
+        }
+
+        /// Set the task local event loop for the given !Send future
+        #[allow(dead_code)]
+        $vis async fn scope_local<F, R>(locals: $crate::TaskLocals, fut: F) -> R
+        where
+            F: ::std::future::Future<Output = R> + 'static,
+        {
+            <$name as $crate::generic::LocalContextExt>::scope_local(locals, fut).await
+        }
+
+        /// Get the current event loop from either Python or Rust async task local context
+        #[allow(dead_code)]
+        $vis fn get_current_loop(
+            py: $crate::__private::pyo3::Python,
+        ) -> $crate::__private::pyo3::PyResult<
+            $crate::__private::pyo3::Bound<$crate::__private::pyo3::PyAny>,
+        > {
+            $crate::generic::get_current_loop::<$name>(py)
+        }
+
+        /// Either copy the task locals from the current task OR get the current running loop and
+        /// contextvars from Python.
+        #[allow(dead_code)]
+        $vis fn get_current_locals(
+            py: $crate::__private::pyo3::Python,
+        ) -> $crate::__private::pyo3::PyResult<$crate::TaskLocals> {
+            $crate::generic::get_current_locals::<$name>(py)
+        }
+
+        /// Run the event loop until the given Future completes
+        #[allow(dead_code)]
+        $vis fn run_until_complete<F, T>(
+            event_loop: $crate::__private::pyo3::Bound<$crate::__private::pyo3::PyAny>,
+            fut: F,
+        ) -> $crate::__private::pyo3::PyResult<T>
+        where
+            F: ::std::future::Future<Output = $crate::__private::pyo3::PyResult<T>> + Send + 'static,
+            T: Send + Sync + 'static,
+        {
+            $crate::generic::run_until_complete::<$name, _, T>(&event_loop, fut)
+        }
+
+        /// Run the event loop until the given Future completes
+        #[allow(dead_code)]
+        $vis fn run<F, T>(
+            py: $crate::__private::pyo3::Python,
+            fut: F,
+        ) -> $crate::__private::pyo3::PyResult<T>
+        where
+            F: ::std::future::Future<Output = $crate::__private::pyo3::PyResult<T>> + Send + 'static,
+            T: Send + Sync + 'static,
+        {
+            $crate::generic::run::<$name, F, T>(py, fut)
+        }
+
+        /// Convert a Rust Future into a Python awaitable
+        #[allow(dead_code)]
+        $vis fn future_into_py_with_locals<F, T>(
+            py: $crate::__private::pyo3::Python,
+            locals: $crate::TaskLocals,
+            fut: F,
+        ) -> $crate::__private::pyo3::PyResult<
+            $crate::__private::pyo3::Bound<$crate::__private::pyo3::PyAny>,
+        >
+        where
+            F: ::std::future::Future<Output = $crate::__private::pyo3::PyResult<T>> + Send + 'static,
+            T: $crate::__private::pyo3::IntoPy<$crate::__private::pyo3::PyObject>,
+        {
+            $crate::generic::future_into_py_with_locals::<$name, F, T>(py, locals, fut)
+        }
+
+        /// Convert a Rust Future into a Python awaitable
+        #[allow(dead_code)]
+        $vis fn future_into_py<F, T>(
+            py: $crate::__private::pyo3::Python,
+            fut: F,
+        ) -> $crate::__private::pyo3::PyResult<
+            $crate::__private::pyo3::Bound<$crate::__private::pyo3::PyAny>,
+        >
+        where
+            F: ::std::future::Future<Output = $crate::__private::pyo3::PyResult<T>> + Send + 'static,
+            T: $crate::__private::pyo3::IntoPy<$crate::__private::pyo3::PyObject>,
+        {
+            $crate::generic::future_into_py::<$name, _, T>(py, fut)
+        }
+
+        /// Convert a Python `awaitable` into a Rust Future
+        #[allow(dead_code)]
+        $vis fn into_future(
+            awaitable: $crate::__private::pyo3::Bound<$crate::__private::pyo3::PyAny>,
+        ) -> $crate::__private::pyo3::PyResult<$crate::PyFuture> {
+            $crate::generic::into_future::<$name>(awaitable)
+        }
+    };
+}
+
+/// Forwards `#[pyo3_async_runtimes::main]` and `#[pyo3_async_runtimes::test]` to the runtime
+/// selected by the enabled Cargo features
+#[doc(hidden)]
+#[cfg(any(
+    feature = "actix-runtime",
+    feature = "async-std-runtime",
+    feature = "compio-runtime",
+    all(feature = "glommio-runtime", target_os = "linux"),
+    feature = "local-pool-runtime",
+    feature = "monoio-runtime",
+    feature = "smol-runtime",
+    feature = "tokio-runtime"
+))]
+#[macro_export]
+macro_rules! __default_runtime {
+    ($kind:ident [$($args:tt)*] $item:item) => {
+        const _: () = ::std::assert!(
+            $crate::__private::ENABLED_RUNTIMES == 1,
+            "multiple runtime features are enabled, choose one with `runtime = \"...\"`"
+        );
+
+        #[$crate::__private::default_runtime::$kind($($args)*)]
+        $item
+    };
+}
+
+/// Forwards `#[pyo3_async_runtimes::main]` and `#[pyo3_async_runtimes::test]` to the runtime
+/// selected by the enabled Cargo features
+#[doc(hidden)]
+#[cfg(not(any(
+    feature = "actix-runtime",
+    feature = "async-std-runtime",
+    feature = "compio-runtime",
+    all(feature = "glommio-runtime", target_os = "linux"),
+    feature = "local-pool-runtime",
+    feature = "monoio-runtime",
+    feature = "smol-runtime",
+    feature = "tokio-runtime"
+)))]
+#[macro_export]
+macro_rules! __default_runtime {
+    ($kind:ident [$($args:tt)*] $item:item) => {
+        ::std::compile_error!(
+            "no runtime feature is enabled, enable one of the `*-runtime` features of pyo3-async-runtimes"
+        );
+    };
+}
+
+/// Expands to the items if the `tokio-runtime` feature is enabled, for the tokio backends of
+/// `#[pyo3_async_runtimes::test_all_backends]`
+#[doc(hidden)]
+#[cfg(feature = "tokio-runtime")]
+#[macro_export]
+macro_rules! __if_tokio_runtime {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+/// Expands to the items if the `tokio-runtime` feature is enabled, for the tokio backends of
+/// `#[pyo3_async_runtimes::test_all_backends]`
+#[doc(hidden)]
+#[cfg(not(feature = "tokio-runtime"))]
+#[macro_export]
+macro_rules! __if_tokio_runtime {
+    ($($item:item)*) => {};
+}
+
+/// Expands to the items if the `async-std-runtime` feature is enabled, for the async-std backend
+/// of `#[pyo3_async_runtimes::test_all_backends]`
+#[doc(hidden)]
+#[cfg(feature = "async-std-runtime")]
+#[macro_export]
+macro_rules! __if_async_std_runtime {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+/// Expands to the items if the `async-std-runtime` feature is enabled, for the async-std backend
+/// of `#[pyo3_async_runtimes::test_all_backends]`
+#[doc(hidden)]
+#[cfg(not(feature = "async-std-runtime"))]
+#[macro_export]
+macro_rules! __if_async_std_runtime {
+    ($($item:item)*) => {};
+}
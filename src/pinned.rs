@@ -0,0 +1,374 @@
+//! Shared plumbing for runtimes whose tasks are pinned to a dedicated executor thread
+
+use std::{
+    any::Any,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::oneshot,
+    future::{AbortHandle, Abortable},
+    FutureExt,
+};
+
+use crate::{
+    generic::JoinError,
+    scoped::{self, Scoped},
+};
+
+/// A unit of work sent to the executor thread, which builds its `!Send` future there
+pub(crate) type Job = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()>>> + Send>;
+
+type TaskResult<T> = Result<T, Box<dyn Any + Send + 'static>>;
+
+/// The error returned by [`PinnedJoinHandle`], which is either a panic or a dropped task
+pub struct PinnedJoinErr(Option<Box<dyn Any + Send + 'static>>);
+
+impl JoinError for PinnedJoinErr {
+    fn is_panic(&self) -> bool {
+        self.0.is_some()
+    }
+    fn into_panic(self) -> Box<dyn Any + Send + 'static> {
+        self.0.expect("task was not panicked")
+    }
+}
+
+/// The runtimes' own join handles are `!Send`, so task results are reported through a oneshot
+/// instead. If the executor drops the task before it completes, the handle resolves to a
+/// non-panic error.
+pub struct PinnedJoinHandle(oneshot::Receiver<TaskResult<()>>);
+
+impl Future for PinnedJoinHandle {
+    type Output = Result<(), PinnedJoinErr>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_unpin(cx).map(|result| match result {
+            Ok(result) => result.map_err(|e| PinnedJoinErr(Some(e))),
+            Err(oneshot::Canceled) => Err(PinnedJoinErr(None)),
+        })
+    }
+}
+
+async fn catch_unwind_into<F>(fut: F, tx: oneshot::Sender<TaskResult<F::Output>>)
+where
+    F: Future,
+{
+    let _ = tx.send(AssertUnwindSafe(fut).catch_unwind().await);
+}
+
+// glommio spawns its executor thread itself
+#[cfg(any(
+    feature = "compio-runtime",
+    feature = "local-pool-runtime",
+    feature = "monoio-runtime"
+))]
+pub(crate) use self::executor::spawn_executor_thread;
+// runtimes that start their own executor thread
+#[cfg(any(
+    feature = "compio-runtime",
+    all(feature = "glommio-runtime", target_os = "linux"),
+    feature = "local-pool-runtime",
+    feature = "monoio-runtime"
+))]
+pub(crate) use self::executor::Executor;
+
+#[cfg(any(
+    feature = "compio-runtime",
+    all(feature = "glommio-runtime", target_os = "linux"),
+    feature = "local-pool-runtime",
+    feature = "monoio-runtime"
+))]
+mod executor {
+    use std::sync::Mutex;
+    #[cfg(any(
+        feature = "compio-runtime",
+        feature = "local-pool-runtime",
+        feature = "monoio-runtime"
+    ))]
+    use std::{fmt::Debug, thread};
+
+    use futures::channel::mpsc;
+    use once_cell::sync::OnceCell;
+
+    use super::Job;
+
+    /// The executor thread of a runtime, which is started on first use and runs the jobs
+    /// submitted to it from any thread
+    ///
+    /// `B` configures the runtime that drives the thread. It can be replaced with
+    /// [`Executor::init`] until the thread is started.
+    pub(crate) struct Executor<B> {
+        /// The builder passed to `init`, if any, until the thread is started
+        builder: Mutex<Option<Option<B>>>,
+        /// Starts the thread with the given builder, or the runtime's default one for `None`
+        start: fn(Option<B>, mpsc::UnboundedReceiver<Job>),
+        jobs: OnceCell<mpsc::UnboundedSender<Job>>,
+    }
+
+    impl<B> Executor<B> {
+        /// Create an executor whose thread is started by `start`, which runs every job received
+        /// from the channel as a task on it
+        pub(crate) const fn new(start: fn(Option<B>, mpsc::UnboundedReceiver<Job>)) -> Self {
+            Self {
+                builder: Mutex::new(Some(None)),
+                start,
+                jobs: OnceCell::new(),
+            }
+        }
+
+        /// Start the thread with `builder` instead of the default one
+        ///
+        /// Returns Err(()) if the thread had already been started, in which case `builder` is
+        /// dropped.
+        #[cfg_attr(
+            not(any(
+                feature = "compio-runtime",
+                all(feature = "glommio-runtime", target_os = "linux"),
+                feature = "monoio-runtime"
+            )),
+            allow(dead_code)
+        )]
+        pub(crate) fn init(&self, builder: B) -> Result<(), ()> {
+            match &mut *self.builder.lock().unwrap() {
+                Some(pending) => {
+                    *pending = Some(builder);
+                    Ok(())
+                }
+                None => Err(()),
+            }
+        }
+
+        /// Submit `job` to the executor thread, starting it if needed
+        pub(crate) fn submit(&self, job: Job) {
+            let jobs = self.jobs.get_or_init(|| {
+                let builder = self
+                    .builder
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("executor thread was already started");
+                let (tx, rx) = mpsc::unbounded();
+                (self.start)(builder, rx);
+                tx
+            });
+
+            // If the executor thread is gone the job is dropped, which the caller observes through
+            // its join handle.
+            let _ = jobs.unbounded_send(job);
+        }
+    }
+
+    /// Spawn the executor thread `name` for a runtime that is `!Send`, so it has to be built on
+    /// the thread that runs it
+    ///
+    /// `build` builds the runtime on the new thread, and `run` then drives it. This waits until
+    /// the runtime is built and panics if that fails.
+    #[cfg(any(
+        feature = "compio-runtime",
+        feature = "local-pool-runtime",
+        feature = "monoio-runtime"
+    ))]
+    pub(crate) fn spawn_executor_thread<R, E>(
+        name: &str,
+        build: impl FnOnce() -> Result<R, E> + Send + 'static,
+        run: impl FnOnce(R) + Send + 'static,
+    ) where
+        E: Debug + Send + 'static,
+    {
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+
+        thread::Builder::new()
+            .name(name.into())
+            .spawn(move || match build() {
+                Ok(rt) => {
+                    let _ = started_tx.send(Ok(()));
+                    run(rt);
+                }
+                Err(e) => {
+                    let _ = started_tx.send(Err(e));
+                }
+            })
+            .unwrap_or_else(|e| panic!("Unable to spawn {} thread: {}", name, e));
+
+        if let Err(e) = started_rx
+            .recv()
+            .unwrap_or_else(|_| panic!("{} thread exited unexpectedly", name))
+        {
+            panic!("Unable to build the runtime of {}: {:?}", name, e);
+        }
+    }
+}
+
+/// Implement the [`generic`](crate::generic) traits for a runtime whose tasks are pinned to
+/// executor threads
+///
+/// `submit` sends a [`Job`] to an executor thread, and `spawn_local` spawns a detached task onto
+/// the executor of the current thread.
+macro_rules! pinned_runtime {
+    ($name:ident, submit = $submit:expr, spawn_local = $spawn_local:expr $(,)?) => {
+        struct $name;
+
+        impl $crate::generic::Runtime for $name {
+            type JoinError = $crate::pinned::PinnedJoinErr;
+            type JoinHandle = $crate::pinned::PinnedJoinHandle;
+
+            fn spawn<F>(fut: F) -> Self::JoinHandle
+            where
+                F: ::std::future::Future<Output = ()> + Send + 'static,
+            {
+                <Self as $crate::generic::SpawnPinnedExt>::spawn_pinned(move || fut)
+            }
+        }
+
+        impl $crate::generic::ContextExt for $name {
+            fn scope<F, R>(
+                locals: $crate::TaskLocals,
+                fut: F,
+            ) -> ::std::pin::Pin<Box<dyn ::std::future::Future<Output = R> + Send>>
+            where
+                F: ::std::future::Future<Output = R> + Send + 'static,
+            {
+                Box::pin($crate::scoped::Scoped::new(locals, fut))
+            }
+
+            fn get_task_locals() -> Option<$crate::TaskLocals> {
+                $crate::scoped::get_task_locals()
+            }
+        }
+
+        impl $crate::generic::SpawnLocalExt for $name {
+            fn spawn_local<F>(fut: F) -> Self::JoinHandle
+            where
+                F: ::std::future::Future<Output = ()> + 'static,
+            {
+                let (task, handle) = $crate::pinned::task(fut);
+                ($spawn_local)(task);
+                handle
+            }
+        }
+
+        impl $crate::generic::SpawnPinnedExt for $name {
+            fn spawn_pinned<F, Fut>(f: F) -> Self::JoinHandle
+            where
+                F: FnOnce() -> Fut + Send + 'static,
+                Fut: ::std::future::Future<Output = ()> + 'static,
+            {
+                let (job, handle) = $crate::pinned::job(f);
+                ($submit)(job);
+                handle
+            }
+        }
+
+        impl $crate::generic::LocalContextExt for $name {
+            fn scope_local<F, R>(
+                locals: $crate::TaskLocals,
+                fut: F,
+            ) -> ::std::pin::Pin<Box<dyn ::std::future::Future<Output = R>>>
+            where
+                F: ::std::future::Future<Output = R> + 'static,
+            {
+                Box::pin($crate::scoped::Scoped::new(locals, fut))
+            }
+        }
+    };
+}
+
+pub(crate) use pinned_runtime;
+
+/// Wrap `fut` so that its completion (or panic) is reported through the returned join handle
+pub fn task<F>(fut: F) -> (impl Future<Output = ()>, PinnedJoinHandle)
+where
+    F: Future<Output = ()>,
+{
+    let (tx, rx) = oneshot::channel();
+    (catch_unwind_into(fut, tx), PinnedJoinHandle(rx))
+}
+
+/// Create a job from the `Send` closure `f` and wrap it so its completion (or panic) is reported
+/// through the returned join handle
+pub(crate) fn job<F, Fut>(f: F) -> (Job, PinnedJoinHandle)
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    (
+        Box::new(move || Box::pin(catch_unwind_into(f(), tx))),
+        PinnedJoinHandle(rx),
+    )
+}
+
+/// Submit the future created by `f` to an executor thread via `submit` and return a `Send` future
+/// that resolves to its output
+///
+/// The task locals of the caller are carried over to the spawned task. Dropping the returned
+/// future aborts the spawned task, and panics are resumed in the task awaiting the result.
+pub(crate) fn spawn_pinned<S, F, Fut>(
+    submit: S,
+    f: F,
+) -> impl Future<Output = Fut::Output> + Send + 'static
+where
+    S: FnOnce(Job),
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future + 'static,
+    Fut::Output: Send + 'static,
+{
+    let locals = scoped::get_task_locals();
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    let (tx, rx) = oneshot::channel();
+
+    submit(Box::new(move || {
+        let fut = Abortable::new(f(), abort_registration);
+        match locals {
+            Some(locals) => Box::pin(catch_unwind_into(Scoped::new(locals, fut), tx)),
+            None => Box::pin(catch_unwind_into(fut, tx)),
+        }
+    }));
+
+    struct AbortOnDrop(AbortHandle);
+
+    impl Drop for AbortOnDrop {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+
+    let guard = AbortOnDrop(abort_handle);
+
+    async move {
+        let result = rx.await.expect("executor was shut down");
+        drop(guard);
+
+        match result {
+            Ok(Ok(val)) => val,
+            Ok(Err(_aborted)) => unreachable!("task was aborted while still being awaited"),
+            Err(e) => panic::resume_unwind(e),
+        }
+    }
+}
+
+/// Run a blocking function on its own thread so that it doesn't stall the executor thread
+///
+/// Panics are propagated to the task awaiting the result.
+#[cfg(feature = "attributes")]
+pub fn unblock<F, T>(f: F) -> impl Future<Output = T> + Send + 'static
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+
+    std::thread::spawn(move || {
+        let _ = tx.send(panic::catch_unwind(AssertUnwindSafe(f)));
+    });
+
+    rx.map(
+        |result| match result.expect("blocking thread was terminated") {
+            Ok(val) => val,
+            Err(e) => panic::resume_unwind(e),
+        },
+    )
+}
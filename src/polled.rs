@@ -0,0 +1,241 @@
+//! Awaiting Python awaitables from Rust by stepping them on the runtime, without an event loop task
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{channel::oneshot, FutureExt};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyStopIteration},
+    prelude::*,
+};
+
+use crate::{add_done_callback, debug, TaskLocals};
+
+/// The most steps an awaitable takes per poll while it only yields to the scheduler
+///
+/// A bare `yield` (e.g. `asyncio.sleep(0)`) asks to run again right away. Up to this many of them
+/// are run back to back, and then the runtime gets to run other tasks before the next batch.
+const POLL_BATCH: usize = 32;
+
+/// Convert a Python `awaitable` into a Rust Future that steps it from the runtime
+///
+/// [`into_future_with_locals`](crate::into_future_with_locals) wraps the awaitable in an
+/// `asyncio.Task` on the event loop, and waits for the done callback of the task. That costs a
+/// trip to the loop to create the task, another one to start it, and a task object per awaited
+/// object. This function instead steps the iterator returned by `awaitable.__await__()` itself,
+/// on the thread that polls the returned future, like `asyncio.Task` does on the loop:
+///
+/// - A bare `yield` runs the next step right away, in batches of a few steps per poll.
+/// - A yielded `asyncio.Future` (or anything that sets `_asyncio_future_blocking`, like a task or
+///   a future of another library) parks the Rust future until that future is done. The done
+///   callback is added on the loop of the future, so futures of any loop can be waited for.
+/// - Anything else fails the future with a `RuntimeError`, like it fails an `asyncio.Task`.
+///
+/// The steps run in the contextvars of `locals`, but outside of any event loop:
+/// `asyncio.get_running_loop()` raises in them, and so do `asyncio.sleep` with a delay and the
+/// other functions that schedule callbacks on the running loop. This suits awaitables that only
+/// compute, yield, or wait for futures that are handed to them, where creating a task per awaited
+/// object is the bulk of the cost. Use `into_future_with_locals` for everything else.
+///
+/// Dropping the returned future before it completes cancels the future the awaitable waits for,
+/// if any, and closes the awaitable, which raises `GeneratorExit` in a coroutine.
+///
+/// # Arguments
+/// * `locals` - The contextvars to step the awaitable in
+/// * `awaitable` - The Python `awaitable` to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def add(a, b):
+///     await asyncio.sleep(0)
+///     return a + b
+/// "#;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// async fn add(a: i32, b: i32) -> PyResult<i32> {
+///     let fut = Python::with_gil(|py| {
+///         let test_mod = PyModule::from_code_bound(py, PYTHON_CODE, "add.py", "add")?;
+///
+///         pyo3_async_runtimes::into_future_polled_with_locals(
+///             &pyo3_async_runtimes::tokio::get_current_locals(py)?,
+///             test_mod.call_method1("add", (a, b))?,
+///         )
+///     })?;
+///
+///     let sum = fut.await?;
+///     Python::with_gil(|py| sum.extract(py))
+/// }
+/// ```
+pub fn into_future_polled_with_locals(
+    locals: &TaskLocals,
+    awaitable: Bound<PyAny>,
+) -> PyResult<PyPolledFuture> {
+    let iter = awaitable.call_method0("__await__")?;
+    debug::count(&debug::AWAITABLES_INTO_RUST);
+
+    Ok(PyPolledFuture {
+        locals: locals.clone(),
+        iter: Some(iter.unbind()),
+        waiting: None,
+    })
+}
+
+/// A Rust Future that steps a Python awaitable, see [`into_future_polled_with_locals`]
+#[derive(Debug)]
+pub struct PyPolledFuture {
+    locals: TaskLocals,
+    /// The iterator of the awaitable, until it finished
+    iter: Option<PyObject>,
+    /// The future that the awaitable waits for, and the receiver that its done callback wakes
+    waiting: Option<(PyObject, oneshot::Receiver<()>)>,
+}
+
+impl PyPolledFuture {
+    /// Run one step of the awaitable, or return its result if it finished
+    ///
+    /// Returns `None` if the awaitable is ready to take the next step, or waits for a future.
+    fn step(&mut self, py: Python) -> Option<PyResult<PyObject>> {
+        let iter = self.iter.as_ref()?.bind(py);
+
+        let next = match iter.getattr("__next__") {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+        let yielded = match self.locals.call_in_context(py, &next, ()) {
+            Ok(yielded) => yielded,
+            Err(e) => return Some(finished(py, e)),
+        };
+
+        if yielded.is_none() {
+            return None;
+        }
+
+        let blocking = yielded
+            .getattr("_asyncio_future_blocking")
+            .and_then(|blocking| blocking.is_truthy())
+            .unwrap_or(false);
+        if !blocking {
+            return Some(Err(PyRuntimeError::new_err(format!(
+                "awaitable got bad yield: {}",
+                yielded
+                    .repr()
+                    .map_or_else(|_| "<unknown>".to_string(), |repr| repr.to_string())
+            ))));
+        }
+
+        // the future is handed back to the awaitable, which takes its result once it's done
+        if let Err(e) = yielded.setattr("_asyncio_future_blocking", false) {
+            return Some(Err(e));
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let on_done = match Bound::new(py, PolledWaker { tx: Some(tx) }) {
+            Ok(on_done) => on_done.into_any(),
+            Err(e) => return Some(Err(e)),
+        };
+        if let Err(e) = add_done_callback(&self.locals, &yielded, on_done) {
+            return Some(Err(e));
+        }
+        self.waiting = Some((yielded.unbind(), rx));
+
+        None
+    }
+}
+
+/// Get the result of an awaitable from the error that ended its iterator
+fn finished(py: Python, e: PyErr) -> PyResult<PyObject> {
+    if e.is_instance_of::<PyStopIteration>(py) {
+        Ok(e.value_bound(py).getattr("value")?.unbind())
+    } else {
+        Err(e)
+    }
+}
+
+impl Future for PyPolledFuture {
+    type Output = PyResult<PyObject>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some((_, rx)) = self.waiting.as_mut() {
+            // the sender is only dropped without sending if the loop of the future is gone, in
+            // which case the awaitable gets to find out from the future
+            if rx.poll_unpin(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.waiting = None;
+        }
+
+        Python::with_gil(|py| {
+            for _ in 0..POLL_BATCH {
+                if let Some(result) = self.step(py) {
+                    self.iter = None;
+                    return Poll::Ready(result);
+                }
+
+                if let Some((_, rx)) = self.waiting.as_mut() {
+                    // a future that is done already calls back right away
+                    if rx.poll_unpin(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.waiting = None;
+                }
+            }
+
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+    }
+}
+
+impl Drop for PyPolledFuture {
+    fn drop(&mut self) {
+        let iter = match self.iter.take() {
+            Some(iter) => iter,
+            None => return,
+        };
+
+        Python::with_gil(|py| {
+            if let Some((future, _)) = self.waiting.take() {
+                // like the task that awaits it would, the future is cancelled on its own loop
+                let future = future.bind(py);
+                let cancelled = future.getattr("cancel").and_then(|cancel| {
+                    let event_loop = future.call_method0("get_loop")?;
+                    event_loop.call_method1("call_soon_threadsafe", (cancel,))
+                });
+                if let Err(e) = cancelled {
+                    e.print_and_set_sys_last_vars(py);
+                }
+            }
+
+            if let Ok(close) = iter.bind(py).getattr("close") {
+                if let Err(e) = self.locals.call_in_context(py, &close, ()) {
+                    e.print_and_set_sys_last_vars(py);
+                }
+            }
+        });
+    }
+}
+
+/// Wakes a [`PyPolledFuture`] once the future it waits for is done
+#[pyclass]
+struct PolledWaker {
+    tx: Option<oneshot::Sender<()>>,
+}
+
+#[pymethods]
+impl PolledWaker {
+    fn __call__(&mut self, _future: &Bound<PyAny>) {
+        if let Some(tx) = self.tx.take() {
+            // the receiver is gone if the Rust future was dropped
+            let _ = tx.send(());
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! A typed handle to a Python event loop
+//!
+//! Not every event loop implements the full `asyncio.AbstractEventLoop` interface, GUI-integrated
+//! loops in particular. The workarounds for them live here, so that the rest of the crate can call
+//! the loop without caring about them.
+
+use pyo3::{
+    exceptions::PyTypeError,
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
+
+use crate::asyncio;
+
+/// A Python `asyncio` event loop
+///
+/// This wraps the loop object with methods for the calls this library makes on it, instead of
+/// going through `getattr`/`call_method1` on a raw `PyObject`. The methods tolerate loops that
+/// don't implement all of `asyncio.AbstractEventLoop`, such as older versions of `qasync`.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+/// use pyo3_async_runtimes::PyEventLoop;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// Python::with_gil(|py| -> PyResult<()> {
+///     let event_loop = PyEventLoop::new(
+///         py.import_bound("asyncio")?.call_method0("new_event_loop")?,
+///     );
+///
+///     let fut = event_loop.create_future(py)?;
+///     let context = py.None().into_bound(py);
+///     event_loop.call_soon_threadsafe(py, &context, (fut.getattr("set_result")?, 42))?;
+///     assert_eq!(
+///         event_loop
+///             .bind(py)
+///             .call_method1("run_until_complete", (fut,))?
+///             .extract::<i32>()?,
+///         42
+///     );
+///
+///     event_loop.bind(py).call_method0("close")?;
+///     assert!(event_loop.is_closed(py)?);
+///     Ok(())
+/// })
+/// # .unwrap();
+/// ```
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct PyEventLoop(PyObject);
+
+impl PyEventLoop {
+    /// Wrap a Python event loop
+    pub fn new(event_loop: Bound<PyAny>) -> Self {
+        Self(event_loop.unbind())
+    }
+
+    /// Get a reference to the wrapped loop object
+    pub fn bind<'a, 'p>(&'a self, py: Python<'p>) -> &'a Bound<'p, PyAny> {
+        self.0.bind(py)
+    }
+
+    /// Convert into the wrapped loop object
+    pub fn into_bound(self, py: Python) -> Bound<PyAny> {
+        self.0.into_bound(py)
+    }
+
+    /// Create another reference to the same loop
+    pub fn clone_ref(&self, py: Python) -> Self {
+        Self(self.0.clone_ref(py))
+    }
+
+    /// Schedule `callback(*args)` on the loop from any thread, like `loop.call_soon_threadsafe`
+    ///
+    /// The callback runs in `context`, or in the loop's context if it's `None`.
+    ///
+    /// # Arguments
+    /// * `py` - The current PyO3 GIL guard
+    /// * `context` - The `contextvars.Context` to run the callback in, or `None`
+    /// * `args` - The callback followed by its arguments
+    pub fn call_soon_threadsafe(
+        &self,
+        py: Python,
+        context: &Bound<PyAny>,
+        args: impl IntoPy<Py<PyTuple>>,
+    ) -> PyResult<()> {
+        call_soon_threadsafe(self.bind(py), context, args)
+    }
+
+    /// Create an `asyncio.Future` attached to the loop, like `loop.create_future`
+    pub fn create_future<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        create_future(self.bind(py).clone())
+    }
+
+    /// Schedule `coro` as a task on the loop, like `loop.create_task`
+    ///
+    /// The task runs in `context`, or in a copy of the current context if it's `None`. Loops that
+    /// don't accept a `context` argument yet, such as the ones of Python < 3.11, create the task
+    /// within `context` instead, which has the same effect.
+    ///
+    /// # Arguments
+    /// * `py` - The current PyO3 GIL guard
+    /// * `coro` - The coroutine to be run
+    /// * `context` - The `contextvars.Context` to run the task in, or `None`
+    pub fn create_task<'p>(
+        &self,
+        py: Python<'p>,
+        coro: &Bound<'p, PyAny>,
+        context: &Bound<'p, PyAny>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        create_task(&self.bind(py).getattr("create_task")?, coro, context)
+    }
+
+    /// Get the loop's current time, like `loop.time`
+    pub fn time(&self, py: Python) -> PyResult<f64> {
+        self.bind(py).call_method0("time")?.extract()
+    }
+
+    /// Check whether the loop is running, like `loop.is_running`
+    pub fn is_running(&self, py: Python) -> PyResult<bool> {
+        self.bind(py).call_method0("is_running")?.is_truthy()
+    }
+
+    /// Check whether the loop has been closed, like `loop.is_closed`
+    pub fn is_closed(&self, py: Python) -> PyResult<bool> {
+        self.bind(py).call_method0("is_closed")?.is_truthy()
+    }
+}
+
+impl From<Bound<'_, PyAny>> for PyEventLoop {
+    fn from(event_loop: Bound<PyAny>) -> Self {
+        Self::new(event_loop)
+    }
+}
+
+impl IntoPy<PyObject> for PyEventLoop {
+    fn into_py(self, _py: Python) -> PyObject {
+        self.0
+    }
+}
+
+impl ToPyObject for PyEventLoop {
+    fn to_object(&self, py: Python) -> PyObject {
+        self.0.clone_ref(py)
+    }
+}
+
+/// The methods of an event loop that are called for every conversion
+///
+/// Looking them up once, when the [`TaskLocals`](crate::TaskLocals) are created, saves a `getattr`
+/// and the allocation of a bound method per call. A method that the loop doesn't have is looked up
+/// again on every call, so that it fails or falls back like it would without the cache.
+#[derive(Debug)]
+pub(crate) struct LoopMethods {
+    call_soon_threadsafe: Option<PyObject>,
+    create_future: Option<PyObject>,
+    create_task: Option<PyObject>,
+}
+
+impl LoopMethods {
+    pub(crate) fn new(event_loop: &Bound<PyAny>) -> Self {
+        let method = |name| event_loop.getattr(name).ok().map(Bound::unbind);
+
+        Self {
+            call_soon_threadsafe: method("call_soon_threadsafe"),
+            create_future: method("create_future"),
+            create_task: method("create_task"),
+        }
+    }
+
+    pub(crate) fn clone_ref(&self, py: Python) -> Self {
+        let clone_ref = |method: &Option<PyObject>| method.as_ref().map(|m| m.clone_ref(py));
+
+        Self {
+            call_soon_threadsafe: clone_ref(&self.call_soon_threadsafe),
+            create_future: clone_ref(&self.create_future),
+            create_task: clone_ref(&self.create_task),
+        }
+    }
+
+    /// Like [`call_soon_threadsafe`], with the cached method of `event_loop`
+    pub(crate) fn call_soon_threadsafe(
+        &self,
+        event_loop: &Bound<PyAny>,
+        context: &Bound<PyAny>,
+        args: impl IntoPy<Py<PyTuple>>,
+    ) -> PyResult<()> {
+        match &self.call_soon_threadsafe {
+            Some(method) => call_soon_threadsafe_with(method.bind(event_loop.py()), context, args),
+            None => call_soon_threadsafe(event_loop, context, args),
+        }
+    }
+
+    /// Like [`create_future`], with the cached method of `event_loop`
+    pub(crate) fn create_future<'p>(
+        &self,
+        event_loop: &Bound<'p, PyAny>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        match &self.create_future {
+            Some(method) => method.bind(event_loop.py()).call0(),
+            None => create_future(event_loop.clone()),
+        }
+    }
+
+    /// The `create_task` method of `event_loop`
+    #[cfg_attr(not(feature = "unstable-streams"), allow(dead_code))]
+    pub(crate) fn create_task<'p>(
+        &self,
+        event_loop: &Bound<'p, PyAny>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        match &self.create_task {
+            Some(method) => Ok(method.bind(event_loop.py()).clone()),
+            None => event_loop.getattr("create_task"),
+        }
+    }
+}
+
+pub(crate) fn create_future(event_loop: Bound<PyAny>) -> PyResult<Bound<'_, PyAny>> {
+    // GUI-integrated loops don't always implement create_future
+    if event_loop.hasattr("create_future")? {
+        event_loop.call_method0("create_future")
+    } else {
+        let py = event_loop.py();
+        let kwargs = PyDict::new_bound(py);
+        kwargs.set_item("loop", event_loop)?;
+
+        asyncio(py)?.getattr("Future")?.call((), Some(&kwargs))
+    }
+}
+
+pub(crate) fn call_soon_threadsafe(
+    event_loop: &Bound<PyAny>,
+    context: &Bound<PyAny>,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> PyResult<()> {
+    call_soon_threadsafe_with(&event_loop.getattr("call_soon_threadsafe")?, context, args)
+}
+
+fn call_soon_threadsafe_with(
+    call_soon_threadsafe: &Bound<PyAny>,
+    context: &Bound<PyAny>,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> PyResult<()> {
+    let py = call_soon_threadsafe.py();
+    let args = args.into_py(py).into_bound(py);
+
+    let kwargs = PyDict::new_bound(py);
+    kwargs.set_item("context", context)?;
+
+    match call_soon_threadsafe.call(args.clone(), Some(&kwargs)) {
+        Ok(_) => Ok(()),
+        // Some GUI-integrated loops don't accept the context argument, so enter the context in the
+        // callback instead
+        Err(e) if e.is_instance_of::<PyTypeError>(py) => {
+            let args = if context.is_none() {
+                args
+            } else {
+                PyTuple::new_bound(
+                    py,
+                    std::iter::once(context.getattr("run")?)
+                        .chain(args.iter())
+                        .collect::<Vec<_>>(),
+                )
+            };
+
+            call_soon_threadsafe.call1(args)?;
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn create_task<'p>(
+    create_task: &Bound<'p, PyAny>,
+    coro: &Bound<'p, PyAny>,
+    context: &Bound<'p, PyAny>,
+) -> PyResult<Bound<'p, PyAny>> {
+    let py = create_task.py();
+    if context.is_none() {
+        return create_task.call1((coro,));
+    }
+
+    let kwargs = PyDict::new_bound(py);
+    kwargs.set_item("context", context)?;
+
+    match create_task.call((coro,), Some(&kwargs)) {
+        Err(e) if e.is_instance_of::<PyTypeError>(py) => {
+            context.call_method1("run", (create_task, coro))
+        }
+        result => result,
+    }
+}
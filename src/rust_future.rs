@@ -0,0 +1,377 @@
+//! A future that conversions resolve directly from Rust, instead of an `asyncio.Future`
+//!
+//! `asyncio.Task` doesn't need its awaitable to be an `asyncio.Future`: anything that yields
+//! itself with `_asyncio_future_blocking` set, and that has `get_loop`, `add_done_callback` and
+//! `result`, is waited for like a future. [`RustFuture`] implements that protocol in Rust, so a
+//! conversion doesn't create an `asyncio.Future` through Python, doesn't register a done callback
+//! on it to forward its cancellation, and its result is set without calling into Python. The
+//! awaiting task is woken up like an `asyncio.Future` does it, with a callback scheduled with
+//! `call_soon`, so it runs in the same order relative to the other ready callbacks of the loop.
+//!
+//! It also implements the rest of the interface of `asyncio.Future` (`done`, `cancel`,
+//! `exception`, ...), so `asyncio.isfuture` is true for it and `asyncio.gather`, `asyncio.wait`
+//! and friends accept it. Code that checks `isinstance(fut, asyncio.Future)` doesn't, see
+//! [`set_rust_futures`] to turn it off. It's only used when the awaiter is a plain `asyncio.Task`.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use futures::future::AbortHandle;
+use once_cell::sync::OnceCell;
+use pyo3::{
+    exceptions::PyStopIteration,
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
+
+use crate::{asyncio, copy_context, is_running_loop, TaskLocals};
+
+static RUST_FUTURES: AtomicBool = AtomicBool::new(true);
+static TASK: OnceCell<PyObject> = OnceCell::new();
+static CURRENT_TASK: OnceCell<PyObject> = OnceCell::new();
+
+/// Set whether conversions awaited by a plain `asyncio.Task` return a future implemented in Rust
+///
+/// This is enabled by default. When [`future_into_py`](crate::generic::future_into_py) and the
+/// functions built on it are called from a coroutine that runs in an `asyncio.Task` (and not a
+/// subclass of it), they return an object that implements the interface of `asyncio.Future` in
+/// Rust. Its result is set without calling into Python, which saves creating an `asyncio.Future`
+/// and registering a done callback on it per conversion. Conversions from anywhere else return an
+/// `asyncio.Future`.
+///
+/// The object passes `asyncio.isfuture`, so it can be handed to `asyncio.gather`, `asyncio.wait`,
+/// `asyncio.wait_for` and `loop.run_until_complete`, but it isn't an instance of `asyncio.Future`
+/// and isn't created by `loop.create_future`. Disable this if code that receives the conversions
+/// checks `isinstance(fut, asyncio.Future)`, or relies on the futures of a loop whose
+/// `create_future` returns futures of its own.
+///
+/// # Arguments
+/// * `enabled` - Whether conversions awaited by a plain task return a future implemented in Rust
+pub fn set_rust_futures(enabled: bool) {
+    RUST_FUTURES.store(enabled, Ordering::Relaxed);
+}
+
+/// Check whether a conversion with `locals` should return a [`RustFuture`]
+///
+/// That's the case if it's made from a coroutine that runs in a plain `asyncio.Task` on the loop
+/// of `locals`, which is almost always the one that awaits the result.
+pub(crate) fn awaited_by_plain_task(py: Python, locals: &TaskLocals) -> PyResult<bool> {
+    if !RUST_FUTURES.load(Ordering::Relaxed) {
+        return Ok(false);
+    }
+
+    // conversions from Rust threads aren't awaited by a task yet
+    let event_loop = locals.py_event_loop().bind(py);
+    if !is_running_loop(event_loop)? {
+        return Ok(false);
+    }
+
+    let current_task = CURRENT_TASK.get_or_try_init(|| -> PyResult<PyObject> {
+        Ok(asyncio(py)?.getattr("current_task")?.into())
+    })?;
+    let task_type =
+        TASK.get_or_try_init(|| -> PyResult<PyObject> { Ok(asyncio(py)?.getattr("Task")?.into()) })?;
+    let task = current_task.bind(py).call1((event_loop,))?;
+
+    Ok(task.get_type().is(task_type.bind(py)))
+}
+
+enum Outcome {
+    Pending,
+    Done(PyResult<PyObject>),
+    Cancelled(Option<PyObject>),
+}
+
+impl Outcome {
+    fn clone_ref(&self, py: Python) -> Self {
+        match self {
+            Outcome::Pending => Outcome::Pending,
+            Outcome::Done(Ok(val)) => Outcome::Done(Ok(val.clone_ref(py))),
+            Outcome::Done(Err(e)) => Outcome::Done(Err(e.clone_ref(py))),
+            Outcome::Cancelled(msg) => {
+                Outcome::Cancelled(msg.as_ref().map(|msg| msg.clone_ref(py)))
+            }
+        }
+    }
+}
+
+fn cancelled_error(py: Python, msg: Option<PyObject>) -> PyResult<PyErr> {
+    let cancelled_error = asyncio(py)?.getattr("CancelledError")?;
+    let err = match msg {
+        Some(msg) => cancelled_error.call1((msg,))?,
+        None => cancelled_error.call0()?,
+    };
+
+    Ok(PyErr::from_value_bound(err))
+}
+
+fn invalid_state_error(py: Python, message: &str) -> PyResult<PyErr> {
+    Ok(PyErr::from_value_bound(
+        asyncio(py)?
+            .getattr("InvalidStateError")?
+            .call1((message,))?,
+    ))
+}
+
+struct State {
+    outcome: Outcome,
+    /// The done callbacks with the `contextvars.Context` to call them in
+    callbacks: Vec<(PyObject, PyObject)>,
+    /// Aborts the Rust future
+    abort: Option<AbortHandle>,
+}
+
+/// A Python awaitable for a Rust future that implements the interface of `asyncio.Future`
+#[pyclass(frozen)]
+pub(crate) struct RustFuture {
+    locals: TaskLocals,
+    state: Mutex<State>,
+    blocking: AtomicBool,
+}
+
+impl RustFuture {
+    /// Create a pending future on the loop of `locals`, which aborts the Rust future with `abort`
+    /// once cancelled
+    pub(crate) fn new_bound(
+        py: Python,
+        locals: TaskLocals,
+        abort: AbortHandle,
+    ) -> PyResult<Bound<RustFuture>> {
+        Bound::new(
+            py,
+            RustFuture {
+                locals,
+                state: Mutex::new(State {
+                    outcome: Outcome::Pending,
+                    callbacks: Vec::new(),
+                    abort: Some(abort),
+                }),
+                blocking: AtomicBool::new(false),
+            },
+        )
+    }
+
+    /// Set the result of the future and schedule its callbacks
+    ///
+    /// The callbacks are scheduled with `call_soon` like `asyncio.Future` does, rather than called
+    /// from the drain that runs this, so that the awaiting task doesn't run ahead of the callbacks
+    /// that are ready already. The result is dropped if the future was cancelled.
+    pub(crate) fn complete(slf: &Bound<RustFuture>, result: PyResult<PyObject>) -> PyResult<()> {
+        let callbacks = {
+            let mut state = slf.get().state.lock().unwrap();
+            if !matches!(state.outcome, Outcome::Pending) {
+                return Ok(());
+            }
+
+            state.outcome = Outcome::Done(result);
+            state.abort = None;
+            std::mem::take(&mut state.callbacks)
+        };
+
+        let mut first_err = None;
+        for (callback, context) in callbacks {
+            // one callback that can't be scheduled doesn't keep the others from being scheduled
+            if let Err(e) = Self::schedule_callback(slf, callback, context) {
+                first_err.get_or_insert(e);
+            }
+        }
+
+        first_err.map_or(Ok(()), Err)
+    }
+
+    /// The outcome so far, read without keeping the state locked while Python runs
+    fn outcome(&self, py: Python) -> Outcome {
+        self.state.lock().unwrap().outcome.clone_ref(py)
+    }
+
+    /// Call `callback(self)` from the loop within `context`, like an `asyncio.Future` does
+    fn schedule_callback(
+        slf: &Bound<RustFuture>,
+        callback: PyObject,
+        context: PyObject,
+    ) -> PyResult<()> {
+        let py = slf.py();
+        let kwargs = PyDict::new_bound(py);
+        kwargs.set_item("context", context)?;
+
+        slf.get().locals.event_loop(py).call_method(
+            "call_soon",
+            PyTuple::new_bound(py, [callback, slf.clone().into_any().unbind()]),
+            Some(&kwargs),
+        )?;
+        Ok(())
+    }
+
+    fn poll(slf: &Bound<RustFuture>) -> PyResult<PyObject> {
+        let py = slf.py();
+
+        match slf.get().outcome(py) {
+            Outcome::Pending => {
+                // yielding the future itself tells the task to wait for its done callbacks
+                slf.get().blocking.store(true, Ordering::Relaxed);
+                Ok(slf.clone().into_any().unbind())
+            }
+            Outcome::Done(Ok(val)) => Err(PyStopIteration::new_err((val,))),
+            Outcome::Done(Err(e)) => Err(e),
+            Outcome::Cancelled(msg) => Err(cancelled_error(py, msg)?),
+        }
+    }
+}
+
+#[pymethods]
+impl RustFuture {
+    fn __await__(slf: Bound<Self>) -> Bound<Self> {
+        slf
+    }
+
+    fn __iter__(slf: Bound<Self>) -> Bound<Self> {
+        slf
+    }
+
+    fn __next__(slf: &Bound<Self>) -> PyResult<PyObject> {
+        Self::poll(slf)
+    }
+
+    fn send(slf: &Bound<Self>, _value: &Bound<PyAny>) -> PyResult<PyObject> {
+        Self::poll(slf)
+    }
+
+    #[pyo3(signature = (typ, val=None, _tb=None))]
+    fn throw(
+        &self,
+        typ: Bound<PyAny>,
+        val: Option<Bound<PyAny>>,
+        _tb: Option<Bound<PyAny>>,
+    ) -> PyResult<PyObject> {
+        // like the generator of `asyncio.Future.__await__`, the exception is raised where it
+        // yielded, which leaves the future alone
+        match val {
+            Some(val) if !val.is_none() => Err(PyErr::from_value_bound(val)),
+            _ => Err(PyErr::from_value_bound(typ)),
+        }
+    }
+
+    fn close(&self) {}
+
+    #[getter]
+    fn _asyncio_future_blocking(&self) -> bool {
+        self.blocking.load(Ordering::Relaxed)
+    }
+
+    #[setter(_asyncio_future_blocking)]
+    fn set_asyncio_future_blocking(&self, blocking: bool) {
+        self.blocking.store(blocking, Ordering::Relaxed);
+    }
+
+    fn get_loop<'p>(&self, py: Python<'p>) -> Bound<'p, PyAny> {
+        self.locals.event_loop(py)
+    }
+
+    fn done(&self) -> bool {
+        !matches!(self.state.lock().unwrap().outcome, Outcome::Pending)
+    }
+
+    fn cancelled(&self) -> bool {
+        matches!(self.state.lock().unwrap().outcome, Outcome::Cancelled(_))
+    }
+
+    fn result(&self, py: Python) -> PyResult<PyObject> {
+        match self.outcome(py) {
+            Outcome::Pending => Err(invalid_state_error(py, "Result is not ready.")?),
+            Outcome::Done(result) => result,
+            Outcome::Cancelled(msg) => Err(cancelled_error(py, msg)?),
+        }
+    }
+
+    fn exception(&self, py: Python) -> PyResult<PyObject> {
+        match self.outcome(py) {
+            Outcome::Pending => Err(invalid_state_error(py, "Exception is not set.")?),
+            Outcome::Done(Ok(_)) => Ok(py.None()),
+            Outcome::Done(Err(e)) => Ok(e.into_value(py).into_any()),
+            Outcome::Cancelled(msg) => Err(cancelled_error(py, msg)?),
+        }
+    }
+
+    /// Cancel the future and the Rust future behind it
+    #[pyo3(signature = (msg=None))]
+    fn cancel(slf: &Bound<Self>, msg: Option<PyObject>) -> PyResult<bool> {
+        let callbacks = {
+            let mut state = slf.get().state.lock().unwrap();
+            if !matches!(state.outcome, Outcome::Pending) {
+                return Ok(false);
+            }
+
+            state.outcome = Outcome::Cancelled(msg);
+            if let Some(abort) = state.abort.take() {
+                abort.abort();
+            }
+            std::mem::take(&mut state.callbacks)
+        };
+
+        for (callback, context) in callbacks {
+            Self::schedule_callback(slf, callback, context)?;
+        }
+
+        Ok(true)
+    }
+
+    #[pyo3(signature = (callback, *, context=None))]
+    fn add_done_callback(
+        slf: &Bound<Self>,
+        callback: PyObject,
+        context: Option<PyObject>,
+    ) -> PyResult<()> {
+        let py = slf.py();
+        let context = match context {
+            Some(context) => context,
+            None => copy_context(py)?.unbind(),
+        };
+
+        {
+            let mut state = slf.get().state.lock().unwrap();
+            if matches!(state.outcome, Outcome::Pending) {
+                state.callbacks.push((callback, context));
+                return Ok(());
+            }
+        }
+
+        Self::schedule_callback(slf, callback, context)
+    }
+
+    fn remove_done_callback(&self, callback: &Bound<PyAny>) -> PyResult<usize> {
+        let py = callback.py();
+        let callbacks = self
+            .state
+            .lock()
+            .unwrap()
+            .callbacks
+            .iter()
+            .map(|(cb, _)| cb.clone_ref(py))
+            .collect::<Vec<_>>();
+
+        // the comparison may run Python code, so the state isn't locked in the meantime
+        let mut removed = Vec::new();
+        for cb in &callbacks {
+            if cb.bind(py).eq(callback)? {
+                removed.push(cb.as_ptr());
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let before = state.callbacks.len();
+        state
+            .callbacks
+            .retain(|(cb, _)| !removed.contains(&cb.as_ptr()));
+        Ok(before - state.callbacks.len())
+    }
+
+    fn __repr__(&self) -> &'static str {
+        match self.state.lock().unwrap().outcome {
+            Outcome::Pending => "<RustFuture pending>",
+            Outcome::Done(_) => "<RustFuture finished>",
+            Outcome::Cancelled(_) => "<RustFuture cancelled>",
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Poll-scoped task locals for runtimes that don't provide task-local storage of their own
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+
+use crate::TaskLocals;
+
+thread_local! {
+    static TASK_LOCALS: RefCell<Option<TaskLocals>> = const { RefCell::new(None) };
+}
+
+pin_project! {
+    /// The locals are swapped into a thread-local for the duration of each poll of the inner
+    /// future, so they are visible to anything that runs synchronously within that poll.
+    pub struct Scoped<F> {
+        locals: Option<TaskLocals>,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F> Scoped<F> {
+    /// Scope `future` to the given task locals
+    pub fn new(locals: TaskLocals, future: F) -> Self {
+        Self {
+            locals: Some(locals),
+            future,
+        }
+    }
+}
+
+impl<F> Future for Scoped<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        struct Guard<'a>(&'a mut Option<TaskLocals>);
+
+        impl Drop for Guard<'_> {
+            fn drop(&mut self) {
+                TASK_LOCALS.with(|c| std::mem::swap(self.0, &mut *c.borrow_mut()));
+            }
+        }
+
+        TASK_LOCALS.with(|c| std::mem::swap(this.locals, &mut *c.borrow_mut()));
+        let _guard = Guard(this.locals);
+
+        this.future.poll(cx)
+    }
+}
+
+/// Get the locals of the [`Scoped`] future that is currently being polled on this thread
+pub fn get_task_locals() -> Option<TaskLocals> {
+    TASK_LOCALS
+        .try_with(|c| c.borrow().as_ref().cloned())
+        .unwrap_or_default()
+}
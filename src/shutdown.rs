@@ -0,0 +1,382 @@
+//! Coordinated shutdown of the conversions between Rust and Python
+//!
+//! Every conversion that's in flight is registered here with its event loop until it's done, so
+//! that [`shutdown`] can wait for the conversions of a loop, and cancel them if they take too long.
+//! The registry also lets a `KeyboardInterrupt` cancel all of them at once, see
+//! [`cancel_on_interrupt`].
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use futures::future::{AbortHandle, AbortRegistration};
+use once_cell::sync::Lazy;
+use pyo3::{
+    exceptions::{PyKeyboardInterrupt, PyRuntimeError},
+    prelude::*,
+    types::PyWeakrefReference,
+};
+
+use crate::{asyncio, call_soon_threadsafe, completions, err::ShuttingDown, PyCancelTask};
+
+/// How long the event loop runs at a time while waiting for the conversions
+pub(crate) const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The event loops that [`shutdown`] was called for
+static SHUT_DOWN: Lazy<Mutex<Vec<LoopRef>>> = Lazy::new(Default::default);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static IN_FLIGHT: Lazy<Mutex<HashMap<u64, Conversion>>> = Lazy::new(Default::default);
+
+static CANCEL_ON_INTERRUPT: AtomicBool = AtomicBool::new(false);
+static ON_INTERRUPT: Lazy<Mutex<Option<InterruptHook>>> = Lazy::new(Default::default);
+
+/// Called once after a `KeyboardInterrupt` cancelled the conversions in flight
+type InterruptHook = Box<dyn FnOnce() + Send>;
+
+/// The Python side of a conversion
+pub(crate) enum Target {
+    /// An `asyncio.Future` that's resolved by a Rust future
+    Future(PyObject),
+    /// The slot of a Python task that a Rust future waits for
+    Task(Arc<Mutex<Option<PyObject>>>),
+}
+
+struct Conversion {
+    event_loop: PyObject,
+    target: Target,
+    /// Aborts the Rust side of the conversion
+    abort: AbortHandle,
+}
+
+/// An event loop in [`SHUT_DOWN`]
+enum LoopRef {
+    /// A weak reference, so that shutting a loop down doesn't keep it alive
+    Weak(Py<PyWeakrefReference>),
+    /// The loop itself, for loops that don't support weak references
+    Strong(PyObject),
+}
+
+impl LoopRef {
+    fn new(event_loop: &Bound<PyAny>) -> Self {
+        match PyWeakrefReference::new_bound(event_loop) {
+            Ok(loop_ref) => Self::Weak(loop_ref.unbind()),
+            Err(_) => Self::Strong(event_loop.clone().unbind()),
+        }
+    }
+
+    /// Get the loop, unless it was collected
+    fn get<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyAny>> {
+        match self {
+            Self::Weak(loop_ref) => loop_ref.bind(py).upgrade(),
+            Self::Strong(event_loop) => Some(event_loop.bind(py).clone()),
+        }
+    }
+}
+
+/// Keeps a conversion registered until it's dropped
+pub(crate) struct Registration {
+    id: u64,
+    abort: Option<AbortRegistration>,
+}
+
+impl Registration {
+    /// Take the registration for the `Abortable` that wraps the Rust side of the conversion
+    ///
+    /// # Panics
+    /// If it has been taken already.
+    pub(crate) fn abort_registration(&mut self) -> AbortRegistration {
+        self.abort
+            .take()
+            .expect("abort registration has been taken already")
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        // drop the Python objects after the lock is released
+        let conversion = IN_FLIGHT.lock().unwrap().remove(&self.id);
+        drop(conversion);
+    }
+}
+
+/// Fail with [`ShuttingDown`] if [`shutdown`] has been called for `event_loop`
+pub(crate) fn ensure_running(event_loop: &Bound<PyAny>) -> PyResult<()> {
+    if is_shutting_down(event_loop) {
+        Err(ShuttingDown::new_err(
+            "the event loop is shutting down, no new conversions are accepted",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Register a conversion whose Python side runs on `event_loop`
+pub(crate) fn register(event_loop: &Bound<PyAny>, target: Target) -> PyResult<Registration> {
+    register_abortable(event_loop, target, AbortHandle::new_pair())
+}
+
+/// Register a conversion like [`register`], with an abort handle that the conversion hands to its
+/// Python side as well
+pub(crate) fn register_abortable(
+    event_loop: &Bound<PyAny>,
+    target: Target,
+    (abort, abort_registration): (AbortHandle, AbortRegistration),
+) -> PyResult<Registration> {
+    ensure_running(event_loop)?;
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    IN_FLIGHT.lock().unwrap().insert(
+        id,
+        Conversion {
+            event_loop: event_loop.clone().unbind(),
+            target,
+            abort,
+        },
+    );
+
+    Ok(Registration {
+        id,
+        abort: Some(abort_registration),
+    })
+}
+
+/// Get the number of conversions that are in flight
+pub(crate) fn in_flight() -> usize {
+    IN_FLIGHT.lock().unwrap().len()
+}
+
+/// Get the number of conversions whose Python side runs on `event_loop` that are in flight
+fn in_flight_on(event_loop: &Bound<PyAny>) -> usize {
+    IN_FLIGHT
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|conversion| conversion.event_loop.bind(event_loop.py()).is(event_loop))
+        .count()
+}
+
+/// Check whether [`shutdown`] has been called for `event_loop`
+pub fn is_shutting_down(event_loop: &Bound<PyAny>) -> bool {
+    let py = event_loop.py();
+    SHUT_DOWN
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|loop_ref| matches!(loop_ref.get(py), Some(shut_down) if shut_down.is(event_loop)))
+}
+
+/// Shut down the conversions between Rust and `event_loop` in an orderly fashion
+///
+/// Call this on the thread of `event_loop` once the loop has stopped, e.g. after
+/// [`tokio::run`](crate::tokio::run) returned, and before the loop is closed. It goes through the
+/// following steps:
+///
+/// 1. New conversions on `event_loop` fail with [`ShuttingDown`] from now on. This can't be
+///    undone, the loop is meant to be closed afterwards.
+/// 2. The conversions of `event_loop` that are in flight get up to `timeout` to finish. The event
+///    loop runs in the meantime, so that Python tasks can make progress and the results of Rust
+///    futures are delivered.
+/// 3. The conversions of `event_loop` that are still in flight are cancelled: Python tasks are
+///    cancelled with `asyncio.Task.cancel`, and the `asyncio.Future`s of Rust futures are
+///    cancelled, which drops the Rust futures on their runtime. The event loop runs for up to
+///    another `timeout` so that they can unwind. If the loop is closed already, the Rust futures
+///    are dropped right away.
+/// 4. `loop.shutdown_asyncgens()` finalizes the asynchronous generators of the loop.
+///
+/// Afterwards, no Rust future spawned by a conversion of `event_loop` is left running on the Rust
+/// runtime, and nothing is left that would call into the event loop after it's closed. The
+/// conversions of other event loops are left alone, so each loop is shut down on its own. The Rust
+/// runtime isn't shut down by this function, since other loops may still use it, see
+/// [`tokio::shutdown`](crate::tokio::shutdown) for the tokio runtime.
+///
+/// If `event_loop` is closed already, the last step is skipped, and Python tasks can't make
+/// progress anymore.
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that was used for the conversions
+/// * `timeout` - How long to wait for the conversions in flight
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio-runtime")]
+/// fn main() -> pyo3::PyResult<()> {
+///     use std::time::Duration;
+///
+///     use pyo3::prelude::*;
+///
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+///
+///         let result = pyo3_async_runtimes::tokio::run_until_complete(event_loop.clone(), async {
+///             // ...
+///             Ok(())
+///         });
+///
+///         pyo3_async_runtimes::shutdown(&event_loop, Duration::from_secs(5))?;
+///         event_loop.call_method0("close")?;
+///
+///         result
+///     })
+/// }
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {}
+/// ```
+pub fn shutdown(event_loop: &Bound<PyAny>, timeout: Duration) -> PyResult<()> {
+    let py = event_loop.py();
+
+    if event_loop.call_method0("is_running")?.is_truthy()? {
+        return Err(PyRuntimeError::new_err(
+            "cannot shut down while the event loop is running",
+        ));
+    }
+
+    if !is_shutting_down(event_loop) {
+        let loop_ref = LoopRef::new(event_loop);
+        let mut shut_down = SHUT_DOWN.lock().unwrap();
+        // forget the loops that are gone while we're at it
+        shut_down.retain(|loop_ref| loop_ref.get(py).is_some());
+        shut_down.push(loop_ref);
+    }
+
+    let closed = event_loop.call_method0("is_closed")?.is_truthy()?;
+
+    drain(event_loop, closed, Instant::now() + timeout)?;
+    cancel_in_flight(py, Some(event_loop), closed);
+    drain(event_loop, closed, Instant::now() + timeout)?;
+
+    if !closed {
+        // the errors of the conversions that their runtime dropped, whose drain may still wait for
+        // the GIL
+        completions::flush(event_loop)?;
+    }
+
+    if !closed && event_loop.hasattr("shutdown_asyncgens")? {
+        event_loop.call_method1(
+            "run_until_complete",
+            (event_loop.call_method0("shutdown_asyncgens")?,),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Wait until no conversion of `event_loop` is in flight anymore, or until `deadline`
+fn drain(event_loop: &Bound<PyAny>, closed: bool, deadline: Instant) -> PyResult<()> {
+    let py = event_loop.py();
+
+    while in_flight_on(event_loop) > 0 {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+
+        let interval = DRAIN_INTERVAL.min(deadline - now);
+        if closed {
+            // the conversions can only finish on other threads
+            py.allow_threads(|| thread::sleep(interval));
+        } else {
+            event_loop.call_method1(
+                "run_until_complete",
+                (asyncio(py)?.call_method1("sleep", (interval.as_secs_f64(),))?,),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Cancel the Python side of every conversion that's in flight, or only of those of `event_loop`
+///
+/// If `abort` is set, the Rust side is aborted as well, instead of waiting for the event loop to
+/// pass the cancellation on.
+fn cancel_in_flight(py: Python, event_loop: Option<&Bound<PyAny>>, abort: bool) {
+    let conversions = IN_FLIGHT
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|conversion| match event_loop {
+            Some(event_loop) => conversion.event_loop.bind(py).is(event_loop),
+            None => true,
+        })
+        .map(|conversion| {
+            if abort {
+                conversion.abort.abort();
+            }
+
+            let target = match &conversion.target {
+                Target::Future(fut) => Target::Future(fut.clone_ref(py)),
+                Target::Task(task) => Target::Task(task.clone()),
+            };
+
+            (conversion.event_loop.clone_ref(py), target)
+        })
+        .collect::<Vec<_>>();
+
+    for (event_loop, target) in conversions {
+        let event_loop = event_loop.bind(py);
+        let context = py.None().into_bound(py);
+
+        // the event loop may be closed already, in which case there's nothing left to cancel
+        let _ = match target {
+            Target::Future(fut) => fut
+                .bind(py)
+                .getattr("cancel")
+                .and_then(|cancel| call_soon_threadsafe(event_loop, &context, (cancel,))),
+            Target::Task(task) => {
+                call_soon_threadsafe(event_loop, &context, (PyCancelTask { task },))
+            }
+        };
+    }
+}
+
+/// Cancel every conversion that's in flight when a `KeyboardInterrupt` stops the event loop
+///
+/// This is disabled by default, so that Rust futures keep running after CTRL-C, e.g. to retry
+/// [`tokio::run`](crate::tokio::run) afterwards. Once it's enabled, a `KeyboardInterrupt` that
+/// escapes [`run_forever`](crate::run_forever) or the `run*` functions of a runtime cancels all
+/// conversions between Rust and Python:
+///
+/// - Rust futures that were converted into Python awaitables are dropped on their runtime, and
+///   their `asyncio.Future`s are cancelled.
+/// - Rust futures that wait for Python awaitables resolve to `asyncio.CancelledError`, and the
+///   Python tasks are cancelled.
+///
+/// The future registered with [`generic::on_interrupt`](crate::generic::on_interrupt) is spawned
+/// afterwards. Unlike [`shutdown`], conversions are still accepted after an interrupt.
+///
+/// # Arguments
+/// * `enabled` - Whether a `KeyboardInterrupt` cancels the conversions in flight
+pub fn cancel_on_interrupt(enabled: bool) {
+    CANCEL_ON_INTERRUPT.store(enabled, Ordering::SeqCst);
+}
+
+/// Register `f` to be called once, after a `KeyboardInterrupt` cancelled the conversions
+pub(crate) fn set_on_interrupt(f: InterruptHook) {
+    *ON_INTERRUPT.lock().unwrap() = Some(f);
+}
+
+/// Cancel the conversions in flight if `err` is a `KeyboardInterrupt` and
+/// [`cancel_on_interrupt`] is enabled
+pub(crate) fn interrupted(py: Python, err: &PyErr) {
+    if !CANCEL_ON_INTERRUPT.load(Ordering::SeqCst) || !err.is_instance_of::<PyKeyboardInterrupt>(py)
+    {
+        return;
+    }
+
+    // the event loop is stopped, so it may never pass the cancellation on
+    cancel_in_flight(py, None, true);
+
+    let on_interrupt = ON_INTERRUPT.lock().unwrap().take();
+    if let Some(on_interrupt) = on_interrupt {
+        on_interrupt();
+    }
+}
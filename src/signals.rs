@@ -0,0 +1,89 @@
+//! Wakes up event loops on the main thread so that they raise pending signals right away
+//!
+//! Python only runs signal handlers on the main thread, between bytecode instructions. An event
+//! loop that is blocked in its selector (or in `GetQueuedCompletionStatus` on the Proactor loop)
+//! doesn't execute any bytecode, so a CTRL-C that isn't delivered to the main thread itself goes
+//! unnoticed until the next callback happens to run. That is always the case for the Proactor
+//! loop, and it's common on Unix when the Rust runtime has threads of its own that the signal can
+//! be delivered to.
+//!
+//! [`SignalWakeup`] works around this on every platform by scheduling a callback on the loop at a
+//! fixed interval. The callback checks for pending signals, so a `KeyboardInterrupt` escapes
+//! `run_forever` / `run_until_complete` at most one interval after CTRL-C was pressed.
+
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+
+/// How often the loop is woken up to check for signals
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Raises pending signals when the event loop runs it
+#[pyclass]
+struct CheckSignals;
+
+#[pymethods]
+impl CheckSignals {
+    fn __call__(&self, py: Python) -> PyResult<()> {
+        // asyncio handles propagate KeyboardInterrupt and SystemExit out of the loop
+        py.check_signals()
+    }
+}
+
+/// Wakes up the event loop periodically until dropped
+///
+/// This does nothing unless it's started on the main thread, since that's the only thread that
+/// Python runs signal handlers on.
+pub(crate) struct SignalWakeup {
+    _stop: Option<mpsc::Sender<()>>,
+}
+
+impl SignalWakeup {
+    /// Start waking up `event_loop`, which is about to run on the current thread
+    pub(crate) fn start(event_loop: &Bound<PyAny>) -> PyResult<Self> {
+        let py = event_loop.py();
+        let threading = py.import_bound("threading")?;
+
+        if !threading
+            .call_method0("current_thread")?
+            .is(&threading.call_method0("main_thread")?)
+        {
+            return Ok(Self { _stop: None });
+        }
+
+        let (stop, stopped) = mpsc::channel::<()>();
+        let event_loop = event_loop.clone().unbind();
+
+        thread::Builder::new()
+            .name("pyo3-async-runtimes-signals".into())
+            .spawn(move || loop {
+                // the sender is dropped once the loop is done running
+                if !matches!(
+                    stopped.recv_timeout(POLL_INTERVAL),
+                    Err(RecvTimeoutError::Timeout)
+                ) {
+                    break;
+                }
+
+                let woken = Python::with_gil(|py| {
+                    // the loop may have finished while this thread waited for the GIL
+                    matches!(stopped.try_recv(), Err(TryRecvError::Empty))
+                        && event_loop
+                            .bind(py)
+                            .call_method1("call_soon_threadsafe", (CheckSignals,))
+                            .is_ok()
+                });
+
+                if !woken {
+                    break;
+                }
+            })
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+
+        Ok(Self { _stop: Some(stop) })
+    }
+}
@@ -137,6 +137,76 @@
 //! # fn main() {}
 //! ```
 //!
+//! ## Running the Tests
+//!
+//! The test harness accepts the arguments of the default test harness that make sense for it (see
+//! [`Args`]), so the tests are filtered and run the way you're used to:
+//!
+//! ```bash
+//! $ cargo test --test test_example -- test_sleep --skip blocking --test-threads 1 --nocapture
+//! ```
+//!
+//! Each test prints the time it took, and the tracebacks of the failed tests are printed after the
+//! tests have finished, unless `--nocapture` is passed. The Python output of each test, i.e. what it
+//! writes to `sys.stdout` and `sys.stderr` and the records of the `logging` module, is captured
+//! the same way and printed along with its traceback. Only the Python code that the test runs on
+//! the event loop or calls from its own task is captured, so the output of the threads it spawns,
+//! such as the thread of a blocking test, is printed as usual.
+//!
+//! To run code around the tests, e.g. to import a Python module that many tests use once, pass
+//! [`Hooks`] to [`main_with_hooks`] instead of calling [`main`].
+//!
+//! Tests that change the global state of Python, such as the installed event loop policy or a
+//! singleton of a module, can opt out of running concurrently with the other tests with the
+//! `serial` argument of the test attributes, e.g. `#[pyo3_async_runtimes::tokio::test(serial)]`.
+//! The harness runs them one at a time, after the other tests. Tests with an event loop policy get
+//! a new event loop each, unless they pass `shared_loop`, in which case all of them that have the
+//! same policy run on one loop that outlives them, so state that is bound to the loop carries over
+//! from one test to the next.
+//!
+//! ## Running the Tests on uvloop
+//!
+//! The `main` attributes create the event loop through the runtime's `run` function, which installs
+//! [uvloop](crate::install_uvloop) first when the `PYO3_ASYNC_RUNTIMES_UVLOOP` environment variable
+//! is set to `1`. This lets you run the same test suite on both event loops without touching the
+//! test harness:
+//!
+//! ```bash
+//! $ PYO3_ASYNC_RUNTIMES_UVLOOP=1 cargo test
+//! ```
+//!
+//! If uvloop is not installed, the test program fails with an `ImportError` rather than silently
+//! falling back to the default event loop. Use [`crate::is_uvloop`] in a test if it depends on the
+//! event loop implementation.
+//!
+//! To always run a suite on uvloop, pass `event_loop_policy = "uvloop"` to the `main` attribute
+//! instead. A single async test can also get an event loop of its own with the `event_loop_policy`
+//! argument of the `test` attributes, e.g.
+//! `#[pyo3_async_runtimes::tokio::test(event_loop_policy = "uvloop")]`, while the other tests keep
+//! running on the event loop of the harness.
+//!
+//! With the `loop` alias of `event_loop_policy`, a single test binary can exercise the bridge on
+//! several event loop implementations, e.g. the `"selector"` and `"proactor"` event loops of asyncio
+//! on Windows:
+//!
+//! ```
+//! # #[cfg(all(feature = "tokio-runtime", feature = "attributes", windows))]
+//! mod tests {
+//!     use pyo3::prelude::*;
+//!
+//!     #[pyo3_async_runtimes::testing::test(runtime = "tokio", loop = "selector")]
+//!     async fn test_selector() -> PyResult<()> {
+//!         Ok(())
+//!     }
+//!
+//!     #[pyo3_async_runtimes::testing::test(runtime = "tokio", loop = "proactor")]
+//!     async fn test_proactor() -> PyResult<()> {
+//!         Ok(())
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+//!
 //! ## Lib Tests
 //!
 //! Unfortunately, as we mentioned at the beginning, these utilities will only run in integration
@@ -179,30 +249,122 @@
 //! # fn main() {}
 //! ```
 
-use std::{future::Future, pin::Pin};
+mod capture;
+mod report;
+
+use std::{
+    env,
+    ffi::OsString,
+    future::Future,
+    ops::Deref,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::Mutex,
+    thread,
+    time::Instant,
+};
+
+use clap::{Arg, ArgAction, Command};
+use futures::{
+    channel::oneshot,
+    stream::{self, StreamExt},
+    FutureExt,
+};
+use pyo3::{
+    exceptions::{PyAssertionError, PyBaseException, PyLookupError, PySystemExit, PyTypeError},
+    prelude::*,
+    types::PyType,
+};
+
+use self::{
+    capture::Capture,
+    report::{Failure, Format, Report, TestResult},
+};
+use crate::{
+    err::RustPanic,
+    generic::{get_panic_message, ContextExt},
+    PyEventLoop, TaskLocals,
+};
 
-use clap::{Arg, Command};
-use futures::stream::{self, StreamExt};
-use pyo3::prelude::*;
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
+/// Registers a test for the runtime selected by the enabled Cargo features with the test harness
+///
+/// This is [`crate::test`], e.g. `#[pyo3_async_runtimes::testing::test(loop = "uvloop")]` runs an
+/// async test on a uvloop event loop of its own with the tokio or async-std runtime.
+#[cfg(feature = "attributes")]
+pub use pyo3_async_runtimes_macros::runtime_test as test;
 
 /// Args that should be provided to the test program
 ///
-/// These args are meant to mirror the default test harness's args.
-/// > Currently only `--filter` is supported.
+/// These args are meant to mirror the default test harness's args, so that `cargo test` works the
+/// same way it does for regular Rust tests:
+///
+/// - `[FILTERS]...` - only run the tests whose names contain one of the filters
+/// - `--skip <FILTER>` - don't run the tests whose names contain the filter, can be repeated
+/// - `--exact` - match the filters against the full test names instead of substrings
+/// - `--test-threads <N>` - the number of tests that run concurrently, defaults to the
+///   `RUST_TEST_THREADS` environment variable or the available parallelism
+/// - `--nocapture` - print the errors of failing tests as they fail instead of collecting them into
+///   the summary, also enabled by setting `RUST_TEST_NOCAPTURE`
+/// - `--list` - list the tests instead of running them
+/// - `--format <pretty|terse|json|junit>` - the output format, `json` prints an object per line
+///   for each event and `junit` prints a JUnit XML report once the tests are done, both with the
+///   tracebacks of the failed tests
+/// - `-q`, `--quiet` - print one character per test instead of a line, like `--format terse`
+///
+/// Unless `--nocapture` is passed, what the tests write to Python's `sys.stdout` and `sys.stderr`
+/// and log with Python's `logging` is captured, and printed along with the traceback if the test
+/// fails. The harness can't capture what Rust prints since that requires an unstable API of the
+/// standard library, so it's always printed as the tests run.
 #[derive(Default)]
 pub struct Args {
-    filter: Option<String>,
+    filters: Vec<String>,
+    skip: Vec<String>,
+    exact: bool,
+    nocapture: bool,
+    test_threads: Option<usize>,
+    list: bool,
+    format: Option<Format>,
+}
+
+impl Args {
+    /// Whether a test with the given name is selected by the filters and not skipped
+    fn is_selected(&self, name: &str) -> bool {
+        let matches = |filter: &String| {
+            if self.exact {
+                name == filter
+            } else {
+                name.contains(filter.as_str())
+            }
+        };
+
+        (self.filters.is_empty() || self.filters.iter().any(matches))
+            && !self.skip.iter().any(matches)
+    }
+
+    /// The output format
+    fn format(&self) -> Format {
+        self.format.unwrap_or(Format::Pretty)
+    }
+
+    /// The number of tests that run concurrently
+    fn test_threads(&self) -> usize {
+        self.test_threads
+            .or_else(|| {
+                env::var("RUST_TEST_THREADS")
+                    .ok()
+                    .and_then(|threads| threads.parse().ok())
+            })
+            .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1)
+            .max(1)
+    }
 }
 
 /// Parse the test args from the command line
 ///
 /// This should be called at the start of your test harness to give the CLI some
-/// control over how our tests are run.
-///
-/// Ideally, we should mirror the default test harness's arguments exactly, but
-/// for the sake of simplicity, only filtering is supported for now. If you want
-/// more features, feel free to request them
-/// [here](https://github.com/awestlake87/pyo3-asyncio/issues).
+/// control over how our tests are run. See [`Args`] for the supported arguments.
 ///
 /// # Examples
 ///
@@ -215,27 +377,115 @@ pub struct Args {
 /// Produces the following usage string:
 ///
 /// ```bash
-/// Pyo3 Asyncio Test Suite
-/// USAGE:
-/// test_example [TESTNAME]
+/// Usage: test_example [OPTIONS] [FILTERS]...
 ///
-/// FLAGS:
-/// -h, --help       Prints help information
-/// -V, --version    Prints version information
+/// Arguments:
+///   [FILTERS]...  If specified, only run tests containing one of these strings in their names
 ///
-/// ARGS:
-/// <TESTNAME>    If specified, only run tests containing this string in their names
+/// Options:
+///       --skip <FILTER>       Skip tests whose names contain FILTER (this flag can be used multiple times)
+///       --exact               Exactly match filters rather than by substring
+///       --test-threads <N>    Number of tests to run concurrently
+///       --nocapture           Print the errors of failing tests as they fail
+///       --list                List all tests
+///       --format <FORMAT>     Configure the formatting of output [possible values: pretty, terse, json, junit]
+///   -q, --quiet               Display one character per test instead of one line
+///   -h, --help                Print help
 /// ```
 pub fn parse_args() -> Args {
+    parse_args_from(env::args_os())
+}
+
+/// Parse the test args from an iterator, where the first item is the name of the program
+///
+/// This is [`parse_args`] for test programs that build their arguments themselves.
+///
+/// # Examples
+///
+/// ```
+/// # use pyo3_async_runtimes::testing::parse_args_from;
+/// let args = parse_args_from(["test_example", "--exact", "tests::test_sleep"]);
+/// ```
+pub fn parse_args_from<I, T>(args: I) -> Args
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
     let matches = Command::new("PyO3 Asyncio Test Suite")
         .arg(
-            Arg::new("TESTNAME")
-                .help("If specified, only run tests containing this string in their names"),
+            Arg::new("FILTERS").num_args(0..).help(
+                "If specified, only run tests containing one of these strings in their names",
+            ),
         )
-        .get_matches();
+        .arg(
+            Arg::new("skip")
+                .long("skip")
+                .value_name("FILTER")
+                .action(ArgAction::Append)
+                .help(
+                    "Skip tests whose names contain FILTER (this flag can be used multiple times)",
+                ),
+        )
+        .arg(
+            Arg::new("exact")
+                .long("exact")
+                .action(ArgAction::SetTrue)
+                .help("Exactly match filters rather than by substring"),
+        )
+        .arg(
+            Arg::new("test-threads")
+                .long("test-threads")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Number of tests to run concurrently"),
+        )
+        .arg(
+            Arg::new("nocapture")
+                .long("nocapture")
+                .action(ArgAction::SetTrue)
+                .help("Print the errors of failing tests as they fail"),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .action(ArgAction::SetTrue)
+                .help("List all tests"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(Format::NAMES)
+                .help("Configure the formatting of output"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .action(ArgAction::SetTrue)
+                .help("Display one character per test instead of one line"),
+        )
+        .get_matches_from(args);
+
+    let values = |id: &str| {
+        matches
+            .get_many::<String>(id)
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default()
+    };
 
     Args {
-        filter: matches.get_one::<String>("TESTNAME").cloned(),
+        filters: values("FILTERS"),
+        skip: values("skip"),
+        exact: matches.get_flag("exact"),
+        nocapture: matches.get_flag("nocapture")
+            || env::var_os("RUST_TEST_NOCAPTURE").map_or(false, |value| value != "0"),
+        test_threads: matches.get_one::<usize>("test-threads").copied(),
+        list: matches.get_flag("list"),
+        format: match matches.get_one::<String>("format") {
+            Some(format) => Format::from_name(format),
+            None if matches.get_flag("quiet") => Some(Format::Terse),
+            None => None,
+        },
     }
 }
 
@@ -248,6 +498,11 @@ pub struct Test {
     pub name: &'static str,
     /// The function used to create the task that runs the test.
     pub test_fn: &'static TestFn,
+    /// Whether the test runs on its own, after the tests that run concurrently
+    pub serial: bool,
+    /// Whether the test shares its event loop with the other tests that share one, instead of
+    /// running on a new event loop
+    pub shared_loop: bool,
 }
 
 impl Test {
@@ -261,29 +516,799 @@ impl Test {
 
 inventory::collect!(Test);
 
+/// A value that the test attributes set up for a parameter of a test
+///
+/// A test can take any number of parameters whose types implement `Fixture`. Before the test runs,
+/// each of them is set up in the task locals of the test, and it is dropped along with the test,
+/// so cleanup goes into a `Drop` implementation. Implement it for your own types to share their
+/// setup between tests:
+///
+/// ```
+/// # #[cfg(all(feature = "tokio-runtime", feature = "attributes"))]
+/// # mod tests {
+/// use std::{future::Future, pin::Pin};
+///
+/// use pyo3::prelude::*;
+/// use pyo3_async_runtimes::testing::Fixture;
+///
+/// /// The `json` module
+/// struct Json(Py<PyModule>);
+///
+/// impl Fixture for Json {
+///     fn setup() -> Pin<Box<dyn Future<Output = PyResult<Self>> + Send>> {
+///         Box::pin(async {
+///             Python::with_gil(|py| Ok(Json(py.import_bound("json")?.unbind())))
+///         })
+///     }
+/// }
+///
+/// #[pyo3_async_runtimes::tokio::test]
+/// async fn test_dumps(json: Json) -> PyResult<()> {
+///     Python::with_gil(|py| {
+///         let dumped = json.0.bind(py).call_method1("dumps", ([1, 2],))?;
+///         assert_eq!(dumped.extract::<String>()?, "[1, 2]");
+///         Ok(())
+///     })
+/// }
+/// # }
+/// ```
+///
+/// For backwards compatibility, a parameter of type `PyObject` is the current event loop rather
+/// than a fixture.
+pub trait Fixture: Sized + Send + 'static {
+    /// Set up the fixture for a test
+    fn setup() -> Pin<Box<dyn Future<Output = PyResult<Self>> + Send>>;
+}
+
+/// The task locals of the test
+impl Fixture for TaskLocals {
+    fn setup() -> Pin<Box<dyn Future<Output = PyResult<Self>> + Send>> {
+        Box::pin(async { Python::with_gil(harness_locals) })
+    }
+}
+
+/// The event loop of the test
+impl Fixture for PyEventLoop {
+    fn setup() -> Pin<Box<dyn Future<Output = PyResult<Self>> + Send>> {
+        Box::pin(async {
+            Python::with_gil(|py| Ok(harness_locals(py)?.py_event_loop().clone_ref(py)))
+        })
+    }
+}
+
+/// A new event loop for a test, which is closed when the test is done
+///
+/// The loop isn't running, so a blocking test can run coroutines on it with
+/// `loop.run_until_complete`, without sharing the event loop of the harness with the other tests.
+#[derive(Debug)]
+pub struct TempEventLoop(PyEventLoop);
+
+impl Fixture for TempEventLoop {
+    fn setup() -> Pin<Box<dyn Future<Output = PyResult<Self>> + Send>> {
+        Box::pin(async {
+            Python::with_gil(|py| {
+                let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+                Ok(TempEventLoop(PyEventLoop::new(event_loop)))
+            })
+        })
+    }
+}
+
+impl Deref for TempEventLoop {
+    type Target = PyEventLoop;
+
+    fn deref(&self) -> &PyEventLoop {
+        &self.0
+    }
+}
+
+impl Drop for TempEventLoop {
+    fn drop(&mut self) {
+        Python::with_gil(|py| {
+            if let Err(e) = self.0.bind(py).call_method0("close") {
+                e.print_and_set_sys_last_vars(py);
+            }
+        });
+    }
+}
+
+/// Look up the exception type `name`, either a builtin such as `ValueError` or the dotted path of a
+/// module attribute such as `asyncio.CancelledError`
+fn exception_type<'py>(py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyType>> {
+    let (module, attr) = name.rsplit_once('.').unwrap_or(("builtins", name));
+    let ty = py
+        .import_bound(module)?
+        .getattr(attr)?
+        .downcast_into::<PyType>()?;
+
+    if !ty.is_subclass_of::<PyBaseException>()? {
+        return Err(PyTypeError::new_err(format!(
+            "{} is not an exception type",
+            name
+        )));
+    }
+
+    Ok(ty)
+}
+
+/// Run a test that is expected to raise the Python exception `exception`
+///
+/// This is what `#[pyo3_async_runtimes::tokio::test(raises = "...")]` expands to. `exception` is
+/// either the name of a builtin exception such as `"ValueError"` or a dotted path such as
+/// `"asyncio.CancelledError"`, and subclasses of it are accepted as well. If the test succeeds or
+/// raises something else, the test fails with an `AssertionError` whose cause is the exception that
+/// was actually raised.
+pub async fn expect_raises<F>(fut: F, exception: &str) -> PyResult<()>
+where
+    F: Future<Output = PyResult<()>>,
+{
+    let result = fut.await;
+
+    Python::with_gil(|py| {
+        let ty = exception_type(py, exception)?;
+
+        match result {
+            Ok(()) => Err(PyAssertionError::new_err(format!(
+                "expected the test to raise {}, but it succeeded",
+                exception
+            ))),
+            Err(e) if e.is_instance_bound(py, &ty) => Ok(()),
+            Err(e) => {
+                let err = PyAssertionError::new_err(format!(
+                    "expected the test to raise {}, but it raised {}",
+                    exception, e
+                ));
+                err.set_cause(py, Some(e));
+                Err(err)
+            }
+        }
+    })
+}
+
+/// Run a test that is expected to panic
+///
+/// This is what `#[pyo3_async_runtimes::tokio::test(should_panic)]` expands to. Panics of the test
+/// itself are caught, as well as the [`RustPanic`] errors that report the panics of futures that
+/// were converted to Python, or of tests that run on another thread. If `expected` is given, the
+/// panic message has to contain it, like with `#[should_panic(expected = "...")]`.
+pub async fn expect_panic<F>(fut: F, expected: Option<&str>) -> PyResult<()>
+where
+    F: Future<Output = PyResult<()>>,
+{
+    let message = match AssertUnwindSafe(fut).catch_unwind().await {
+        Ok(Ok(())) => {
+            return Err(PyAssertionError::new_err(
+                "expected the test to panic, but it succeeded",
+            ))
+        }
+        Ok(Err(e)) => Python::with_gil(|py| {
+            if e.is_instance_of::<RustPanic>(py) {
+                Ok(e.value_bound(py).to_string())
+            } else {
+                let err = PyAssertionError::new_err(format!(
+                    "expected the test to panic, but it raised {}",
+                    e
+                ));
+                err.set_cause(py, Some(e));
+                Err(err)
+            }
+        })?,
+        Err(payload) => get_panic_message(&*payload).to_string(),
+    };
+
+    match expected {
+        Some(expected) if !message.contains(expected) => Err(PyAssertionError::new_err(format!(
+            "panic did not contain the expected string\n      panic message: {:?}\n expected substring: {:?}",
+            message, expected
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// The result of the thread that runs an event loop until it's stopped
+type LoopThread = oneshot::Receiver<PyResult<()>>;
+
+/// Create an event loop of the event loop policy `policy` that runs on a thread of its own
+fn spawn_event_loop(policy: &str) -> PyResult<(PyObject, LoopThread)> {
+    let event_loop = Python::with_gil(|py| -> PyResult<PyObject> {
+        Ok(crate::event_loop_policy(py, policy)?
+            .call_method0("new_event_loop")?
+            .unbind())
+    })?;
+
+    let (tx, rx) = oneshot::channel();
+    let loop_thread = Python::with_gil(|py| event_loop.clone_ref(py));
+    thread::spawn(move || {
+        let result = Python::with_gil(|py| -> PyResult<()> {
+            let event_loop = loop_thread.bind(py);
+            event_loop.call_method0("run_forever")?;
+            event_loop.call_method0("close")?;
+            Ok(())
+        });
+        let _ = tx.send(result);
+    });
+
+    Ok((event_loop, rx))
+}
+
+/// Stop an event loop of [`spawn_event_loop`] and wait for its thread to close it
+async fn stop_event_loop(event_loop: PyObject, loop_thread: LoopThread) -> PyResult<()> {
+    Python::with_gil(|py| -> PyResult<()> {
+        let event_loop = event_loop.bind(py);
+        event_loop.call_method1("call_soon_threadsafe", (event_loop.getattr("stop")?,))?;
+        Ok(())
+    })?;
+
+    loop_thread
+        .await
+        .expect("the thread of the event loop exited before it completed")
+}
+
+/// Run a test on a new event loop of the event loop policy `policy`
+///
+/// This is what the `event_loop_policy = "..."` argument of the test attributes expands to. The
+/// policy isn't installed, so the other tests keep running on the event loop of the harness.
+/// Instead, the policy creates a loop that runs on a thread of its own until the test completes,
+/// and `scope` sets the task locals of that loop for the test, e.g.
+/// `|locals| pyo3_async_runtimes::tokio::scope(locals, test)`.
+///
+/// `policy` is one of [`EVENT_LOOP_POLICIES`](crate::EVENT_LOOP_POLICIES).
+pub async fn with_event_loop_policy<F, Fut>(policy: &str, scope: F) -> PyResult<()>
+where
+    F: FnOnce(TaskLocals) -> Fut,
+    Fut: Future<Output = PyResult<()>>,
+{
+    let (event_loop, loop_thread) = spawn_event_loop(policy)?;
+    let locals =
+        Python::with_gil(|py| TaskLocals::new(event_loop.bind(py).clone()).copy_context(py))?;
+
+    let result = scope(locals).await;
+
+    result.and(stop_event_loop(event_loop, loop_thread).await)
+}
+
+/// The event loops of the tests with a shared loop, by event loop policy
+static SHARED_EVENT_LOOPS: Mutex<Vec<(String, PyObject, LoopThread)>> = Mutex::new(Vec::new());
+
+/// Run a test on the shared event loop of the event loop policy `policy`
+///
+/// This is what the `event_loop_policy = "..."` argument of the test attributes expands to along
+/// with `shared_loop`. It's [`with_event_loop_policy`], except that the loop is created by the
+/// first test of the policy, and the other tests with the policy run on it as well, until the test
+/// harness stops it after the tests.
+pub async fn with_shared_event_loop<F, Fut>(policy: &str, scope: F) -> PyResult<()>
+where
+    F: FnOnce(TaskLocals) -> Fut,
+    Fut: Future<Output = PyResult<()>>,
+{
+    let locals = {
+        let mut event_loops = SHARED_EVENT_LOOPS.lock().unwrap();
+        let event_loop = match event_loops.iter().find(|(name, _, _)| name == policy) {
+            Some((_, event_loop, _)) => Python::with_gil(|py| event_loop.clone_ref(py)),
+            None => {
+                let (event_loop, loop_thread) = spawn_event_loop(policy)?;
+                let shared = Python::with_gil(|py| event_loop.clone_ref(py));
+                event_loops.push((policy.to_owned(), shared, loop_thread));
+                event_loop
+            }
+        };
+
+        Python::with_gil(|py| TaskLocals::new(event_loop.into_bound(py)).copy_context(py))?
+    };
+
+    scope(locals).await
+}
+
+/// Stop the shared event loops of the tests
+async fn stop_shared_event_loops() -> PyResult<()> {
+    let event_loops = std::mem::take(&mut *SHARED_EVENT_LOOPS.lock().unwrap());
+
+    let mut result = Ok(());
+    for (_, event_loop, loop_thread) in event_loops {
+        result = result.and(stop_event_loop(event_loop, loop_thread).await);
+    }
+    result
+}
+
+/// The runtimes that [`#[pyo3_async_runtimes::test_all_backends]`](crate::test_all_backends) runs
+/// a test on
+///
+/// Only the backends of the enabled runtime features exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// A `multi_thread` tokio runtime of the test's own
+    #[cfg(feature = "tokio-runtime")]
+    TokioMultiThread,
+    /// A `current_thread` tokio runtime of the test's own
+    #[cfg(feature = "tokio-runtime")]
+    TokioCurrentThread,
+    /// The global `async-std` executor
+    #[cfg(feature = "async-std-runtime")]
+    AsyncStd,
+}
+
+impl Backend {
+    /// Block the current thread on the future returned by `test`, with the task locals `locals`
+    fn block_on<F, Fut>(self, locals: TaskLocals, test: F) -> PyResult<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = PyResult<()>> + Send + 'static,
+    {
+        // without a runtime feature there are no backends, so this is never called
+        #[cfg(not(any(feature = "tokio-runtime", feature = "async-std-runtime")))]
+        let _ = (locals, test);
+
+        match self {
+            #[cfg(feature = "tokio-runtime")]
+            Backend::TokioMultiThread => ::tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build the tokio runtime of the test")
+                .block_on(crate::tokio::scope(locals, test())),
+            #[cfg(feature = "tokio-runtime")]
+            Backend::TokioCurrentThread => ::tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build the tokio runtime of the test")
+                .block_on(crate::tokio::scope(locals, test())),
+            #[cfg(feature = "async-std-runtime")]
+            Backend::AsyncStd => {
+                ::async_std::task::block_on(crate::async_std::scope(locals, test()))
+            }
+        }
+    }
+}
+
+/// The task locals of the harness, whichever runtime it runs on
+fn harness_locals(py: Python) -> PyResult<TaskLocals> {
+    #[cfg(feature = "tokio-runtime")]
+    if let Some(locals) =
+        <crate::tokio::TokioRuntime as crate::generic::ContextExt>::get_task_locals()
+    {
+        return Ok(locals);
+    }
+
+    match crate::scoped::get_task_locals() {
+        Some(locals) => Ok(locals),
+        None => crate::current_locals(py),
+    }
+}
+
+/// Run the future returned by `test` on `backend`
+///
+/// This is what the tests generated by
+/// [`#[pyo3_async_runtimes::test_all_backends]`](crate::test_all_backends) call. The backend is
+/// blocked on by a thread of its own, so that the harness keeps running in the meantime, and the
+/// test has the task locals of the harness. A panic of the test is turned into a [`RustPanic`]
+/// error.
+pub async fn run_on_backend<F, Fut>(backend: Backend, test: F) -> PyResult<()>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = PyResult<()>> + Send + 'static,
+{
+    let locals = Python::with_gil(harness_locals)?;
+    let (tx, rx) = oneshot::channel();
+
+    thread::spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(move || backend.block_on(locals, test)));
+        let _ = tx.send(result);
+    });
+
+    match rx
+        .await
+        .expect("the thread of the test exited before it completed")
+    {
+        Ok(result) => result,
+        Err(payload) => Err(RustPanic::new_err(format!(
+            "rust future panicked: {}",
+            get_panic_message(&*payload)
+        ))),
+    }
+}
+
+type TestFuture = Pin<Box<dyn Future<Output = PyResult<()>> + Send>>;
+
+type EachHook = dyn Fn(&'static str) -> TestFuture + Send + Sync;
+
+/// Hooks that the test harness runs around the tests
+///
+/// The hooks run in the task locals of the harness, so they can use the event loop of the tests,
+/// e.g. to import the Python modules that are used by many tests once, or to reset the state of a
+/// module between tests:
+///
+/// ```
+/// # #[cfg(all(feature = "tokio-runtime", feature = "attributes"))]
+/// use pyo3::prelude::*;
+/// # #[cfg(all(feature = "tokio-runtime", feature = "attributes"))]
+/// use pyo3_async_runtimes::testing::Hooks;
+///
+/// # #[cfg(all(feature = "tokio-runtime", feature = "attributes"))]
+/// #[pyo3_async_runtimes::tokio::main]
+/// async fn main() -> PyResult<()> {
+///     let hooks = Hooks::new()
+///         .with_setup(|| async {
+///             Python::with_gil(|py| py.import_bound("json").map(drop))
+///         })
+///         .with_after_each(|name| async move {
+///             println!("cleaning up after {}", name);
+///             Ok(())
+///         });
+///
+///     pyo3_async_runtimes::testing::main_with_hooks(hooks).await
+/// }
+/// # #[cfg(not(all(feature = "tokio-runtime", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[derive(Default)]
+pub struct Hooks {
+    setup: Option<Box<dyn FnOnce() -> TestFuture + Send>>,
+    teardown: Option<Box<dyn FnOnce() -> TestFuture + Send>>,
+    before_each: Option<Box<EachHook>>,
+    after_each: Option<Box<EachHook>>,
+}
+
+impl Hooks {
+    /// No hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `setup` once before the tests
+    ///
+    /// If it fails, none of the tests run and the harness fails with the error.
+    pub fn with_setup<F, Fut>(self, setup: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = PyResult<()>> + Send + 'static,
+    {
+        Self {
+            setup: Some(Box::new(move || Box::pin(setup()))),
+            ..self
+        }
+    }
+
+    /// Run `teardown` once after the tests, whether they passed or not
+    ///
+    /// The harness fails if it does.
+    pub fn with_teardown<F, Fut>(self, teardown: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = PyResult<()>> + Send + 'static,
+    {
+        Self {
+            teardown: Some(Box::new(move || Box::pin(teardown()))),
+            ..self
+        }
+    }
+
+    /// Run `before_each` with the name of each test before the test
+    ///
+    /// If it fails, the test fails with the error without running.
+    pub fn with_before_each<F, Fut>(self, before_each: F) -> Self
+    where
+        F: Fn(&'static str) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = PyResult<()>> + Send + 'static,
+    {
+        Self {
+            before_each: Some(Box::new(move |name| Box::pin(before_each(name)))),
+            ..self
+        }
+    }
+
+    /// Run `after_each` with the name of each test after the test, whether it passed or not
+    ///
+    /// The test fails if it does. It isn't run for tests whose `before_each` hook failed.
+    pub fn with_after_each<F, Fut>(self, after_each: F) -> Self
+    where
+        F: Fn(&'static str) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = PyResult<()>> + Send + 'static,
+    {
+        Self {
+            after_each: Some(Box::new(move |name| Box::pin(after_each(name)))),
+            ..self
+        }
+    }
+}
+
+/// Run a test future, turning a panic into a [`RustPanic`] error
+async fn catch_panic(test: TestFuture) -> PyResult<()> {
+    match AssertUnwindSafe(test).catch_unwind().await {
+        Ok(result) => result,
+        Err(payload) => Err(RustPanic::new_err(format!(
+            "rust future panicked: {}",
+            get_panic_message(&*payload)
+        ))),
+    }
+}
+
+/// Run a test with the hooks around it, capturing the output of each of them with `capture`
+async fn run_with_hooks(
+    test: &Test,
+    before_each: Option<&EachHook>,
+    after_each: Option<&EachHook>,
+    capture: Option<&Capture>,
+) -> PyResult<()> {
+    let run = |task: TestFuture| match capture {
+        Some(capture) => capture.run(task),
+        None => task,
+    };
+
+    if let Some(before_each) = before_each {
+        run(before_each(test.name)).await?;
+    }
+    let result = run(Box::pin(catch_panic(test.task()))).await;
+    match after_each {
+        Some(after_each) => result.and(run(after_each(test.name)).await),
+        None => result,
+    }
+}
+
+/// The traceback of a failed test, along with the exceptions it was caused by
+fn format_traceback(py: Python, err: &PyErr) -> String {
+    let formatted = py.import_bound("traceback").and_then(|traceback| {
+        traceback
+            .call_method1(
+                "format_exception",
+                (
+                    err.get_type_bound(py),
+                    err.value_bound(py),
+                    err.traceback_bound(py),
+                ),
+            )?
+            .extract::<Vec<String>>()
+    });
+
+    match formatted {
+        Ok(lines) => lines.concat(),
+        Err(_) => format!("{}\n", err),
+    }
+}
+
 /// Run a sequence of tests while applying any necessary filtering from the `Args`
+///
+/// The output mirrors the default test harness, with the time each test took. If any test fails,
+/// the tracebacks of the failures are printed after the tests, and the harness fails with a
+/// `SystemExit` of code 101, which is the exit code of failing Rust tests.
+///
+/// The tests run concurrently, except for the [`serial`](Test::serial) ones, which run one at a
+/// time once the others are done. The event loops of the tests with a
+/// [`shared_loop`](Test::shared_loop) are stopped after the tests.
 pub async fn test_harness(tests: Vec<Test>, args: Args) -> PyResult<()> {
-    stream::iter(tests)
-        .for_each_concurrent(Some(4), |test| {
-            let mut ignore = false;
+    test_harness_with_hooks(tests, args, Hooks::default()).await
+}
 
-            if let Some(filter) = args.filter.as_ref() {
-                if !test.name.contains(filter) {
-                    ignore = true;
-                }
-            }
+/// Run a sequence of tests like [`test_harness`], with hooks around them
+///
+/// Listing the tests with `--list` doesn't run the hooks.
+pub async fn test_harness_with_hooks(
+    mut tests: Vec<Test>,
+    args: Args,
+    hooks: Hooks,
+) -> PyResult<()> {
+    tests.sort_by_key(|test| test.name);
+    let total = tests.len();
+    tests.retain(|test| args.is_selected(test.name));
+    let filtered_out = total - tests.len();
+
+    let report = Report::new(args.format(), args.nocapture);
+    if args.list {
+        report.list(&tests.iter().map(|test| test.name).collect::<Vec<_>>());
+        return Ok(());
+    }
+
+    let Hooks {
+        setup,
+        teardown,
+        before_each,
+        after_each,
+    } = hooks;
+    if let Some(setup) = setup {
+        setup().await?;
+    }
+
+    report.suite_started(tests.len());
+
+    let start = Instant::now();
+    let results = Mutex::new(Vec::new());
+    let (serial, concurrent): (Vec<_>, Vec<_>) = tests.into_iter().partition(|test| test.serial);
 
-            async move {
-                if !ignore {
-                    test.task().await.unwrap();
+    let run_test = |test: Test| {
+        let results = &results;
+        let report = &report;
+        let nocapture = args.nocapture;
+        let before_each = &before_each;
+        let after_each = &after_each;
 
-                    println!("test {} ... ok", test.name);
+        async move {
+            report.test_started(test.name);
+
+            let test_start = Instant::now();
+            let capture = if nocapture {
+                Ok(None)
+            } else {
+                Python::with_gil(Capture::new).map(Some)
+            };
+            let (result, capture) = match capture {
+                Ok(capture) => {
+                    let result = run_with_hooks(
+                        &test,
+                        before_each.as_deref(),
+                        after_each.as_deref(),
+                        capture.as_ref(),
+                    )
+                    .await;
+                    (result, capture)
                 }
-            }
-        })
+                Err(e) => (Err(e), None),
+            };
+
+            let result = TestResult {
+                name: test.name,
+                exec_time: test_start.elapsed().as_secs_f64(),
+                failure: result.err().map(|e| {
+                    Python::with_gil(|py| {
+                        // print the traceback, along with the exception an expectation failed
+                        // on
+                        if nocapture {
+                            e.display(py);
+                        }
+                        Failure {
+                            message: e.to_string(),
+                            traceback: format_traceback(py, &e),
+                            output: capture
+                                .map(|capture| capture.output(py))
+                                .unwrap_or_default(),
+                        }
+                    })
+                }),
+            };
+            report.test_finished(&result);
+            results.lock().unwrap().push(result);
+        }
+    };
+
+    stream::iter(concurrent)
+        .for_each_concurrent(Some(args.test_threads()), &run_test)
         .await;
+    // the serial tests run one at a time, once the others are done
+    stream::iter(serial).for_each(&run_test).await;
+    let stopped = stop_shared_event_loops().await;
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|result| result.name);
+    report.suite_finished(&results, filtered_out, start.elapsed().as_secs_f64());
+
+    if let Some(teardown) = teardown {
+        teardown().await?;
+    }
+    stopped?;
+
+    if results.iter().all(|result| result.failure.is_none()) {
+        Ok(())
+    } else {
+        Err(PySystemExit::new_err(101))
+    }
+}
+
+const PYTEST_PLUGIN: &str = r#"
+import asyncio
+
+import pytest
+
+def make_test(tests):
+    shared_loop = None
+
+    @pytest.mark.parametrize("rust_test", tests.names())
+    def test_rust(rust_test):
+        nonlocal shared_loop
 
-    Ok(())
+        async def run():
+            await tests.run(rust_test)
+
+        if tests.shared_loop(rust_test):
+            if shared_loop is None:
+                shared_loop = asyncio.new_event_loop()
+            shared_loop.run_until_complete(run())
+        else:
+            asyncio.run(run())
+
+    return test_rust
+"#;
+
+/// The Rust tests of a module with a pytest plugin
+#[pyclass(frozen, module = "pyo3_async_runtimes")]
+struct RustTests {
+    run: for<'py> fn(Python<'py>, Test) -> PyResult<Bound<'py, PyAny>>,
+}
+
+#[pymethods]
+impl RustTests {
+    /// The names of the tests, in the order they run in
+    fn names(&self) -> Vec<&'static str> {
+        let mut names = inventory::iter::<Test>()
+            .map(|test| test.name)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Whether the test with the given name shares its event loop with the other tests
+    fn shared_loop(&self, name: &str) -> PyResult<bool> {
+        Ok(find_test(name)?.shared_loop)
+    }
+
+    /// Run the test with the given name, as an awaitable of the running event loop
+    fn run<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyAny>> {
+        (self.run)(py, find_test(name)?.clone())
+    }
+}
+
+fn find_test(name: &str) -> PyResult<&'static Test> {
+    inventory::iter::<Test>()
+        .find(|test| test.name == name)
+        .ok_or_else(|| PyLookupError::new_err(format!("no such test `{}`", name)))
+}
+
+fn run_test<R>(py: Python, test: Test) -> PyResult<Bound<PyAny>>
+where
+    R: ContextExt,
+{
+    crate::generic::future_into_py::<R, _, _>(py, catch_panic(test.task()))
+}
+
+/// Add a pytest test to `module` that runs the tests of the `#[test]` attributes
+///
+/// This lets you run the tests with pytest instead of the test harness of this module, e.g. to
+/// report them along with the Python tests of your project. Build the tests into an extension
+/// module of their own, and add the pytest test to it with the runtime the tests should run on:
+///
+/// ```
+/// # #[cfg(all(feature = "tokio-runtime", feature = "attributes"))]
+/// # mod tests {
+/// use pyo3::prelude::*;
+///
+/// #[pyo3_async_runtimes::tokio::test]
+/// async fn test_sleep() -> PyResult<()> {
+///     tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+///     Ok(())
+/// }
+///
+/// #[pymodule]
+/// fn my_tests(m: &Bound<PyModule>) -> PyResult<()> {
+///     pyo3_async_runtimes::testing::add_pytest_plugin::<pyo3_async_runtimes::tokio::TokioRuntime>(m)
+/// }
+/// # }
+/// ```
+///
+/// The module then has a `test_rust` function, which pytest collects from any test file that
+/// imports it:
+///
+/// ```python
+/// # tests/test_rust.py
+/// from my_tests import test_rust
+/// ```
+///
+/// Each Rust test is a case of `test_rust` that's named after the test, e.g.
+/// `tests/test_rust.py::test_rust[my_tests::test_sleep]`, so the tests can be selected with `-k` and
+/// show up in the reports of pytest. Every test runs in an event loop of its own, created with
+/// `asyncio.run`, except for the tests with `shared_loop`, which all run on one event loop. The
+/// `main` attributes aren't involved, so the runtime `R` has to be usable without them. The
+/// `pytest` package has to be installed when the module is imported.
+pub fn add_pytest_plugin<R>(module: &Bound<PyModule>) -> PyResult<()>
+where
+    R: ContextExt,
+{
+    let py = module.py();
+    let plugin = PyModule::from_code_bound(
+        py,
+        PYTEST_PLUGIN,
+        "pyo3_async_runtimes/pytest_plugin.py",
+        "pyo3_async_runtimes_pytest_plugin",
+    )?;
+    let tests = Bound::new(py, RustTests { run: run_test::<R> })?;
+
+    module.add("rust_tests", &tests)?;
+    module.add("test_rust", plugin.call_method1("make_test", (tests,))?)
 }
 
 /// Parses test arguments and passes the tests to the `pyo3-asyncio` test harness
@@ -306,9 +1331,16 @@ pub async fn test_harness(tests: Vec<Test>, args: Args) -> PyResult<()> {
 /// # fn main() { }
 /// ```
 pub async fn main() -> PyResult<()> {
+    main_with_hooks(Hooks::default()).await
+}
+
+/// Parses test arguments and passes the tests to the test harness with hooks around them
+///
+/// See [`Hooks`] for an example.
+pub async fn main_with_hooks(hooks: Hooks) -> PyResult<()> {
     let args = parse_args();
 
-    test_harness(inventory::iter::<Test>().cloned().collect(), args).await
+    test_harness_with_hooks(inventory::iter::<Test>().cloned().collect(), args, hooks).await
 }
 
 #[cfg(test)]
@@ -0,0 +1,208 @@
+//! The capture of the Python output of each test
+//!
+//! `sys.stdout` and `sys.stderr` are replaced once by streams that write to the output of the
+//! current test, if there is one, and a handler on the root logger writes the log records to it.
+//! The output of a test is found in two places: the context of the test's task locals, for the
+//! coroutines it runs on the event loop, and a thread-local that is set while the test is polled,
+//! for the Python it calls synchronously.
+
+use std::{
+    cell::RefCell,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use once_cell::sync::OnceCell;
+use pin_project_lite::pin_project;
+use pyo3::prelude::*;
+
+use super::{harness_locals, TestFuture};
+use crate::{scoped::Scoped, TaskLocals};
+
+const CAPTURE_GLUE: &str = r#"
+import contextvars
+import io
+import logging
+import sys
+
+output_var = contextvars.ContextVar("pyo3_async_runtimes_test_output")
+
+class CapturedStream:
+    def __init__(self, stream, current_output):
+        self._stream = stream
+        self._current_output = current_output
+
+    def _output(self):
+        output = output_var.get(None)
+        return output if output is not None else self._current_output()
+
+    def write(self, s):
+        output = self._output()
+        if output is None:
+            return self._stream.write(s)
+        return output.write(s)
+
+    def flush(self):
+        if self._output() is None:
+            self._stream.flush()
+
+    def __getattr__(self, name):
+        return getattr(self._stream, name)
+
+class CaptureHandler(logging.Handler):
+    def __init__(self, current_output):
+        super().__init__()
+        self._current_output = current_output
+        self.setFormatter(logging.Formatter("%(levelname)s %(name)s: %(message)s"))
+
+    def emit(self, record):
+        output = output_var.get(None)
+        if output is None:
+            output = self._current_output()
+
+        if output is not None:
+            output.write(self.format(record) + "\n")
+        elif logging.getLogger().handlers == [self] and record.levelno >= logging.lastResort.level:
+            # outside of the tests, the records are handled as if there was no handler
+            logging.lastResort.handle(record)
+
+def install(current_output):
+    sys.stdout = CapturedStream(sys.stdout, current_output)
+    sys.stderr = CapturedStream(sys.stderr, current_output)
+    logging.getLogger().addHandler(CaptureHandler(current_output))
+
+def capture(context):
+    output = io.StringIO()
+    context.run(output_var.set, output)
+    return output
+"#;
+
+static CAPTURE_GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
+
+/// The glue, which replaces the streams the first time it's used
+fn glue(py: Python) -> PyResult<&Bound<PyAny>> {
+    CAPTURE_GLUE_MOD
+        .get_or_try_init(|| -> PyResult<PyObject> {
+            let glue = PyModule::from_code_bound(
+                py,
+                CAPTURE_GLUE,
+                "pyo3_asyncio/pyo3_asyncio_capture_glue.py",
+                "pyo3_asyncio_capture_glue",
+            )?;
+            glue.call_method1("install", (CurrentOutput,))?;
+
+            Ok(glue.into())
+        })
+        .map(|glue| glue.bind(py))
+}
+
+thread_local! {
+    static OUTPUT: RefCell<Option<PyObject>> = const { RefCell::new(None) };
+}
+
+/// The output of the test that is being polled on the calling thread, which the glue calls into
+#[pyclass]
+struct CurrentOutput;
+
+#[pymethods]
+impl CurrentOutput {
+    fn __call__(&self, py: Python) -> Option<PyObject> {
+        OUTPUT
+            .try_with(|output| output.borrow().as_ref().map(|output| output.clone_ref(py)))
+            .unwrap_or_default()
+    }
+}
+
+pin_project! {
+    /// The output is swapped into a thread-local for the duration of each poll of the inner
+    /// future, like the locals of [`Scoped`].
+    struct Captured<F> {
+        output: Option<PyObject>,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F> std::future::Future for Captured<F>
+where
+    F: std::future::Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        struct Guard<'a>(&'a mut Option<PyObject>);
+
+        impl Drop for Guard<'_> {
+            fn drop(&mut self) {
+                OUTPUT.with(|c| std::mem::swap(self.0, &mut *c.borrow_mut()));
+            }
+        }
+
+        OUTPUT.with(|c| std::mem::swap(this.output, &mut *c.borrow_mut()));
+        let _guard = Guard(this.output);
+
+        this.future.poll(cx)
+    }
+}
+
+/// The captured output of a test
+pub(crate) struct Capture {
+    /// The locals of the harness, with a copy of its context in which the output is set
+    locals: TaskLocals,
+    output: PyObject,
+}
+
+impl Capture {
+    pub(crate) fn new(py: Python) -> PyResult<Self> {
+        let glue = glue(py)?;
+
+        let locals = harness_locals(py)?;
+        let context = locals.context(py);
+        let context = if context.is_none() {
+            crate::copy_context(py)?
+        } else {
+            context.call_method0("copy")?
+        };
+        let output = glue.call_method1("capture", (&context,))?;
+
+        Ok(Self {
+            locals: locals.with_context(context),
+            output: output.unbind(),
+        })
+    }
+
+    /// Run `task` with its output captured
+    pub(crate) fn run(&self, task: TestFuture) -> TestFuture {
+        let (locals, output) =
+            Python::with_gil(|py| (self.locals.clone_ref(py), self.output.clone_ref(py)));
+
+        scope(
+            locals,
+            Box::pin(Captured {
+                output: Some(output),
+                future: task,
+            }),
+        )
+    }
+
+    /// The output so far
+    pub(crate) fn output(&self, py: Python) -> String {
+        self.output
+            .bind(py)
+            .call_method0("getvalue")
+            .and_then(|output| output.extract())
+            .unwrap_or_default()
+    }
+}
+
+/// Scope `task` to `locals` in the task locals of the runtime the harness runs on
+fn scope(locals: TaskLocals, task: TestFuture) -> TestFuture {
+    #[cfg(feature = "tokio-runtime")]
+    if <crate::tokio::TokioRuntime as crate::generic::ContextExt>::get_task_locals().is_some() {
+        return <crate::tokio::TokioRuntime as crate::generic::ContextExt>::scope(locals, task);
+    }
+
+    Box::pin(Scoped::new(locals, task))
+}
@@ -0,0 +1,278 @@
+//! The output of the test harness in the formats of the `--format` argument
+
+use std::io::{self, Write};
+
+/// The output format of the test harness
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// A line per test, like the default test harness
+    Pretty,
+    /// A character per test
+    Terse,
+    /// A JSON object per line for each event, like the unstable JSON format of the default test
+    /// harness
+    Json,
+    /// A JUnit XML report once the tests are done
+    Junit,
+}
+
+impl Format {
+    /// The names of the formats for the `--format` argument
+    pub(crate) const NAMES: [&'static str; 4] = ["pretty", "terse", "json", "junit"];
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pretty" => Some(Format::Pretty),
+            "terse" => Some(Format::Terse),
+            "json" => Some(Format::Json),
+            "junit" => Some(Format::Junit),
+            _ => None,
+        }
+    }
+}
+
+/// The error a test failed with
+pub(crate) struct Failure {
+    /// The exception, e.g. `ValueError: invalid value`
+    pub(crate) message: String,
+    /// The traceback of the exception, along with the exceptions it was caused by
+    pub(crate) traceback: String,
+    /// The Python output of the test, if it was captured
+    pub(crate) output: String,
+}
+
+impl Failure {
+    /// The output of the test followed by the traceback, which is what the test printed
+    fn stdout(&self) -> String {
+        format!("{}{}", self.output, self.traceback)
+    }
+}
+
+/// The outcome of a test
+pub(crate) struct TestResult {
+    pub(crate) name: &'static str,
+    /// The time the test took in seconds
+    pub(crate) exec_time: f64,
+    /// `None` if the test passed
+    pub(crate) failure: Option<Failure>,
+}
+
+/// Prints the events of a test run in the chosen format
+pub(crate) struct Report {
+    format: Format,
+    nocapture: bool,
+}
+
+impl Report {
+    pub(crate) fn new(format: Format, nocapture: bool) -> Self {
+        Self { format, nocapture }
+    }
+
+    /// List the tests instead of running them
+    pub(crate) fn list(&self, names: &[&str]) {
+        for name in names {
+            println!("{}: test", name);
+        }
+        if self.format == Format::Pretty {
+            println!();
+            println!("{} tests, 0 benchmarks", names.len());
+        }
+    }
+
+    pub(crate) fn suite_started(&self, test_count: usize) {
+        match self.format {
+            Format::Pretty | Format::Terse => {
+                println!();
+                println!(
+                    "running {} test{}",
+                    test_count,
+                    if test_count == 1 { "" } else { "s" }
+                );
+            }
+            Format::Json => println!(
+                r#"{{ "type": "suite", "event": "started", "test_count": {} }}"#,
+                test_count
+            ),
+            Format::Junit => {}
+        }
+    }
+
+    pub(crate) fn test_started(&self, name: &str) {
+        if self.format == Format::Json {
+            println!(
+                r#"{{ "type": "test", "event": "started", "name": {} }}"#,
+                json_string(name)
+            );
+        }
+    }
+
+    pub(crate) fn test_finished(&self, result: &TestResult) {
+        match (self.format, &result.failure) {
+            (Format::Pretty, None) => {
+                println!("test {} ... ok <{:.3}s>", result.name, result.exec_time)
+            }
+            (Format::Pretty, Some(_)) => {
+                println!("test {} ... FAILED <{:.3}s>", result.name, result.exec_time)
+            }
+            (Format::Terse, None) => print!("."),
+            (Format::Terse, Some(_)) => print!("F"),
+            (Format::Json, None) => println!(
+                r#"{{ "type": "test", "name": {}, "event": "ok", "exec_time": {} }}"#,
+                json_string(result.name),
+                result.exec_time
+            ),
+            (Format::Json, Some(failure)) => println!(
+                r#"{{ "type": "test", "name": {}, "event": "failed", "exec_time": {}, "stdout": {} }}"#,
+                json_string(result.name),
+                result.exec_time,
+                json_string(&failure.stdout())
+            ),
+            (Format::Junit, _) => {}
+        }
+        let _ = io::stdout().flush();
+    }
+
+    /// Print the summary of the tests, which are sorted by name
+    pub(crate) fn suite_finished(
+        &self,
+        results: &[TestResult],
+        filtered_out: usize,
+        exec_time: f64,
+    ) {
+        let failed = results
+            .iter()
+            .filter(|result| result.failure.is_some())
+            .count();
+        let passed = results.len() - failed;
+
+        match self.format {
+            Format::Pretty | Format::Terse => {
+                if self.format == Format::Terse {
+                    println!();
+                }
+                self.print_failures(results);
+
+                println!();
+                println!(
+                    "test result: {}. {} passed; {} failed; 0 ignored; 0 measured; {} filtered out; finished in {:.2}s",
+                    if failed == 0 { "ok" } else { "FAILED" },
+                    passed,
+                    failed,
+                    filtered_out,
+                    exec_time
+                );
+                println!();
+            }
+            Format::Json => println!(
+                r#"{{ "type": "suite", "event": "{}", "passed": {}, "failed": {}, "ignored": 0, "measured": 0, "filtered_out": {}, "exec_time": {} }}"#,
+                if failed == 0 { "ok" } else { "failed" },
+                passed,
+                failed,
+                filtered_out,
+                exec_time
+            ),
+            Format::Junit => print_junit(results, failed),
+        }
+    }
+
+    fn print_failures(&self, results: &[TestResult]) {
+        let failures = results
+            .iter()
+            .filter_map(|result| {
+                result
+                    .failure
+                    .as_ref()
+                    .map(|failure| (result.name, failure))
+            })
+            .collect::<Vec<_>>();
+        if failures.is_empty() {
+            return;
+        }
+
+        println!();
+        println!("failures:");
+        // the tracebacks were printed as the tests failed
+        if !self.nocapture {
+            for (name, failure) in &failures {
+                println!();
+                println!("---- {} stdout ----", name);
+                print!("{}", failure.stdout());
+            }
+        }
+        println!();
+        println!("failures:");
+        for (name, _) in &failures {
+            println!("    {}", name);
+        }
+    }
+}
+
+/// Print the JUnit XML report, in the layout of the default test harness
+fn print_junit(results: &[TestResult], failed: usize) {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str("<testsuites>");
+    xml.push_str(&format!(
+        r#"<testsuite name="test" package="test" id="0" errors="0" failures="{}" tests="{}" skipped="0" >"#,
+        failed,
+        results.len()
+    ));
+
+    for result in results {
+        let (classname, name) = result.name.rsplit_once("::").unwrap_or(("", result.name));
+        xml.push_str(&format!(
+            r#"<testcase classname="{}" name="{}" time="{}""#,
+            xml_escape(classname),
+            xml_escape(name),
+            result.exec_time
+        ));
+
+        match &result.failure {
+            None => xml.push_str("/>"),
+            Some(failure) => {
+                xml.push_str(&format!(
+                    r#"><failure type="{}" message="{}"/><system-out><![CDATA[{}]]></system-out></testcase>"#,
+                    xml_escape(
+                        failure
+                            .message
+                            .split(':')
+                            .next()
+                            .unwrap_or(&failure.message)
+                    ),
+                    xml_escape(&failure.message),
+                    failure.stdout().replace("]]>", "]]]]><![CDATA[>")
+                ));
+            }
+        }
+    }
+
+    xml.push_str("<system-out/><system-err/></testsuite></testsuites>");
+    println!("{}", xml);
+}
+
+/// Quote `s` as a JSON string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escape `s` for an XML attribute
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -12,24 +12,151 @@
 //! version = "0.21"
 //! features = ["unstable-streams"]
 //! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-uring-runtime</code></span>
+//! > are only available when the `tokio-uring-runtime` Cargo feature is enabled (Linux only):
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-uring-runtime"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-event-loop</code></span>
+//! > are only available when the `tokio-event-loop` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-event-loop"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-io</code></span>
+//! > are only available when the `tokio-io` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-io"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-sync</code></span>
+//! > are only available when the `tokio-sync` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-sync"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-cancellation</code></span>
+//! > are only available when the `tokio-cancellation` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-cancellation"]
+//! ```
+//!
+//! Items marked with
+//! <span
+//!   class="module-item stab portability"
+//!   style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"
+//! ><code>tokio-test-util</code></span>
+//! > are only available when the `tokio-test-util` Cargo feature is enabled:
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["tokio-test-util"]
+//! ```
+
+#[cfg(feature = "tokio-cancellation")]
+mod cancel;
+#[cfg(feature = "tokio-event-loop")]
+mod event_loop;
+#[cfg(feature = "tokio-io")]
+mod io;
+#[cfg(feature = "tokio-test-util")]
+mod mock_clock;
+mod shutdown;
+#[cfg(feature = "tokio-sync")]
+mod sync;
+mod task_group;
 
-use std::ops::Deref;
-use std::{future::Future, pin::Pin, sync::Mutex};
+use std::{future::Future, pin::Pin, sync::Mutex, time::Duration};
 
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+use ::tokio::runtime::Handle;
 use ::tokio::{
     runtime::{Builder, Runtime},
     task,
 };
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
 use once_cell::{
     sync::{Lazy, OnceCell},
     unsync::OnceCell as UnsyncOnceCell,
 };
-use pyo3::prelude::*;
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
 
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+use crate::generic::SpawnPinnedExt;
 use crate::{
-    generic::{self, ContextExt, LocalContextExt, Runtime as GenericRuntime, SpawnLocalExt},
-    TaskLocals,
+    err::{abort_error, asyncio_exception, translate_error_or, AbortReason},
+    generic::{
+        self, CancelHandle, ContextExt, LocalContextExt, Runtime as GenericRuntime, SpawnLocalExt,
+    },
+    PyFuture, PyOutcomeFuture, PyPolledFuture, PyTask, RunnerOptions, TaskLocals,
+};
+
+#[cfg(feature = "tokio-cancellation")]
+pub use cancel::{
+    future_into_py_with_token, future_into_py_with_token_and_locals, link_cancellation,
 };
+#[cfg(feature = "tokio-event-loop")]
+pub use event_loop::EventLoop;
+#[cfg(feature = "tokio-io")]
+pub use io::{
+    asyncio_duplex, asyncio_duplex_with_locals, into_asyncio_streams,
+    into_asyncio_streams_with_locals, AsyncioReader, AsyncioStream,
+};
+#[cfg(feature = "tokio-test-util")]
+pub use mock_clock::with_mock_clock;
+pub use shutdown::shutdown;
+#[cfg(feature = "tokio-sync")]
+pub use sync::{
+    broadcast_into_py, oneshot_into_py, oneshot_into_py_with_locals, queue_into_receiver,
+    queue_into_receiver_with_locals, queue_into_sender, queue_into_sender_with_locals,
+    receiver_into_py, sender_into_py, watch_into_py,
+};
+pub use task_group::TaskGroup;
 
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
 /// re-exports for macros
@@ -39,6 +166,8 @@ pub mod re_exports {
     pub use futures::future::pending;
     /// re-export tokio::runtime to build runtimes in tokio macros without additional dependency
     pub use tokio::runtime;
+    /// re-export timeout for the `timeout` argument of the tokio test macro
+    pub use tokio::time::timeout;
 }
 
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>attributes</code></span>
@@ -51,22 +180,13 @@ pub use pyo3_async_runtimes_macros::tokio_main as main;
 #[cfg(all(feature = "attributes", feature = "testing"))]
 pub use pyo3_async_runtimes_macros::tokio_test as test;
 
-enum Pyo3Runtime {
-    Borrowed(&'static Runtime),
-    Owned(Runtime),
-}
-impl Deref for Pyo3Runtime {
-    type Target = Runtime;
-    fn deref(&self) -> &Self::Target {
-        match self {
-            Self::Borrowed(rt) => rt,
-            Self::Owned(rt) => rt,
-        }
-    }
-}
-
 static TOKIO_BUILDER: Lazy<Mutex<Builder>> = Lazy::new(|| Mutex::new(multi_thread()));
-static TOKIO_RUNTIME: OnceCell<Pyo3Runtime> = OnceCell::new();
+/// The runtime that the conversions spawn onto
+///
+/// The builder is only locked until the runtime is set. After that, every conversion reads this
+/// with a single atomic load, and the runtime that was built here is leaked so that it can be
+/// stored the same way as the one of [`init_with_runtime`].
+static TOKIO_RUNTIME: OnceCell<&'static Runtime> = OnceCell::new();
 
 impl generic::JoinError for task::JoinError {
     fn is_panic(&self) -> bool {
@@ -77,7 +197,11 @@ impl generic::JoinError for task::JoinError {
     }
 }
 
-struct TokioRuntime;
+/// The tokio runtime as a [`generic`] runtime
+///
+/// Conversions that are generic over the Rust runtime, such as the ones in the `trio`, `anyio`, and
+/// `gevent` modules, can run on tokio by naming this type.
+pub struct TokioRuntime;
 
 tokio::task_local! {
     static TASK_LOCALS: UnsyncOnceCell<TaskLocals>;
@@ -91,9 +215,14 @@ impl GenericRuntime for TokioRuntime {
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        get_runtime().spawn(async move {
-            fut.await;
-        })
+        let fut = shutdown::tracked(fut);
+
+        #[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+        if let Some(uring) = TOKIO_URING.get() {
+            return uring.handle.spawn(fut);
+        }
+
+        get_runtime().spawn(fut)
     }
 }
 
@@ -110,10 +239,7 @@ impl ContextExt for TokioRuntime {
 
     fn get_task_locals() -> Option<TaskLocals> {
         TASK_LOCALS
-            .try_with(|c| {
-                c.get()
-                    .map(|locals| Python::with_gil(|py| locals.clone_ref(py)))
-            })
+            .try_with(|c| c.get().cloned())
             .unwrap_or_default()
     }
 }
@@ -123,7 +249,18 @@ impl SpawnLocalExt for TokioRuntime {
     where
         F: Future<Output = ()> + 'static,
     {
-        tokio::task::spawn_local(fut)
+        tokio::task::spawn_local(shutdown::tracked(fut))
+    }
+}
+
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+impl SpawnPinnedExt for TokioRuntime {
+    fn spawn_pinned<F, Fut>(f: F) -> Self::JoinHandle
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        get_uring().handle.spawn(spawn_pinned(f))
     }
 }
 
@@ -155,46 +292,204 @@ where
     TokioRuntime::scope_local(locals, fut).await
 }
 
+/// Spawn a future onto the tokio runtime so that it inherits the task locals of the current task
+///
+/// tokio task-locals aren't passed on by `tokio::spawn`, so a future spawned from within a
+/// converted future loses its event loop association, and [`get_current_locals`] fails in it.
+/// Futures spawned with this function are scoped to the task locals of the spawning task
+/// instead, if it has any, which works at any depth of nesting.
+///
+/// # Arguments
+/// * `fut` - The future to be spawned
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable function that looks up the event loop from a nested task
+/// #[pyfunction]
+/// fn nested(py: Python) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::future_into_py(py, async {
+///         pyo3_async_runtimes::tokio::spawn(async {
+///             Python::with_gil(|py| {
+///                 let locals = pyo3_async_runtimes::tokio::get_current_locals(py)?;
+///                 Ok(locals.event_loop(py).unbind())
+///             })
+///         })
+///         .await
+///         .unwrap()
+///     })
+/// }
+/// ```
+pub fn spawn<F>(fut: F) -> task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    spawn_scoped(TokioRuntime::get_task_locals(), fut)
+}
+
+/// Spawn a future onto the tokio runtime, scoped to the current task locals
+///
+/// Unlike [`spawn`], this captures the locals with [`get_current_locals`], so it also works on a
+/// thread with a running Python event loop, e.g. in a `#[pyfunction]` that's called from a
+/// coroutine. The spawned future can call [`get_current_locals`] and the conversions that rely on
+/// it, and futures it spawns with [`spawn`] inherit the locals in turn.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The future to be spawned
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Starts a background job that reports back to the calling event loop
+/// #[pyfunction]
+/// fn start_job(py: Python, callback: PyObject) -> PyResult<()> {
+///     pyo3_async_runtimes::tokio::spawn_with_locals(py, async move {
+///         Python::with_gil(|py| -> PyResult<()> {
+///             let locals = pyo3_async_runtimes::tokio::get_current_locals(py)?;
+///             locals
+///                 .event_loop(py)
+///                 .call_method1("call_soon_threadsafe", (callback,))?;
+///             Ok(())
+///         })
+///     })?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn spawn_with_locals<F>(py: Python, fut: F) -> PyResult<task::JoinHandle<F::Output>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    Ok(spawn_scoped(Some(get_current_locals(py)?), fut))
+}
+
+fn spawn_scoped<F>(locals: Option<TaskLocals>, fut: F) -> task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let fut: Pin<Box<dyn Future<Output = F::Output> + Send>> = match locals {
+        Some(locals) => TokioRuntime::scope(locals, fut),
+        None => Box::pin(fut),
+    };
+
+    #[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+    if let Some(uring) = TOKIO_URING.get() {
+        return uring.handle.spawn(fut);
+    }
+
+    get_runtime().spawn(fut)
+}
+
+/// Spawn a !Send future onto the current `LocalSet` so that it inherits the task locals of the
+/// current task
+///
+/// This is the !Send counterpart of [`spawn`], see there for details.
+///
+/// # Arguments
+/// * `fut` - The future to be spawned
+///
+/// # Panics
+/// If it's called outside of a `LocalSet`, like `tokio::task::spawn_local`.
+pub fn spawn_local<F>(fut: F) -> task::JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    match TokioRuntime::get_task_locals() {
+        Some(locals) => task::spawn_local(TokioRuntime::scope_local(locals, fut)),
+        None => task::spawn_local(fut),
+    }
+}
+
 /// Get the current event loop from either Python or Rust async task local context
 ///
-/// This function first checks if the runtime has a task-local reference to the Python event loop.
-/// If not, it calls [`get_running_loop`](`crate::get_running_loop`) to get the event loop
-/// associated with the current OS thread.
+/// Within [`with_event_loop`](`crate::with_event_loop`), this returns the loop passed to it.
+/// Otherwise, it first checks if the runtime has a task-local reference to the Python event loop,
+/// and then whether a loop was set for the current OS thread with
+/// [`set_thread_event_loop`](`crate::set_thread_event_loop`). If not, it calls
+/// [`get_running_loop`](`crate::get_running_loop`) to get the event loop running on the thread.
 pub fn get_current_loop(py: Python) -> PyResult<Bound<PyAny>> {
     generic::get_current_loop::<TokioRuntime>(py)
 }
 
 /// Either copy the task locals from the current task OR get the current running loop and
 /// contextvars from Python.
+///
+/// Futures spawned with [`spawn`] or [`spawn_local`] inherit the task locals of the task that
+/// spawned them, so this works in them as well.
 pub fn get_current_locals(py: Python) -> PyResult<TaskLocals> {
     generic::get_current_locals::<TokioRuntime>(py)
 }
 
 /// Initialize the Tokio runtime with a custom build
+///
+/// The builder has no effect once the runtime was built, see [`try_init`] to find out.
 pub fn init(builder: Builder) {
     *TOKIO_BUILDER.lock().unwrap() = builder
 }
 
+/// Initialize the Tokio runtime with a custom build, unless it was built already
+///
+/// Returns Ok(()) if success and Err(()) if the runtime had already been built or set with
+/// [`init_with_runtime`], in which case `builder` is dropped.
+#[allow(clippy::result_unit_err)]
+pub fn try_init(builder: Builder) -> Result<(), ()> {
+    // the runtime is set with the builder locked, so it can't be set in between
+    let mut current = TOKIO_BUILDER.lock().unwrap();
+    if is_initialized() {
+        return Err(());
+    }
+
+    *current = builder;
+    Ok(())
+}
+
+/// Whether the Tokio runtime was built or set with [`init_with_runtime`]
+pub fn is_initialized() -> bool {
+    TOKIO_RUNTIME.get().is_some()
+}
+
 /// Initialize the Tokio runtime with a custom Tokio runtime
 ///
 /// Returns Ok(()) if success and Err(()) if it had been inited.
 #[allow(clippy::result_unit_err)]
 pub fn init_with_runtime(runtime: &'static Runtime) -> Result<(), ()> {
-    TOKIO_RUNTIME
-        .set(Pyo3Runtime::Borrowed(runtime))
-        .map_err(|_| ())
+    let _builder = TOKIO_BUILDER.lock().unwrap();
+    TOKIO_RUNTIME.set(runtime).map_err(|_| ())
 }
 
 /// Get a reference to the current tokio runtime
+///
+/// # Panics
+/// If the runtime is built by this call and that fails, see [`try_get_runtime`].
 pub fn get_runtime<'a>() -> &'a Runtime {
-    TOKIO_RUNTIME.get_or_init(|| {
-        let rt = TOKIO_BUILDER
-            .lock()
-            .unwrap()
-            .build()
-            .expect("Unable to build Tokio runtime");
-        Pyo3Runtime::Owned(rt)
-    })
+    try_get_runtime().expect("Unable to build Tokio runtime")
+}
+
+/// Get a reference to the current tokio runtime, or the error of building it
+///
+/// The runtime is built the first time it's needed, with the builder of [`init`]. Unlike
+/// [`get_runtime`], this returns the error of building it, e.g. if the builder asks for an I/O
+/// driver that the platform doesn't have, so that it can be raised in Python with `?` instead of
+/// panicking. The next call tries to build the runtime again.
+pub fn try_get_runtime<'a>() -> std::io::Result<&'a Runtime> {
+    if let Some(&rt) = TOKIO_RUNTIME.get() {
+        return Ok(rt);
+    }
+
+    // the builder stays locked until the runtime is set, so that `try_init` can't miss it
+    let mut builder = TOKIO_BUILDER.lock().unwrap();
+    TOKIO_RUNTIME
+        .get_or_try_init(|| Ok(&*Box::leak(Box::new(builder.build()?))))
+        .copied()
 }
 
 fn multi_thread() -> Builder {
@@ -203,95 +498,599 @@ fn multi_thread() -> Builder {
     builder
 }
 
-/// Run the event loop until the given Future completes
-///
-/// The event loop runs until the given future is complete.
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+struct UringExecutor {
+    handle: Handle,
+    jobs: mpsc::UnboundedSender<Box<dyn FnOnce() + Send>>,
+}
+
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+static TOKIO_URING: OnceCell<UringExecutor> = OnceCell::new();
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-uring-runtime</code></span> Initialize a `tokio-uring` runtime for PyO3 Asyncio
+///
+/// The runtime is started on a dedicated thread with the given builder. Once it is running, all of
+/// the conversions in this module spawn their tasks onto it instead of the runtime returned by
+/// [`get_runtime`], and `!Send` futures that use `tokio-uring`'s resources can be converted with
+/// [`pinned_future_into_py`] or awaited with [`spawn_pinned`].
+///
+/// This must be called before any conversions are made. Returns Ok(()) if success and Err(()) if
+/// the `tokio-uring` runtime had already been started.
+///
+/// # Examples
+///
+/// ```no_run
+/// pyo3_async_runtimes::tokio::init_uring(tokio_uring::builder()).unwrap();
+/// ```
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+#[allow(clippy::result_unit_err)]
+pub fn init_uring(builder: tokio_uring::Builder) -> Result<(), ()> {
+    let mut started = false;
+
+    TOKIO_URING.get_or_init(|| {
+        started = true;
+
+        let (jobs, mut rx) = mpsc::unbounded::<Box<dyn FnOnce() + Send>>();
+        let (handle_tx, handle_rx) = std::sync::mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("pyo3-tokio-uring".into())
+            .spawn(move || {
+                builder.start(async move {
+                    let _ = handle_tx.send(Handle::current());
+
+                    // Jobs are run inside the runtime's `LocalSet` so that they can use
+                    // `tokio::task::spawn_local`
+                    while let Some(job) = rx.next().await {
+                        job();
+                    }
+                })
+            })
+            .expect("Unable to spawn tokio-uring runtime thread");
+
+        UringExecutor {
+            handle: handle_rx
+                .recv()
+                .expect("tokio-uring runtime thread exited unexpectedly"),
+            jobs,
+        }
+    });
+
+    if started {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+fn get_uring() -> &'static UringExecutor {
+    TOKIO_URING
+        .get()
+        .expect("the tokio-uring runtime has not been started, call `init_uring` first")
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-uring-runtime</code></span> Spawn a `!Send` future onto the `tokio-uring` runtime thread from any thread
+///
+/// The future is created by `f` on the runtime thread and spawned with
+/// `tokio::task::spawn_local`, so it is free to use `tokio-uring`'s resources. The returned future
+/// is `Send` and resolves to the output of the spawned future, which makes it suitable for the
+/// conversions in this module. The task locals of the caller are carried over to the spawned task.
+///
+/// Dropping the returned future aborts the spawned task. If the spawned task panics, the panic is
+/// resumed in the task awaiting the returned future.
+///
+/// # Panics
+///
+/// Panics if the runtime hasn't been started with [`init_uring`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable function that reads a file with io_uring
+/// #[pyfunction]
+/// fn read_file(py: Python, path: String) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::future_into_py(py, async move {
+///         let buf = pyo3_async_runtimes::tokio::spawn_pinned(move || async move {
+///             let file = tokio_uring::fs::File::open(path).await?;
+///             let (res, buf) = file.read_at(vec![0; 4096], 0).await;
+///             res.map(|n| buf[..n].to_vec())
+///         })
+///         .await?;
+///         Ok(buf)
+///     })
+/// }
+/// ```
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+pub fn spawn_pinned<F, Fut>(f: F) -> impl Future<Output = Fut::Output> + Send + 'static
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future + 'static,
+    Fut::Output: Send + 'static,
+{
+    struct AbortOnDrop<T>(task::JoinHandle<T>);
+
+    impl<T> Drop for AbortOnDrop<T> {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+
+    let locals = TokioRuntime::get_task_locals();
+    let (tx, rx) = oneshot::channel();
+
+    let _ = get_uring().jobs.unbounded_send(Box::new(move || {
+        let handle = match locals {
+            Some(locals) => task::spawn_local(TokioRuntime::scope_local(locals, f())),
+            None => task::spawn_local(f()),
+        };
+        let _ = tx.send(handle);
+    }));
+
+    async move {
+        let mut handle = AbortOnDrop(rx.await.expect("tokio-uring runtime was shut down"));
+
+        match (&mut handle.0).await {
+            Ok(val) => val,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(_) => unreachable!("task was aborted while still being awaited"),
+        }
+    }
+}
+
+/// Run the event loop until the given Future completes
+///
+/// The event loop runs until the given future is complete.
+///
+/// After this function returns, the event loop can be resumed with [`run_until_complete`]
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that should run the future
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// # pyo3::prepare_freethreaded_python();
+/// # Python::with_gil(|py| -> PyResult<()> {
+/// # let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+/// pyo3_async_runtimes::tokio::run_until_complete(event_loop, async move {
+///     tokio::time::sleep(Duration::from_secs(1)).await;
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }).unwrap();
+/// ```
+pub fn run_until_complete<F, T>(event_loop: Bound<PyAny>, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_until_complete::<TokioRuntime, _, T>(&event_loop, fut)
+}
+
+/// Run the event loop until the given Future completes
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() {
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::tokio::run(py, async move {
+///             tokio::time::sleep(Duration::from_secs(1)).await;
+///             Ok(())
+///         })
+///         .map_err(|e| {
+///             e.print_and_set_sys_last_vars(py);
+///         })
+///         .unwrap();
+///     })
+/// }
+/// ```
+pub fn run<F, T>(py: Python, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run::<TokioRuntime, F, T>(py, fut)
+}
+
+/// Run the given Future on a new `asyncio.Runner` (Python 3.11+)
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `options` - The options for the runner
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// #
+/// # use pyo3::prelude::*;
+/// #
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         let options = pyo3_async_runtimes::RunnerOptions::new().with_debug(true);
+///
+///         pyo3_async_runtimes::tokio::run_with_runner(py, options, async move {
+///             tokio::time::sleep(Duration::from_secs(1)).await;
+///             Ok(())
+///         })
+///     })
+/// }
+/// ```
+pub fn run_with_runner<F, T>(py: Python, options: RunnerOptions, fut: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + Sync + 'static,
+{
+    generic::run_with_runner::<TokioRuntime, F, T>(py, options, fut)
+}
+
+/// Spawn `fut` on the tokio runtime after a `KeyboardInterrupt` cancelled the conversions in flight
+///
+/// This only happens if [`cancel_on_interrupt`](crate::cancel_on_interrupt) is enabled, and only
+/// once. Registering another future replaces the previous one.
+///
+/// # Arguments
+/// * `fut` - The shutdown future to be spawned
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     // CTRL-C stops the Rust futures along with the event loop
+///     pyo3_async_runtimes::cancel_on_interrupt(true);
+///     pyo3_async_runtimes::tokio::on_interrupt(async {
+///         println!("interrupted, cleaning up");
+///     });
+///
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::tokio::run(py, async move {
+///             tokio::time::sleep(Duration::from_secs(3600)).await;
+///             Ok(())
+///         })
+///     })
+/// }
+/// ```
+pub fn on_interrupt<F>(fut: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    generic::on_interrupt::<TokioRuntime, F>(fut)
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`] (new behaviour in `v0.15`).
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// > unfortunately fail to resolve them when called within the Rust future. This is because the
+/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// > synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::tokio::future_into_py_with_locals(
+///         py,
+///         pyo3_async_runtimes::tokio::get_current_locals(py)?,
+///         async move {
+///             tokio::time::sleep(Duration::from_secs(secs)).await;
+///             Python::with_gil(|py| Ok(py.None()))
+///         }
+///     )
+/// }
+/// ```
+pub fn future_into_py_with_locals<F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py_with_locals::<TokioRuntime, F, T>(py, locals, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+///
+/// Python `contextvars` are preserved when calling async Python functions within the Rust future
+/// via [`into_future`] (new behaviour in `v0.15`).
+///
+/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
+/// > unfortunately fail to resolve them when called within the Rust future. This is because the
+/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
+/// >
+/// > As a workaround, you can get the `contextvars` from the current task locals using
+/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
+/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
+/// > synchronous function, and restore the previous context when it returns or raises an exception.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::tokio::future_into_py(py, async move {
+///         tokio::time::sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::future_into_py::<TokioRuntime, _, T>(py, fut)
+}
+
+/// Convert a Rust Future that fails with a Rust error into a Python awaitable
+///
+/// The error can be any error that converts into a `Box<dyn Error + Send + Sync>`, so async fns of
+/// the application don't need to convert their errors into a `PyErr` first. See
+/// [`generic::try_future_into_py_with_locals`] for how the error is raised, and
+/// [`anyhow_future_into_py`] for futures that fail with an `anyhow::Error`.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// async fn read_config(path: String) -> std::io::Result<String> {
+///     tokio::task::spawn_blocking(move || std::fs::read_to_string(path)).await?
+/// }
+///
+/// /// Raises `FileNotFoundError` if there's no such file
+/// #[pyfunction]
+/// fn load_config(py: Python, path: String) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::try_future_into_py(py, read_config(path))
+/// }
+/// ```
+pub fn try_future_into_py<F, T, E>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+{
+    generic::try_future_into_py::<TokioRuntime, _, T, E>(py, fut)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>anyhow</code></span> Convert a Rust Future that fails with an `anyhow::Error` into a Python awaitable
+///
+/// The error goes through the [error translator](crate::err::set_error_translator) as the error
+/// that the `anyhow::Error` wraps. See [`generic::anyhow_future_into_py_with_locals`] for how the
+/// error is raised.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # #[cfg(feature = "anyhow")]
+/// async fn read_config(path: String) -> anyhow::Result<String> {
+///     use anyhow::Context;
+///
+///     let read = path.clone();
+///     let config = tokio::task::spawn_blocking(move || std::fs::read_to_string(read))
+///         .await?
+///         .with_context(|| format!("can't read {}", path))?;
+///     Ok(config)
+/// }
+///
+/// /// Raises `FileNotFoundError` if there's no such file
+/// # #[cfg(feature = "anyhow")]
+/// #[pyfunction]
+/// fn load_config(py: Python, path: String) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::anyhow_future_into_py(py, read_config(path))
+/// }
+/// ```
+#[cfg(feature = "anyhow")]
+pub fn anyhow_future_into_py<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = anyhow::Result<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::anyhow_future_into_py::<TokioRuntime, _, T>(py, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable that can't be cancelled from Python, with the
+/// given task locals
+///
+/// Returns a `(shielded, handle)` pair. See [`generic::shielded_future_into_py_with_locals`] for
+/// more details.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+pub fn shielded_future_into_py_with_locals<F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<(Bound<PyAny>, Bound<PyAny>)>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::shielded_future_into_py_with_locals::<TokioRuntime, F, T>(py, locals, fut)
+}
+
+/// Convert a Rust Future into a Python awaitable that can't be cancelled from Python
 ///
-/// After this function returns, the event loop can be resumed with [`run_until_complete`]
+/// Returns a `(shielded, handle)` pair. Cancelling `shielded` doesn't stop the Rust future, and
+/// `handle` can still be awaited for its result. See
+/// [`generic::shielded_future_into_py_with_locals`] for more details.
 ///
 /// # Arguments
-/// * `event_loop` - The Python event loop that should run the future
-/// * `fut` - The future to drive to completion
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
 ///
 /// # Examples
 ///
 /// ```
-/// # use std::time::Duration;
-/// #
-/// # use pyo3::prelude::*;
-/// #
-/// # pyo3::prepare_freethreaded_python();
-/// # Python::with_gil(|py| -> PyResult<()> {
-/// # let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
-/// pyo3_async_runtimes::tokio::run_until_complete(event_loop, async move {
-///     tokio::time::sleep(Duration::from_secs(1)).await;
-///     Ok(())
-/// })?;
-/// # Ok(())
-/// # }).unwrap();
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Commit that runs to completion even if the caller gives up on it
+/// #[pyfunction]
+/// fn commit(py: Python) -> PyResult<Bound<PyAny>> {
+///     let (shielded, _handle) = pyo3_async_runtimes::tokio::shielded_future_into_py(py, async {
+///         tokio::time::sleep(Duration::from_millis(100)).await;
+///         Ok(())
+///     })?;
+///
+///     Ok(shielded)
+/// }
 /// ```
-pub fn run_until_complete<F, T>(event_loop: Bound<PyAny>, fut: F) -> PyResult<T>
+pub fn shielded_future_into_py<F, T>(py: Python, fut: F) -> PyResult<(Bound<PyAny>, Bound<PyAny>)>
 where
     F: Future<Output = PyResult<T>> + Send + 'static,
-    T: Send + Sync + 'static,
+    T: IntoPy<PyObject>,
 {
-    generic::run_until_complete::<TokioRuntime, _, T>(&event_loop, fut)
+    generic::shielded_future_into_py::<TokioRuntime, F, T>(py, fut)
 }
 
-/// Run the event loop until the given Future completes
+/// Convert a Rust Future that reacts to cancellation into a Python awaitable with the given task
+/// locals
+///
+/// The [`CancelHandle`] passed to `f` fires when the Python awaitable is cancelled. See
+/// [`generic::cancellable_future_into_py_with_locals`] for more details.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `f` - The function that creates the Rust future from a [`CancelHandle`]
+pub fn cancellable_future_into_py_with_locals<F, Fut, T>(
+    py: Python,
+    locals: TaskLocals,
+    f: F,
+) -> PyResult<Bound<PyAny>>
+where
+    F: FnOnce(CancelHandle) -> Fut,
+    Fut: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::cancellable_future_into_py_with_locals::<TokioRuntime, F, Fut, T>(py, locals, f)
+}
+
+/// Convert a Rust Future that reacts to cancellation into a Python awaitable
+///
+/// The [`CancelHandle`] passed to `f` fires when the Python awaitable is cancelled, and the
+/// future keeps running so that it can clean up.
 ///
 /// # Arguments
 /// * `py` - The current PyO3 GIL guard
-/// * `fut` - The future to drive to completion
+/// * `f` - The function that creates the Rust future from a [`CancelHandle`]
 ///
 /// # Examples
 ///
-/// ```no_run
-/// # use std::time::Duration;
-/// #
-/// # use pyo3::prelude::*;
-/// #
-/// fn main() {
-///     Python::with_gil(|py| {
-///         pyo3_async_runtimes::tokio::run(py, async move {
-///             tokio::time::sleep(Duration::from_secs(1)).await;
-///             Ok(())
-///         })
-///         .map_err(|e| {
-///             e.print_and_set_sys_last_vars(py);
-///         })
-///         .unwrap();
+/// ```
+/// use std::time::Duration;
+///
+/// use futures::future::{self, Either};
+/// use pyo3::prelude::*;
+///
+/// /// Sleep that reports whether it was interrupted
+/// #[pyfunction]
+/// fn interruptible_sleep<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::tokio::cancellable_future_into_py(py, move |handle| async move {
+///         let sleep = Box::pin(tokio::time::sleep(Duration::from_secs(secs)));
+///
+///         match future::select(sleep, Box::pin(handle.cancelled())).await {
+///             Either::Left(_) => Ok(true),
+///             Either::Right(_) => {
+///                 println!("sleep was cancelled");
+///                 Ok(false)
+///             }
+///         }
 ///     })
 /// }
 /// ```
-pub fn run<F, T>(py: Python, fut: F) -> PyResult<T>
+pub fn cancellable_future_into_py<F, Fut, T>(py: Python, f: F) -> PyResult<Bound<PyAny>>
 where
-    F: Future<Output = PyResult<T>> + Send + 'static,
-    T: Send + Sync + 'static,
+    F: FnOnce(CancelHandle) -> Fut,
+    Fut: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
 {
-    generic::run::<TokioRuntime, F, T>(py, fut)
+    generic::cancellable_future_into_py::<TokioRuntime, F, Fut, T>(py, f)
 }
 
-/// Convert a Rust Future into a Python awaitable
-///
-/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
-/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
-///
-/// Python `contextvars` are preserved when calling async Python functions within the Rust future
-/// via [`into_future`] (new behaviour in `v0.15`).
+/// Convert a Rust Future into a Python awaitable that doesn't need an event loop
 ///
-/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
-/// > unfortunately fail to resolve them when called within the Rust future. This is because the
-/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
-/// >
-/// > As a workaround, you can get the `contextvars` from the current task locals using
-/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
-/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
-/// > synchronous function, and restore the previous context when it returns or raises an exception.
+/// See [`generic::future_into_awaitable`] for how the returned object is awaited and cancelled.
 ///
 /// # Arguments
-/// * `py` - PyO3 GIL guard
-/// * `locals` - The task locals for the given future
+/// * `py` - The current PyO3 GIL guard
 /// * `fut` - The Rust future to be converted
 ///
 /// # Examples
@@ -301,52 +1100,75 @@ where
 ///
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable sleep function
+/// /// Sleep function that can be awaited under any coroutine scheduler
 /// #[pyfunction]
 /// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
 ///     let secs = secs.extract()?;
-///     pyo3_async_runtimes::tokio::future_into_py_with_locals(
-///         py,
-///         pyo3_async_runtimes::tokio::get_current_locals(py)?,
-///         async move {
-///             tokio::time::sleep(Duration::from_secs(secs)).await;
-///             Python::with_gil(|py| Ok(py.None()))
-///         }
-///     )
+///     pyo3_async_runtimes::tokio::future_into_awaitable(py, async move {
+///         tokio::time::sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
 /// }
 /// ```
-pub fn future_into_py_with_locals<F, T>(
-    py: Python,
-    locals: TaskLocals,
-    fut: F,
-) -> PyResult<Bound<PyAny>>
+pub fn future_into_awaitable<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
 where
     F: Future<Output = PyResult<T>> + Send + 'static,
     T: IntoPy<PyObject>,
 {
-    generic::future_into_py_with_locals::<TokioRuntime, F, T>(py, locals, fut)
+    generic::future_into_awaitable::<TokioRuntime, _, T>(py, fut)
 }
 
-/// Convert a Rust Future into a Python awaitable
+/// Aborts the task when the Python side stops waiting for it
+struct AbortOnDrop<T>(task::JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Convert a `JoinHandle` of a task that was already spawned on the runtime into an
+/// `asyncio.Future` with the given task locals
 ///
-/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
-/// the Rust future will be cancelled as well (new behaviour in `v0.15`).
+/// Awaiting the returned future waits for the task to finish. A task that panicked raises
+/// [`RustPanic`](crate::err::RustPanic) (unless [configured](crate::err::set_abort_error)
+/// otherwise) and a task that was aborted raises `asyncio.CancelledError` (unless
+/// [translated](crate::err::set_error_translator) otherwise). Cancelling the `asyncio.Future` aborts the task.
 ///
-/// Python `contextvars` are preserved when calling async Python functions within the Rust future
-/// via [`into_future`] (new behaviour in `v0.15`).
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals holding the event loop to resolve the future on
+/// * `handle` - The handle of the task
+pub fn join_handle_into_py_with_locals<T>(
+    py: Python,
+    locals: TaskLocals,
+    handle: task::JoinHandle<PyResult<T>>,
+) -> PyResult<Bound<PyAny>>
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    generic::completion_into_py::<TokioRuntime, _, T>(py, locals, async move {
+        let mut handle = AbortOnDrop(handle);
+
+        match (&mut handle.0).await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => Err(abort_error(AbortReason::Panic(e.into_panic()))),
+            Err(e) => Err(translate_error_or(e, |py, _| {
+                asyncio_exception(py, "CancelledError")
+            })),
+        }
+    })
+}
+
+/// Convert a `JoinHandle` of a task that was already spawned on the runtime into an
+/// `asyncio.Future`
 ///
-/// > Although `contextvars` are preserved for async Python functions, synchronous functions will
-/// > unfortunately fail to resolve them when called within the Rust future. This is because the
-/// > function is being called from a Rust thread, not inside an actual Python coroutine context.
-/// >
-/// > As a workaround, you can get the `contextvars` from the current task locals using
-/// > [`get_current_locals`] and [`TaskLocals::context`](`crate::TaskLocals::context`), then wrap your
-/// > synchronous function in a call to `contextvars.Context.run`. This will set the context, call the
-/// > synchronous function, and restore the previous context when it returns or raises an exception.
+/// This lets Rust code own the task while Python only waits for it to complete. See
+/// [`join_handle_into_py_with_locals`] for how errors and cancellation are handled.
 ///
 /// # Arguments
 /// * `py` - The current PyO3 GIL guard
-/// * `fut` - The Rust future to be converted
+/// * `handle` - The handle of the task
 ///
 /// # Examples
 ///
@@ -355,22 +1177,105 @@ where
 ///
 /// use pyo3::prelude::*;
 ///
-/// /// Awaitable sleep function
+/// /// Start a job in the background and hand Python a future for its completion
+/// #[pyfunction]
+/// fn start_job(py: Python) -> PyResult<Bound<PyAny>> {
+///     let handle = pyo3_async_runtimes::tokio::get_runtime().spawn(async {
+///         tokio::time::sleep(Duration::from_secs(1)).await;
+///         Ok("done")
+///     });
+///
+///     pyo3_async_runtimes::tokio::join_handle_into_py(py, handle)
+/// }
+/// ```
+pub fn join_handle_into_py<T>(
+    py: Python,
+    handle: task::JoinHandle<PyResult<T>>,
+) -> PyResult<Bound<PyAny>>
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    join_handle_into_py_with_locals(py, get_current_locals(py)?, handle)
+}
+
+/// Limit the time that a Rust future may take to complete
+///
+/// This is [`tokio::time::timeout`](::tokio::time::timeout) with the `Elapsed` error mapped to
+/// `asyncio.TimeoutError`, which is also what [`crate::timeout`] raises for Python awaitables. The
+/// future is dropped when it times out. The `Elapsed` error goes through the
+/// [error translator](crate::err::set_error_translator) first, if one is set.
+///
+/// # Arguments
+/// * `duration` - How long the future may take
+/// * `fut` - The Rust future to be limited
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Sleep that gives up after a second
 /// #[pyfunction]
 /// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
 ///     let secs = secs.extract()?;
 ///     pyo3_async_runtimes::tokio::future_into_py(py, async move {
-///         tokio::time::sleep(Duration::from_secs(secs)).await;
-///         Ok(())
+///         pyo3_async_runtimes::tokio::timeout(Duration::from_secs(1), async move {
+///             tokio::time::sleep(Duration::from_secs(secs)).await;
+///             Ok(())
+///         })
+///         .await
 ///     })
 /// }
 /// ```
-pub fn future_into_py<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+pub async fn timeout<F, T>(duration: Duration, fut: F) -> PyResult<T>
 where
-    F: Future<Output = PyResult<T>> + Send + 'static,
+    F: Future<Output = PyResult<T>>,
+{
+    match ::tokio::time::timeout(duration, fut).await {
+        Ok(result) => result,
+        Err(elapsed) => Err(translate_error_or(elapsed, |py, _| {
+            asyncio_exception(py, "TimeoutError")
+        })),
+    }
+}
+
+/// Wrap a Rust async function into a Python callable that returns a coroutine
+///
+/// See [`generic::py_async_callback`] for how the callable is called and awaited.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - The function called with the arguments of each call
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Async handler that greets the name it's called with after a short delay
+/// #[pyfunction]
+/// fn greeter(py: Python) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::py_async_callback(py, |args, _kwargs| {
+///         let name: String = args.get_item(0)?.extract()?;
+///
+///         Ok(async move {
+///             tokio::time::sleep(Duration::from_millis(10)).await;
+///             Ok(format!("hello, {}", name))
+///         })
+///     })
+/// }
+/// ```
+pub fn py_async_callback<F, Fut, T>(py: Python, f: F) -> PyResult<Bound<PyAny>>
+where
+    F: Fn(&Bound<PyTuple>, Option<&Bound<PyDict>>) -> PyResult<Fut> + Send + Sync + 'static,
+    Fut: Future<Output = PyResult<T>> + Send + 'static,
     T: IntoPy<PyObject>,
 {
-    generic::future_into_py::<TokioRuntime, _, T>(py, fut)
+    generic::py_async_callback::<TokioRuntime, F, Fut, T>(py, f)
 }
 
 /// Convert a `!Send` Rust Future into a Python awaitable
@@ -539,10 +1444,101 @@ where
 #[allow(deprecated)]
 pub fn local_future_into_py<F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
 where
-    F: Future<Output = PyResult<T>> + 'static,
+    F: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::local_future_into_py::<TokioRuntime, _, T>(py, fut)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-uring-runtime</code></span> Convert a `!Send` Rust Future into a Python awaitable
+///
+/// Unlike [`future_into_py_with_locals`], the future is created by `f` on the `tokio-uring`
+/// runtime thread, so it is free to use `tokio-uring`'s `!Send` resources across await points.
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `f` - Creates the Rust future to be converted on the runtime thread
+///
+/// # Panics
+///
+/// Panics if the runtime hasn't been started with [`init_uring`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable function that reads a file with io_uring
+/// #[pyfunction]
+/// fn read_file(py: Python, path: String) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::pinned_future_into_py_with_locals(
+///         py,
+///         pyo3_async_runtimes::tokio::get_current_locals(py)?,
+///         move || async move {
+///             let file = tokio_uring::fs::File::open(path).await?;
+///             let (res, buf) = file.read_at(vec![0; 4096], 0).await;
+///             Ok(buf[..res?].to_vec())
+///         }
+///     )
+/// }
+/// ```
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+pub fn pinned_future_into_py_with_locals<F, Fut, T>(
+    py: Python,
+    locals: TaskLocals,
+    f: F,
+) -> PyResult<Bound<PyAny>>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = PyResult<T>> + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::pinned_future_into_py_with_locals::<TokioRuntime, F, Fut, T>(py, locals, f)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-uring-runtime</code></span> Convert a `!Send` Rust Future into a Python awaitable
+///
+/// Unlike [`future_into_py`], the future is created by `f` on the `tokio-uring` runtime thread,
+/// so it is free to use `tokio-uring`'s `!Send` resources across await points.
+///
+/// If the `asyncio.Future` returned by this conversion is cancelled via `asyncio.Future.cancel`,
+/// the Rust future will be cancelled as well.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `f` - Creates the Rust future to be converted on the runtime thread
+///
+/// # Panics
+///
+/// Panics if the runtime hasn't been started with [`init_uring`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable function that reads a file with io_uring
+/// #[pyfunction]
+/// fn read_file(py: Python, path: String) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::pinned_future_into_py(py, move || async move {
+///         let file = tokio_uring::fs::File::open(path).await?;
+///         let (res, buf) = file.read_at(vec![0; 4096], 0).await;
+///         Ok(buf[..res?].to_vec())
+///     })
+/// }
+/// ```
+#[cfg(all(feature = "tokio-uring-runtime", target_os = "linux"))]
+pub fn pinned_future_into_py<F, Fut, T>(py: Python, f: F) -> PyResult<Bound<PyAny>>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = PyResult<T>> + 'static,
     T: IntoPy<PyObject>,
 {
-    generic::local_future_into_py::<TokioRuntime, _, T>(py, fut)
+    generic::pinned_future_into_py::<TokioRuntime, F, Fut, T>(py, f)
 }
 
 /// Convert a Python `awaitable` into a Rust Future
@@ -593,12 +1589,74 @@ where
 ///     Ok(())
 /// }
 /// ```
-pub fn into_future(
-    awaitable: Bound<PyAny>,
-) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+pub fn into_future(awaitable: Bound<PyAny>) -> PyResult<PyFuture> {
     generic::into_future::<TokioRuntime>(awaitable)
 }
 
+/// Convert a Python `awaitable` into a Rust Future resolving to how it finished
+///
+/// The future resolves to a [`PyOutcome`](crate::PyOutcome) that tells a cancelled awaitable apart
+/// from one that raised. See
+/// [`into_future_result_with_locals`](crate::into_future_result_with_locals) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_result(awaitable: Bound<PyAny>) -> PyResult<PyOutcomeFuture> {
+    generic::into_future_result::<TokioRuntime>(awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future resolving to its result extracted into `T`
+///
+/// The result is extracted on the event loop once the awaitable is done, so no extra
+/// `Python::with_gil` is needed after awaiting the future. See
+/// [`into_future_typed_with_locals`](crate::into_future_typed_with_locals) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_typed<T>(awaitable: Bound<PyAny>) -> PyResult<PyFuture<T>>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    generic::into_future_typed::<TokioRuntime, T>(awaitable)
+}
+
+/// Convert a Python `awaitable` into a Rust Future that steps it from the runtime
+///
+/// The awaitable isn't wrapped in an `asyncio.Task`, and runs outside of the event loop. See
+/// [`into_future_polled_with_locals`](crate::into_future_polled_with_locals) for what it can
+/// await.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be converted
+pub fn into_future_polled(awaitable: Bound<PyAny>) -> PyResult<PyPolledFuture> {
+    generic::into_future_polled::<TokioRuntime>(awaitable)
+}
+
+/// Run a synchronous Python function in an executor
+///
+/// The function runs in the executor of the current task locals, or in the event loop's default
+/// executor. See [`run_in_executor_with_locals`](crate::run_in_executor_with_locals) for more
+/// details.
+///
+/// # Arguments
+/// * `func` - The synchronous Python function to be called
+/// * `args` - The positional arguments for `func`
+pub fn run_in_executor(func: Bound<PyAny>, args: impl IntoPy<Py<PyTuple>>) -> PyResult<PyFuture> {
+    generic::run_in_executor::<TokioRuntime>(func, args)
+}
+
+/// Spawn a Python `awaitable` as a task and get a handle to it
+///
+/// The returned [`PyTask`] can cancel the task, check whether it's done and be
+/// awaited for the result of the task, extracted into `T`. See
+/// [`spawn_py_with_locals`](crate::spawn_py_with_locals) for more details.
+///
+/// # Arguments
+/// * `awaitable` - The Python `awaitable` to be spawned
+pub fn spawn_py<T>(awaitable: Bound<PyAny>) -> PyResult<PyTask<T>> {
+    generic::spawn_py::<TokioRuntime, T>(awaitable)
+}
+
 /// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream
 ///
 /// **This API is marked as unstable** and is only available when the
@@ -607,7 +1665,7 @@ pub fn into_future(
 ///
 /// # Arguments
 /// * `locals` - The current task locals
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```
@@ -667,7 +1725,7 @@ pub fn into_stream_with_locals_v1(
 /// stability guarantees, and could be changed or removed at any time.
 ///
 /// # Arguments
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```
@@ -724,7 +1782,7 @@ pub fn into_stream_v1(
 ///
 /// # Arguments
 /// * `locals` - The current task locals
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```
@@ -784,7 +1842,7 @@ pub fn into_stream_with_locals_v2(
 /// stability guarantees, and could be changed or removed at any time.
 ///
 /// # Arguments
-/// * `gen` - The Python async generator to be converted
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
 ///
 /// # Examples
 /// ```
@@ -832,3 +1890,338 @@ pub fn into_stream_v2(
 ) -> PyResult<impl futures::Stream<Item = PyObject> + 'static> {
     generic::into_stream_v2::<TokioRuntime>(gen)
 }
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream of extracted items
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`generic::into_stream_typed_with_locals`] for how the items are extracted.
+///
+/// # Arguments
+/// * `locals` - The current task locals
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_typed_with_locals<T>(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyResult<T>> + 'static>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    generic::into_stream_typed_with_locals::<TokioRuntime, T>(locals, gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async generator into a stream of extracted items
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`generic::into_stream_typed_with_locals`] for how the items are extracted.
+///
+/// # Arguments
+/// * `gen` - The Python async generator (or any other async iterable) to be converted
+///
+/// # Examples
+/// ```
+/// use pyo3::prelude::*;
+/// use futures::TryStreamExt;
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # #[cfg(all(feature = "unstable-streams", feature = "attributes"))]
+/// # #[pyo3_async_runtimes::tokio::main]
+/// # async fn main() -> PyResult<()> {
+/// let stream = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::tokio::into_stream_typed::<i32>(test_mod.call_method0("gen")?)
+/// })?;
+///
+/// let vals = stream.try_collect::<Vec<i32>>().await?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # #[cfg(not(all(feature = "unstable-streams", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_typed<T>(
+    gen: Bound<'_, PyAny>,
+) -> PyResult<impl futures::Stream<Item = PyResult<T>> + 'static>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    generic::into_stream_typed::<TokioRuntime, T>(gen)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async iterator into a stream, keeping several items in flight
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`generic::into_stream_prefetch_with_locals`] for which iterators support prefetching.
+///
+/// # Arguments
+/// * `locals` - The current task locals
+/// * `gen` - The Python async iterable to be converted
+/// * `prefetch` - The maximum number of `__anext__` calls in flight, at least 1
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_prefetch_with_locals(
+    locals: TaskLocals,
+    gen: Bound<'_, PyAny>,
+    prefetch: usize,
+) -> PyResult<impl futures::Stream<Item = PyObject> + 'static> {
+    generic::into_stream_prefetch_with_locals::<TokioRuntime>(locals, gen, prefetch)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert an async iterator into a stream, keeping several items in flight
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`generic::into_stream_prefetch_with_locals`] for which iterators support prefetching.
+///
+/// # Arguments
+/// * `gen` - The Python async iterable to be converted
+/// * `prefetch` - The maximum number of `__anext__` calls in flight, at least 1
+#[cfg(feature = "unstable-streams")]
+pub fn into_stream_prefetch(
+    gen: Bound<'_, PyAny>,
+    prefetch: usize,
+) -> PyResult<impl futures::Stream<Item = PyObject> + 'static> {
+    generic::into_stream_prefetch::<TokioRuntime>(gen, prefetch)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Forward the items of an async iterable into a sink
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`generic::forward_into_sink_with_locals`] for how items are buffered and errors are
+/// handled.
+///
+/// # Arguments
+/// * `iterable` - The Python async iterable to be consumed
+/// * `sink` - The Rust sink that receives the items
+/// * `buffer` - The number of items to buffer in front of the sink
+///
+/// # Examples
+/// ```
+/// use pyo3::{exceptions::PyRuntimeError, prelude::*};
+/// use futures::{SinkExt, StreamExt};
+///
+/// const TEST_MOD: &str = r#"
+/// import asyncio
+///
+/// async def gen():
+///     for i in range(10):
+///         await asyncio.sleep(0.1)
+///         yield i
+/// "#;
+///
+/// # #[cfg(all(feature = "unstable-streams", feature = "attributes"))]
+/// # #[pyo3_async_runtimes::tokio::main]
+/// # async fn main() -> PyResult<()> {
+/// let (tx, rx) = futures::channel::mpsc::channel::<i32>(10);
+///
+/// let forward = Python::with_gil(|py| {
+///     let test_mod = PyModule::from_code_bound(
+///         py,
+///         TEST_MOD,
+///         "test_rust_coroutine/test_mod.py",
+///         "test_mod",
+///     )?;
+///
+///     pyo3_async_runtimes::tokio::forward_into_sink(
+///         test_mod.call_method0("gen")?,
+///         tx.sink_map_err(|e| PyRuntimeError::new_err(e.to_string())),
+///         0,
+///     )
+/// })?;
+///
+/// let (forwarded, vals) = futures::join!(forward, rx.collect::<Vec<i32>>());
+/// forwarded?;
+///
+/// assert_eq!((0..10).collect::<Vec<i32>>(), vals);
+///
+/// Ok(())
+/// # }
+/// # #[cfg(not(all(feature = "unstable-streams", feature = "attributes")))]
+/// # fn main() {}
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn forward_into_sink<T, S>(
+    iterable: Bound<'_, PyAny>,
+    sink: S,
+    buffer: usize,
+) -> PyResult<impl Future<Output = PyResult<()>> + Send + 'static>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+    S: futures::Sink<T> + Send + 'static,
+    S::Error: Into<PyErr>,
+{
+    generic::forward_into_sink::<TokioRuntime, T, S>(iterable, sink, buffer)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert a stream into a Python async iterator with manual
+/// specification of task locals.
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`generic::stream_into_py_with_locals`] for how the stream is driven.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the futures returned by `__anext__`
+/// * `stream` - The Rust stream to be converted
+#[cfg(feature = "unstable-streams")]
+pub fn stream_into_py_with_locals<S, T>(
+    py: Python,
+    locals: TaskLocals,
+    stream: S,
+) -> PyResult<Bound<PyAny>>
+where
+    S: futures::Stream<Item = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::stream_into_py_with_locals::<TokioRuntime, S, T>(py, locals, stream)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert a stream into a Python async iterator
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// The stream is only polled while Python is waiting on `__anext__`, see
+/// [`generic::stream_into_py_with_locals`].
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `stream` - The Rust stream to be converted
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use futures::StreamExt;
+/// use pyo3::prelude::*;
+///
+/// /// Async iterator counting to `n`, one number per second
+/// # #[cfg(feature = "unstable-streams")]
+/// #[pyfunction]
+/// fn count_to(py: Python, n: u64) -> PyResult<Bound<PyAny>> {
+///     let stream = futures::stream::iter(0..n).then(|i| async move {
+///         tokio::time::sleep(Duration::from_secs(1)).await;
+///         PyResult::Ok(i)
+///     });
+///
+///     pyo3_async_runtimes::tokio::stream_into_py(py, stream)
+/// }
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn stream_into_py<S, T>(py: Python, stream: S) -> PyResult<Bound<PyAny>>
+where
+    S: futures::Stream<Item = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    generic::stream_into_py::<TokioRuntime, S, T>(py, stream)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert a stream of results into a Python async iterator
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`generic::try_stream_into_py_with_locals`] for how errors are raised.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the futures returned by `__anext__`
+/// * `stream` - The Rust stream to be converted
+/// * `on_error` - What to do after an error
+#[cfg(feature = "unstable-streams")]
+pub fn try_stream_into_py_with_locals<S, T, E>(
+    py: Python,
+    locals: TaskLocals,
+    stream: S,
+    on_error: generic::OnError,
+) -> PyResult<Bound<PyAny>>
+where
+    S: futures::Stream<Item = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: Into<PyErr>,
+{
+    generic::try_stream_into_py_with_locals::<TokioRuntime, S, T, E>(py, locals, stream, on_error)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>unstable-streams</code></span> Convert a stream of results into a Python async iterator
+///
+/// **This API is marked as unstable** and is only available when the
+/// `unstable-streams` crate feature is enabled. This comes with no
+/// stability guarantees, and could be changed or removed at any time.
+///
+/// See [`generic::try_stream_into_py_with_locals`] for how errors are raised.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `stream` - The Rust stream to be converted
+/// * `on_error` - What to do after an error
+///
+/// # Examples
+/// ```
+/// use futures::StreamExt;
+/// use pyo3::{exceptions::PyValueError, prelude::*};
+///
+/// /// Parse numbers until the first one that isn't valid
+/// # #[cfg(feature = "unstable-streams")]
+/// #[pyfunction]
+/// fn parse_all(py: Python, lines: Vec<String>) -> PyResult<Bound<PyAny>> {
+///     let stream = futures::stream::iter(lines).map(|line| {
+///         line.parse::<i64>()
+///             .map_err(|e| PyValueError::new_err(e.to_string()))
+///     });
+///
+///     pyo3_async_runtimes::tokio::try_stream_into_py(
+///         py,
+///         stream,
+///         pyo3_async_runtimes::generic::OnError::Abort,
+///     )
+/// }
+/// ```
+#[cfg(feature = "unstable-streams")]
+pub fn try_stream_into_py<S, T, E>(
+    py: Python,
+    stream: S,
+    on_error: generic::OnError,
+) -> PyResult<Bound<PyAny>>
+where
+    S: futures::Stream<Item = Result<T, E>> + Send + 'static,
+    T: IntoPy<PyObject>,
+    E: Into<PyErr>,
+{
+    generic::try_stream_into_py::<TokioRuntime, S, T, E>(py, stream, on_error)
+}
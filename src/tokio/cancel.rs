@@ -0,0 +1,153 @@
+use std::future::Future;
+
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+};
+use pyo3::prelude::*;
+use tokio_util::sync::CancellationToken;
+
+use crate::{dump_err, TaskLocals};
+
+/// Trips the token when the linked future is cancelled, and stops watching the token once the
+/// future is done either way
+#[pyclass]
+struct TokenLink {
+    token: CancellationToken,
+    done_tx: Option<oneshot::Sender<()>>,
+}
+
+#[pymethods]
+impl TokenLink {
+    fn __call__(&mut self, fut: &Bound<PyAny>) -> PyResult<()> {
+        if fut.call_method0("cancelled")?.is_truthy()? {
+            self.token.cancel();
+        }
+
+        self.done_tx.take();
+        Ok(())
+    }
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-cancellation</code></span> Link an `asyncio.Future` or `asyncio.Task` to a `CancellationToken`
+///
+/// The link goes both ways:
+///
+/// - Cancelling `token` cancels `fut` on its event loop.
+/// - If `fut` is cancelled (i.e. awaiting it raises `asyncio.CancelledError`), `token` is
+///   cancelled as well.
+///
+/// A token can be linked to any number of futures, so that a single cancellation signal reaches
+/// both the Rust and the Python side of an application. The link is removed once `fut` is done.
+///
+/// This can be called from any thread.
+///
+/// # Arguments
+/// * `fut` - The `asyncio.Future` or `asyncio.Task` to be linked
+/// * `token` - The token to be linked
+pub fn link_cancellation(fut: &Bound<PyAny>, token: CancellationToken) -> PyResult<()> {
+    let (done_tx, done_rx) = oneshot::channel::<()>();
+    let event_loop = fut.call_method0("get_loop")?;
+
+    // add_done_callback isn't threadsafe
+    event_loop.call_method1(
+        "call_soon_threadsafe",
+        (
+            fut.getattr("add_done_callback")?,
+            TokenLink {
+                token: token.clone(),
+                done_tx: Some(done_tx),
+            },
+        ),
+    )?;
+
+    let fut = PyObject::from(fut.clone());
+    let event_loop = PyObject::from(event_loop);
+
+    super::get_runtime().spawn(async move {
+        // the sender is dropped once the future is done
+        if let Either::Left(_) = future::select(Box::pin(token.cancelled()), done_rx).await {
+            Python::with_gil(|py| {
+                let _ = fut
+                    .bind(py)
+                    .getattr("cancel")
+                    .and_then(|cancel| {
+                        event_loop
+                            .bind(py)
+                            .call_method1("call_soon_threadsafe", (cancel,))
+                    })
+                    .map_err(dump_err(py));
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-cancellation</code></span> Convert a Rust Future into a Python awaitable linked to a `CancellationToken`
+///
+/// This works like [`future_into_py_with_locals`](super::future_into_py_with_locals), and links
+/// the returned `asyncio.Future` to `token` with [`link_cancellation`]. Cancelling the token
+/// cancels the awaitable and drops the Rust future.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `token` - The token to be linked
+/// * `fut` - The Rust future to be converted
+pub fn future_into_py_with_token_and_locals<F, T>(
+    py: Python,
+    locals: TaskLocals,
+    token: CancellationToken,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let py_fut = super::future_into_py_with_locals(py, locals, fut)?;
+    link_cancellation(&py_fut, token)?;
+
+    Ok(py_fut)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-cancellation</code></span> Convert a Rust Future into a Python awaitable linked to a `CancellationToken`
+///
+/// See [`future_into_py_with_token_and_locals`] for how the token is linked.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `token` - The token to be linked
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+/// use tokio_util::sync::CancellationToken;
+///
+/// /// Sleep that stops early when the service shuts down
+/// fn sleep_until_shutdown<'p>(
+///     py: Python<'p>,
+///     shutdown: CancellationToken,
+///     secs: u64,
+/// ) -> PyResult<Bound<'p, PyAny>> {
+///     pyo3_async_runtimes::tokio::future_into_py_with_token(py, shutdown, async move {
+///         tokio::time::sleep(Duration::from_secs(secs)).await;
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn future_into_py_with_token<F, T>(
+    py: Python,
+    token: CancellationToken,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_with_token_and_locals(py, super::get_current_locals(py)?, token, fut)
+}
@@ -0,0 +1,907 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    os::unix::io::{AsRawFd, RawFd},
+};
+
+#[cfg(unix)]
+use ::tokio::{
+    io::{unix::AsyncFd, Interest},
+    task::JoinHandle,
+};
+use ::tokio::{sync::Notify, time::Instant};
+use futures::channel::oneshot;
+#[cfg(not(unix))]
+use pyo3::exceptions::PyNotImplementedError;
+use pyo3::{
+    exceptions::PyRuntimeError,
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
+
+use super::get_runtime;
+use crate::{
+    asyncio,
+    completions::{self, CompletionQueue, DirectLoop},
+};
+
+/// The longest the loop waits without checking for signals
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+struct Timer {
+    when: f64,
+    seq: u64,
+    handle: PyObject,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    // reversed so that the BinaryHeap pops the earliest timer first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .when
+            .total_cmp(&self.when)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+enum Ready {
+    Handle(PyObject),
+    /// An fd callback, the watcher waits for the ack before checking the fd again
+    Io(Arc<PyObject>, oneshot::Sender<()>),
+    /// The results of conversions, which are set without going through `call_soon_threadsafe`
+    Completions(Arc<CompletionQueue>),
+}
+
+#[cfg(unix)]
+struct Fd(RawFd);
+
+#[cfg(unix)]
+impl AsRawFd for Fd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+#[cfg(unix)]
+type Watcher = (JoinHandle<()>, Arc<PyObject>);
+
+#[cfg(unix)]
+struct FdEntry {
+    async_fd: Arc<AsyncFd<Fd>>,
+    reader: Option<Watcher>,
+    writer: Option<Watcher>,
+}
+
+#[derive(Default)]
+struct State {
+    ready: VecDeque<Ready>,
+    timers: BinaryHeap<Timer>,
+    seq: u64,
+    running: bool,
+    stopping: bool,
+    closed: bool,
+    debug: bool,
+    exception_handler: Option<PyObject>,
+    #[cfg(unix)]
+    fds: HashMap<RawFd, FdEntry>,
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-event-loop</code></span> An `asyncio` event loop driven by the tokio runtime
+///
+/// The default `asyncio` loops block in their own selector, so a program that mixes Rust and
+/// Python async code runs two reactors side by side. This loop waits on the reactor of the runtime
+/// returned by [`get_runtime`] instead: timers are tokio timers, file descriptors registered with
+/// `add_reader` / `add_writer` are watched by tokio, and `call_soon_threadsafe` wakes the loop
+/// through a tokio `Notify`. Callbacks still run on the thread that called `run_forever`, as
+/// `asyncio` requires.
+///
+/// The conversions of this crate hand their results to the loop directly instead of through
+/// `call_soon_threadsafe`, so a Rust future that completes doesn't create a Python callback and a
+/// handle to wake up the Python task that awaits it.
+///
+/// The loop implements the scheduling part of the `asyncio` loop API (`call_soon`, `call_later`,
+/// `call_at`, `call_soon_threadsafe`, `create_future`, `create_task`, `run_forever`,
+/// `run_until_complete`, `run_in_executor`, the fd watchers, and the exception handler methods),
+/// which covers coroutines, tasks, futures, and `asyncio.sleep`. The transport and socket APIs are
+/// not implemented.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         let event_loop = Bound::new(py, pyo3_async_runtimes::tokio::EventLoop::new())?;
+///
+///         pyo3_async_runtimes::tokio::run_until_complete(event_loop.clone().into_any(), async move {
+///             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+///             Ok(())
+///         })?;
+///
+///         event_loop.call_method0("close")?;
+///         Ok(())
+///     })
+/// }
+/// ```
+///
+/// The class can also be added to a Python module and used as a loop factory, e.g.
+/// `asyncio.run(main(), loop_factory=my_module.EventLoop)` on Python 3.12+.
+#[pyclass(module = "pyo3_async_runtimes", name = "EventLoop", weakref)]
+pub struct EventLoop {
+    state: Arc<Mutex<State>>,
+    wakeup: Arc<Notify>,
+    start: Instant,
+}
+
+impl Default for EventLoop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventLoop {
+    fn check_closed(&self) -> PyResult<()> {
+        if self.state.lock().unwrap().closed {
+            Err(PyRuntimeError::new_err("Event loop is closed"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn push_ready(&self, ready: Ready) {
+        self.state.lock().unwrap().ready.push_back(ready);
+        self.wakeup.notify_one();
+    }
+
+    /// Get the handle that the completions of conversions are scheduled with
+    pub(crate) fn direct(&self) -> Arc<dyn DirectLoop> {
+        Arc::new(Direct {
+            state: self.state.clone(),
+            wakeup: self.wakeup.clone(),
+        })
+    }
+
+    fn schedule(
+        slf: &Bound<Self>,
+        callback: Bound<PyAny>,
+        args: Bound<PyTuple>,
+        context: Option<Bound<PyAny>>,
+    ) -> PyResult<PyObject> {
+        let this = slf.borrow();
+        this.check_closed()?;
+
+        let handle = asyncio(slf.py())?
+            .getattr("Handle")?
+            .call1((callback, args, slf, context))?
+            .unbind();
+        this.push_ready(Ready::Handle(handle.clone_ref(slf.py())));
+
+        Ok(handle)
+    }
+
+    fn run(&self, event_loop: &Bound<Self>) -> PyResult<()> {
+        let py = event_loop.py();
+
+        loop {
+            let now = self.time();
+
+            let batch = {
+                let mut state = self.state.lock().unwrap();
+
+                while state.timers.peek().map_or(false, |timer| timer.when <= now) {
+                    let timer = state.timers.pop().unwrap();
+                    state.ready.push_back(Ready::Handle(timer.handle));
+                }
+
+                // callbacks scheduled by this batch run in the next iteration
+                state.ready.drain(..).collect::<Vec<_>>()
+            };
+
+            for ready in batch {
+                match ready {
+                    Ready::Handle(handle) => run_handle(handle.bind(py))?,
+                    Ready::Io(handle, ack) => {
+                        run_handle(handle.bind(py))?;
+                        let _ = ack.send(());
+                    }
+                    Ready::Completions(queue) => completions::drain(&queue, event_loop.as_any())?,
+                }
+            }
+
+            let timeout = {
+                let mut state = self.state.lock().unwrap();
+
+                if state.stopping {
+                    state.stopping = false;
+                    return Ok(());
+                }
+
+                if !state.ready.is_empty() {
+                    Some(Duration::ZERO)
+                } else {
+                    state
+                        .timers
+                        .peek()
+                        .map(|timer| Duration::from_secs_f64((timer.when - self.time()).max(0.0)))
+                }
+            };
+
+            if timeout != Some(Duration::ZERO) {
+                let timeout = timeout.map_or(SIGNAL_CHECK_INTERVAL, |timeout| {
+                    timeout.min(SIGNAL_CHECK_INTERVAL)
+                });
+                let wakeup = self.wakeup.clone();
+
+                py.allow_threads(|| {
+                    get_runtime().block_on(async move {
+                        let _ = ::tokio::time::timeout(timeout, wakeup.notified()).await;
+                    })
+                });
+            }
+
+            // let Ctrl-C interrupt the loop while it's idle
+            py.check_signals()?;
+        }
+    }
+
+    #[cfg(unix)]
+    fn watch(
+        slf: &Bound<Self>,
+        fd: &Bound<PyAny>,
+        callback: Bound<PyAny>,
+        args: Bound<PyTuple>,
+        writable: bool,
+    ) -> PyResult<()> {
+        let py = slf.py();
+        let this = slf.borrow();
+        this.check_closed()?;
+
+        let fd = fileobj_to_fd(fd)?;
+        let handle = Arc::new(
+            asyncio(py)?
+                .getattr("Handle")?
+                .call1((callback, args, slf, py.None()))?
+                .unbind(),
+        );
+
+        let replaced = {
+            let mut state = this.state.lock().unwrap();
+
+            let entry = match state.fds.entry(fd) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let _guard = get_runtime().enter();
+                    let async_fd =
+                        AsyncFd::with_interest(Fd(fd), Interest::READABLE | Interest::WRITABLE)?;
+
+                    entry.insert(FdEntry {
+                        async_fd: Arc::new(async_fd),
+                        reader: None,
+                        writer: None,
+                    })
+                }
+            };
+            let task = get_runtime().spawn(watch_fd(
+                entry.async_fd.clone(),
+                writable,
+                handle.clone(),
+                this.state.clone(),
+                this.wakeup.clone(),
+            ));
+
+            if writable {
+                entry.writer.replace((task, handle))
+            } else {
+                entry.reader.replace((task, handle))
+            }
+        };
+
+        if let Some((task, handle)) = replaced {
+            task.abort();
+            handle.bind(py).call_method0("cancel")?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn unwatch(&self, py: Python, fd: &Bound<PyAny>, writable: bool) -> PyResult<bool> {
+        let fd = fileobj_to_fd(fd)?;
+
+        let removed = {
+            let mut state = self.state.lock().unwrap();
+
+            let removed = match state.fds.get_mut(&fd) {
+                Some(entry) if writable => entry.writer.take(),
+                Some(entry) => entry.reader.take(),
+                None => None,
+            };
+
+            if let Some(entry) = state.fds.get(&fd) {
+                if entry.reader.is_none() && entry.writer.is_none() {
+                    state.fds.remove(&fd);
+                }
+            }
+
+            removed
+        };
+
+        match removed {
+            Some((task, handle)) => {
+                task.abort();
+                handle.bind(py).call_method0("cancel")?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+fn run_handle(handle: &Bound<PyAny>) -> PyResult<()> {
+    if !handle.call_method0("cancelled")?.is_truthy()? {
+        // Handle._run reports exceptions to the exception handler, only BaseExceptions like
+        // KeyboardInterrupt propagate
+        handle.call_method0("_run")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn fileobj_to_fd(fileobj: &Bound<PyAny>) -> PyResult<RawFd> {
+    match fileobj.extract::<RawFd>() {
+        Ok(fd) => Ok(fd),
+        Err(_) => fileobj.call_method0("fileno")?.extract(),
+    }
+}
+
+/// Check whether the fd is still ready without blocking
+///
+/// tokio only reports edge-triggered readiness, but `asyncio` callbacks expect to be called again
+/// as long as the fd stays ready.
+#[cfg(unix)]
+fn still_ready(fd: RawFd, writable: bool) -> bool {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: if writable {
+            libc::POLLOUT
+        } else {
+            libc::POLLIN
+        },
+        revents: 0,
+    };
+
+    // SAFETY: pollfd points to exactly one valid pollfd struct
+    unsafe { libc::poll(&mut pollfd, 1, 0) > 0 }
+}
+
+#[cfg(unix)]
+async fn watch_fd(
+    async_fd: Arc<AsyncFd<Fd>>,
+    writable: bool,
+    handle: Arc<PyObject>,
+    state: Arc<Mutex<State>>,
+    wakeup: Arc<Notify>,
+) {
+    loop {
+        let guard = if writable {
+            async_fd.writable().await
+        } else {
+            async_fd.readable().await
+        };
+        let mut guard = match guard {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        state
+            .lock()
+            .unwrap()
+            .ready
+            .push_back(Ready::Io(handle.clone(), ack_tx));
+        wakeup.notify_one();
+
+        if ack_rx.await.is_err() {
+            return;
+        }
+
+        if !still_ready(async_fd.get_ref().0, writable) {
+            guard.clear_ready();
+        }
+    }
+}
+
+/// Schedules the completions of conversions on the loop, see [`completions`]
+struct Direct {
+    state: Arc<Mutex<State>>,
+    wakeup: Arc<Notify>,
+}
+
+impl DirectLoop for Direct {
+    fn schedule_drain(&self, queue: Arc<CompletionQueue>) -> PyResult<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.closed {
+                return Err(PyRuntimeError::new_err("Event loop is closed"));
+            }
+
+            state.ready.push_back(Ready::Completions(queue));
+        }
+        self.wakeup.notify_one();
+
+        Ok(())
+    }
+}
+
+/// Stops the loop once the future passed to `run_until_complete` is done
+#[pyclass]
+struct StopLoop {
+    state: Arc<Mutex<State>>,
+    wakeup: Arc<Notify>,
+}
+
+#[pymethods]
+impl StopLoop {
+    fn __call__(&self, _fut: &Bound<PyAny>) {
+        self.state.lock().unwrap().stopping = true;
+        self.wakeup.notify_one();
+    }
+}
+
+/// Completes the future returned by `run_in_executor` on the loop's thread
+#[pyclass]
+struct CompleteFuture {
+    future: PyObject,
+    result: Option<PyResult<PyObject>>,
+}
+
+#[pymethods]
+impl CompleteFuture {
+    fn __call__(&mut self, py: Python) -> PyResult<()> {
+        let future = self.future.bind(py);
+
+        if future.call_method0("cancelled")?.is_truthy()? {
+            return Ok(());
+        }
+
+        match self.result.take() {
+            Some(Ok(val)) => future.call_method1("set_result", (val,))?,
+            Some(Err(err)) => future.call_method1("set_exception", (err,))?,
+            None => return Ok(()),
+        };
+
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl EventLoop {
+    /// Create a new event loop on the tokio runtime
+    #[new]
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::default())),
+            wakeup: Arc::new(Notify::new()),
+            start: Instant::now(),
+        }
+    }
+
+    /// The loop's monotonic clock in seconds
+    pub fn time(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    #[pyo3(signature = (callback, *args, context = None))]
+    fn call_soon(
+        slf: &Bound<Self>,
+        callback: Bound<PyAny>,
+        args: Bound<PyTuple>,
+        context: Option<Bound<PyAny>>,
+    ) -> PyResult<PyObject> {
+        Self::schedule(slf, callback, args, context)
+    }
+
+    #[pyo3(signature = (callback, *args, context = None))]
+    fn call_soon_threadsafe(
+        slf: &Bound<Self>,
+        callback: Bound<PyAny>,
+        args: Bound<PyTuple>,
+        context: Option<Bound<PyAny>>,
+    ) -> PyResult<PyObject> {
+        Self::schedule(slf, callback, args, context)
+    }
+
+    #[pyo3(signature = (delay, callback, *args, context = None))]
+    fn call_later(
+        slf: &Bound<Self>,
+        delay: f64,
+        callback: Bound<PyAny>,
+        args: Bound<PyTuple>,
+        context: Option<Bound<PyAny>>,
+    ) -> PyResult<PyObject> {
+        let when = slf.borrow().time() + delay;
+        Self::call_at(slf, when, callback, args, context)
+    }
+
+    #[pyo3(signature = (when, callback, *args, context = None))]
+    fn call_at(
+        slf: &Bound<Self>,
+        when: f64,
+        callback: Bound<PyAny>,
+        args: Bound<PyTuple>,
+        context: Option<Bound<PyAny>>,
+    ) -> PyResult<PyObject> {
+        let this = slf.borrow();
+        this.check_closed()?;
+
+        let handle = asyncio(slf.py())?
+            .getattr("TimerHandle")?
+            .call1((when, callback, args, slf, context))?
+            .unbind();
+
+        {
+            let mut state = this.state.lock().unwrap();
+            state.seq += 1;
+            let seq = state.seq;
+            state.timers.push(Timer {
+                when,
+                seq,
+                handle: handle.clone_ref(slf.py()),
+            });
+        }
+        this.wakeup.notify_one();
+
+        Ok(handle)
+    }
+
+    /// Called by `TimerHandle.cancel`, cancelled timers are skipped when they come due
+    fn _timer_handle_cancelled(&self, _handle: &Bound<PyAny>) {}
+
+    fn create_future<'p>(slf: &Bound<'p, Self>) -> PyResult<Bound<'p, PyAny>> {
+        let kwargs = PyDict::new_bound(slf.py());
+        kwargs.set_item("loop", slf)?;
+
+        asyncio(slf.py())?
+            .getattr("Future")?
+            .call((), Some(&kwargs))
+    }
+
+    #[pyo3(signature = (coro, *, name = None, context = None))]
+    fn create_task<'p>(
+        slf: &Bound<'p, Self>,
+        coro: Bound<'p, PyAny>,
+        name: Option<Bound<'p, PyAny>>,
+        context: Option<Bound<'p, PyAny>>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        slf.borrow().check_closed()?;
+
+        let kwargs = PyDict::new_bound(slf.py());
+        kwargs.set_item("loop", slf)?;
+        if let Some(name) = name {
+            kwargs.set_item("name", name)?;
+        }
+        if let Some(context) = context {
+            kwargs.set_item("context", context)?;
+        }
+
+        asyncio(slf.py())?
+            .getattr("Task")?
+            .call((coro,), Some(&kwargs))
+    }
+
+    fn run_forever(slf: &Bound<Self>) -> PyResult<()> {
+        let py = slf.py();
+        let this = slf.borrow();
+        this.check_closed()?;
+
+        let events = asyncio(py)?.getattr("events")?;
+        if this.state.lock().unwrap().running {
+            return Err(PyRuntimeError::new_err(
+                "This event loop is already running",
+            ));
+        }
+        if !events.call_method0("_get_running_loop")?.is_none() {
+            return Err(PyRuntimeError::new_err(
+                "Cannot run the event loop while another loop is running",
+            ));
+        }
+
+        this.state.lock().unwrap().running = true;
+        events.call_method1("_set_running_loop", (slf,))?;
+
+        let result = this.run(slf);
+
+        {
+            let mut state = this.state.lock().unwrap();
+            state.running = false;
+            state.stopping = false;
+        }
+        events.call_method1("_set_running_loop", (py.None(),))?;
+
+        result
+    }
+
+    fn run_until_complete<'p>(
+        slf: &Bound<'p, Self>,
+        future: Bound<'p, PyAny>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let py = slf.py();
+        let this = slf.borrow();
+        this.check_closed()?;
+
+        let kwargs = PyDict::new_bound(py);
+        kwargs.set_item("loop", slf)?;
+        let future = asyncio(py)?
+            .getattr("ensure_future")?
+            .call((future,), Some(&kwargs))?;
+
+        let stop = Bound::new(
+            py,
+            StopLoop {
+                state: this.state.clone(),
+                wakeup: this.wakeup.clone(),
+            },
+        )?;
+        future.call_method1("add_done_callback", (&stop,))?;
+        drop(this);
+
+        let result = Self::run_forever(slf);
+        future.call_method1("remove_done_callback", (&stop,))?;
+        result?;
+
+        if !future.call_method0("done")?.is_truthy()? {
+            return Err(PyRuntimeError::new_err(
+                "Event loop stopped before Future completed.",
+            ));
+        }
+
+        future.call_method0("result")
+    }
+
+    fn stop(&self) {
+        self.state.lock().unwrap().stopping = true;
+        self.wakeup.notify_one();
+    }
+
+    fn is_running(&self) -> bool {
+        self.state.lock().unwrap().running
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    fn close(&self) -> PyResult<()> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.running {
+            return Err(PyRuntimeError::new_err("Cannot close a running event loop"));
+        }
+
+        state.closed = true;
+        state.ready.clear();
+        state.timers.clear();
+
+        #[cfg(unix)]
+        for (_, entry) in state.fds.drain() {
+            for (task, _) in entry.reader.into_iter().chain(entry.writer) {
+                task.abort();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_debug(&self) -> bool {
+        self.state.lock().unwrap().debug
+    }
+
+    fn set_debug(&self, enabled: bool) {
+        self.state.lock().unwrap().debug = enabled;
+    }
+
+    fn shutdown_asyncgens<'p>(slf: &Bound<'p, Self>) -> PyResult<Bound<'p, PyAny>> {
+        // async generators aren't tracked, so there's nothing to shut down
+        let future = Self::create_future(slf)?;
+        future.call_method1("set_result", (slf.py().None(),))?;
+        Ok(future)
+    }
+
+    #[pyo3(signature = (timeout = None))]
+    fn shutdown_default_executor<'p>(
+        slf: &Bound<'p, Self>,
+        timeout: Option<f64>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        // the default executor is tokio's blocking pool, which is owned by the runtime
+        let _ = timeout;
+        Self::shutdown_asyncgens(slf)
+    }
+
+    #[pyo3(signature = (executor, func, *args))]
+    fn run_in_executor<'p>(
+        slf: &Bound<'p, Self>,
+        executor: Option<Bound<'p, PyAny>>,
+        func: Bound<'p, PyAny>,
+        args: Bound<'p, PyTuple>,
+    ) -> PyResult<Bound<'p, PyAny>> {
+        let py = slf.py();
+        slf.borrow().check_closed()?;
+
+        if let Some(executor) = executor {
+            let submit_args = PyTuple::new_bound(
+                py,
+                std::iter::once(func).chain(args.iter()).collect::<Vec<_>>(),
+            );
+            let kwargs = PyDict::new_bound(py);
+            kwargs.set_item("loop", slf)?;
+
+            return asyncio(py)?.getattr("wrap_future")?.call(
+                (executor.call_method1("submit", submit_args)?,),
+                Some(&kwargs),
+            );
+        }
+
+        let future = Self::create_future(slf)?;
+        let event_loop = slf.clone().unbind();
+        let future_tx = future.clone().unbind();
+        let func = func.unbind();
+        let args = args.unbind();
+
+        get_runtime().spawn_blocking(move || {
+            Python::with_gil(|py| {
+                let result = func.bind(py).call1(args.bind(py)).map(Bound::unbind);
+                let complete = CompleteFuture {
+                    future: future_tx,
+                    result: Some(result),
+                };
+
+                if let Err(e) = event_loop
+                    .bind(py)
+                    .call_method1("call_soon_threadsafe", (complete,))
+                {
+                    e.print_and_set_sys_last_vars(py);
+                }
+            })
+        });
+
+        Ok(future)
+    }
+
+    #[pyo3(signature = (handler))]
+    fn set_exception_handler(&self, handler: Option<PyObject>) {
+        self.state.lock().unwrap().exception_handler = handler;
+    }
+
+    fn get_exception_handler(&self, py: Python) -> Option<PyObject> {
+        self.state
+            .lock()
+            .unwrap()
+            .exception_handler
+            .as_ref()
+            .map(|handler| handler.clone_ref(py))
+    }
+
+    fn default_exception_handler(&self, context: &Bound<PyDict>) -> PyResult<()> {
+        let py = context.py();
+
+        let message = match context.get_item("message")? {
+            Some(message) => message.str()?.to_string(),
+            None => "Unhandled exception in event loop".to_string(),
+        };
+        let exception = context
+            .get_item("exception")?
+            .filter(|exception| !exception.is_none());
+
+        let mut lines = vec![message];
+        for (key, value) in context.iter() {
+            let key = key.str()?.to_string();
+            if key != "message" && key != "exception" {
+                lines.push(format!("{}: {}", key, value.repr()?));
+            }
+        }
+
+        let kwargs = PyDict::new_bound(py);
+        if let Some(exception) = exception {
+            kwargs.set_item("exc_info", exception)?;
+        }
+
+        py.import_bound("logging")?
+            .call_method1("getLogger", ("asyncio",))?
+            .call_method("error", (lines.join("\n"),), Some(&kwargs))?;
+
+        Ok(())
+    }
+
+    fn call_exception_handler(slf: &Bound<Self>, context: &Bound<PyDict>) -> PyResult<()> {
+        let this = slf.borrow();
+
+        match this.get_exception_handler(slf.py()) {
+            Some(handler) => {
+                if let Err(e) = handler.bind(slf.py()).call1((slf, context)) {
+                    let handler_context = PyDict::new_bound(slf.py());
+                    handler_context.set_item("message", "Unhandled error in exception handler")?;
+                    handler_context.set_item("exception", e.into_value(slf.py()))?;
+                    handler_context.set_item("context", context)?;
+
+                    this.default_exception_handler(&handler_context)?;
+                }
+
+                Ok(())
+            }
+            None => this.default_exception_handler(context),
+        }
+    }
+
+    #[cfg(unix)]
+    #[pyo3(signature = (fd, callback, *args))]
+    fn add_reader(
+        slf: &Bound<Self>,
+        fd: &Bound<PyAny>,
+        callback: Bound<PyAny>,
+        args: Bound<PyTuple>,
+    ) -> PyResult<()> {
+        Self::watch(slf, fd, callback, args, false)
+    }
+
+    #[cfg(unix)]
+    fn remove_reader(&self, py: Python, fd: &Bound<PyAny>) -> PyResult<bool> {
+        self.unwatch(py, fd, false)
+    }
+
+    #[cfg(unix)]
+    #[pyo3(signature = (fd, callback, *args))]
+    fn add_writer(
+        slf: &Bound<Self>,
+        fd: &Bound<PyAny>,
+        callback: Bound<PyAny>,
+        args: Bound<PyTuple>,
+    ) -> PyResult<()> {
+        Self::watch(slf, fd, callback, args, true)
+    }
+
+    #[cfg(unix)]
+    fn remove_writer(&self, py: Python, fd: &Bound<PyAny>) -> PyResult<bool> {
+        self.unwatch(py, fd, true)
+    }
+
+    #[cfg(not(unix))]
+    #[pyo3(signature = (*_args))]
+    fn add_reader(&self, _args: &Bound<PyTuple>) -> PyResult<()> {
+        Err(PyNotImplementedError::new_err(
+            "fd watchers are only supported on unix",
+        ))
+    }
+
+    #[cfg(not(unix))]
+    #[pyo3(signature = (*_args))]
+    fn add_writer(&self, _args: &Bound<PyTuple>) -> PyResult<()> {
+        Err(PyNotImplementedError::new_err(
+            "fd watchers are only supported on unix",
+        ))
+    }
+}
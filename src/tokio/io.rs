@@ -0,0 +1,740 @@
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use ::tokio::{
+    io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf},
+    sync::{mpsc, watch},
+};
+use futures::{
+    future::{AbortHandle, Abortable},
+    ready,
+};
+use once_cell::sync::OnceCell;
+use pyo3::{
+    prelude::*,
+    types::{PyByteArray, PyBytes, PyDict},
+    PyTraverseError, PyVisit,
+};
+
+use super::get_runtime;
+use crate::{asyncio, dump_err, err::translate_error, into_future_with_locals, TaskLocals};
+
+const IO_GLUE: &str = r#"
+async def write(writer, data):
+    writer.write(data)
+    await writer.drain()
+
+async def shutdown(writer):
+    if writer.can_write_eof():
+        writer.write_eof()
+    else:
+        writer.close()
+        await writer.wait_closed()
+"#;
+
+/// How much is read from the Rust side at a time
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// `asyncio` transports pause the protocol above this many buffered bytes...
+const WRITE_HIGH_WATER: usize = 64 * 1024;
+
+/// ...and resume it once the buffer drains below this
+const WRITE_LOW_WATER: usize = 16 * 1024;
+
+static IO_GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
+
+fn io_glue(py: Python) -> PyResult<&Bound<PyAny>> {
+    IO_GLUE_MOD
+        .get_or_try_init(|| -> PyResult<PyObject> {
+            Ok(PyModule::from_code_bound(
+                py,
+                IO_GLUE,
+                "pyo3_asyncio/pyo3_asyncio_io_glue.py",
+                "pyo3_asyncio_io_glue",
+            )?
+            .into())
+        })
+        .map(|glue| glue.bind(py))
+}
+
+type PyFuture = Pin<Box<dyn Future<Output = PyResult<PyObject>> + Send>>;
+
+fn to_io_error(e: PyErr) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-io</code></span> A connection established in Python, as seen from Rust
+///
+/// This wraps an `asyncio.StreamReader` / `asyncio.StreamWriter` pair (as returned by
+/// `asyncio.open_connection`) in [`AsyncRead`] and [`AsyncWrite`], so that Rust protocol
+/// implementations can run over it. Reads and writes are run on the event loop of the task locals,
+/// and writes wait for `StreamWriter.drain()`, so a slow peer holds back the Rust side.
+///
+/// Shutting down the stream sends EOF if the transport supports it, and closes the writer
+/// otherwise. Dropping the stream doesn't close the writer.
+pub struct AsyncioStream {
+    locals: TaskLocals,
+    reader: PyObject,
+    writer: PyObject,
+    read: Option<PyFuture>,
+    read_buf: Vec<u8>,
+    write: Option<(usize, PyFuture)>,
+    shutdown: Option<PyFuture>,
+}
+
+impl AsyncioStream {
+    /// Wrap a `StreamReader` / `StreamWriter` pair, using the task locals of the current task
+    ///
+    /// # Arguments
+    /// * `reader` - The `asyncio.StreamReader` to read from
+    /// * `writer` - The `asyncio.StreamWriter` to write to
+    pub fn new(reader: Bound<PyAny>, writer: Bound<PyAny>) -> PyResult<Self> {
+        Ok(Self::with_locals(
+            super::get_current_locals(reader.py())?,
+            reader,
+            writer,
+        ))
+    }
+
+    /// Wrap a `StreamReader` / `StreamWriter` pair that belongs to the event loop in `locals`
+    ///
+    /// # Arguments
+    /// * `locals` - The task locals holding the event loop of the streams
+    /// * `reader` - The `asyncio.StreamReader` to read from
+    /// * `writer` - The `asyncio.StreamWriter` to write to
+    pub fn with_locals(locals: TaskLocals, reader: Bound<PyAny>, writer: Bound<PyAny>) -> Self {
+        Self {
+            locals,
+            reader: reader.unbind(),
+            writer: writer.unbind(),
+            read: None,
+            read_buf: Vec::new(),
+            write: None,
+            shutdown: None,
+        }
+    }
+
+    fn glue_future(&self, name: &str, data: Option<&[u8]>) -> io::Result<PyFuture> {
+        Python::with_gil(|py| {
+            let writer = self.writer.bind(py);
+            let coro = match data {
+                Some(data) => {
+                    io_glue(py)?.call_method1(name, (writer, PyBytes::new_bound(py, data)))?
+                }
+                None => io_glue(py)?.call_method1(name, (writer,))?,
+            };
+
+            Ok(Box::pin(into_future_with_locals(&self.locals, coro)?) as PyFuture)
+        })
+        .map_err(to_io_error)
+    }
+
+    fn poll_pending_write(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        match self.write.as_mut() {
+            Some((len, fut)) => {
+                let len = *len;
+                let result = ready!(fut.as_mut().poll(cx));
+                self.write = None;
+
+                Poll::Ready(result.map(|_| len).map_err(to_io_error))
+            }
+            None => Poll::Ready(Ok(0)),
+        }
+    }
+}
+
+impl AsyncRead for AsyncioStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        if this.read_buf.is_empty() {
+            if this.read.is_none() {
+                if buf.remaining() == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+
+                let n = buf.remaining();
+                let fut = Python::with_gil(|py| {
+                    let coro = this.reader.bind(py).call_method1("read", (n,))?;
+                    Ok(Box::pin(into_future_with_locals(&this.locals, coro)?) as PyFuture)
+                })
+                .map_err(to_io_error)?;
+
+                this.read = Some(fut);
+            }
+
+            let data = ready!(this.read.as_mut().unwrap().as_mut().poll(cx));
+            this.read = None;
+
+            let data = data.map_err(to_io_error)?;
+
+            // copy straight into the caller's buffer, read(n) never returns more than fits
+            return Python::with_gil(|py| {
+                let data = data.bind(py).downcast::<PyBytes>()?.as_bytes();
+                let n = data.len().min(buf.remaining());
+
+                buf.put_slice(&data[..n]);
+                this.read_buf.extend_from_slice(&data[n..]);
+
+                Ok(())
+            })
+            .map_err(to_io_error)
+            .into();
+        }
+
+        // an empty read means EOF, otherwise keep what doesn't fit for the next read
+        let n = this.read_buf.len().min(buf.remaining());
+        buf.put_slice(&this.read_buf[..n]);
+        this.read_buf.drain(..n);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for AsyncioStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+
+        if this.write.is_none() {
+            let fut = this.glue_future("write", Some(buf))?;
+            this.write = Some((buf.len(), fut));
+        }
+
+        this.poll_pending_write(cx)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // writes are only reported once the writer is drained, so there's nothing else to flush
+        self.poll_pending_write(cx).map_ok(|_| ())
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        ready!(this.poll_pending_write(cx))?;
+
+        if this.shutdown.is_none() {
+            this.shutdown = Some(this.glue_future("shutdown", None)?);
+        }
+
+        ready!(this.shutdown.as_mut().unwrap().as_mut().poll(cx)).map_err(to_io_error)?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-io</code></span> Buffered reader over an `asyncio.StreamReader`
+///
+/// This implements [`AsyncBufRead`], so the helpers of `tokio::io::AsyncBufReadExt` such as
+/// `read_until` and `lines` work on a connection that's owned by Python, and parsers that expect a
+/// buffered reader can run over it directly. Data is read from the `StreamReader` in chunks of up
+/// to 64 KiB on the event loop of the task locals. The end of the stream shows up as an empty
+/// buffer, like for any other `AsyncBufRead`, and errors raised by the `StreamReader` are returned
+/// as `io::Error`s wrapping the Python exception.
+pub struct AsyncioReader {
+    locals: TaskLocals,
+    reader: PyObject,
+    read: Option<PyFuture>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl AsyncioReader {
+    /// Wrap a `StreamReader`, using the task locals of the current task
+    ///
+    /// # Arguments
+    /// * `reader` - The `asyncio.StreamReader` to read from
+    pub fn new(reader: Bound<PyAny>) -> PyResult<Self> {
+        Ok(Self::with_locals(
+            super::get_current_locals(reader.py())?,
+            reader,
+        ))
+    }
+
+    /// Wrap a `StreamReader` that belongs to the event loop in `locals`
+    ///
+    /// # Arguments
+    /// * `locals` - The task locals holding the event loop of the reader
+    /// * `reader` - The `asyncio.StreamReader` to read from
+    pub fn with_locals(locals: TaskLocals, reader: Bound<PyAny>) -> Self {
+        Self {
+            locals,
+            reader: reader.unbind(),
+            read: None,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl AsyncBufRead for AsyncioReader {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        if this.pos == this.buf.len() {
+            if this.read.is_none() {
+                let fut = Python::with_gil(|py| {
+                    let coro = this
+                        .reader
+                        .bind(py)
+                        .call_method1("read", (READ_CHUNK_SIZE,))?;
+                    Ok(Box::pin(into_future_with_locals(&this.locals, coro)?) as PyFuture)
+                })
+                .map_err(to_io_error)?;
+
+                this.read = Some(fut);
+            }
+
+            let data = ready!(this.read.as_mut().unwrap().as_mut().poll(cx));
+            this.read = None;
+
+            let data = data.map_err(to_io_error)?;
+            this.buf.clear();
+            this.pos = 0;
+            // an empty chunk means EOF
+            Python::with_gil(|py| {
+                this.buf
+                    .extend_from_slice(data.bind(py).downcast::<PyBytes>()?.as_bytes());
+                Ok::<_, PyErr>(())
+            })
+            .map_err(to_io_error)?;
+        }
+
+        Poll::Ready(Ok(&this.buf[this.pos..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.pos = (this.pos + amt).min(this.buf.len());
+    }
+}
+
+impl AsyncRead for AsyncioReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let n = available.len().min(buf.remaining());
+
+        buf.put_slice(&available[..n]);
+        self.consume(n);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+enum WriteOp {
+    Data(Vec<u8>),
+    Eof,
+    Close,
+}
+
+struct WriteState {
+    buffered: usize,
+    paused: bool,
+}
+
+/// State shared between the transport and the tasks that drive the Rust connection
+struct Connection {
+    event_loop: PyObject,
+    protocol: PyObject,
+    write_state: Mutex<WriteState>,
+    lost: AtomicBool,
+}
+
+impl Connection {
+    /// Call a protocol method on the event loop's thread
+    fn call_protocol(&self, method: &str, arg: Option<PyObject>) {
+        Python::with_gil(|py| {
+            let _ = self
+                .protocol
+                .bind(py)
+                .getattr(method)
+                .and_then(|method| match arg {
+                    Some(arg) => self
+                        .event_loop
+                        .bind(py)
+                        .call_method1("call_soon_threadsafe", (method, arg)),
+                    None => self
+                        .event_loop
+                        .bind(py)
+                        .call_method1("call_soon_threadsafe", (method,)),
+                })
+                .map_err(dump_err(py));
+        });
+    }
+
+    /// `connection_lost` is called exactly once, by whichever side finishes first
+    fn lose(&self, err: Option<io::Error>) {
+        if self.lost.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let exc = Python::with_gil(|py| match err {
+            Some(err) => translate_error(err).into_value(py).into_py(py),
+            None => py.None(),
+        });
+        self.call_protocol("connection_lost", Some(exc));
+    }
+}
+
+/// `asyncio.Transport` over a Rust connection, see [`into_asyncio_streams_with_locals`]
+#[pyclass]
+struct RustTransport {
+    connection: Arc<Connection>,
+    protocol: Option<PyObject>,
+    tx: mpsc::UnboundedSender<WriteOp>,
+    reading: watch::Sender<bool>,
+    reader: AbortHandle,
+    closing: bool,
+}
+
+#[pymethods]
+impl RustTransport {
+    fn write(&mut self, data: &Bound<PyAny>) -> PyResult<()> {
+        // StreamWriter passes along whatever it's given, including bytearray and memoryview, which
+        // are copied once into the buffer for the writer task
+        let data = if let Ok(data) = data.downcast::<PyBytes>() {
+            data.as_bytes().to_vec()
+        } else if let Ok(data) = data.downcast::<PyByteArray>() {
+            data.to_vec()
+        } else {
+            data.py()
+                .get_type_bound::<PyBytes>()
+                .call1((data,))?
+                .downcast_into::<PyBytes>()?
+                .as_bytes()
+                .to_vec()
+        };
+
+        if self.closing || data.is_empty() {
+            return Ok(());
+        }
+
+        let pause = {
+            let mut state = self.connection.write_state.lock().unwrap();
+            state.buffered += data.len();
+
+            let pause = !state.paused && state.buffered > WRITE_HIGH_WATER;
+            state.paused |= pause;
+            pause
+        };
+
+        let _ = self.tx.send(WriteOp::Data(data));
+
+        // we're on the event loop's thread, so the protocol can be paused right away
+        if let (true, Some(protocol)) = (pause, &self.protocol) {
+            Python::with_gil(|py| protocol.bind(py).call_method0("pause_writing").map(drop))?;
+        }
+
+        Ok(())
+    }
+
+    fn writelines(&mut self, lines: &Bound<PyAny>) -> PyResult<()> {
+        for line in lines.iter()? {
+            self.write(&line?)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_eof(&mut self) {
+        let _ = self.tx.send(WriteOp::Eof);
+    }
+
+    fn can_write_eof(&self) -> bool {
+        true
+    }
+
+    fn close(&mut self) {
+        if !self.closing {
+            self.closing = true;
+            let _ = self.tx.send(WriteOp::Close);
+        }
+    }
+
+    fn abort(&mut self) {
+        self.close();
+        self.reader.abort();
+    }
+
+    fn is_closing(&self) -> bool {
+        self.closing
+    }
+
+    fn pause_reading(&self) {
+        let _ = self.reading.send(false);
+    }
+
+    fn resume_reading(&self) {
+        let _ = self.reading.send(true);
+    }
+
+    fn is_reading(&self) -> bool {
+        *self.reading.borrow()
+    }
+
+    fn get_write_buffer_size(&self) -> usize {
+        self.connection.write_state.lock().unwrap().buffered
+    }
+
+    #[pyo3(signature = (_name, default=None))]
+    fn get_extra_info(&self, _name: &str, default: Option<PyObject>) -> Option<PyObject> {
+        default
+    }
+
+    fn __traverse__(&self, visit: PyVisit<'_>) -> Result<(), PyTraverseError> {
+        if let Some(protocol) = &self.protocol {
+            visit.call(protocol)?;
+        }
+
+        Ok(())
+    }
+
+    fn __clear__(&mut self) {
+        self.protocol = None;
+    }
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-io</code></span> Hand a Rust connection to Python as an `asyncio.StreamReader` / `asyncio.StreamWriter` pair
+///
+/// This is the reverse of [`AsyncioStream`]: `io` is driven by the tokio runtime and connected to
+/// the event loop in `locals` through an `asyncio.Transport`, so the streams behave like the ones
+/// returned by `asyncio.open_connection`. Reading is paused while the `StreamReader` is full, and
+/// `StreamWriter.drain()` waits while too much data is queued for `io`.
+///
+/// # Arguments
+/// * `locals` - The task locals holding the event loop the streams belong to
+/// * `io` - The Rust connection
+pub fn into_asyncio_streams_with_locals<IO>(
+    locals: TaskLocals,
+    io: IO,
+) -> PyResult<(PyObject, PyObject)>
+where
+    IO: AsyncRead + AsyncWrite + Send + 'static,
+{
+    Python::with_gil(|py| {
+        let event_loop = locals.event_loop(py);
+        let kwargs = PyDict::new_bound(py);
+        kwargs.set_item("loop", &event_loop)?;
+
+        let reader = asyncio(py)?
+            .getattr("StreamReader")?
+            .call((), Some(&kwargs))?;
+        let protocol = asyncio(py)?
+            .getattr("StreamReaderProtocol")?
+            .call((&reader,), Some(&kwargs))?;
+
+        let connection = Arc::new(Connection {
+            event_loop: event_loop.clone().unbind(),
+            protocol: protocol.clone().unbind(),
+            write_state: Mutex::new(WriteState {
+                buffered: 0,
+                paused: false,
+            }),
+            lost: AtomicBool::new(false),
+        });
+
+        let (mut read_half, mut write_half) = ::tokio::io::split(io);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (reading, mut reading_rx) = watch::channel(true);
+        let (reader_abort, registration) = AbortHandle::new_pair();
+
+        let transport = Bound::new(
+            py,
+            RustTransport {
+                connection: connection.clone(),
+                protocol: Some(protocol.clone().unbind()),
+                tx,
+                reading,
+                reader: reader_abort.clone(),
+                closing: false,
+            },
+        )?;
+        protocol.call_method1("connection_made", (&transport,))?;
+
+        let writer = asyncio(py)?.getattr("StreamWriter")?.call1((
+            &transport,
+            &protocol,
+            &reader,
+            &event_loop,
+        ))?;
+
+        let read_connection = connection.clone();
+        get_runtime().spawn(Abortable::new(
+            async move {
+                let connection = read_connection;
+                let mut buf = vec![0; READ_CHUNK_SIZE];
+
+                loop {
+                    // the StreamReader pauses us while its buffer is full
+                    while !*reading_rx.borrow() {
+                        if reading_rx.changed().await.is_err() {
+                            return;
+                        }
+                    }
+
+                    match read_half.read(&mut buf).await {
+                        Ok(0) => {
+                            connection.call_protocol("eof_received", None);
+                            return;
+                        }
+                        Ok(n) => {
+                            let data = Python::with_gil(|py| {
+                                PyBytes::new_bound(py, &buf[..n]).into_py(py)
+                            });
+                            connection.call_protocol("data_received", Some(data));
+                        }
+                        Err(e) => {
+                            connection.lose(Some(e));
+                            return;
+                        }
+                    }
+                }
+            },
+            registration,
+        ));
+
+        get_runtime().spawn(async move {
+            while let Some(op) = rx.recv().await {
+                let result = match op {
+                    WriteOp::Data(data) => {
+                        let result = write_half.write_all(&data).await;
+
+                        let resume = {
+                            let mut state = connection.write_state.lock().unwrap();
+                            state.buffered -= data.len();
+
+                            let resume = state.paused && state.buffered <= WRITE_LOW_WATER;
+                            state.paused &= !resume;
+                            resume
+                        };
+
+                        if resume {
+                            connection.call_protocol("resume_writing", None);
+                        }
+
+                        result
+                    }
+                    WriteOp::Eof => write_half.shutdown().await,
+                    WriteOp::Close => {
+                        let _ = write_half.shutdown().await;
+                        break;
+                    }
+                };
+
+                if let Err(e) = result {
+                    reader_abort.abort();
+                    connection.lose(Some(e));
+                    return;
+                }
+            }
+
+            reader_abort.abort();
+            connection.lose(None);
+        });
+
+        Ok((reader.unbind(), writer.unbind()))
+    })
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-io</code></span> Hand a Rust connection to Python as an `asyncio.StreamReader` / `asyncio.StreamWriter` pair
+///
+/// The streams belong to the event loop of the current task, see
+/// [`into_asyncio_streams_with_locals`].
+///
+/// # Arguments
+/// * `io` - The Rust connection
+///
+/// # Examples
+///
+/// ```no_run
+/// use pyo3::prelude::*;
+///
+/// /// Open an in-memory pipe that Python can use like a network connection
+/// #[pyfunction]
+/// fn open_pipe(py: Python) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::future_into_py(py, async move {
+///         let (client, server) = tokio::io::duplex(64 * 1024);
+///         // hand `server` to the Rust side of the protocol here
+///         # drop(server);
+///         pyo3_async_runtimes::tokio::into_asyncio_streams(client)
+///     })
+/// }
+/// ```
+pub fn into_asyncio_streams<IO>(io: IO) -> PyResult<(PyObject, PyObject)>
+where
+    IO: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let locals = Python::with_gil(super::get_current_locals)?;
+    into_asyncio_streams_with_locals(locals, io)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-io</code></span> Create an in-memory connection between Rust and Python with the given task locals
+///
+/// One end of a `tokio::io::duplex` pipe is handed to Python with
+/// [`into_asyncio_streams_with_locals`], the other end is returned to Rust. Whatever Python writes
+/// to the `StreamWriter` can be read from the `DuplexStream` and vice versa, which makes it easy to
+/// test protocol code on both sides without opening a socket.
+///
+/// # Arguments
+/// * `locals` - The task locals holding the event loop the streams belong to
+/// * `max_buf_size` - How many bytes each direction of the pipe buffers before writes wait
+pub fn asyncio_duplex_with_locals(
+    locals: TaskLocals,
+    max_buf_size: usize,
+) -> PyResult<(DuplexStream, (PyObject, PyObject))> {
+    let (rust_half, py_half) = ::tokio::io::duplex(max_buf_size);
+    let streams = into_asyncio_streams_with_locals(locals, py_half)?;
+
+    Ok((rust_half, streams))
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-io</code></span> Create an in-memory connection between Rust and Python
+///
+/// The streams belong to the event loop of the current task, see [`asyncio_duplex_with_locals`].
+///
+/// # Arguments
+/// * `max_buf_size` - How many bytes each direction of the pipe buffers before writes wait
+///
+/// # Examples
+///
+/// ```no_run
+/// use pyo3::prelude::*;
+/// use tokio::io::AsyncWriteExt;
+///
+/// /// Greet Python through an in-memory connection
+/// #[pyfunction]
+/// fn connect(py: Python) -> PyResult<Bound<PyAny>> {
+///     pyo3_async_runtimes::tokio::future_into_py(py, async move {
+///         let (mut rust_half, streams) = pyo3_async_runtimes::tokio::asyncio_duplex(1024)?;
+///
+///         pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+///             let _ = rust_half.write_all(b"hello\n").await;
+///             let _ = rust_half.shutdown().await;
+///         });
+///
+///         Ok(streams)
+///     })
+/// }
+/// ```
+pub fn asyncio_duplex(max_buf_size: usize) -> PyResult<(DuplexStream, (PyObject, PyObject))> {
+    let locals = Python::with_gil(super::get_current_locals)?;
+    asyncio_duplex_with_locals(locals, max_buf_size)
+}
@@ -0,0 +1,254 @@
+//! An event loop whose clock follows the paused clock of a tokio test
+//!
+//! The loop's `time()` is read from the paused clock, so both clocks always agree. The timers of
+//! the loop are mirrored by tokio timers that wake the loop once the paused clock reaches them, so
+//! an idle runtime advances to the next timer of either side. Whenever the loop is handed work from
+//! another thread, the runtime is kept busy until the loop has run every callback that is ready or
+//! due, since the paused clock would otherwise skip ahead while Python is still running.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use ::tokio::{
+    runtime::Handle,
+    task,
+    time::{self, Instant},
+};
+use futures::channel::oneshot;
+use once_cell::sync::OnceCell;
+use pyo3::prelude::*;
+
+use crate::{dump_err, TaskLocals};
+
+const MOCK_CLOCK_GLUE: &str = r#"
+def install(loop, clock):
+    call_at = loop.call_at
+    call_soon_threadsafe = loop.call_soon_threadsafe
+
+    def settle():
+        # wait for the callbacks that are ready or due, and the ones they schedule in turn, but
+        # not for the settles of other hand-overs, which would wait for this one in turn
+        ready = any(handle._callback is not settle for handle in loop._ready)
+        scheduled = loop._scheduled
+        if ready or (scheduled and scheduled[0].when() <= loop.time()):
+            loop.call_soon(settle)
+        else:
+            clock.idle()
+
+    def mock_call_at(when, callback, *args, context=None):
+        handle = call_at(when, callback, *args, context=context)
+        clock.timer(when)
+        return handle
+
+    def mock_call_soon_threadsafe(callback, *args, context=None):
+        clock.busy()
+        handle = call_soon_threadsafe(callback, *args, context=context)
+        call_soon_threadsafe(settle)
+        return handle
+
+    loop.time = clock.time
+    loop.call_at = mock_call_at
+    loop.call_soon_threadsafe = mock_call_soon_threadsafe
+
+def uninstall(loop):
+    del loop.time
+    del loop.call_at
+    del loop.call_soon_threadsafe
+
+def wake():
+    pass
+"#;
+
+static MOCK_CLOCK_GLUE_MOD: OnceCell<PyObject> = OnceCell::new();
+
+fn glue(py: Python) -> PyResult<&Bound<PyAny>> {
+    MOCK_CLOCK_GLUE_MOD
+        .get_or_try_init(|| -> PyResult<PyObject> {
+            Ok(PyModule::from_code_bound(
+                py,
+                MOCK_CLOCK_GLUE,
+                "pyo3_asyncio/pyo3_asyncio_mock_clock_glue.py",
+                "pyo3_asyncio_mock_clock_glue",
+            )?
+            .into())
+        })
+        .map(|glue| glue.bind(py))
+}
+
+struct Shared {
+    handle: Handle,
+    event_loop: PyObject,
+    /// The paused clock when the loop was created
+    start: Instant,
+    /// The loop's own `time()` when it was created
+    base: f64,
+    /// The number of hand-overs to the loop that haven't settled yet
+    busy: AtomicUsize,
+    closed: AtomicBool,
+}
+
+/// The clock of the event loop, which the glue calls into
+#[pyclass]
+struct MockClock {
+    shared: Arc<Shared>,
+}
+
+#[pymethods]
+impl MockClock {
+    fn time(&self) -> f64 {
+        let _guard = self.shared.handle.enter();
+        self.shared.base + self.shared.start.elapsed().as_secs_f64()
+    }
+
+    fn timer(&self, when: f64) {
+        let shared = self.shared.clone();
+        let deadline = shared.start + Duration::from_secs_f64((when - shared.base).max(0.0));
+
+        self.shared.handle.spawn(async move {
+            time::sleep_until(deadline).await;
+            if shared.closed.load(Ordering::SeqCst) {
+                return;
+            }
+
+            Python::with_gil(|py| {
+                // the loop may have been closed in the meantime, so the error is only reported
+                let result = glue(py).and_then(|glue| {
+                    shared
+                        .event_loop
+                        .bind(py)
+                        .call_method1("call_soon_threadsafe", (glue.getattr("wake")?,))
+                });
+                if let Err(e) = result {
+                    dump_err(py)(e);
+                }
+            });
+        });
+    }
+
+    fn busy(&self) {
+        if self.shared.busy.fetch_add(1, Ordering::SeqCst) > 0 {
+            return;
+        }
+
+        // an idle runtime would advance the paused clock, so it polls this task until the loop has
+        // settled
+        let shared = self.shared.clone();
+        self.shared.handle.spawn(async move {
+            while shared.busy.load(Ordering::SeqCst) > 0 && !shared.closed.load(Ordering::SeqCst) {
+                task::yield_now().await;
+            }
+        });
+    }
+
+    fn idle(&self) {
+        let _ = self
+            .shared
+            .busy
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    }
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-test-util</code></span> Run `fut` on an event loop whose clock is the paused clock of the current runtime
+///
+/// A runtime that starts paused skips ahead to its next timer whenever it's idle, while
+/// `loop.time()` of an ordinary event loop follows the real clock. Within `with_mock_clock`, `fut`
+/// gets an event loop of its own whose `loop.time()` is the paused clock instead, so
+/// `asyncio.sleep`, `asyncio.wait_for` and the other timers of the loop advance along with
+/// `tokio::time::sleep` and `tokio::time::timeout`. When both sides are waiting, the clock skips
+/// ahead to the next timer of either of them, so the test runs instantly and the timers always
+/// fire in the same order.
+///
+/// The loop runs on a thread of its own until `fut` completes, and `fut` has its task locals. The
+/// runtime has to be a `current_thread` runtime that starts paused. Tests with
+/// `#[pyo3_async_runtimes::tokio::test(start_paused = true, mock_clock)]` already run in
+/// `with_mock_clock`, so this is for runtimes that are built by hand. Only the work that the Rust side hands to the
+/// loop, and the timers of the loop, are in step with the clock; I/O that the loop waits for
+/// isn't.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// # pyo3::prepare_freethreaded_python();
+/// let rt = tokio::runtime::Builder::new_current_thread()
+///     .enable_all()
+///     .start_paused(true)
+///     .build()
+///     .unwrap();
+///
+/// rt.block_on(pyo3_async_runtimes::tokio::with_mock_clock(async {
+///     let sleep = Python::with_gil(|py| {
+///         pyo3_async_runtimes::tokio::into_future(
+///             py.import_bound("asyncio")?.call_method1("sleep", (3600,))?,
+///         )
+///     })?;
+///
+///     // times out right away, without waiting for a second
+///     assert!(tokio::time::timeout(Duration::from_secs(1), sleep).await.is_err());
+///     Ok(())
+/// }))
+/// .unwrap();
+/// ```
+pub async fn with_mock_clock<F, T>(fut: F) -> PyResult<T>
+where
+    F: std::future::Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    let (shared, locals) = Python::with_gil(|py| -> PyResult<_> {
+        let event_loop = crate::asyncio(py)?.call_method0("new_event_loop")?;
+        let shared = Arc::new(Shared {
+            handle: Handle::current(),
+            event_loop: event_loop.clone().unbind(),
+            start: Instant::now(),
+            base: event_loop.call_method0("time")?.extract()?,
+            busy: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        });
+        let clock = MockClock {
+            shared: shared.clone(),
+        };
+        glue(py)?.call_method1("install", (&event_loop, clock))?;
+
+        let locals = TaskLocals::new(event_loop).copy_context(py)?;
+        Ok((shared, locals))
+    })?;
+
+    let (tx, rx) = oneshot::channel();
+    let loop_thread = Python::with_gil(|py| shared.event_loop.clone_ref(py));
+    thread::spawn(move || {
+        let result = Python::with_gil(|py| -> PyResult<()> {
+            let event_loop = loop_thread.bind(py);
+            event_loop.call_method0("run_forever")?;
+            event_loop.call_method0("close")?;
+            Ok(())
+        });
+        let _ = tx.send(result);
+    });
+
+    let result = super::scope(locals, fut).await;
+
+    // the glue is removed first, so that stopping the loop doesn't wait for it to settle
+    shared.closed.store(true, Ordering::SeqCst);
+    Python::with_gil(|py| -> PyResult<()> {
+        let event_loop = shared.event_loop.bind(py);
+        glue(py)?.call_method1("uninstall", (event_loop,))?;
+        event_loop.call_method1("call_soon_threadsafe", (event_loop.getattr("stop")?,))?;
+        Ok(())
+    })?;
+    let loop_result = rx
+        .await
+        .expect("the thread of the event loop exited before it completed");
+
+    let value = result?;
+    loop_result?;
+    Ok(value)
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use futures::future::{AbortHandle, Abortable};
+use once_cell::sync::Lazy;
+use pyo3::prelude::*;
+
+use crate::shutdown::DRAIN_INTERVAL;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+/// The tasks that the conversions spawned onto the tokio runtime and that are still running
+static TASKS: Lazy<Mutex<HashMap<u64, AbortHandle>>> = Lazy::new(Default::default);
+
+/// Keeps a task registered until it's dropped
+struct Task {
+    id: u64,
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        TASKS.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Register `fut` as a task of the runtime, so that [`shutdown`] can wait for it and abort it
+pub(super) fn tracked<F>(fut: F) -> impl Future<Output = ()>
+where
+    F: Future<Output = ()>,
+{
+    let (abort, abort_registration) = AbortHandle::new_pair();
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    TASKS.lock().unwrap().insert(id, abort);
+    let task = Task { id };
+
+    async move {
+        let _task = task;
+        let _ = Abortable::new(fut, abort_registration).await;
+    }
+}
+
+/// Shut down the conversions of `event_loop` and the tasks they left on the tokio runtime
+///
+/// This runs [`crate::shutdown`] for `event_loop` first, see there. Then the tasks that the
+/// conversions of any event loop spawned onto the tokio runtime get up to `timeout` to finish, and
+/// the ones that are still running are aborted and get up to another `timeout` to be dropped. Call
+/// this for the last event loop, once the others were shut down with [`crate::shutdown`], since
+/// their tasks are aborted as well.
+///
+/// The runtime itself can't be shut down, because [`get_runtime`](super::get_runtime) hands out
+/// `'static` references to it, so its worker threads stay around for the remaining lifetime of
+/// the process. Tasks that were spawned onto it directly, e.g. with `tokio::spawn` or this
+/// module's [`spawn`](super::spawn), aren't conversions and are left alone.
+///
+/// # Arguments
+/// * `event_loop` - The Python event loop that was used for the conversions
+/// * `timeout` - How long to wait for the conversions and for the tasks of the runtime
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         let event_loop = py.import_bound("asyncio")?.call_method0("new_event_loop")?;
+///
+///         let result = pyo3_async_runtimes::tokio::run_until_complete(event_loop.clone(), async {
+///             // ...
+///             Ok(())
+///         });
+///
+///         pyo3_async_runtimes::tokio::shutdown(&event_loop, Duration::from_secs(5))?;
+///         event_loop.call_method0("close")?;
+///
+///         result
+///     })
+/// }
+/// ```
+pub fn shutdown(event_loop: &Bound<PyAny>, timeout: Duration) -> PyResult<()> {
+    let py = event_loop.py();
+
+    crate::shutdown(event_loop, timeout)?;
+
+    py.allow_threads(|| {
+        join(Instant::now() + timeout);
+
+        for abort in TASKS.lock().unwrap().values() {
+            abort.abort();
+        }
+        join(Instant::now() + timeout);
+    });
+
+    Ok(())
+}
+
+/// Wait until no task of the runtime is running anymore, or until `deadline`
+fn join(deadline: Instant) {
+    while !TASKS.lock().unwrap().is_empty() {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+
+        thread::sleep(DRAIN_INTERVAL.min(deadline - now));
+    }
+}
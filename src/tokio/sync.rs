@@ -0,0 +1,665 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use ::tokio::sync::{broadcast, mpsc, oneshot, watch};
+use futures::{
+    future::{self, Either},
+    lock::Mutex,
+    stream, FutureExt, Stream, StreamExt,
+};
+use pyo3::{exceptions::PyStopAsyncIteration, prelude::*};
+
+use crate::{
+    asyncio, dump_err,
+    err::{ChannelClosed, ChannelLagged},
+    generic, into_future_with_locals, TaskLocals,
+};
+
+type PyItemStream = Pin<Box<dyn Stream<Item = PyObject> + Send>>;
+type PyResultStream = Pin<Box<dyn Stream<Item = PyResult<PyObject>> + Send>>;
+type SendFuture = Pin<Box<dyn Future<Output = PyResult<()>> + Send>>;
+
+fn queue_error(py: Python, name: &str) -> PyErr {
+    match asyncio(py).and_then(|asyncio| asyncio.getattr(name)?.call0()) {
+        Ok(err) => PyErr::from_value_bound(err),
+        Err(e) => e,
+    }
+}
+
+fn channel_closed() -> PyErr {
+    ChannelClosed::new_err("the Rust side of the channel was closed")
+}
+
+struct ReceiverState {
+    stream: Option<PyItemStream>,
+    // taken from the channel by `empty()`, handed out by the next `get`
+    peeked: Option<PyObject>,
+}
+
+impl ReceiverState {
+    async fn next(&mut self) -> Option<PyObject> {
+        if let Some(item) = self.peeked.take() {
+            return Some(item);
+        }
+
+        let item = match self.stream.as_mut() {
+            Some(stream) => stream.next().await,
+            None => None,
+        };
+
+        if item.is_none() {
+            self.stream = None;
+        }
+
+        item
+    }
+}
+
+/// Receiving end of a Rust channel with the interface of `asyncio.Queue`
+#[pyclass]
+struct ReceiverQueue {
+    state: Arc<Mutex<ReceiverState>>,
+}
+
+#[pymethods]
+impl ReceiverQueue {
+    fn get<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        let state = self.state.clone();
+
+        super::future_into_py(py, async move {
+            state.lock().await.next().await.ok_or_else(channel_closed)
+        })
+    }
+
+    fn get_nowait(&self, py: Python) -> PyResult<PyObject> {
+        let mut state = match self.state.try_lock() {
+            Some(state) => state,
+            // another task is already waiting for the next item
+            None => return Err(queue_error(py, "QueueEmpty")),
+        };
+
+        match state.next().now_or_never() {
+            Some(Some(item)) => Ok(item),
+            Some(None) => Err(channel_closed()),
+            None => Err(queue_error(py, "QueueEmpty")),
+        }
+    }
+
+    fn empty(&self) -> bool {
+        let mut state = match self.state.try_lock() {
+            Some(state) => state,
+            None => return true,
+        };
+
+        if state.peeked.is_some() {
+            return false;
+        }
+
+        match state.next().now_or_never() {
+            Some(Some(item)) => {
+                state.peeked = Some(item);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Close the channel, the Rust senders see it as closed from now on
+    fn close(&self) {
+        let state = self.state.clone();
+
+        // a pending `get` holds the lock until it has an item
+        super::get_runtime().spawn(async move {
+            state.lock().await.stream = None;
+        });
+    }
+
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        let state = self.state.clone();
+
+        super::future_into_py(py, async move {
+            state
+                .lock()
+                .await
+                .next()
+                .await
+                .ok_or_else(|| PyStopAsyncIteration::new_err(()))
+        })
+    }
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Expose the receiving end of a channel to Python as an `asyncio.Queue`
+///
+/// The returned object supports `get()`, `get_nowait()`, `empty()` and `async for`, plus `close()`
+/// to close the channel from Python. The bound of the queue is the bound of the channel, so Rust
+/// senders wait while Python falls behind. Once all senders are dropped and the channel is empty,
+/// `get()` raises [`ChannelClosed`](crate::err::ChannelClosed) and `async for` loops end.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `rx` - The receiving end of the channel
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Queue of the numbers from 0 to 9, produced in Rust
+/// # #[cfg(feature = "tokio-sync")]
+/// #[pyfunction]
+/// fn numbers(py: Python) -> PyResult<Bound<PyAny>> {
+///     let (tx, rx) = tokio::sync::mpsc::channel(1);
+///
+///     pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+///         for i in 0..10 {
+///             if tx.send(i).await.is_err() {
+///                 break;
+///             }
+///         }
+///     });
+///
+///     pyo3_async_runtimes::tokio::receiver_into_py(py, rx)
+/// }
+/// ```
+pub fn receiver_into_py<T>(py: Python, mut rx: mpsc::Receiver<T>) -> PyResult<Bound<PyAny>>
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    let stream = stream::poll_fn(move |cx| rx.poll_recv(cx))
+        .map(|item| Python::with_gil(|py| item.into_py(py)));
+
+    Ok(Bound::new(
+        py,
+        ReceiverQueue {
+            state: Arc::new(Mutex::new(ReceiverState {
+                stream: Some(Box::pin(stream)),
+                peeked: None,
+            })),
+        },
+    )?
+    .into_any())
+}
+
+/// Type-erased `mpsc::Sender` that extracts the items from Python
+trait PySender: Send + Sync {
+    fn try_send(&self, item: &Bound<PyAny>) -> PyResult<()>;
+    fn send(&self, item: &Bound<PyAny>) -> PyResult<SendFuture>;
+    fn is_full(&self) -> bool;
+}
+
+impl<T> PySender for mpsc::Sender<T>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    fn try_send(&self, item: &Bound<PyAny>) -> PyResult<()> {
+        match mpsc::Sender::try_send(self, item.extract()?) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(_)) => Err(queue_error(item.py(), "QueueFull")),
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(channel_closed()),
+        }
+    }
+
+    fn send(&self, item: &Bound<PyAny>) -> PyResult<SendFuture> {
+        let tx = self.clone();
+        let item = item.extract::<T>()?;
+
+        Ok(Box::pin(async move {
+            tx.send(item).await.map_err(|_| channel_closed())
+        }))
+    }
+
+    fn is_full(&self) -> bool {
+        self.capacity() == 0
+    }
+}
+
+/// Sending end of a Rust channel with the interface of `asyncio.Queue`
+#[pyclass]
+struct SenderQueue {
+    tx: Option<Box<dyn PySender>>,
+}
+
+impl SenderQueue {
+    fn tx(&self) -> PyResult<&dyn PySender> {
+        self.tx.as_deref().ok_or_else(channel_closed)
+    }
+}
+
+#[pymethods]
+impl SenderQueue {
+    fn put<'p>(&self, item: &Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+        let send = self.tx()?.send(item)?;
+        super::future_into_py(item.py(), send)
+    }
+
+    fn put_nowait(&self, item: &Bound<PyAny>) -> PyResult<()> {
+        self.tx()?.try_send(item)
+    }
+
+    fn full(&self) -> bool {
+        self.tx.as_ref().map_or(false, |tx| tx.is_full())
+    }
+
+    /// Drop this sender, the Rust receiver sees the channel as closed once all senders are gone
+    fn close(&mut self) {
+        self.tx = None;
+    }
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Expose the sending end of a channel to Python as an `asyncio.Queue`
+///
+/// The returned object supports `put()`, `put_nowait()` and `full()`, plus `close()` to drop the
+/// sender. Items are extracted into `T` when they're put. `put()` waits while the channel is full,
+/// `put_nowait()` raises `asyncio.QueueFull` instead, and both raise
+/// [`ChannelClosed`](crate::err::ChannelClosed) once the receiver is gone.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `tx` - The sending end of the channel
+pub fn sender_into_py<T>(py: Python, tx: mpsc::Sender<T>) -> PyResult<Bound<PyAny>>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    Ok(Bound::new(
+        py,
+        SenderQueue {
+            tx: Some(Box::new(tx)),
+        },
+    )?
+    .into_any())
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Receive the items of an `asyncio.Queue` through a channel
+///
+/// A task on the tokio runtime takes items from `queue` with `queue.get()` on the event loop in
+/// `locals` and sends them into a channel that holds up to `capacity` items. Items that fail to
+/// extract are received as errors. The task stops when the receiver is dropped or when `get()`
+/// fails, e.g. because the queue was shut down (Python 3.13+), which closes the channel.
+///
+/// # Arguments
+/// * `locals` - The task locals holding the event loop of the queue
+/// * `queue` - The `asyncio.Queue` to take the items from
+/// * `capacity` - The bound of the channel
+pub fn queue_into_receiver_with_locals<T>(
+    locals: TaskLocals,
+    queue: Bound<PyAny>,
+    capacity: usize,
+) -> mpsc::Receiver<PyResult<T>>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(capacity);
+    let queue = queue.unbind();
+
+    super::get_runtime().spawn(async move {
+        loop {
+            let get = Python::with_gil(|py| {
+                into_future_with_locals(&locals, queue.bind(py).call_method0("get")?)
+            });
+            let get = match get {
+                Ok(get) => get,
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    return;
+                }
+            };
+
+            let item = match future::select(Box::pin(get), Box::pin(tx.closed())).await {
+                Either::Left((Ok(item), _)) => item,
+                // the queue was shut down or the get failed, either way there's nothing more to take
+                Either::Left((Err(_), _)) | Either::Right(_) => return,
+            };
+
+            let item = Python::with_gil(|py| item.extract::<T>(py));
+            if tx.send(item).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    rx
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Receive the items of an `asyncio.Queue` through a channel
+///
+/// The queue has to belong to the event loop of the current task, see
+/// [`queue_into_receiver_with_locals`].
+///
+/// # Arguments
+/// * `queue` - The `asyncio.Queue` to take the items from
+/// * `capacity` - The bound of the channel
+pub fn queue_into_receiver<T>(
+    queue: Bound<PyAny>,
+    capacity: usize,
+) -> PyResult<mpsc::Receiver<PyResult<T>>>
+where
+    T: for<'py> FromPyObject<'py> + Send + 'static,
+{
+    let locals = super::get_current_locals(queue.py())?;
+    Ok(queue_into_receiver_with_locals(locals, queue, capacity))
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Feed an `asyncio.Queue` from a channel
+///
+/// A task on the tokio runtime receives items from a channel that holds up to `capacity` items and
+/// puts them into `queue` with `queue.put()` on the event loop in `locals`, so a full queue holds
+/// back the Rust senders. Once all senders are dropped, the queue is shut down with
+/// `queue.shutdown()` if `shutdown_on_close` is set and the queue supports it (Python 3.13+).
+///
+/// # Arguments
+/// * `locals` - The task locals holding the event loop of the queue
+/// * `queue` - The `asyncio.Queue` to put the items into
+/// * `capacity` - The bound of the channel
+/// * `shutdown_on_close` - Whether to shut down the queue once the channel is closed
+pub fn queue_into_sender_with_locals<T>(
+    locals: TaskLocals,
+    queue: Bound<PyAny>,
+    capacity: usize,
+    shutdown_on_close: bool,
+) -> mpsc::Sender<T>
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::channel::<T>(capacity);
+    let queue = queue.unbind();
+
+    super::get_runtime().spawn(async move {
+        while let Some(item) = rx.recv().await {
+            let put = Python::with_gil(|py| {
+                into_future_with_locals(&locals, queue.bind(py).call_method1("put", (item,))?)
+            });
+
+            let put = match put {
+                Ok(put) => put.await,
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = put {
+                Python::with_gil(|py| dump_err(py)(e));
+                // the queue can't take any more items, so close the channel for the senders
+                return;
+            }
+        }
+
+        if shutdown_on_close {
+            Python::with_gil(|py| {
+                let queue = queue.bind(py);
+                if queue.hasattr("shutdown").unwrap_or(false) {
+                    let _ = queue
+                        .getattr("shutdown")
+                        .and_then(|shutdown| {
+                            locals
+                                .event_loop(py)
+                                .call_method1("call_soon_threadsafe", (shutdown,))
+                        })
+                        .map_err(dump_err(py));
+                }
+            });
+        }
+    });
+
+    tx
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Feed an `asyncio.Queue` from a channel
+///
+/// The queue has to belong to the event loop of the current task, see
+/// [`queue_into_sender_with_locals`].
+///
+/// # Arguments
+/// * `queue` - The `asyncio.Queue` to put the items into
+/// * `capacity` - The bound of the channel
+/// * `shutdown_on_close` - Whether to shut down the queue once the channel is closed
+pub fn queue_into_sender<T>(
+    queue: Bound<PyAny>,
+    capacity: usize,
+    shutdown_on_close: bool,
+) -> PyResult<mpsc::Sender<T>>
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    let locals = super::get_current_locals(queue.py())?;
+    Ok(queue_into_sender_with_locals(
+        locals,
+        queue,
+        capacity,
+        shutdown_on_close,
+    ))
+}
+
+/// Async iterator over the items of a Rust channel
+#[pyclass]
+struct ChannelIter {
+    stream: Arc<Mutex<PyResultStream>>,
+}
+
+#[pymethods]
+impl ChannelIter {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+        let stream = self.stream.clone();
+
+        super::future_into_py(py, async move {
+            match stream.lock().await.next().await {
+                Some(item) => item,
+                None => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })
+    }
+}
+
+fn channel_iter<S>(py: Python, stream: S) -> PyResult<Bound<PyAny>>
+where
+    S: Stream<Item = PyResult<PyObject>> + Send + 'static,
+{
+    Ok(Bound::new(
+        py,
+        ChannelIter {
+            stream: Arc::new(Mutex::new(Box::pin(stream))),
+        },
+    )?
+    .into_any())
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Expose a receiver of a broadcast channel to Python as an async iterator
+///
+/// Every Python consumer needs a receiver of its own, so call `subscribe()` on the sender once per
+/// consumer. Iteration ends once all senders are dropped.
+///
+/// A consumer that falls behind by more than the capacity of the channel misses the oldest items.
+/// The next `__anext__` then raises [`ChannelLagged`](crate::err::ChannelLagged) with the number of
+/// skipped items as its argument. The iterator stays usable, so the consumer can catch the
+/// exception and continue with the oldest item still in the channel.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `rx` - The receiver of the broadcast channel
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Subscribe to the ticks of a clock running in Rust
+/// # #[cfg(feature = "tokio-sync")]
+/// #[pyclass]
+/// struct Clock {
+///     ticks: tokio::sync::broadcast::Sender<u64>,
+/// }
+///
+/// # #[cfg(feature = "tokio-sync")]
+/// #[pymethods]
+/// impl Clock {
+///     #[new]
+///     fn new() -> Self {
+///         let (ticks, _) = tokio::sync::broadcast::channel(16);
+///         let sender = ticks.clone();
+///
+///         pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+///             for tick in 0.. {
+///                 tokio::time::sleep(Duration::from_secs(1)).await;
+///                 // there may be nobody listening right now
+///                 let _ = sender.send(tick);
+///             }
+///         });
+///
+///         Self { ticks }
+///     }
+///
+///     fn subscribe<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+///         pyo3_async_runtimes::tokio::broadcast_into_py(py, self.ticks.subscribe())
+///     }
+/// }
+/// ```
+pub fn broadcast_into_py<T>(py: Python, rx: broadcast::Receiver<T>) -> PyResult<Bound<PyAny>>
+where
+    T: IntoPy<PyObject> + Clone + Send + 'static,
+{
+    let stream = stream::unfold(rx, |mut rx| async move {
+        let item = match rx.recv().await {
+            Ok(item) => Ok(item),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => Err(skipped),
+            Err(broadcast::error::RecvError::Closed) => return None,
+        };
+
+        Some((item, rx))
+    })
+    .map(|item| {
+        Python::with_gil(|py| match item {
+            Ok(item) => Ok(item.into_py(py)),
+            Err(skipped) => Err(ChannelLagged::new_err(skipped)),
+        })
+    });
+
+    channel_iter(py, stream)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Expose a receiver of a watch channel to Python as an async iterator of updates
+///
+/// The iterator yields the new value every time it changes. With `initial` set, it yields the
+/// current value right away, so consumers don't have to fetch the starting state separately.
+/// Like the receiver itself, it only ever yields the latest value, so a slow consumer skips the
+/// intermediate ones. Iteration ends once the sender is dropped.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `rx` - The receiver of the watch channel
+/// * `initial` - Whether to yield the current value before the first change
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Configuration that Python plugins can follow with `async for config in store.watch()`
+/// # #[cfg(feature = "tokio-sync")]
+/// #[pyclass]
+/// struct ConfigStore {
+///     tx: tokio::sync::watch::Sender<String>,
+///     rx: tokio::sync::watch::Receiver<String>,
+/// }
+///
+/// # #[cfg(feature = "tokio-sync")]
+/// #[pymethods]
+/// impl ConfigStore {
+///     #[new]
+///     fn new(config: String) -> Self {
+///         let (tx, rx) = tokio::sync::watch::channel(config);
+///         Self { tx, rx }
+///     }
+///
+///     fn set(&self, config: String) {
+///         let _ = self.tx.send(config);
+///     }
+///
+///     fn watch<'p>(&self, py: Python<'p>) -> PyResult<Bound<'p, PyAny>> {
+///         pyo3_async_runtimes::tokio::watch_into_py(py, self.rx.clone(), true)
+///     }
+/// }
+/// ```
+pub fn watch_into_py<T>(py: Python, rx: watch::Receiver<T>, initial: bool) -> PyResult<Bound<PyAny>>
+where
+    T: IntoPy<PyObject> + Clone + Send + Sync + 'static,
+{
+    let stream = stream::unfold((rx, initial), |(mut rx, initial)| async move {
+        if !initial && rx.changed().await.is_err() {
+            return None;
+        }
+
+        // clone the value rather than holding the lock while waiting for the GIL
+        let value = rx.borrow().clone();
+        Some((value, (rx, false)))
+    })
+    .map(|value| Python::with_gil(|py| Ok(value.into_py(py))));
+
+    channel_iter(py, stream)
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Convert the receiving end of a oneshot channel into an `asyncio.Future` with the given task locals
+///
+/// This is a lighter alternative to
+/// [`future_into_py_with_locals`](super::future_into_py_with_locals) for values that are produced
+/// elsewhere, e.g. by a callback or by a task that's already running. The future is resolved
+/// threadsafely on the event loop in `locals` as soon as the value is sent. If the sender is
+/// dropped without sending a value, the future raises
+/// [`ChannelClosed`](crate::err::ChannelClosed). Cancelling the future drops the receiver, which
+/// the sender can check for with `is_closed()`.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals holding the event loop to resolve the future on
+/// * `rx` - The receiving end of the channel
+pub fn oneshot_into_py_with_locals<T>(
+    py: Python,
+    locals: TaskLocals,
+    rx: oneshot::Receiver<T>,
+) -> PyResult<Bound<PyAny>>
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    generic::completion_into_py::<super::TokioRuntime, _, T>(py, locals, async move {
+        rx.await.map_err(|_| generic::sender_dropped())
+    })
+}
+
+/// <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>tokio-sync</code></span> Convert the receiving end of a oneshot channel into an `asyncio.Future`
+///
+/// See [`oneshot_into_py_with_locals`] for details.
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `rx` - The receiving end of the channel
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// /// Resolves once the Rust side is ready, without spawning a future per call
+/// # #[cfg(feature = "tokio-sync")]
+/// #[pyfunction]
+/// fn wait_ready(py: Python) -> PyResult<Bound<PyAny>> {
+///     let (tx, rx) = tokio::sync::oneshot::channel();
+///
+///     std::thread::spawn(move || {
+///         // ... some blocking setup ...
+///         let _ = tx.send(true);
+///     });
+///
+///     pyo3_async_runtimes::tokio::oneshot_into_py(py, rx)
+/// }
+/// ```
+pub fn oneshot_into_py<T>(py: Python, rx: oneshot::Receiver<T>) -> PyResult<Bound<PyAny>>
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    oneshot_into_py_with_locals(py, super::get_current_locals(py)?, rx)
+}
@@ -0,0 +1,204 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    future::{AbortHandle, Abortable},
+    stream::FuturesUnordered,
+    StreamExt,
+};
+use pyo3::prelude::*;
+
+use super::TokioRuntime;
+use crate::{
+    asyncio,
+    err::{abort_error, exception_group, AbortReason},
+    generic::ContextExt,
+    spawn_py_with_locals, PyCancelTask, TaskLocals,
+};
+
+/// Resolves to the error of a member, if it failed
+type Member = Pin<Box<dyn Future<Output = Option<PyErr>> + Send>>;
+
+/// Group of Rust futures and Python tasks that succeed or fail together
+///
+/// This mirrors `asyncio.TaskGroup` across the language boundary. Rust futures are spawned on the
+/// tokio runtime with [`TaskGroup::spawn`] and Python awaitables are spawned as tasks on the event
+/// loop with [`TaskGroup::spawn_py`]. [`TaskGroup::join`] waits for all of them:
+///
+/// - As soon as one member fails, all of the others are cancelled. Rust futures are aborted and
+///   Python tasks are cancelled with `asyncio.Task.cancel`.
+/// - The errors of all failed members are raised together in an exception group, see
+///   [`exception_group`](crate::err::exception_group) for the versions of Python before 3.11.
+/// - Like in `asyncio.TaskGroup`, a member that is cancelled on its own isn't a failure.
+///
+/// Dropping the group without joining it cancels all of its members.
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// const PYTHON_CODE: &'static str = r#"
+/// import asyncio
+///
+/// async def fetch(n):
+///     await asyncio.sleep(0.1)
+///     return n
+/// "#;
+///
+/// async fn fetch_all() -> PyResult<()> {
+///     let mut group = Python::with_gil(|py| -> PyResult<_> {
+///         let test_mod = PyModule::from_code_bound(py, PYTHON_CODE, "fetch.py", "fetch")?;
+///
+///         let mut group = pyo3_async_runtimes::tokio::TaskGroup::new(py)?;
+///         for n in 0..3 {
+///             group.spawn_py(test_mod.call_method1("fetch", (n,))?)?;
+///         }
+///
+///         Ok(group)
+///     })?;
+///
+///     group.spawn(async {
+///         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+///         Ok(())
+///     });
+///
+///     group.join().await
+/// }
+/// ```
+pub struct TaskGroup {
+    locals: TaskLocals,
+    members: FuturesUnordered<Member>,
+    rust_tasks: Vec<AbortHandle>,
+    py_tasks: Vec<Arc<Mutex<Option<PyObject>>>>,
+}
+
+impl TaskGroup {
+    /// Create a group whose Python tasks run on the event loop of the current task locals
+    pub fn new(py: Python) -> PyResult<Self> {
+        Ok(Self::with_locals(super::get_current_locals(py)?))
+    }
+
+    /// Create a group whose Python tasks run on the event loop in `locals`
+    ///
+    /// The Rust futures of the group are scoped to `locals` as well.
+    pub fn with_locals(locals: TaskLocals) -> Self {
+        Self {
+            locals,
+            members: FuturesUnordered::new(),
+            rust_tasks: Vec::new(),
+            py_tasks: Vec::new(),
+        }
+    }
+
+    /// Spawn a Rust future on the tokio runtime as a member of the group
+    pub fn spawn<F>(&mut self, fut: F)
+    where
+        F: Future<Output = PyResult<()>> + Send + 'static,
+    {
+        let (abort, registration) = AbortHandle::new_pair();
+        let locals = self.locals.clone();
+        let handle = super::get_runtime().spawn(TokioRuntime::scope(
+            locals,
+            Abortable::new(fut, registration),
+        ));
+
+        self.rust_tasks.push(abort);
+        self.members.push(Box::pin(async move {
+            match handle.await {
+                Ok(Ok(result)) => result.err(),
+                // aborted by the group
+                Ok(Err(_)) => None,
+                Err(e) if e.is_panic() => Some(abort_error(AbortReason::Panic(e.into_panic()))),
+                Err(_) => None,
+            }
+        }));
+    }
+
+    /// Spawn a Python `awaitable` as a task on the event loop as a member of the group
+    pub fn spawn_py(&mut self, awaitable: Bound<PyAny>) -> PyResult<()> {
+        let task = spawn_py_with_locals::<PyObject>(&self.locals, awaitable)?;
+
+        self.py_tasks.push(task.task.clone());
+        self.members.push(Box::pin(async move {
+            let err = task.await.err()?;
+
+            Python::with_gil(|py| {
+                let cancelled = asyncio(py)
+                    .and_then(|asyncio| asyncio.getattr("CancelledError"))
+                    .map(|cancelled_error| err.is_instance_bound(py, &cancelled_error))
+                    .unwrap_or(false);
+
+                if cancelled {
+                    None
+                } else {
+                    Some(err)
+                }
+            })
+        }));
+
+        Ok(())
+    }
+
+    /// Wait for all members of the group to finish
+    ///
+    /// If any member fails, the others are cancelled and the errors are raised together once all
+    /// of them are done.
+    pub async fn join(mut self) -> PyResult<()> {
+        let mut errors = Vec::new();
+
+        while let Some(result) = self.members.next().await {
+            if let Some(err) = result {
+                if errors.is_empty() {
+                    self.cancel_all();
+                }
+
+                errors.push(err);
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        Python::with_gil(|py| {
+            Err(exception_group(
+                py,
+                "unhandled errors in a TaskGroup",
+                errors,
+            ))
+        })
+    }
+
+    fn cancel_all(&self) {
+        for abort in &self.rust_tasks {
+            abort.abort();
+        }
+
+        if self.py_tasks.is_empty() {
+            return;
+        }
+
+        Python::with_gil(|py| {
+            for task in &self.py_tasks {
+                // the event loop may be closed already, in which case there's nothing to cancel
+                let _ = self.locals.call_soon_threadsafe(
+                    py,
+                    &py.None().into_bound(py),
+                    (PyCancelTask { task: task.clone() },),
+                );
+            }
+        });
+    }
+}
+
+impl Drop for TaskGroup {
+    fn drop(&mut self) {
+        if !self.members.is_empty() {
+            self.cancel_all();
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! <span class="module-item stab portability" style="display: inline; border-radius: 3px; padding: 2px; font-size: 80%; line-height: 1.2;"><code>trio-asyncio</code></span> Conversions between Rust futures and [trio](https://trio.readthedocs.io)
+//!
+//! The rest of this crate speaks `asyncio`. This module bridges the gap with
+//! [trio-asyncio](https://trio-asyncio.readthedocs.io), which runs an `asyncio` event loop inside of
+//! trio. Rust futures are still driven by a Rust runtime and resolve on the trio-asyncio loop, and
+//! this module translates them to and from trio's calling convention:
+//!
+//! - [`future_into_py`] converts a Rust future into an awaitable that can be awaited from trio.
+//! - [`into_future`] converts a trio awaitable into a Rust future.
+//! - [`run`] runs trio with a trio-asyncio loop until a Rust future completes.
+//!
+//! The functions are generic over the Rust runtime, so they can be used with any of the runtimes
+//! of this crate or a runtime implemented with the [`generic`](crate::generic) traits.
+//!
+//! The conversions need a trio-asyncio loop, so trio code that calls into Rust must run inside of
+//! `async with trio_asyncio.open_loop():` (or `trio_asyncio.run`).
+//!
+//! ```toml
+//! [dependencies.pyo3-asyncio-0-21]
+//! version = "0.21"
+//! features = ["trio-asyncio"]
+//! ```
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use futures::channel::oneshot;
+use once_cell::sync::OnceCell;
+use pyo3::{exceptions::PyRuntimeError, prelude::*};
+
+use crate::{
+    asyncio,
+    generic::{self, ContextExt, Runtime},
+    PyTaskCompleter, TaskLocals,
+};
+
+static TRIO_ASYNCIO: OnceCell<PyObject> = OnceCell::new();
+
+fn trio_asyncio(py: Python) -> PyResult<&Bound<PyAny>> {
+    TRIO_ASYNCIO
+        .get_or_try_init(|| Ok(py.import_bound("trio_asyncio")?.into()))
+        .map(|trio_asyncio| trio_asyncio.bind(py))
+}
+
+/// Get the trio-asyncio loop of the current trio task
+///
+/// Fails if the current trio task isn't running inside of `trio_asyncio.open_loop()`.
+pub fn get_current_loop(py: Python) -> PyResult<Bound<PyAny>> {
+    let event_loop = trio_asyncio(py)?
+        .getattr("current_loop")?
+        .call_method1("get", (py.None(),))?;
+
+    if event_loop.is_none() {
+        return Err(PyRuntimeError::new_err(
+            "no trio-asyncio loop is open, run the trio code inside of `trio_asyncio.open_loop()`",
+        ));
+    }
+
+    Ok(event_loop)
+}
+
+/// Either copy the task locals from the current Rust task OR get the trio-asyncio loop and
+/// contextvars of the current trio task
+pub fn get_current_locals<R>(py: Python) -> PyResult<TaskLocals>
+where
+    R: ContextExt,
+{
+    if let Some(locals) = R::get_task_locals() {
+        Ok(locals)
+    } else {
+        TaskLocals::new(get_current_loop(py)?).copy_context_if_enabled(py)
+    }
+}
+
+/// Convert a Rust Future into a trio awaitable with the given task locals
+///
+/// The `locals` have to refer to a trio-asyncio loop. The returned object can only be awaited from
+/// trio, use [`generic::future_into_py_with_locals`] to await the future from `asyncio` code
+/// running on the same loop.
+///
+/// # Arguments
+/// * `py` - PyO3 GIL guard
+/// * `locals` - The task locals for the given future
+/// * `fut` - The Rust future to be converted
+pub fn future_into_py_with_locals<R, F, T>(
+    py: Python,
+    locals: TaskLocals,
+    fut: F,
+) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    let aio_fut = generic::future_into_py_with_locals::<R, F, T>(py, locals, fut)?;
+    trio_asyncio(py)?.call_method1("aio_as_trio", (aio_fut,))
+}
+
+/// Convert a Rust Future into a trio awaitable
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The Rust future to be converted
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use pyo3::prelude::*;
+///
+/// /// Awaitable sleep function for trio
+/// # #[cfg(feature = "tokio-runtime")]
+/// #[pyfunction]
+/// fn sleep_for<'p>(py: Python<'p>, secs: Bound<'p, PyAny>) -> PyResult<Bound<'p, PyAny>> {
+///     let secs = secs.extract()?;
+///     pyo3_async_runtimes::trio::future_into_py::<pyo3_async_runtimes::tokio::TokioRuntime, _, _>(
+///         py,
+///         async move {
+///             tokio::time::sleep(Duration::from_secs(secs)).await;
+///             Ok(())
+///         },
+///     )
+/// }
+/// ```
+pub fn future_into_py<R, F, T>(py: Python, fut: F) -> PyResult<Bound<PyAny>>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: IntoPy<PyObject>,
+{
+    future_into_py_with_locals::<R, F, T>(py, get_current_locals::<R>(py)?, fut)
+}
+
+/// Hands a trio awaitable to trio-asyncio, which expects an async function
+#[pyclass]
+struct TrioProc {
+    awaitable: Option<PyObject>,
+}
+
+#[pymethods]
+impl TrioProc {
+    fn __call__(&mut self) -> PyResult<PyObject> {
+        self.awaitable
+            .take()
+            .ok_or_else(|| PyRuntimeError::new_err("trio awaitable was already awaited"))
+    }
+}
+
+#[pyclass]
+struct PyTrioAsFuture {
+    event_loop: PyObject,
+    proc: Option<TrioProc>,
+    tx: Option<oneshot::Sender<PyResult<PyObject>>>,
+}
+
+#[pymethods]
+impl PyTrioAsFuture {
+    fn __call__(&mut self, py: Python) -> PyResult<()> {
+        let fut = self
+            .event_loop
+            .bind(py)
+            .call_method1("trio_as_future", (self.proc.take(),))?;
+        let on_complete = PyTaskCompleter { tx: self.tx.take() };
+        fut.call_method1("add_done_callback", (on_complete,))?;
+
+        Ok(())
+    }
+}
+
+/// Convert a trio awaitable into a Rust Future with the given task locals
+///
+/// The awaitable is run as a trio task by the trio-asyncio loop in `locals`.
+///
+/// # Arguments
+/// * `locals` - The trio-asyncio loop and context to be used for the provided awaitable
+/// * `awaitable` - The trio awaitable to be converted
+pub fn into_future_with_locals(
+    locals: &TaskLocals,
+    awaitable: Bound<PyAny>,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send> {
+    let py = awaitable.py();
+    let (tx, rx) = oneshot::channel();
+
+    locals.call_soon_threadsafe(
+        py,
+        &locals.context(py),
+        (PyTrioAsFuture {
+            event_loop: locals.event_loop(py).into(),
+            proc: Some(TrioProc {
+                awaitable: Some(awaitable.into()),
+            }),
+            tx: Some(tx),
+        },),
+    )?;
+
+    Ok(async move {
+        match rx.await {
+            Ok(item) => item,
+            Err(_) => Python::with_gil(|py| {
+                Err(PyErr::from_value_bound(
+                    asyncio(py)?.call_method0("CancelledError")?,
+                ))
+            }),
+        }
+    })
+}
+
+/// Convert a trio awaitable into a Rust Future
+///
+/// # Arguments
+/// * `awaitable` - The trio awaitable to be converted
+///
+/// # Examples
+///
+/// ```
+/// use pyo3::prelude::*;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// async fn trio_sleep() -> PyResult<()> {
+///     let fut = Python::with_gil(|py| {
+///         pyo3_async_runtimes::trio::into_future::<pyo3_async_runtimes::tokio::TokioRuntime>(
+///             py.import_bound("trio")?.call_method1("sleep", (1,))?,
+///         )
+///     })?;
+///
+///     fut.await?;
+///     Ok(())
+/// }
+/// ```
+pub fn into_future<R>(
+    awaitable: Bound<PyAny>,
+) -> PyResult<impl Future<Output = PyResult<PyObject>> + Send>
+where
+    R: ContextExt,
+{
+    into_future_with_locals(&get_current_locals::<R>(awaitable.py())?, awaitable)
+}
+
+type StartMain = Box<dyn FnOnce(Python) -> PyResult<PyObject> + Send>;
+
+/// Called by `trio_asyncio.run` inside of trio to start the Rust future
+#[pyclass]
+struct TrioMain {
+    start: Option<StartMain>,
+}
+
+#[pymethods]
+impl TrioMain {
+    fn __call__(&mut self, py: Python) -> PyResult<PyObject> {
+        match self.start.take() {
+            Some(start) => start(py),
+            None => Err(PyRuntimeError::new_err("trio main was already started")),
+        }
+    }
+}
+
+/// Run trio with a trio-asyncio loop until the given Future completes
+///
+/// This is the trio counterpart to [`generic::run`]. The future can convert trio awaitables with
+/// [`into_future`] and hand Rust futures to trio with [`future_into_py`].
+///
+/// # Arguments
+/// * `py` - The current PyO3 GIL guard
+/// * `fut` - The future to drive to completion
+///
+/// # Examples
+///
+/// ```no_run
+/// use pyo3::prelude::*;
+///
+/// # #[cfg(feature = "tokio-runtime")]
+/// fn main() -> PyResult<()> {
+///     pyo3::prepare_freethreaded_python();
+///
+///     Python::with_gil(|py| {
+///         pyo3_async_runtimes::trio::run::<pyo3_async_runtimes::tokio::TokioRuntime, _, _>(
+///             py,
+///             async move {
+///                 let fut = Python::with_gil(|py| {
+///                     pyo3_async_runtimes::trio::into_future::<
+///                         pyo3_async_runtimes::tokio::TokioRuntime,
+///                     >(py.import_bound("trio")?.call_method1("sleep", (1,))?)
+///                 })?;
+///
+///                 fut.await?;
+///                 Ok(())
+///             },
+///         )
+///     })
+/// }
+/// # #[cfg(not(feature = "tokio-runtime"))]
+/// # fn main() {}
+/// ```
+pub fn run<R, F, T>(py: Python, fut: F) -> PyResult<T>
+where
+    R: Runtime + ContextExt,
+    F: Future<Output = PyResult<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    generic::check_blocking::<R>(py, "run")?;
+
+    let result = Arc::new(Mutex::new(None));
+    let result_tx = result.clone();
+
+    let main = TrioMain {
+        start: Some(Box::new(move |py| {
+            future_into_py::<R, _, ()>(py, async move {
+                let value = fut.await;
+                *result_tx.lock().unwrap() = Some(value);
+                Ok(())
+            })
+            .map(Into::into)
+        })),
+    };
+
+    trio_asyncio(py)?.call_method1("run", (main,))?;
+
+    let value = result.lock().unwrap().take();
+    value.unwrap_or_else(|| {
+        Err(PyRuntimeError::new_err(
+            "trio exited before the future completed",
+        ))
+    })
+}